@@ -0,0 +1,93 @@
+use block_chain_db::BlockChainDatabase;
+use kv::{KeyValueDatabase, MemoryDatabase};
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::Arc;
+use storage::{BackupHookRef, Error, SharedStore};
+
+/// Key-value backend of the blockchain database.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DatabaseBackend {
+    /// RocksDB database in the data directory.
+    RocksDb,
+    /// Database in the process memory. Nothing survives restart of the node.
+    Memory,
+}
+
+impl Default for DatabaseBackend {
+    fn default() -> Self {
+        DatabaseBackend::RocksDb
+    }
+}
+
+impl FromStr for DatabaseBackend {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "rocksdb" => Ok(DatabaseBackend::RocksDb),
+            "memory" => Ok(DatabaseBackend::Memory),
+            backend => Err(format!("Unknown database backend: {}", backend)),
+        }
+    }
+}
+
+impl DatabaseBackend {
+    /// Returns true if database is stored on disk.
+    pub fn is_persistent(&self) -> bool {
+        match *self {
+            DatabaseBackend::RocksDb => true,
+            DatabaseBackend::Memory => false,
+        }
+    }
+
+    /// Opens blockchain database with this backend.
+    /// In-memory database ignores both `path` and `total_cache`.
+    /// If `backup_hook` is set, it is called before bodies are pruned and before
+    /// reorganizations, disconnecting at least `backup_reorg_depth` blocks.
+    pub fn open<P>(
+        &self,
+        path: P,
+        total_cache: usize,
+        compress_bodies: bool,
+        chain_events_retention_s: Option<u32>,
+        backup_hook: Option<BackupHookRef>,
+        backup_reorg_depth: u32,
+    ) -> Result<SharedStore, Error>
+    where
+        P: AsRef<Path>,
+    {
+        match *self {
+            DatabaseBackend::RocksDb => Ok(Arc::new(configure(
+                BlockChainDatabase::open_at_path(path, total_cache)?,
+                compress_bodies,
+                chain_events_retention_s,
+                backup_hook,
+                backup_reorg_depth,
+            ))),
+            DatabaseBackend::Memory => Ok(Arc::new(configure(
+                BlockChainDatabase::open(MemoryDatabase::default()),
+                compress_bodies,
+                chain_events_retention_s,
+                backup_hook,
+                backup_reorg_depth,
+            ))),
+        }
+    }
+}
+
+fn configure<T: KeyValueDatabase>(
+    db: BlockChainDatabase<T>,
+    compress_bodies: bool,
+    chain_events_retention_s: Option<u32>,
+    backup_hook: Option<BackupHookRef>,
+    backup_reorg_depth: u32,
+) -> BlockChainDatabase<T> {
+    let db = db
+        .with_block_compression(compress_bodies)
+        .with_chain_events_retention(chain_events_retention_s);
+    match backup_hook {
+        Some(hook) => db.with_backup_hook(hook, backup_reorg_depth),
+        None => db,
+    }
+}
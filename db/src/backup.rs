@@ -0,0 +1,103 @@
+use chain::Block;
+use ser::Stream;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use storage::{BackupHook, BackupReason, Error};
+
+/// Backup hook, which writes blocks to the rollback directory. Every backup is a separate
+/// `<reason>-<time>-<hash of the first block>.blocks` file with serialized blocks, which
+/// could be imported back with `randchaind import`.
+#[derive(Debug, Clone)]
+pub struct BlockFilesBackup {
+    dir: PathBuf,
+}
+
+impl BlockFilesBackup {
+    pub fn new<P: AsRef<Path>>(dir: P) -> Self {
+        BlockFilesBackup {
+            dir: dir.as_ref().to_path_buf(),
+        }
+    }
+
+    /// Directory with backup files.
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+}
+
+impl BackupHook for BlockFilesBackup {
+    fn backup_blocks(&self, reason: BackupReason, blocks: &[Block]) -> Result<(), Error> {
+        let first_hash = match blocks.first() {
+            Some(block) => block.hash(),
+            None => return Ok(()),
+        };
+        let time = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        let path = self.dir.join(format!(
+            "{}-{}-{}.blocks",
+            reason,
+            time,
+            first_hash.reversed()
+        ));
+
+        let mut stream = Stream::new();
+        for block in blocks {
+            stream.append(block);
+        }
+
+        let io_error = |err: ::std::io::Error| {
+            Error::DatabaseError(format!(
+                "Failed to write backup {}: {}",
+                path.display(),
+                err
+            ))
+        };
+        fs::create_dir_all(&self.dir).map_err(&io_error)?;
+        let mut file = fs::File::create(&path).map_err(&io_error)?;
+        file.write_all(&stream.out()).map_err(&io_error)?;
+        file.sync_all().map_err(&io_error)?;
+
+        info!(target: "db", "{} blocks are backed up to {} before {}", blocks.len(), path.display(), reason);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate tempdir;
+
+    use self::tempdir::TempDir;
+    use super::BlockFilesBackup;
+    use chain::Block;
+    use ser::deserialize_iterator;
+    use std::fs;
+    use storage::{BackupHook, BackupReason};
+    use test_data;
+
+    #[test]
+    fn block_files_backup_is_importable() {
+        let dir = TempDir::new("backup").unwrap();
+        let backup = BlockFilesBackup::new(dir.path().join("rollback"));
+        let blocks = vec![test_data::block_h1(), test_data::block_h2()];
+        backup.backup_blocks(BackupReason::Reorg, &blocks).unwrap();
+
+        let files = fs::read_dir(backup.dir())
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .collect::<Vec<_>>();
+        assert_eq!(files.len(), 1);
+        let name = files[0].file_name().unwrap().to_str().unwrap().to_owned();
+        assert!(name.starts_with("reorg-"));
+        assert!(name.ends_with(&format!("-{}.blocks", blocks[0].hash().reversed())));
+
+        let file = fs::File::open(&files[0]).unwrap();
+        let restored = deserialize_iterator::<_, Block>(file)
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(restored, blocks);
+    }
+}
@@ -1,31 +1,82 @@
+use block_compression::{
+    deserialize_stats, serialize_stats, BlockCompression, KEY_COMPRESSION_DICTIONARY, KEY_DB_STATS,
+};
 use bytes::Bytes;
-use chain::{Block, IndexedBlock, IndexedBlockHeader};
+use chain::{Block, BlockHeader, IndexedBlock, IndexedBlockHeader};
+use chain_events::{
+    deserialize_event, deserialize_reorg, new_event, serialize_event, serialize_reorg,
+    KEY_FIRST_CHAIN_EVENT_SEQ, KEY_LAST_CHAIN_EVENT_SEQ, KEY_LAST_REORG,
+};
 use hash::H256;
 use kv::{
     AutoFlushingOverlayDatabase, CacheDatabase, DatabaseConfig, DiskDatabase, Key, KeyState,
-    KeyValue, KeyValueDatabase, MemoryDatabase, OverlayDatabase, Transaction as DBTransaction,
-    Value,
+    KeyValue, KeyValueDatabase, MemoryDatabase, MinerBlockKey, OverlayDatabase,
+    Transaction as DBTransaction, Value,
 };
 use kv::{COL_BLOCKS, COL_BLOCK_HASHES, COL_BLOCK_NUMBERS, COL_COUNT};
-use parking_lot::RwLock;
-use ser::{deserialize, serialize};
-use std::fs;
+use mmr::{deserialize_mmr, serialize_mmr, MerkleMountainRange};
+use parking_lot::{Mutex, RwLock};
+use primitives::bigint::U256;
+use ser::{deserialize, serialize, serialize_list, List};
+use std::collections::HashMap;
+use std::ops::Range;
 use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::{cmp, fs};
 use storage::{
-    BestBlock, BlockChain, BlockHeaderProvider, BlockOrigin, BlockProvider, BlockRef, CanonStore,
-    ConfigStore, Error, ForkChain, Forkable, SideChainOrigin, Store,
+    BackupHookRef, BackupReason, BestBlock, BlockChain, BlockHeaderProvider, BlockOrigin,
+    BlockProvider, BlockRangeIterator, BlockRef, CanonStore, ChainEvent, ChainEventKind,
+    ChainEventStore, Checkpoint, ConfigStore, DbSize, DbStats, Error, ForkChain, Forkable,
+    MinerIndexStore, PruneGuard, PruneStore, Reorg, RetainedBodies, SideChainOrigin, Store,
+    VerifierState, VerifierStateStore,
 };
 
-const KEY_BEST_BLOCK_NUMBER: &'static str = "best_block_number";
-const KEY_BEST_BLOCK_HASH: &'static str = "best_block_hash";
+pub const KEY_BEST_BLOCK_NUMBER: &'static str = "best_block_number";
+pub const KEY_BEST_BLOCK_HASH: &'static str = "best_block_hash";
+pub const KEY_PRUNED_HEIGHT: &'static str = "pruned_height";
+/// Number of the best block, which is covered by the miner index. The key has been renamed
+/// when the index has been keyed by block number, so indexes of previous versions are rebuilt
+pub const KEY_MINER_INDEX_HEIGHT: &'static str = "miner_block_index_height";
+/// Set when children of all stored blocks are indexed
+pub const KEY_BLOCK_CHILDREN_INDEXED: &'static str = "block_children_indexed";
+/// Set when chain work of all stored blocks is computed
+pub const KEY_CHAIN_WORK_COMPUTED: &'static str = "chain_work_computed";
+/// Merkle mountain range over canon blocks below the tail of the imported verifier state
+pub const KEY_VERIFIER_STATE_MMR: &'static str = "verifier_state_mmr";
+/// Blocks, which have been manually invalidated, with all their stored descendants
+pub const KEY_INVALIDATED_BLOCKS: &'static str = "invalidated_blocks";
 
 const MAX_FORK_ROUTE_PRESET: usize = 2048;
+/// Max number of blocks, which are indexed by miner in a single transaction by `enable_miner_index`
+const MINER_INDEX_BATCH_SIZE: u32 = 4096;
+/// Max number of pruned blocks, which are passed to the backup hook at once
+const PRUNE_BACKUP_BATCH_SIZE: u32 = 128;
+/// Max number of blocks, which chain work is written in a single transaction by `recompute_chain_work`
+const CHAIN_WORK_BATCH_SIZE: usize = 4096;
+/// Max number of blocks, which children are written in a single transaction by `index_block_children`
+const BLOCK_CHILDREN_BATCH_SIZE: usize = 4096;
 
 pub struct BlockChainDatabase<T>
 where
     T: KeyValueDatabase,
 {
     best_block: RwLock<BestBlock>,
+    pruned_height: RwLock<Option<u32>>,
+    retained_bodies: Arc<RetainedBodies>,
+    /// If true, bodies of inserted blocks are compressed
+    compress_bodies: bool,
+    compression: BlockCompression,
+    /// Chain events, older than this number of seconds, are removed from the journal
+    chain_events_retention: Option<u32>,
+    /// Hook, which is called before blocks are pruned or disconnected by deep reorganization
+    backup_hook: Option<BackupHookRef>,
+    /// Reorganizations, disconnecting at least this number of blocks, are backed up
+    backup_reorg_depth: u32,
+    /// If true, canon blocks are indexed by miner public key
+    miner_index: AtomicBool,
+    /// Serializes read-modify-write of the db stats, so concurrent updates are not lost
+    stats_lock: Mutex<()>,
     db: T,
 }
 
@@ -34,6 +85,10 @@ where
     T: 'a + KeyValueDatabase,
 {
     blockchain: BlockChainDatabase<OverlayDatabase<'a, T>>,
+    /// Number of the common ancestor of the canon chain and the fork
+    ancestor: u32,
+    /// Canon blocks, disconnected by the fork. Ordered from oldest to newest
+    disconnected: Vec<H256>,
 }
 
 impl<'a, T> ForkChain for ForkChainDatabase<'a, T>
@@ -45,6 +100,32 @@ where
     }
 
     fn flush(&self) -> Result<(), Error> {
+        // fork is only flushed when it becomes the canon chain
+        let best_block = self.blockchain.best_block();
+        let reorg = Reorg {
+            ancestor: self.ancestor,
+            disconnected: self.disconnected.clone(),
+            connected: (self.ancestor + 1..best_block.number + 1)
+                .map(|number| {
+                    self.blockchain
+                        .block_hash(number)
+                        .expect("number is not above the fork best block; qed")
+                })
+                .collect(),
+        };
+        self.blockchain.backup_reorg(&reorg);
+        let mut update = DBTransaction::new();
+        update.insert(KeyValue::Meta(KEY_LAST_REORG, serialize_reorg(&reorg)));
+        self.blockchain
+            .db
+            .write(update)
+            .map_err(Error::DatabaseError)?;
+
+        self.blockchain.write_chain_event(
+            ChainEventKind::ReorgFinished,
+            best_block.hash,
+            best_block.number,
+        )?;
         self.blockchain.db.flush().map_err(Error::DatabaseError)
     }
 }
@@ -94,8 +175,19 @@ where
     pub fn open_with_cache(db: T) -> Self {
         let db = CacheDatabase::new(AutoFlushingOverlayDatabase::new(db, 50));
         let best_block = Self::read_best_block(&db).unwrap_or_default();
+        let pruned_height = Self::read_pruned_height(&db);
+        let dictionary = BlockCompression::read_dictionary(&db);
         BlockChainDatabase {
             best_block: RwLock::new(best_block),
+            pruned_height: RwLock::new(pruned_height),
+            retained_bodies: Arc::default(),
+            compress_bodies: false,
+            compression: BlockCompression::new(dictionary),
+            chain_events_retention: None,
+            backup_hook: None,
+            backup_reorg_depth: 0,
+            miner_index: AtomicBool::new(false),
+            stats_lock: Mutex::new(()),
             db: db,
         }
     }
@@ -126,24 +218,93 @@ where
         }
     }
 
+    fn read_pruned_height(db: &T) -> Option<u32> {
+        db.get(&Key::Meta(KEY_PRUNED_HEIGHT))
+            .expect("db value to be fine")
+            .into_option()
+            .and_then(Value::as_meta)
+            .map(|height| deserialize(&**height).expect("Inconsistent DB. Invalid pruned height."))
+    }
+
     pub fn open(db: T) -> Self {
         let best_block = Self::read_best_block(&db).unwrap_or_default();
+        let pruned_height = Self::read_pruned_height(&db);
+        let dictionary = BlockCompression::read_dictionary(&db);
         BlockChainDatabase {
             best_block: RwLock::new(best_block),
+            pruned_height: RwLock::new(pruned_height),
+            retained_bodies: Arc::default(),
+            compress_bodies: false,
+            compression: BlockCompression::new(dictionary),
+            chain_events_retention: None,
+            backup_hook: None,
+            backup_reorg_depth: 0,
+            miner_index: AtomicBool::new(false),
+            stats_lock: Mutex::new(()),
             db: db,
         }
     }
 
+    /// Enables compression of bodies of inserted blocks. Headers are never compressed.
+    /// Already stored bodies are kept as is.
+    pub fn with_block_compression(mut self, compress_bodies: bool) -> Self {
+        self.compress_bodies = compress_bodies;
+        self
+    }
+
+    /// Limits age of the chain events, kept in the journal. The journal is trimmed
+    /// when new events are recorded.
+    pub fn with_chain_events_retention(mut self, retention_s: Option<u32>) -> Self {
+        self.chain_events_retention = retention_s;
+        self
+    }
+
+    /// Calls the hook before bodies are pruned and before reorganizations, disconnecting at
+    /// least `reorg_depth` canon blocks, are applied.
+    pub fn with_backup_hook(mut self, hook: BackupHookRef, reorg_depth: u32) -> Self {
+        self.backup_hook = Some(hook);
+        self.backup_reorg_depth = reorg_depth;
+        self
+    }
+
     pub fn best_block(&self) -> BestBlock {
         self.best_block.read().clone()
     }
 
-    pub fn fork(&self, side_chain: SideChainOrigin) -> Result<ForkChainDatabase<T>, Error> {
-        let overlay = BlockChainDatabase::open(OverlayDatabase::new(&self.db));
+    pub fn stats(&self) -> DbStats {
+        self.get(Key::Meta(KEY_DB_STATS))
+            .and_then(Value::as_meta)
+            .map(|stats| deserialize_stats(&stats).expect("Inconsistent DB. Invalid db stats."))
+            .unwrap_or_default()
+    }
+
+    pub fn size(&self) -> Result<DbSize, Error> {
+        self.db.size().map_err(Error::DatabaseError)
+    }
+
+    pub fn compact(&self) -> Result<(), Error> {
+        self.db.compact().map_err(Error::DatabaseError)
+    }
 
-        for hash in side_chain.decanonized_route.into_iter().rev() {
+    pub fn fork(&self, side_chain: SideChainOrigin) -> Result<ForkChainDatabase<T>, Error> {
+        let mut overlay = BlockChainDatabase::open(OverlayDatabase::new(&self.db))
+            .with_chain_events_retention(self.chain_events_retention);
+        // disconnected blocks are backed up when fork becomes the canon chain
+        overlay.backup_hook = self.backup_hook.clone();
+        overlay.backup_reorg_depth = self.backup_reorg_depth;
+        overlay.miner_index = AtomicBool::new(self.miner_index.load(Ordering::SeqCst));
+        let ancestor_hash = self
+            .block_hash(side_chain.ancestor)
+            .expect("fork ancestor is a canon block; qed");
+        overlay.write_chain_event(
+            ChainEventKind::ReorgStarted,
+            ancestor_hash,
+            side_chain.ancestor,
+        )?;
+
+        for hash in side_chain.decanonized_route.iter().rev() {
             let decanonized_hash = overlay.decanonize()?;
-            assert_eq!(hash, decanonized_hash);
+            assert_eq!(*hash, decanonized_hash);
         }
 
         for block_hash in &side_chain.canonized_route {
@@ -152,6 +313,8 @@ where
 
         let fork = ForkChainDatabase {
             blockchain: overlay,
+            ancestor: side_chain.ancestor,
+            disconnected: side_chain.decanonized_route,
         };
 
         Ok(fork)
@@ -160,7 +323,32 @@ where
     pub fn switch_to_fork(&self, fork: ForkChainDatabase<T>) -> Result<(), Error> {
         let mut best_block = self.best_block.write();
         *best_block = fork.blockchain.best_block.read().clone();
-        fork.blockchain.db.flush().map_err(Error::DatabaseError)
+        fork.flush()
+    }
+
+    /// Backs up canon blocks, which are about to be disconnected by the deep reorganization.
+    /// Disconnected blocks stay in the database as a side chain, so failed backup doesn't
+    /// prevent reorganization.
+    fn backup_reorg(&self, reorg: &Reorg) {
+        let hook = match self.backup_hook {
+            Some(ref hook) if reorg.disconnected.len() >= self.backup_reorg_depth as usize => hook,
+            _ => return,
+        };
+        let blocks = reorg
+            .disconnected
+            .iter()
+            .filter_map(|hash| self.read_block(hash))
+            .collect::<Vec<_>>();
+        if let Err(err) = hook.backup_blocks(BackupReason::Reorg, &blocks) {
+            error!(target: "db", "Failed to back up {} blocks, disconnected by reorganization: {}", blocks.len(), err);
+        }
+    }
+
+    /// Returns the last reorganization of the canon chain, if any.
+    pub fn last_reorg(&self) -> Option<Reorg> {
+        self.get(Key::Meta(KEY_LAST_REORG))
+            .and_then(Value::as_meta)
+            .map(|reorg| deserialize_reorg(&reorg).expect("Inconsistent DB. Invalid last reorg."))
     }
 
     pub fn block_origin(&self, header: &IndexedBlockHeader) -> Result<BlockOrigin, Error> {
@@ -186,6 +374,8 @@ where
 
         let mut sidechain_route = Vec::new();
         let mut next_hash = header.raw.previous_header_hash.clone();
+        // work of the side chain since the fork point
+        let mut sidechain_work = header.raw.bits.add_work(U256::zero());
 
         for fork_len in 0..MAX_FORK_ROUTE_PRESET {
             match self.block_number(&next_hash) {
@@ -200,7 +390,20 @@ where
                             .collect(),
                         block_number: block_number,
                     };
-                    if block_number > best_block.number {
+                    // chain with more work is the best chain. Work of both chains is compared
+                    // since the fork point, so it is known even for blocks without stored work
+                    let canon_work =
+                        origin
+                            .decanonized_route
+                            .iter()
+                            .fold(U256::zero(), |work, hash| {
+                                self.block_header(hash.clone().into())
+                                    .expect("canon blocks are stored; qed")
+                                    .raw
+                                    .bits
+                                    .add_work(work)
+                            });
+                    if sidechain_work > canon_work {
                         return Ok(BlockOrigin::SideChainBecomingCanonChain(origin));
                     } else {
                         return Ok(BlockOrigin::SideChain(origin));
@@ -208,11 +411,12 @@ where
                 }
                 None => {
                     sidechain_route.push(next_hash.clone());
-                    next_hash = self
+                    let sidechain_header = self
                         .block_header(next_hash.into())
                         .expect("not to find orphaned side chain in database; qed")
-                        .raw
-                        .previous_header_hash;
+                        .raw;
+                    sidechain_work = sidechain_header.bits.add_work(sidechain_work);
+                    next_hash = sidechain_header.previous_header_hash;
                 }
             }
         }
@@ -220,6 +424,109 @@ where
         Err(Error::AncientFork)
     }
 
+    /// Writes genesis block to the empty database. Returns true if the block has been written,
+    /// or false if the database already has the same genesis block
+    pub fn init_genesis(&self, genesis: IndexedBlock) -> Result<bool, Error> {
+        match self.block_hash(0) {
+            Some(ref hash) if hash == genesis.hash() => return Ok(false),
+            Some(hash) => {
+                return Err(Error::InvalidGenesis(format!(
+                    "database has another genesis block {}",
+                    hash.reversed()
+                )))
+            }
+            None => (),
+        }
+        if !genesis.header.raw.previous_header_hash.is_zero() {
+            return Err(Error::InvalidGenesis(
+                "previous header hash must be zero".into(),
+            ));
+        }
+
+        let hash = genesis.hash().clone();
+        self.insert(genesis)?;
+        self.canonize(&hash)?;
+        // children and chain work of all blocks of the new database are written on insertion
+        let mut update = DBTransaction::new();
+        update.insert(KeyValue::Meta(KEY_BLOCK_CHILDREN_INDEXED, serialize(&1u8)));
+        update.insert(KeyValue::Meta(KEY_CHAIN_WORK_COMPUTED, serialize(&1u8)));
+        self.db.write(update).map_err(Error::DatabaseError)?;
+        Ok(true)
+    }
+
+    /// Returns blocks, which have been manually invalidated
+    pub fn invalidated_blocks(&self) -> Vec<H256> {
+        self.get(Key::Meta(KEY_INVALIDATED_BLOCKS))
+            .and_then(Value::as_meta)
+            .map(|blocks| {
+                deserialize::<_, List<H256>>(&**blocks)
+                    .expect("Inconsistent DB. Invalid invalidated blocks.")
+                    .into()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Replaces the set of manually invalidated blocks
+    pub fn set_invalidated_blocks(&self, blocks: &[H256]) -> Result<(), Error> {
+        let mut update = DBTransaction::new();
+        if blocks.is_empty() {
+            update.delete(Key::Meta(KEY_INVALIDATED_BLOCKS));
+        } else {
+            update.insert(KeyValue::Meta(
+                KEY_INVALIDATED_BLOCKS,
+                serialize(&List::from(blocks.to_vec())),
+            ));
+        }
+        self.db.write(update).map_err(Error::DatabaseError)
+    }
+
+    /// Returns true if children of all stored blocks are indexed
+    pub fn block_children_indexed(&self) -> bool {
+        self.get(Key::Meta(KEY_BLOCK_CHILDREN_INDEXED)).is_some()
+    }
+
+    /// Indexes children of blocks, which have been stored before children have been indexed.
+    /// Every stored block is read, so it could take a while. Returns number of updated parents
+    pub fn index_block_children(&self) -> Result<u32, Error> {
+        let mut missing_children: HashMap<H256, Vec<H256>> = HashMap::new();
+        for (hash, parent_hash) in self.db.block_links().map_err(Error::DatabaseError)? {
+            if !parent_hash.is_zero() {
+                missing_children
+                    .entry(parent_hash)
+                    .or_insert_with(Vec::new)
+                    .push(hash);
+            }
+        }
+
+        let mut updated = 0;
+        let mut update = DBTransaction::new();
+        for (parent_hash, mut missing) in missing_children {
+            let mut children = self.block_children(&parent_hash);
+            missing.retain(|child| !children.contains(child));
+            if missing.is_empty() {
+                continue;
+            }
+
+            // insertion order of missing children is unknown, so the canon child goes first
+            missing.sort_by_key(|child| self.block_number(child).is_none());
+            children.extend(missing);
+            update.insert(KeyValue::BlockChildren(parent_hash, List::from(children)));
+            updated += 1;
+
+            if update.operations.len() >= BLOCK_CHILDREN_BATCH_SIZE {
+                let batch = ::std::mem::replace(&mut update, DBTransaction::new());
+                self.db.write(batch).map_err(Error::DatabaseError)?;
+            }
+        }
+
+        update.insert(KeyValue::Meta(KEY_BLOCK_CHILDREN_INDEXED, serialize(&1u8)));
+        self.db.write(update).map_err(Error::DatabaseError)?;
+        if updated != 0 {
+            debug!(target: "db", "Children of {} blocks are indexed", updated);
+        }
+        Ok(updated)
+    }
+
     pub fn insert(&self, block: IndexedBlock) -> Result<(), Error> {
         if self.contains_block(block.hash().clone().into()) {
             return Ok(());
@@ -231,15 +538,67 @@ where
         }
 
         let mut update = DBTransaction::new();
-        update.insert(KeyValue::Block(
-            block.hash().clone(),
-            Block {
-                block_header: block.header.raw,
-                proof: block.proof,
-            },
-        ));
+        if let Some(chain_work) = self.compute_chain_work(&block.header.raw) {
+            update.insert(KeyValue::ChainWork(block.hash().clone(), chain_work));
+        }
+        if !parent_hash.is_zero() {
+            let mut children = self.block_children(&parent_hash);
+            children.push(block.hash().clone());
+            update.insert(KeyValue::BlockChildren(parent_hash, List::from(children)));
+        }
 
-        self.db.write(update).map_err(Error::DatabaseError)
+        let (compressed_body, dictionary) = match self.compress_bodies && !block.proof.is_empty() {
+            true => {
+                let raw_body = serialize_list(&block.proof);
+                let compressed_body = self
+                    .compression
+                    .compress(&raw_body)
+                    .map(|compressed_body| (raw_body.len(), compressed_body));
+                (compressed_body, self.compression.note_sample(&raw_body))
+            }
+            false => (None, None),
+        };
+
+        // stats are read and written back under the lock
+        let _stats_lock = self.stats_lock.lock();
+        match compressed_body {
+            Some((raw_body_size, compressed_body)) => {
+                let mut stats = self.stats();
+                stats.compressed_blocks += 1;
+                stats.compressed_bodies_raw_size += raw_body_size as u64;
+                stats.compressed_bodies_size += compressed_body.len() as u64;
+                update.insert(KeyValue::Meta(KEY_DB_STATS, serialize_stats(&stats)));
+                update.insert(KeyValue::BlockBody(block.hash().clone(), compressed_body));
+                // body is only stored in the compressed form
+                update.insert(KeyValue::Block(
+                    block.hash().clone(),
+                    Block {
+                        block_header: block.header.raw,
+                        proof: Vec::new(),
+                    },
+                ));
+            }
+            None => update.insert(KeyValue::Block(
+                block.hash().clone(),
+                Block {
+                    block_header: block.header.raw,
+                    proof: block.proof,
+                },
+            )),
+        }
+        if let Some(ref dictionary) = dictionary {
+            update.insert(KeyValue::Meta(
+                KEY_COMPRESSION_DICTIONARY,
+                dictionary.clone(),
+            ));
+        }
+
+        self.db.write(update).map_err(Error::DatabaseError)?;
+        if let Some(dictionary) = dictionary {
+            debug!(target: "db", "Block bodies compression dictionary of {} bytes is trained", dictionary.len());
+            self.compression.set_dictionary(dictionary);
+        }
+        Ok(())
     }
 
     /// Rollbacks single best block
@@ -274,27 +633,27 @@ where
     /// Updates meta data.
     pub fn canonize(&self, hash: &H256) -> Result<(), Error> {
         let mut best_block = self.best_block.write();
-        let block = match self.block(hash.clone().into()) {
-            Some(block) => block,
+        let header = match self.block_header(hash.clone().into()) {
+            Some(header) => header,
             None => {
                 error!(target: "db", "Block is not found during canonization: {}", hash.reversed());
                 return Err(Error::CannotCanonize);
             }
         };
 
-        if best_block.hash != block.header.raw.previous_header_hash {
+        if best_block.hash != header.raw.previous_header_hash {
             error!(
                 target: "db",
                 "Wrong best block during canonization. Best {}, parent: {}",
                 best_block.hash.reversed(),
-                block.header.raw.previous_header_hash.reversed(),
+                header.raw.previous_header_hash.reversed(),
             );
             return Err(Error::CannotCanonize);
         }
 
         let new_best_block = BestBlock {
             hash: hash.clone(),
-            number: if block.header.raw.previous_header_hash.is_zero() {
+            number: if header.raw.previous_header_hash.is_zero() {
                 assert_eq!(best_block.number, 0);
                 0
             } else {
@@ -305,6 +664,11 @@ where
         trace!(target: "db", "canonize {:?}", new_best_block);
 
         let mut update = DBTransaction::new();
+        if self.chain_work(hash).is_none() {
+            if let Some(chain_work) = self.compute_chain_work(&header.raw) {
+                update.insert(KeyValue::ChainWork(hash.clone(), chain_work));
+            }
+        }
         update.insert(KeyValue::BlockHash(
             new_best_block.number,
             new_best_block.hash.clone(),
@@ -321,6 +685,18 @@ where
             KEY_BEST_BLOCK_NUMBER,
             serialize(&new_best_block.number),
         ));
+        self.update_miner_index(
+            &mut update,
+            &header,
+            new_best_block.number,
+            new_best_block.number,
+        );
+        self.push_chain_event(
+            &mut update,
+            ChainEventKind::BlockConnected,
+            new_best_block.hash.clone(),
+            new_best_block.number,
+        );
 
         self.db.write(update).map_err(Error::DatabaseError)?;
         *best_block = new_best_block;
@@ -329,8 +705,8 @@ where
 
     pub fn decanonize(&self) -> Result<H256, Error> {
         let mut best_block = self.best_block.write();
-        let block = match self.block(best_block.hash.clone().into()) {
-            Some(block) => block,
+        let header = match self.block_header(best_block.hash.clone().into()) {
+            Some(header) => header,
             None => {
                 error!(target: "db", "Block is not found during decanonization: {}", best_block.hash.reversed());
                 return Err(Error::CannotDecanonize);
@@ -340,11 +716,11 @@ where
         let block_hash = best_block.hash.clone();
 
         let new_best_block = BestBlock {
-            hash: block.header.raw.previous_header_hash.clone(),
+            hash: header.raw.previous_header_hash.clone(),
             number: if best_block.number > 0 {
                 best_block.number - 1
             } else {
-                assert!(block.header.raw.previous_header_hash.is_zero());
+                assert!(header.raw.previous_header_hash.is_zero());
                 0
             },
         };
@@ -362,12 +738,632 @@ where
             KEY_BEST_BLOCK_NUMBER,
             serialize(&new_best_block.number),
         ));
+        self.update_miner_index(&mut update, &header, block_number, new_best_block.number);
+        self.push_chain_event(
+            &mut update,
+            ChainEventKind::BlockDisconnected,
+            block_hash.clone(),
+            block_number,
+        );
 
         self.db.write(update).map_err(Error::DatabaseError)?;
         *best_block = new_best_block;
         Ok(block_hash)
     }
 
+    /// Starts indexing canon blocks by miner public key. The index is rebuilt if it doesn't
+    /// cover the current best block. Returns number of indexed blocks
+    pub fn enable_miner_index(&self) -> Result<u32, Error> {
+        // blocks, canonized while the index is rebuilt, are indexed on canonization
+        self.miner_index.store(true, Ordering::SeqCst);
+        if self.miner_index_height() == Some(self.best_block().number) {
+            return Ok(0);
+        }
+
+        // canonization is only blocked while the single batch is written
+        let mut indexed = 0;
+        let mut from = 0;
+        loop {
+            let best_block = self.best_block.read();
+            let batch_end = cmp::min(from + MINER_INDEX_BATCH_SIZE, best_block.number + 1);
+            let mut update = DBTransaction::new();
+            // headers below the imported verifier state are not stored
+            for number in from..batch_end {
+                if let Some(header) = self.block_header(BlockRef::Number(number)) {
+                    update.insert(KeyValue::MinerBlock(
+                        MinerBlockKey {
+                            miner: miner_key(&header.raw),
+                            number: number,
+                        },
+                        header.hash,
+                    ));
+                    indexed += 1;
+                }
+            }
+            from = batch_end;
+
+            let is_finished = from > best_block.number;
+            if is_finished {
+                update.insert(KeyValue::Meta(
+                    KEY_MINER_INDEX_HEIGHT,
+                    serialize(&best_block.number),
+                ));
+            }
+            self.db.write(update).map_err(Error::DatabaseError)?;
+            if is_finished {
+                break;
+            }
+        }
+
+        debug!(target: "db", "{} blocks are indexed by miner", indexed);
+        Ok(indexed)
+    }
+
+    /// Returns numbers of canon blocks in the range, mined with given public key.
+    /// Returns None if the miner index is disabled
+    pub fn blocks_by_miner(&self, miner: &Bytes, range: Range<u32>) -> Option<Vec<u32>> {
+        if !self.miner_index.load(Ordering::SeqCst) {
+            return None;
+        }
+
+        // entries of blocks, which have been disconnected while the index has been disabled,
+        // are not removed, so every block is checked against the canon chain
+        Some(
+            self.db
+                .miner_blocks(miner, range)
+                .expect("db value to be fine")
+                .into_iter()
+                .filter(|&(number, ref hash)| self.block_hash(number).as_ref() == Some(hash))
+                .map(|(number, _)| number)
+                .collect(),
+        )
+    }
+
+    fn miner_index_height(&self) -> Option<u32> {
+        self.get(Key::Meta(KEY_MINER_INDEX_HEIGHT))
+            .and_then(Value::as_meta)
+            .map(|height| {
+                deserialize(&**height).expect("Inconsistent DB. Invalid miner index height.")
+            })
+    }
+
+    /// Updates miner index, when block at given number is connected or disconnected
+    fn update_miner_index(
+        &self,
+        update: &mut DBTransaction,
+        header: &IndexedBlockHeader,
+        number: u32,
+        best_number: u32,
+    ) {
+        let index_height = self.miner_index_height();
+        if !self.miner_index.load(Ordering::SeqCst) {
+            // the index doesn't cover this block => it is rebuilt when enabled again
+            if index_height.is_some() {
+                update.delete(Key::Meta(KEY_MINER_INDEX_HEIGHT));
+            }
+            return;
+        }
+
+        let key = MinerBlockKey {
+            miner: miner_key(&header.raw),
+            number: number,
+        };
+        if number == best_number {
+            update.insert(KeyValue::MinerBlock(key, header.hash.clone()));
+        } else {
+            update.delete(Key::MinerBlock(key));
+        }
+        // while the index is rebuilt, it only covers the best block once the rebuild is finished
+        let previous_best_number = if number == best_number {
+            best_number.checked_sub(1)
+        } else {
+            Some(number)
+        };
+        if index_height.is_some() && index_height == previous_best_number {
+            update.insert(KeyValue::Meta(
+                KEY_MINER_INDEX_HEIGHT,
+                serialize(&best_number),
+            ));
+        }
+    }
+
+    pub fn last_chain_event_seq(&self) -> u64 {
+        self.get(Key::Meta(KEY_LAST_CHAIN_EVENT_SEQ))
+            .and_then(Value::as_meta)
+            .map(|seq| deserialize(&**seq).expect("Inconsistent DB. Invalid chain event seq."))
+            .unwrap_or(0)
+    }
+
+    pub fn first_chain_event_seq(&self) -> u64 {
+        self.get(Key::Meta(KEY_FIRST_CHAIN_EVENT_SEQ))
+            .and_then(Value::as_meta)
+            .map(|seq| deserialize(&**seq).expect("Inconsistent DB. Invalid chain event seq."))
+            .unwrap_or(1)
+    }
+
+    pub fn chain_events(&self, since_seq: u64, max: usize) -> Vec<ChainEvent> {
+        let last_seq = cmp::min(
+            self.last_chain_event_seq(),
+            since_seq.saturating_add(max as u64),
+        );
+        (since_seq.saturating_add(1)..last_seq + 1)
+            .filter_map(|seq| self.chain_event(seq))
+            .collect()
+    }
+
+    pub fn chain_event_seq_at(&self, time: u32) -> Option<u64> {
+        // events are recorded in time order => search for the last event, recorded at or before given time
+        let (mut lo, mut hi) = (self.first_chain_event_seq(), self.last_chain_event_seq());
+        let mut found = None;
+        while lo <= hi {
+            let seq = lo + (hi - lo) / 2;
+            match self.chain_event(seq) {
+                Some(ref event) if event.time <= time => {
+                    found = Some(seq);
+                    lo = seq + 1;
+                }
+                _ => hi = seq - 1,
+            }
+        }
+        found
+    }
+
+    pub fn best_block_at_seq(&self, seq: u64) -> Option<BestBlock> {
+        let last_seq = self.last_chain_event_seq();
+        if seq < self.first_chain_event_seq() || seq > last_seq {
+            return None;
+        }
+
+        // roll back the current best block through all events, recorded after the given one
+        let mut best_block = self.best_block();
+        for seq in (seq + 1..last_seq + 1).rev() {
+            let event = self.chain_event(seq)?;
+            match event.kind {
+                ChainEventKind::BlockConnected => {
+                    best_block = BestBlock {
+                        number: event.number.checked_sub(1)?,
+                        hash: self
+                            .block_header(BlockRef::Hash(event.hash))?
+                            .raw
+                            .previous_header_hash,
+                    }
+                }
+                ChainEventKind::BlockDisconnected => {
+                    best_block = BestBlock {
+                        number: event.number,
+                        hash: event.hash,
+                    }
+                }
+                ChainEventKind::ReorgStarted | ChainEventKind::ReorgFinished => (),
+            }
+        }
+        Some(best_block)
+    }
+
+    fn chain_event(&self, seq: u64) -> Option<ChainEvent> {
+        self.get(Key::ChainEvent(seq))
+            .and_then(Value::as_chain_event)
+            .map(|event| deserialize_event(&event).expect("Inconsistent DB. Invalid chain event."))
+    }
+
+    /// Appends event to the chain events journal as a part of given update.
+    /// Events, which are older than the retention period, are removed from the journal.
+    fn push_chain_event(
+        &self,
+        update: &mut DBTransaction,
+        kind: ChainEventKind,
+        hash: H256,
+        number: u32,
+    ) {
+        let seq = self.last_chain_event_seq() + 1;
+        let event = new_event(seq, kind, hash, number);
+        update.insert(KeyValue::ChainEvent(seq, serialize_event(&event)));
+        update.insert(KeyValue::Meta(KEY_LAST_CHAIN_EVENT_SEQ, serialize(&seq)));
+
+        if let Some(retention_s) = self.chain_events_retention {
+            let oldest_time = event.time.saturating_sub(retention_s);
+            let first_seq = self.first_chain_event_seq();
+            let mut new_first_seq = first_seq;
+            while new_first_seq < seq {
+                match self.chain_event(new_first_seq) {
+                    Some(ref old_event) if old_event.time < oldest_time => {
+                        update.delete(Key::ChainEvent(new_first_seq));
+                        new_first_seq += 1;
+                    }
+                    _ => break,
+                }
+            }
+            if new_first_seq != first_seq {
+                update.insert(KeyValue::Meta(
+                    KEY_FIRST_CHAIN_EVENT_SEQ,
+                    serialize(&new_first_seq),
+                ));
+            }
+        }
+    }
+
+    /// Appends single event to the chain events journal.
+    fn write_chain_event(
+        &self,
+        kind: ChainEventKind,
+        hash: H256,
+        number: u32,
+    ) -> Result<(), Error> {
+        let mut update = DBTransaction::new();
+        self.push_chain_event(&mut update, kind, hash, number);
+        self.db.write(update).map_err(Error::DatabaseError)
+    }
+
+    pub fn verifier_state(&self, tail_len: u32, checkpoint_interval: u32) -> VerifierState {
+        let best_number = self.best_block().number;
+        // canon blocks below the imported state are unknown, so the range is extended from its peaks
+        let imported_mmr = self.imported_mmr();
+        // genesis block is never a part of the tail
+        let tail_number = cmp::max((best_number + 1).saturating_sub(tail_len), 1);
+        let tail_number = imported_mmr
+            .as_ref()
+            .map_or(tail_number, |mmr| cmp::max(tail_number, mmr.leaves()));
+        let mut mmr = imported_mmr.unwrap_or_default();
+        for number in mmr.leaves()..tail_number {
+            mmr.append(
+                self.block_hash(number)
+                    .expect("canon blocks above the imported verifier state are stored; qed"),
+            );
+        }
+        let checkpoints = (0..tail_number)
+            .step_by(cmp::max(checkpoint_interval, 1) as usize)
+            .filter_map(|number| {
+                self.block_hash(number).map(|hash| Checkpoint {
+                    number: number,
+                    hash: hash,
+                })
+            })
+            .collect();
+        let tail: Vec<IndexedBlockHeader> = (tail_number..best_number + 1)
+            .filter_map(|number| self.block_header(number.into()))
+            .collect();
+        // parent of the tail could be unknown, e.g. when it is below the imported verifier state
+        let tail_parent_work = tail
+            .first()
+            .and_then(|header| {
+                self.chain_work(&header.hash)
+                    .map(|work| work - header.raw.bits.to_work())
+            })
+            .unwrap_or_else(U256::zero);
+
+        VerifierState {
+            checkpoints: checkpoints,
+            mmr_peaks: mmr.peaks().to_vec(),
+            tail_number: tail_number,
+            tail_parent_work: tail_parent_work,
+            tail: tail,
+        }
+    }
+
+    /// Imports tail headers as blocks with pruned bodies and indexes checkpoints.
+    /// Imported chain becomes the canon chain.
+    pub fn import_verifier_state(&self, state: VerifierState) -> Result<(), Error> {
+        let mut best_block = self.best_block.write();
+        if best_block.number != 0 || best_block.hash.is_zero() {
+            return Err(invalid_state("database must only have genesis block"));
+        }
+
+        let new_best_block = state
+            .best_block()
+            .ok_or_else(|| invalid_state("state has no headers"))?;
+        match state.checkpoints.first() {
+            Some(genesis) if genesis.number == 0 && genesis.hash == best_block.hash => (),
+            _ => return Err(invalid_state("genesis block does not match")),
+        }
+        let checkpoints_ordered = state
+            .checkpoints
+            .windows(2)
+            .all(|pair| pair[0].number < pair[1].number);
+        let last_checkpoint = state
+            .checkpoints
+            .last()
+            .expect("genesis checkpoint is checked above; qed");
+        if !checkpoints_ordered || last_checkpoint.number >= state.tail_number {
+            return Err(invalid_state(
+                "checkpoints must be ordered and below the tail",
+            ));
+        }
+        if last_checkpoint.number + 1 == state.tail_number
+            && last_checkpoint.hash != state.tail[0].raw.previous_header_hash
+        {
+            return Err(invalid_state("tail does not follow the last checkpoint"));
+        }
+        if state
+            .tail
+            .windows(2)
+            .any(|pair| pair[1].raw.previous_header_hash != pair[0].hash)
+        {
+            return Err(invalid_state("tail headers are not linked"));
+        }
+        let mmr = MerkleMountainRange::from_peaks(state.tail_number, state.mmr_peaks)
+            .ok_or_else(|| invalid_state("merkle mountain range does not match the tail"))?;
+
+        let mut update = DBTransaction::new();
+        update.insert(KeyValue::Meta(KEY_VERIFIER_STATE_MMR, serialize_mmr(&mmr)));
+        for checkpoint in &state.checkpoints[1..] {
+            update.insert(KeyValue::BlockHash(
+                checkpoint.number,
+                checkpoint.hash.clone(),
+            ));
+            update.insert(KeyValue::BlockNumber(
+                checkpoint.hash.clone(),
+                checkpoint.number,
+            ));
+        }
+        let mut chain_work = state.tail_parent_work;
+        for (number, header) in (state.tail_number..).zip(state.tail) {
+            chain_work = header.raw.bits.add_work(chain_work);
+            update.insert(KeyValue::ChainWork(header.hash.clone(), chain_work));
+            let mut children = self.block_children(&header.raw.previous_header_hash);
+            children.push(header.hash.clone());
+            update.insert(KeyValue::BlockChildren(
+                header.raw.previous_header_hash.clone(),
+                List::from(children),
+            ));
+            update.insert(KeyValue::BlockHash(number, header.hash.clone()));
+            update.insert(KeyValue::BlockNumber(header.hash.clone(), number));
+            update.insert(KeyValue::Block(
+                header.hash,
+                Block {
+                    block_header: header.raw,
+                    proof: Vec::new(),
+                },
+            ));
+        }
+        update.insert(KeyValue::Meta(
+            KEY_BEST_BLOCK_HASH,
+            serialize(&new_best_block.hash),
+        ));
+        update.insert(KeyValue::Meta(
+            KEY_BEST_BLOCK_NUMBER,
+            serialize(&new_best_block.number),
+        ));
+        // bodies of imported blocks are unknown, so they're never served to peers
+        update.insert(KeyValue::Meta(
+            KEY_PRUNED_HEIGHT,
+            serialize(&new_best_block.number),
+        ));
+
+        trace!(target: "db", "import verifier state, new best: {:?}", new_best_block);
+
+        self.db.write(update).map_err(Error::DatabaseError)?;
+        *self.pruned_height.write() = Some(new_best_block.number);
+        *best_block = new_best_block;
+        Ok(())
+    }
+
+    /// Returns merkle mountain range over canon blocks below the imported verifier state, if any
+    fn imported_mmr(&self) -> Option<MerkleMountainRange> {
+        self.get(Key::Meta(KEY_VERIFIER_STATE_MMR))
+            .and_then(Value::as_meta)
+            .map(|mmr| deserialize_mmr(&mmr).expect("Inconsistent DB. Invalid verifier state MMR."))
+    }
+
+    /// Returns true if chain work of all stored blocks is computed
+    pub fn chain_work_computed(&self) -> bool {
+        self.get(Key::Meta(KEY_CHAIN_WORK_COMPUTED)).is_some()
+    }
+
+    /// Computes chain work of stored blocks, which have been inserted before it has been tracked.
+    /// Canon blocks are walked by number, so their work is computed even if their children
+    /// are not indexed. Numbers between checkpoints of the imported verifier state are unknown,
+    /// so the walk continues from the stored work of the imported tail. Side chains are walked
+    /// through the children index. Returns number of updated blocks
+    pub fn recompute_chain_work(&self) -> Result<u32, Error> {
+        let mut updated = 0;
+        let mut update = DBTransaction::new();
+        let mut parent_work = Some(U256::zero());
+        for number in 0..=self.best_block().number {
+            let hash = match self.block_hash(number) {
+                Some(hash) => hash,
+                None => {
+                    parent_work = None;
+                    continue;
+                }
+            };
+
+            // work of parents is only written when the batch is flushed, so it is passed here
+            let chain_work = match parent_work {
+                Some(parent_work) => {
+                    self.recompute_block_work(&hash, parent_work, &mut update, &mut updated)
+                }
+                None => match self.chain_work(&hash) {
+                    Some(chain_work) => chain_work,
+                    None => continue,
+                },
+            };
+            let mut side_works = vec![(hash, chain_work)];
+            while let Some((hash, work)) = side_works.pop() {
+                for child in self.block_children(&hash) {
+                    if self.block_number(&child).is_none() {
+                        let child_work =
+                            self.recompute_block_work(&child, work, &mut update, &mut updated);
+                        side_works.push((child, child_work));
+                    }
+                }
+            }
+
+            if update.operations.len() >= CHAIN_WORK_BATCH_SIZE {
+                let batch = ::std::mem::replace(&mut update, DBTransaction::new());
+                self.db.write(batch).map_err(Error::DatabaseError)?;
+            }
+            parent_work = Some(chain_work);
+        }
+
+        update.insert(KeyValue::Meta(KEY_CHAIN_WORK_COMPUTED, serialize(&1u8)));
+        self.db.write(update).map_err(Error::DatabaseError)?;
+        if updated != 0 {
+            debug!(target: "db", "Chain work of {} blocks is computed", updated);
+        }
+        Ok(updated)
+    }
+
+    /// Returns stored chain work of the block, or computes it from the work of the parent chain
+    fn recompute_block_work(
+        &self,
+        hash: &H256,
+        parent_work: U256,
+        update: &mut DBTransaction,
+        updated: &mut u32,
+    ) -> U256 {
+        if let Some(chain_work) = self.chain_work(hash) {
+            return chain_work;
+        }
+
+        let header = self
+            .block_header(hash.clone().into())
+            .expect("indexed blocks are stored; qed");
+        let chain_work = header.raw.bits.add_work(parent_work);
+        update.insert(KeyValue::ChainWork(hash.clone(), chain_work));
+        *updated += 1;
+        chain_work
+    }
+
+    /// Returns cumulative work of the chain, ending with the block with given hash
+    pub fn chain_work(&self, hash: &H256) -> Option<U256> {
+        self.get(Key::ChainWork(hash.clone()))
+            .and_then(Value::as_chain_work)
+    }
+
+    /// Computes cumulative work of the chain, ending with given header.
+    /// Returns None if work of the parent chain is unknown
+    fn compute_chain_work(&self, header: &BlockHeader) -> Option<U256> {
+        let parent_work = match header.previous_header_hash.is_zero() {
+            true => U256::zero(),
+            false => self.chain_work(&header.previous_header_hash)?,
+        };
+        Some(header.bits.add_work(parent_work))
+    }
+
+    pub fn pruned_height(&self) -> Option<u32> {
+        *self.pruned_height.read()
+    }
+
+    /// Replaces bodies of canon blocks in range (pruned_height; up_to] with empty proofs.
+    /// Bodies, retained by in-flight requests, are kept.
+    pub fn prune(&self, up_to: u32) -> Result<Option<u32>, Error> {
+        self.retained_bodies
+            .with_lowest(|retained_from| self.prune_below(up_to, retained_from))
+    }
+
+    fn prune_below(&self, up_to: u32, retained_from: Option<u32>) -> Result<Option<u32>, Error> {
+        let mut pruned_height = self.pruned_height.write();
+        // never prune genesis block and blocks above the best block
+        let up_to = cmp::min(up_to, self.best_block.read().number);
+        // never prune bodies, which are still in use
+        let up_to = match retained_from {
+            Some(retained_from) if retained_from <= up_to => {
+                trace!(target: "db", "prune is limited by retained block body {}", retained_from);
+                retained_from.saturating_sub(1)
+            }
+            _ => up_to,
+        };
+        let from = pruned_height.map_or(1, |height| height + 1);
+        if up_to < from {
+            return Ok(*pruned_height);
+        }
+
+        if let Some(ref hook) = self.backup_hook {
+            // blocks are backed up in batches, so that bodies of the whole range are never
+            // kept in memory at once
+            let mut batch_from = from;
+            while batch_from <= up_to {
+                let batch_to = cmp::min(batch_from + (PRUNE_BACKUP_BATCH_SIZE - 1), up_to);
+                let blocks = (batch_from..batch_to + 1)
+                    .filter_map(|number| self.block_hash(number))
+                    .filter_map(|hash| self.read_block(&hash))
+                    .filter(|block| !block.proof.is_empty())
+                    .collect::<Vec<_>>();
+                if !blocks.is_empty() {
+                    hook.backup_blocks(BackupReason::Prune, &blocks)?;
+                }
+                batch_from = batch_to + 1;
+            }
+        }
+
+        let mut update = DBTransaction::new();
+        // stats are read and written back under the lock
+        let _stats_lock = self.stats_lock.lock();
+        let mut stats = self.stats();
+        for number in from..up_to + 1 {
+            let hash = self
+                .block_hash(number)
+                .expect("number is not above the best block; qed");
+            if let Some(body) = self
+                .get(Key::BlockBody(hash.clone()))
+                .and_then(Value::as_block_body)
+            {
+                let raw_body = self
+                    .compression
+                    .decompress_raw(&body)
+                    .expect("Inconsistent DB. Invalid compressed block body.");
+                stats.compressed_blocks = stats.compressed_blocks.saturating_sub(1);
+                stats.compressed_bodies_raw_size = stats
+                    .compressed_bodies_raw_size
+                    .saturating_sub(raw_body.len() as u64);
+                stats.compressed_bodies_size = stats
+                    .compressed_bodies_size
+                    .saturating_sub(body.len() as u64);
+                update.delete(Key::BlockBody(hash.clone()));
+            }
+            if let Some(block) = self.get(Key::Block(hash.clone())).and_then(Value::as_block) {
+                update.insert(KeyValue::Block(
+                    hash,
+                    Block {
+                        block_header: block.block_header,
+                        proof: Vec::new(),
+                    },
+                ));
+            }
+        }
+        update.insert(KeyValue::Meta(KEY_PRUNED_HEIGHT, serialize(&up_to)));
+        update.insert(KeyValue::Meta(KEY_DB_STATS, serialize_stats(&stats)));
+
+        trace!(target: "db", "prune block bodies {}..{}", from, up_to);
+
+        self.db.write(update).map_err(Error::DatabaseError)?;
+        *pruned_height = Some(up_to);
+        Ok(Some(up_to))
+    }
+
+    pub fn retain_bodies(&self, from: u32) -> PruneGuard {
+        RetainedBodies::retain(&self.retained_bodies, from)
+    }
+
+    /// Returns true if body of the block with given hash is pruned.
+    fn is_pruned(&self, hash: &H256) -> bool {
+        match *self.pruned_height.read() {
+            Some(pruned_height) => self
+                .block_number(hash)
+                .map_or(false, |number| number != 0 && number <= pruned_height),
+            None => false,
+        }
+    }
+
+    /// Reads block, decompressing its body if required.
+    fn read_block(&self, hash: &H256) -> Option<Block> {
+        let mut block = self
+            .get(Key::Block(hash.clone()))
+            .and_then(Value::as_block)?;
+        if block.proof.is_empty() {
+            if let Some(body) = self
+                .get(Key::BlockBody(hash.clone()))
+                .and_then(Value::as_block_body)
+            {
+                block.proof = self
+                    .compression
+                    .decompress(&body)
+                    .expect("Inconsistent DB. Invalid compressed block body.");
+            }
+        }
+        Some(block)
+    }
+
     fn get(&self, key: Key) -> Option<Value> {
         self.db
             .get(&key)
@@ -417,22 +1413,39 @@ where
 
     fn block(&self, block_ref: BlockRef) -> Option<IndexedBlock> {
         self.resolve_hash(block_ref).and_then(|block_hash| {
-            self.get(Key::Block(block_hash.clone()))
-                .and_then(Value::as_block)
-                .map(|block| {
-                    IndexedBlock::new(
-                        IndexedBlockHeader::new(block_hash, block.block_header),
-                        block.proof,
-                    )
-                })
+            if self.is_pruned(&block_hash) {
+                return None;
+            }
+
+            self.read_block(&block_hash).map(|block| {
+                IndexedBlock::new(
+                    IndexedBlockHeader::new(block_hash, block.block_header),
+                    block.proof,
+                )
+            })
         })
     }
 
+    fn block_iter(&self, range: Range<u32>) -> BlockRangeIterator {
+        BlockRangeIterator::new(range, self)
+    }
+
     fn contains_block(&self, block_ref: BlockRef) -> bool {
         self.resolve_hash(block_ref)
             .and_then(|hash| self.get(Key::Block(hash)))
             .is_some()
     }
+
+    fn block_children(&self, hash: &H256) -> Vec<H256> {
+        self.get(Key::BlockChildren(hash.clone()))
+            .and_then(Value::as_block_children)
+            .map(List::into)
+            .unwrap_or_default()
+    }
+
+    fn chain_work(&self, hash: &H256) -> Option<U256> {
+        BlockChainDatabase::chain_work(self, hash)
+    }
 }
 
 impl<T> BlockChain for BlockChainDatabase<T>
@@ -478,6 +1491,78 @@ where
     }
 }
 
+impl<T> PruneStore for BlockChainDatabase<T>
+where
+    T: KeyValueDatabase,
+{
+    fn pruned_height(&self) -> Option<u32> {
+        BlockChainDatabase::pruned_height(self)
+    }
+
+    fn prune(&self, up_to: u32) -> Result<Option<u32>, Error> {
+        BlockChainDatabase::prune(self, up_to)
+    }
+
+    fn retain_bodies(&self, from: u32) -> PruneGuard {
+        BlockChainDatabase::retain_bodies(self, from)
+    }
+}
+
+impl<T> ChainEventStore for BlockChainDatabase<T>
+where
+    T: KeyValueDatabase,
+{
+    fn last_chain_event_seq(&self) -> u64 {
+        BlockChainDatabase::last_chain_event_seq(self)
+    }
+
+    fn chain_events(&self, since_seq: u64, max: usize) -> Vec<ChainEvent> {
+        BlockChainDatabase::chain_events(self, since_seq, max)
+    }
+
+    fn first_chain_event_seq(&self) -> u64 {
+        BlockChainDatabase::first_chain_event_seq(self)
+    }
+
+    fn chain_event_seq_at(&self, time: u32) -> Option<u64> {
+        BlockChainDatabase::chain_event_seq_at(self, time)
+    }
+
+    fn best_block_at_seq(&self, seq: u64) -> Option<BestBlock> {
+        BlockChainDatabase::best_block_at_seq(self, seq)
+    }
+
+    fn last_reorg(&self) -> Option<Reorg> {
+        BlockChainDatabase::last_reorg(self)
+    }
+}
+
+impl<T> MinerIndexStore for BlockChainDatabase<T>
+where
+    T: KeyValueDatabase,
+{
+    fn enable_miner_index(&self) -> Result<u32, Error> {
+        BlockChainDatabase::enable_miner_index(self)
+    }
+
+    fn blocks_by_miner(&self, miner: &Bytes, range: Range<u32>) -> Option<Vec<u32>> {
+        BlockChainDatabase::blocks_by_miner(self, miner, range)
+    }
+}
+
+impl<T> VerifierStateStore for BlockChainDatabase<T>
+where
+    T: KeyValueDatabase,
+{
+    fn verifier_state(&self, tail_len: u32, checkpoint_interval: u32) -> VerifierState {
+        BlockChainDatabase::verifier_state(self, tail_len, checkpoint_interval)
+    }
+
+    fn import_verifier_state(&self, state: VerifierState) -> Result<(), Error> {
+        BlockChainDatabase::import_verifier_state(self, state)
+    }
+}
+
 impl<T> CanonStore for BlockChainDatabase<T>
 where
     T: KeyValueDatabase,
@@ -485,6 +1570,38 @@ where
     fn as_store(&self) -> &dyn Store {
         &*self
     }
+
+    fn stats(&self) -> DbStats {
+        BlockChainDatabase::stats(self)
+    }
+
+    fn size(&self) -> Result<DbSize, Error> {
+        BlockChainDatabase::size(self)
+    }
+
+    fn compact(&self) -> Result<(), Error> {
+        BlockChainDatabase::compact(self)
+    }
+
+    fn block_children_indexed(&self) -> bool {
+        BlockChainDatabase::block_children_indexed(self)
+    }
+
+    fn index_block_children(&self) -> Result<u32, Error> {
+        BlockChainDatabase::index_block_children(self)
+    }
+
+    fn chain_work_computed(&self) -> bool {
+        BlockChainDatabase::chain_work_computed(self)
+    }
+
+    fn recompute_chain_work(&self) -> Result<u32, Error> {
+        BlockChainDatabase::recompute_chain_work(self)
+    }
+
+    fn init_genesis(&self, genesis: IndexedBlock) -> Result<bool, Error> {
+        BlockChainDatabase::init_genesis(self, genesis)
+    }
 }
 
 impl<T> Store for BlockChainDatabase<T>
@@ -511,7 +1628,20 @@ impl<T> ConfigStore for BlockChainDatabase<T>
 where
     T: KeyValueDatabase,
 {
-    // TODO:
-    // + get something
-    // + set something
+    fn invalidated_blocks(&self) -> Vec<H256> {
+        BlockChainDatabase::invalidated_blocks(self)
+    }
+
+    fn set_invalidated_blocks(&self, blocks: &[H256]) -> Result<(), Error> {
+        BlockChainDatabase::set_invalidated_blocks(self, blocks)
+    }
+}
+
+/// Key of the miner index: serialized public key of the block miner
+fn miner_key(header: &BlockHeader) -> Bytes {
+    header.pubkey.to_bytes().to_vec().into()
+}
+
+fn invalid_state(reason: &str) -> Error {
+    Error::CannotImportVerifierState(reason.into())
 }
@@ -0,0 +1,199 @@
+use bytes::Bytes;
+use kv::{Key, KeyValueDatabase, Value};
+use parking_lot::{Mutex, RwLock};
+use ser::{Deserializable, Reader, Stream};
+use std::io::{Read, Write};
+use storage::DbStats;
+use zstd;
+
+/// Key of the trained compression dictionary in the meta column.
+pub const KEY_COMPRESSION_DICTIONARY: &'static str = "compression_dictionary";
+/// Key of the database statistics in the meta column.
+pub const KEY_DB_STATS: &'static str = "db_stats";
+
+/// Body is compressed without dictionary.
+const BODY_ZSTD: u8 = 1;
+/// Body is compressed with the trained dictionary.
+const BODY_ZSTD_DICTIONARY: u8 = 2;
+/// zstd compression level.
+const COMPRESSION_LEVEL: i32 = 3;
+/// Number of raw bodies, the dictionary is trained on.
+const DICTIONARY_TRAINING_SAMPLES: usize = 256;
+/// Max size of the trained dictionary.
+const MAX_DICTIONARY_SIZE: usize = 16 * 1024;
+
+/// Compression of block bodies at rest.
+/// Compressed body is prefixed with the flag, telling how it has been compressed.
+pub struct BlockCompression {
+    /// Trained dictionary. Once stored, it is never changed, because stored bodies depend on it.
+    dictionary: RwLock<Option<Bytes>>,
+    /// Raw bodies, collected to train the dictionary.
+    samples: Mutex<Vec<Bytes>>,
+}
+
+impl BlockCompression {
+    /// Creates compression with dictionary, which has been read from the database.
+    pub fn new(dictionary: Option<Bytes>) -> Self {
+        BlockCompression {
+            dictionary: RwLock::new(dictionary),
+            samples: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Reads dictionary from the database.
+    pub fn read_dictionary<T: KeyValueDatabase + ?Sized>(db: &T) -> Option<Bytes> {
+        db.get(&Key::Meta(KEY_COMPRESSION_DICTIONARY))
+            .expect("db value to be fine")
+            .into_option()
+            .and_then(Value::as_meta)
+    }
+
+    /// Compresses raw body. Returns None if compressed body is not smaller than the raw one.
+    pub fn compress(&self, raw: &[u8]) -> Option<Bytes> {
+        let dictionary = self.dictionary.read();
+        let mut compressed = Bytes::new();
+        let result = match *dictionary {
+            Some(ref dictionary) => {
+                compressed.push(BODY_ZSTD_DICTIONARY);
+                zstd::stream::Encoder::with_dictionary(compressed, COMPRESSION_LEVEL, dictionary)
+                    .and_then(|mut encoder| encoder.write_all(raw).and_then(|_| encoder.finish()))
+            }
+            None => {
+                compressed.push(BODY_ZSTD);
+                zstd::stream::Encoder::new(compressed, COMPRESSION_LEVEL)
+                    .and_then(|mut encoder| encoder.write_all(raw).and_then(|_| encoder.finish()))
+            }
+        };
+
+        match result {
+            Ok(ref compressed) if compressed.len() < raw.len() => Some(compressed.clone()),
+            Ok(_) => None,
+            Err(err) => {
+                warn!(target: "db", "Failed to compress block body: {}", err);
+                None
+            }
+        }
+    }
+
+    /// Remembers raw body as the dictionary training sample.
+    /// Returns new dictionary, once enough samples are collected.
+    pub fn note_sample(&self, raw: &[u8]) -> Option<Bytes> {
+        if self.dictionary.read().is_some() {
+            return None;
+        }
+
+        let mut samples = self.samples.lock();
+        samples.push(raw.into());
+        if samples.len() < DICTIONARY_TRAINING_SAMPLES {
+            return None;
+        }
+
+        let result = zstd::dict::from_samples(&*samples, MAX_DICTIONARY_SIZE);
+        samples.clear();
+        match result {
+            Ok(dictionary) => Some(dictionary.into()),
+            Err(err) => {
+                // bodies are still compressed without dictionary
+                debug!(target: "db", "Failed to train block bodies compression dictionary: {}", err);
+                None
+            }
+        }
+    }
+
+    /// Starts using dictionary, which has been written to the database.
+    pub fn set_dictionary(&self, dictionary: Bytes) {
+        *self.dictionary.write() = Some(dictionary);
+    }
+
+    /// Decompresses stored body and reads proof from it.
+    pub fn decompress<T: Deserializable>(&self, stored: &[u8]) -> Result<Vec<T>, String> {
+        let raw = self.decompress_raw(stored)?;
+        Reader::new(&raw)
+            .read_list()
+            .map_err(|err| format!("{:?}", err))
+    }
+
+    /// Decompresses stored body into raw body.
+    pub fn decompress_raw(&self, stored: &[u8]) -> Result<Vec<u8>, String> {
+        let (flag, compressed) = match stored.split_first() {
+            Some((flag, compressed)) => (*flag, compressed),
+            None => return Err("empty compressed body".into()),
+        };
+
+        let mut raw = Vec::new();
+        let result = match flag {
+            BODY_ZSTD => zstd::stream::Decoder::new(compressed)
+                .and_then(|mut decoder| decoder.read_to_end(&mut raw)),
+            BODY_ZSTD_DICTIONARY => match *self.dictionary.read() {
+                Some(ref dictionary) => {
+                    zstd::stream::Decoder::with_dictionary(compressed, dictionary)
+                        .and_then(|mut decoder| decoder.read_to_end(&mut raw))
+                }
+                None => return Err("compression dictionary is missing".into()),
+            },
+            flag => return Err(format!("unknown compression flag {}", flag)),
+        };
+
+        result.map(|_| raw).map_err(|err| err.to_string())
+    }
+}
+
+/// Serializes database statistics into the meta value.
+pub fn serialize_stats(stats: &DbStats) -> Bytes {
+    let mut stream = Stream::new();
+    stream
+        .append(&stats.compressed_blocks)
+        .append(&stats.compressed_bodies_raw_size)
+        .append(&stats.compressed_bodies_size);
+    stream.out()
+}
+
+/// Deserializes database statistics from the meta value.
+pub fn deserialize_stats(bytes: &[u8]) -> Result<DbStats, String> {
+    let mut reader = Reader::new(bytes);
+    let read = |reader: &mut Reader<&[u8]>| reader.read().map_err(|err| format!("{:?}", err));
+    Ok(DbStats {
+        compressed_blocks: read(&mut reader)?,
+        compressed_bodies_raw_size: read(&mut reader)?,
+        compressed_bodies_size: read(&mut reader)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{deserialize_stats, serialize_stats, BlockCompression, BODY_ZSTD};
+    use storage::DbStats;
+
+    #[test]
+    fn compressed_body_is_decompressed() {
+        let compression = BlockCompression::new(None);
+        let raw = vec![42u8; 4096];
+        let compressed = compression.compress(&raw).unwrap();
+        assert_eq!(compressed[0], BODY_ZSTD);
+        assert!(compressed.len() < raw.len());
+        assert_eq!(compression.decompress_raw(&compressed), Ok(raw));
+    }
+
+    #[test]
+    fn incompressible_body_is_not_compressed() {
+        let compression = BlockCompression::new(None);
+        assert_eq!(compression.compress(&[1u8, 2, 3]), None);
+        assert!(compression.decompress_raw(&[]).is_err());
+        assert!(compression.decompress_raw(&[255u8, 0]).is_err());
+    }
+
+    #[test]
+    fn db_stats_are_serialized() {
+        let stats = DbStats {
+            compressed_blocks: 2,
+            compressed_bodies_raw_size: 300,
+            compressed_bodies_size: 100,
+        };
+        assert_eq!(
+            deserialize_stats(&serialize_stats(&stats)),
+            Ok(stats.clone())
+        );
+        assert_eq!(stats.compression_ratio(), Some(3f64));
+        assert_eq!(DbStats::default().compression_ratio(), None);
+    }
+}
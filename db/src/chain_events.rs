@@ -0,0 +1,127 @@
+use bytes::Bytes;
+use hash::H256;
+use ser::{Reader, Stream};
+use std::time::{SystemTime, UNIX_EPOCH};
+use storage::{ChainEvent, ChainEventKind, Reorg};
+
+/// Key of the last chain event sequence number in the meta column.
+pub const KEY_LAST_CHAIN_EVENT_SEQ: &'static str = "last_chain_event_seq";
+/// Key of the first retained chain event sequence number in the meta column.
+pub const KEY_FIRST_CHAIN_EVENT_SEQ: &'static str = "first_chain_event_seq";
+/// Key of the last reorganization record in the meta column.
+pub const KEY_LAST_REORG: &'static str = "last_reorg";
+
+const EVENT_BLOCK_CONNECTED: u8 = 0;
+const EVENT_BLOCK_DISCONNECTED: u8 = 1;
+const EVENT_REORG_STARTED: u8 = 2;
+const EVENT_REORG_FINISHED: u8 = 3;
+
+/// Creates new chain event, timestamped with the current time.
+pub fn new_event(seq: u64, kind: ChainEventKind, hash: H256, number: u32) -> ChainEvent {
+    ChainEvent {
+        seq: seq,
+        kind: kind,
+        hash: hash,
+        number: number,
+        time: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs() as u32)
+            .unwrap_or(0),
+    }
+}
+
+/// Serializes chain event into the journal value.
+pub fn serialize_event(event: &ChainEvent) -> Bytes {
+    let kind = match event.kind {
+        ChainEventKind::BlockConnected => EVENT_BLOCK_CONNECTED,
+        ChainEventKind::BlockDisconnected => EVENT_BLOCK_DISCONNECTED,
+        ChainEventKind::ReorgStarted => EVENT_REORG_STARTED,
+        ChainEventKind::ReorgFinished => EVENT_REORG_FINISHED,
+    };
+
+    let mut stream = Stream::new();
+    stream
+        .append(&event.seq)
+        .append(&kind)
+        .append(&event.hash)
+        .append(&event.number)
+        .append(&event.time);
+    stream.out()
+}
+
+/// Deserializes chain event from the journal value.
+pub fn deserialize_event(bytes: &[u8]) -> Result<ChainEvent, String> {
+    let mut reader = Reader::new(bytes);
+    let seq = reader.read().map_err(|err| format!("{:?}", err))?;
+    let kind = match reader.read::<u8>().map_err(|err| format!("{:?}", err))? {
+        EVENT_BLOCK_CONNECTED => ChainEventKind::BlockConnected,
+        EVENT_BLOCK_DISCONNECTED => ChainEventKind::BlockDisconnected,
+        EVENT_REORG_STARTED => ChainEventKind::ReorgStarted,
+        EVENT_REORG_FINISHED => ChainEventKind::ReorgFinished,
+        kind => return Err(format!("unknown chain event kind {}", kind)),
+    };
+    Ok(ChainEvent {
+        seq: seq,
+        kind: kind,
+        hash: reader.read().map_err(|err| format!("{:?}", err))?,
+        number: reader.read().map_err(|err| format!("{:?}", err))?,
+        time: reader.read().map_err(|err| format!("{:?}", err))?,
+    })
+}
+
+/// Serializes reorganization record.
+pub fn serialize_reorg(reorg: &Reorg) -> Bytes {
+    let mut stream = Stream::new();
+    stream
+        .append(&reorg.ancestor)
+        .append_list::<H256, H256>(&reorg.disconnected)
+        .append_list::<H256, H256>(&reorg.connected);
+    stream.out()
+}
+
+/// Deserializes reorganization record.
+pub fn deserialize_reorg(bytes: &[u8]) -> Result<Reorg, String> {
+    let mut reader = Reader::new(bytes);
+    Ok(Reorg {
+        ancestor: reader.read().map_err(|err| format!("{:?}", err))?,
+        disconnected: reader.read_list().map_err(|err| format!("{:?}", err))?,
+        connected: reader.read_list().map_err(|err| format!("{:?}", err))?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        deserialize_event, deserialize_reorg, new_event, serialize_event, serialize_reorg,
+    };
+    use storage::{ChainEventKind, Reorg};
+
+    #[test]
+    fn chain_event_is_serialized() {
+        let event = new_event(7, ChainEventKind::ReorgStarted, 42.into(), 3);
+        assert_eq!(
+            deserialize_event(&serialize_event(&event)),
+            Ok(event.clone())
+        );
+
+        let mut bytes = serialize_event(&event);
+        bytes[8] = 255;
+        assert!(deserialize_event(&bytes).is_err());
+    }
+
+    #[test]
+    fn reorg_is_serialized() {
+        let reorg = Reorg {
+            ancestor: 10,
+            disconnected: vec![1.into(), 2.into()],
+            connected: vec![3.into(), 4.into(), 5.into()],
+        };
+        assert_eq!(
+            deserialize_reorg(&serialize_reorg(&reorg)),
+            Ok(reorg.clone())
+        );
+
+        let bytes = serialize_reorg(&reorg);
+        assert!(deserialize_reorg(&bytes[..bytes.len() - 1]).is_err());
+    }
+}
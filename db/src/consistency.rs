@@ -0,0 +1,422 @@
+use block_chain_db::{KEY_BEST_BLOCK_HASH, KEY_BEST_BLOCK_NUMBER, KEY_PRUNED_HEIGHT};
+use block_compression::{deserialize_stats, serialize_stats, BlockCompression, KEY_DB_STATS};
+use bytes::Bytes;
+use hash::H256;
+use kv::{
+    DatabaseConfig, DiskDatabase, Key, KeyValue, KeyValueDatabase, Location, Transaction, Value,
+};
+use kv::{COL_BLOCK_BODIES, COL_BLOCK_NUMBERS, COL_COUNT};
+use ser::{deserialize, Deserializable};
+use std::fmt;
+use std::path::Path;
+use storage::Error;
+
+/// Inconsistency of the database indexes and contents.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Inconsistency {
+    /// Best block is not the canon block at the best height
+    BestBlockMismatch {
+        number: u32,
+        best_hash: H256,
+        canon_hash: Option<H256>,
+    },
+    /// Hash of the canon block at this height is not indexed
+    MissingCanonHash(u32),
+    /// Number of the canon block is not indexed or differs from its height
+    BadBlockNumber {
+        number: u32,
+        hash: H256,
+        indexed: Option<u32>,
+    },
+    /// Number is indexed for the block, which is not canon at this height
+    StaleBlockNumber { number: u32, hash: H256 },
+    /// Header of the canon block is missing
+    MissingHeader { number: u32, hash: H256 },
+    /// Canon block does not follow the canon block at the previous height
+    BadParent {
+        number: u32,
+        hash: H256,
+        parent: H256,
+    },
+    /// Compressed body is stored for the unknown block
+    OrphanBody(H256),
+    /// Body of the block is stored both compressed and uncompressed
+    DuplicateBody(H256),
+    /// Compressed body of the pruned block is not removed
+    PrunedBody { number: u32, hash: H256 },
+    /// Compressed body can't be decompressed
+    UnreadableBody { hash: H256, error: String },
+}
+
+/// Result of the database consistency check.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ConsistencyReport {
+    /// Number of checked canon blocks
+    pub checked_blocks: u32,
+    /// Found inconsistencies
+    pub inconsistencies: Vec<Inconsistency>,
+    /// Number of repaired inconsistencies
+    pub repaired: usize,
+}
+
+/// Offline consistency checker of the database. Database must not be used by running node.
+pub struct ConsistencyChecker {
+    db: DiskDatabase,
+    compression: BlockCompression,
+}
+
+impl Inconsistency {
+    /// Returns true if inconsistency could be repaired without data loss. Remaining
+    /// inconsistencies are only fixed by reindex
+    pub fn is_repairable(&self) -> bool {
+        match *self {
+            Inconsistency::BadBlockNumber { .. }
+            | Inconsistency::StaleBlockNumber { .. }
+            | Inconsistency::OrphanBody(_)
+            | Inconsistency::DuplicateBody(_)
+            | Inconsistency::PrunedBody { .. } => true,
+            Inconsistency::BestBlockMismatch { .. }
+            | Inconsistency::MissingCanonHash(_)
+            | Inconsistency::MissingHeader { .. }
+            | Inconsistency::BadParent { .. }
+            | Inconsistency::UnreadableBody { .. } => false,
+        }
+    }
+}
+
+impl fmt::Display for Inconsistency {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Inconsistency::BestBlockMismatch {
+                number,
+                ref best_hash,
+                ref canon_hash,
+            } => write!(
+                f,
+                "best block {} is not canon at height {} (canon: {})",
+                best_hash.reversed(),
+                number,
+                canon_hash
+                    .as_ref()
+                    .map_or("none".to_owned(), |hash| hash.reversed().to_string()),
+            ),
+            Inconsistency::MissingCanonHash(number) => {
+                write!(f, "hash of the canon block at height {} is missing", number)
+            }
+            Inconsistency::BadBlockNumber {
+                number,
+                ref hash,
+                indexed,
+            } => write!(
+                f,
+                "canon block {} at height {} is indexed at height {}",
+                hash.reversed(),
+                number,
+                indexed.map_or("none".to_owned(), |indexed| indexed.to_string()),
+            ),
+            Inconsistency::StaleBlockNumber { number, ref hash } => write!(
+                f,
+                "block {} is indexed at height {}, but it isn't canon there",
+                hash.reversed(),
+                number
+            ),
+            Inconsistency::MissingHeader { number, ref hash } => write!(
+                f,
+                "header of the canon block {} at height {} is missing",
+                hash.reversed(),
+                number
+            ),
+            Inconsistency::BadParent {
+                number,
+                ref hash,
+                ref parent,
+            } => write!(
+                f,
+                "canon block {} at height {} follows {} instead of the previous canon block",
+                hash.reversed(),
+                number,
+                parent.reversed()
+            ),
+            Inconsistency::OrphanBody(ref hash) => {
+                write!(f, "body of the unknown block {} is stored", hash.reversed())
+            }
+            Inconsistency::DuplicateBody(ref hash) => write!(
+                f,
+                "body of the block {} is stored both compressed and uncompressed",
+                hash.reversed()
+            ),
+            Inconsistency::PrunedBody { number, ref hash } => write!(
+                f,
+                "body of the pruned block {} at height {} is stored",
+                hash.reversed(),
+                number
+            ),
+            Inconsistency::UnreadableBody {
+                ref hash,
+                ref error,
+            } => write!(
+                f,
+                "body of the block {} is unreadable: {}",
+                hash.reversed(),
+                error
+            ),
+        }
+    }
+}
+
+impl ConsistencyReport {
+    /// Returns true if no inconsistencies are found
+    pub fn is_consistent(&self) -> bool {
+        self.inconsistencies.is_empty()
+    }
+}
+
+impl ConsistencyChecker {
+    pub fn open_at_path<P>(path: P) -> Result<Self, Error>
+    where
+        P: AsRef<Path>,
+    {
+        let cfg = DatabaseConfig::with_columns(Some(COL_COUNT));
+        match DiskDatabase::open(cfg, path) {
+            Ok(db) => {
+                let dictionary = BlockCompression::read_dictionary(&db);
+                Ok(ConsistencyChecker {
+                    db: db,
+                    compression: BlockCompression::new(dictionary),
+                })
+            }
+            Err(err) => Err(Error::DatabaseError(err)),
+        }
+    }
+
+    /// Checks round-trips of the number→hash and hash→number indexes, parent links of the
+    /// canon chain and agreement of stored headers and compressed bodies. When `repair` is
+    /// true, repairable inconsistencies are fixed.
+    pub fn check(&self, repair: bool) -> Result<ConsistencyReport, Error> {
+        let mut report = ConsistencyReport::default();
+        let best_number: Option<u32> = self.meta(KEY_BEST_BLOCK_NUMBER)?;
+        let best_hash: Option<H256> = self.meta(KEY_BEST_BLOCK_HASH)?;
+        let pruned_height: Option<u32> = self.meta(KEY_PRUNED_HEIGHT)?;
+        // headers and hashes of blocks, which are below the imported verifier state, are unknown
+        let is_pruned = |number: u32| pruned_height.map_or(false, |height| number <= height);
+
+        let best_number = match (best_number, best_hash) {
+            (Some(best_number), Some(best_hash)) => {
+                let canon_hash = self.block_hash(best_number)?;
+                if canon_hash.as_ref() != Some(&best_hash) {
+                    report
+                        .inconsistencies
+                        .push(Inconsistency::BestBlockMismatch {
+                            number: best_number,
+                            best_hash: best_hash,
+                            canon_hash: canon_hash,
+                        });
+                }
+                best_number
+            }
+            (None, None) => return Ok(report),
+            _ => {
+                return Err(Error::DatabaseError(
+                    "best block is partially stored".into(),
+                ))
+            }
+        };
+
+        let mut previous_hash = None;
+        for number in 0..best_number + 1 {
+            let hash = match self.block_hash(number)? {
+                Some(hash) => hash,
+                None => {
+                    if !is_pruned(number) {
+                        report
+                            .inconsistencies
+                            .push(Inconsistency::MissingCanonHash(number));
+                    }
+                    previous_hash = None;
+                    continue;
+                }
+            };
+
+            let indexed = self.block_number(&hash)?;
+            if indexed != Some(number) {
+                report.inconsistencies.push(Inconsistency::BadBlockNumber {
+                    number: number,
+                    hash: hash.clone(),
+                    indexed: indexed,
+                });
+            }
+
+            match self
+                .get(Key::Block(hash.clone()))?
+                .and_then(Value::as_block)
+            {
+                Some(block) => {
+                    let parent = block.block_header.previous_header_hash;
+                    match previous_hash {
+                        Some(ref previous_hash) if *previous_hash != parent => {
+                            report.inconsistencies.push(Inconsistency::BadParent {
+                                number: number,
+                                hash: hash.clone(),
+                                parent: parent,
+                            })
+                        }
+                        _ => (),
+                    }
+                }
+                None if is_pruned(number) => (),
+                None => report.inconsistencies.push(Inconsistency::MissingHeader {
+                    number: number,
+                    hash: hash.clone(),
+                }),
+            }
+
+            report.checked_blocks += 1;
+            previous_hash = Some(hash);
+        }
+
+        for (key, value) in self.db.iter(Location::Column(COL_BLOCK_NUMBERS)) {
+            let hash: H256 = deserialize(&*key).map_err(|err| corrupted(&err))?;
+            let number: u32 = deserialize(&*value).map_err(|err| corrupted(&err))?;
+            match self.block_hash(number)? {
+                Some(ref canon_hash) if *canon_hash != hash => {
+                    report
+                        .inconsistencies
+                        .push(Inconsistency::StaleBlockNumber {
+                            number: number,
+                            hash: hash,
+                        })
+                }
+                _ => (),
+            }
+        }
+
+        for (key, value) in self.db.iter(Location::Column(COL_BLOCK_BODIES)) {
+            let hash: H256 = deserialize(&*key).map_err(|err| corrupted(&err))?;
+            let body = Value::for_key(&Key::BlockBody(hash.clone()), &value)
+                .map_err(Error::DatabaseError)?
+                .as_block_body()
+                .expect("value is read for the body key; qed");
+            let block = match self
+                .get(Key::Block(hash.clone()))?
+                .and_then(Value::as_block)
+            {
+                Some(block) => block,
+                None => {
+                    report.inconsistencies.push(Inconsistency::OrphanBody(hash));
+                    continue;
+                }
+            };
+            match self.block_number(&hash)? {
+                Some(number) if number != 0 && is_pruned(number) => {
+                    report.inconsistencies.push(Inconsistency::PrunedBody {
+                        number: number,
+                        hash: hash,
+                    });
+                    continue;
+                }
+                _ => (),
+            }
+            if !block.proof.is_empty() {
+                report
+                    .inconsistencies
+                    .push(Inconsistency::DuplicateBody(hash));
+            } else if let Err(error) = self.compression.decompress_raw(&body) {
+                report.inconsistencies.push(Inconsistency::UnreadableBody {
+                    hash: hash,
+                    error: error,
+                });
+            }
+        }
+
+        if repair {
+            report.repaired = self.repair(&report.inconsistencies)?;
+        }
+        Ok(report)
+    }
+
+    /// Fixes repairable inconsistencies. Returns number of fixed inconsistencies
+    fn repair(&self, inconsistencies: &[Inconsistency]) -> Result<usize, Error> {
+        let mut update = Transaction::new();
+        let mut stats = self
+            .meta_bytes(KEY_DB_STATS)?
+            .map(|stats| deserialize_stats(&stats))
+            .unwrap_or_else(|| Ok(Default::default()))
+            .map_err(Error::DatabaseError)?;
+        let mut repaired = 0;
+        for inconsistency in inconsistencies {
+            match *inconsistency {
+                Inconsistency::BadBlockNumber {
+                    number, ref hash, ..
+                } => update.insert(KeyValue::BlockNumber(hash.clone(), number)),
+                Inconsistency::StaleBlockNumber { ref hash, .. } => {
+                    update.delete(Key::BlockNumber(hash.clone()))
+                }
+                Inconsistency::OrphanBody(ref hash)
+                | Inconsistency::DuplicateBody(ref hash)
+                | Inconsistency::PrunedBody { ref hash, .. } => {
+                    if let Some(body) = self
+                        .get(Key::BlockBody(hash.clone()))?
+                        .and_then(Value::as_block_body)
+                    {
+                        let raw_size = self
+                            .compression
+                            .decompress_raw(&body)
+                            .map(|raw| raw.len() as u64)
+                            .unwrap_or(0);
+                        stats.compressed_blocks = stats.compressed_blocks.saturating_sub(1);
+                        stats.compressed_bodies_raw_size =
+                            stats.compressed_bodies_raw_size.saturating_sub(raw_size);
+                        stats.compressed_bodies_size = stats
+                            .compressed_bodies_size
+                            .saturating_sub(body.len() as u64);
+                    }
+                    update.delete(Key::BlockBody(hash.clone()));
+                }
+                _ => continue,
+            }
+            repaired += 1;
+        }
+
+        if repaired != 0 {
+            update.insert(KeyValue::Meta(KEY_DB_STATS, serialize_stats(&stats)));
+            self.db.write(update).map_err(Error::DatabaseError)?;
+        }
+        Ok(repaired)
+    }
+
+    fn get(&self, key: Key) -> Result<Option<Value>, Error> {
+        self.db
+            .get(&key)
+            .map(|state| state.into_option())
+            .map_err(Error::DatabaseError)
+    }
+
+    fn meta_bytes(&self, key: &'static str) -> Result<Option<Bytes>, Error> {
+        Ok(self.get(Key::Meta(key))?.and_then(Value::as_meta))
+    }
+
+    fn meta<T: Deserializable>(&self, key: &'static str) -> Result<Option<T>, Error> {
+        match self.meta_bytes(key)? {
+            Some(bytes) => deserialize(&*bytes)
+                .map(Some)
+                .map_err(|err| corrupted(&err)),
+            None => Ok(None),
+        }
+    }
+
+    fn block_hash(&self, number: u32) -> Result<Option<H256>, Error> {
+        Ok(self
+            .get(Key::BlockHash(number))?
+            .and_then(Value::as_block_hash))
+    }
+
+    fn block_number(&self, hash: &H256) -> Result<Option<u32>, Error> {
+        Ok(self
+            .get(Key::BlockNumber(hash.clone()))?
+            .and_then(Value::as_block_number))
+    }
+}
+
+fn corrupted<E: fmt::Debug>(err: &E) -> Error {
+    Error::DatabaseError(format!("Corrupted database value: {:?}", err))
+}
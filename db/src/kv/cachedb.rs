@@ -1,8 +1,11 @@
+use bytes::Bytes;
 use chain::Block;
 use hash::H256;
 use kv::{Key, KeyState, KeyValue, KeyValueDatabase, Operation, Transaction, Value};
 use lru_cache::LruCache;
 use parking_lot::Mutex;
+use std::ops::Range;
+use storage::DbSize;
 
 pub struct CacheDatabase<T>
 where
@@ -56,4 +59,21 @@ where
         }
         self.db.get(key)
     }
+
+    fn block_links(&self) -> Result<Vec<(H256, H256)>, String> {
+        // cache is written through, so the database has all cached blocks
+        self.db.block_links()
+    }
+
+    fn miner_blocks(&self, miner: &Bytes, range: Range<u32>) -> Result<Vec<(u32, H256)>, String> {
+        self.db.miner_blocks(miner, range)
+    }
+
+    fn size(&self) -> Result<DbSize, String> {
+        self.db.size()
+    }
+
+    fn compact(&self) -> Result<(), String> {
+        self.db.compact()
+    }
 }
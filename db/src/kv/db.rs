@@ -1,7 +1,29 @@
+use bytes::Bytes;
+use hash::H256;
 use kv::{Key, KeyState, Transaction, Value};
+use std::ops::Range;
+use storage::DbSize;
 
 pub trait KeyValueDatabase: Send + Sync {
     fn write(&self, tx: Transaction) -> Result<(), String>;
 
     fn get(&self, key: &Key) -> Result<KeyState<Value>, String>;
+
+    /// Hashes of all stored blocks with hashes of their parents, in no particular order.
+    /// Every stored block is read, so it could take a while on large databases
+    fn block_links(&self) -> Result<Vec<(H256, H256)>, String>;
+
+    /// Numbers and hashes of blocks of the miner index entries in the range, ordered by number
+    fn miner_blocks(&self, miner: &Bytes, range: Range<u32>) -> Result<Vec<(u32, H256)>, String>;
+
+    /// Sizes of the database files and columns. Only data, which has been flushed to disk, is
+    /// accounted. In-memory databases report no sizes
+    fn size(&self) -> Result<DbSize, String> {
+        Ok(DbSize::default())
+    }
+
+    /// Compacts the database files. Does nothing for in-memory databases
+    fn compact(&self) -> Result<(), String> {
+        Ok(())
+    }
 }
@@ -1,19 +1,28 @@
 //! Key-Value store abstraction with `RocksDB` backend.
 
 use bytes::Bytes;
+use chain::Block;
+use hash::H256;
 use kv::{
-    Key, KeyState, KeyValueDatabase, Location, RawKey, RawKeyValue, RawOperation, RawTransaction,
-    Transaction, Value,
+    column_name, Key, KeyState, KeyValueDatabase, Location, MinerBlockKey, RawKey, RawKeyValue,
+    RawOperation, RawTransaction, Transaction, Value, COL_BLOCKS, COL_MINER_BLOCKS,
 };
 use rocksdb::{
-    BlockBasedOptions, Cache, Column, DBCompactionStyle, DBIterator, IteratorMode, Options,
-    ReadOptions, Writable, WriteBatch, WriteOptions, DB,
+    BlockBasedOptions, Cache, Column, DBCompactionStyle, DBIterator, Direction, IteratorMode,
+    Options, ReadOptions, Writable, WriteBatch, WriteOptions, DB,
 };
+use ser::deserialize;
 use std::collections::HashMap;
-use std::path::Path;
+use std::fs;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use storage::{ColumnSize, DbSize};
 
 const DB_BACKGROUND_FLUSHES: i32 = 2;
 const DB_BACKGROUND_COMPACTIONS: i32 = 2;
+/// Upper bound of the manually compacted keys range. It is greater than any key,
+/// stored in the database.
+const COMPACTION_RANGE_END: [u8; 64] = [0xff; 64];
 
 /// Compaction profile for the database settings
 #[derive(Clone, Copy, PartialEq, Debug)]
@@ -116,6 +125,7 @@ struct DBAndColumns {
 /// Key-Value database.
 pub struct Database {
     db: DBAndColumns,
+    path: PathBuf,
     write_opts: WriteOptions,
     read_opts: ReadOptions,
 }
@@ -131,6 +141,53 @@ impl KeyValueDatabase for Database {
             None => Ok(KeyState::Unknown),
         }
     }
+
+    fn block_links(&self) -> Result<Vec<(H256, H256)>, String> {
+        self.iter(Location::Column(COL_BLOCKS))
+            .map(|(key, value)| {
+                let hash: H256 =
+                    deserialize(&*key).map_err(|err| format!("Invalid block key: {:?}", err))?;
+                let block: Block = deserialize(&*value)
+                    .map_err(|err| format!("Invalid block {}: {:?}", hash.reversed(), err))?;
+                Ok((hash, block.block_header.previous_header_hash))
+            })
+            .collect()
+    }
+
+    fn miner_blocks(&self, miner: &Bytes, range: Range<u32>) -> Result<Vec<(u32, H256)>, String> {
+        let prefix = MinerBlockKey::raw_prefix(miner);
+        let from = MinerBlockKey {
+            miner: miner.clone(),
+            number: range.start,
+        };
+        let mut blocks = Vec::new();
+        for (key, value) in self.iter_from(Location::Column(COL_MINER_BLOCKS), &from.to_raw()) {
+            // entries of the miner are adjacent, so the scan stops at the entry of another miner
+            if !key.starts_with(&prefix) {
+                break;
+            }
+            let number = match MinerBlockKey::number_from_raw(&prefix, &key) {
+                Some(number) => number,
+                None => continue,
+            };
+            if number >= range.end {
+                break;
+            }
+            let hash = deserialize(&*value)
+                .map_err(|err| format!("Invalid miner block {}: {:?}", number, err))?;
+            blocks.push((number, hash));
+        }
+        Ok(blocks)
+    }
+
+    fn size(&self) -> Result<DbSize, String> {
+        Database::size(self)
+    }
+
+    fn compact(&self) -> Result<(), String> {
+        Database::compact(self);
+        Ok(())
+    }
 }
 
 impl Database {
@@ -147,6 +204,7 @@ impl Database {
     where
         P: AsRef<Path>,
     {
+        let db_path = path.as_ref().to_path_buf();
         let path = path.as_ref().to_string_lossy();
         // default cache size for columns not specified.
         const DEFAULT_CACHE: usize = 2;
@@ -257,6 +315,7 @@ impl Database {
         };
         Ok(Database {
             db: DBAndColumns { db: db, cfs: cfs },
+            path: db_path,
             write_opts: write_opts,
             read_opts: read_opts,
         })
@@ -303,6 +362,48 @@ impl Database {
     /// Close the database
     pub fn close(self) {}
 
+    /// Sizes of the database files and columns. Columns are scanned, so it could take a while
+    pub fn size(&self) -> Result<DbSize, String> {
+        let mut disk_size = 0;
+        for entry in fs::read_dir(&self.path).map_err(|err| err.to_string())? {
+            let metadata = entry
+                .and_then(|entry| entry.metadata())
+                .map_err(|err| err.to_string())?;
+            if metadata.is_file() {
+                disk_size += metadata.len();
+            }
+        }
+
+        let columns = (0..self.db.cfs.len() as u32)
+            .map(|column| {
+                let mut size = ColumnSize {
+                    name: column_name(column),
+                    ..Default::default()
+                };
+                for (key, value) in self.iter(Location::Column(column)) {
+                    size.keys += 1;
+                    size.size += (key.len() + value.len()) as u64;
+                }
+                size
+            })
+            .collect();
+
+        Ok(DbSize {
+            disk_size: Some(disk_size),
+            columns: columns,
+        })
+    }
+
+    /// Compacts all keys of all columns. Blocks until compaction is finished, but the database
+    /// could be used from other threads meanwhile
+    pub fn compact(&self) {
+        let DBAndColumns { ref db, ref cfs } = self.db;
+        db.compact_range(&[], &COMPACTION_RANGE_END);
+        for cf in cfs {
+            db.compact_range_cf(*cf, &[], &COMPACTION_RANGE_END);
+        }
+    }
+
     pub fn iter(&self, location: Location) -> DatabaseIterator {
         let DBAndColumns { ref db, ref cfs } = self.db;
         match location {
@@ -316,6 +417,22 @@ impl Database {
             },
         }
     }
+
+    /// Iterates keys, starting with the first key, which is not less than given key
+    pub fn iter_from(&self, location: Location, key: &[u8]) -> DatabaseIterator {
+        let DBAndColumns { ref db, ref cfs } = self.db;
+        let mode = IteratorMode::From(key, Direction::Forward);
+        match location {
+            Location::DB => DatabaseIterator {
+                iter: db.iterator_opt(mode, &self.read_opts),
+            },
+            Location::Column(column) => DatabaseIterator {
+                iter: db
+                    .iterator_cf_opt(cfs[column as usize], mode, &self.read_opts)
+                    .expect("iterator params are valid; qed"),
+            },
+        }
+    }
 }
 
 #[cfg(test)]
@@ -324,7 +441,7 @@ mod tests {
 
     use self::tempdir::TempDir;
     use super::*;
-    use kv::{Location, RawTransaction};
+    use kv::{KeyValue, Location, RawTransaction, COL_COUNT};
 
     fn test_db(config: DatabaseConfig) {
         let tempdir = TempDir::new("").unwrap();
@@ -384,4 +501,41 @@ mod tests {
         let _ = Database::open_default(tempdir.path()).unwrap();
         test_db(DatabaseConfig::default());
     }
+
+    #[test]
+    fn miner_blocks_are_scanned_by_range() {
+        let tempdir = TempDir::new("").unwrap();
+        let db = Database::open(
+            DatabaseConfig::with_columns(Some(COL_COUNT)),
+            tempdir.path(),
+        )
+        .unwrap();
+        let miner1: Bytes = vec![1u8; 4].into();
+        let miner2: Bytes = vec![2u8; 4].into();
+
+        let mut transaction = Transaction::new();
+        for &(ref miner, number) in &[(&miner1, 256), (&miner1, 1), (&miner2, 2), (&miner1, 3)] {
+            transaction.insert(KeyValue::MinerBlock(
+                MinerBlockKey {
+                    miner: (*miner).clone(),
+                    number: number,
+                },
+                H256::from(number as u8),
+            ));
+        }
+        KeyValueDatabase::write(&db, transaction).unwrap();
+
+        assert_eq!(
+            db.miner_blocks(&miner1, 0..1000).unwrap(),
+            vec![(1, H256::from(1)), (3, H256::from(3)), (256, H256::from(0))]
+        );
+        assert_eq!(
+            db.miner_blocks(&miner1, 2..256).unwrap(),
+            vec![(3, H256::from(3))]
+        );
+        assert_eq!(
+            db.miner_blocks(&miner2, 0..1000).unwrap(),
+            vec![(2, H256::from(2))]
+        );
+    }
 }
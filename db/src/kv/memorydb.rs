@@ -1,10 +1,13 @@
 use bytes::Bytes;
 use chain::Block;
 use hash::H256;
-use kv::{Key, KeyState, KeyValue, KeyValueDatabase, Operation, Transaction, Value};
+use kv::{Key, KeyState, KeyValue, KeyValueDatabase, MinerBlockKey, Operation, Transaction, Value};
 use parking_lot::RwLock;
+use primitives::bigint::U256;
+use ser::List;
 use std::collections::HashMap;
 use std::mem::replace;
+use std::ops::Range;
 use std::sync::Arc;
 
 #[derive(Default, Debug)]
@@ -14,6 +17,11 @@ struct InnerDatabase {
     block: HashMap<H256, KeyState<Block>>,
     block_number: HashMap<H256, KeyState<u32>>,
     configuration: HashMap<&'static str, KeyState<Bytes>>,
+    block_children: HashMap<H256, KeyState<List<H256>>>,
+    block_body: HashMap<H256, KeyState<Bytes>>,
+    chain_event: HashMap<u64, KeyState<Bytes>>,
+    chain_work: HashMap<H256, KeyState<U256>>,
+    miner_blocks: HashMap<MinerBlockKey, KeyState<H256>>,
 }
 
 #[derive(Default, Debug)]
@@ -50,17 +58,80 @@ impl MemoryDatabase {
                 state.into_operation(key, KeyValue::Configuration, Key::Configuration)
             });
 
+        let block_children = replace(&mut db.block_children, HashMap::default())
+            .into_iter()
+            .flat_map(|(key, state)| {
+                state.into_operation(key, KeyValue::BlockChildren, Key::BlockChildren)
+            });
+
+        let block_body = replace(&mut db.block_body, HashMap::default())
+            .into_iter()
+            .flat_map(|(key, state)| {
+                state.into_operation(key, KeyValue::BlockBody, Key::BlockBody)
+            });
+
+        let chain_event = replace(&mut db.chain_event, HashMap::default())
+            .into_iter()
+            .flat_map(|(key, state)| {
+                state.into_operation(key, KeyValue::ChainEvent, Key::ChainEvent)
+            });
+
+        let chain_work = replace(&mut db.chain_work, HashMap::default())
+            .into_iter()
+            .flat_map(|(key, state)| {
+                state.into_operation(key, KeyValue::ChainWork, Key::ChainWork)
+            });
+
+        let miner_blocks = replace(&mut db.miner_blocks, HashMap::default())
+            .into_iter()
+            .flat_map(|(key, state)| {
+                state.into_operation(key, KeyValue::MinerBlock, Key::MinerBlock)
+            });
+
         Transaction {
             operations: meta
                 .chain(block_hash)
                 .chain(block)
                 .chain(block_number)
                 .chain(configuration)
+                .chain(block_children)
+                .chain(block_body)
+                .chain(chain_event)
+                .chain(chain_work)
+                .chain(miner_blocks)
                 .collect(),
         }
     }
 }
 
+impl MemoryDatabase {
+    /// Hashes of blocks, which have been deleted
+    pub fn deleted_blocks(&self) -> Vec<H256> {
+        self.db
+            .read()
+            .block
+            .iter()
+            .filter_map(|(hash, state)| match *state {
+                KeyState::Delete => Some(hash.clone()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Numbers of the miner index entries of the miner, which have been deleted
+    pub fn deleted_miner_blocks(&self, miner: &Bytes) -> Vec<u32> {
+        self.db
+            .read()
+            .miner_blocks
+            .iter()
+            .filter_map(|(key, state)| match *state {
+                KeyState::Delete if key.miner == *miner => Some(key.number),
+                _ => None,
+            })
+            .collect()
+    }
+}
+
 impl KeyValueDatabase for MemoryDatabase {
     fn write(&self, tx: Transaction) -> Result<(), String> {
         let mut db = self.db.write();
@@ -82,6 +153,21 @@ impl KeyValueDatabase for MemoryDatabase {
                     KeyValue::Configuration(key, value) => {
                         db.configuration.insert(key, KeyState::Insert(value));
                     }
+                    KeyValue::BlockChildren(key, value) => {
+                        db.block_children.insert(key, KeyState::Insert(value));
+                    }
+                    KeyValue::BlockBody(key, value) => {
+                        db.block_body.insert(key, KeyState::Insert(value));
+                    }
+                    KeyValue::ChainEvent(key, value) => {
+                        db.chain_event.insert(key, KeyState::Insert(value));
+                    }
+                    KeyValue::ChainWork(key, value) => {
+                        db.chain_work.insert(key, KeyState::Insert(value));
+                    }
+                    KeyValue::MinerBlock(key, value) => {
+                        db.miner_blocks.insert(key, KeyState::Insert(value));
+                    }
                 },
                 Operation::Delete(delete) => match delete {
                     Key::Meta(key) => {
@@ -99,6 +185,21 @@ impl KeyValueDatabase for MemoryDatabase {
                     Key::Configuration(key) => {
                         db.configuration.insert(key, KeyState::Delete);
                     }
+                    Key::BlockChildren(key) => {
+                        db.block_children.insert(key, KeyState::Delete);
+                    }
+                    Key::BlockBody(key) => {
+                        db.block_body.insert(key, KeyState::Delete);
+                    }
+                    Key::ChainEvent(key) => {
+                        db.chain_event.insert(key, KeyState::Delete);
+                    }
+                    Key::ChainWork(key) => {
+                        db.chain_work.insert(key, KeyState::Delete);
+                    }
+                    Key::MinerBlock(key) => {
+                        db.miner_blocks.insert(key, KeyState::Delete);
+                    }
                 },
             }
         }
@@ -138,10 +239,72 @@ impl KeyValueDatabase for MemoryDatabase {
                 .cloned()
                 .unwrap_or_default()
                 .map(Value::Configuration),
+            Key::BlockChildren(ref key) => db
+                .block_children
+                .get(key)
+                .cloned()
+                .unwrap_or_default()
+                .map(Value::BlockChildren),
+            Key::BlockBody(ref key) => db
+                .block_body
+                .get(key)
+                .cloned()
+                .unwrap_or_default()
+                .map(Value::BlockBody),
+            Key::ChainEvent(ref key) => db
+                .chain_event
+                .get(key)
+                .cloned()
+                .unwrap_or_default()
+                .map(Value::ChainEvent),
+            Key::ChainWork(ref key) => db
+                .chain_work
+                .get(key)
+                .cloned()
+                .unwrap_or_default()
+                .map(Value::ChainWork),
+            Key::MinerBlock(ref key) => db
+                .miner_blocks
+                .get(key)
+                .cloned()
+                .unwrap_or_default()
+                .map(Value::MinerBlock),
         };
 
         Ok(result)
     }
+
+    fn block_links(&self) -> Result<Vec<(H256, H256)>, String> {
+        Ok(self
+            .db
+            .read()
+            .block
+            .iter()
+            .filter_map(|(hash, state)| match *state {
+                KeyState::Insert(ref block) => Some((
+                    hash.clone(),
+                    block.block_header.previous_header_hash.clone(),
+                )),
+                _ => None,
+            })
+            .collect())
+    }
+
+    fn miner_blocks(&self, miner: &Bytes, range: Range<u32>) -> Result<Vec<(u32, H256)>, String> {
+        let mut blocks: Vec<(u32, H256)> = self
+            .db
+            .read()
+            .miner_blocks
+            .iter()
+            .filter(|&(key, _)| key.miner == *miner && range.contains(&key.number))
+            .filter_map(|(key, state)| match *state {
+                KeyState::Insert(ref hash) => Some((key.number, hash.clone())),
+                _ => None,
+            })
+            .collect();
+        blocks.sort_by_key(|&(number, _)| number);
+        Ok(blocks)
+    }
 }
 
 #[derive(Debug)]
@@ -171,4 +334,12 @@ impl KeyValueDatabase for SharedMemoryDatabase {
     fn get(&self, key: &Key) -> Result<KeyState<Value>, String> {
         self.db.get(key)
     }
+
+    fn block_links(&self) -> Result<Vec<(H256, H256)>, String> {
+        self.db.block_links()
+    }
+
+    fn miner_blocks(&self, miner: &Bytes, range: Range<u32>) -> Result<Vec<(u32, H256)>, String> {
+        self.db.miner_blocks(miner, range)
+    }
 }
@@ -11,7 +11,8 @@ pub use self::diskdb::{CompactionProfile, Database as DiskDatabase, DatabaseConf
 pub use self::memorydb::{MemoryDatabase, SharedMemoryDatabase};
 pub use self::overlaydb::{AutoFlushingOverlayDatabase, OverlayDatabase};
 pub use self::transaction::{
-    Key, KeyState, KeyValue, Location, Operation, RawKey, RawKeyValue, RawOperation,
-    RawTransaction, Transaction, Value, COL_BLOCKS, COL_BLOCK_HASHES, COL_BLOCK_NUMBERS, COL_COUNT,
-    COL_META,
+    column_name, Key, KeyState, KeyValue, Location, MinerBlockKey, Operation, RawKey, RawKeyValue,
+    RawOperation, RawTransaction, Transaction, Value, COL_BLOCKS, COL_BLOCK_BODIES,
+    COL_BLOCK_CHILDREN, COL_BLOCK_HASHES, COL_BLOCK_NUMBERS, COL_CHAIN_EVENTS, COL_CHAIN_WORK,
+    COL_COUNT, COL_META, COL_MINER_BLOCKS,
 };
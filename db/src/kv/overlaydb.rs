@@ -1,5 +1,41 @@
+use bytes::Bytes;
+use hash::H256;
 use kv::{Key, KeyState, KeyValueDatabase, MemoryDatabase, Transaction, Value};
 use parking_lot::Mutex;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::ops::Range;
+use storage::DbSize;
+
+/// Applies blocks, inserted and deleted in the overlay, to the blocks of the underlying database
+fn merge_block_links(
+    overlay: &MemoryDatabase,
+    links: Vec<(H256, H256)>,
+) -> Result<Vec<(H256, H256)>, String> {
+    let deleted: HashSet<H256> = overlay.deleted_blocks().into_iter().collect();
+    let mut links: HashMap<H256, H256> = links
+        .into_iter()
+        .filter(|&(ref hash, _)| !deleted.contains(hash))
+        .collect();
+    links.extend(overlay.block_links()?);
+    Ok(links.into_iter().collect())
+}
+
+/// Applies miner index entries, inserted and deleted in the overlay, to the entries of the
+/// underlying database
+fn merge_miner_blocks(
+    overlay: &MemoryDatabase,
+    miner: &Bytes,
+    range: Range<u32>,
+    blocks: Vec<(u32, H256)>,
+) -> Result<Vec<(u32, H256)>, String> {
+    let deleted: HashSet<u32> = overlay.deleted_miner_blocks(miner).into_iter().collect();
+    let mut blocks: BTreeMap<u32, H256> = blocks
+        .into_iter()
+        .filter(|&(number, _)| !deleted.contains(&number))
+        .collect();
+    blocks.extend(overlay.miner_blocks(miner, range)?);
+    Ok(blocks.into_iter().collect())
+}
 
 pub struct OverlayDatabase<'a, T>
 where
@@ -39,6 +75,23 @@ where
             exists => Ok(exists),
         }
     }
+
+    fn block_links(&self) -> Result<Vec<(H256, H256)>, String> {
+        merge_block_links(&self.overlay, self.db.block_links()?)
+    }
+
+    fn miner_blocks(&self, miner: &Bytes, range: Range<u32>) -> Result<Vec<(u32, H256)>, String> {
+        let blocks = self.db.miner_blocks(miner, range.clone())?;
+        merge_miner_blocks(&self.overlay, miner, range, blocks)
+    }
+
+    fn size(&self) -> Result<DbSize, String> {
+        self.db.size()
+    }
+
+    fn compact(&self) -> Result<(), String> {
+        self.db.compact()
+    }
 }
 
 pub struct AutoFlushingOverlayDatabase<T>
@@ -90,6 +143,23 @@ where
             exists => Ok(exists),
         }
     }
+
+    fn block_links(&self) -> Result<Vec<(H256, H256)>, String> {
+        merge_block_links(&self.overlay, self.db.block_links()?)
+    }
+
+    fn miner_blocks(&self, miner: &Bytes, range: Range<u32>) -> Result<Vec<(u32, H256)>, String> {
+        let blocks = self.db.miner_blocks(miner, range.clone())?;
+        merge_miner_blocks(&self.overlay, miner, range, blocks)
+    }
+
+    fn size(&self) -> Result<DbSize, String> {
+        self.db.size()
+    }
+
+    fn compact(&self) -> Result<(), String> {
+        self.db.compact()
+    }
 }
 
 impl<T> Drop for AutoFlushingOverlayDatabase<T>
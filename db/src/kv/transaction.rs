@@ -1,7 +1,8 @@
 use bytes::Bytes;
 use chain::Block;
 use hash::H256;
-use ser::{deserialize, serialize};
+use primitives::bigint::U256;
+use ser::{deserialize, serialize, List};
 
 pub const COL_COUNT: u32 = 10;
 pub const COL_META: u32 = 0;
@@ -9,6 +10,28 @@ pub const COL_BLOCK_HASHES: u32 = 1;
 pub const COL_BLOCKS: u32 = 2;
 pub const COL_BLOCK_NUMBERS: u32 = 3;
 pub const COL_CONFIGURATION: u32 = 4;
+pub const COL_BLOCK_CHILDREN: u32 = 5;
+pub const COL_BLOCK_BODIES: u32 = 6;
+pub const COL_CHAIN_EVENTS: u32 = 7;
+pub const COL_CHAIN_WORK: u32 = 8;
+pub const COL_MINER_BLOCKS: u32 = 9;
+
+/// Human-readable name of the column
+pub fn column_name(column: u32) -> String {
+    match column {
+        COL_META => "meta".into(),
+        COL_BLOCK_HASHES => "block_hashes".into(),
+        COL_BLOCKS => "blocks".into(),
+        COL_BLOCK_NUMBERS => "block_numbers".into(),
+        COL_CONFIGURATION => "configuration".into(),
+        COL_BLOCK_CHILDREN => "block_children".into(),
+        COL_BLOCK_BODIES => "block_bodies".into(),
+        COL_CHAIN_EVENTS => "chain_events".into(),
+        COL_CHAIN_WORK => "chain_work".into(),
+        COL_MINER_BLOCKS => "miner_blocks".into(),
+        _ => format!("col{}", column),
+    }
+}
 
 #[derive(Debug)]
 pub enum Operation {
@@ -24,6 +47,11 @@ pub enum KeyValue {
     Block(H256, Block),
     BlockNumber(H256, u32),
     Configuration(&'static str, Bytes),
+    BlockChildren(H256, List<H256>),
+    BlockBody(H256, Bytes),
+    ChainEvent(u64, Bytes),
+    ChainWork(H256, U256),
+    MinerBlock(MinerBlockKey, H256),
 }
 
 #[derive(Debug)]
@@ -33,6 +61,11 @@ pub enum Key {
     Block(H256),
     BlockNumber(H256),
     Configuration(&'static str),
+    BlockChildren(H256),
+    BlockBody(H256),
+    ChainEvent(u64),
+    ChainWork(H256),
+    MinerBlock(MinerBlockKey),
 }
 
 #[derive(Debug, Clone)]
@@ -42,6 +75,11 @@ pub enum Value {
     Block(Block),
     BlockNumber(u32),
     Configuration(Bytes),
+    BlockChildren(List<H256>),
+    BlockBody(Bytes),
+    ChainEvent(Bytes),
+    ChainWork(U256),
+    MinerBlock(H256),
 }
 
 impl Value {
@@ -52,6 +90,11 @@ impl Value {
             Key::Block(_) => deserialize(bytes).map(Value::Block),
             Key::BlockNumber(_) => deserialize(bytes).map(Value::BlockNumber),
             Key::Configuration(_) => deserialize(bytes).map(Value::Configuration),
+            Key::BlockChildren(_) => deserialize(bytes).map(Value::BlockChildren),
+            Key::BlockBody(_) => deserialize(bytes).map(Value::BlockBody),
+            Key::ChainEvent(_) => deserialize(bytes).map(Value::ChainEvent),
+            Key::ChainWork(_) => deserialize(bytes).map(Value::ChainWork),
+            Key::MinerBlock(_) => deserialize(bytes).map(Value::MinerBlock),
         }
         .map_err(|e| format!("{:?}", e))
     }
@@ -90,6 +133,72 @@ impl Value {
             _ => None,
         }
     }
+
+    pub fn as_block_children(self) -> Option<List<H256>> {
+        match self {
+            Value::BlockChildren(children) => Some(children),
+            _ => None,
+        }
+    }
+
+    pub fn as_block_body(self) -> Option<Bytes> {
+        match self {
+            Value::BlockBody(body) => Some(body),
+            _ => None,
+        }
+    }
+
+    pub fn as_chain_event(self) -> Option<Bytes> {
+        match self {
+            Value::ChainEvent(event) => Some(event),
+            _ => None,
+        }
+    }
+
+    pub fn as_chain_work(self) -> Option<U256> {
+        match self {
+            Value::ChainWork(work) => Some(work),
+            _ => None,
+        }
+    }
+
+    pub fn as_miner_block(self) -> Option<H256> {
+        match self {
+            Value::MinerBlock(hash) => Some(hash),
+            _ => None,
+        }
+    }
+}
+
+/// Key of the miner index entry. Number is appended to the miner in big-endian order,
+/// so entries of the miner are ordered by block number and could be scanned by range
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct MinerBlockKey {
+    pub miner: Bytes,
+    pub number: u32,
+}
+
+impl MinerBlockKey {
+    /// Raw keys of all blocks of the miner start with this prefix
+    pub fn raw_prefix(miner: &Bytes) -> Bytes {
+        serialize(miner)
+    }
+
+    pub fn to_raw(&self) -> Bytes {
+        let mut key: Vec<u8> = Self::raw_prefix(&self.miner).into();
+        key.extend_from_slice(&self.number.to_be_bytes());
+        key.into()
+    }
+
+    /// Returns number of the raw key, if the key belongs to the miner with given prefix
+    pub fn number_from_raw(prefix: &[u8], key: &[u8]) -> Option<u32> {
+        if key.len() != prefix.len() + 4 || !key.starts_with(prefix) {
+            return None;
+        }
+        let mut number = [0u8; 4];
+        number.copy_from_slice(&key[prefix.len()..]);
+        Some(u32::from_be_bytes(number))
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -202,6 +311,21 @@ impl<'a> From<&'a KeyValue> for RawKeyValue {
             KeyValue::Configuration(ref key, ref value) => {
                 (COL_CONFIGURATION, serialize(key), serialize(value))
             }
+            KeyValue::BlockChildren(ref key, ref value) => {
+                (COL_BLOCK_CHILDREN, serialize(key), serialize(value))
+            }
+            KeyValue::BlockBody(ref key, ref value) => {
+                (COL_BLOCK_BODIES, serialize(key), serialize(value))
+            }
+            KeyValue::ChainEvent(ref key, ref value) => {
+                (COL_CHAIN_EVENTS, serialize(key), serialize(value))
+            }
+            KeyValue::ChainWork(ref key, ref value) => {
+                (COL_CHAIN_WORK, serialize(key), serialize(value))
+            }
+            KeyValue::MinerBlock(ref key, ref value) => {
+                (COL_MINER_BLOCKS, key.to_raw(), serialize(value))
+            }
         };
 
         RawKeyValue {
@@ -237,6 +361,11 @@ impl<'a> From<&'a Key> for RawKey {
             Key::Block(ref key) => (COL_BLOCKS, serialize(key)),
             Key::BlockNumber(ref key) => (COL_BLOCK_NUMBERS, serialize(key)),
             Key::Configuration(ref key) => (COL_CONFIGURATION, serialize(key)),
+            Key::BlockChildren(ref key) => (COL_BLOCK_CHILDREN, serialize(key)),
+            Key::BlockBody(ref key) => (COL_BLOCK_BODIES, serialize(key)),
+            Key::ChainEvent(ref key) => (COL_CHAIN_EVENTS, serialize(key)),
+            Key::ChainWork(ref key) => (COL_CHAIN_WORK, serialize(key)),
+            Key::MinerBlock(ref key) => (COL_MINER_BLOCKS, key.to_raw()),
         };
 
         RawKey {
@@ -5,14 +5,35 @@ extern crate rocksdb;
 extern crate log;
 extern crate bit_vec;
 extern crate lru_cache;
+extern crate zstd;
 
+extern crate bitcrypto as crypto;
 extern crate chain;
 extern crate primitives;
 extern crate serialization as ser;
 extern crate storage;
 
+#[cfg(test)]
+extern crate test_data;
+
+mod backend;
+mod backup;
 mod block_chain_db;
+mod block_compression;
+mod chain_events;
+mod consistency;
 pub mod kv;
+mod mmr;
+mod stored_blocks;
+mod verifier_state;
 
+pub use backend::DatabaseBackend;
+pub use backup::BlockFilesBackup;
 pub use block_chain_db::{BlockChainDatabase, ForkChainDatabase};
+pub use consistency::{ConsistencyChecker, ConsistencyReport, Inconsistency};
+pub use mmr::MerkleMountainRange;
 pub use primitives::{bytes, hash};
+pub use stored_blocks::StoredBlocks;
+pub use verifier_state::{
+    deserialize_verifier_state, serialize_verifier_state, verifier_state_mmr,
+};
@@ -0,0 +1,140 @@
+use bytes::Bytes;
+use crypto::dhash256;
+use hash::H256;
+use ser::{Reader, Stream};
+
+/// Merkle mountain range over hashes of canon blocks.
+///
+/// Only peaks of the range are kept, so the range is extended with new blocks
+/// without knowing hashes of the previous blocks.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct MerkleMountainRange {
+    /// Number of appended hashes
+    leaves: u32,
+    /// Roots of the perfect binary trees, from the highest tree to the lowest
+    peaks: Vec<H256>,
+}
+
+impl MerkleMountainRange {
+    /// Restores range from its peaks. There is a peak for every non-zero bit of the leaves number
+    pub fn from_peaks(leaves: u32, peaks: Vec<H256>) -> Option<Self> {
+        if leaves.count_ones() as usize != peaks.len() {
+            return None;
+        }
+
+        Some(MerkleMountainRange {
+            leaves: leaves,
+            peaks: peaks,
+        })
+    }
+
+    /// Number of appended hashes
+    pub fn leaves(&self) -> u32 {
+        self.leaves
+    }
+
+    /// Roots of the perfect binary trees, from the highest tree to the lowest
+    pub fn peaks(&self) -> &[H256] {
+        &self.peaks
+    }
+
+    /// Appends hash to the range, merging trees of the same height
+    pub fn append(&mut self, hash: H256) {
+        let mut node = hash;
+        let mut leaves = self.leaves;
+        while leaves & 1 == 1 {
+            let peak = self
+                .peaks
+                .pop()
+                .expect("there is a peak for every non-zero bit of leaves; qed");
+            node = merge(&peak, &node);
+            leaves >>= 1;
+        }
+        self.peaks.push(node);
+        self.leaves += 1;
+    }
+
+    /// Root of the range, which bags peaks from the lowest to the highest
+    pub fn root(&self) -> Option<H256> {
+        let mut peaks = self.peaks.iter().rev();
+        let mut root = peaks.next()?.clone();
+        for peak in peaks {
+            root = merge(peak, &root);
+        }
+        Some(root)
+    }
+}
+
+/// Serializes merkle mountain range for the meta column.
+pub fn serialize_mmr(mmr: &MerkleMountainRange) -> Bytes {
+    let mut stream = Stream::new();
+    stream
+        .append(&mmr.leaves)
+        .append_list::<H256, H256>(&mmr.peaks);
+    stream.out()
+}
+
+/// Deserializes merkle mountain range.
+pub fn deserialize_mmr(bytes: &[u8]) -> Result<MerkleMountainRange, String> {
+    let mut reader = Reader::new(bytes);
+    let leaves = reader.read().map_err(|err| format!("{:?}", err))?;
+    let peaks = reader.read_list().map_err(|err| format!("{:?}", err))?;
+    MerkleMountainRange::from_peaks(leaves, peaks).ok_or_else(|| "peaks do not match leaves".into())
+}
+
+fn merge(left: &H256, right: &H256) -> H256 {
+    let mut data = [0u8; 64];
+    data[..32].copy_from_slice(&**left);
+    data[32..].copy_from_slice(&**right);
+    dhash256(&data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{deserialize_mmr, merge, serialize_mmr, MerkleMountainRange};
+    use hash::H256;
+
+    #[test]
+    fn mmr_root_bags_peaks() {
+        let hashes: Vec<H256> = (1u8..6).map(H256::from).collect();
+        let mut mmr = MerkleMountainRange::default();
+        assert_eq!(mmr.root(), None);
+
+        for hash in &hashes {
+            mmr.append(hash.clone());
+        }
+        let tree = merge(
+            &merge(&hashes[0], &hashes[1]),
+            &merge(&hashes[2], &hashes[3]),
+        );
+        assert_eq!(mmr.leaves(), 5);
+        assert_eq!(mmr.peaks(), &[tree.clone(), hashes[4].clone()][..]);
+        assert_eq!(mmr.root(), Some(merge(&tree, &hashes[4])));
+    }
+
+    #[test]
+    fn mmr_is_extended_from_peaks() {
+        let hashes: Vec<H256> = (1u8..8).map(H256::from).collect();
+        let mut full = MerkleMountainRange::default();
+        for hash in &hashes {
+            full.append(hash.clone());
+        }
+
+        let mut partial = MerkleMountainRange::default();
+        for hash in &hashes[..3] {
+            partial.append(hash.clone());
+        }
+        assert_eq!(
+            MerkleMountainRange::from_peaks(3, vec![H256::default()]),
+            None
+        );
+        let mut restored = MerkleMountainRange::from_peaks(3, partial.peaks().to_vec())
+            .expect("peaks match leaves");
+        for hash in &hashes[3..] {
+            restored.append(hash.clone());
+        }
+        assert_eq!(restored, full);
+        assert_eq!(restored.peaks().len(), 3);
+        assert_eq!(deserialize_mmr(&serialize_mmr(&restored)), Ok(restored));
+    }
+}
@@ -0,0 +1,130 @@
+use block_compression::BlockCompression;
+use chain::{Block, IndexedBlock, IndexedBlockHeader};
+use hash::H256;
+use kv::{DatabaseConfig, DiskDatabase, Key, KeyValueDatabase, Location, Value};
+use kv::{COL_BLOCKS, COL_COUNT};
+use ser::deserialize;
+use std::collections::{HashMap, VecDeque};
+use std::path::Path;
+use storage::Error;
+
+/// Raw blocks of the database, accessed without using (possibly corrupted) chain indexes.
+pub struct StoredBlocks {
+    db: DiskDatabase,
+    compression: BlockCompression,
+}
+
+impl StoredBlocks {
+    pub fn open_at_path<P>(path: P) -> Result<Self, Error>
+    where
+        P: AsRef<Path>,
+    {
+        let cfg = DatabaseConfig::with_columns(Some(COL_COUNT));
+        match DiskDatabase::open(cfg, path) {
+            Ok(db) => {
+                let dictionary = BlockCompression::read_dictionary(&db);
+                Ok(StoredBlocks {
+                    db: db,
+                    compression: BlockCompression::new(dictionary),
+                })
+            }
+            Err(err) => Err(Error::DatabaseError(err)),
+        }
+    }
+
+    /// Returns hashes of all stored descendants of the `root` block.
+    /// Every block is preceded by its parent. Unreadable blocks and their descendants are skipped.
+    pub fn descendants_of(&self, root: &H256) -> Vec<H256> {
+        let mut links = Vec::new();
+        for (key, value) in self.db.iter(Location::Column(COL_BLOCKS)) {
+            let hash: H256 = match deserialize(&*key) {
+                Ok(hash) => hash,
+                Err(err) => {
+                    warn!(target: "db", "Skipping block with invalid key: {:?}", err);
+                    continue;
+                }
+            };
+            match deserialize::<_, Block>(&*value) {
+                Ok(block) => links.push((hash, block.block_header.previous_header_hash)),
+                Err(err) => {
+                    warn!(target: "db", "Skipping unreadable block {}: {:?}", hash.reversed(), err)
+                }
+            }
+        }
+
+        order_by_parent(root, links)
+    }
+
+    /// Reads block with given hash. Unreadable compressed body is reported as missing block.
+    pub fn block(&self, hash: &H256) -> Option<IndexedBlock> {
+        let mut block = self
+            .db
+            .get(&Key::Block(hash.clone()))
+            .ok()
+            .and_then(|state| state.into_option())
+            .and_then(Value::as_block)?;
+        if block.proof.is_empty() {
+            let body = self
+                .db
+                .get(&Key::BlockBody(hash.clone()))
+                .ok()
+                .and_then(|state| state.into_option())
+                .and_then(Value::as_block_body);
+            if let Some(body) = body {
+                match self.compression.decompress(&body) {
+                    Ok(proof) => block.proof = proof,
+                    Err(err) => {
+                        warn!(target: "db", "Skipping block {} with unreadable body: {}", hash.reversed(), err);
+                        return None;
+                    }
+                }
+            }
+        }
+
+        Some(IndexedBlock::new(
+            IndexedBlockHeader::new(hash.clone(), block.block_header),
+            block.proof,
+        ))
+    }
+}
+
+/// Orders (block, parent) links so that every descendant of the `root` follows its parent.
+/// Blocks, which are not descendants of the `root`, are dropped.
+fn order_by_parent(root: &H256, links: Vec<(H256, H256)>) -> Vec<H256> {
+    let mut children: HashMap<H256, Vec<H256>> = HashMap::new();
+    for (hash, parent) in links {
+        children.entry(parent).or_insert_with(Vec::new).push(hash);
+    }
+
+    let mut ordered = Vec::new();
+    let mut queue = VecDeque::new();
+    queue.push_back(root.clone());
+    while let Some(parent) = queue.pop_front() {
+        for hash in children.remove(&parent).unwrap_or_default() {
+            queue.push_back(hash.clone());
+            ordered.push(hash);
+        }
+    }
+    ordered
+}
+
+#[cfg(test)]
+mod tests {
+    use super::order_by_parent;
+    use hash::H256;
+
+    #[test]
+    fn order_by_parent_puts_parents_first() {
+        let links = vec![
+            (H256::from(3), H256::from(2)),
+            (H256::from(4), H256::from(2)),
+            (H256::from(2), H256::from(1)),
+            (H256::from(6), H256::from(5)),
+            (H256::from(5), H256::from(7)),
+        ];
+        assert_eq!(
+            order_by_parent(&H256::from(1), links),
+            vec![H256::from(2), H256::from(3), H256::from(4)]
+        );
+    }
+}
@@ -0,0 +1,129 @@
+use bytes::Bytes;
+use chain::BlockHeader;
+use hash::H256;
+use mmr::MerkleMountainRange;
+use ser::{CompactInteger, Reader, Stream};
+use storage::{Checkpoint, VerifierState};
+
+/// Version of the serialized verifier state format.
+const VERIFIER_STATE_VERSION: u8 = 3;
+
+/// Returns merkle mountain range over hashes of all canon blocks of the state,
+/// or None if the peaks don't match the tail number.
+pub fn verifier_state_mmr(state: &VerifierState) -> Option<MerkleMountainRange> {
+    let mut mmr = MerkleMountainRange::from_peaks(state.tail_number, state.mmr_peaks.clone())?;
+    for header in &state.tail {
+        mmr.append(header.hash.clone());
+    }
+    Some(mmr)
+}
+
+/// Serializes verifier state for the export.
+pub fn serialize_verifier_state(state: &VerifierState) -> Bytes {
+    let tail: Vec<&BlockHeader> = state.tail.iter().map(|header| &header.raw).collect();
+
+    let mut stream = Stream::new();
+    stream
+        .append(&VERIFIER_STATE_VERSION)
+        .append(&CompactInteger::from(state.checkpoints.len()));
+    for checkpoint in &state.checkpoints {
+        stream.append(&checkpoint.number).append(&checkpoint.hash);
+    }
+    stream
+        .append_list::<H256, H256>(&state.mmr_peaks)
+        .append(&state.tail_number)
+        .append(&state.tail_parent_work)
+        .append_list::<BlockHeader, _>(&tail);
+    stream.out()
+}
+
+/// Deserializes exported verifier state.
+pub fn deserialize_verifier_state(bytes: &[u8]) -> Result<VerifierState, String> {
+    let mut reader = Reader::new(bytes);
+    let version: u8 = reader.read().map_err(|err| format!("{:?}", err))?;
+    if version != VERIFIER_STATE_VERSION {
+        return Err(format!("unsupported verifier state version {}", version));
+    }
+
+    let checkpoints_len: usize = reader
+        .read::<CompactInteger>()
+        .map_err(|err| format!("{:?}", err))?
+        .into();
+    let mut checkpoints = Vec::new();
+    for _ in 0..checkpoints_len {
+        checkpoints.push(Checkpoint {
+            number: reader.read().map_err(|err| format!("{:?}", err))?,
+            hash: reader.read().map_err(|err| format!("{:?}", err))?,
+        });
+    }
+
+    let state = VerifierState {
+        checkpoints: checkpoints,
+        mmr_peaks: reader.read_list().map_err(|err| format!("{:?}", err))?,
+        tail_number: reader.read().map_err(|err| format!("{:?}", err))?,
+        tail_parent_work: reader.read().map_err(|err| format!("{:?}", err))?,
+        tail: reader.read_list().map_err(|err| format!("{:?}", err))?,
+    };
+    if !reader.is_finished() {
+        return Err("unexpected data after verifier state".into());
+    }
+    Ok(state)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{deserialize_verifier_state, serialize_verifier_state, verifier_state_mmr};
+    use chain::IndexedBlockHeader;
+    use mmr::MerkleMountainRange;
+    use primitives::bigint::U256;
+    use storage::{Checkpoint, VerifierState};
+    use test_data;
+
+    #[test]
+    fn verifier_state_is_serialized() {
+        let state = VerifierState {
+            checkpoints: vec![Checkpoint {
+                number: 0,
+                hash: test_data::genesis().hash(),
+            }],
+            mmr_peaks: vec![test_data::genesis().hash()],
+            tail_number: 1,
+            tail_parent_work: U256::from(2),
+            tail: vec![
+                IndexedBlockHeader::from_raw(test_data::block_h1().block_header),
+                IndexedBlockHeader::from_raw(test_data::block_h2().block_header),
+            ],
+        };
+        let bytes = serialize_verifier_state(&state);
+        assert_eq!(deserialize_verifier_state(&bytes), Ok(state));
+        assert!(deserialize_verifier_state(&bytes[..bytes.len() - 1]).is_err());
+
+        let mut bytes = bytes.to_vec();
+        bytes[0] = 1;
+        assert!(deserialize_verifier_state(&bytes).is_err());
+    }
+
+    #[test]
+    fn verifier_state_mmr_commits_to_tail() {
+        let mut state = VerifierState {
+            checkpoints: vec![Checkpoint {
+                number: 0,
+                hash: test_data::genesis().hash(),
+            }],
+            mmr_peaks: vec![test_data::genesis().hash()],
+            tail_number: 1,
+            tail_parent_work: U256::from(2),
+            tail: vec![IndexedBlockHeader::from_raw(
+                test_data::block_h1().block_header,
+            )],
+        };
+
+        let mut expected = MerkleMountainRange::default();
+        expected.append(test_data::genesis().hash());
+        expected.append(test_data::block_h1().hash());
+        assert_eq!(verifier_state_mmr(&state), Some(expected));
+
+        state.tail_number = 2;
+        assert_eq!(verifier_state_mmr(&state), None);
+    }
+}
@@ -1,12 +1,44 @@
 extern crate chain;
 extern crate db;
 extern crate storage;
+extern crate tempdir;
 extern crate test_data;
 
-use chain::IndexedBlock;
-use db::kv::{MemoryDatabase, SharedMemoryDatabase};
-use db::BlockChainDatabase;
-use storage::{BlockProvider, ForkChain, SideChainOrigin};
+use chain::bigint::U256;
+use chain::compact::Compact;
+use chain::{Block, IndexedBlock};
+use db::bytes::Bytes;
+use db::hash::H256;
+use db::kv::{
+    DatabaseConfig, DiskDatabase, Key, KeyValue, KeyValueDatabase, MemoryDatabase, MinerBlockKey,
+    SharedMemoryDatabase, Transaction, COL_COUNT,
+};
+use db::{
+    verifier_state_mmr, BlockChainDatabase, ConsistencyChecker, DatabaseBackend, Inconsistency,
+    MerkleMountainRange, StoredBlocks,
+};
+use std::sync::{Arc, Mutex};
+use storage::{
+    BackupHook, BackupReason, BlockChain, BlockHeaderProvider, BlockOrigin, BlockProvider,
+    ChainEventKind, ChainEventStore, ChainMembership, Checkpoint, Error, ForkChain,
+    MinerIndexStore, Reorg, SideChainOrigin, Store,
+};
+use tempdir::TempDir;
+
+#[derive(Default)]
+struct RecordingBackup {
+    backups: Mutex<Vec<(BackupReason, Vec<H256>)>>,
+}
+
+impl BackupHook for RecordingBackup {
+    fn backup_blocks(&self, reason: BackupReason, blocks: &[Block]) -> Result<(), Error> {
+        self.backups
+            .lock()
+            .unwrap()
+            .push((reason, blocks.iter().map(Block::hash).collect()));
+        Ok(())
+    }
+}
 
 #[test]
 fn insert_block() {
@@ -127,3 +159,890 @@ fn switch_to_simple_fork() {
     assert_eq!(b2.hash(), &store.best_block().hash);
     assert_eq!(store.best_block().hash, store.block_hash(2).unwrap());
 }
+
+#[test]
+fn chain_events_are_journaled() {
+    let store = BlockChainDatabase::open(MemoryDatabase::default());
+    let b0: IndexedBlock = test_data::block_h0().into();
+    let b1: IndexedBlock = test_data::block_h1().into();
+    let b2: IndexedBlock = test_data::block_h2().into();
+
+    store.insert(b0.clone()).unwrap();
+    store.insert(b1.clone()).unwrap();
+    store.insert(b2.clone()).unwrap();
+    store.canonize(b0.hash()).unwrap();
+    store.canonize(b1.hash()).unwrap();
+    store.canonize(b2.hash()).unwrap();
+
+    // fork, which is not switched to, leaves no events
+    let side_chain_origin = SideChainOrigin {
+        ancestor: 1,
+        canonized_route: Vec::new(),
+        decanonized_route: vec![b2.hash().clone()],
+        block_number: 2,
+    };
+    drop(store.fork(side_chain_origin.clone()).unwrap());
+    assert_eq!(store.last_chain_event_seq(), 3);
+
+    let fork = store.fork(side_chain_origin).unwrap();
+    fork.store().canonize(b2.hash()).unwrap();
+    store.switch_to_fork(fork).unwrap();
+
+    let events = store.chain_events(0, 100);
+    assert_eq!(
+        events
+            .iter()
+            .map(|event| (event.seq, event.kind, event.hash.clone(), event.number))
+            .collect::<Vec<_>>(),
+        vec![
+            (1, ChainEventKind::BlockConnected, b0.hash().clone(), 0),
+            (2, ChainEventKind::BlockConnected, b1.hash().clone(), 1),
+            (3, ChainEventKind::BlockConnected, b2.hash().clone(), 2),
+            (4, ChainEventKind::ReorgStarted, b1.hash().clone(), 1),
+            (5, ChainEventKind::BlockDisconnected, b2.hash().clone(), 2),
+            (6, ChainEventKind::BlockConnected, b2.hash().clone(), 2),
+            (7, ChainEventKind::ReorgFinished, b2.hash().clone(), 2),
+        ]
+    );
+    assert_eq!(store.last_chain_event_seq(), 7);
+    assert_eq!(store.chain_events(5, 1), vec![events[5].clone()]);
+    assert!(store.chain_events(7, 100).is_empty());
+}
+
+#[test]
+fn deep_reorgs_and_pruning_are_backed_up() {
+    let backup = Arc::new(RecordingBackup::default());
+    let store =
+        BlockChainDatabase::open(MemoryDatabase::default()).with_backup_hook(backup.clone(), 2);
+    let b0: IndexedBlock = test_data::block_h0().into();
+    let b1: IndexedBlock = test_data::block_h1().into();
+    let b2: IndexedBlock = test_data::block_h2().into();
+
+    store.insert(b0.clone()).unwrap();
+    store.insert(b1.clone()).unwrap();
+    store.insert(b2.clone()).unwrap();
+    store.canonize(b0.hash()).unwrap();
+    store.canonize(b1.hash()).unwrap();
+    store.canonize(b2.hash()).unwrap();
+    assert_eq!(store.last_reorg(), None);
+
+    // reorg, which is not deep enough, is only recorded
+    let fork = store
+        .fork(SideChainOrigin {
+            ancestor: 1,
+            canonized_route: Vec::new(),
+            decanonized_route: vec![b2.hash().clone()],
+            block_number: 2,
+        })
+        .unwrap();
+    fork.store().canonize(b2.hash()).unwrap();
+    store.switch_to_fork(fork).unwrap();
+    assert_eq!(
+        store.last_reorg(),
+        Some(Reorg {
+            ancestor: 1,
+            disconnected: vec![b2.hash().clone()],
+            connected: vec![b2.hash().clone()],
+        })
+    );
+    assert!(backup.backups.lock().unwrap().is_empty());
+
+    let fork = store
+        .fork(SideChainOrigin {
+            ancestor: 0,
+            canonized_route: vec![b1.hash().clone()],
+            decanonized_route: vec![b1.hash().clone(), b2.hash().clone()],
+            block_number: 2,
+        })
+        .unwrap();
+    fork.store().canonize(b2.hash()).unwrap();
+    store.switch_to_fork(fork).unwrap();
+    assert_eq!(store.last_reorg().unwrap().ancestor, 0);
+
+    assert_eq!(Some(1), store.prune(1).unwrap());
+    assert_eq!(
+        *backup.backups.lock().unwrap(),
+        vec![
+            (
+                BackupReason::Reorg,
+                vec![b1.hash().clone(), b2.hash().clone()]
+            ),
+            (BackupReason::Prune, vec![b1.hash().clone()]),
+        ]
+    );
+}
+
+#[test]
+fn best_block_is_restored_from_chain_events() {
+    let store = BlockChainDatabase::open(MemoryDatabase::default());
+    let b0: IndexedBlock = test_data::block_h0().into();
+    let b1: IndexedBlock = test_data::block_h1().into();
+    let b2: IndexedBlock = test_data::block_h2().into();
+
+    store.insert(b0.clone()).unwrap();
+    store.insert(b1.clone()).unwrap();
+    store.insert(b2.clone()).unwrap();
+    store.canonize(b0.hash()).unwrap();
+    store.canonize(b1.hash()).unwrap();
+    store.canonize(b2.hash()).unwrap();
+    store.decanonize().unwrap();
+
+    let best_blocks = (0..6)
+        .map(|seq| {
+            store
+                .best_block_at_seq(seq)
+                .map(|best_block| (best_block.number, best_block.hash))
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        best_blocks,
+        vec![
+            None,
+            Some((0, b0.hash().clone())),
+            Some((1, b1.hash().clone())),
+            Some((2, b2.hash().clone())),
+            Some((1, b1.hash().clone())),
+            None,
+        ]
+    );
+
+    // all events are recorded just now
+    assert_eq!(store.first_chain_event_seq(), 1);
+    assert_eq!(store.chain_event_seq_at(u32::max_value()), Some(4));
+    assert_eq!(store.chain_event_seq_at(0), None);
+}
+
+#[test]
+fn block_children_are_indexed() {
+    let store = BlockChainDatabase::open(MemoryDatabase::default());
+    let b0: IndexedBlock = test_data::block_h0().into();
+    let b1: IndexedBlock = test_data::block_h1().into();
+    let b2: IndexedBlock = test_data::block_h2().into();
+    let mut side_b1 = test_data::block_h1();
+    side_b1.block_header.time += 1;
+    let side_b1: IndexedBlock = side_b1.into();
+
+    store.insert(b0.clone()).unwrap();
+    store.insert(b1.clone()).unwrap();
+    store.insert(side_b1.clone()).unwrap();
+    store.insert(b2.clone()).unwrap();
+    store.canonize(b0.hash()).unwrap();
+    store.canonize(b1.hash()).unwrap();
+
+    assert_eq!(
+        store.block_children(b0.hash()),
+        vec![b1.hash().clone(), side_b1.hash().clone()]
+    );
+    assert_eq!(store.block_children(b1.hash()), vec![b2.hash().clone()]);
+    assert!(store.block_children(b2.hash()).is_empty());
+
+    assert_eq!(store.next_block_hash(b0.hash()), Some(b1.hash().clone()));
+    // b2 is not canonized yet
+    assert_eq!(store.next_block_hash(b1.hash()), None);
+    assert_eq!(store.next_block_hash(side_b1.hash()), None);
+}
+
+#[test]
+fn block_children_are_indexed_for_old_databases() {
+    let tempdir = TempDir::new("").unwrap();
+    let b0: IndexedBlock = test_data::block_h0().into();
+    let b1: IndexedBlock = test_data::block_h1().into();
+    let b2: IndexedBlock = test_data::block_h2().into();
+    let mut side_b1 = test_data::block_h1();
+    side_b1.block_header.time += 1;
+    let side_b1: IndexedBlock = side_b1.into();
+
+    {
+        let store = BlockChainDatabase::open_at_path(tempdir.path(), 1).unwrap();
+        for block in vec![b0.clone(), b1.clone(), b2.clone()] {
+            let hash = block.hash().clone();
+            store.insert(block).unwrap();
+            store.canonize(&hash).unwrap();
+        }
+        store.insert(side_b1.clone()).unwrap();
+    }
+
+    // blocks, stored by previous versions, have no children indexed
+    {
+        let db = DiskDatabase::open(
+            DatabaseConfig::with_columns(Some(COL_COUNT)),
+            tempdir.path(),
+        )
+        .unwrap();
+        let mut update = Transaction::new();
+        update.delete(Key::BlockChildren(b0.hash().clone()));
+        update.delete(Key::BlockChildren(b1.hash().clone()));
+        db.write(update).unwrap();
+    }
+
+    let store = BlockChainDatabase::open_at_path(tempdir.path(), 1).unwrap();
+    assert!(!store.block_children_indexed());
+    assert!(store.block_children(b0.hash()).is_empty());
+    // canon children are resolved without the index
+    assert_eq!(store.next_block_hash(b0.hash()), Some(b1.hash().clone()));
+    assert_eq!(store.next_block_hash(b1.hash()), Some(b2.hash().clone()));
+
+    assert_eq!(store.index_block_children().unwrap(), 2);
+    assert!(store.block_children_indexed());
+    assert_eq!(
+        store.block_children(b0.hash()),
+        vec![b1.hash().clone(), side_b1.hash().clone()]
+    );
+    assert_eq!(store.block_children(b1.hash()), vec![b2.hash().clone()]);
+    assert_eq!(store.index_block_children().unwrap(), 0);
+}
+
+#[test]
+fn genesis_is_written_once() {
+    let store = BlockChainDatabase::open(MemoryDatabase::default());
+    let b0: IndexedBlock = test_data::block_h0().into();
+    let b1: IndexedBlock = test_data::block_h1().into();
+
+    // only blocks without parent could be genesis blocks
+    match store.init_genesis(b1.clone()) {
+        Err(Error::InvalidGenesis(_)) => (),
+        result => panic!("unexpected result: {:?}", result),
+    }
+    assert_eq!(store.block_hash(0), None);
+
+    assert_eq!(store.init_genesis(b0.clone()), Ok(true));
+    assert_eq!(store.best_block().hash, b0.hash().clone());
+    assert_eq!(store.init_genesis(b0.clone()), Ok(false));
+
+    let mut other_b0 = test_data::block_h0();
+    other_b0.block_header.time += 1;
+    match store.init_genesis(other_b0.into()) {
+        Err(Error::InvalidGenesis(_)) => (),
+        result => panic!("unexpected result: {:?}", result),
+    }
+    assert_eq!(store.block_hash(0), Some(b0.hash().clone()));
+}
+
+#[test]
+fn chain_work_is_tracked() {
+    let store = BlockChainDatabase::open(MemoryDatabase::default());
+    let b0: IndexedBlock = test_data::block_h0().into();
+    let b1: IndexedBlock = test_data::block_h1().into();
+    let b2: IndexedBlock = test_data::block_h2().into();
+    let mut side_b1 = test_data::block_h1();
+    side_b1.block_header.bits = Compact::new(0x1f7fffff);
+    let side_b1: IndexedBlock = side_b1.into();
+
+    for block in vec![b0.clone(), b1.clone(), b2.clone()] {
+        let hash = block.hash().clone();
+        store.insert(block).unwrap();
+        store.canonize(&hash).unwrap();
+    }
+    assert_eq!(store.chain_work(b0.hash()), Some(U256::from(2)));
+    assert_eq!(store.chain_work(b2.hash()), Some(U256::from(6)));
+
+    // shorter side chain with more work becomes the canon chain
+    match store.block_origin(&side_b1.header).unwrap() {
+        BlockOrigin::SideChainBecomingCanonChain(origin) => {
+            assert_eq!(origin.block_number, 1);
+            assert_eq!(
+                origin.decanonized_route,
+                vec![b1.hash().clone(), b2.hash().clone()]
+            );
+        }
+        origin => panic!("unexpected block origin: {:?}", origin),
+    }
+    store.insert(side_b1.clone()).unwrap();
+    assert_eq!(store.chain_work(side_b1.hash()), Some(U256::from(514)));
+}
+
+#[test]
+fn chain_work_is_recomputed() {
+    let shared_database = SharedMemoryDatabase::default();
+    let b0: IndexedBlock = test_data::block_h0().into();
+    let b1: IndexedBlock = test_data::block_h1().into();
+    let b2: IndexedBlock = test_data::block_h2().into();
+    let mut side_b1 = test_data::block_h1();
+    side_b1.block_header.time += 1;
+    let side_b1: IndexedBlock = side_b1.into();
+
+    let store = BlockChainDatabase::open(shared_database.clone());
+    for block in vec![b0.clone(), b1.clone(), b2.clone()] {
+        let hash = block.hash().clone();
+        store.insert(block).unwrap();
+        store.canonize(&hash).unwrap();
+    }
+    store.insert(side_b1.clone()).unwrap();
+
+    // blocks, stored by previous versions, have no chain work
+    let mut update = Transaction::new();
+    for hash in vec![b0.hash(), b1.hash(), b2.hash(), side_b1.hash()] {
+        update.delete(Key::ChainWork(hash.clone()));
+    }
+    shared_database.write(update).unwrap();
+    assert_eq!(store.chain_work(b2.hash()), None);
+    assert!(!store.chain_work_computed());
+
+    assert_eq!(store.recompute_chain_work().unwrap(), 4);
+    assert!(store.chain_work_computed());
+    assert_eq!(store.chain_work(b1.hash()), Some(U256::from(4)));
+    assert_eq!(store.chain_work(side_b1.hash()), Some(U256::from(4)));
+    assert_eq!(store.chain_work(b2.hash()), Some(U256::from(6)));
+    assert_eq!(store.recompute_chain_work().unwrap(), 0);
+}
+
+#[test]
+fn chain_work_is_recomputed_without_children_index() {
+    let shared_database = SharedMemoryDatabase::default();
+    let b0: IndexedBlock = test_data::block_h0().into();
+    let b1: IndexedBlock = test_data::block_h1().into();
+    let b2: IndexedBlock = test_data::block_h2().into();
+    let mut side_b1 = test_data::block_h1();
+    side_b1.block_header.time += 1;
+    let side_b1: IndexedBlock = side_b1.into();
+
+    let store = BlockChainDatabase::open(shared_database.clone());
+    for block in vec![b0.clone(), b1.clone(), b2.clone()] {
+        let hash = block.hash().clone();
+        store.insert(block).unwrap();
+        store.canonize(&hash).unwrap();
+    }
+    store.insert(side_b1.clone()).unwrap();
+
+    // blocks, stored by versions before children have been indexed, have neither children
+    // nor chain work
+    let mut update = Transaction::new();
+    for hash in vec![b0.hash(), b1.hash(), b2.hash(), side_b1.hash()] {
+        update.delete(Key::ChainWork(hash.clone()));
+        update.delete(Key::BlockChildren(hash.clone()));
+    }
+    shared_database.write(update).unwrap();
+
+    // canon chain is walked by number
+    assert_eq!(store.recompute_chain_work().unwrap(), 3);
+    assert_eq!(store.chain_work(b2.hash()), Some(U256::from(6)));
+    assert_eq!(store.chain_work(side_b1.hash()), None);
+
+    // side chains are found, once children are indexed
+    assert_eq!(store.index_block_children().unwrap(), 2);
+    assert_eq!(store.recompute_chain_work().unwrap(), 1);
+    assert_eq!(store.chain_work(side_b1.hash()), Some(U256::from(4)));
+}
+
+#[test]
+fn chain_membership_of_side_blocks() {
+    let store = BlockChainDatabase::open(MemoryDatabase::default());
+    let b0: IndexedBlock = test_data::block_h0().into();
+    let b1: IndexedBlock = test_data::block_h1().into();
+    let mut side_b1 = test_data::block_h1();
+    side_b1.block_header.time += 1;
+    let side_b1: IndexedBlock = side_b1.into();
+    let mut side_b2 = test_data::block_h2();
+    side_b2.block_header.previous_header_hash = side_b1.hash().clone();
+    let side_b2: IndexedBlock = side_b2.into();
+
+    store.insert(b0.clone()).unwrap();
+    store.insert(b1.clone()).unwrap();
+    store.insert(side_b1.clone()).unwrap();
+    store.insert(side_b2.clone()).unwrap();
+    store.canonize(b0.hash()).unwrap();
+    store.canonize(b1.hash()).unwrap();
+
+    assert_eq!(
+        store.chain_membership(b1.hash()),
+        Some(ChainMembership::CanonChain { block_number: 1 })
+    );
+    assert_eq!(
+        store.chain_membership(side_b1.hash()),
+        Some(ChainMembership::SideChain {
+            fork_block_number: 0
+        })
+    );
+    assert_eq!(
+        store.chain_membership(side_b2.hash()),
+        Some(ChainMembership::SideChain {
+            fork_block_number: 0
+        })
+    );
+    assert_eq!(store.chain_membership(&Default::default()), None);
+}
+
+#[test]
+fn chain_snapshot_is_consistent_after_reorg() {
+    let store = BlockChainDatabase::open(MemoryDatabase::default());
+    let b0: IndexedBlock = test_data::block_h0().into();
+    let b1: IndexedBlock = test_data::block_h1().into();
+    let b2: IndexedBlock = test_data::block_h2().into();
+    let mut side_b1 = test_data::block_h1();
+    side_b1.block_header.time += 1;
+    let side_b1: IndexedBlock = side_b1.into();
+    let mut side_b2 = test_data::block_h2();
+    side_b2.block_header.previous_header_hash = side_b1.hash().clone();
+    let side_b2: IndexedBlock = side_b2.into();
+
+    for block in &[&b0, &b1, &b2, &side_b1, &side_b2] {
+        store.insert((*block).clone()).unwrap();
+    }
+    store.canonize(b0.hash()).unwrap();
+    store.canonize(b1.hash()).unwrap();
+    store.canonize(b2.hash()).unwrap();
+
+    let snapshot = store.chain_snapshot();
+    assert_eq!(snapshot.tip().number, 2);
+    assert_eq!(
+        snapshot.block_hashes(&store, 1..10),
+        vec![b1.hash().clone(), b2.hash().clone()]
+    );
+
+    // reorganize to the side chain
+    store.decanonize().unwrap();
+    store.decanonize().unwrap();
+    store.canonize(side_b1.hash()).unwrap();
+    store.canonize(side_b2.hash()).unwrap();
+
+    assert!(!snapshot.is_canonical(&store));
+    assert_eq!(
+        snapshot.block_hashes(&store, 0..10),
+        vec![b0.hash().clone(), b1.hash().clone(), b2.hash().clone()]
+    );
+    assert_eq!(snapshot.block_hash(&store, 1), Some(b1.hash().clone()));
+    assert_eq!(snapshot.block_hash(&store, 3), None);
+
+    let snapshot = store.chain_snapshot();
+    assert!(snapshot.is_canonical(&store));
+    assert_eq!(
+        snapshot.block_hashes(&store, 1..10),
+        vec![side_b1.hash().clone(), side_b2.hash().clone()]
+    );
+}
+
+#[test]
+fn verifier_state_is_exported_and_imported() {
+    let mut blocks = vec![test_data::genesis()];
+    blocks.extend(test_data::build_n_empty_blocks_from_genesis(11, 1));
+    let blocks: Vec<IndexedBlock> = blocks.into_iter().map(Into::into).collect();
+    let store = BlockChainDatabase::init_test_chain(blocks[..11].to_vec());
+
+    let state = store.verifier_state(3, 4);
+    assert_eq!(state.tail_number, 8);
+    assert_eq!(
+        state.checkpoints,
+        vec![
+            Checkpoint {
+                number: 0,
+                hash: blocks[0].hash().clone(),
+            },
+            Checkpoint {
+                number: 4,
+                hash: blocks[4].hash().clone(),
+            },
+        ]
+    );
+    assert_eq!(state.tail.len(), 3);
+    assert_eq!(state.best_block().unwrap(), store.best_block());
+    assert_eq!(
+        Some(state.tail_parent_work),
+        store.chain_work(blocks[7].hash())
+    );
+    let mut mmr = MerkleMountainRange::default();
+    for block in &blocks[..11] {
+        mmr.append(block.hash().clone());
+    }
+    assert_eq!(verifier_state_mmr(&state), Some(mmr.clone()));
+
+    let imported = BlockChainDatabase::init_test_chain(vec![blocks[0].clone()]);
+    imported.import_verifier_state(state.clone()).unwrap();
+    assert_eq!(imported.best_block(), store.best_block());
+    assert_eq!(imported.pruned_height(), Some(10));
+    assert_eq!(imported.block_hash(4), Some(blocks[4].hash().clone()));
+    assert_eq!(imported.block_hash(5), None);
+    assert_eq!(blocks[9].header, imported.block_header(9.into()).unwrap());
+    assert!(imported.block(9.into()).is_none());
+    // chain work of the tail is seeded by the state, so it isn't recomputed
+    assert_eq!(
+        imported.chain_work(blocks[10].hash()),
+        store.chain_work(blocks[10].hash())
+    );
+    assert_eq!(imported.chain_work(blocks[4].hash()), None);
+    assert_eq!(imported.recompute_chain_work().unwrap(), 0);
+    assert!(imported.chain_work_computed());
+
+    // next blocks are appended to the imported chain
+    imported.insert(blocks[11].clone()).unwrap();
+    imported.canonize(blocks[11].hash()).unwrap();
+    assert_eq!(imported.best_block().number, 11);
+    assert!(imported.chain_work(blocks[11].hash()).is_some());
+
+    // state is re-exported from the imported chain, which has no blocks below the tail
+    mmr.append(blocks[11].hash().clone());
+    let reexported = imported.verifier_state(3, 4);
+    assert_eq!(reexported.tail_number, 9);
+    assert_eq!(verifier_state_mmr(&reexported), Some(mmr.clone()));
+    let reexported = imported.verifier_state(10, 4);
+    assert_eq!(reexported.tail_number, 8);
+    assert_eq!(reexported.tail_parent_work, state.tail_parent_work);
+    assert_eq!(verifier_state_mmr(&reexported), Some(mmr));
+
+    // state is only imported into database with genesis block
+    assert!(imported.import_verifier_state(state.clone()).is_err());
+    let mut unlinked = state.clone();
+    unlinked.tail.remove(1);
+    let empty = BlockChainDatabase::init_test_chain(vec![blocks[0].clone()]);
+    assert!(empty.import_verifier_state(unlinked).is_err());
+    let mut without_mmr = state;
+    without_mmr.mmr_peaks.clear();
+    assert!(empty.import_verifier_state(without_mmr).is_err());
+}
+
+#[test]
+fn prune_block_bodies() {
+    let shared_database = SharedMemoryDatabase::default();
+    let b0: IndexedBlock = test_data::block_h0().into();
+    let b1: IndexedBlock = test_data::block_h1().into();
+    let b2: IndexedBlock = test_data::block_h2().into();
+
+    {
+        let store = BlockChainDatabase::open(shared_database.clone());
+        store.insert(b0.clone()).unwrap();
+        store.insert(b1.clone()).unwrap();
+        store.insert(b2.clone()).unwrap();
+
+        store.canonize(b0.hash()).unwrap();
+        store.canonize(b1.hash()).unwrap();
+        store.canonize(b2.hash()).unwrap();
+
+        assert_eq!(None, store.pruned_height());
+        // blocks above the best block are never pruned
+        assert_eq!(Some(2), store.prune(10).unwrap());
+        assert_eq!(Some(2), store.prune(1).unwrap());
+    }
+    {
+        let store = BlockChainDatabase::open(shared_database);
+        assert_eq!(Some(2), store.pruned_height());
+        // genesis block is never pruned
+        assert!(store.block(0.into()).is_some());
+        assert!(store.block(1.into()).is_none());
+        assert!(store.block(2.into()).is_none());
+        // while headers are still there
+        assert!(store.contains_block(1.into()));
+        assert_eq!(b2.header, store.block_header(2.into()).unwrap());
+    }
+}
+
+#[test]
+fn blocks_are_indexed_by_miner() {
+    let shared_database = SharedMemoryDatabase::default();
+    let blocks: Vec<IndexedBlock> = vec![
+        test_data::block_h0().into(),
+        test_data::block_h1().into(),
+        test_data::block_h2().into(),
+    ];
+    let miner =
+        |block: &IndexedBlock| -> Bytes { block.header.raw.pubkey.to_bytes().to_vec().into() };
+    let mined_by = |key: &Bytes, best: usize| -> Vec<u32> {
+        (0..best + 1)
+            .filter(|number| miner(&blocks[*number]) == *key)
+            .map(|number| number as u32)
+            .collect()
+    };
+    let key = miner(&blocks[2]);
+
+    {
+        let store = BlockChainDatabase::open(shared_database.clone());
+        for block in &blocks {
+            store.insert(block.clone()).unwrap();
+        }
+        store.canonize(blocks[0].hash()).unwrap();
+        store.canonize(blocks[1].hash()).unwrap();
+        assert_eq!(store.blocks_by_miner(&key, 0..10), None);
+
+        // blocks, connected before the index has been enabled, are indexed
+        assert_eq!(store.enable_miner_index().unwrap(), 2);
+        assert_eq!(store.enable_miner_index().unwrap(), 0);
+        assert_eq!(store.blocks_by_miner(&key, 0..10), Some(mined_by(&key, 1)));
+
+        store.canonize(blocks[2].hash()).unwrap();
+        assert_eq!(store.blocks_by_miner(&key, 0..10), Some(mined_by(&key, 2)));
+        assert_eq!(store.blocks_by_miner(&key, 2..3), Some(vec![2]));
+        assert_eq!(store.blocks_by_miner(&key, 3..10), Some(vec![]));
+
+        // entry of the disconnected block is removed
+        store.decanonize().unwrap();
+        assert_eq!(store.blocks_by_miner(&key, 2..3), Some(vec![]));
+        assert!(shared_database
+            .get(&Key::MinerBlock(MinerBlockKey {
+                miner: key.clone(),
+                number: 2,
+            }))
+            .unwrap()
+            .into_option()
+            .is_none());
+        store.canonize(blocks[2].hash()).unwrap();
+    }
+    {
+        // block is disconnected while the index is disabled
+        let store = BlockChainDatabase::open(shared_database.clone());
+        store.decanonize().unwrap();
+    }
+    {
+        let store = BlockChainDatabase::open(shared_database);
+        assert_eq!(store.enable_miner_index().unwrap(), 2);
+        assert_eq!(store.blocks_by_miner(&key, 0..10), Some(mined_by(&key, 1)));
+    }
+}
+
+#[test]
+fn retained_block_bodies_are_not_pruned() {
+    let store = BlockChainDatabase::init_test_chain(vec![
+        test_data::block_h0().into(),
+        test_data::block_h1().into(),
+        test_data::block_h2().into(),
+    ]);
+
+    {
+        let _guard = store.retain_bodies(2);
+        let _other_guard = store.retain_bodies(2);
+        assert_eq!(Some(1), store.prune(10).unwrap());
+        assert!(store.block(2.into()).is_some());
+    }
+
+    assert_eq!(Some(2), store.prune(10).unwrap());
+    assert!(store.block(2.into()).is_none());
+}
+
+#[test]
+fn block_iter_streams_canonical_blocks() {
+    let mut blocks: Vec<IndexedBlock> = vec![test_data::genesis().into()];
+    blocks.extend(
+        test_data::build_n_empty_blocks_from_genesis(40, 1)
+            .into_iter()
+            .map(Into::into),
+    );
+    let store = BlockChainDatabase::init_test_chain(blocks.clone());
+    assert_eq!(Some(5), store.prune(5).unwrap());
+
+    // iteration stops at the best block
+    let streamed: Vec<_> = store.block_iter(0..100).collect();
+    assert_eq!(streamed.len(), blocks.len());
+    for (number, (streamed, block)) in streamed.iter().zip(blocks.iter()).enumerate() {
+        assert_eq!(streamed.header, block.header);
+        // pruned blocks are streamed with empty proof
+        if number != 0 && number <= 5 {
+            assert!(streamed.proof.is_empty());
+        } else {
+            assert_eq!(streamed.proof, block.proof);
+        }
+    }
+
+    let hashes: Vec<_> = store.block_iter(3..20).map(|b| b.header.hash).collect();
+    let expected: Vec<_> = blocks[3..20]
+        .iter()
+        .map(|b| b.header.hash.clone())
+        .collect();
+    assert_eq!(hashes, expected);
+    assert_eq!(store.block_iter(41..50).count(), 0);
+}
+
+#[test]
+fn stored_blocks_are_read_without_index() {
+    let tempdir = TempDir::new("").unwrap();
+    let b0: IndexedBlock = test_data::block_h0().into();
+    let b1: IndexedBlock = test_data::block_h1().into();
+    let b2: IndexedBlock = test_data::block_h2().into();
+
+    {
+        let store = BlockChainDatabase::open_at_path(tempdir.path(), 1).unwrap();
+        store.insert(b0.clone()).unwrap();
+        store.insert(b1.clone()).unwrap();
+        store.insert(b2.clone()).unwrap();
+        store.canonize(b0.hash()).unwrap();
+    }
+
+    let stored_blocks = StoredBlocks::open_at_path(tempdir.path()).unwrap();
+    assert_eq!(
+        stored_blocks.descendants_of(b0.hash()),
+        vec![b1.hash().clone(), b2.hash().clone()]
+    );
+    assert_eq!(stored_blocks.block(b2.hash()), Some(b2));
+}
+
+#[test]
+fn database_inconsistencies_are_found_and_repaired() {
+    let tempdir = TempDir::new("").unwrap();
+    let b0: IndexedBlock = test_data::block_h0().into();
+    let b1: IndexedBlock = test_data::block_h1().into();
+    let b2: IndexedBlock = test_data::block_h2().into();
+
+    {
+        let store = BlockChainDatabase::open_at_path(tempdir.path(), 1).unwrap();
+        for block in vec![b0.clone(), b1.clone(), b2.clone()] {
+            let hash = block.hash().clone();
+            store.insert(block).unwrap();
+            store.canonize(&hash).unwrap();
+        }
+    }
+
+    {
+        let checker = ConsistencyChecker::open_at_path(tempdir.path()).unwrap();
+        let report = checker.check(false).unwrap();
+        assert_eq!(report.checked_blocks, 3);
+        assert!(report.is_consistent());
+    }
+
+    {
+        let db = DiskDatabase::open(
+            DatabaseConfig::with_columns(Some(COL_COUNT)),
+            tempdir.path(),
+        )
+        .unwrap();
+        let mut update = Transaction::new();
+        update.insert(KeyValue::BlockNumber(b1.hash().clone(), 5));
+        update.insert(KeyValue::BlockNumber(H256::from(7), 2));
+        update.insert(KeyValue::BlockBody(
+            H256::from(9),
+            Bytes::from(vec![1, 2, 3]),
+        ));
+        db.write(update).unwrap();
+    }
+
+    let checker = ConsistencyChecker::open_at_path(tempdir.path()).unwrap();
+    let expected = vec![
+        Inconsistency::BadBlockNumber {
+            number: 1,
+            hash: b1.hash().clone(),
+            indexed: Some(5),
+        },
+        Inconsistency::StaleBlockNumber {
+            number: 2,
+            hash: H256::from(7),
+        },
+        Inconsistency::OrphanBody(H256::from(9)),
+    ];
+    let report = checker.check(false).unwrap();
+    assert_eq!(report.inconsistencies, expected);
+    assert_eq!(report.repaired, 0);
+    assert!(report
+        .inconsistencies
+        .iter()
+        .all(Inconsistency::is_repairable));
+
+    let report = checker.check(true).unwrap();
+    assert_eq!(report.inconsistencies, expected);
+    assert_eq!(report.repaired, 3);
+    assert!(checker.check(false).unwrap().is_consistent());
+}
+
+#[test]
+fn database_backends_store_blocks() {
+    let tempdir = TempDir::new("").unwrap();
+    let b0: IndexedBlock = test_data::block_h0().into();
+    let b1: IndexedBlock = test_data::block_h1().into();
+
+    for backend in &[DatabaseBackend::RocksDb, DatabaseBackend::Memory] {
+        let store = backend
+            .open(
+                tempdir.path().join(format!("{:?}", backend)),
+                1,
+                false,
+                None,
+                None,
+                0,
+            )
+            .unwrap();
+        store.insert(b0.clone()).unwrap();
+        store.insert(b1.clone()).unwrap();
+        store.canonize(b0.hash()).unwrap();
+        store.canonize(b1.hash()).unwrap();
+
+        assert_eq!(1, store.best_block().number);
+        assert_eq!(Some(b1.hash().clone()), store.block_hash(1));
+        assert_eq!(Some(b1.clone()), store.block(1.into()));
+    }
+}
+
+#[test]
+fn compressed_block_bodies_are_read_back() {
+    let store = BlockChainDatabase::open(MemoryDatabase::default()).with_block_compression(true);
+    // bodies are not verified by the storage, so repeated proofs are stored to make sure
+    // that they compress well
+    let compressible = |block: IndexedBlock| {
+        let proof = (0..16).flat_map(|_| block.proof.clone()).collect();
+        IndexedBlock::new(block.header, proof)
+    };
+    let b0: IndexedBlock = test_data::block_h0().into();
+    let b1 = compressible(test_data::block_h1().into());
+    let b2 = compressible(test_data::block_h2().into());
+
+    for block in vec![b0.clone(), b1.clone(), b2.clone()] {
+        let hash = block.hash().clone();
+        store.insert(block).unwrap();
+        store.canonize(&hash).unwrap();
+    }
+
+    assert_eq!(Some(b1.clone()), store.block(1.into()));
+    assert_eq!(Some(b2.header.clone()), store.block_header(2.into()));
+    let stats = store.stats();
+    assert_eq!(stats.compressed_blocks, 2);
+    assert!(stats.compression_ratio().unwrap() > 1f64);
+
+    // compressed bodies are pruned along with uncompressed ones
+    assert_eq!(Some(2), store.prune(2).unwrap());
+    assert_eq!(None, store.block(2.into()));
+    assert_eq!(Some(b2.header.clone()), store.block_header(2.into()));
+    assert_eq!(0, store.stats().compressed_blocks);
+}
+
+#[test]
+fn database_size_is_reported_and_compacted() {
+    let tempdir = TempDir::new("").unwrap();
+    let b0: IndexedBlock = test_data::block_h0().into();
+    let b1: IndexedBlock = test_data::block_h1().into();
+
+    {
+        let store = BlockChainDatabase::open_at_path(tempdir.path(), 1).unwrap();
+        store.insert(b0.clone()).unwrap();
+        store.insert(b1.clone()).unwrap();
+        store.canonize(b0.hash()).unwrap();
+        store.canonize(b1.hash()).unwrap();
+    }
+
+    let store = BlockChainDatabase::open_at_path(tempdir.path(), 1).unwrap();
+    let size = store.size().unwrap();
+    assert!(size.disk_size.unwrap() > 0);
+    let blocks = size
+        .columns
+        .iter()
+        .find(|column| column.name == "blocks")
+        .unwrap();
+    assert_eq!(blocks.keys, 2);
+    assert!(blocks.size > 0);
+    assert!(size.data_size() >= blocks.size);
+
+    store.compact().unwrap();
+    assert_eq!(Some(b1.clone()), store.block(1.into()));
+    assert_eq!(size.columns, store.size().unwrap().columns);
+
+    // in-memory database has no files
+    let store = BlockChainDatabase::init_test_chain(vec![b0]);
+    assert_eq!(store.size().unwrap().disk_size, None);
+    assert_eq!(store.compact(), Ok(()));
+}
+
+#[test]
+fn database_backend_from_str() {
+    assert_eq!("rocksdb".parse(), Ok(DatabaseBackend::RocksDb));
+    assert_eq!("memory".parse(), Ok(DatabaseBackend::Memory));
+    assert!("sled".parse::<DatabaseBackend>().is_err());
+}
+
+#[test]
+fn invalidated_blocks_are_persisted() {
+    let tempdir = TempDir::new("").unwrap();
+    let blocks = vec![H256::from(1), H256::from(2)];
+
+    {
+        let store = BlockChainDatabase::open_at_path(tempdir.path(), 1).unwrap();
+        assert!(store.invalidated_blocks().is_empty());
+        store.set_invalidated_blocks(&blocks).unwrap();
+    }
+
+    let store = BlockChainDatabase::open_at_path(tempdir.path(), 1).unwrap();
+    assert_eq!(store.invalidated_blocks(), blocks);
+    store.set_invalidated_blocks(&[]).unwrap();
+    assert!(store.invalidated_blocks().is_empty());
+}
@@ -2,12 +2,22 @@ extern crate ansi_term;
 extern crate env_logger;
 extern crate log;
 extern crate time;
+extern crate tracing_core;
+
+#[cfg(test)]
+#[macro_use]
+extern crate tracing;
+
+mod spans;
 
 use ansi_term::Colour as Color;
-use env_logger::Builder;
-use log::{Level, Record};
+use env_logger::{Builder, Logger};
+use log::{Level, Log, Metadata, Record};
 use std::env;
 use std::io::Write;
+use std::sync::{Arc, RwLock};
+
+pub use spans::{init_spans, LogSubscriber};
 
 fn strftime() -> String {
     time::strftime("%Y-%m-%d %H:%M:%S %Z", &time::now()).expect("Time is incorrectly formatted")
@@ -54,10 +64,46 @@ impl LogFormatter for DateAndColorLogFormatter {
     }
 }
 
-pub fn init<T>(filters: &str, formatter: T)
-where
-    T: LogFormatter,
-{
+/// Handle, which allows to change log filters at runtime.
+#[derive(Clone)]
+pub struct LogFilters {
+    logger: Arc<RwLock<Logger>>,
+    formatter: Arc<dyn LogFormatter>,
+}
+
+impl LogFilters {
+    /// Replaces current log filters. `RUST_LOG` filters are still applied on top of given filters.
+    pub fn set(&self, filters: &str) {
+        let logger = build_logger(filters, self.formatter.clone());
+        log::set_max_level(logger.filter());
+        *self.logger.write().expect("logger lock is never poisoned") = logger;
+    }
+}
+
+/// Logger, which filters could be replaced at runtime.
+struct ReloadableLogger {
+    logger: Arc<RwLock<Logger>>,
+}
+
+impl Log for ReloadableLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        self.logger
+            .read()
+            .expect("logger lock is never poisoned")
+            .enabled(metadata)
+    }
+
+    fn log(&self, record: &Record) {
+        self.logger
+            .read()
+            .expect("logger lock is never poisoned")
+            .log(record)
+    }
+
+    fn flush(&self) {}
+}
+
+fn build_logger(filters: &str, formatter: Arc<dyn LogFormatter>) -> Logger {
     let mut builder = Builder::new();
 
     let filters = match env::var("RUST_LOG") {
@@ -68,5 +114,26 @@ where
     builder.parse(&filters);
     builder.format(move |buf, record| writeln!(buf, "{}", formatter.format(record)));
 
-    builder.init();
+    builder.build()
+}
+
+pub fn init<T>(filters: &str, formatter: T) -> LogFilters
+where
+    T: LogFormatter,
+{
+    let formatter: Arc<dyn LogFormatter> = Arc::new(formatter);
+    let logger = build_logger(filters, formatter.clone());
+    let max_level = logger.filter();
+    let logger = Arc::new(RwLock::new(logger));
+
+    log::set_boxed_logger(Box::new(ReloadableLogger {
+        logger: logger.clone(),
+    }))
+    .expect("logger is initialized once");
+    log::set_max_level(max_level);
+
+    LogFilters {
+        logger: logger,
+        formatter: formatter,
+    }
 }
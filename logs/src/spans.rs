@@ -0,0 +1,369 @@
+//! `tracing` subscriber, which writes events to the `log` logger.
+//!
+//! Every event is prefixed with the names and fields of the spans it is nested in
+//! (e.g. `block{hash=.. peer=3}:verify_block: ..`), so all events of the traced block
+//! could be found by its hash.
+
+use log;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt::{self, Write};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use tracing_core::field::{Field, Visit};
+use tracing_core::span::{Attributes, Id, Record};
+use tracing_core::{dispatcher, Dispatch, Event, Interest, Level, Metadata, Subscriber};
+
+thread_local! {
+    /// Spans, entered by the current thread.
+    static ENTERED_SPANS: RefCell<Vec<u64>> = RefCell::new(Vec::new());
+}
+
+/// Span, known to the subscriber.
+struct SpanData {
+    /// Context of the parent span.
+    parent_context: String,
+    /// Name of the span.
+    name: &'static str,
+    /// Recorded fields of the span.
+    fields: FieldsVisitor,
+    /// Number of span handles.
+    refs: usize,
+}
+
+impl SpanData {
+    /// Returns names and fields of the span and its parents.
+    fn context(&self) -> String {
+        let mut context = self.parent_context.clone();
+        if !context.is_empty() {
+            context.push(':');
+        }
+        context.push_str(self.name);
+        if !self.fields.fields.is_empty() {
+            let _ = write!(context, "{{{}}}", self.fields.fields);
+        }
+        context
+    }
+}
+
+/// Formats fields of spans and events.
+#[derive(Default)]
+struct FieldsVisitor {
+    /// Message of the event.
+    message: String,
+    /// Formatted `name=value` pairs.
+    fields: String,
+}
+
+impl Visit for FieldsVisitor {
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        if field.name() == "message" {
+            let _ = write!(self.message, "{:?}", value);
+            return;
+        }
+
+        if !self.fields.is_empty() {
+            self.fields.push(' ');
+        }
+        let _ = write!(self.fields, "{}={:?}", field.name(), value);
+    }
+}
+
+/// Subscriber, which writes `tracing` events to the `log` logger.
+/// Spans and events are enabled by the filters of the logger.
+#[derive(Default)]
+pub struct LogSubscriber {
+    /// Spans, which have open handles.
+    spans: Mutex<HashMap<u64, SpanData>>,
+    /// Last assigned span id.
+    last_id: AtomicUsize,
+}
+
+impl LogSubscriber {
+    fn current_span(&self) -> Option<u64> {
+        ENTERED_SPANS.with(|spans| spans.borrow().last().cloned())
+    }
+
+    fn context(&self, span: Option<u64>) -> String {
+        span.and_then(|span| {
+            self.spans
+                .lock()
+                .expect("spans lock is never poisoned")
+                .get(&span)
+                .map(SpanData::context)
+        })
+        .unwrap_or_default()
+    }
+}
+
+fn log_level(level: &Level) -> log::Level {
+    if *level == Level::ERROR {
+        log::Level::Error
+    } else if *level == Level::WARN {
+        log::Level::Warn
+    } else if *level == Level::INFO {
+        log::Level::Info
+    } else if *level == Level::DEBUG {
+        log::Level::Debug
+    } else {
+        log::Level::Trace
+    }
+}
+
+impl Subscriber for LogSubscriber {
+    fn register_callsite(&self, _metadata: &'static Metadata<'static>) -> Interest {
+        // log filters could be changed at runtime, so callsites are never cached
+        Interest::sometimes()
+    }
+
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        log::logger().enabled(
+            &log::Metadata::builder()
+                .level(log_level(metadata.level()))
+                .target(metadata.target())
+                .build(),
+        )
+    }
+
+    fn new_span(&self, attributes: &Attributes) -> Id {
+        let parent = if attributes.is_contextual() {
+            self.current_span()
+        } else {
+            attributes.parent().map(Id::into_u64)
+        };
+        let mut fields = FieldsVisitor::default();
+        attributes.record(&mut fields);
+        let span = SpanData {
+            parent_context: self.context(parent),
+            name: attributes.metadata().name(),
+            fields: fields,
+            refs: 1,
+        };
+
+        let id = self.last_id.fetch_add(1, Ordering::SeqCst) as u64 + 1;
+        self.spans
+            .lock()
+            .expect("spans lock is never poisoned")
+            .insert(id, span);
+        Id::from_u64(id)
+    }
+
+    fn record(&self, span: &Id, values: &Record) {
+        if let Some(span) = self
+            .spans
+            .lock()
+            .expect("spans lock is never poisoned")
+            .get_mut(&span.into_u64())
+        {
+            values.record(&mut span.fields);
+        }
+    }
+
+    fn record_follows_from(&self, _span: &Id, _follows: &Id) {}
+
+    fn event(&self, event: &Event) {
+        let metadata = event.metadata();
+        let parent = if event.is_contextual() {
+            self.current_span()
+        } else {
+            event.parent().map(Id::into_u64)
+        };
+        let mut fields = FieldsVisitor::default();
+        event.record(&mut fields);
+
+        let mut line = self.context(parent);
+        if !line.is_empty() {
+            line.push_str(": ");
+        }
+        line.push_str(&fields.message);
+        if !fields.fields.is_empty() {
+            let _ = write!(line, " {}", fields.fields);
+        }
+
+        log::logger().log(
+            &log::Record::builder()
+                .args(format_args!("{}", line))
+                .level(log_level(metadata.level()))
+                .target(metadata.target())
+                .module_path(metadata.module_path())
+                .file(metadata.file())
+                .line(metadata.line())
+                .build(),
+        );
+    }
+
+    fn enter(&self, span: &Id) {
+        ENTERED_SPANS.with(|spans| spans.borrow_mut().push(span.into_u64()));
+    }
+
+    fn exit(&self, span: &Id) {
+        let span = span.into_u64();
+        ENTERED_SPANS.with(|spans| {
+            let mut spans = spans.borrow_mut();
+            if let Some(position) = spans.iter().rposition(|entered| *entered == span) {
+                spans.remove(position);
+            }
+        });
+    }
+
+    fn clone_span(&self, span: &Id) -> Id {
+        if let Some(span) = self
+            .spans
+            .lock()
+            .expect("spans lock is never poisoned")
+            .get_mut(&span.into_u64())
+        {
+            span.refs += 1;
+        }
+        span.clone()
+    }
+
+    fn try_close(&self, span: Id) -> bool {
+        let mut spans = self.spans.lock().expect("spans lock is never poisoned");
+        let id = span.into_u64();
+        let closed = match spans.get_mut(&id) {
+            Some(span) => {
+                span.refs -= 1;
+                span.refs == 0
+            }
+            None => false,
+        };
+        if closed {
+            spans.remove(&id);
+        }
+        closed
+    }
+}
+
+/// Installs the subscriber, which writes `tracing` events to the `log` logger.
+pub fn init_spans() {
+    dispatcher::set_global_default(Dispatch::new(LogSubscriber::default()))
+        .expect("tracing subscriber is initialized once");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::LogSubscriber;
+    use log;
+    use std::cell::RefCell;
+    use tracing;
+    use tracing_core::{dispatcher, Dispatch};
+
+    thread_local! {
+        /// Lines, logged by the current thread.
+        static LOGGED: RefCell<Vec<String>> = RefCell::new(Vec::new());
+    }
+
+    struct TestLogger;
+
+    impl log::Log for TestLogger {
+        fn enabled(&self, metadata: &log::Metadata) -> bool {
+            metadata.target() != "disabled"
+        }
+
+        fn log(&self, record: &log::Record) {
+            LOGGED.with(|logged| {
+                logged
+                    .borrow_mut()
+                    .push(format!("{} {}", record.level(), record.args()))
+            });
+        }
+
+        fn flush(&self) {}
+    }
+
+    static LOGGER: TestLogger = TestLogger;
+
+    /// Runs given closure with the test subscriber and returns lines, it has logged.
+    fn logged_with_subscriber<F: FnOnce(&LogSubscriber)>(f: F) -> Vec<String> {
+        // logger is global => it is installed by the first test
+        let _ = log::set_logger(&LOGGER);
+        log::set_max_level(log::LevelFilter::Trace);
+
+        let dispatch = Dispatch::new(LogSubscriber::default());
+        dispatcher::with_default(&dispatch, || {
+            f(dispatch
+                .downcast_ref::<LogSubscriber>()
+                .expect("dispatch wraps LogSubscriber; qed"))
+        });
+        LOGGED.with(|logged| logged.borrow_mut().drain(..).collect())
+    }
+
+    #[test]
+    fn event_is_prefixed_with_span_context() {
+        let logged = logged_with_subscriber(|_| {
+            let block = debug_span!(target: "sync", "block", hash = "01", peer = 3);
+            let _block = block.enter();
+            let verify = debug_span!(target: "sync", "verify_block");
+            let _verify = verify.enter();
+            warn!(target: "sync", height = 10, "verification failed");
+        });
+        assert_eq!(
+            logged,
+            vec![r#"WARN block{hash="01" peer=3}:verify_block: verification failed height=10"#]
+        );
+    }
+
+    #[test]
+    fn event_outside_of_spans_is_not_prefixed() {
+        let logged = logged_with_subscriber(|_| {
+            {
+                let span = info_span!(target: "sync", "block");
+                let _span = span.enter();
+            }
+            info!(target: "sync", "best block changed");
+        });
+        assert_eq!(logged, vec!["INFO best block changed"]);
+    }
+
+    #[test]
+    fn explicit_parent_span_is_used() {
+        let logged = logged_with_subscriber(|_| {
+            let block = debug_span!(target: "sync", "block", peer = 1);
+            let verify = debug_span!(target: "sync", parent: &block, "verify_block");
+            let _verify = verify.enter();
+            trace!(target: "sync", parent: &block, "queued");
+            trace!(target: "sync", "verifying");
+        });
+        assert_eq!(
+            logged,
+            vec![
+                "TRACE block{peer=1}: queued",
+                "TRACE block{peer=1}:verify_block: verifying",
+            ]
+        );
+    }
+
+    #[test]
+    fn recorded_fields_are_added_to_span_context() {
+        let logged = logged_with_subscriber(|_| {
+            let span = debug_span!(target: "sync", "block", peer = tracing::field::Empty);
+            span.record("peer", &5);
+            let _span = span.enter();
+            debug!(target: "sync", "received");
+        });
+        assert_eq!(logged, vec!["DEBUG block{peer=5}: received"]);
+    }
+
+    #[test]
+    fn disabled_events_are_not_logged() {
+        let logged = logged_with_subscriber(|_| {
+            error!(target: "disabled", "hidden");
+            error!(target: "sync", "shown");
+        });
+        assert_eq!(logged, vec!["ERROR shown"]);
+    }
+
+    #[test]
+    fn span_is_forgotten_when_last_handle_is_dropped() {
+        logged_with_subscriber(|subscriber| {
+            let span = debug_span!(target: "sync", "block");
+            let clone = span.clone();
+            assert_eq!(subscriber.spans.lock().unwrap().len(), 1);
+            drop(span);
+            assert_eq!(subscriber.spans.lock().unwrap().len(), 1);
+            drop(clone);
+            assert!(subscriber.spans.lock().unwrap().is_empty());
+        });
+    }
+}
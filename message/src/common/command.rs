@@ -35,6 +35,12 @@ impl Command {
         self.0.is_zero()
     }
 
+    /// Is command a non-empty printable ASCII string, padded with zeros?
+    pub fn is_valid(&self) -> bool {
+        let len = self.len();
+        len != 0 && self.0[..len].iter().all(|c| c.is_ascii_graphic())
+    }
+
     fn as_string(&self) -> String {
         String::from_utf8_lossy(&self.0[..self.len()]).to_ascii_lowercase()
     }
@@ -100,4 +106,13 @@ mod tests {
         assert!(command != "ver");
         assert!(command != "versionx");
     }
+
+    #[test]
+    fn command_validity() {
+        let command: Command = "version".into();
+        assert!(command.is_valid());
+        assert!(!Command("76657200696f6e0000000000".into()).is_valid());
+        assert!(!Command("0a6572000000000000000000".into()).is_valid());
+        assert!(!Command("000000000000000000000000".into()).is_valid());
+    }
 }
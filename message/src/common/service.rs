@@ -23,6 +23,26 @@ impl Services {
         self
     }
 
+    /// Large messages could be compressed with snappy.
+    pub fn snappy_compression(&self) -> bool {
+        self.bit_at(24)
+    }
+
+    pub fn with_snappy_compression(mut self, v: bool) -> Self {
+        self.set_bit(24, v);
+        self
+    }
+
+    /// Headers could be sent with `headers2` message, omitting fields derivable from the previous header.
+    pub fn compressed_headers(&self) -> bool {
+        self.bit_at(25)
+    }
+
+    pub fn with_compressed_headers(mut self, v: bool) -> Self {
+        self.set_bit(25, v);
+        self
+    }
+
     pub fn includes(&self, other: &Self) -> bool {
         self.0 & other.0 == other.0
     }
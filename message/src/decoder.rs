@@ -0,0 +1,429 @@
+//! Strict decoder of raw network messages. Every malformed input is rejected with the reason,
+//! so the same checks are applied on the p2p read path and when the decoder is fuzzed.
+
+use bytes::Bytes;
+use common::Command;
+use crypto::checksum;
+use message::Compression;
+use network::{Magic, Network};
+use serialization::PayloadReader;
+use std::{error, fmt};
+use types;
+use {Error, MessageHeader, Payload};
+
+/// Size of the serialized message header.
+pub const MESSAGE_HEADER_LEN: usize = 24;
+/// Max size of the message payload, accepted from peers.
+pub const MAX_PAYLOAD_LEN: usize = 32 * 1024 * 1024;
+
+/// Reason, why the message has been rejected.
+#[derive(Debug, Clone, PartialEq)]
+pub enum DecodeError {
+    /// Input is shorter than the message header.
+    TruncatedHeader,
+    /// Network magic comes from different network.
+    InvalidMagic,
+    /// Command is not a printable ASCII string, padded with zeros.
+    InvalidCommand,
+    /// Payload length from the header exceeds the limit.
+    PayloadTooLarge(u32),
+    /// Number of bytes after the header differs from the payload length from the header.
+    PayloadLengthMismatch { expected: u32, actual: usize },
+    /// Payload checksum does not match the checksum from the header.
+    InvalidChecksum,
+    /// Compressed payload is malformed or too large.
+    InvalidCompression,
+    /// Command is unknown (strict mode only).
+    UnknownCommand(Command),
+    /// Message is not supported by the protocol version.
+    UnsupportedVersion(Command),
+    /// Payload can't be deserialized.
+    MalformedPayload(Command),
+    /// Payload has bytes after the message (strict mode only).
+    TrailingBytes(Command),
+}
+
+/// Decoded network message.
+#[derive(Debug, PartialEq)]
+pub enum DecodedMessage {
+    Version(types::Version),
+    Verack(types::Verack),
+    Addr(types::Addr),
+    GetAddr(types::GetAddr),
+    Ping(types::Ping),
+    Pong(types::Pong),
+    Reject(types::Reject),
+    Inv(types::Inv),
+    GetData(types::GetData),
+    GetBlocks(types::GetBlocks),
+    GetHeaders(types::GetHeaders),
+    Headers(types::Headers),
+    Headers2(types::Headers2),
+    Block(types::Block),
+    NotFound(types::NotFound),
+    SendHeaders(types::SendHeaders),
+    /// Message with unknown command and its raw payload (lenient mode only).
+    Unknown(Command, Bytes),
+}
+
+/// Decoder of messages, received from the single peer.
+#[derive(Debug, Clone)]
+pub struct MessageDecoder {
+    /// Magic of the network, peer is connected to
+    magic: Magic,
+    /// Negotiated protocol version
+    version: u32,
+    /// Negotiated payloads compression
+    compression: Compression,
+    /// Max size of the payload, before decompression
+    max_payload_len: usize,
+    /// If true, unknown commands and trailing payload bytes are accepted
+    lenient: bool,
+}
+
+impl DecodeError {
+    /// Is the stream of messages broken by this error? Errors are classified by
+    /// `Error::is_recoverable`, which is used on the p2p read path.
+    pub fn is_fatal(&self) -> bool {
+        !Error::from(self.clone()).is_recoverable()
+    }
+}
+
+impl From<DecodeError> for Error {
+    fn from(err: DecodeError) -> Self {
+        match err {
+            DecodeError::TruncatedHeader | DecodeError::PayloadLengthMismatch { .. } => {
+                Error::MalformedHeader
+            }
+            DecodeError::InvalidMagic => Error::InvalidMagic,
+            DecodeError::InvalidCommand => Error::InvalidCommand,
+            DecodeError::PayloadTooLarge(_) => Error::PayloadTooLarge,
+            DecodeError::InvalidChecksum => Error::InvalidChecksum,
+            DecodeError::InvalidCompression => Error::InvalidCompression,
+            DecodeError::UnsupportedVersion(_) => Error::InvalidVersion,
+            DecodeError::UnknownCommand(_)
+            | DecodeError::MalformedPayload(_)
+            | DecodeError::TrailingBytes(_) => Error::Deserialize,
+        }
+    }
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            DecodeError::TruncatedHeader => f.write_str("Message header is truncated"),
+            DecodeError::InvalidMagic => f.write_str("Invalid network magic"),
+            DecodeError::InvalidCommand => f.write_str("Invalid message command"),
+            DecodeError::PayloadTooLarge(len) => {
+                write!(f, "Message payload of {} bytes is too large", len)
+            }
+            DecodeError::PayloadLengthMismatch { expected, actual } => write!(
+                f,
+                "Message payload has {} bytes instead of {}",
+                actual, expected
+            ),
+            DecodeError::InvalidChecksum => f.write_str("Invalid message checksum"),
+            DecodeError::InvalidCompression => f.write_str("Invalid compressed payload"),
+            DecodeError::UnknownCommand(ref command) => {
+                write!(f, "Unknown message command {}", command)
+            }
+            DecodeError::UnsupportedVersion(ref command) => write!(
+                f,
+                "Message {} is not supported by protocol version",
+                command
+            ),
+            DecodeError::MalformedPayload(ref command) => {
+                write!(f, "Malformed {} message payload", command)
+            }
+            DecodeError::TrailingBytes(ref command) => {
+                write!(f, "Trailing bytes after {} message payload", command)
+            }
+        }
+    }
+}
+
+impl error::Error for DecodeError {
+    fn description(&self) -> &str {
+        "Message decoding error"
+    }
+}
+
+impl MessageDecoder {
+    /// Strict decoder, accepting messages of all protocol versions
+    pub fn new(magic: Magic) -> Self {
+        MessageDecoder {
+            magic: magic,
+            version: u32::max_value(),
+            compression: Compression::None,
+            max_payload_len: MAX_PAYLOAD_LEN,
+            lenient: false,
+        }
+    }
+
+    /// Reject messages, which are not supported by given protocol version
+    pub fn with_version(mut self, version: u32) -> Self {
+        self.version = version;
+        self
+    }
+
+    /// Decompress payloads with given compression
+    pub fn with_compression(mut self, compression: Compression) -> Self {
+        self.compression = compression;
+        self
+    }
+
+    /// Reject payloads, which are larger than given number of bytes
+    pub fn with_max_payload_len(mut self, max_payload_len: usize) -> Self {
+        self.max_payload_len = max_payload_len;
+        self
+    }
+
+    /// Accept messages with unknown commands and payloads with trailing bytes
+    pub fn lenient(mut self, lenient: bool) -> Self {
+        self.lenient = lenient;
+        self
+    }
+
+    /// Decode and check message header
+    pub fn decode_header(&self, data: &[u8]) -> Result<MessageHeader, DecodeError> {
+        if data.len() < MESSAGE_HEADER_LEN {
+            return Err(DecodeError::TruncatedHeader);
+        }
+
+        let header =
+            MessageHeader::deserialize(&data[..MESSAGE_HEADER_LEN], self.magic).map_err(|err| {
+                match err {
+                    Error::InvalidMagic => DecodeError::InvalidMagic,
+                    _ => DecodeError::TruncatedHeader,
+                }
+            })?;
+        if !header.command.is_valid() {
+            return Err(DecodeError::InvalidCommand);
+        }
+        if header.len as usize > self.max_payload_len {
+            return Err(DecodeError::PayloadTooLarge(header.len));
+        }
+
+        Ok(header)
+    }
+
+    /// Check received payload of the message with given header
+    pub fn check_payload(&self, header: &MessageHeader, payload: &[u8]) -> Result<(), DecodeError> {
+        if payload.len() != header.len as usize {
+            return Err(DecodeError::PayloadLengthMismatch {
+                expected: header.len,
+                actual: payload.len(),
+            });
+        }
+        if checksum(payload) != header.checksum {
+            return Err(DecodeError::InvalidChecksum);
+        }
+
+        Ok(())
+    }
+
+    /// Decompress checked payload of the message with given command
+    pub fn decompress(&self, command: &Command, payload: Bytes) -> Result<Bytes, DecodeError> {
+        self.compression
+            .decompress(command, payload)
+            .map_err(|_| DecodeError::InvalidCompression)
+    }
+
+    /// Decompress and decode checked payload of the message with given command
+    pub fn decode_payload(
+        &self,
+        command: &Command,
+        payload: Bytes,
+    ) -> Result<DecodedMessage, DecodeError> {
+        let payload = self.decompress(command, payload)?;
+
+        let message = if *command == types::Version::command() {
+            DecodedMessage::Version(self.read_payload(&payload)?)
+        } else if *command == types::Verack::command() {
+            DecodedMessage::Verack(self.read_payload(&payload)?)
+        } else if *command == types::Addr::command() {
+            DecodedMessage::Addr(self.read_payload(&payload)?)
+        } else if *command == types::GetAddr::command() {
+            DecodedMessage::GetAddr(self.read_payload(&payload)?)
+        } else if *command == types::Ping::command() {
+            DecodedMessage::Ping(self.read_payload(&payload)?)
+        } else if *command == types::Pong::command() {
+            DecodedMessage::Pong(self.read_payload(&payload)?)
+        } else if *command == types::Reject::command() {
+            DecodedMessage::Reject(self.read_payload(&payload)?)
+        } else if *command == types::Inv::command() {
+            DecodedMessage::Inv(self.read_payload(&payload)?)
+        } else if *command == types::GetData::command() {
+            DecodedMessage::GetData(self.read_payload(&payload)?)
+        } else if *command == types::GetBlocks::command() {
+            DecodedMessage::GetBlocks(self.read_payload(&payload)?)
+        } else if *command == types::GetHeaders::command() {
+            DecodedMessage::GetHeaders(self.read_payload(&payload)?)
+        } else if *command == types::Headers::command() {
+            DecodedMessage::Headers(self.read_payload(&payload)?)
+        } else if *command == types::Headers2::command() {
+            DecodedMessage::Headers2(self.read_payload(&payload)?)
+        } else if *command == types::Block::command() {
+            DecodedMessage::Block(self.read_payload(&payload)?)
+        } else if *command == types::NotFound::command() {
+            DecodedMessage::NotFound(self.read_payload(&payload)?)
+        } else if *command == types::SendHeaders::command() {
+            DecodedMessage::SendHeaders(self.read_payload(&payload)?)
+        } else if self.lenient {
+            DecodedMessage::Unknown(command.clone(), payload)
+        } else {
+            return Err(DecodeError::UnknownCommand(command.clone()));
+        };
+
+        Ok(message)
+    }
+
+    /// Decode the whole message: header, followed by the payload
+    pub fn decode(&self, data: &[u8]) -> Result<DecodedMessage, DecodeError> {
+        let header = self.decode_header(data)?;
+        let payload = &data[MESSAGE_HEADER_LEN..];
+        self.check_payload(&header, payload)?;
+        self.decode_payload(&header.command, payload.to_vec().into())
+    }
+
+    /// Decode decompressed payload of the message of type T
+    pub fn read_payload<T: Payload>(&self, payload: &[u8]) -> Result<T, DecodeError> {
+        let mut reader = PayloadReader::new(payload, self.version);
+        let message = reader.read().map_err(|err| match err {
+            Error::InvalidVersion => DecodeError::UnsupportedVersion(T::command().into()),
+            _ => DecodeError::MalformedPayload(T::command().into()),
+        })?;
+        if !self.lenient && !reader.is_finished() {
+            return Err(DecodeError::TrailingBytes(T::command().into()));
+        }
+
+        Ok(message)
+    }
+}
+
+/// Decode raw message of the given network with strict checks and default limits
+pub fn decode_message(data: &[u8], network: Network) -> Result<DecodedMessage, DecodeError> {
+    MessageDecoder::new(network.magic()).decode(data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{decode_message, DecodeError, DecodedMessage, MessageDecoder};
+    use bytes::Bytes;
+    use common::Command;
+    use network::Network;
+    use types;
+    use {to_raw_message, Error, Message};
+
+    fn ping_message() -> Bytes {
+        "f9beb4d970696e6700000000000000000800000083c00c765845303b6da97786".into()
+    }
+
+    #[test]
+    fn valid_messages_are_decoded() {
+        assert_eq!(
+            decode_message(&ping_message(), Network::Mainnet),
+            Ok(DecodedMessage::Ping(types::Ping::new(0x8677a96d3b304558)))
+        );
+
+        let getaddr: Bytes = Message::new(Network::Mainnet.magic(), 70001, &types::GetAddr)
+            .unwrap()
+            .into();
+        assert_eq!(
+            decode_message(&getaddr, Network::Mainnet),
+            Ok(DecodedMessage::GetAddr(types::GetAddr))
+        );
+    }
+
+    #[test]
+    fn malformed_messages_are_rejected_with_reason() {
+        let ping = ping_message();
+        assert_eq!(
+            decode_message(&ping[..20], Network::Mainnet),
+            Err(DecodeError::TruncatedHeader)
+        );
+        assert_eq!(
+            decode_message(&ping, Network::Testnet),
+            Err(DecodeError::InvalidMagic)
+        );
+        assert_eq!(
+            decode_message(&ping[..30], Network::Mainnet),
+            Err(DecodeError::PayloadLengthMismatch {
+                expected: 8,
+                actual: 6
+            })
+        );
+
+        let mut corrupted = ping.to_vec();
+        corrupted[24] ^= 1;
+        assert_eq!(
+            decode_message(&corrupted, Network::Mainnet),
+            Err(DecodeError::InvalidChecksum)
+        );
+        let mut corrupted = ping.to_vec();
+        corrupted[5] = 0;
+        assert_eq!(
+            decode_message(&corrupted, Network::Mainnet),
+            Err(DecodeError::InvalidCommand)
+        );
+
+        assert_eq!(
+            MessageDecoder::new(Network::Mainnet.magic())
+                .with_max_payload_len(4)
+                .decode(&ping),
+            Err(DecodeError::PayloadTooLarge(8))
+        );
+
+        let magic = Network::Mainnet.magic();
+        let short_ping = to_raw_message(magic, "ping".into(), &"5845303b".into());
+        assert_eq!(
+            decode_message(&short_ping, Network::Mainnet),
+            Err(DecodeError::MalformedPayload("ping".into()))
+        );
+        assert!(!DecodeError::MalformedPayload("ping".into()).is_fatal());
+        assert!(DecodeError::InvalidCommand.is_fatal());
+        assert!(DecodeError::TruncatedHeader.is_fatal());
+        assert!(!Error::from(DecodeError::TruncatedHeader).is_recoverable());
+    }
+
+    #[test]
+    fn lenient_decoder_accepts_unknown_commands_and_trailing_bytes() {
+        let magic = Network::Mainnet.magic();
+        let unknown_command: Command = "feefilter".into();
+        let unknown = to_raw_message(magic, unknown_command.clone(), &"0100".into());
+        let long_ping = to_raw_message(magic, "ping".into(), &"5845303b6da9778601".into());
+
+        assert_eq!(
+            decode_message(&unknown, Network::Mainnet),
+            Err(DecodeError::UnknownCommand(unknown_command.clone()))
+        );
+        assert_eq!(
+            decode_message(&long_ping, Network::Mainnet),
+            Err(DecodeError::TrailingBytes("ping".into()))
+        );
+
+        let lenient = MessageDecoder::new(magic).lenient(true);
+        assert_eq!(
+            lenient.decode(&unknown),
+            Ok(DecodedMessage::Unknown(unknown_command, "0100".into()))
+        );
+        assert_eq!(
+            lenient.decode(&long_ping),
+            Ok(DecodedMessage::Ping(types::Ping::new(0x8677a96d3b304558)))
+        );
+    }
+
+    #[test]
+    fn unsupported_messages_are_rejected() {
+        let magic = Network::Mainnet.magic();
+        let sendheaders: Bytes = Message::new(magic, 70012, &types::SendHeaders)
+            .unwrap()
+            .into();
+        assert_eq!(
+            MessageDecoder::new(magic)
+                .with_version(70001)
+                .decode(&sendheaders),
+            Err(DecodeError::UnsupportedVersion("sendheaders".into()))
+        );
+    }
+}
@@ -15,6 +15,30 @@ pub enum Error {
     InvalidChecksum,
     /// Invalid version.
     InvalidVersion,
+    /// Compressed payload is malformed or too large.
+    InvalidCompression,
+    /// Payload is larger than the max allowed message size.
+    PayloadTooLarge,
+    /// Message header is truncated or does not match the payload.
+    MalformedHeader,
+}
+
+impl Error {
+    /// Is the error caused by the single malformed message? The stream of messages is still
+    /// framed properly after such error, so the message could be skipped. When header is
+    /// malformed, the next message can't be located, so the connection must be closed.
+    pub fn is_recoverable(&self) -> bool {
+        match *self {
+            Error::Deserialize
+            | Error::InvalidChecksum
+            | Error::InvalidVersion
+            | Error::InvalidCompression => true,
+            Error::InvalidCommand
+            | Error::InvalidMagic
+            | Error::PayloadTooLarge
+            | Error::MalformedHeader => false,
+        }
+    }
 }
 
 impl From<ReaderError> for Error {
@@ -37,6 +61,9 @@ impl error::Error for Error {
             Error::InvalidMagic => "Invalid Network Magic",
             Error::InvalidChecksum => "Invalid message chacksum",
             Error::InvalidVersion => "Unsupported protocol version",
+            Error::InvalidCompression => "Invalid compressed payload",
+            Error::PayloadTooLarge => "Message payload is too large",
+            Error::MalformedHeader => "Malformed message header",
         }
     }
 }
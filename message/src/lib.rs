@@ -1,13 +1,16 @@
 extern crate bitcrypto as crypto;
 extern crate byteorder;
 extern crate chain;
+extern crate ecvrf;
 extern crate primitives;
 extern crate serialization as ser;
+extern crate snap;
 #[macro_use]
 extern crate serialization_derive;
 extern crate network;
 
 pub mod common;
+mod decoder;
 mod error;
 mod message;
 mod serialization;
@@ -16,6 +19,10 @@ pub mod types;
 pub use primitives::{bytes, hash};
 
 pub use common::{Command, Services};
+pub use decoder::{
+    decode_message, DecodeError, DecodedMessage, MessageDecoder, MAX_PAYLOAD_LEN,
+    MESSAGE_HEADER_LEN,
+};
 pub use error::{Error, MessageResult};
-pub use message::{to_raw_message, Message, MessageHeader, Payload};
+pub use message::{to_raw_message, Compression, Message, MessageHeader, Payload, SharedPayload};
 pub use serialization::{deserialize_payload, serialize_payload};
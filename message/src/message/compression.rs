@@ -0,0 +1,119 @@
+use bytes::Bytes;
+use common::{Command, Services};
+use snap;
+use {Error, MessageResult};
+
+/// Max size of the decompressed payload.
+pub const MAX_DECOMPRESSED_PAYLOAD_LEN: usize = 32 * 1024 * 1024;
+/// Commands, which payloads are compressed when compression is negotiated.
+const COMPRESSED_COMMANDS: [&'static str; 3] = ["block", "headers", "headers2"];
+
+/// Compression of large messages payloads, negotiated during the version handshake.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Compression {
+    /// Payloads are sent as is.
+    None,
+    /// Payloads of large messages are compressed with snappy.
+    Snappy,
+}
+
+impl Default for Compression {
+    fn default() -> Self {
+        Compression::None
+    }
+}
+
+impl Compression {
+    /// Selects compression, supported by both sides of the connection.
+    pub fn negotiate(local: Services, remote: Services) -> Self {
+        if local.snappy_compression() && remote.snappy_compression() {
+            Compression::Snappy
+        } else {
+            Compression::None
+        }
+    }
+
+    /// Is payload of the message with given command compressed?
+    pub fn is_applied_to(&self, command: &Command) -> bool {
+        match *self {
+            Compression::None => false,
+            Compression::Snappy => COMPRESSED_COMMANDS.iter().any(|c| command == c),
+        }
+    }
+
+    /// Compresses serialized payload of the message with given command.
+    pub fn compress(&self, command: &Command, payload: Bytes) -> Bytes {
+        if !self.is_applied_to(command) {
+            return payload;
+        }
+
+        snap::Encoder::new()
+            .compress_vec(&payload)
+            .expect("payload is always less than max snappy input size")
+            .into()
+    }
+
+    /// Decompresses payload of the received message with given command.
+    pub fn decompress(&self, command: &Command, payload: Bytes) -> MessageResult<Bytes> {
+        if !self.is_applied_to(command) {
+            return Ok(payload);
+        }
+
+        // do not allocate huge buffers for malicious payloads
+        let len = snap::decompress_len(&payload).map_err(|_| Error::InvalidCompression)?;
+        if len > MAX_DECOMPRESSED_PAYLOAD_LEN {
+            return Err(Error::InvalidCompression);
+        }
+
+        snap::Decoder::new()
+            .decompress_vec(&payload)
+            .map(Into::into)
+            .map_err(|_| Error::InvalidCompression)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Compression;
+    use bytes::Bytes;
+    use common::Services;
+    use Error;
+
+    #[test]
+    fn compression_is_negotiated_when_both_sides_support_it() {
+        let plain = Services::default();
+        let snappy = Services::default().with_snappy_compression(true);
+        assert_eq!(Compression::negotiate(plain, snappy), Compression::None);
+        assert_eq!(Compression::negotiate(snappy, plain), Compression::None);
+        assert_eq!(Compression::negotiate(snappy, snappy), Compression::Snappy);
+    }
+
+    #[test]
+    fn compression_is_applied_to_large_messages_only() {
+        let payload: Bytes = vec![0u8; 1024].into();
+
+        let compressed = Compression::Snappy.compress(&"block".into(), payload.clone());
+        assert!(compressed.len() < payload.len());
+        assert_eq!(
+            Compression::Snappy.decompress(&"block".into(), compressed),
+            Ok(payload.clone())
+        );
+
+        assert_eq!(
+            Compression::Snappy.compress(&"ping".into(), payload.clone()),
+            payload
+        );
+        assert_eq!(
+            Compression::None.compress(&"block".into(), payload.clone()),
+            payload
+        );
+    }
+
+    #[test]
+    fn invalid_compressed_payload_is_rejected() {
+        assert_eq!(
+            Compression::Snappy.decompress(&"headers".into(), vec![0xffu8; 16].into()),
+            Err(Error::InvalidCompression)
+        );
+    }
+}
@@ -1,5 +1,6 @@
 use bytes::{Bytes, TaggedBytes};
 use common::Command;
+use message::Compression;
 use network::Magic;
 use ser::Stream;
 use serialization::serialize_payload;
@@ -15,6 +16,8 @@ pub fn to_raw_message(magic: Magic, command: Command, payload: &Bytes) -> Bytes
 
 pub struct Message<T> {
     bytes: TaggedBytes<T>,
+    /// Number of bytes, saved by payload compression.
+    compression_saving: usize,
 }
 
 impl<T> Message<T>
@@ -22,10 +25,23 @@ where
     T: Payload,
 {
     pub fn new(magic: Magic, version: u32, payload: &T) -> MessageResult<Self> {
+        Message::with_compression(magic, version, Compression::None, payload)
+    }
+
+    pub fn with_compression(
+        magic: Magic,
+        version: u32,
+        compression: Compression,
+        payload: &T,
+    ) -> MessageResult<Self> {
+        let command: Command = T::command().into();
         let serialized = serialize_payload(payload, version)?;
+        let serialized_len = serialized.len();
+        let serialized = compression.compress(&command, serialized);
 
         let message = Message {
-            bytes: TaggedBytes::new(to_raw_message(magic, T::command().into(), &serialized)),
+            compression_saving: serialized_len.saturating_sub(serialized.len()),
+            bytes: TaggedBytes::new(to_raw_message(magic, command, &serialized)),
         };
 
         Ok(message)
@@ -34,6 +50,11 @@ where
     pub fn len(&self) -> usize {
         self.bytes.len()
     }
+
+    /// Number of bytes, saved by payload compression.
+    pub fn compression_saving(&self) -> usize {
+        self.compression_saving
+    }
 }
 
 impl<T> AsRef<[u8]> for Message<T> {
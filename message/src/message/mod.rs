@@ -1,7 +1,11 @@
+mod compression;
 mod message;
 mod message_header;
 pub mod payload;
+mod shared_payload;
 
+pub use self::compression::Compression;
 pub use self::message::{to_raw_message, Message};
 pub use self::message_header::MessageHeader;
 pub use self::payload::Payload;
+pub use self::shared_payload::SharedPayload;
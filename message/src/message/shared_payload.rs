@@ -0,0 +1,92 @@
+use bytes::Bytes;
+use common::Command;
+use crypto::checksum;
+use hash::H32;
+use message::{to_raw_message, Compression};
+use network::Magic;
+use ser::Stream;
+use serialization::serialize_payload;
+use std::sync::Arc;
+use {MessageHeader, MessageResult, Payload};
+
+/// Message payload, which is serialized once and shared by all peers it is sent to
+/// (e.g. recent block, requested by several peers). Only payloads, which serialization
+/// doesn't depend on the protocol version, could be shared.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SharedPayload {
+    command: Command,
+    payload: Arc<Bytes>,
+    checksum: H32,
+}
+
+impl SharedPayload {
+    pub fn new<T>(payload: &T) -> MessageResult<Self>
+    where
+        T: Payload,
+    {
+        let payload = serialize_payload(payload, T::version())?;
+        Ok(SharedPayload {
+            command: T::command().into(),
+            checksum: checksum(&payload),
+            payload: Arc::new(payload),
+        })
+    }
+
+    pub fn command(&self) -> &Command {
+        &self.command
+    }
+
+    /// Serialized payload.
+    pub fn payload(&self) -> &Arc<Bytes> {
+        &self.payload
+    }
+
+    /// Size of serialized payload.
+    pub fn size(&self) -> usize {
+        self.payload.len()
+    }
+
+    /// Serialized header of the uncompressed message with this payload.
+    pub fn header(&self, magic: Magic) -> Bytes {
+        let header = MessageHeader {
+            magic: magic,
+            command: self.command.clone(),
+            len: self.payload.len() as u32,
+            checksum: self.checksum.clone(),
+        };
+        let mut stream = Stream::default();
+        stream.append(&header);
+        stream.out()
+    }
+
+    /// Whole message with compressed payload. Compressed message can't be shared, so
+    /// it is only built for peers, which have negotiated compression.
+    pub fn compressed_message(&self, magic: Magic, compression: Compression) -> Bytes {
+        let compressed = compression.compress(&self.command, (*self.payload).clone());
+        to_raw_message(magic, self.command.clone(), &compressed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SharedPayload;
+    use bytes::Bytes;
+    use message::{Compression, Message};
+    use network::Network;
+    use types::Ping;
+
+    #[test]
+    fn shared_payload_message_is_the_same_as_regular_message() {
+        let magic = Network::Mainnet.magic();
+        let ping = Ping::new(42);
+        let shared = SharedPayload::new(&ping).unwrap();
+        let message: Bytes = Message::new(magic, 0, &ping).unwrap().into();
+
+        assert_eq!(shared.size(), 8);
+        assert_eq!(shared.compressed_message(magic, Compression::None), message);
+
+        let mut shared_message = shared.header(magic).take();
+        shared_message.extend_from_slice(&shared.payload()[..]);
+        assert_eq!(shared_message, message.take());
+    }
+}
@@ -1,5 +1,5 @@
 mod reader;
 mod stream;
 
-pub use self::reader::deserialize_payload;
+pub use self::reader::{deserialize_payload, PayloadReader};
 pub use self::stream::serialize_payload;
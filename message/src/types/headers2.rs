@@ -0,0 +1,271 @@
+use chain::BlockHeader;
+use crypto::dhash256;
+use ecvrf::VrfPk;
+use hash::H256;
+use ser::{serialize, CompactInteger, Error as ReaderError, Reader, Stream};
+use std::io;
+use types::Headers;
+use {MessageResult, Payload};
+
+/// Version of the header is the same as the version of the previous header.
+const SAME_VERSION: u8 = 1 << 0;
+/// Difficulty of the header is the same as the difficulty of the previous header.
+const SAME_BITS: u8 = 1 << 1;
+/// Miner public key of the header is the same as the key of the previous header.
+const SAME_PUBKEY: u8 = 1 << 2;
+/// All known flags.
+const ALL_FLAGS: u8 = SAME_VERSION | SAME_BITS | SAME_PUBKEY;
+
+/// Chain of headers, where every header (except the first one) omits fields, which could be
+/// derived from the previous header. Previous header hash is never sent, time is sent as a
+/// delta, and version, bits and pubkey are sent only when they change. Sent instead of
+/// `headers`, if both peers have advertised compressed headers support.
+#[derive(Debug, PartialEq)]
+pub struct Headers2 {
+    pub headers: Vec<BlockHeader>,
+}
+
+impl Headers2 {
+    pub fn with_headers(headers: Vec<BlockHeader>) -> Self {
+        Headers2 { headers: headers }
+    }
+}
+
+impl From<Headers> for Headers2 {
+    fn from(headers: Headers) -> Self {
+        Headers2::with_headers(headers.headers)
+    }
+}
+
+impl From<Headers2> for Headers {
+    fn from(headers: Headers2) -> Self {
+        Headers::with_headers(headers.headers)
+    }
+}
+
+/// Maps signed delta to unsigned value, so that small negative deltas are encoded with one byte.
+fn zigzag_encode(delta: i64) -> u64 {
+    ((delta << 1) ^ (delta >> 63)) as u64
+}
+
+fn zigzag_decode(value: u64) -> i64 {
+    ((value >> 1) as i64) ^ -((value & 1) as i64)
+}
+
+fn write_header(stream: &mut Stream, header: &BlockHeader, previous: Option<&BlockHeader>) {
+    let mut flags = 0u8;
+    if let Some(previous) = previous {
+        if header.version == previous.version {
+            flags |= SAME_VERSION;
+        }
+        if header.bits == previous.bits {
+            flags |= SAME_BITS;
+        }
+        if header.pubkey == previous.pubkey {
+            flags |= SAME_PUBKEY;
+        }
+    }
+
+    stream.append(&flags);
+    if flags & SAME_VERSION == 0 {
+        stream.append(&header.version);
+    }
+    match previous {
+        Some(previous) => {
+            let delta = i64::from(header.time) - i64::from(previous.time);
+            stream.append(&CompactInteger::from(zigzag_encode(delta)));
+        }
+        None => {
+            stream
+                .append(&header.previous_header_hash)
+                .append(&header.time);
+        }
+    }
+    if flags & SAME_BITS == 0 {
+        stream.append(&header.bits);
+    }
+    if flags & SAME_PUBKEY == 0 {
+        stream.append_slice(&header.pubkey.to_bytes());
+    }
+    stream
+        .append(&CompactInteger::from(header.iterations))
+        .append(&header.randomness);
+}
+
+fn read_header<T>(
+    reader: &mut Reader<T>,
+    previous: Option<&BlockHeader>,
+) -> Result<BlockHeader, ReaderError>
+where
+    T: io::Read,
+{
+    let flags: u8 = reader.read()?;
+    if flags & !ALL_FLAGS != 0 || (previous.is_none() && flags != 0) {
+        return Err(ReaderError::MalformedData);
+    }
+
+    let version = match previous {
+        Some(previous) if flags & SAME_VERSION != 0 => previous.version,
+        _ => reader.read()?,
+    };
+    let (previous_header_hash, time) = match previous {
+        Some(previous) => {
+            let delta: CompactInteger = reader.read()?;
+            let time = i64::from(previous.time) + zigzag_decode(u64::from(delta));
+            if time < 0 || time > i64::from(u32::max_value()) {
+                return Err(ReaderError::MalformedData);
+            }
+            (dhash256(&serialize(previous)), time as u32)
+        }
+        None => {
+            let previous_header_hash: H256 = reader.read()?;
+            (previous_header_hash, reader.read()?)
+        }
+    };
+    let bits = match previous {
+        Some(previous) if flags & SAME_BITS != 0 => previous.bits,
+        _ => reader.read()?,
+    };
+    let pubkey = match previous {
+        Some(previous) if flags & SAME_PUBKEY != 0 => previous.pubkey.clone(),
+        _ => {
+            let mut pubkey = [0u8; 32];
+            reader.read_slice(&mut pubkey)?;
+            VrfPk::from_bytes(&pubkey).map_err(|_| ReaderError::MalformedData)?
+        }
+    };
+    let iterations: CompactInteger = reader.read()?;
+    let iterations = u64::from(iterations);
+    if iterations > u64::from(u32::max_value()) {
+        return Err(ReaderError::MalformedData);
+    }
+
+    Ok(BlockHeader {
+        version: version,
+        previous_header_hash: previous_header_hash,
+        time: time,
+        bits: bits,
+        pubkey: pubkey,
+        iterations: iterations as u32,
+        randomness: reader.read()?,
+    })
+}
+
+impl Payload for Headers2 {
+    fn version() -> u32 {
+        0
+    }
+
+    fn command() -> &'static str {
+        "headers2"
+    }
+
+    fn deserialize_payload<T>(reader: &mut Reader<T>, _version: u32) -> MessageResult<Self>
+    where
+        T: io::Read,
+    {
+        let len: usize = reader.read::<CompactInteger>()?.into();
+        // do not preallocate: the length is not trusted
+        let mut headers: Vec<BlockHeader> = Vec::new();
+        for _ in 0..len {
+            let header = read_header(reader, headers.last())?;
+            headers.push(header);
+        }
+
+        Ok(Headers2 { headers: headers })
+    }
+
+    fn serialize_payload(&self, stream: &mut Stream, _version: u32) -> MessageResult<()> {
+        stream.append(&CompactInteger::from(self.headers.len()));
+        let mut previous = None;
+        for header in &self.headers {
+            write_header(stream, header, previous);
+            previous = Some(header);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{zigzag_decode, zigzag_encode, Headers2};
+    use chain::BlockHeader;
+    use crypto::dhash256;
+    use ecvrf::VrfPk;
+    use ser::serialize;
+    use serialization::{deserialize_payload, serialize_payload};
+    use types::Headers;
+
+    fn header(previous: &BlockHeader, time: u32, key: u8) -> BlockHeader {
+        BlockHeader {
+            version: previous.version,
+            previous_header_hash: dhash256(&serialize(previous)),
+            time: time,
+            bits: previous.bits,
+            pubkey: VrfPk::from_bytes(&[key; 32]).unwrap(),
+            iterations: previous.iterations + 1,
+            randomness: previous.randomness.clone() * 3,
+        }
+    }
+
+    fn headers() -> Vec<BlockHeader> {
+        let first = BlockHeader {
+            version: 1,
+            previous_header_hash: [2; 32].into(),
+            time: 1_000_000,
+            bits: 5.into(),
+            pubkey: VrfPk::from_bytes(&[6; 32]).unwrap(),
+            iterations: 1000,
+            randomness: 8.into(),
+        };
+        let second = header(&first, 1_000_600, 6);
+        // timestamps are not monotonic
+        let third = header(&second, 1_000_590, 7);
+        let mut fourth = header(&third, 1_001_200, 7);
+        fourth.version = 2;
+        fourth.bits = 4.into();
+        vec![first, second, third, fourth]
+    }
+
+    #[test]
+    fn zigzag_roundtrip() {
+        for delta in &[0i64, 1, -1, 600, -600, i64::from(u32::max_value())] {
+            assert_eq!(zigzag_decode(zigzag_encode(*delta)), *delta);
+        }
+        assert_eq!(zigzag_encode(-1), 1);
+        assert_eq!(zigzag_encode(1), 2);
+    }
+
+    #[test]
+    fn headers2_roundtrip() {
+        let message = Headers2::with_headers(headers());
+        let serialized = serialize_payload(&message, 0).unwrap();
+        let deserialized: Headers2 = deserialize_payload(&serialized, 0).unwrap();
+        assert_eq!(deserialized, message);
+
+        let empty = Headers2::with_headers(Vec::new());
+        let serialized = serialize_payload(&empty, 0).unwrap();
+        assert_eq!(
+            deserialize_payload::<Headers2>(&serialized, 0).unwrap(),
+            empty
+        );
+    }
+
+    #[test]
+    fn headers2_is_smaller_than_headers() {
+        let headers = headers();
+        let compressed = serialize_payload(&Headers2::with_headers(headers.clone()), 0).unwrap();
+        let uncompressed = serialize_payload(&Headers::with_headers(headers), 0).unwrap();
+        assert!(compressed.len() * 2 < uncompressed.len());
+    }
+
+    #[test]
+    fn headers2_rejects_flags_of_first_header() {
+        let mut serialized: Vec<u8> = serialize_payload(&Headers2::with_headers(headers()), 0)
+            .unwrap()
+            .into();
+        // the first byte is the number of headers, then flags of the first header follow
+        serialized[1] = 1;
+        assert!(deserialize_payload::<Headers2>(&serialized, 0).is_err());
+    }
+}
@@ -5,6 +5,7 @@ mod getblocks;
 mod getdata;
 mod getheaders;
 mod headers;
+mod headers2;
 mod inv;
 mod notfound;
 mod ping;
@@ -21,6 +22,7 @@ pub use self::getblocks::{GetBlocks, GETBLOCKS_MAX_RESPONSE_HASHES};
 pub use self::getdata::{GetData, GETDATA_MAX_INVENTORY_LEN};
 pub use self::getheaders::{GetHeaders, GETHEADERS_MAX_RESPONSE_HEADERS};
 pub use self::headers::{Headers, HEADERS_MAX_HEADERS_LEN};
+pub use self::headers2::Headers2;
 pub use self::inv::{Inv, INV_MAX_INVENTORY_LEN};
 pub use self::notfound::NotFound;
 pub use self::ping::Ping;
@@ -72,6 +72,14 @@ impl Version {
         }
     }
 
+    pub fn timestamp(&self) -> i64 {
+        match *self {
+            Version::V0(ref s) | Version::V106(ref s, _) | Version::V70001(ref s, _, _) => {
+                s.timestamp
+            }
+        }
+    }
+
     pub fn nonce(&self) -> Option<u64> {
         match *self {
             Version::V0(_) => None,
@@ -1,19 +1,19 @@
 use std::time::{Duration, Instant};
 
 use block_assembler::BlockTemplate;
-use chain::BlockHeader;
+use chain::{Block, BlockHeader};
 use crypto::dhash256;
 use ecvrf::VrfPk;
+use network::VdfParams;
 use primitives::bytes::Bytes;
-use rug::{integer::Order, Integer};
+use rug::Integer;
 use ser::{serialize, Stream};
-use sha2::{Digest, Sha256};
-use verification::is_valid_proof_of_work_hash;
+use verification::{hash_to_group, is_valid_proof_of_work_hash};
 
 const STEP: u32 = 1024;
 
 // consistent with verification/src/verify_block.rs
-fn h_g(block: &BlockTemplate, pubkey: &VrfPk) -> Integer {
+pub(crate) fn h_g(block: &BlockTemplate, pubkey: &VrfPk, params: &VdfParams) -> Integer {
     let mut stream = Stream::default();
     stream
         .append(&block.version)
@@ -21,22 +21,7 @@ fn h_g(block: &BlockTemplate, pubkey: &VrfPk) -> Integer {
         .append(&block.time)
         .append(&block.bits)
         .append(&Bytes::from(pubkey.to_bytes().to_vec()));
-    let data = stream.out();
-    let seed = dhash256(&data);
-    let prefix = "residue_part_".as_bytes();
-    // concat 8 sha256 to a 2048-bit hash
-    let all_2048: Vec<u8> = (0..((2048 / 256) as u8))
-        .map(|index| {
-            let mut hasher = Sha256::new();
-            hasher.update(prefix);
-            hasher.update(vec![index]);
-            hasher.update(<[u8; 32]>::from(seed));
-            hasher.finalize()
-        })
-        .flatten()
-        .collect();
-    let result = Integer::from_digits(&all_2048, Order::Lsf);
-    result.div_rem_floor(vdf::MODULUS.clone()).1
+    hash_to_group(&stream.out(), params)
 }
 
 /// Cpu miner solution.
@@ -46,11 +31,17 @@ pub struct Solution {
     pub proof: vdf::Proof,
 }
 
-/// Simple randchain cpu miner.
-pub fn find_solution(block: &BlockTemplate, pubkey: &VrfPk, timeout: Duration) -> Option<Solution> {
+/// Simple randchain cpu miner. Solution is computed in the VDF group of the network
+/// and its number of iterations is within the network bounds.
+pub fn find_solution(
+    block: &BlockTemplate,
+    pubkey: &VrfPk,
+    params: &VdfParams,
+    timeout: Duration,
+) -> Option<Solution> {
     let start_time = Instant::now();
 
-    let g = h_g(block, pubkey);
+    let g = h_g(block, pubkey, params);
     let mut cur_y = g.clone();
     let mut iterations = 0u64;
     loop {
@@ -59,11 +50,16 @@ pub fn find_solution(block: &BlockTemplate, pubkey: &VrfPk, timeout: Duration) -
         }
 
         iterations += STEP as u64;
-        if iterations > (u32::max_value() as u64) {
+        if iterations > params.max_iterations as u64 {
             return None;
         }
 
-        let new_y = vdf::eval(&cur_y, STEP);
+        let new_y = vdf::eval_with_modulus(&cur_y, STEP, &params.modulus);
+        if iterations < params.min_iterations as u64 {
+            cur_y = new_y;
+            continue;
+        }
+
         // consistent with chain/src/block_header.rs
         let block_header_hash = dhash256(&serialize(&BlockHeader {
             version: block.version,
@@ -78,7 +74,7 @@ pub fn find_solution(block: &BlockTemplate, pubkey: &VrfPk, timeout: Duration) -
             let solution = Solution {
                 iterations: iterations as u32,
                 randomness: new_y.clone(),
-                proof: vdf::prove(&g, &new_y, iterations as u32),
+                proof: vdf::prove_with_modulus(&g, &new_y, iterations as u32, &params.modulus),
             };
 
             return Some(solution);
@@ -88,13 +84,39 @@ pub fn find_solution(block: &BlockTemplate, pubkey: &VrfPk, timeout: Duration) -
     }
 }
 
+/// Mines block on top of the template. Returns None if block isn't mined within timeout.
+pub fn mine_block(
+    block: &BlockTemplate,
+    pubkey: &VrfPk,
+    params: &VdfParams,
+    timeout: Duration,
+) -> Option<Block> {
+    let solution = find_solution(block, pubkey, params, timeout)?;
+    Some(Block {
+        block_header: BlockHeader {
+            version: block.version,
+            previous_header_hash: block.previous_header_hash.clone(),
+            time: block.time,
+            bits: block.bits,
+            pubkey: pubkey.clone(),
+            iterations: solution.iterations,
+            randomness: solution.randomness,
+        },
+        proof: solution.proof,
+    })
+}
+
 #[cfg(test)]
 mod tests {
-    use super::find_solution;
+    use super::{find_solution, h_g};
     use block_assembler::BlockTemplate;
+    use chain::{Block, BlockHeader, IndexedBlock};
     use ecvrf::VrfPk;
+    use network::{Network, VdfParams};
     use primitives::bigint::{Uint, U256};
+    use rug::Integer;
     use std::time::Duration;
+    use verification::h_g_with_params;
 
     #[test]
     fn test_cpu_miner_low_difficulty() {
@@ -108,7 +130,60 @@ mod tests {
 
         // generate or load key
         let pubkey: VrfPk = VrfPk::from_bytes(&[0; 32]).unwrap();
-        let solution = find_solution(&block_template, &pubkey, Duration::from_secs(0));
+        let solution = find_solution(
+            &block_template,
+            &pubkey,
+            Network::Unitest.vdf_params(),
+            Duration::from_secs(0),
+        );
         assert!(solution.is_some());
     }
+
+    #[test]
+    fn test_cpu_miner_uses_network_vdf_params() {
+        let block_template = BlockTemplate {
+            version: 0,
+            previous_header_hash: 0.into(),
+            time: 0,
+            bits: U256::max_value().into(),
+            height: 0,
+        };
+        let pubkey: VrfPk = VrfPk::from_bytes(&[0; 32]).unwrap();
+        let params = VdfParams {
+            modulus: Integer::from(1_000_003),
+            element_bits: 256,
+            min_iterations: 3000,
+            max_iterations: 4096,
+        };
+
+        // block is verified with the same group element
+        let block: IndexedBlock = Block {
+            block_header: BlockHeader {
+                version: block_template.version,
+                previous_header_hash: block_template.previous_header_hash.clone(),
+                time: block_template.time,
+                bits: block_template.bits,
+                pubkey: pubkey.clone(),
+                iterations: 0,
+                randomness: Integer::from(0),
+            },
+            proof: Vec::new(),
+        }
+        .into();
+        assert_eq!(
+            h_g(&block_template, &pubkey, &params),
+            h_g_with_params(&block, &params)
+        );
+
+        let solution = find_solution(&block_template, &pubkey, &params, Duration::from_secs(0))
+            .expect("block is mined with the trivial difficulty");
+        assert!(params.is_valid_iterations(solution.iterations));
+        assert!(solution.randomness < params.modulus);
+
+        let params = VdfParams {
+            max_iterations: 2000,
+            ..params
+        };
+        assert!(find_solution(&block_template, &pubkey, &params, Duration::from_secs(0)).is_none());
+    }
 }
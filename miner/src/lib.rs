@@ -1,8 +1,8 @@
 extern crate bigint;
 extern crate ecvrf;
 extern crate heapsize;
+extern crate parking_lot;
 extern crate rug;
-extern crate sha2;
 
 extern crate bitcrypto as crypto;
 extern crate chain;
@@ -17,8 +17,11 @@ extern crate verification;
 
 mod block_assembler;
 mod cpu_miner;
+mod mining_control;
 pub mod mock;
 
 pub use block_assembler::{BlockAssembler, BlockTemplate};
-pub use cpu_miner::{find_solution, Solution};
+pub use cpu_miner::{find_solution, mine_block, Solution};
+pub use ecvrf::VrfPk;
+pub use mining_control::{MiningControl, MiningCounters, MiningStats};
 pub use primitives::hash;
@@ -0,0 +1,272 @@
+use parking_lot::{Condvar, Mutex};
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Iterations rate is averaged over this period.
+const RATE_WINDOW_S: u64 = 60;
+
+/// Mining threads, which could be paused, resumed and resized at runtime.
+/// Threads, which are not needed anymore, are parked until they're needed again.
+#[derive(Clone)]
+pub struct MiningControl {
+    inner: Arc<MiningControlInner>,
+}
+
+struct MiningControlInner {
+    state: Mutex<MiningState>,
+    changed: Condvar,
+    counters: MiningCounters,
+    job: Box<dyn Fn(&MiningCounters) + Send + Sync>,
+}
+
+/// Progress of the mining threads, reported by mining jobs.
+pub struct MiningCounters {
+    /// Time when mining has been started
+    started: Instant,
+    /// Iterations, done within the rate window. Ordered by time
+    iterations: Mutex<VecDeque<(Instant, u64)>>,
+    /// Height of the last block template, jobs have been mining on. Zero if there were no templates
+    template_height: AtomicU64,
+    /// Number of blocks, found by mining threads
+    blocks_found: AtomicU64,
+}
+
+/// Snapshot of the mining threads state.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MiningStats {
+    /// Number of threads, which are allowed to mine
+    pub threads: usize,
+    /// True if all threads are paused
+    pub paused: bool,
+    /// Iterations per second, achieved by all threads within the last minute
+    pub iterations_per_second: u64,
+    /// Height of the last block template. None if no templates have been mined yet
+    pub template_height: Option<u32>,
+    /// Number of blocks, found by mining threads
+    pub blocks_found: u64,
+}
+
+struct MiningState {
+    /// Number of threads, which are allowed to mine
+    threads: usize,
+    /// Number of threads, which have been spawned
+    spawned: usize,
+    /// True if all threads are paused
+    paused: bool,
+}
+
+impl MiningControl {
+    /// Starts given number of threads, each calling `job` repeatedly while mining is not paused.
+    pub fn start<F>(threads: usize, job: F) -> Self
+    where
+        F: Fn(&MiningCounters) + Send + Sync + 'static,
+    {
+        let control = MiningControl {
+            inner: Arc::new(MiningControlInner {
+                state: Mutex::new(MiningState {
+                    threads: 0,
+                    spawned: 0,
+                    paused: false,
+                }),
+                changed: Condvar::new(),
+                counters: MiningCounters::new(),
+                job: Box::new(job),
+            }),
+        };
+        control.set_threads(threads);
+        control
+    }
+
+    /// Number of threads, which are allowed to mine.
+    pub fn threads(&self) -> usize {
+        self.inner.state.lock().threads
+    }
+
+    /// Changes number of mining threads. New threads are spawned if required.
+    pub fn set_threads(&self, threads: usize) {
+        let mut state = self.inner.state.lock();
+        state.threads = threads;
+        for index in state.spawned..threads {
+            let inner = self.inner.clone();
+            thread::Builder::new()
+                .name(format!("Miner #{}", index))
+                .spawn(move || run(inner, index))
+                .expect("Error creating mining thread");
+        }
+        if threads > state.spawned {
+            state.spawned = threads;
+        }
+        self.inner.changed.notify_all();
+    }
+
+    /// Is mining paused?
+    pub fn is_paused(&self) -> bool {
+        self.inner.state.lock().paused
+    }
+
+    /// Pauses all mining threads. Jobs, which are running, are completed.
+    pub fn pause(&self) {
+        self.inner.state.lock().paused = true;
+    }
+
+    /// Resumes paused mining threads.
+    pub fn resume(&self) {
+        self.inner.state.lock().paused = false;
+        self.inner.changed.notify_all();
+    }
+
+    /// Current state and progress of mining threads.
+    pub fn stats(&self) -> MiningStats {
+        let (threads, paused) = {
+            let state = self.inner.state.lock();
+            (state.threads, state.paused)
+        };
+        let counters = &self.inner.counters;
+        MiningStats {
+            threads: threads,
+            paused: paused,
+            iterations_per_second: counters.iterations_per_second(Instant::now()),
+            template_height: match counters.template_height.load(Ordering::Relaxed) {
+                0 => None,
+                height => Some((height - 1) as u32),
+            },
+            blocks_found: counters.blocks_found.load(Ordering::Relaxed),
+        }
+    }
+}
+
+impl MiningCounters {
+    fn new() -> Self {
+        MiningCounters {
+            started: Instant::now(),
+            iterations: Mutex::new(VecDeque::new()),
+            template_height: AtomicU64::new(0),
+            blocks_found: AtomicU64::new(0),
+        }
+    }
+
+    /// Remember that job has started mining on the template of given height.
+    pub fn note_template(&self, height: u32) {
+        self.template_height
+            .store(u64::from(height) + 1, Ordering::Relaxed);
+    }
+
+    /// Remember that job has done given number of iterations.
+    pub fn note_iterations(&self, iterations: u64) {
+        let now = Instant::now();
+        let mut samples = self.iterations.lock();
+        samples.push_back((now, iterations));
+        prune_samples(&mut samples, now);
+    }
+
+    /// Remember that job has found the block.
+    pub fn note_block_found(&self) {
+        self.blocks_found.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Iterations per second within the rate window (or since start, if mining is younger).
+    fn iterations_per_second(&self, now: Instant) -> u64 {
+        let mut samples = self.iterations.lock();
+        prune_samples(&mut samples, now);
+        let window = Duration::from_secs(RATE_WINDOW_S);
+        let elapsed = now.duration_since(self.started);
+        let period = if elapsed < window { elapsed } else { window };
+        let period = period.as_secs_f64();
+        if period <= 0f64 {
+            return 0;
+        }
+        let iterations: u64 = samples.iter().map(|&(_, iterations)| iterations).sum();
+        (iterations as f64 / period) as u64
+    }
+}
+
+/// Forgets samples, which are older than the rate window.
+fn prune_samples(samples: &mut VecDeque<(Instant, u64)>, now: Instant) {
+    let window = Duration::from_secs(RATE_WINDOW_S);
+    while samples
+        .front()
+        .map_or(false, |&(time, _)| now.duration_since(time) > window)
+    {
+        samples.pop_front();
+    }
+}
+
+fn run(inner: Arc<MiningControlInner>, index: usize) {
+    loop {
+        {
+            let mut state = inner.state.lock();
+            while state.paused || index >= state.threads {
+                inner.changed.wait(&mut state);
+            }
+        }
+
+        (inner.job)(&inner.counters);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::MiningControl;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::thread::sleep;
+    use std::time::Duration;
+
+    #[test]
+    fn mining_control_pauses_and_resizes_threads() {
+        let jobs = Arc::new(AtomicUsize::new(0));
+        let jobs_counter = jobs.clone();
+        let control = MiningControl::start(0, move |_| {
+            jobs_counter.fetch_add(1, Ordering::SeqCst);
+            sleep(Duration::from_millis(1));
+        });
+        sleep(Duration::from_millis(50));
+        assert_eq!(jobs.load(Ordering::SeqCst), 0);
+
+        control.set_threads(2);
+        sleep(Duration::from_millis(50));
+        assert!(jobs.load(Ordering::SeqCst) > 0);
+
+        // wait for running jobs to complete
+        control.pause();
+        assert!(control.is_paused());
+        sleep(Duration::from_millis(50));
+        let paused_jobs = jobs.load(Ordering::SeqCst);
+        sleep(Duration::from_millis(50));
+        assert_eq!(jobs.load(Ordering::SeqCst), paused_jobs);
+
+        control.resume();
+        sleep(Duration::from_millis(50));
+        assert!(jobs.load(Ordering::SeqCst) > paused_jobs);
+
+        control.set_threads(0);
+        assert_eq!(control.threads(), 0);
+    }
+
+    #[test]
+    fn mining_control_reports_progress() {
+        let control = MiningControl::start(0, |counters| {
+            counters.note_template(10);
+            counters.note_iterations(1000);
+            counters.note_block_found();
+            sleep(Duration::from_millis(1));
+        });
+        let stats = control.stats();
+        assert_eq!(stats.threads, 0);
+        assert!(!stats.paused);
+        assert_eq!(stats.iterations_per_second, 0);
+        assert_eq!(stats.template_height, None);
+        assert_eq!(stats.blocks_found, 0);
+
+        control.set_threads(1);
+        sleep(Duration::from_millis(50));
+        control.set_threads(0);
+        let stats = control.stats();
+        assert_eq!(stats.template_height, Some(10));
+        assert!(stats.blocks_found > 0);
+        assert!(stats.iterations_per_second > 0);
+    }
+}
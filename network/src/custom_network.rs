@@ -0,0 +1,147 @@
+//! Registry of custom networks, defined at runtime
+
+use chain::{Block, IndexedBlock};
+use network::{Magic, Network};
+use primitives::bigint::U256;
+use std::collections::HashMap;
+use std::sync::RwLock;
+use vdf_params::VdfParams;
+
+lazy_static! {
+    static ref CUSTOM_NETWORKS: RwLock<HashMap<Magic, &'static CustomNetwork>> =
+        RwLock::new(HashMap::new());
+}
+
+/// Parameters of the custom (e.g. private development) network.
+/// Custom network is `Network::Other` with the magic of the network.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CustomNetwork {
+    /// Network name
+    pub name: String,
+    /// Network magic
+    pub magic: Magic,
+    /// Default p2p port
+    pub port: u16,
+    /// Default JSONRPC port
+    pub rpc_port: u16,
+    /// Target interval between blocks, in seconds
+    pub block_interval: u16,
+    /// Max target of the block
+    pub max_bits: U256,
+    /// VDF parameters
+    pub vdf_params: VdfParams,
+    /// Genesis block
+    pub genesis_block: Block,
+}
+
+/// Derives magic of the private network from its genesis block, so that nodes of networks with
+/// different genesis blocks never connect to each other.
+pub fn genesis_magic(genesis_block: &Block) -> Magic {
+    let hash = IndexedBlock::from_raw(genesis_block.clone()).hash().clone();
+    let mut magic = [0u8; 4];
+    magic.copy_from_slice(&hash[..4]);
+    Magic::from_le_bytes(magic)
+}
+
+/// Registers custom network. Parameters of the network could not be changed after registration.
+pub fn register_custom_network(params: CustomNetwork) -> Result<Network, String> {
+    let network = Network::Other(params.magic);
+    if Network::builtin()
+        .iter()
+        .any(|builtin| builtin.magic() == params.magic)
+    {
+        return Err(format!(
+            "Magic {:#010x} of {} network is used by builtin network",
+            params.magic, params.name
+        ));
+    }
+
+    let mut networks = CUSTOM_NETWORKS
+        .write()
+        .expect("custom networks lock is never poisoned");
+    if networks.values().any(|network| network.name == params.name) {
+        return Err(format!("Network {} is already registered", params.name));
+    }
+    if networks.contains_key(&params.magic) {
+        return Err(format!(
+            "Magic {:#010x} of {} network is already used",
+            params.magic, params.name
+        ));
+    }
+
+    // registered networks live until the process exits
+    networks.insert(params.magic, Box::leak(Box::new(params)));
+    Ok(network)
+}
+
+/// Returns parameters of the registered custom network with given magic.
+pub fn custom_network(magic: Magic) -> Option<&'static CustomNetwork> {
+    CUSTOM_NETWORKS
+        .read()
+        .expect("custom networks lock is never poisoned")
+        .get(&magic)
+        .cloned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{custom_network, genesis_magic, register_custom_network, CustomNetwork};
+    use network::Network;
+    use vdf_params::VdfParams;
+
+    fn devnet(name: &str, magic: u32) -> CustomNetwork {
+        let mut genesis_block = Network::Regtest.genesis_block().to_raw_block();
+        genesis_block.block_header.time = 1000;
+        CustomNetwork {
+            name: name.to_owned(),
+            magic: magic,
+            port: 28333,
+            rpc_port: 28332,
+            block_interval: 30,
+            max_bits: Network::Regtest.max_bits(),
+            vdf_params: VdfParams::default(),
+            genesis_block: genesis_block,
+        }
+    }
+
+    #[test]
+    fn custom_network_params_are_used() {
+        let network = register_custom_network(devnet("devnet-params", 0x1234_5678)).unwrap();
+        assert_eq!(network, Network::Other(0x1234_5678));
+        assert_eq!(network.port(), 28333);
+        assert_eq!(network.rpc_port(), 28332);
+        assert_eq!(network.block_interval(), 30);
+        assert_eq!(network.genesis_block().header.raw.time, 1000);
+        assert_eq!(network.validate(), Ok(()));
+        assert_eq!(
+            custom_network(0x1234_5678).map(|params| params.name.as_str()),
+            Some("devnet-params")
+        );
+
+        // unregistered networks behave like mainnet
+        assert_eq!(Network::Other(0x1234_5679).port(), Network::Mainnet.port());
+    }
+
+    #[test]
+    fn custom_network_is_registered_once() {
+        register_custom_network(devnet("devnet-once", 0x2234_5678)).unwrap();
+        assert!(register_custom_network(devnet("devnet-once", 0x2234_5679)).is_err());
+        assert!(register_custom_network(devnet("devnet-other", 0x2234_5678)).is_err());
+        assert!(
+            register_custom_network(devnet("devnet-mainnet", Network::Mainnet.magic())).is_err()
+        );
+    }
+
+    #[test]
+    fn genesis_magic_depends_on_genesis_block() {
+        let genesis_block = devnet("devnet-magic", 0).genesis_block;
+        let mut other_genesis_block = genesis_block.clone();
+        other_genesis_block.block_header.time += 1;
+
+        assert_eq!(genesis_magic(&genesis_block), genesis_magic(&genesis_block));
+        assert_ne!(
+            genesis_magic(&genesis_block),
+            genesis_magic(&other_genesis_block)
+        );
+    }
+}
@@ -5,9 +5,14 @@ extern crate rug;
 
 extern crate chain;
 extern crate primitives;
+extern crate vdf;
 
+mod custom_network;
 mod network;
+mod vdf_params;
 
 pub use primitives::{compact, hash};
 
+pub use custom_network::{genesis_magic, register_custom_network, CustomNetwork};
 pub use network::{Magic, Network};
+pub use vdf_params::VdfParams;
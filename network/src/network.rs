@@ -2,8 +2,10 @@
 
 use chain::{Block, BlockHeader, IndexedBlock};
 use compact::Compact;
+use custom_network::{custom_network, CustomNetwork};
 use primitives::bigint::U256;
 use primitives::hash::H256;
+use vdf_params::VdfParams;
 
 // TODO:
 // These are the same as bitcoin as described in
@@ -14,6 +16,9 @@ const MAGIC_TESTNET: u32 = 0x0709110B;
 const MAGIC_REGTEST: u32 = 0xDAB5BFFA;
 const MAGIC_UNITEST: u32 = 0x00000000;
 
+/// Target interval between blocks of builtin networks, in seconds.
+const BLOCK_INTERVAL_S: u16 = 10;
+
 lazy_static! {
     static ref MAX_BITS_MAINNET: U256 =
         "7fffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff"
@@ -27,6 +32,9 @@ lazy_static! {
         "7fffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff"
             .parse()
             .expect("hardcoded value should parse without errors");
+    static ref VDF_PARAMS_MAINNET: VdfParams = VdfParams::default();
+    static ref VDF_PARAMS_TESTNET: VdfParams = VdfParams::default();
+    static ref VDF_PARAMS_REGTEST: VdfParams = VdfParams::default();
 }
 
 /// Network magic type.
@@ -43,11 +51,30 @@ pub enum Network {
     Regtest,
     /// Testnet for unittests, proof of work difficulty is almost 0
     Unitest,
-    /// Any other network. By default behaves like RandChain mainnet.
+    /// Any other network. Custom network, registered with this magic, uses its own parameters.
+    /// By default behaves like RandChain mainnet.
     Other(u32),
 }
 
 impl Network {
+    /// Networks with hardcoded parameters.
+    pub fn builtin() -> [Network; 4] {
+        [
+            Network::Mainnet,
+            Network::Testnet,
+            Network::Regtest,
+            Network::Unitest,
+        ]
+    }
+
+    /// Parameters of the registered custom network.
+    pub fn custom(&self) -> Option<&'static CustomNetwork> {
+        match *self {
+            Network::Other(magic) => custom_network(magic),
+            _ => None,
+        }
+    }
+
     pub fn magic(&self) -> Magic {
         match *self {
             Network::Mainnet => MAGIC_MAINNET,
@@ -59,6 +86,10 @@ impl Network {
     }
 
     pub fn max_bits(&self) -> U256 {
+        if let Some(custom) = self.custom() {
+            return custom.max_bits.clone();
+        }
+
         match *self {
             Network::Mainnet | Network::Other(_) => MAX_BITS_MAINNET.clone(),
             Network::Testnet => MAX_BITS_TESTNET.clone(),
@@ -67,7 +98,38 @@ impl Network {
         }
     }
 
+    pub fn vdf_params(&self) -> &'static VdfParams {
+        if let Some(custom) = self.custom() {
+            return &custom.vdf_params;
+        }
+
+        match *self {
+            Network::Mainnet | Network::Other(_) => &VDF_PARAMS_MAINNET,
+            Network::Testnet => &VDF_PARAMS_TESTNET,
+            Network::Regtest | Network::Unitest => &VDF_PARAMS_REGTEST,
+        }
+    }
+
+    /// Checks that network parameters are consistent.
+    pub fn validate(&self) -> Result<(), String> {
+        let vdf_params = self.vdf_params();
+        vdf_params.validate()?;
+
+        let genesis_iterations = self.genesis_block().header.raw.iterations;
+        if !vdf_params.is_valid_iterations(genesis_iterations) {
+            return Err(format!(
+                "Genesis block has {} VDF iterations, which is out of [{}; {}] range",
+                genesis_iterations, vdf_params.min_iterations, vdf_params.max_iterations
+            ));
+        }
+        Ok(())
+    }
+
     pub fn port(&self) -> u16 {
+        if let Some(custom) = self.custom() {
+            return custom.port;
+        }
+
         match *self {
             Network::Mainnet | Network::Other(_) => 8333,
             Network::Testnet => 18333,
@@ -80,6 +142,10 @@ impl Network {
     }
 
     pub fn rpc_port(&self) -> u16 {
+        if let Some(custom) = self.custom() {
+            return custom.rpc_port;
+        }
+
         match *self {
             Network::Mainnet | Network::Other(_) => 8332,
             Network::Testnet => 18332,
@@ -87,7 +153,18 @@ impl Network {
         }
     }
 
+    /// Target interval between blocks, in seconds.
+    pub fn block_interval(&self) -> u16 {
+        self.custom()
+            .map(|custom| custom.block_interval)
+            .unwrap_or(BLOCK_INTERVAL_S)
+    }
+
     pub fn genesis_block(&self) -> IndexedBlock {
+        if let Some(custom) = self.custom() {
+            return IndexedBlock::from_raw(custom.genesis_block.clone());
+        }
+
         match *self {
             Network::Mainnet | Network::Other(_) => {
                 let blk = Block {
@@ -174,6 +251,14 @@ mod tests {
         assert_eq!(Network::Unitest.max_bits(), Compact::max_value().into());
     }
 
+    #[test]
+    fn test_network_params_are_valid() {
+        assert_eq!(Network::Mainnet.validate(), Ok(()));
+        assert_eq!(Network::Testnet.validate(), Ok(()));
+        assert_eq!(Network::Regtest.validate(), Ok(()));
+        assert_eq!(Network::Unitest.validate(), Ok(()));
+    }
+
     #[test]
     fn test_network_port() {
         assert_eq!(Network::Mainnet.port(), 8333);
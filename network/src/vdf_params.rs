@@ -0,0 +1,115 @@
+//! VDF group parameters
+
+use rug::Integer;
+use vdf;
+
+/// Parameters of the VDF group of unknown order.
+#[derive(Debug, Clone, PartialEq)]
+pub struct VdfParams {
+    /// RSA modulus of the group.
+    pub modulus: Integer,
+    /// Size of encoded group element (in bits). Hash-to-group output has this size.
+    pub element_bits: u32,
+    /// Minimal number of VDF iterations in a block.
+    pub min_iterations: u32,
+    /// Maximal number of VDF iterations in a block.
+    pub max_iterations: u32,
+}
+
+impl Default for VdfParams {
+    fn default() -> Self {
+        VdfParams {
+            modulus: vdf::MODULUS.clone(),
+            element_bits: 2048,
+            min_iterations: 0,
+            max_iterations: u32::max_value(),
+        }
+    }
+}
+
+impl VdfParams {
+    /// Checks that parameters are consistent.
+    pub fn validate(&self) -> Result<(), String> {
+        if self.modulus <= 1 || self.modulus.is_even() {
+            return Err("VDF modulus must be odd and greater than 1".into());
+        }
+        if self.element_bits == 0 || self.element_bits % 256 != 0 {
+            return Err(format!(
+                "VDF element size must be a positive multiple of 256 bits, got {}",
+                self.element_bits
+            ));
+        }
+        if self.element_bits < self.modulus.significant_bits() {
+            return Err(format!(
+                "VDF element size ({} bits) is less than modulus size ({} bits)",
+                self.element_bits,
+                self.modulus.significant_bits()
+            ));
+        }
+        if self.min_iterations > self.max_iterations {
+            return Err(format!(
+                "VDF min iterations ({}) is greater than max iterations ({})",
+                self.min_iterations, self.max_iterations
+            ));
+        }
+        Ok(())
+    }
+
+    /// Returns true if given number of iterations is allowed.
+    pub fn is_valid_iterations(&self, iterations: u32) -> bool {
+        iterations >= self.min_iterations && iterations <= self.max_iterations
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::VdfParams;
+    use rug::Integer;
+
+    #[test]
+    fn default_params_are_valid() {
+        assert_eq!(VdfParams::default().validate(), Ok(()));
+    }
+
+    #[test]
+    fn invalid_params_are_rejected() {
+        let params = VdfParams {
+            modulus: Integer::from(100),
+            ..VdfParams::default()
+        };
+        assert!(params.validate().is_err());
+
+        let params = VdfParams {
+            element_bits: 1000,
+            ..VdfParams::default()
+        };
+        assert!(params.validate().is_err());
+
+        let params = VdfParams {
+            element_bits: 1024,
+            ..VdfParams::default()
+        };
+        assert!(params.validate().is_err());
+
+        let params = VdfParams {
+            min_iterations: 10,
+            max_iterations: 1,
+            ..VdfParams::default()
+        };
+        assert!(params.validate().is_err());
+    }
+
+    #[test]
+    fn small_params_are_valid() {
+        let params = VdfParams {
+            modulus: Integer::from(3233),
+            element_bits: 256,
+            min_iterations: 1,
+            max_iterations: 16,
+        };
+        assert_eq!(params.validate(), Ok(()));
+        assert!(!params.is_valid_iterations(0));
+        assert!(params.is_valid_iterations(16));
+        assert!(!params.is_valid_iterations(17));
+    }
+}
@@ -1,7 +1,7 @@
 use message::common::Services;
 use net::Config as NetConfig;
 use std::{net, path};
-use util::InternetProtocol;
+use util::{BandwidthLimit, ExperimentsConfig, InternetProtocol, IpRange};
 
 #[derive(Debug, Clone)]
 pub struct Config {
@@ -11,8 +11,15 @@ pub struct Config {
     pub inbound_connections: u32,
     /// Number of outbound connections.
     pub outbound_connections: u32,
+    /// Number of outbound block-relay-only connections. They are maintained in addition to `outbound_connections`.
+    pub block_relay_connections: u32,
     /// Configuration for every connection.
     pub connection: NetConfig,
+    /// Addresses to listen for incoming connections on. Address, advertised to peers,
+    /// is `connection.local_address`.
+    pub listen: Vec<net::SocketAddr>,
+    /// Map listening ports on the gateway with NAT-PMP, so that peers could connect from outside.
+    pub port_mapping: bool,
     /// Connect only to these nodes.
     pub peers: Vec<net::SocketAddr>,
     /// Connect to these nodes to retrieve peer addresses, and disconnect.
@@ -23,4 +30,10 @@ pub struct Config {
     pub preferable_services: Services,
     /// Internet protocol.
     pub internet_protocol: InternetProtocol,
+    /// Percentage of connections with experimental protocol behaviors.
+    pub experiments: ExperimentsConfig,
+    /// Peers from these ranges are never banned or evicted, and are accepted even if all inbound slots are used.
+    pub whitelist: Vec<IpRange>,
+    /// Bandwidth limits of messages, sent to peers. Could be changed at runtime.
+    pub bandwidth_limits: Vec<BandwidthLimit>,
 }
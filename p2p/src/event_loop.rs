@@ -1,7 +1,10 @@
 use futures::{empty, Empty};
 use tokio_core::reactor::Core;
 
-pub fn event_loop() -> Core {
+/// Event loop, shared by all networking modules of the process.
+pub type EventLoop = Core;
+
+pub fn event_loop() -> EventLoop {
     Core::new().unwrap()
 }
 
@@ -1,5 +1,5 @@
 use futures::{Async, Future, Poll};
-use message::{MessageHeader, MessageResult};
+use message::{MessageDecoder, MessageHeader, MessageResult};
 use network::Magic;
 use std::io;
 use tokio_io::io::{read_exact, ReadExact};
@@ -11,13 +11,14 @@ where
 {
     ReadHeader {
         reader: read_exact(a, [0u8; 24]),
-        magic: magic,
+        decoder: MessageDecoder::new(magic),
     }
 }
 
 pub struct ReadHeader<A> {
     reader: ReadExact<A, [u8; 24]>,
-    magic: Magic,
+    /// Checks header before the payload buffer is allocated
+    decoder: MessageDecoder,
 }
 
 impl<A> Future for ReadHeader<A>
@@ -29,7 +30,7 @@ where
 
     fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
         let (read, data) = try_ready!(self.reader.poll());
-        let header = MessageHeader::deserialize(&data, self.magic);
+        let header = self.decoder.decode_header(&data).map_err(Into::into);
         Ok(Async::Ready((read, header)))
     }
 }
@@ -80,6 +81,18 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_read_header_with_too_large_payload() {
+        let raw: Bytes = "f9beb4d9616464720000000000000000ffffffffed52399b".into();
+        assert_eq!(
+            read_header(raw.as_ref(), Network::Mainnet.magic())
+                .wait()
+                .unwrap()
+                .1,
+            Err(Error::PayloadTooLarge)
+        );
+    }
+
     #[test]
     fn test_read_too_short_header() {
         let raw: Bytes = "f9beb4d96164647200000000000000001f000000ed5239".into();
@@ -1,6 +1,6 @@
 use futures::{Async, Future, Poll};
 use io::{read_header, read_payload, ReadHeader, ReadPayload};
-use message::{Error, MessageResult, Payload};
+use message::{Error, MessageDecoder, MessageResult, Payload};
 use network::Magic;
 use std::io;
 use std::marker::PhantomData;
@@ -13,7 +13,7 @@ where
 {
     ReadMessage {
         state: ReadMessageState::ReadHeader {
-            version: version,
+            decoder: MessageDecoder::new(magic).with_version(version),
             future: read_header(a, magic),
         },
         message_type: PhantomData,
@@ -21,8 +21,13 @@ where
 }
 
 enum ReadMessageState<M, A> {
-    ReadHeader { version: u32, future: ReadHeader<A> },
-    ReadPayload { future: ReadPayload<M, A> },
+    ReadHeader {
+        decoder: MessageDecoder,
+        future: ReadHeader<A>,
+    },
+    ReadPayload {
+        future: ReadPayload<M, A>,
+    },
 }
 
 pub struct ReadMessage<M, A> {
@@ -42,7 +47,7 @@ where
         loop {
             let next_state = match self.state {
                 ReadMessageState::ReadHeader {
-                    version,
+                    ref decoder,
                     ref mut future,
                 } => {
                     let (read, header) = try_ready!(future.poll());
@@ -53,7 +58,8 @@ where
                     if header.command != M::command() {
                         return Ok((read, Err(Error::InvalidCommand)).into());
                     }
-                    let future = read_payload(read, version, header.len as usize, header.checksum);
+                    let future =
+                        read_payload(read, decoder.clone(), header.len as usize, header.checksum);
                     ReadMessageState::ReadPayload { future: future }
                 }
                 ReadMessageState::ReadPayload { ref mut future } => {
@@ -2,20 +2,25 @@ use bytes::Bytes;
 use crypto::checksum;
 use futures::{Future, Poll};
 use hash::H32;
-use message::{deserialize_payload, Error, MessageResult, Payload};
+use message::{Error, MessageDecoder, MessageResult, Payload};
 use std::io;
 use std::marker::PhantomData;
 use tokio_io::io::{read_exact, ReadExact};
 use tokio_io::AsyncRead;
 
-pub fn read_payload<M, A>(a: A, version: u32, len: usize, checksum: H32) -> ReadPayload<M, A>
+pub fn read_payload<M, A>(
+    a: A,
+    decoder: MessageDecoder,
+    len: usize,
+    checksum: H32,
+) -> ReadPayload<M, A>
 where
     A: AsyncRead,
     M: Payload,
 {
     ReadPayload {
         reader: read_exact(a, Bytes::new_with_len(len)),
-        version: version,
+        decoder: decoder,
         checksum: checksum,
         payload_type: PhantomData,
     }
@@ -23,7 +28,7 @@ where
 
 pub struct ReadPayload<M, A> {
     reader: ReadExact<A, Bytes>,
-    version: u32,
+    decoder: MessageDecoder,
     checksum: H32,
     payload_type: PhantomData<M>,
 }
@@ -41,7 +46,7 @@ where
         if checksum(&data) != self.checksum {
             return Ok((read, Err(Error::InvalidChecksum)).into());
         }
-        let payload = deserialize_payload(&data, self.version);
+        let payload = self.decoder.read_payload::<M>(&data).map_err(Into::into);
         Ok((read, payload).into())
     }
 }
@@ -52,14 +57,19 @@ mod tests {
     use bytes::Bytes;
     use futures::Future;
     use message::types::Ping;
-    use message::Error;
+    use message::{Error, MessageDecoder};
+    use network::Network;
+
+    fn decoder() -> MessageDecoder {
+        MessageDecoder::new(Network::Mainnet.magic()).with_version(0)
+    }
 
     #[test]
     fn test_read_payload() {
         let raw: Bytes = "5845303b6da97786".into();
         let ping = Ping::new(u64::from_str_radix("8677a96d3b304558", 16).unwrap());
         assert_eq!(
-            read_payload(raw.as_ref(), 0, 8, "83c00c76".into())
+            read_payload(raw.as_ref(), decoder(), 8, "83c00c76".into())
                 .wait()
                 .unwrap()
                 .1,
@@ -71,7 +81,7 @@ mod tests {
     fn test_read_payload_with_invalid_checksum() {
         let raw: Bytes = "5845303b6da97786".into();
         assert_eq!(
-            read_payload::<Ping, _>(raw.as_ref(), 0, 8, "83c00c75".into())
+            read_payload::<Ping, _>(raw.as_ref(), decoder(), 8, "83c00c75".into())
                 .wait()
                 .unwrap()
                 .1,
@@ -83,7 +93,7 @@ mod tests {
     fn test_read_too_short_payload() {
         let raw: Bytes = "5845303b6da977".into();
         assert!(
-            read_payload::<Ping, _>(raw.as_ref(), 0, 8, "83c00c76".into())
+            read_payload::<Ping, _>(raw.as_ref(), decoder(), 8, "83c00c76".into())
                 .wait()
                 .is_err()
         );
@@ -0,0 +1,231 @@
+use futures::{future, Future};
+use std::io;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use tokio_io::io::{read_exact, write_all};
+use tokio_io::{AsyncRead, AsyncWrite};
+
+const SOCKS_VERSION: u8 = 5;
+const METHOD_NO_AUTH: u8 = 0;
+const CMD_CONNECT: u8 = 1;
+/// Tor extension: resolve host name without connecting to it.
+const CMD_RESOLVE: u8 = 0xf0;
+const ATYP_IPV4: u8 = 1;
+const ATYP_DOMAIN: u8 = 3;
+const ATYP_IPV6: u8 = 4;
+const REPLY_SUCCEEDED: u8 = 0;
+
+/// Destination of the proxied request.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Socks5Target {
+    /// Connect to the IP address.
+    Address(SocketAddr),
+    /// Connect to the host name, which is resolved by the proxy.
+    Domain(String, u16),
+}
+
+pub type Socks5Future<A, T> = Box<dyn Future<Item = (A, T), Error = io::Error> + Send>;
+
+/// Asks SOCKS5 proxy, the stream is connected to, to connect to the target.
+/// Once future is resolved, stream is connected to the target.
+pub fn socks5_connect<A>(stream: A, target: Socks5Target) -> Socks5Future<A, ()>
+where
+    A: AsyncRead + AsyncWrite + Send + 'static,
+{
+    Box::new(socks5_request(stream, CMD_CONNECT, target).map(|(stream, _)| (stream, ())))
+}
+
+/// Asks SOCKS5 proxy, the stream is connected to, to resolve host name.
+/// Stream must not be used after future is resolved.
+pub fn socks5_resolve<A>(stream: A, host: String) -> Socks5Future<A, IpAddr>
+where
+    A: AsyncRead + AsyncWrite + Send + 'static,
+{
+    Box::new(
+        socks5_request(stream, CMD_RESOLVE, Socks5Target::Domain(host, 0))
+            .map(|(stream, address)| (stream, address.ip())),
+    )
+}
+
+/// Authenticates with the proxy (no authentication is supported) and sends request.
+/// Returns address from the proxy reply.
+fn socks5_request<A>(stream: A, command: u8, target: Socks5Target) -> Socks5Future<A, SocketAddr>
+where
+    A: AsyncRead + AsyncWrite + Send + 'static,
+{
+    let request = match encode_request(command, &target) {
+        Ok(request) => request,
+        Err(err) => return Box::new(future::err(err)),
+    };
+
+    Box::new(
+        write_all(stream, [SOCKS_VERSION, 1, METHOD_NO_AUTH])
+            .and_then(|(stream, _)| read_exact(stream, [0u8; 2]))
+            .and_then(|(stream, reply)| match reply {
+                [SOCKS_VERSION, METHOD_NO_AUTH] => Ok(stream),
+                _ => Err(proxy_error("proxy requires unsupported authentication")),
+            })
+            .and_then(move |stream| write_all(stream, request))
+            .and_then(|(stream, _)| read_exact(stream, [0u8; 4]))
+            .and_then(|(stream, reply)| {
+                if reply[0] != SOCKS_VERSION {
+                    return Err(proxy_error("invalid proxy reply"));
+                }
+                if reply[1] != REPLY_SUCCEEDED {
+                    return Err(proxy_error(&format!(
+                        "proxy request failed with code {}",
+                        reply[1]
+                    )));
+                }
+                match reply[3] {
+                    ATYP_IPV4 => Ok((stream, 4 + 2)),
+                    ATYP_IPV6 => Ok((stream, 16 + 2)),
+                    _ => Err(proxy_error("unsupported proxy reply address type")),
+                }
+            })
+            .and_then(|(stream, len)| read_exact(stream, vec![0u8; len]))
+            .map(|(stream, address)| (stream, decode_address(&address))),
+    )
+}
+
+fn encode_request(command: u8, target: &Socks5Target) -> Result<Vec<u8>, io::Error> {
+    let mut request = vec![SOCKS_VERSION, command, 0];
+    let port = match *target {
+        Socks5Target::Address(SocketAddr::V4(ref address)) => {
+            request.push(ATYP_IPV4);
+            request.extend_from_slice(&address.ip().octets());
+            address.port()
+        }
+        Socks5Target::Address(SocketAddr::V6(ref address)) => {
+            request.push(ATYP_IPV6);
+            request.extend_from_slice(&address.ip().octets());
+            address.port()
+        }
+        Socks5Target::Domain(ref host, port) => {
+            if host.is_empty() || host.len() > u8::max_value() as usize {
+                return Err(proxy_error("invalid host name length"));
+            }
+            request.push(ATYP_DOMAIN);
+            request.push(host.len() as u8);
+            request.extend_from_slice(host.as_bytes());
+            port
+        }
+    };
+    request.push((port >> 8) as u8);
+    request.push(port as u8);
+    Ok(request)
+}
+
+fn decode_address(bytes: &[u8]) -> SocketAddr {
+    let (ip, port) = match bytes.len() {
+        6 => (
+            IpAddr::V4(Ipv4Addr::new(bytes[0], bytes[1], bytes[2], bytes[3])),
+            &bytes[4..],
+        ),
+        _ => {
+            let mut octets = [0u8; 16];
+            octets.copy_from_slice(&bytes[..16]);
+            (IpAddr::V6(Ipv6Addr::from(octets)), &bytes[16..])
+        }
+    };
+    SocketAddr::new(ip, (port[0] as u16) << 8 | port[1] as u16)
+}
+
+fn proxy_error(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::Other, format!("SOCKS5: {}", message))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{socks5_connect, socks5_resolve, Socks5Target};
+    use futures::{Future, Poll};
+    use std::io;
+    use tokio_io::{AsyncRead, AsyncWrite};
+
+    pub struct TestIo {
+        read: io::Cursor<Vec<u8>>,
+        write: Vec<u8>,
+    }
+
+    impl TestIo {
+        fn new(read: Vec<u8>) -> Self {
+            TestIo {
+                read: io::Cursor::new(read),
+                write: Vec::new(),
+            }
+        }
+    }
+
+    impl io::Read for TestIo {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            io::Read::read(&mut self.read, buf)
+        }
+    }
+
+    impl AsyncRead for TestIo {}
+
+    impl io::Write for TestIo {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            io::Write::write(&mut self.write, buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl AsyncWrite for TestIo {
+        fn shutdown(&mut self) -> Poll<(), io::Error> {
+            Ok(().into())
+        }
+    }
+
+    #[test]
+    fn socks5_connect_to_domain() {
+        let stream = TestIo::new(vec![5, 0, 5, 0, 0, 1, 0, 0, 0, 0, 0, 0]);
+        let target = Socks5Target::Domain("abc.onion".into(), 8333);
+        let (stream, _) = socks5_connect(stream, target).wait().unwrap();
+
+        let mut expected = vec![5, 1, 0, 5, 1, 0, 3, 9];
+        expected.extend_from_slice(b"abc.onion");
+        expected.extend_from_slice(&[0x20, 0x8d]);
+        assert_eq!(stream.write, expected);
+    }
+
+    #[test]
+    fn socks5_connect_to_address() {
+        let stream = TestIo::new(vec![5, 0, 5, 0, 0, 1, 0, 0, 0, 0, 0, 0]);
+        let target = Socks5Target::Address("127.0.0.1:8333".parse().unwrap());
+        let (stream, _) = socks5_connect(stream, target).wait().unwrap();
+        assert_eq!(
+            stream.write,
+            vec![5, 1, 0, 5, 1, 0, 1, 127, 0, 0, 1, 0x20, 0x8d]
+        );
+    }
+
+    #[test]
+    fn socks5_resolve_host() {
+        let stream = TestIo::new(vec![5, 0, 5, 0, 0, 1, 10, 0, 0, 1, 0, 0]);
+        let (stream, ip) = socks5_resolve(stream, "seed.example".into())
+            .wait()
+            .unwrap();
+        assert_eq!(ip, "10.0.0.1".parse::<::std::net::IpAddr>().unwrap());
+        assert_eq!(&stream.write[3..5], &[5, 0xf0]);
+    }
+
+    #[test]
+    fn socks5_errors() {
+        // authentication is required
+        let stream = TestIo::new(vec![5, 0xff]);
+        let target = Socks5Target::Address("127.0.0.1:8333".parse().unwrap());
+        assert!(socks5_connect(stream, target.clone()).wait().is_err());
+
+        // host is unreachable
+        let stream = TestIo::new(vec![5, 0, 5, 4, 0, 1, 0, 0, 0, 0, 0, 0]);
+        assert!(socks5_connect(stream, target).wait().is_err());
+
+        // host name is too long
+        let stream = TestIo::new(vec![5, 0]);
+        let target = Socks5Target::Domain(::std::iter::repeat('a').take(256).collect(), 1);
+        assert!(socks5_connect(stream, target).wait().is_err());
+    }
+}
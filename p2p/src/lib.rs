@@ -10,6 +10,7 @@ extern crate tokio_io;
 extern crate log;
 extern crate abstract_ns;
 extern crate csv;
+extern crate net2;
 extern crate ns_dns_tokio;
 
 extern crate bitcrypto as crypto;
@@ -30,7 +31,8 @@ mod util;
 pub use primitives::{bytes, hash};
 
 pub use config::Config;
-pub use event_loop::{event_loop, forever};
+pub use event_loop::{event_loop, forever, EventLoop};
+pub use message::Compression;
 pub use net::Config as NetConfig;
 pub use p2p::{Context, P2P};
 pub use protocol::{
@@ -38,4 +40,9 @@ pub use protocol::{
     InboundSyncConnectionStateRef, LocalSyncNode, LocalSyncNodeRef, OutboundSyncConnection,
     OutboundSyncConnectionRef,
 };
-pub use util::{Direction, InternetProtocol, NodeTableError, PeerId, PeerInfo};
+pub use util::{
+    parse_onion_address, BandwidthLimit, ConnectionCount, Direction, ExperimentFlags,
+    ExperimentGroupStats, ExperimentStats, ExperimentsConfig, InternetProtocol, IpRange,
+    NodeTableError, PeerClass, PeerCompressionStats, PeerFilter, PeerId, PeerInfo, PeerPolicy,
+    PeerPolicyStats, PeerVersionStats,
+};
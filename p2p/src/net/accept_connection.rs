@@ -1,6 +1,7 @@
 use futures::{Future, Poll};
 use io::{accept_handshake, deadline, AcceptHandshake, Deadline};
-use message::MessageResult;
+use message::common::Services;
+use message::{Compression, MessageResult};
 use net::{Config, Connection};
 use network::Magic;
 use std::time::Duration;
@@ -23,6 +24,7 @@ pub fn accept_connection(
         ),
         magic: config.magic,
         address: address,
+        services: config.services,
     };
 
     deadline(Duration::new(5, 0), handle, accept).expect("Failed to create timeout")
@@ -32,6 +34,7 @@ pub struct AcceptConnection {
     handshake: AcceptHandshake<TcpStream>,
     magic: Magic,
     address: net::SocketAddr,
+    services: Services,
 }
 
 impl Future for AcceptConnection {
@@ -47,6 +50,9 @@ impl Future for AcceptConnection {
         let connection = Connection {
             stream: stream.into(),
             services: result.version.services(),
+            compression: Compression::negotiate(self.services, result.version.services()),
+            compressed_headers: self.services.compressed_headers()
+                && result.version.services().compressed_headers(),
             version: result.negotiated_version,
             version_message: result.version,
             magic: self.magic,
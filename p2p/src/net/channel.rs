@@ -1,12 +1,169 @@
+use bytes::Bytes;
+use futures::sync::oneshot;
 use io::{read_any_message, ReadAnyMessage, SharedTcpStream};
+use parking_lot::Mutex;
 use session::Session;
+use std::collections::VecDeque;
+use std::ops::Range;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::{cmp, io, mem};
 use tokio_io::io::{write_all, WriteAll};
-use util::PeerInfo;
+use util::{Direction, PeerInfo};
+
+/// Max number of bytes of shared payload, written to the stream at once.
+const STREAM_CHUNK_SIZE: usize = 64 * 1024;
+
+/// Queued data: either copy of (possibly several) messages, or the payload, shared with other channels.
+enum Segment {
+    Owned(Vec<u8>),
+    Shared(Arc<Bytes>, usize),
+}
+
+/// Data, written to the stream with single write.
+#[derive(Debug, PartialEq)]
+pub enum WriteChunk {
+    /// Queued messages
+    Owned(Vec<u8>),
+    /// Part of the shared payload
+    Shared(Arc<Bytes>, Range<usize>),
+}
+
+impl AsRef<[u8]> for WriteChunk {
+    fn as_ref(&self) -> &[u8] {
+        match *self {
+            WriteChunk::Owned(ref bytes) => bytes,
+            WriteChunk::Shared(ref bytes, ref range) => &bytes[range.clone()],
+        }
+    }
+}
+
+/// Result of the flush, reported to the messages, queued while it is pending.
+pub type FlushResult = oneshot::Receiver<Result<(), io::ErrorKind>>;
+
+/// Result of queueing message for writing.
+#[derive(Debug)]
+pub enum Queued {
+    /// Caller must start the flush
+    Flush,
+    /// Message is written by the pending flush, which reports its result to the receiver
+    Pending(FlushResult),
+    /// Previous write has failed, so nothing is written to the stream anymore
+    Failed(io::ErrorKind),
+}
+
+/// Outgoing messages, waiting to be written to the stream.
+#[derive(Default)]
+struct WriteQueue {
+    /// Serialized messages.
+    segments: VecDeque<Segment>,
+    /// Is flush scheduled or in progress?
+    is_flushing: bool,
+    /// Senders of messages, queued while flush is pending.
+    waiters: Vec<oneshot::Sender<Result<(), io::ErrorKind>>>,
+    /// Error of the failed write.
+    failure: Option<io::ErrorKind>,
+}
+
+impl WriteQueue {
+    /// Appends message to the queue.
+    fn push(&mut self, message: &[u8]) -> Queued {
+        if let Some(kind) = self.failure {
+            return Queued::Failed(kind);
+        }
+
+        if let Some(&mut Segment::Owned(ref mut buffer)) = self.segments.back_mut() {
+            buffer.extend_from_slice(message);
+        } else {
+            self.segments.push_back(Segment::Owned(message.to_vec()));
+        }
+
+        if !mem::replace(&mut self.is_flushing, true) {
+            return Queued::Flush;
+        }
+        let (sender, receiver) = oneshot::channel();
+        self.waiters.push(sender);
+        Queued::Pending(receiver)
+    }
+
+    /// Appends shared payload to the queue. Payload is never copied, it is written to the
+    /// stream by chunks. Must follow the push of the message header.
+    fn push_shared(&mut self, payload: Arc<Bytes>) {
+        if !payload.is_empty() && self.failure.is_none() {
+            self.segments.push_back(Segment::Shared(payload, 0));
+        }
+    }
+
+    /// Drops queued data after the failed write and reports the error to the waiting senders.
+    fn fail(&mut self, kind: io::ErrorKind) {
+        self.segments.clear();
+        self.is_flushing = false;
+        self.failure = Some(kind);
+        for waiter in self.waiters.drain(..) {
+            let _ = waiter.send(Err(kind));
+        }
+    }
+
+    /// Takes next chunk of queued data. Returns None and finishes flush if queue is empty.
+    fn take(&mut self) -> Option<WriteChunk> {
+        let chunk = match self.segments.pop_front() {
+            None => {
+                self.is_flushing = false;
+                for waiter in self.waiters.drain(..) {
+                    let _ = waiter.send(Ok(()));
+                }
+                return None;
+            }
+            Some(Segment::Owned(buffer)) => WriteChunk::Owned(buffer),
+            Some(Segment::Shared(payload, offset)) => {
+                let end = cmp::min(offset + STREAM_CHUNK_SIZE, payload.len());
+                if end != payload.len() {
+                    self.segments
+                        .push_front(Segment::Shared(payload.clone(), end));
+                }
+                WriteChunk::Shared(payload, offset..end)
+            }
+        };
+        Some(chunk)
+    }
+}
+
+/// Size of messages, which are delayed by the bandwidth throttle.
+#[derive(Default)]
+struct DelayedBytes {
+    bytes: AtomicUsize,
+}
+
+impl DelayedBytes {
+    /// Accounts delayed message. Returns false if it doesn't fit the limit. Single message is
+    /// always accepted, even if it exceeds the limit.
+    fn reserve(&self, len: usize, limit: usize) -> bool {
+        let delayed = self.bytes.fetch_add(len, Ordering::SeqCst) + len;
+        if delayed > limit && delayed != len {
+            self.bytes.fetch_sub(len, Ordering::SeqCst);
+            return false;
+        }
+        true
+    }
+
+    /// Forgets delayed message, which is being sent.
+    fn release(&self, len: usize) {
+        self.bytes.fetch_sub(len, Ordering::SeqCst);
+    }
+}
 
 pub struct Channel {
     stream: SharedTcpStream,
     peer_info: PeerInfo,
     session: Session,
+    /// Time when connection has been established.
+    connected_at: u32,
+    /// Messages, waiting to be written to the stream.
+    write_queue: Mutex<WriteQueue>,
+    /// Number of malformed messages, received from the peer.
+    malformed_messages: AtomicUsize,
+    /// Size of messages, delayed by the bandwidth throttle.
+    delayed_bytes: DelayedBytes,
 }
 
 impl Channel {
@@ -15,6 +172,10 @@ impl Channel {
             stream: stream,
             peer_info: peer_info,
             session: session,
+            connected_at: ::time::get_time().sec as u32,
+            write_queue: Mutex::default(),
+            malformed_messages: AtomicUsize::new(0),
+            delayed_bytes: DelayedBytes::default(),
         }
     }
 
@@ -25,10 +186,51 @@ impl Channel {
         write_all(self.stream.clone(), message)
     }
 
+    /// Queues message for writing.
+    /// Messages, queued while flush is pending, are written to the stream at once.
+    pub fn queue_message(&self, message: &[u8]) -> Queued {
+        self.write_queue.lock().push(message)
+    }
+
+    /// Queues message with shared payload for writing.
+    pub fn queue_shared_message(&self, header: &[u8], payload: Arc<Bytes>) -> Queued {
+        let mut write_queue = self.write_queue.lock();
+        let queued = write_queue.push(header);
+        write_queue.push_shared(payload);
+        queued
+    }
+
+    /// Takes next chunk of queued messages. Returns None if there are no queued messages.
+    pub fn take_queued_messages(&self) -> Option<WriteChunk> {
+        self.write_queue.lock().take()
+    }
+
+    /// Drops queued messages after the failed write. Messages are never written to the stream again.
+    pub fn fail_queued_messages(&self, kind: io::ErrorKind) {
+        self.write_queue.lock().fail(kind)
+    }
+
     pub fn read_message(&self) -> ReadAnyMessage<SharedTcpStream> {
         read_any_message(self.stream.clone(), self.peer_info.magic)
     }
 
+    /// Remembers that malformed message has been received. Returns number of malformed messages
+    /// since connection has been established.
+    pub fn note_malformed_message(&self) -> usize {
+        self.malformed_messages.fetch_add(1, Ordering::SeqCst) + 1
+    }
+
+    /// Accounts message, delayed by the bandwidth throttle. Returns false if the peer already
+    /// has more than `limit` delayed bytes, so the message must not be sent.
+    pub fn reserve_delayed_bytes(&self, len: usize, limit: usize) -> bool {
+        self.delayed_bytes.reserve(len, limit)
+    }
+
+    /// Forgets delayed message, once its delay has expired.
+    pub fn release_delayed_bytes(&self, len: usize) {
+        self.delayed_bytes.release(len)
+    }
+
     pub fn shutdown(&self) {
         self.stream.shutdown();
     }
@@ -41,7 +243,125 @@ impl Channel {
         self.peer_info.clone()
     }
 
+    pub fn direction(&self) -> Direction {
+        self.peer_info.direction
+    }
+
+    pub fn is_block_relay_only(&self) -> bool {
+        self.peer_info.block_relay_only
+    }
+
+    pub fn is_whitelisted(&self) -> bool {
+        self.peer_info.whitelisted
+    }
+
+    pub fn is_deprioritized(&self) -> bool {
+        self.peer_info.deprioritized
+    }
+
+    pub fn connected_at(&self) -> u32 {
+        self.connected_at
+    }
+
     pub fn session(&self) -> &Session {
         &self.session
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{DelayedBytes, FlushResult, Queued, WriteChunk, WriteQueue, STREAM_CHUNK_SIZE};
+    use bytes::Bytes;
+    use futures::Future;
+    use std::io;
+    use std::sync::Arc;
+
+    fn flush(queued: Queued) -> bool {
+        match queued {
+            Queued::Flush => true,
+            _ => false,
+        }
+    }
+
+    fn pending(queued: Queued) -> FlushResult {
+        match queued {
+            Queued::Pending(result) => result,
+            queued => panic!("message is not pending: {:?}", queued),
+        }
+    }
+
+    #[test]
+    fn delayed_bytes_are_limited() {
+        let delayed = DelayedBytes::default();
+        // single message is accepted, even if it exceeds the limit
+        assert!(delayed.reserve(150, 100));
+        assert!(!delayed.reserve(10, 100));
+        delayed.release(150);
+
+        assert!(delayed.reserve(60, 100));
+        assert!(delayed.reserve(40, 100));
+        assert!(!delayed.reserve(1, 100));
+        delayed.release(60);
+        assert!(delayed.reserve(50, 100));
+    }
+
+    #[test]
+    fn write_queue_coalesces_messages() {
+        let mut queue = WriteQueue::default();
+        // first message starts the flush
+        assert!(flush(queue.push(&[1, 2])));
+        // while flush is pending, messages are appended
+        let result = pending(queue.push(&[3]));
+        assert_eq!(queue.take(), Some(WriteChunk::Owned(vec![1, 2, 3])));
+        // while write is in progress, messages are still appended
+        let next_result = pending(queue.push(&[4]));
+        assert_eq!(queue.take(), Some(WriteChunk::Owned(vec![4])));
+        // flush is finished when queue is empty
+        assert_eq!(queue.take(), None);
+        assert_eq!(result.wait(), Ok(Ok(())));
+        assert_eq!(next_result.wait(), Ok(Ok(())));
+        assert!(flush(queue.push(&[5])));
+    }
+
+    #[test]
+    fn write_queue_reports_failed_write() {
+        let mut queue = WriteQueue::default();
+        assert!(flush(queue.push(&[1, 2])));
+        let result = pending(queue.push(&[3]));
+        assert_eq!(queue.take(), Some(WriteChunk::Owned(vec![1, 2, 3])));
+        let next_result = pending(queue.push(&[4]));
+
+        // queued messages are dropped and their senders are notified
+        queue.fail(io::ErrorKind::BrokenPipe);
+        assert_eq!(queue.take(), None);
+        assert_eq!(result.wait(), Ok(Err(io::ErrorKind::BrokenPipe)));
+        assert_eq!(next_result.wait(), Ok(Err(io::ErrorKind::BrokenPipe)));
+
+        // nothing is queued after the failure
+        match queue.push(&[5]) {
+            Queued::Failed(io::ErrorKind::BrokenPipe) => (),
+            queued => panic!("message is queued after failure: {:?}", queued),
+        }
+        queue.push_shared(Arc::new(Bytes::from(vec![6u8])));
+        assert_eq!(queue.take(), None);
+    }
+
+    #[test]
+    fn write_queue_streams_shared_payload_by_chunks() {
+        let payload = Arc::new(Bytes::from(vec![7u8; STREAM_CHUNK_SIZE + 10]));
+        let mut queue = WriteQueue::default();
+        assert!(flush(queue.push(&[1, 2])));
+        queue.push_shared(payload.clone());
+        pending(queue.push(&[3]));
+
+        assert_eq!(queue.take(), Some(WriteChunk::Owned(vec![1, 2])));
+        let chunk = queue.take().unwrap();
+        assert_eq!(chunk.as_ref().len(), STREAM_CHUNK_SIZE);
+        let chunk = queue.take().unwrap();
+        assert_eq!(chunk.as_ref(), &[7u8; 10][..]);
+        assert_eq!(queue.take(), Some(WriteChunk::Owned(vec![3])));
+        assert_eq!(queue.take(), None);
+        // payload isn't copied
+        assert_eq!(Arc::strong_count(&payload), 1);
+    }
+}
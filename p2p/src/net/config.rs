@@ -4,18 +4,27 @@ use network::Magic;
 use std::net::SocketAddr;
 use util::nonce::{NonceGenerator, RandomNonce};
 use util::time::{RealTime, Time};
+use util::PeerPolicy;
 
 #[derive(Debug, Clone)]
 pub struct Config {
     pub protocol_version: u32,
     pub protocol_minimum: u32,
+    /// Peers with lower protocol version are disconnected after handshake.
+    pub min_peer_version: u32,
+    /// Peers, rejected or deprioritized after handshake because of their version or user agent.
+    pub peer_policy: PeerPolicy,
     pub magic: Magic,
+    /// Address, advertised to peers. It could differ from addresses the node is listening on
+    /// (e.g. when node is behind NAT).
     pub local_address: SocketAddr,
     pub services: Services,
     pub user_agent: String,
     pub start_height: i32,
     pub relay: bool,
     pub network: network::Network,
+    /// SOCKS5 proxy, all outbound connections are made through.
+    pub proxy: Option<SocketAddr>,
 }
 
 impl Config {
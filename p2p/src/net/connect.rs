@@ -1,32 +1,70 @@
-use futures::{Async, Future, Poll};
-use io::{deadline, handshake, Deadline, Handshake};
+use futures::{future, Async, Future, Poll};
+use io::{deadline, handshake, socks5_connect, Deadline, Handshake, Socks5Target};
+use message::common::Services;
 use message::types::Version;
-use message::Error;
+use message::{Compression, Error};
 use net::{Config, Connection};
 use network::Magic;
 use std::io;
 use std::net::SocketAddr;
 use std::time::Duration;
-use tokio_core::net::{TcpStream, TcpStreamNew};
+use tokio_core::net::TcpStream;
 use tokio_core::reactor::Handle;
+use util::ip_to_onion;
+
+/// Connection and handshake timeout.
+const CONNECT_TIMEOUT_S: u64 = 5;
+/// Connection and handshake timeout, when connecting through the proxy.
+const PROXY_CONNECT_TIMEOUT_S: u64 = 20;
+
+type TcpConnect = Box<dyn Future<Item = TcpStream, Error = io::Error> + Send>;
 
 pub fn connect(address: &SocketAddr, handle: &Handle, config: &Config) -> Deadline<Connect> {
     let connect = Connect {
         state: ConnectState::TcpConnect {
-            future: TcpStream::connect(address, handle),
+            future: tcp_connect(address, handle, config.proxy),
             version: Some(config.version(address)),
         },
         magic: config.magic,
         address: *address,
         protocol_minimum: config.protocol_minimum,
+        services: config.services,
     };
 
-    deadline(Duration::new(5, 0), handle, connect).expect("Failed to create timeout")
+    let timeout = match config.proxy {
+        Some(_) => PROXY_CONNECT_TIMEOUT_S,
+        None => CONNECT_TIMEOUT_S,
+    };
+    deadline(Duration::new(timeout, 0), handle, connect).expect("Failed to create timeout")
+}
+
+/// Opens TCP connection to the address, either directly or through the SOCKS5 proxy.
+/// Hidden services (addresses from the OnionCat range) are only reachable through the proxy.
+fn tcp_connect(address: &SocketAddr, handle: &Handle, proxy: Option<SocketAddr>) -> TcpConnect {
+    let onion = ip_to_onion(&address.ip());
+    match (proxy, onion) {
+        (Some(proxy), onion) => {
+            let target = match onion {
+                Some(name) => Socks5Target::Domain(name, address.port()),
+                None => Socks5Target::Address(*address),
+            };
+            Box::new(
+                TcpStream::connect(&proxy, handle)
+                    .and_then(move |stream| socks5_connect(stream, target))
+                    .map(|(stream, _)| stream),
+            )
+        }
+        (None, Some(_)) => Box::new(future::err(io::Error::new(
+            io::ErrorKind::Other,
+            "hidden service is only reachable through the proxy",
+        ))),
+        (None, None) => Box::new(TcpStream::connect(address, handle)),
+    }
 }
 
 enum ConnectState {
     TcpConnect {
-        future: TcpStreamNew,
+        future: TcpConnect,
         version: Option<Version>,
     },
     Handshake(Handshake<TcpStream>),
@@ -38,6 +76,7 @@ pub struct Connect {
     magic: Magic,
     address: SocketAddr,
     protocol_minimum: u32,
+    services: Services,
 }
 
 impl Future for Connect {
@@ -64,6 +103,9 @@ impl Future for Connect {
                 let connection = Connection {
                     stream: stream.into(),
                     services: result.version.services(),
+                    compression: Compression::negotiate(self.services, result.version.services()),
+                    compressed_headers: self.services.compressed_headers()
+                        && result.version.services().compressed_headers(),
                     version: result.negotiated_version,
                     version_message: result.version,
                     magic: self.magic,
@@ -1,6 +1,7 @@
 use io::SharedTcpStream;
 use message::common::Services;
 use message::types;
+use message::Compression;
 use network::Magic;
 use std::net;
 
@@ -10,5 +11,9 @@ pub struct Connection {
     pub version_message: types::Version,
     pub magic: Magic,
     pub services: Services,
+    /// Compression of large messages, negotiated during handshake.
+    pub compression: Compression,
+    /// Headers are sent with `headers2` message, negotiated during handshake.
+    pub compressed_headers: bool,
     pub address: net::SocketAddr,
 }
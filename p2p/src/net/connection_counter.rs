@@ -8,21 +8,46 @@ pub struct ConnectionCounter {
     /// Current number of outbound connections.
     current_outbound_connections: AtomicUsize,
     /// Maximum number of inbound connections.
-    max_inbound_connections: u32,
+    max_inbound_connections: AtomicUsize,
     /// Maximum number of outbound connections.
-    max_outbound_connections: u32,
+    max_outbound_connections: AtomicUsize,
+    /// Current number of outbound block-relay-only connections.
+    current_block_relay_connections: AtomicUsize,
+    /// Maximum number of outbound block-relay-only connections.
+    max_block_relay_connections: AtomicUsize,
 }
 
 impl ConnectionCounter {
-    pub fn new(max_inbound_connections: u32, max_outbound_connections: u32) -> Self {
+    pub fn new(
+        max_inbound_connections: u32,
+        max_outbound_connections: u32,
+        max_block_relay_connections: u32,
+    ) -> Self {
         ConnectionCounter {
             current_inbound_connections: AtomicUsize::new(0),
             current_outbound_connections: AtomicUsize::new(0),
-            max_inbound_connections: max_inbound_connections,
-            max_outbound_connections: max_outbound_connections,
+            max_inbound_connections: AtomicUsize::new(max_inbound_connections as usize),
+            max_outbound_connections: AtomicUsize::new(max_outbound_connections as usize),
+            current_block_relay_connections: AtomicUsize::new(0),
+            max_block_relay_connections: AtomicUsize::new(max_block_relay_connections as usize),
         }
     }
 
+    /// Changes maximal numbers of connections. Connections above the new maximum are not closed.
+    pub fn set_max_connections(
+        &self,
+        max_inbound_connections: u32,
+        max_outbound_connections: u32,
+        max_block_relay_connections: u32,
+    ) {
+        self.max_inbound_connections
+            .store(max_inbound_connections as usize, Ordering::Release);
+        self.max_outbound_connections
+            .store(max_outbound_connections as usize, Ordering::Release);
+        self.max_block_relay_connections
+            .store(max_block_relay_connections as usize, Ordering::Release);
+    }
+
     /// Increases inbound connections counter by 1.
     pub fn note_new_inbound_connection(&self) {
         self.current_inbound_connections
@@ -49,6 +74,19 @@ impl ConnectionCounter {
             .fetch_sub(1, Ordering::AcqRel);
     }
 
+    /// Increases outbound block-relay-only connections counter by 1.
+    pub fn note_new_block_relay_connection(&self) {
+        self.current_block_relay_connections
+            .fetch_add(1, Ordering::AcqRel);
+    }
+
+    /// Decreases outbound block-relay-only connections counter by 1.
+    /// If it underflows, it means, that there is a logic error.
+    pub fn note_close_block_relay_connection(&self) {
+        self.current_block_relay_connections
+            .fetch_sub(1, Ordering::AcqRel);
+    }
+
     /// Returns number of inbound connections needed to reach the maximum
     pub fn inbound_connections_needed(&self) -> u32 {
         let ic = self.inbound_connections();
@@ -61,16 +99,31 @@ impl ConnectionCounter {
         oc.1 - cmp::min(oc.0, oc.1)
     }
 
+    /// Returns number of outbound block-relay-only connections needed to reach the maximum
+    pub fn block_relay_connections_needed(&self) -> u32 {
+        let bc = self.block_relay_connections();
+        bc.1 - cmp::min(bc.0, bc.1)
+    }
+
     /// Returns a pair of unsigned integers where first element is current number of connections and the second is max.
     pub fn inbound_connections(&self) -> (u32, u32) {
         let current = self.current_inbound_connections.load(Ordering::Acquire) as u32;
-        (current, self.max_inbound_connections)
+        let max = self.max_inbound_connections.load(Ordering::Acquire) as u32;
+        (current, max)
     }
 
     /// Returns a pair of unsigned integers where first element is current number of connections and the second is max.
     pub fn outbound_connections(&self) -> (u32, u32) {
         let current = self.current_outbound_connections.load(Ordering::Acquire) as u32;
-        (current, self.max_outbound_connections)
+        let max = self.max_outbound_connections.load(Ordering::Acquire) as u32;
+        (current, max)
+    }
+
+    /// Returns a pair of unsigned integers where first element is current number of connections and the second is max.
+    pub fn block_relay_connections(&self) -> (u32, u32) {
+        let current = self.current_block_relay_connections.load(Ordering::Acquire) as u32;
+        let max = self.max_block_relay_connections.load(Ordering::Acquire) as u32;
+        (current, max)
     }
 }
 
@@ -80,7 +133,7 @@ mod tests {
 
     #[test]
     fn test_inbound_connection_counter() {
-        let cc = ConnectionCounter::new(5, 10);
+        let cc = ConnectionCounter::new(5, 10, 0);
         assert_eq!(cc.inbound_connections_needed(), 5);
         assert_eq!(cc.inbound_connections(), (0, 5));
         cc.note_new_inbound_connection();
@@ -102,7 +155,7 @@ mod tests {
 
     #[test]
     fn test_outbound_connection_counter() {
-        let cc = ConnectionCounter::new(0, 4);
+        let cc = ConnectionCounter::new(0, 4, 0);
         assert_eq!(cc.outbound_connections_needed(), 4);
         assert_eq!(cc.outbound_connections(), (0, 4));
         cc.note_new_outbound_connection();
@@ -113,4 +166,32 @@ mod tests {
         assert_eq!(cc.outbound_connections_needed(), 3);
         assert_eq!(cc.outbound_connections(), (1, 4));
     }
+
+    #[test]
+    fn test_block_relay_connection_counter() {
+        let cc = ConnectionCounter::new(0, 4, 2);
+        assert_eq!(cc.block_relay_connections_needed(), 2);
+        cc.note_new_block_relay_connection();
+        assert_eq!(cc.block_relay_connections_needed(), 1);
+        assert_eq!(cc.block_relay_connections(), (1, 2));
+        // block-relay-only connections are counted separately
+        assert_eq!(cc.outbound_connections_needed(), 4);
+        cc.note_close_block_relay_connection();
+        assert_eq!(cc.block_relay_connections(), (0, 2));
+    }
+
+    #[test]
+    fn test_max_connections_are_changed() {
+        let cc = ConnectionCounter::new(2, 4, 1);
+        cc.note_new_inbound_connection();
+        cc.note_new_inbound_connection();
+        cc.note_new_outbound_connection();
+
+        cc.set_max_connections(1, 8, 0);
+        // existing connections are kept
+        assert_eq!(cc.inbound_connections(), (2, 1));
+        assert_eq!(cc.inbound_connections_needed(), 0);
+        assert_eq!(cc.outbound_connections_needed(), 7);
+        assert_eq!(cc.block_relay_connections_needed(), 0);
+    }
 }
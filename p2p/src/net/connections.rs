@@ -6,7 +6,7 @@ use std::collections::{HashMap, HashSet};
 use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
 use std::{mem, net};
-use util::{Direction, PeerInfo};
+use util::{ConnectionCount, Direction, PeerInfo};
 use PeerId;
 
 const SYNCHRONOUS_RESPONSES: bool = true;
@@ -51,6 +51,28 @@ impl Connections {
         self.channels.read().len()
     }
 
+    /// Returns numbers of connections by direction (without copying peers info).
+    pub fn count_by_direction(&self) -> ConnectionCount {
+        let channels = self.channels.read();
+        let mut count = ConnectionCount {
+            total: channels.len(),
+            ..Default::default()
+        };
+        for channel in channels.values() {
+            match channel.direction() {
+                Direction::Inbound => count.inbound += 1,
+                Direction::Outbound => count.outbound += 1,
+            }
+            if channel.is_block_relay_only() {
+                count.block_relay += 1;
+            }
+            if channel.is_whitelisted() {
+                count.whitelisted += 1;
+            }
+        }
+        count
+    }
+
     /// Stores new channel.
     /// Returnes a shared pointer to it.
     pub fn store<T>(
@@ -72,8 +94,14 @@ impl Connections {
                 .user_agent()
                 .unwrap_or("unknown".into()),
             direction: direction,
+            block_relay_only: T::is_block_relay_only(),
+            whitelisted: context.is_whitelisted(&connection.address.ip()),
+            deprioritized: context.is_deprioritized(&connection),
+            experiments: context.select_experiments(),
             version: connection.version,
             version_message: connection.version_message,
+            compression: connection.compression,
+            compressed_headers: connection.compressed_headers,
             magic: connection.magic,
         };
 
@@ -0,0 +1,204 @@
+use net::Channel;
+use std::cmp::{self, Ordering};
+use PeerId;
+
+/// Number of longest-connected peers, which are never evicted.
+const PROTECTED_LONG_LIVED_PEERS: usize = 4;
+/// Number of inbound peers with lowest ping, which are never evicted.
+const PROTECTED_LOW_PING_PEERS: usize = 4;
+/// Peer is considered stalled if it has sent us nothing but pings/pongs during this interval.
+const STALLED_PEER_TIMEOUT_S: u32 = 20 * 60;
+
+/// Connection properties, considered when selecting peer to evict.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EvictionCandidate {
+    /// Peer id.
+    pub id: PeerId,
+    /// Time when connection has been established.
+    pub connected_at: u32,
+    /// Time of last received message other than ping/pong.
+    pub last_useful_recv: u32,
+    /// Minimal ping time.
+    pub min_ping: Option<f64>,
+    /// Peer is deprioritized by connection policy.
+    pub deprioritized: bool,
+    /// Peer only relays blocks to us.
+    pub block_relay_only: bool,
+}
+
+impl EvictionCandidate {
+    pub fn with_channel(channel: &Channel) -> Self {
+        let stats = channel.session().stats().lock();
+        EvictionCandidate {
+            id: channel.peer_info().id,
+            connected_at: channel.connected_at(),
+            last_useful_recv: cmp::max(stats.last_useful_recv, channel.connected_at()),
+            min_ping: stats.min_ping,
+            deprioritized: channel.is_deprioritized(),
+            block_relay_only: channel.is_block_relay_only(),
+        }
+    }
+}
+
+/// Removes longest-connected peers from candidates list.
+fn protect_long_lived(candidates: &mut Vec<EvictionCandidate>) {
+    candidates.sort_by_key(|candidate| candidate.connected_at);
+    let protected = cmp::min(PROTECTED_LONG_LIVED_PEERS, candidates.len());
+    candidates.drain(..protected);
+}
+
+/// Removes peers with lowest ping from candidates list.
+fn protect_low_ping(candidates: &mut Vec<EvictionCandidate>) {
+    // peers that have never responded to ping go last
+    candidates.sort_by(|a, b| match (a.min_ping, b.min_ping) {
+        (Some(a), Some(b)) => a.partial_cmp(&b).unwrap_or(Ordering::Equal),
+        (Some(_), None) => Ordering::Less,
+        (None, Some(_)) => Ordering::Greater,
+        (None, None) => Ordering::Equal,
+    });
+    let protected = cmp::min(PROTECTED_LOW_PING_PEERS, candidates.len());
+    candidates.drain(..protected);
+}
+
+/// Selects inbound peer to disconnect in favor of new inbound connection. Youngest deprioritized
+/// peer is evicted first. Returns None if all peers are protected.
+pub fn select_inbound_peer_to_evict(mut candidates: Vec<EvictionCandidate>) -> Option<PeerId> {
+    if let Some(candidate) = candidates
+        .iter()
+        .filter(|candidate| candidate.deprioritized)
+        .max_by_key(|candidate| candidate.connected_at)
+    {
+        return Some(candidate.id);
+    }
+
+    protect_long_lived(&mut candidates);
+    protect_low_ping(&mut candidates);
+
+    // evict peer that is silent for the longest time. Prefer younger connection if there's a tie
+    candidates
+        .into_iter()
+        .min_by(|a, b| {
+            a.last_useful_recv
+                .cmp(&b.last_useful_recv)
+                .then(b.connected_at.cmp(&a.connected_at))
+        })
+        .map(|candidate| candidate.id)
+}
+
+/// Selects stalled outbound peer, which should be replaced with another one.
+/// Returns None if there are no stalled peers, or if all of them are protected.
+pub fn select_stalled_outbound_peer(
+    mut candidates: Vec<EvictionCandidate>,
+    now: u32,
+) -> Option<PeerId> {
+    // block-relay-only peers send us nothing between blocks, so they are never considered stalled
+    candidates.retain(|candidate| !candidate.block_relay_only);
+    protect_long_lived(&mut candidates);
+
+    candidates
+        .into_iter()
+        .filter(|candidate| now.saturating_sub(candidate.last_useful_recv) > STALLED_PEER_TIMEOUT_S)
+        .min_by_key(|candidate| candidate.last_useful_recv)
+        .map(|candidate| candidate.id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        select_inbound_peer_to_evict, select_stalled_outbound_peer, EvictionCandidate,
+        STALLED_PEER_TIMEOUT_S,
+    };
+
+    fn candidate(id: usize, connected_at: u32, last_useful_recv: u32) -> EvictionCandidate {
+        EvictionCandidate {
+            id: id,
+            connected_at: connected_at,
+            last_useful_recv: last_useful_recv,
+            min_ping: None,
+            deprioritized: false,
+            block_relay_only: false,
+        }
+    }
+
+    #[test]
+    fn inbound_peers_are_protected() {
+        // 4 long-lived + 4 fast peers are protected
+        let candidates = (0..8)
+            .map(|id| EvictionCandidate {
+                min_ping: Some(id as f64),
+                ..candidate(id, id as u32, 0)
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(select_inbound_peer_to_evict(candidates), None);
+    }
+
+    #[test]
+    fn inbound_silent_peer_is_evicted() {
+        let mut candidates = (0..8)
+            .map(|id| EvictionCandidate {
+                min_ping: Some(id as f64),
+                ..candidate(id, id as u32, 100)
+            })
+            .collect::<Vec<_>>();
+        candidates.push(candidate(8, 10, 50));
+        candidates.push(candidate(9, 11, 20));
+        candidates.push(candidate(10, 12, 20));
+        // 9 and 10 are equally silent => younger connection is evicted
+        assert_eq!(select_inbound_peer_to_evict(candidates), Some(10));
+    }
+
+    #[test]
+    fn inbound_long_lived_silent_peer_is_protected() {
+        let mut candidates = (1..9)
+            .map(|id| EvictionCandidate {
+                min_ping: Some(id as f64),
+                ..candidate(id, 10 + id as u32, 100)
+            })
+            .collect::<Vec<_>>();
+        // the oldest connection, which is also the most silent one
+        candidates.push(candidate(0, 0, 0));
+        assert_eq!(select_inbound_peer_to_evict(candidates), Some(8));
+    }
+
+    #[test]
+    fn inbound_deprioritized_peer_is_evicted_first() {
+        let mut candidates = (0..8)
+            .map(|id| EvictionCandidate {
+                min_ping: Some(id as f64),
+                ..candidate(id, id as u32, 100)
+            })
+            .collect::<Vec<_>>();
+        candidates.push(candidate(8, 10, 0));
+        // long-lived and fast, but deprioritized
+        candidates[0].deprioritized = true;
+        candidates[1].deprioritized = true;
+        assert_eq!(select_inbound_peer_to_evict(candidates), Some(1));
+    }
+
+    #[test]
+    fn stalled_outbound_peer_is_selected() {
+        let now = 10 * STALLED_PEER_TIMEOUT_S;
+        let mut candidates = (0..4)
+            .map(|id| candidate(id, id as u32, 0))
+            .collect::<Vec<_>>();
+        // all stalled peers are protected
+        assert_eq!(select_stalled_outbound_peer(candidates.clone(), now), None);
+
+        candidates.push(candidate(4, 100, now - 10));
+        assert_eq!(select_stalled_outbound_peer(candidates.clone(), now), None);
+
+        candidates.push(candidate(5, 100, now - STALLED_PEER_TIMEOUT_S - 1));
+        candidates.push(candidate(6, 100, now - STALLED_PEER_TIMEOUT_S - 2));
+        assert_eq!(
+            select_stalled_outbound_peer(candidates.clone(), now),
+            Some(6)
+        );
+
+        // silent block-relay-only peer is kept
+        candidates.push(EvictionCandidate {
+            block_relay_only: true,
+            ..candidate(7, 100, 0)
+        });
+        assert_eq!(select_stalled_outbound_peer(candidates, now), Some(6));
+    }
+}
@@ -5,15 +5,19 @@ mod connect;
 mod connection;
 mod connection_counter;
 mod connections;
+mod eviction;
 mod peer_context;
 mod stats;
 
 pub use self::accept_connection::{accept_connection, AcceptConnection};
-pub use self::channel::Channel;
+pub use self::channel::{Channel, Queued};
 pub use self::config::Config;
 pub use self::connect::{connect, Connect};
 pub use self::connection::Connection;
 pub use self::connection_counter::ConnectionCounter;
 pub use self::connections::Connections;
+pub use self::eviction::{
+    select_inbound_peer_to_evict, select_stalled_outbound_peer, EvictionCandidate,
+};
 pub use self::peer_context::PeerContext;
-pub use self::stats::PeerStats;
+pub use self::stats::{Flow, PeerStats};
@@ -1,5 +1,5 @@
 use futures::{finished, lazy};
-use message::{Message, Payload};
+use message::{Message, MessageDecoder, Payload, SharedPayload};
 use net::PeerStats;
 use p2p::Context;
 use parking_lot::Mutex;
@@ -9,6 +9,8 @@ use util::{ConfigurableSynchronizer, PeerInfo, ResponseQueue, Responses, Synchro
 pub struct PeerContext {
     context: Arc<Context>,
     info: PeerInfo,
+    /// Decoder of messages, received from the peer
+    decoder: MessageDecoder,
     synchronizer: Mutex<ConfigurableSynchronizer>,
     response_queue: Mutex<ResponseQueue>,
     stats: Mutex<PeerStats>,
@@ -16,9 +18,13 @@ pub struct PeerContext {
 
 impl PeerContext {
     pub fn new(context: Arc<Context>, info: PeerInfo, synchronous: bool) -> Self {
+        let decoder = MessageDecoder::new(info.magic)
+            .with_version(info.version)
+            .with_compression(info.compression);
         PeerContext {
             context: context,
             info: info,
+            decoder: decoder,
             synchronizer: Mutex::new(ConfigurableSynchronizer::new(synchronous)),
             response_queue: Mutex::default(),
             stats: Mutex::default(),
@@ -29,8 +35,13 @@ impl PeerContext {
     where
         T: Payload,
     {
-        Message::new(self.info.magic, self.info.version, payload)
-            .expect("failed to create outgoing message")
+        Message::with_compression(
+            self.info.magic,
+            self.info.version,
+            self.info.compression,
+            payload,
+        )
+        .expect("failed to create outgoing message")
     }
 
     fn send_awaiting(
@@ -85,6 +96,12 @@ impl PeerContext {
         self.context.spawn(send);
     }
 
+    /// Request with payload, shared with other peers, is always automatically send.
+    pub fn send_shared_request(&self, payload: &SharedPayload) {
+        let send = Context::send_shared_to_peer(self.context.clone(), self.info.id, payload);
+        self.context.spawn(send);
+    }
+
     pub fn declare_response(&self) -> u32 {
         let d = self.synchronizer.lock().declare_response();
         trace!("declared response: {}", d);
@@ -154,6 +171,10 @@ impl PeerContext {
         &self.info
     }
 
+    pub fn decoder(&self) -> &MessageDecoder {
+        &self.decoder
+    }
+
     pub fn global(&self) -> &Arc<Context> {
         &self.context
     }
@@ -54,12 +54,21 @@ pub enum Flow {
 pub struct PeerStats<T: Interval = RealInterval> {
     pub last_send: u32,
     pub last_recv: u32,
+    /// Time of last received message other than ping/pong.
+    pub last_useful_recv: u32,
 
     pub total_send: u64,
     pub total_recv: u64,
 
+    /// Number of bytes, saved by compression of sent messages.
+    pub compression_saved_send: u64,
+    /// Number of bytes, saved by compression of received messages.
+    pub compression_saved_recv: u64,
+
     pub avg_ping: f64,
     pub min_ping: Option<f64>,
+    /// Round-trip time of the last answered ping, which has not been consumed yet.
+    pub last_rtt: Option<f64>,
 
     send_avg: HashMap<Command, RunningAverage>,
     recv_avg: HashMap<Command, RunningAverage>,
@@ -96,6 +105,13 @@ impl<I: Interval> PeerStats<I> {
         }
     }
 
+    pub fn report_compression_saving(&mut self, dir: Flow, bytes: usize) {
+        match dir {
+            Flow::Receive => self.compression_saved_recv += bytes as u64,
+            Flow::Send => self.compression_saved_send += bytes as u64,
+        }
+    }
+
     fn report_ping_send(&mut self) {
         self.last_ping = Some(self.interval.now());
         self.ping_count += 1;
@@ -111,6 +127,7 @@ impl<I: Interval> PeerStats<I> {
                 f64::from(dur.as_secs() as u32) + f64::from(dur.subsec_nanos()) / 1e9
             };
             self.min_ping = Some(self.min_ping.unwrap_or(ENORMOUS_PING_DELAY).min(update));
+            self.last_rtt = Some(update);
             self.avg_ping += (update - self.avg_ping) / (self.ping_count as f64);
         }
     }
@@ -121,6 +138,8 @@ impl<I: Interval> PeerStats<I> {
 
         if command == Pong::command() {
             self.report_pong_recv();
+        } else if command != Ping::command() {
+            self.last_useful_recv = self.last_recv;
         }
 
         match self.recv_avg.entry(command) {
@@ -188,6 +207,11 @@ mod tests {
         stats.report_recv("pong".into(), 50);
         assert!(stats.avg_ping > 0.03);
         assert!(stats.avg_ping < 0.1);
+        assert_eq!(stats.last_rtt, Some(stats.avg_ping));
+        assert_eq!(stats.last_useful_recv, 0);
+
+        stats.report_recv("inv".into(), 50);
+        assert_eq!(stats.last_useful_recv, stats.last_recv);
     }
 
     #[test]
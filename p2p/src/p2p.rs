@@ -1,31 +1,51 @@
 use abstract_ns::Resolver;
 use futures::stream::Stream;
-use futures::{failed, finished, Future};
+use futures::{failed, finished, lazy, Future};
 use futures_cpupool::{Builder as CpuPoolBuilder, CpuPool};
-use io::DeadlineStatus;
-use message::common::Services;
+use io::{socks5_resolve, write_message, DeadlineStatus};
+use message::common::{Command, Services};
 use message::types::addr::AddressEntry;
-use message::{Message, MessageResult, Payload};
+use message::types::reject::{Reject, RejectCode};
+use message::{Error as MessageError, Message, MessageResult, Payload, SharedPayload};
 use net::{
-    accept_connection, connect, Channel, Config as NetConfig, ConnectionCounter, Connections,
+    accept_connection, connect, select_inbound_peer_to_evict, select_stalled_outbound_peer,
+    Channel, Config as NetConfig, Connection, ConnectionCounter, Connections, EvictionCandidate,
+    Flow, Queued,
 };
+use net2::TcpBuilder;
 use ns_dns_tokio::DnsResolver;
-use parking_lot::RwLock;
+use parking_lot::{Mutex, RwLock};
 use protocol::{InboundSyncConnectionRef, LocalSyncNodeRef, OutboundSyncConnectionRef};
 use rand::seq::SliceRandom;
 use rand::thread_rng;
-use session::{NormalSessionFactory, SeednodeSessionFactory, SessionFactory};
+use session::{
+    BlockRelaySessionFactory, NormalSessionFactory, SeednodeSessionFactory, SessionFactory,
+};
 use std::net::SocketAddr;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
-use std::{error, io, net, time};
+use std::{cmp, error, io, net, time};
 use tokio_core::net::{TcpListener, TcpStream};
 use tokio_core::reactor::{Handle, Interval, Remote, Timeout};
 use tokio_io::IoFuture;
-use util::{Direction, Node, NodeTable, NodeTableError};
+use util::{
+    parse_onion_address, start_port_mapping, BandwidthLimit, BandwidthThrottle, Direction,
+    ExperimentFlags, ExperimentGroupStats, ExperimentStats, Node, NodeTable, NodeTableError,
+    PeerCompressionStats, PeerFilter, PeerPolicyCounters, PeerPolicyDecision, PeerPolicyStats,
+    PeerVersionStats, RejectedPeerVersions,
+};
 use {Config, PeerId};
 
 pub type BoxedEmptyFuture = Box<dyn Future<Item = (), Error = ()> + Send>;
 
+/// Malformed messages are skipped and the peer is penalized, until it sends more than this
+/// number of malformed messages. Then the connection is closed.
+const MAX_MALFORMED_MESSAGES: usize = 8;
+/// Max size of messages to the single peer, delayed by the bandwidth throttle. When it is
+/// reached, further throttled messages to the peer are dropped, so its requests are not served
+/// until delayed messages are sent.
+const MAX_DELAYED_BYTES_PER_PEER: usize = 16 * 1024 * 1024;
+
 /// Network context.
 pub struct Context {
     /// Connections.
@@ -34,6 +54,12 @@ pub struct Context {
     connection_counter: ConnectionCounter,
     /// Node Table.
     node_table: RwLock<NodeTable>,
+    /// Peers, rejected because of obsolete protocol version.
+    rejected_peer_versions: RwLock<RejectedPeerVersions>,
+    /// Peers, matched by connection policy.
+    peer_policy_counters: RwLock<PeerPolicyCounters>,
+    /// Bandwidth limits of sent messages.
+    bandwidth_throttle: Mutex<BandwidthThrottle>,
     /// Thread pool handle.
     pool: CpuPool,
     /// Remote event loop handle.
@@ -57,11 +83,18 @@ impl Context {
             connection_counter: ConnectionCounter::new(
                 config.inbound_connections,
                 config.outbound_connections,
+                config.block_relay_connections,
             ),
             node_table: RwLock::new(NodeTable::from_file(
                 config.preferable_services,
                 &config.node_table_path,
             )?),
+            rejected_peer_versions: Default::default(),
+            peer_policy_counters: Default::default(),
+            bandwidth_throttle: Mutex::new(BandwidthThrottle::new(
+                config.bandwidth_limits.clone(),
+                ::time::precise_time_s(),
+            )),
             pool: pool_handle,
             remote: remote,
             local_sync_node: local_sync_node,
@@ -146,31 +179,49 @@ impl Context {
                     // print traces
                     let ic = context.connection_counter.inbound_connections();
                     let oc = context.connection_counter.outbound_connections();
+                    let bc = context.connection_counter.block_relay_connections();
                     info!("Inbound connections: ({}/{})", ic.0, ic.1);
                     info!("Outbound connections: ({}/{})", oc.0, oc.1);
+                    info!("Block-relay-only connections: ({}/{})", bc.0, bc.1);
 
                     for channel in context.connections.channels().values() {
                         channel.session().maintain();
                     }
 
+                    // replace stalled peer, so that it is reconnected below
+                    context.replace_stalled_outbound_peer();
+
                     let needed = context.connection_counter.outbound_connections_needed() as usize;
-                    if needed != 0 {
+                    let block_relay_needed =
+                        context.connection_counter.block_relay_connections_needed() as usize;
+                    if needed + block_relay_needed != 0 {
                         let used_addresses = context.connections.addresses();
                         let peers = context.node_table.read().nodes_with_services(
                             &Services::default(),
                             context.config.internet_protocol,
                             &used_addresses,
-                            needed,
+                            needed + block_relay_needed,
                         );
-                        let addresses = peers
+                        let mut addresses = peers
                             .into_iter()
                             .map(|peer| peer.address())
                             .collect::<Vec<_>>();
+                        // full-relay connections are more important => they're created first
+                        let block_relay_addresses =
+                            addresses.split_off(cmp::min(needed, addresses.len()));
 
                         trace!("Creating {} more outbound connections", addresses.len());
                         for address in addresses {
                             Context::connect::<NormalSessionFactory>(context.clone(), address);
                         }
+
+                        trace!(
+                            "Creating {} more block-relay-only connections",
+                            block_relay_addresses.len()
+                        );
+                        for address in block_relay_addresses {
+                            Context::connect::<BlockRelaySessionFactory>(context.clone(), address);
+                        }
                     }
 
                     if let Err(_err) = context
@@ -189,6 +240,64 @@ impl Context {
         c.spawn(interval);
     }
 
+    /// Returns eviction candidates for all connections in given direction. Whitelisted peers are never evicted.
+    fn eviction_candidates(&self, direction: Direction) -> Vec<EvictionCandidate> {
+        self.connections
+            .channels()
+            .values()
+            .filter(|channel| channel.direction() == direction && !channel.is_whitelisted())
+            .map(|channel| EvictionCandidate::with_channel(channel))
+            .collect()
+    }
+
+    /// Disconnects the worst inbound peer to free slot for new inbound connection.
+    /// Returns false if all inbound peers are protected from eviction.
+    fn evict_inbound_peer(&self) -> bool {
+        match select_inbound_peer_to_evict(self.eviction_candidates(Direction::Inbound)) {
+            Some(peer) => {
+                trace!("Evicting inbound peer#{} to free connection slot", peer);
+                self.close_channel(peer);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Disconnects stalled outbound peer if there's another node to connect to instead.
+    fn replace_stalled_outbound_peer(&self) {
+        let used_addresses = self.connections.addresses();
+        let has_replacement = !self
+            .node_table
+            .read()
+            .nodes_with_services(
+                &Services::default(),
+                self.config.internet_protocol,
+                &used_addresses,
+                1,
+            )
+            .is_empty();
+        if !has_replacement {
+            return;
+        }
+
+        let now = ::time::get_time().sec as u32;
+        if let Some(peer) =
+            select_stalled_outbound_peer(self.eviction_candidates(Direction::Outbound), now)
+        {
+            trace!("Replacing stalled outbound peer#{}", peer);
+            self.close_channel(peer);
+        }
+    }
+
+    /// Decreases counter of outbound connections of given type.
+    fn note_close_outbound_connection(&self, block_relay_only: bool) {
+        if block_relay_only {
+            self.connection_counter.note_close_block_relay_connection();
+        } else {
+            self.connection_counter.note_close_outbound_connection();
+        }
+    }
+
     /// Connect to socket using given context and handle.
     fn connect_future<T>(
         context: Arc<Context>,
@@ -200,12 +309,24 @@ impl Context {
         T: SessionFactory,
     {
         trace!("Trying to connect to: {}", socket);
+        let min_peer_version = config.min_peer_version;
         let connection = connect(&socket, handle, config);
         Box::new(
             connection
                 .then(move |result| {
                     match result {
                         Ok(DeadlineStatus::Meet(Ok(connection))) => {
+                            if connection.version_message.version() < min_peer_version {
+                                context.node_table.write().note_failure(&socket);
+                                context.note_close_outbound_connection(T::is_block_relay_only());
+                                return context.reject_obsolete_peer(connection, min_peer_version);
+                            }
+                            if let Some(filter) = context.apply_peer_policy(&connection) {
+                                context.node_table.write().note_failure(&socket);
+                                context.note_close_outbound_connection(T::is_block_relay_only());
+                                return context.reject_peer_by_policy(connection, filter);
+                            }
+
                             // successfull hanshake
                             trace!("Connected to {}", connection.address);
                             context
@@ -227,7 +348,7 @@ impl Context {
                             trace!("Handshake with {} failed: {}", socket, err);
                             // TODO: close socket
                             context.node_table.write().note_failure(&socket);
-                            context.connection_counter.note_close_outbound_connection();
+                            context.note_close_outbound_connection(T::is_block_relay_only());
                             Box::new(finished(Ok(())))
                         }
                         Ok(DeadlineStatus::Timeout) => {
@@ -235,14 +356,14 @@ impl Context {
                             trace!("Handshake with {} timed out", socket);
                             // TODO: close socket
                             context.node_table.write().note_failure(&socket);
-                            context.connection_counter.note_close_outbound_connection();
+                            context.note_close_outbound_connection(T::is_block_relay_only());
                             Box::new(finished(Ok(())))
                         }
                         Err(err) => {
                             // network error
                             trace!("Unable to connect to {}: {}", socket, err);
                             context.node_table.write().note_failure(&socket);
-                            context.connection_counter.note_close_outbound_connection();
+                            context.note_close_outbound_connection(T::is_block_relay_only());
                             Box::new(finished(Ok(())))
                         }
                     }
@@ -256,14 +377,19 @@ impl Context {
     where
         T: SessionFactory,
     {
-        context.connection_counter.note_new_outbound_connection();
+        if T::is_block_relay_only() {
+            context.connection_counter.note_new_block_relay_connection();
+        } else {
+            context.connection_counter.note_new_outbound_connection();
+        }
         context.remote.clone().spawn(move |handle| {
-            let config = context.config.clone();
+            let mut config = context.config.connection.clone();
+            // ask peer to not relay anything but blocks
+            if T::is_block_relay_only() {
+                config.relay = false;
+            }
             context.pool.clone().spawn(Context::connect_future::<T>(
-                context,
-                socket,
-                handle,
-                &config.connection,
+                context, socket, handle, &config,
             ))
         })
     }
@@ -284,6 +410,16 @@ impl Context {
                 .then(move |result| {
                     match result {
                         Ok(DeadlineStatus::Meet(Ok(connection))) => {
+                            if connection.version_message.version() < config.min_peer_version {
+                                context.connection_counter.note_close_inbound_connection();
+                                return context
+                                    .reject_obsolete_peer(connection, config.min_peer_version);
+                            }
+                            if let Some(filter) = context.apply_peer_policy(&connection) {
+                                context.connection_counter.note_close_inbound_connection();
+                                return context.reject_peer_by_policy(connection, filter);
+                            }
+
                             // successfull hanshake
                             trace!("Accepted connection from {}", connection.address);
                             // PROTOTYPE ONLY: Replace port to the default one
@@ -355,14 +491,15 @@ impl Context {
         })
     }
 
-    /// Starts tcp server and listens for incomming connections.
+    /// Starts tcp server and listens for incomming connections on given address.
     pub fn listen(
         context: Arc<Context>,
         handle: &Handle,
+        address: &SocketAddr,
         config: NetConfig,
     ) -> Result<BoxedEmptyFuture, io::Error> {
-        trace!("Starting tcp server");
-        let server = TcpListener::bind(&config.local_address, handle)?;
+        trace!("Starting tcp server on {}", address);
+        let server = TcpListener::from_listener(bind_listener(address)?, address, handle)?;
         let server = Box::new(
             server
                 .incoming()
@@ -370,7 +507,10 @@ impl Context {
                     // because we acquire atomic value twice,
                     // it may happen that accept slightly more connections than we need
                     // we don't mind
-                    if context.connection_counter.inbound_connections_needed() > 0 {
+                    if context.is_whitelisted(&socket.ip())
+                        || context.connection_counter.inbound_connections_needed() > 0
+                        || context.evict_inbound_peer()
+                    {
                         Context::accept_connection(context.clone(), stream, socket, config.clone());
                     } else {
                         // ignore result
@@ -406,18 +546,10 @@ impl Context {
                             context.spawn(on_message);
                             Box::new(finished(Ok(())))
                         }
-                        Err(err) => {
-                            // protocol error
-                            context.close_channel_with_error(channel.peer_info().id, &err);
-                            Box::new(finished(Err(err)))
-                        }
+                        Err(err) => Context::on_malformed_message(context, channel, err),
                     }
                 }
-                Ok(Err(err)) => {
-                    // protocol error
-                    context.close_channel_with_error(channel.peer_info().id, &err);
-                    Box::new(finished(Err(err)))
-                }
+                Ok(Err(err)) => Context::on_malformed_message(context, channel, err),
                 Err(err) => {
                     // network error
                     // TODO: remote node was just turned off. should we mark it as not reliable?
@@ -428,6 +560,30 @@ impl Context {
         }))
     }
 
+    /// Called when message can't be read or handled. Message is skipped and the peer is penalized,
+    /// if the stream of messages is still readable and the peer has not sent too many
+    /// malformed messages. Otherwise the connection is closed.
+    fn on_malformed_message(
+        context: Arc<Context>,
+        channel: Arc<Channel>,
+        err: MessageError,
+    ) -> IoFuture<MessageResult<()>> {
+        let info = channel.peer_info();
+        if !err.is_recoverable() || channel.note_malformed_message() > MAX_MALFORMED_MESSAGES {
+            // protocol error
+            context.close_channel_with_error(info.id, &err);
+            return Box::new(finished(Err(err)));
+        }
+
+        warn!("Skipping malformed message from {}: {}", info.address, err);
+        if !info.whitelisted {
+            context.penalize_node(&info.address);
+        }
+        let on_message = Context::on_message(context.clone(), channel);
+        context.spawn(on_message);
+        Box::new(finished(Ok(())))
+    }
+
     /// Send message to a channel with given peer id.
     pub fn send_to_peer<T>(context: Arc<Context>, peer: PeerId, payload: &T) -> IoFuture<()>
     where
@@ -436,14 +592,22 @@ impl Context {
         match context.connections.channel(peer) {
             Some(channel) => {
                 let info = channel.peer_info();
-                let message = Message::new(info.magic, info.version, payload)
-                    .expect("failed to create outgoing message");
-                channel
-                    .session()
-                    .stats()
-                    .lock()
-                    .report_send(T::command().into(), message.len());
-                Context::send(context, channel, message)
+                let message =
+                    Message::with_compression(info.magic, info.version, info.compression, payload)
+                        .expect("failed to create outgoing message");
+                {
+                    let mut stats = channel.session().stats().lock();
+                    stats.report_send(T::command().into(), message.len());
+                    stats.report_compression_saving(Flow::Send, message.compression_saving());
+                }
+                let len = message.len();
+                Context::send_throttled(
+                    context,
+                    channel,
+                    &T::command().into(),
+                    len,
+                    move |context, channel| Context::send(context, channel, message),
+                )
             }
             None => {
                 // peer no longer exists.
@@ -453,6 +617,109 @@ impl Context {
         }
     }
 
+    /// Send message with payload, shared with other peers. Payload is streamed to the peer without copying,
+    /// unless it must be compressed for this peer.
+    pub fn send_shared_to_peer(
+        context: Arc<Context>,
+        peer: PeerId,
+        payload: &SharedPayload,
+    ) -> IoFuture<()> {
+        let channel = match context.connections.channel(peer) {
+            Some(channel) => channel,
+            // peer no longer exists.
+            None => return Box::new(finished(())),
+        };
+
+        let info = channel.peer_info();
+        let header = payload.header(info.magic);
+        let uncompressed_len = header.len() + payload.size();
+        if info.compression.is_applied_to(payload.command()) {
+            let message = payload.compressed_message(info.magic, info.compression);
+            {
+                let mut stats = channel.session().stats().lock();
+                stats.report_send(payload.command().clone(), message.len());
+                stats.report_compression_saving(
+                    Flow::Send,
+                    uncompressed_len.saturating_sub(message.len()),
+                );
+            }
+            let len = message.len();
+            return Context::send_throttled(
+                context,
+                channel,
+                payload.command(),
+                len,
+                move |context, channel| Context::send(context, channel, message),
+            );
+        }
+
+        channel
+            .session()
+            .stats()
+            .lock()
+            .report_send(payload.command().clone(), uncompressed_len);
+        let shared_payload = payload.payload().clone();
+        Context::send_throttled(
+            context,
+            channel,
+            payload.command(),
+            uncompressed_len,
+            move |context, channel| {
+                let queued = channel.queue_shared_message(&header, shared_payload);
+                Context::flush_queued(context, channel, queued)
+            },
+        )
+    }
+
+    /// Sends message now, or schedules its sending, if it exceeds bandwidth limit of its type.
+    /// Messages of the same type are delayed in order, so they are never reordered.
+    fn send_throttled<F>(
+        context: Arc<Context>,
+        channel: Arc<Channel>,
+        command: &Command,
+        len: usize,
+        send: F,
+    ) -> IoFuture<()>
+    where
+        F: FnOnce(Arc<Context>, Arc<Channel>) -> IoFuture<()> + Send + 'static,
+    {
+        let info = channel.peer_info();
+        let delay = context.bandwidth_throttle.lock().delay(
+            command,
+            info.direction,
+            info.deprioritized,
+            len as u64,
+            ::time::precise_time_s(),
+        );
+        if delay <= 0f64 {
+            return send(context, channel);
+        }
+        if !channel.reserve_delayed_bytes(len, MAX_DELAYED_BYTES_PER_PEER) {
+            debug!(
+                "Dropping {} message to {}: too many messages are delayed",
+                command, info.address
+            );
+            return Box::new(finished(()));
+        }
+
+        trace!(
+            "Delaying {} message to {} by {:.3}s",
+            command,
+            info.address,
+            delay
+        );
+        let delayed_context = context.clone();
+        context.execute_after(
+            time::Duration::from_millis((delay * 1000f64).ceil() as u64),
+            move || {
+                channel.release_delayed_bytes(len);
+                let send = send(delayed_context.clone(), channel);
+                delayed_context.spawn(send);
+            },
+        );
+        Box::new(finished(()))
+    }
+
     pub fn send_message_to_peer<T>(context: Arc<Context>, peer: PeerId, message: T) -> IoFuture<()>
     where
         T: AsRef<[u8]> + Send + 'static,
@@ -468,23 +735,50 @@ impl Context {
     }
 
     /// Send message using given channel.
-    pub fn send<T>(_context: Arc<Context>, channel: Arc<Channel>, message: T) -> IoFuture<()>
+    pub fn send<T>(context: Arc<Context>, channel: Arc<Channel>, message: T) -> IoFuture<()>
     where
         T: AsRef<[u8]> + Send + 'static,
     {
-        //trace!("Sending {} message to {}", T::command(), channel.peer_info().address);
-        Box::new(channel.write_message(message).then(move |result| {
-            match result {
-                Ok(_) => {
-                    // successful send
-                    //trace!("Sent {} message to {}", T::command(), channel.peer_info().address);
-                    Box::new(finished(()))
-                }
-                Err(err) => {
-                    // network error
-                    // closing connection is handled in on_message`
-                    Box::new(failed(err))
-                }
+        // messages, sent to the same peer before the flush is executed, are written with single write
+        let queued = channel.queue_message(message.as_ref());
+        Context::flush_queued(context, channel, queued)
+    }
+
+    /// Starts the flush of the queued message, or waits for the result of the pending flush.
+    fn flush_queued(context: Arc<Context>, channel: Arc<Channel>, queued: Queued) -> IoFuture<()> {
+        match queued {
+            Queued::Flush => Context::flush(context, channel),
+            Queued::Pending(result) => Box::new(result.then(|result| match result {
+                Ok(Ok(())) => Ok(()),
+                Ok(Err(kind)) => Err(io::Error::from(kind)),
+                Err(_) => Err(io::Error::new(
+                    io::ErrorKind::Other,
+                    "flush has been cancelled",
+                )),
+            })),
+            Queued::Failed(kind) => Box::new(failed(io::Error::from(kind))),
+        }
+    }
+
+    /// Write all queued messages to the channel.
+    fn flush(context: Arc<Context>, channel: Arc<Channel>) -> IoFuture<()> {
+        Box::new(lazy(move || -> IoFuture<()> {
+            match channel.take_queued_messages() {
+                Some(messages) => Box::new(channel.write_message(messages).then(
+                    move |result| -> IoFuture<()> {
+                        match result {
+                            // successful send => write messages, queued during this write
+                            Ok(_) => Context::flush(context, channel),
+                            // network error => drop queued messages and close the connection
+                            Err(err) => {
+                                channel.fail_queued_messages(err.kind());
+                                context.close_channel_with_error(channel.peer_info().id, &err);
+                                Box::new(failed(err))
+                            }
+                        }
+                    },
+                )),
+                None => Box::new(finished(())),
             }
         }))
     }
@@ -498,7 +792,7 @@ impl Context {
             channel.shutdown();
             match info.direction {
                 Direction::Inbound => self.connection_counter.note_close_inbound_connection(),
-                Direction::Outbound => self.connection_counter.note_close_outbound_connection(),
+                Direction::Outbound => self.note_close_outbound_connection(info.block_relay_only),
             }
         }
     }
@@ -514,10 +808,12 @@ impl Context {
                 error.description()
             );
             channel.shutdown();
-            self.node_table.write().note_failure(&info.address);
+            if !info.whitelisted {
+                self.node_table.write().note_failure(&info.address);
+            }
             match info.direction {
                 Direction::Inbound => self.connection_counter.note_close_inbound_connection(),
-                Direction::Outbound => self.connection_counter.note_close_outbound_connection(),
+                Direction::Outbound => self.note_close_outbound_connection(info.block_relay_only),
             }
         }
     }
@@ -536,9 +832,205 @@ impl Context {
         &self.connections
     }
 
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    /// Changes maximal numbers of inbound and outbound connections. Existing connections are not closed,
+    /// missing outbound connections are opened by the next autoconnect round.
+    pub fn set_max_connections(&self, inbound_connections: u32, outbound_connections: u32) {
+        let (_, block_relay_connections) = self.connection_counter.block_relay_connections();
+        self.connection_counter.set_max_connections(
+            inbound_connections,
+            outbound_connections,
+            block_relay_connections,
+        );
+    }
+
+    /// Is peer with given address exempt from bans, eviction and inbound slots limit?
+    pub fn is_whitelisted(&self, ip: &net::IpAddr) -> bool {
+        self.config.whitelist.iter().any(|range| range.contains(ip))
+    }
+
+    /// Does peer match connection policy deprioritize filter?
+    pub fn is_deprioritized(&self, connection: &Connection) -> bool {
+        match self.peer_policy_decision(connection) {
+            PeerPolicyDecision::Deprioritize(_) => true,
+            _ => false,
+        }
+    }
+
+    /// Randomly selects experimental protocol behaviors for new connection.
+    pub fn select_experiments(&self) -> ExperimentFlags {
+        self.config.experiments.select(&mut thread_rng())
+    }
+
+    /// Returns outcomes of all configured experiments, collected from active connections.
+    pub fn experiment_stats(&self) -> Vec<ExperimentStats> {
+        let channels = self.connections.channels();
+        self.config
+            .experiments
+            .experiments()
+            .into_iter()
+            .map(|(name, percent)| {
+                let mut stats = ExperimentStats {
+                    name: name,
+                    percent: percent,
+                    enabled: ExperimentGroupStats::default(),
+                    disabled: ExperimentGroupStats::default(),
+                };
+                for channel in channels.values() {
+                    let peer_stats = channel.session().stats().lock();
+                    let group = if channel.peer_info().experiments.is_enabled(name) {
+                        &mut stats.enabled
+                    } else {
+                        &mut stats.disabled
+                    };
+                    group.add_peer(
+                        peer_stats.total_send,
+                        peer_stats.total_recv,
+                        peer_stats.avg_ping,
+                    );
+                }
+                stats
+            })
+            .collect()
+    }
+
     pub fn nodes(&self) -> Vec<Node> {
         self.node_table.read().nodes()
     }
+
+    /// Returns outcomes of messages compression for all active connections.
+    pub fn compression_stats(&self) -> Vec<PeerCompressionStats> {
+        let mut stats: Vec<_> = self
+            .connections
+            .channels()
+            .values()
+            .map(|channel| {
+                let info = channel.peer_info();
+                let peer_stats = channel.session().stats().lock();
+                PeerCompressionStats {
+                    id: info.id,
+                    address: info.address,
+                    compression: info.compression,
+                    bytes_sent: peer_stats.total_send,
+                    bytes_received: peer_stats.total_recv,
+                    saved_sent: peer_stats.compression_saved_send,
+                    saved_received: peer_stats.compression_saved_recv,
+                }
+            })
+            .collect();
+        stats.sort_by_key(|stats| stats.id);
+        stats
+    }
+
+    /// Returns numbers of connected and rejected peers by protocol version.
+    pub fn peer_version_stats(&self) -> Vec<PeerVersionStats> {
+        let connected_versions = self
+            .connections
+            .channels()
+            .values()
+            .map(|channel| channel.peer_info().version_message.version())
+            .collect::<Vec<_>>();
+        self.rejected_peer_versions.read().stats(connected_versions)
+    }
+
+    /// Replaces bandwidth limits of sent messages. Already delayed messages are sent on schedule.
+    pub fn set_bandwidth_limits(&self, limits: Vec<BandwidthLimit>) {
+        self.bandwidth_throttle
+            .lock()
+            .set_limits(limits, ::time::precise_time_s());
+    }
+
+    /// Returns numbers of peers, rejected or deprioritized by connection policy filters.
+    pub fn peer_policy_stats(&self) -> Vec<PeerPolicyStats> {
+        self.peer_policy_counters.read().stats()
+    }
+
+    fn peer_policy_decision(&self, connection: &Connection) -> PeerPolicyDecision {
+        let user_agent = connection.version_message.user_agent().unwrap_or_default();
+        self.config
+            .connection
+            .peer_policy
+            .check(connection.version_message.version(), &user_agent)
+    }
+
+    /// Checks peer against connection policy and counts the decision.
+    /// Returns filter, which peer is rejected by.
+    fn apply_peer_policy(&self, connection: &Connection) -> Option<PeerFilter> {
+        let decision = self.peer_policy_decision(connection);
+        self.peer_policy_counters.write().note_decision(&decision);
+        match decision {
+            PeerPolicyDecision::Accept => None,
+            PeerPolicyDecision::Deprioritize(filter) => {
+                info!(
+                    "Deprioritizing {}: protocol version {}, user agent {:?} match {}",
+                    connection.address,
+                    connection.version_message.version(),
+                    connection.version_message.user_agent().unwrap_or_default(),
+                    filter
+                );
+                None
+            }
+            PeerPolicyDecision::Reject(filter) => Some(filter),
+        }
+    }
+
+    /// Politely disconnects from peer, which is rejected by connection policy.
+    fn reject_peer_by_policy(
+        &self,
+        connection: Connection,
+        filter: PeerFilter,
+    ) -> IoFuture<MessageResult<()>> {
+        info!(
+            "Disconnecting from {}: protocol version {}, user agent {:?} match {}",
+            connection.address,
+            connection.version_message.version(),
+            connection.version_message.user_agent().unwrap_or_default(),
+            filter
+        );
+
+        let reject = Reject {
+            message: "version".into(),
+            code: RejectCode::Nonstandard,
+            reason: format!("Rejected by connection policy: {}", filter),
+        };
+        Self::write_reject(connection, reject)
+    }
+
+    /// Politely disconnects from peer, which protocol version is below the minimum.
+    fn reject_obsolete_peer(
+        &self,
+        connection: Connection,
+        min_peer_version: u32,
+    ) -> IoFuture<MessageResult<()>> {
+        let peer_version = connection.version_message.version();
+        info!(
+            "Disconnecting from {}: protocol version {} is below minimum {}",
+            connection.address, peer_version, min_peer_version
+        );
+        self.rejected_peer_versions
+            .write()
+            .note_rejected(peer_version);
+
+        let reject = Reject {
+            message: "version".into(),
+            code: RejectCode::Obsolate,
+            reason: format!(
+                "Protocol version {} is below minimum {}",
+                peer_version, min_peer_version
+            ),
+        };
+        Self::write_reject(connection, reject)
+    }
+
+    /// Sends reject message to the peer, which is being disconnected after handshake.
+    fn write_reject(connection: Connection, reject: Reject) -> IoFuture<MessageResult<()>> {
+        let message = Message::new(connection.magic, connection.version, &reject)
+            .expect("reject message should always be serialized correctly");
+        Box::new(write_message(connection.stream, message).then(|_| finished(Ok(()))))
+    }
 }
 
 pub struct P2P {
@@ -550,6 +1042,8 @@ pub struct P2P {
     config: Config,
     /// Network context.
     context: Arc<Context>,
+    /// Stops NAT-PMP port mapping.
+    port_mapping_stop: Arc<AtomicBool>,
 }
 
 impl Drop for P2P {
@@ -562,6 +1056,7 @@ impl Drop for P2P {
             // done, now let's finish on_message
             channel.shutdown();
         }
+        self.port_mapping_stop.store(true, Ordering::SeqCst);
     }
 }
 
@@ -588,6 +1083,7 @@ impl P2P {
             pool: pool,
             context: Arc::new(context),
             config: config,
+            port_mapping_stop: Arc::new(AtomicBool::new(false)),
         };
 
         Ok(p2p)
@@ -624,6 +1120,10 @@ impl P2P {
     }
 
     pub fn connect_to_seednode(&self, resolver: &dyn Resolver, seednode: &str) {
+        if let Some(proxy) = self.config.connection.proxy {
+            return self.connect_to_seednode_through_proxy(proxy, seednode);
+        }
+
         let owned_seednode = seednode.to_owned();
         let context = self.context.clone();
         let dns_lookup = resolver.resolve(seednode).then(move |result| {
@@ -654,13 +1154,85 @@ impl P2P {
         self.event_loop_handle.spawn(pool_work);
     }
 
+    /// Resolves seednode through the proxy, so that DNS requests are not leaked,
+    /// and connects to it through the proxy.
+    fn connect_to_seednode_through_proxy(&self, proxy: net::SocketAddr, seednode: &str) {
+        let context = self.context.clone();
+        let default_port = self.config.connection.network.port();
+        match parse_onion_address(seednode, default_port) {
+            Ok(Some(address)) => {
+                Context::connect::<SeednodeSessionFactory>(context, address);
+                return;
+            }
+            Ok(None) => (),
+            Err(err) => {
+                warn!("Invalid seednode {}: {}", seednode, err);
+                return;
+            }
+        }
+
+        let (host, port) = match seednode.rfind(':') {
+            Some(pos) => match seednode[pos + 1..].parse() {
+                Ok(port) => (seednode[..pos].to_owned(), port),
+                Err(_) => {
+                    warn!("Invalid port of seednode {}", seednode);
+                    return;
+                }
+            },
+            None => (seednode.to_owned(), default_port),
+        };
+        let owned_seednode = seednode.to_owned();
+        let proxy_lookup = TcpStream::connect(&proxy, &self.event_loop_handle)
+            .and_then(move |stream| socks5_resolve(stream, host))
+            .then(move |result| {
+                match result {
+                    Ok((_, ip)) => {
+                        let socket = net::SocketAddr::new(ip, port);
+                        trace!(
+                            "Proxy lookup of seednode {} finished. Connecting to {}",
+                            owned_seednode,
+                            socket
+                        );
+                        Context::connect::<SeednodeSessionFactory>(context, socket);
+                    }
+                    Err(err) => {
+                        trace!(
+                            "Proxy lookup of seednode {} failed: {}",
+                            owned_seednode,
+                            err
+                        );
+                    }
+                }
+                finished(())
+            });
+        self.event_loop_handle.spawn(proxy_lookup);
+    }
+
     fn listen(&self) -> Result<(), Box<dyn error::Error>> {
-        let server = Context::listen(
-            self.context.clone(),
-            &self.event_loop_handle,
-            self.config.connection.clone(),
-        )?;
-        self.event_loop_handle.spawn(server);
+        for address in &self.config.listen {
+            let server = Context::listen(
+                self.context.clone(),
+                &self.event_loop_handle,
+                address,
+                self.config.connection.clone(),
+            )
+            .map_err(|err| format!("Failed to listen on {}: {}", address, err))?;
+            self.event_loop_handle.spawn(server);
+        }
+
+        if self.config.port_mapping {
+            let mut ports: Vec<u16> = self
+                .config
+                .listen
+                .iter()
+                .filter(|address| address.is_ipv4())
+                .map(|address| address.port())
+                .collect();
+            ports.dedup();
+            if let Err(err) = start_port_mapping(ports, None, self.port_mapping_stop.clone()) {
+                warn!("NAT-PMP port mapping is not started: {}", err);
+            }
+        }
         Ok(())
     }
 
@@ -668,3 +1240,21 @@ impl P2P {
         &self.context
     }
 }
+
+/// Binds listening socket. IPv6 sockets only accept IPv6 connections, so that node could listen
+/// on both IPv4 and IPv6 wildcard addresses with the same port.
+fn bind_listener(address: &SocketAddr) -> Result<net::TcpListener, io::Error> {
+    let builder = match *address {
+        SocketAddr::V4(_) => TcpBuilder::new_v4()?,
+        SocketAddr::V6(_) => {
+            let builder = TcpBuilder::new_v6()?;
+            builder.only_v6(true)?;
+            builder
+        }
+    };
+    if cfg!(unix) {
+        builder.reuse_address(true)?;
+    }
+    builder.bind(address)?;
+    builder.listen(1024)
+}
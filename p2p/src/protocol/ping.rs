@@ -1,25 +1,117 @@
 use bytes::Bytes;
 use message::common::Command;
 use message::types::{Ping, Pong};
-use message::{deserialize_payload, Error, Payload};
+use message::{Error, Payload};
 use net::PeerContext;
 use protocol::Protocol;
 use std::sync::Arc;
 use time;
 use util::nonce::{NonceGenerator, RandomNonce};
 
-/// Time that must pass since last message from this peer, before we send ping request
+/// Interval between ping requests. Round-trip time of every request is measured
 const PING_INTERVAL_S: f64 = 60f64;
-/// If peer has not responded to our ping request with pong during this interval => close connection
+/// If peer has not responded to our ping request with pong during this interval => ping is timed out
 const MAX_PING_RESPONSE_TIME_S: f64 = 60f64;
+/// If this number of ping requests in a row are timed out => close connection
+const MAX_PING_TIMEOUTS: u32 = 3;
 
-/// Ping state
-#[derive(Debug, Copy, Clone, PartialEq)]
-enum State {
-    /// Peer is sending us messages && we wait for `PING_INTERVAL_S` to pass before sending ping request
-    WaitingTimeout(f64),
-    /// Ping message is sent to the peer && we are waiting for pong response for `MAX_PING_RESPONSE_TIME_S`
-    WaitingPong(f64),
+/// Action, which should be taken when ping timer fires.
+#[derive(Debug, PartialEq)]
+enum PingAction {
+    /// Nothing to do
+    None,
+    /// Send ping request with given nonce
+    SendPing(u64),
+    /// Close connection, because there were no messages from peer since ping request
+    CloseSilent,
+    /// Close connection, because `MAX_PING_TIMEOUTS` ping requests in a row have timed out
+    CloseLagging,
+}
+
+/// Kind of the received pong response.
+#[derive(Debug, PartialEq)]
+enum PongKind {
+    /// Response to the last ping request. Its round-trip time is measured
+    Answered,
+    /// Late response to the timed out request. Its round-trip time is not measured, because
+    /// pong is matched against the time of the last request
+    Late,
+}
+
+/// Ping requests timing, independent of the connection.
+#[derive(Debug)]
+struct PingState {
+    /// Time of the last message from the peer.
+    last_message: f64,
+    /// Time of the last ping request. None if ping has never been sent.
+    last_ping: Option<f64>,
+    /// Nonce of the ping request, which is waiting for pong response.
+    last_ping_nonce: Option<u64>,
+    /// Number of ping requests in a row, which have not been answered in time.
+    ping_timeouts: u32,
+}
+
+impl PingState {
+    fn new(now: f64) -> Self {
+        PingState {
+            last_message: now,
+            last_ping: None,
+            last_ping_nonce: None,
+            ping_timeouts: 0,
+        }
+    }
+
+    fn maintain<F>(&mut self, now: f64, nonce: F) -> PingAction
+    where
+        F: FnOnce() -> u64,
+    {
+        if let (Some(last_ping), Some(_)) = (self.last_ping, self.last_ping_nonce) {
+            if now - last_ping <= MAX_PING_RESPONSE_TIME_S {
+                return PingAction::None;
+            }
+
+            // if no new messages from peer since ping request => disconnect
+            if self.last_message < last_ping {
+                return PingAction::CloseSilent;
+            }
+
+            // peer is alive, but it is lagging => disconnect if it keeps lagging
+            self.ping_timeouts += 1;
+            if self.ping_timeouts >= MAX_PING_TIMEOUTS {
+                return PingAction::CloseLagging;
+            }
+        }
+
+        // send ping request periodically to measure round-trip time
+        if self
+            .last_ping
+            .map_or(true, |last_ping| now - last_ping > PING_INTERVAL_S)
+        {
+            let nonce = nonce();
+            self.last_ping = Some(now);
+            self.last_ping_nonce = Some(nonce);
+            return PingAction::SendPing(nonce);
+        }
+
+        PingAction::None
+    }
+
+    fn on_message(&mut self, now: f64) {
+        self.last_message = now;
+    }
+
+    fn on_pong(&mut self, nonce: u64) -> Result<PongKind, Error> {
+        match self.last_ping_nonce {
+            Some(last_nonce) if last_nonce == nonce => {
+                self.last_ping_nonce = None;
+                self.ping_timeouts = 0;
+                Ok(PongKind::Answered)
+            }
+            // late response to the timed out request
+            Some(_) if self.ping_timeouts != 0 => Ok(PongKind::Late),
+            _ => Err(Error::InvalidCommand),
+        }
+    }
 }
 
 pub struct PingProtocol<T = RandomNonce, C = PeerContext> {
@@ -27,10 +119,8 @@ pub struct PingProtocol<T = RandomNonce, C = PeerContext> {
     context: Arc<C>,
     /// Nonce generator.
     nonce_generator: T,
-    /// Ping state
-    state: State,
-    /// Last nonce sent in the ping message.
-    last_ping_nonce: Option<u64>,
+    /// Ping requests timing.
+    state: PingState,
 }
 
 impl PingProtocol {
@@ -38,8 +128,7 @@ impl PingProtocol {
         PingProtocol {
             context: context,
             nonce_generator: RandomNonce::default(),
-            state: State::WaitingTimeout(time::precise_time_s()),
-            last_ping_nonce: None,
+            state: PingState::new(time::precise_time_s()),
         }
     }
 }
@@ -52,46 +141,129 @@ impl Protocol for PingProtocol {
 
     fn maintain(&mut self) {
         let now = time::precise_time_s();
-        match self.state {
-            State::WaitingTimeout(time) => {
-                // send ping request if enough time has passed since last message
-                if now - time > PING_INTERVAL_S {
-                    let nonce = self.nonce_generator.get();
-                    self.state = State::WaitingPong(now);
-                    self.last_ping_nonce = Some(nonce);
-                    let ping = Ping::new(nonce);
-                    self.context.send_request(&ping);
-                }
+        let nonce_generator = &self.nonce_generator;
+        match self.state.maintain(now, || nonce_generator.get()) {
+            PingAction::None => (),
+            PingAction::SendPing(nonce) => self.context.send_request(&Ping::new(nonce)),
+            PingAction::CloseSilent => {
+                trace!(
+                    "closing connection to peer {}: no messages for last {} seconds",
+                    self.context.info().id,
+                    now - self.state.last_message
+                );
+                self.context.close();
             }
-            State::WaitingPong(time) => {
-                // if no new messages from peer for last MAX_PING_RESPONSE_TIME_S => disconnect
-                if now - time > MAX_PING_RESPONSE_TIME_S {
-                    trace!(
-                        "closing connection to peer {}: no messages for last {} seconds",
-                        self.context.info().id,
-                        now - time
-                    );
-                    self.context.close();
-                }
+            PingAction::CloseLagging => {
+                trace!(
+                    "closing connection to peer {}: {} ping requests in a row have timed out",
+                    self.context.info().id,
+                    self.state.ping_timeouts
+                );
+                self.context.close();
             }
         }
     }
 
     fn on_message(&mut self, command: &Command, payload: &Bytes) -> Result<(), Error> {
         // we have received new message => do not close connection because of timeout
-        self.state = State::WaitingTimeout(time::precise_time_s());
+        self.state.on_message(time::precise_time_s());
 
         if command == &Ping::command() {
-            let ping: Ping = deserialize_payload(payload, self.context.info().version)?;
+            let ping: Ping = self.context.decoder().read_payload(payload)?;
             let pong = Pong::new(ping.nonce);
             self.context.send_response_inline(&pong);
         } else if command == &Pong::command() {
-            let pong: Pong = deserialize_payload(payload, self.context.info().version)?;
-            if Some(pong.nonce) != self.last_ping_nonce.take() {
-                return Err(Error::InvalidCommand);
+            let pong: Pong = self.context.decoder().read_payload(payload)?;
+            if self.state.on_pong(pong.nonce)? == PongKind::Late {
+                // protocols are notified in order, so the sample is dropped before sync
+                // protocol reports it
+                self.context.stats().lock().last_rtt = None;
             }
         }
 
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{PingAction, PingState, PongKind, MAX_PING_RESPONSE_TIME_S, PING_INTERVAL_S};
+    use message::Error;
+
+    #[test]
+    fn ping_is_sent_periodically() {
+        let mut state = PingState::new(0f64);
+        assert_eq!(state.maintain(0f64, || 1), PingAction::SendPing(1));
+        assert_eq!(state.on_pong(1), Ok(PongKind::Answered));
+        assert_eq!(state.maintain(PING_INTERVAL_S, || 2), PingAction::None);
+        assert_eq!(
+            state.maintain(PING_INTERVAL_S + 1f64, || 2),
+            PingAction::SendPing(2)
+        );
+    }
+
+    #[test]
+    fn ping_timeouts_are_counted() {
+        let mut state = PingState::new(0f64);
+        assert_eq!(state.maintain(0f64, || 1), PingAction::SendPing(1));
+        // peer is not timed out, while waiting for the response
+        assert_eq!(
+            state.maintain(MAX_PING_RESPONSE_TIME_S, || 2),
+            PingAction::None
+        );
+        assert_eq!(state.ping_timeouts, 0);
+
+        // peer is alive, so the timed out request is replaced with the new one
+        state.on_message(1f64);
+        let now = MAX_PING_RESPONSE_TIME_S + 1f64;
+        assert_eq!(state.maintain(now, || 2), PingAction::SendPing(2));
+        assert_eq!(state.ping_timeouts, 1);
+
+        // answered request resets the counter
+        assert_eq!(state.on_pong(2), Ok(PongKind::Answered));
+        assert_eq!(state.ping_timeouts, 0);
+    }
+
+    #[test]
+    fn lagging_peer_is_disconnected_after_max_ping_timeouts() {
+        let mut state = PingState::new(0f64);
+        let mut now = 0f64;
+        assert_eq!(state.maintain(now, || 0), PingAction::SendPing(0));
+        for nonce in 1..3 {
+            now += MAX_PING_RESPONSE_TIME_S + 1f64;
+            state.on_message(now - 1f64);
+            assert_eq!(state.maintain(now, || nonce), PingAction::SendPing(nonce));
+        }
+        assert_eq!(state.ping_timeouts, 2);
+
+        now += MAX_PING_RESPONSE_TIME_S + 1f64;
+        state.on_message(now - 1f64);
+        assert_eq!(state.maintain(now, || 3), PingAction::CloseLagging);
+    }
+
+    #[test]
+    fn silent_peer_is_disconnected() {
+        let mut state = PingState::new(0f64);
+        assert_eq!(state.maintain(1f64, || 1), PingAction::SendPing(1));
+        assert_eq!(
+            state.maintain(MAX_PING_RESPONSE_TIME_S + 2f64, || 2),
+            PingAction::CloseSilent
+        );
+    }
+
+    #[test]
+    fn late_pong_is_not_measured() {
+        let mut state = PingState::new(0f64);
+        assert_eq!(state.maintain(0f64, || 1), PingAction::SendPing(1));
+        state.on_message(1f64);
+        let now = MAX_PING_RESPONSE_TIME_S + 1f64;
+        assert_eq!(state.maintain(now, || 2), PingAction::SendPing(2));
+
+        // late response to the first request is accepted, but it doesn't answer the second one
+        assert_eq!(state.on_pong(1), Ok(PongKind::Late));
+        assert_eq!(state.ping_timeouts, 1);
+        assert_eq!(state.on_pong(2), Ok(PongKind::Answered));
+        // unexpected pong is not accepted
+        assert_eq!(state.on_pong(1), Err(Error::InvalidCommand));
+    }
+}
@@ -1,8 +1,10 @@
 use bytes::Bytes;
-use message::{deserialize_payload, types, Command, Error, Payload, Services};
+use message::{types, Command, Error, Payload, Services, SharedPayload};
 use net::PeerContext;
 use protocol::Protocol;
+use std::net::SocketAddr;
 use std::sync::Arc;
+use util::{Direction, ExperimentFlags};
 
 pub type InboundSyncConnectionRef = Box<dyn InboundSyncConnection>;
 pub type OutboundSyncConnectionRef = Arc<dyn OutboundSyncConnection>;
@@ -34,6 +36,8 @@ pub trait InboundSyncConnection: Send + Sync {
     fn on_headers(&self, message: types::Headers);
     fn on_sendheaders(&self, message: types::SendHeaders);
     fn on_notfound(&self, message: types::NotFound);
+    /// Round-trip time of the ping request to the peer has been measured (in seconds)
+    fn on_latency(&self, rtt: f64);
 }
 
 pub trait OutboundSyncConnection: Send + Sync {
@@ -42,12 +46,22 @@ pub trait OutboundSyncConnection: Send + Sync {
     fn send_getblocks(&self, message: &types::GetBlocks);
     fn send_getheaders(&self, message: &types::GetHeaders);
     fn send_block(&self, message: &types::Block);
+    /// Send block, serialized once for all peers it is sent to.
+    fn send_serialized_block(&self, block: &SharedPayload);
     fn send_headers(&self, message: &types::Headers);
     fn respond_headers(&self, message: &types::Headers, id: u32);
     fn send_sendheaders(&self, message: &types::SendHeaders);
     fn send_notfound(&self, message: &types::NotFound);
     fn ignored(&self, id: u32);
     fn close(&self);
+    /// Experimental protocol behaviors, enabled for this connection.
+    fn experiments(&self) -> ExperimentFlags;
+    /// Direction of this connection.
+    fn direction(&self) -> Direction;
+    /// Is peer whitelisted? Whitelisted peers are not disconnected due to misbehavior.
+    fn is_whitelisted(&self) -> bool;
+    /// Address of the peer.
+    fn address(&self) -> SocketAddr;
 }
 
 struct OutboundSync {
@@ -81,12 +95,29 @@ impl OutboundSyncConnection for OutboundSync {
         self.context.send_request(message);
     }
 
+    fn send_serialized_block(&self, block: &SharedPayload) {
+        self.context.send_shared_request(block);
+    }
+
     fn send_headers(&self, message: &types::Headers) {
-        self.context.send_request(message);
+        if self.context.info().compressed_headers {
+            self.context
+                .send_request(&types::Headers2::with_headers(message.headers.clone()));
+        } else {
+            self.context.send_request(message);
+        }
     }
 
     fn respond_headers(&self, message: &types::Headers, id: u32) {
-        self.context.send_response(message, id, true);
+        if self.context.info().compressed_headers {
+            self.context.send_response(
+                &types::Headers2::with_headers(message.headers.clone()),
+                id,
+                true,
+            );
+        } else {
+            self.context.send_response(message, id, true);
+        }
     }
 
     fn send_sendheaders(&self, message: &types::SendHeaders) {
@@ -102,11 +133,29 @@ impl OutboundSyncConnection for OutboundSync {
     }
 
     fn close(&self) {
-        self.context
-            .global()
-            .penalize_node(&self.context.info().address);
+        if !self.context.info().whitelisted {
+            self.context
+                .global()
+                .penalize_node(&self.context.info().address);
+        }
         self.context.close()
     }
+
+    fn experiments(&self) -> ExperimentFlags {
+        self.context.info().experiments
+    }
+
+    fn direction(&self) -> Direction {
+        self.context.info().direction
+    }
+
+    fn is_whitelisted(&self) -> bool {
+        self.context.info().whitelisted
+    }
+
+    fn address(&self) -> SocketAddr {
+        self.context.info().address
+    }
 }
 
 pub struct SyncProtocol {
@@ -142,7 +191,6 @@ impl Protocol for SyncProtocol {
     }
 
     fn on_message(&mut self, command: &Command, payload: &Bytes) -> Result<(), Error> {
-        let version = self.context.info().version;
         if command == &types::Inv::command() {
             // we are synchronizing => we ask only for blocks with known headers
             // => there are no useful blocks hashes for us
@@ -152,28 +200,28 @@ impl Protocol for SyncProtocol {
                 return Ok(());
             }
 
-            let message: types::Inv = deserialize_payload(payload, version)?;
+            let message: types::Inv = self.context.decoder().read_payload(payload)?;
             self.inbound_connection.on_inventory(message);
         } else if command == &types::GetData::command() {
             if self.state.synchronizing() {
                 return Ok(());
             }
 
-            let message: types::GetData = deserialize_payload(payload, version)?;
+            let message: types::GetData = self.context.decoder().read_payload(payload)?;
             self.inbound_connection.on_getdata(message);
         } else if command == &types::GetBlocks::command() {
             if self.state.synchronizing() {
                 return Ok(());
             }
 
-            let message: types::GetBlocks = deserialize_payload(payload, version)?;
+            let message: types::GetBlocks = self.context.decoder().read_payload(payload)?;
             self.inbound_connection.on_getblocks(message);
         } else if command == &types::GetHeaders::command() {
             if self.state.synchronizing() {
                 return Ok(());
             }
 
-            let message: types::GetHeaders = deserialize_payload(payload, version)?;
+            let message: types::GetHeaders = self.context.decoder().read_payload(payload)?;
             let id = self.context.declare_response();
             trace!(
                 "declared response {} for request: {}",
@@ -182,17 +230,26 @@ impl Protocol for SyncProtocol {
             );
             self.inbound_connection.on_getheaders(message, id);
         } else if command == &types::Block::command() {
-            let message: types::Block = deserialize_payload(payload, version)?;
+            let message: types::Block = self.context.decoder().read_payload(payload)?;
             self.inbound_connection.on_block(message);
         } else if command == &types::Headers::command() {
-            let message: types::Headers = deserialize_payload(payload, version)?;
+            let message: types::Headers = self.context.decoder().read_payload(payload)?;
             self.inbound_connection.on_headers(message);
+        } else if command == &types::Headers2::command() {
+            let message: types::Headers2 = self.context.decoder().read_payload(payload)?;
+            self.inbound_connection.on_headers(message.into());
         } else if command == &types::SendHeaders::command() {
-            let message: types::SendHeaders = deserialize_payload(payload, version)?;
+            let message: types::SendHeaders = self.context.decoder().read_payload(payload)?;
             self.inbound_connection.on_sendheaders(message);
         } else if command == &types::NotFound::command() {
-            let message: types::NotFound = deserialize_payload(payload, version)?;
+            let message: types::NotFound = self.context.decoder().read_payload(payload)?;
             self.inbound_connection.on_notfound(message);
+        } else if command == &types::Pong::command() {
+            // round-trip time is measured, when pong is received
+            let rtt = self.context.stats().lock().last_rtt.take();
+            if let Some(rtt) = rtt {
+                self.inbound_connection.on_latency(rtt);
+            }
         }
         Ok(())
     }
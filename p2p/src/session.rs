@@ -1,6 +1,6 @@
 use bytes::Bytes;
 use message::{Command, Error};
-use net::{PeerContext, PeerStats};
+use net::{Flow, PeerContext, PeerStats};
 use p2p::Context;
 use parking_lot::Mutex;
 use protocol::{AddrProtocol, PingProtocol, Protocol, SeednodeProtocol, SyncProtocol};
@@ -9,6 +9,11 @@ use util::PeerInfo;
 
 pub trait SessionFactory {
     fn new_session(context: Arc<Context>, info: PeerInfo, synchronous: bool) -> Session;
+
+    /// Returns true if sessions are only used to relay blocks.
+    fn is_block_relay_only() -> bool {
+        false
+    }
 }
 
 pub struct SeednodeSessionFactory;
@@ -35,6 +40,23 @@ impl SessionFactory for NormalSessionFactory {
     }
 }
 
+/// Session that relays blocks only: there's no addr protocol, so node addresses are neither
+/// requested from, nor announced to the peer.
+pub struct BlockRelaySessionFactory;
+
+impl SessionFactory for BlockRelaySessionFactory {
+    fn new_session(context: Arc<Context>, info: PeerInfo, synchronous: bool) -> Session {
+        let peer_context = Arc::new(PeerContext::new(context, info, synchronous));
+        let ping = PingProtocol::new(peer_context.clone()).boxed();
+        let sync = SyncProtocol::new(peer_context.clone()).boxed();
+        Session::new(peer_context, vec![ping, sync])
+    }
+
+    fn is_block_relay_only() -> bool {
+        true
+    }
+}
+
 pub struct Session {
     peer_context: Arc<PeerContext>,
     protocols: Mutex<Vec<Box<dyn Protocol>>>,
@@ -61,9 +83,16 @@ impl Session {
     }
 
     pub fn on_message(&self, command: Command, payload: Bytes) -> Result<(), Error> {
-        self.stats()
-            .lock()
-            .report_recv(command.clone(), payload.len());
+        let received_len = payload.len();
+        let payload = self.peer_context.decoder().decompress(&command, payload)?;
+        {
+            let mut stats = self.stats().lock();
+            stats.report_recv(command.clone(), received_len);
+            stats.report_compression_saving(
+                Flow::Receive,
+                payload.len().saturating_sub(received_len),
+            );
+        }
 
         self.protocols
             .lock()
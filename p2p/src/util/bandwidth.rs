@@ -0,0 +1,288 @@
+use message::Command;
+use std::fmt;
+use std::str::FromStr;
+use util::Direction;
+
+/// Peers, which share bandwidth limit.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PeerClass {
+    /// Peers, which have connected to us.
+    Inbound,
+    /// Peers, we have connected to.
+    Outbound,
+    /// Peers, matched by deprioritize filters of the connection policy.
+    Deprioritized,
+}
+
+/// Limit of the bandwidth, used by messages of given type, sent to peers of given class:
+/// `block:512` or `block@inbound:512` (KiB per second). All peers of the class share the limit.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BandwidthLimit {
+    /// Command of limited messages.
+    pub command: Command,
+    /// Class of peers. None if messages to all peers are limited.
+    pub class: Option<PeerClass>,
+    /// Max number of bytes, sent per second. Up to one second of unused bandwidth could be sent at once.
+    pub bytes_per_second: u64,
+}
+
+/// Token bucket of the single bandwidth limit.
+#[derive(Debug)]
+struct TokenBucket {
+    /// Bytes, which could be sent right now. Negative when sends are delayed.
+    tokens: f64,
+    /// Time of the last update, in seconds.
+    updated: f64,
+}
+
+/// Delays sending of messages, which exceed bandwidth limits, so that bulk transfers (e.g. blocks,
+/// served to leechers) do not crowd out latency-critical messages on constrained links.
+/// Messages, which are not matched by any limit, are never delayed.
+#[derive(Debug, Default)]
+pub struct BandwidthThrottle {
+    limits: Vec<BandwidthLimit>,
+    buckets: Vec<TokenBucket>,
+}
+
+impl PeerClass {
+    /// Does peer belong to this class?
+    pub fn matches(&self, direction: Direction, deprioritized: bool) -> bool {
+        match *self {
+            PeerClass::Inbound => direction == Direction::Inbound,
+            PeerClass::Outbound => direction == Direction::Outbound,
+            PeerClass::Deprioritized => deprioritized,
+        }
+    }
+}
+
+impl FromStr for PeerClass {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "inbound" => Ok(PeerClass::Inbound),
+            "outbound" => Ok(PeerClass::Outbound),
+            "deprioritized" => Ok(PeerClass::Deprioritized),
+            _ => Err(format!(
+                "Invalid peer class: {} - should be inbound, outbound or deprioritized",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for PeerClass {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match *self {
+            PeerClass::Inbound => "inbound",
+            PeerClass::Outbound => "outbound",
+            PeerClass::Deprioritized => "deprioritized",
+        })
+    }
+}
+
+impl FromStr for BandwidthLimit {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || {
+            format!(
+                "Invalid bandwidth limit: {} - should be COMMAND[@CLASS]:KIB_PER_SECOND",
+                s
+            )
+        };
+        let pos = s.rfind(':').ok_or_else(invalid)?;
+        let (target, rate) = (&s[..pos], &s[pos + 1..]);
+        let (command, class) = match target.find('@') {
+            Some(pos) => (&target[..pos], Some(target[pos + 1..].parse()?)),
+            None => (target, None),
+        };
+        let command: Command = command.parse().map_err(|_| invalid())?;
+        if command.is_empty() {
+            return Err(invalid());
+        }
+        match rate.parse::<u64>() {
+            Ok(kib) if kib > 0 => Ok(BandwidthLimit {
+                command: command,
+                class: class,
+                bytes_per_second: kib.saturating_mul(1024),
+            }),
+            _ => Err(invalid()),
+        }
+    }
+}
+
+impl fmt::Display for BandwidthLimit {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.class {
+            Some(class) => write!(f, "{}@{}", self.command, class)?,
+            None => write!(f, "{}", self.command)?,
+        }
+        write!(f, ":{}", self.bytes_per_second / 1024)
+    }
+}
+
+impl BandwidthLimit {
+    /// Is message with given command, sent to given peer, limited?
+    pub fn matches(&self, command: &Command, direction: Direction, deprioritized: bool) -> bool {
+        self.command == *command
+            && self
+                .class
+                .map_or(true, |class| class.matches(direction, deprioritized))
+    }
+}
+
+impl TokenBucket {
+    /// Creates bucket with one second of bandwidth.
+    fn new(limit: &BandwidthLimit, now: f64) -> Self {
+        TokenBucket {
+            tokens: limit.bytes_per_second as f64,
+            updated: now,
+        }
+    }
+
+    /// Takes bytes from the bucket. Returns number of seconds to wait before they could be sent.
+    fn take(&mut self, rate: u64, bytes: u64, now: f64) -> f64 {
+        let rate = rate as f64;
+        if now > self.updated {
+            self.tokens = (self.tokens + (now - self.updated) * rate).min(rate);
+            self.updated = now;
+        }
+        self.tokens -= bytes as f64;
+        if self.tokens >= 0f64 {
+            0f64
+        } else {
+            -self.tokens / rate
+        }
+    }
+}
+
+impl BandwidthThrottle {
+    pub fn new(limits: Vec<BandwidthLimit>, now: f64) -> Self {
+        let mut throttle = BandwidthThrottle::default();
+        throttle.set_limits(limits, now);
+        throttle
+    }
+
+    /// Replaces bandwidth limits. Sends, which have already been delayed, are not affected.
+    pub fn set_limits(&mut self, limits: Vec<BandwidthLimit>, now: f64) {
+        self.buckets = limits
+            .iter()
+            .map(|limit| TokenBucket::new(limit, now))
+            .collect();
+        self.limits = limits;
+    }
+
+    /// Current bandwidth limits.
+    #[cfg(test)]
+    pub fn limits(&self) -> &[BandwidthLimit] {
+        &self.limits
+    }
+
+    /// Accounts message of given size, which is about to be sent to the peer. Returns number of
+    /// seconds to delay the message. Only the first matching limit is applied.
+    pub fn delay(
+        &mut self,
+        command: &Command,
+        direction: Direction,
+        deprioritized: bool,
+        bytes: u64,
+        now: f64,
+    ) -> f64 {
+        match self
+            .limits
+            .iter()
+            .position(|limit| limit.matches(command, direction, deprioritized))
+        {
+            Some(index) => {
+                self.buckets[index].take(self.limits[index].bytes_per_second, bytes, now)
+            }
+            None => 0f64,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{BandwidthLimit, BandwidthThrottle, PeerClass};
+    use util::Direction;
+
+    #[test]
+    fn bandwidth_limit_parse() {
+        assert_eq!(
+            "block@inbound:512".parse::<BandwidthLimit>(),
+            Ok(BandwidthLimit {
+                command: "block".into(),
+                class: Some(PeerClass::Inbound),
+                bytes_per_second: 512 * 1024,
+            })
+        );
+        assert_eq!(
+            "headers:64".parse::<BandwidthLimit>().unwrap().to_string(),
+            "headers:64"
+        );
+        assert_eq!(
+            "block@deprioritized:8"
+                .parse::<BandwidthLimit>()
+                .unwrap()
+                .to_string(),
+            "block@deprioritized:8"
+        );
+        assert!("block@leecher:8".parse::<BandwidthLimit>().is_err());
+        assert!("block:0".parse::<BandwidthLimit>().is_err());
+        assert!(":8".parse::<BandwidthLimit>().is_err());
+        assert!("block".parse::<BandwidthLimit>().is_err());
+    }
+
+    #[test]
+    fn bandwidth_throttle_delays_only_limited_messages() {
+        let mut throttle = BandwidthThrottle::new(
+            vec![
+                "block@deprioritized:1".parse().unwrap(),
+                "block@inbound:2".parse().unwrap(),
+            ],
+            0f64,
+        );
+
+        // latency-critical messages are never delayed
+        assert_eq!(
+            throttle.delay(&"ping".into(), Direction::Inbound, true, 100_000, 0f64),
+            0f64
+        );
+        // one second of bandwidth is sent at once
+        assert_eq!(
+            throttle.delay(&"block".into(), Direction::Outbound, false, 100_000, 0f64),
+            0f64
+        );
+        assert_eq!(
+            throttle.delay(&"block".into(), Direction::Inbound, true, 1024, 0f64),
+            0f64
+        );
+        // then messages are delayed, in order
+        assert_eq!(
+            throttle.delay(&"block".into(), Direction::Inbound, true, 512, 0f64),
+            0.5
+        );
+        assert_eq!(
+            throttle.delay(&"block".into(), Direction::Inbound, true, 512, 0f64),
+            1.0
+        );
+        // bucket is refilled over time
+        assert_eq!(
+            throttle.delay(&"block".into(), Direction::Inbound, true, 512, 1.0),
+            0.5
+        );
+        // inbound peers, which are not deprioritized, have their own limit
+        assert_eq!(
+            throttle.delay(&"block".into(), Direction::Inbound, false, 2048, 0f64),
+            0f64
+        );
+
+        throttle.set_limits(Vec::new(), 2.0);
+        assert!(throttle.limits().is_empty());
+        assert_eq!(
+            throttle.delay(&"block".into(), Direction::Inbound, true, 100_000, 2.0),
+            0f64
+        );
+    }
+}
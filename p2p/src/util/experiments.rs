@@ -0,0 +1,128 @@
+use rand::Rng;
+
+/// Experimental protocol behaviors, enabled for the connection.
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+pub struct ExperimentFlags {
+    /// New blocks are announced with `headers` message, even if peer has not asked for it.
+    pub headers_announcement: bool,
+}
+
+/// Percentage of connections, for which experimental behaviors are enabled.
+#[derive(Debug, Default, PartialEq, Clone)]
+pub struct ExperimentsConfig {
+    /// Percentage of connections with `headers_announcement` experiment.
+    pub headers_announcement_percent: u8,
+}
+
+impl ExperimentsConfig {
+    /// Randomly selects experiments for new connection.
+    pub fn select<R: Rng>(&self, rng: &mut R) -> ExperimentFlags {
+        ExperimentFlags {
+            headers_announcement: is_selected(rng, self.headers_announcement_percent),
+        }
+    }
+
+    /// Returns all configured experiments with their names and percentages.
+    pub fn experiments(&self) -> Vec<(&'static str, u8)> {
+        vec![("headers_announcement", self.headers_announcement_percent)]
+    }
+}
+
+impl ExperimentFlags {
+    /// Returns true if experiment with given name is enabled.
+    pub fn is_enabled(&self, experiment: &str) -> bool {
+        match experiment {
+            "headers_announcement" => self.headers_announcement,
+            _ => false,
+        }
+    }
+}
+
+/// Outcomes of experiment for a group of connections.
+#[derive(Debug, Default, PartialEq, Clone)]
+pub struct ExperimentGroupStats {
+    /// Number of connections in the group.
+    pub peers: usize,
+    /// Total number of bytes sent to peers of the group.
+    pub bytes_sent: u64,
+    /// Total number of bytes received from peers of the group.
+    pub bytes_received: u64,
+    /// Average ping of peers in the group, in seconds.
+    pub avg_ping: f64,
+}
+
+impl ExperimentGroupStats {
+    /// Include connection stats into the group stats.
+    pub fn add_peer(&mut self, bytes_sent: u64, bytes_received: u64, avg_ping: f64) {
+        self.avg_ping = (self.avg_ping * self.peers as f64 + avg_ping) / (self.peers as f64 + 1f64);
+        self.peers += 1;
+        self.bytes_sent += bytes_sent;
+        self.bytes_received += bytes_received;
+    }
+}
+
+/// Outcomes of single experiment.
+#[derive(Debug, PartialEq, Clone)]
+pub struct ExperimentStats {
+    /// Experiment name.
+    pub name: &'static str,
+    /// Percentage of connections, for which experiment is enabled.
+    pub percent: u8,
+    /// Stats of connections with experiment enabled.
+    pub enabled: ExperimentGroupStats,
+    /// Stats of connections with experiment disabled (control group).
+    pub disabled: ExperimentGroupStats,
+}
+
+fn is_selected<R: Rng>(rng: &mut R, percent: u8) -> bool {
+    match percent {
+        0 => false,
+        percent if percent >= 100 => true,
+        percent => rng.gen_range(0, 100) < percent,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ExperimentGroupStats, ExperimentsConfig};
+    use rand::thread_rng;
+
+    #[test]
+    fn experiments_are_selected_by_percentage() {
+        let mut rng = thread_rng();
+        let never = ExperimentsConfig {
+            headers_announcement_percent: 0,
+        };
+        let always = ExperimentsConfig {
+            headers_announcement_percent: 100,
+        };
+        for _ in 0..100 {
+            assert!(!never.select(&mut rng).headers_announcement);
+            assert!(always.select(&mut rng).headers_announcement);
+        }
+
+        let half = ExperimentsConfig {
+            headers_announcement_percent: 50,
+        };
+        let selected = (0..1000)
+            .filter(|_| half.select(&mut rng).headers_announcement)
+            .count();
+        assert!(selected > 300 && selected < 700);
+    }
+
+    #[test]
+    fn experiment_group_stats_average_ping() {
+        let mut stats = ExperimentGroupStats::default();
+        stats.add_peer(10, 20, 1f64);
+        stats.add_peer(30, 40, 3f64);
+        assert_eq!(
+            stats,
+            ExperimentGroupStats {
+                peers: 2,
+                bytes_sent: 40,
+                bytes_received: 60,
+                avg_ping: 2f64,
+            }
+        );
+    }
+}
@@ -0,0 +1,118 @@
+use std::net::IpAddr;
+use std::str::FromStr;
+
+/// Range of IP addresses, e.g. `10.0.0.0/8` or `fd00::/8`. Single address is the range
+/// with maximal prefix length.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct IpRange {
+    network: IpAddr,
+    prefix_len: u8,
+}
+
+impl IpRange {
+    /// Does range contain given address? IPv4-mapped IPv6 addresses are matched against IPv4 ranges.
+    pub fn contains(&self, ip: &IpAddr) -> bool {
+        match (self.network, normalize(ip)) {
+            (IpAddr::V4(network), IpAddr::V4(ip)) => {
+                prefix_matches(&network.octets(), &ip.octets(), self.prefix_len)
+            }
+            (IpAddr::V6(network), IpAddr::V6(ip)) => {
+                prefix_matches(&network.octets(), &ip.octets(), self.prefix_len)
+            }
+            _ => false,
+        }
+    }
+}
+
+impl FromStr for IpRange {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("Invalid IP range: {}", s);
+        let (network, prefix_len) = match s.find('/') {
+            Some(pos) => (&s[..pos], Some(&s[pos + 1..])),
+            None => (s, None),
+        };
+        let network: IpAddr = network.parse().map_err(|_| invalid())?;
+        let max_prefix_len = match network {
+            IpAddr::V4(_) => 32,
+            IpAddr::V6(_) => 128,
+        };
+        let prefix_len = match prefix_len {
+            Some(prefix_len) => match prefix_len.parse() {
+                Ok(prefix_len) if prefix_len <= max_prefix_len => prefix_len,
+                _ => return Err(invalid()),
+            },
+            None => max_prefix_len,
+        };
+
+        Ok(IpRange {
+            network: network,
+            prefix_len: prefix_len,
+        })
+    }
+}
+
+fn normalize(ip: &IpAddr) -> IpAddr {
+    match *ip {
+        IpAddr::V6(ref ip) => match ip.segments() {
+            [0, 0, 0, 0, 0, 0xffff, _, _] => {
+                let octets = ip.octets();
+                IpAddr::from([octets[12], octets[13], octets[14], octets[15]])
+            }
+            _ => IpAddr::V6(*ip),
+        },
+        IpAddr::V4(_) => *ip,
+    }
+}
+
+fn prefix_matches(network: &[u8], ip: &[u8], prefix_len: u8) -> bool {
+    let full_bytes = prefix_len as usize / 8;
+    let rest_bits = prefix_len % 8;
+    if network[..full_bytes] != ip[..full_bytes] {
+        return false;
+    }
+    if rest_bits == 0 {
+        return true;
+    }
+
+    let mask = 0xffu8 << (8 - rest_bits);
+    network[full_bytes] & mask == ip[full_bytes] & mask
+}
+
+#[cfg(test)]
+mod tests {
+    use super::IpRange;
+
+    #[test]
+    fn ip_range_contains_address() {
+        let range: IpRange = "10.1.0.0/16".parse().unwrap();
+        assert!(range.contains(&"10.1.2.3".parse().unwrap()));
+        assert!(range.contains(&"::ffff:10.1.2.3".parse().unwrap()));
+        assert!(!range.contains(&"10.2.0.1".parse().unwrap()));
+        assert!(!range.contains(&"fd00::1".parse().unwrap()));
+
+        let range: IpRange = "192.168.1.128/25".parse().unwrap();
+        assert!(range.contains(&"192.168.1.200".parse().unwrap()));
+        assert!(!range.contains(&"192.168.1.100".parse().unwrap()));
+
+        let range: IpRange = "fd00::/8".parse().unwrap();
+        assert!(range.contains(&"fd12::1".parse().unwrap()));
+        assert!(!range.contains(&"fe80::1".parse().unwrap()));
+
+        let range: IpRange = "127.0.0.1".parse().unwrap();
+        assert!(range.contains(&"127.0.0.1".parse().unwrap()));
+        assert!(!range.contains(&"127.0.0.2".parse().unwrap()));
+
+        let range: IpRange = "0.0.0.0/0".parse().unwrap();
+        assert!(range.contains(&"8.8.8.8".parse().unwrap()));
+    }
+
+    #[test]
+    fn invalid_ip_ranges_are_rejected() {
+        assert!("10.0.0.0/33".parse::<IpRange>().is_err());
+        assert!("fd00::/129".parse::<IpRange>().is_err());
+        assert!("10.0.0/8".parse::<IpRange>().is_err());
+        assert!("localhost".parse::<IpRange>().is_err());
+    }
+}
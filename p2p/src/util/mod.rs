@@ -1,14 +1,32 @@
+mod bandwidth;
+mod experiments;
 mod internet_protocol;
 pub mod interval;
+mod ip_range;
+mod nat_pmp;
 mod node_table;
 pub mod nonce;
+mod onion;
 mod peer;
+mod peer_policy;
+mod peer_versions;
 mod response_queue;
 mod synchronizer;
 pub mod time;
 
+pub use self::bandwidth::{BandwidthLimit, BandwidthThrottle, PeerClass};
+pub use self::experiments::{
+    ExperimentFlags, ExperimentGroupStats, ExperimentStats, ExperimentsConfig,
+};
 pub use self::internet_protocol::InternetProtocol;
+pub use self::ip_range::IpRange;
+pub use self::nat_pmp::start_port_mapping;
 pub use self::node_table::{Node, NodeTable, NodeTableError};
-pub use self::peer::{Direction, PeerId, PeerInfo};
+pub use self::onion::{ip_to_onion, onion_to_ip, parse_onion_address};
+pub use self::peer::{ConnectionCount, Direction, PeerCompressionStats, PeerId, PeerInfo};
+pub use self::peer_policy::{
+    PeerFilter, PeerPolicy, PeerPolicyCounters, PeerPolicyDecision, PeerPolicyStats,
+};
+pub use self::peer_versions::{PeerVersionStats, RejectedPeerVersions};
 pub use self::response_queue::{ResponseQueue, Responses};
 pub use self::synchronizer::{ConfigurableSynchronizer, Synchronizer};
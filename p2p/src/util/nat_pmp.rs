@@ -0,0 +1,262 @@
+//! NAT-PMP (RFC 6886) client. Maps listening ports on the gateway, so that nodes behind
+//! home routers could accept inbound connections.
+
+use std::fs;
+use std::io;
+use std::net::{Ipv4Addr, SocketAddr, SocketAddrV4, UdpSocket};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Port, gateway is listening for NAT-PMP requests on.
+const NAT_PMP_PORT: u16 = 5351;
+/// Requested lifetime of the mapping, in seconds. Mapping is renewed after half of its lifetime.
+const MAPPING_LIFETIME_S: u32 = 2 * 60 * 60;
+/// Mapping is retried after this number of seconds, if gateway has failed to map the port.
+const RETRY_INTERVAL_S: u64 = 5 * 60;
+/// Timeout of the first request. Timeout is doubled on every retransmission.
+const INITIAL_TIMEOUT_MS: u64 = 250;
+/// Max number of request retransmissions.
+const MAX_RETRANSMISSIONS: u32 = 4;
+
+const OPCODE_EXTERNAL_ADDRESS: u8 = 0;
+const OPCODE_MAP_TCP: u8 = 2;
+/// Opcode of the response is opcode of the request + 128.
+const OPCODE_RESPONSE: u8 = 128;
+const RESULT_SUCCESS: u16 = 0;
+
+/// Port mapping, created by the gateway.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PortMapping {
+    /// Local port
+    pub internal_port: u16,
+    /// Port on the gateway external address
+    pub external_port: u16,
+    /// Lifetime of the mapping, in seconds
+    pub lifetime_s: u32,
+}
+
+/// NAT-PMP client of the single gateway.
+pub struct NatPmpClient {
+    socket: UdpSocket,
+    gateway: SocketAddr,
+}
+
+impl NatPmpClient {
+    pub fn new(gateway: Ipv4Addr) -> io::Result<Self> {
+        let socket = UdpSocket::bind("0.0.0.0:0")?;
+        Ok(NatPmpClient {
+            socket: socket,
+            gateway: SocketAddrV4::new(gateway, NAT_PMP_PORT).into(),
+        })
+    }
+
+    /// Asks the gateway for its external address
+    pub fn external_address(&self) -> io::Result<Ipv4Addr> {
+        let response = self.request(&[0, OPCODE_EXTERNAL_ADDRESS], 12)?;
+        Ok(Ipv4Addr::new(
+            response[8],
+            response[9],
+            response[10],
+            response[11],
+        ))
+    }
+
+    /// Asks the gateway to map TCP port. Zero lifetime removes the mapping
+    pub fn map_tcp_port(
+        &self,
+        internal_port: u16,
+        external_port: u16,
+        lifetime_s: u32,
+    ) -> io::Result<PortMapping> {
+        let request = encode_mapping_request(internal_port, external_port, lifetime_s);
+        let response = self.request(&request, 16)?;
+        Ok(decode_mapping_response(&response))
+    }
+
+    /// Sends request, retransmitting it until the response is received
+    fn request(&self, request: &[u8], response_len: usize) -> io::Result<Vec<u8>> {
+        let mut timeout = Duration::from_millis(INITIAL_TIMEOUT_MS);
+        let mut buffer = [0u8; 16];
+        for _ in 0..MAX_RETRANSMISSIONS + 1 {
+            self.socket.send_to(request, &self.gateway)?;
+            let deadline = Instant::now() + timeout;
+            loop {
+                let now = Instant::now();
+                if now >= deadline {
+                    break;
+                }
+                self.socket.set_read_timeout(Some(deadline - now))?;
+                let (len, from) = match self.socket.recv_from(&mut buffer) {
+                    Ok(received) => received,
+                    Err(ref err)
+                        if err.kind() == io::ErrorKind::WouldBlock
+                            || err.kind() == io::ErrorKind::TimedOut =>
+                    {
+                        break
+                    }
+                    Err(err) => return Err(err),
+                };
+                // ignore datagrams from other hosts and responses to other requests
+                if from != self.gateway || len < response_len {
+                    continue;
+                }
+                if buffer[1] != request[1] + OPCODE_RESPONSE {
+                    continue;
+                }
+                return check_result(&buffer[..response_len]).map(|_| buffer[..len].to_vec());
+            }
+            timeout *= 2;
+        }
+        Err(io::Error::new(
+            io::ErrorKind::TimedOut,
+            "NAT-PMP gateway has not responded",
+        ))
+    }
+}
+
+fn encode_mapping_request(internal_port: u16, external_port: u16, lifetime_s: u32) -> [u8; 12] {
+    let mut request = [0u8; 12];
+    request[1] = OPCODE_MAP_TCP;
+    request[4..6].copy_from_slice(&internal_port.to_be_bytes());
+    request[6..8].copy_from_slice(&external_port.to_be_bytes());
+    request[8..12].copy_from_slice(&lifetime_s.to_be_bytes());
+    request
+}
+
+fn decode_mapping_response(response: &[u8]) -> PortMapping {
+    PortMapping {
+        internal_port: u16::from_be_bytes([response[8], response[9]]),
+        external_port: u16::from_be_bytes([response[10], response[11]]),
+        lifetime_s: u32::from_be_bytes([response[12], response[13], response[14], response[15]]),
+    }
+}
+
+fn check_result(response: &[u8]) -> io::Result<()> {
+    match u16::from_be_bytes([response[2], response[3]]) {
+        RESULT_SUCCESS => Ok(()),
+        code => Err(io::Error::new(
+            io::ErrorKind::Other,
+            format!("NAT-PMP request has failed with result code {}", code),
+        )),
+    }
+}
+
+/// Default IPv4 gateway of the host. Only Linux routing table is supported
+pub fn default_gateway() -> Option<Ipv4Addr> {
+    fs::read_to_string("/proc/net/route")
+        .ok()
+        .and_then(|routes| parse_default_gateway(&routes))
+}
+
+/// Finds default gateway in the /proc/net/route contents. Addresses there are network-order
+/// bytes, printed as host-order numbers
+fn parse_default_gateway(routes: &str) -> Option<Ipv4Addr> {
+    routes.lines().skip(1).find_map(|route| {
+        let fields: Vec<&str> = route.split_whitespace().collect();
+        if fields.len() < 3 || fields[1] != "00000000" {
+            return None;
+        }
+        u32::from_str_radix(fields[2], 16)
+            .ok()
+            .filter(|gateway| *gateway != 0)
+            .map(|gateway| Ipv4Addr::from(gateway.to_ne_bytes()))
+    })
+}
+
+/// Maps given TCP ports on the gateway in background thread and keeps renewing mappings.
+/// Mappings are removed when `stop` is set.
+pub fn start_port_mapping(
+    ports: Vec<u16>,
+    gateway: Option<Ipv4Addr>,
+    stop: Arc<AtomicBool>,
+) -> io::Result<thread::JoinHandle<()>> {
+    let gateway = match gateway.or_else(default_gateway) {
+        Some(gateway) => gateway,
+        None => {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                "Default gateway is unknown",
+            ))
+        }
+    };
+    let client = NatPmpClient::new(gateway)?;
+    thread::Builder::new()
+        .name("NAT-PMP port mapping".to_owned())
+        .spawn(move || {
+            while !stop.load(Ordering::SeqCst) {
+                let mut renew_after_s = RETRY_INTERVAL_S;
+                for port in &ports {
+                    match client.map_tcp_port(*port, *port, MAPPING_LIFETIME_S) {
+                        Ok(mapping) => {
+                            match client.external_address() {
+                                Ok(address) => info!(
+                                    "Port {} is mapped to {}:{} by NAT-PMP gateway {}",
+                                    port, address, mapping.external_port, gateway
+                                ),
+                                Err(_) => info!(
+                                    "Port {} is mapped to port {} by NAT-PMP gateway {}",
+                                    port, mapping.external_port, gateway
+                                ),
+                            }
+                            renew_after_s = renew_after_s.min(mapping.lifetime_s as u64 / 2);
+                        }
+                        Err(err) => warn!(
+                            "Failed to map port {} by NAT-PMP gateway {}: {}",
+                            port, gateway, err
+                        ),
+                    }
+                }
+
+                let renew_at = Instant::now() + Duration::from_secs(renew_after_s.max(1));
+                while !stop.load(Ordering::SeqCst) && Instant::now() < renew_at {
+                    thread::sleep(Duration::from_secs(1));
+                }
+            }
+
+            for port in &ports {
+                if let Err(err) = client.map_tcp_port(*port, 0, 0) {
+                    trace!("Failed to remove NAT-PMP mapping of port {}: {}", port, err);
+                }
+            }
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        decode_mapping_response, encode_mapping_request, parse_default_gateway, PortMapping,
+    };
+    use std::net::Ipv4Addr;
+
+    #[test]
+    fn nat_pmp_mapping_messages() {
+        assert_eq!(
+            encode_mapping_request(8333, 8334, 7200),
+            [0, 2, 0, 0, 0x20, 0x8d, 0x20, 0x8e, 0, 0, 0x1c, 0x20]
+        );
+        assert_eq!(
+            decode_mapping_response(&[
+                0, 130, 0, 0, 0, 0, 0, 1, 0x20, 0x8d, 0x20, 0x8e, 0, 0, 0x0e, 0x10
+            ]),
+            PortMapping {
+                internal_port: 8333,
+                external_port: 8334,
+                lifetime_s: 3600,
+            }
+        );
+    }
+
+    #[test]
+    fn default_gateway_is_read_from_routing_table() {
+        let routes = "Iface\tDestination\tGateway \tFlags\tRefCnt\tUse\tMetric\tMask\n\
+                      eth0\t0000A8C0\t00000000\t0001\t0\t0\t0\t00FFFFFF\n\
+                      eth0\t00000000\t0101A8C0\t0003\t0\t0\t100\t00000000\n";
+        assert_eq!(
+            parse_default_gateway(routes),
+            Some(Ipv4Addr::new(192, 168, 1, 1))
+        );
+        assert_eq!(parse_default_gateway("Iface\tDestination\tGateway\n"), None);
+    }
+}
@@ -0,0 +1,139 @@
+use std::net::{IpAddr, Ipv6Addr, SocketAddr};
+
+/// OnionCat range (fd87:d87e:eb43::/48), which .onion names are mapped into.
+const ONION_PREFIX: [u8; 6] = [0xfd, 0x87, 0xd8, 0x7e, 0xeb, 0x43];
+const ONION_SUFFIX: &'static str = ".onion";
+/// Number of base32 characters in the .onion name.
+const ONION_NAME_LEN: usize = 16;
+/// Number of base32 characters in the Tor v3 .onion name. v3 names encode 35 bytes, which
+/// do not fit the OnionCat range, so they can't be stored and gossiped as IPv6 addresses.
+const ONION_V3_NAME_LEN: usize = 56;
+const BASE32_ALPHABET: &'static [u8] = b"abcdefghijklmnopqrstuvwxyz234567";
+
+/// Maps `<16 base32 characters>.onion` name to the address from the OnionCat range,
+/// so that hidden service is stored and gossiped as a regular IPv6 address.
+pub fn onion_to_ip(name: &str) -> Option<IpAddr> {
+    let name = name.to_lowercase();
+    if !name.ends_with(ONION_SUFFIX) || name.len() != ONION_NAME_LEN + ONION_SUFFIX.len() {
+        return None;
+    }
+
+    let mut octets = [0u8; 16];
+    octets[..ONION_PREFIX.len()].copy_from_slice(&ONION_PREFIX);
+    let (mut buffer, mut bits, mut index) = (0u32, 0u32, ONION_PREFIX.len());
+    for c in name[..ONION_NAME_LEN].bytes() {
+        let value = BASE32_ALPHABET.iter().position(|a| *a == c)? as u32;
+        buffer = (buffer << 5) | value;
+        bits += 5;
+        if bits >= 8 {
+            bits -= 8;
+            octets[index] = (buffer >> bits) as u8;
+            index += 1;
+        }
+    }
+
+    Some(IpAddr::V6(Ipv6Addr::from(octets)))
+}
+
+/// Returns .onion name of the address from the OnionCat range.
+pub fn ip_to_onion(ip: &IpAddr) -> Option<String> {
+    let octets = match *ip {
+        IpAddr::V6(ref ip) => ip.octets(),
+        IpAddr::V4(_) => return None,
+    };
+    if octets[..ONION_PREFIX.len()] != ONION_PREFIX {
+        return None;
+    }
+
+    let mut name = String::with_capacity(ONION_NAME_LEN + ONION_SUFFIX.len());
+    let (mut buffer, mut bits) = (0u32, 0u32);
+    for octet in &octets[ONION_PREFIX.len()..] {
+        buffer = (buffer << 8) | *octet as u32;
+        bits += 8;
+        while bits >= 5 {
+            bits -= 5;
+            name.push(BASE32_ALPHABET[((buffer >> bits) & 0x1f) as usize] as char);
+        }
+    }
+    name.push_str(ONION_SUFFIX);
+    Some(name)
+}
+
+/// Parses `<name>.onion` or `<name>.onion:<port>` into the address from the OnionCat range.
+/// Returns None if `s` is not a .onion address and error if it is an invalid or unsupported one.
+pub fn parse_onion_address(s: &str, default_port: u16) -> Result<Option<SocketAddr>, String> {
+    let (name, port) = match s.rfind(':') {
+        Some(pos) => (&s[..pos], Some(&s[pos + 1..])),
+        None => (s, None),
+    };
+    if !name.to_lowercase().ends_with(ONION_SUFFIX) {
+        return Ok(None);
+    }
+    if name.len() == ONION_V3_NAME_LEN + ONION_SUFFIX.len() {
+        return Err(format!(
+            "Tor v3 address {} is not supported: only {}-character .onion names are",
+            name, ONION_NAME_LEN
+        ));
+    }
+
+    let ip = onion_to_ip(name).ok_or_else(|| format!("Invalid .onion address {}", name))?;
+    let port = match port {
+        Some(port) => port
+            .parse()
+            .map_err(|_| format!("Invalid port of .onion address {}", s))?,
+        None => default_port,
+    };
+    Ok(Some(SocketAddr::new(ip, port)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ip_to_onion, onion_to_ip, parse_onion_address};
+    use std::net::SocketAddr;
+
+    #[test]
+    fn onion_name_is_mapped_to_onioncat_address() {
+        let ip = onion_to_ip("5wyqrzbvrdsumnok.onion").unwrap();
+        assert_eq!(
+            ip,
+            "fd87:d87e:eb43:edb1:8e4:3588:e546:35ca"
+                .parse::<::std::net::IpAddr>()
+                .unwrap()
+        );
+        assert_eq!(ip_to_onion(&ip), Some("5wyqrzbvrdsumnok.onion".to_owned()));
+        assert_eq!(onion_to_ip("5WYQRZBVRDSUMNOK.onion"), Some(ip));
+    }
+
+    #[test]
+    fn invalid_onion_names_are_rejected() {
+        assert_eq!(onion_to_ip("5wyqrzbvrdsumno.onion"), None);
+        assert_eq!(onion_to_ip("5wyqrzbvrdsumno1.onion"), None);
+        assert_eq!(onion_to_ip("5wyqrzbvrdsumnok.com"), None);
+        assert_eq!(ip_to_onion(&"127.0.0.1".parse().unwrap()), None);
+        assert_eq!(ip_to_onion(&"::1".parse().unwrap()), None);
+    }
+
+    #[test]
+    fn onion_address_is_parsed() {
+        let ip = onion_to_ip("5wyqrzbvrdsumnok.onion").unwrap();
+        assert_eq!(
+            parse_onion_address("5wyqrzbvrdsumnok.onion:8333", 1),
+            Ok(Some(SocketAddr::new(ip, 8333)))
+        );
+        assert_eq!(
+            parse_onion_address("5wyqrzbvrdsumnok.onion", 1),
+            Ok(Some(SocketAddr::new(ip, 1)))
+        );
+        assert_eq!(parse_onion_address("127.0.0.1:8333", 1), Ok(None));
+        assert!(parse_onion_address("5wyqrzbvrdsumno1.onion", 1).is_err());
+        assert!(parse_onion_address("5wyqrzbvrdsumnok.onion:port", 1).is_err());
+    }
+
+    #[test]
+    fn onion_v3_address_is_rejected() {
+        let v3_name = "pg6mmjiyjmcrsslvykfwnntlaru7p5svn6y2ymmju6nubxndf4pscryd.onion";
+        assert_eq!(onion_to_ip(v3_name), None);
+        let error = parse_onion_address(&format!("{}:8333", v3_name), 1).unwrap_err();
+        assert!(error.contains("Tor v3 address"));
+    }
+}
@@ -1,4 +1,6 @@
+use super::ExperimentFlags;
 use message::types;
+use message::Compression;
 use network::Magic;
 use std::net::SocketAddr;
 
@@ -16,7 +18,48 @@ pub struct PeerInfo {
     pub address: SocketAddr,
     pub user_agent: String,
     pub direction: Direction,
+    /// Only blocks are relayed over this connection (no addresses gossip).
+    pub block_relay_only: bool,
+    /// Peer address is whitelisted, so it is never banned or evicted.
+    pub whitelisted: bool,
+    /// Peer matches connection policy deprioritize filter, so it is evicted first.
+    pub deprioritized: bool,
+    /// Experimental protocol behaviors, enabled for this connection.
+    pub experiments: ExperimentFlags,
     pub version: u32,
     pub version_message: types::Version,
+    /// Compression of large messages, negotiated during handshake.
+    pub compression: Compression,
+    /// Headers are sent with `headers2` message, negotiated during handshake.
+    pub compressed_headers: bool,
     pub magic: Magic,
 }
+
+/// Numbers of open connections by direction.
+#[derive(Debug, Default, PartialEq, Clone)]
+pub struct ConnectionCount {
+    pub total: usize,
+    pub inbound: usize,
+    /// Outbound connections, including block-relay-only connections.
+    pub outbound: usize,
+    /// Outbound block-relay-only connections.
+    pub block_relay: usize,
+    /// Connections with whitelisted peers, in both directions.
+    pub whitelisted: usize,
+}
+
+/// Outcomes of messages compression for single connection.
+#[derive(Debug, PartialEq, Clone)]
+pub struct PeerCompressionStats {
+    pub id: PeerId,
+    pub address: SocketAddr,
+    pub compression: Compression,
+    /// Total number of bytes sent to the peer.
+    pub bytes_sent: u64,
+    /// Total number of bytes received from the peer.
+    pub bytes_received: u64,
+    /// Number of bytes, saved by compression of sent messages.
+    pub saved_sent: u64,
+    /// Number of bytes, saved by compression of received messages.
+    pub saved_received: u64,
+}
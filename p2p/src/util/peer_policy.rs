@@ -0,0 +1,226 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+
+/// Peer property, matched by connection policy: `version:70001`, `version:70001-70005`
+/// or `agent:/randchaind:0.1.0/` (substring of the advertised user agent).
+#[derive(Debug, Clone, PartialEq)]
+pub enum PeerFilter {
+    /// Protocol versions in the inclusive range.
+    Versions(u32, u32),
+    /// User agents, containing the pattern.
+    UserAgent(String),
+}
+
+/// Outcome of checking the peer against connection policy.
+#[derive(Debug, Clone, PartialEq)]
+pub enum PeerPolicyDecision {
+    /// Peer matches no filters.
+    Accept,
+    /// Peer is accepted, but it is the first to be evicted.
+    Deprioritize(PeerFilter),
+    /// Peer is disconnected right after handshake.
+    Reject(PeerFilter),
+}
+
+/// Connection policy, enforced at handshake. Reject filters take precedence over deprioritize filters.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PeerPolicy {
+    /// Peers matching any of these filters are disconnected.
+    pub reject: Vec<PeerFilter>,
+    /// Peers matching any of these filters are evicted before other inbound peers.
+    pub deprioritize: Vec<PeerFilter>,
+}
+
+/// Number of peers, matched by connection policy filter.
+#[derive(Debug, Default, PartialEq, Clone)]
+pub struct PeerPolicyStats {
+    /// Filter, e.g. `agent:/buggy:0.1/`.
+    pub filter: String,
+    /// Number of peers, which have been disconnected because of this filter.
+    pub rejected: u64,
+    /// Number of peers, which have been deprioritized because of this filter.
+    pub deprioritized: u64,
+}
+
+/// Counts peers, matched by connection policy filters.
+#[derive(Debug, Default)]
+pub struct PeerPolicyCounters {
+    matched: BTreeMap<String, PeerPolicyStats>,
+}
+
+impl PeerFilter {
+    /// Does peer with given version and user agent match the filter?
+    pub fn matches(&self, version: u32, user_agent: &str) -> bool {
+        match *self {
+            PeerFilter::Versions(min, max) => version >= min && version <= max,
+            PeerFilter::UserAgent(ref pattern) => user_agent.contains(pattern.as_str()),
+        }
+    }
+}
+
+impl FromStr for PeerFilter {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || {
+            format!(
+                "Invalid peer filter: {} - should be version:N, version:N-M or agent:PATTERN",
+                s
+            )
+        };
+        let pos = s.find(':').ok_or_else(invalid)?;
+        let (kind, value) = (&s[..pos], &s[pos + 1..]);
+        match kind {
+            "version" => {
+                let (min, max) = match value.find('-') {
+                    Some(pos) => (&value[..pos], &value[pos + 1..]),
+                    None => (value, value),
+                };
+                match (min.parse(), max.parse()) {
+                    (Ok(min), Ok(max)) if min <= max => Ok(PeerFilter::Versions(min, max)),
+                    _ => Err(invalid()),
+                }
+            }
+            "agent" if !value.is_empty() => Ok(PeerFilter::UserAgent(value.into())),
+            _ => Err(invalid()),
+        }
+    }
+}
+
+impl fmt::Display for PeerFilter {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            PeerFilter::Versions(min, max) if min == max => write!(f, "version:{}", min),
+            PeerFilter::Versions(min, max) => write!(f, "version:{}-{}", min, max),
+            PeerFilter::UserAgent(ref pattern) => write!(f, "agent:{}", pattern),
+        }
+    }
+}
+
+impl PeerPolicy {
+    /// Checks peer, advertising given version and user agent.
+    pub fn check(&self, version: u32, user_agent: &str) -> PeerPolicyDecision {
+        if let Some(filter) = self
+            .reject
+            .iter()
+            .find(|filter| filter.matches(version, user_agent))
+        {
+            return PeerPolicyDecision::Reject(filter.clone());
+        }
+        if let Some(filter) = self
+            .deprioritize
+            .iter()
+            .find(|filter| filter.matches(version, user_agent))
+        {
+            return PeerPolicyDecision::Deprioritize(filter.clone());
+        }
+        PeerPolicyDecision::Accept
+    }
+}
+
+impl PeerPolicyCounters {
+    /// Remember that peer has been rejected or deprioritized by the policy.
+    pub fn note_decision(&mut self, decision: &PeerPolicyDecision) {
+        let (filter, rejected) = match *decision {
+            PeerPolicyDecision::Accept => return,
+            PeerPolicyDecision::Deprioritize(ref filter) => (filter, false),
+            PeerPolicyDecision::Reject(ref filter) => (filter, true),
+        };
+        let filter = filter.to_string();
+        let stats = self
+            .matched
+            .entry(filter.clone())
+            .or_insert_with(|| PeerPolicyStats {
+                filter: filter,
+                ..Default::default()
+            });
+        if rejected {
+            stats.rejected += 1;
+        } else {
+            stats.deprioritized += 1;
+        }
+    }
+
+    /// Numbers of matched peers. Ordered by filter.
+    pub fn stats(&self) -> Vec<PeerPolicyStats> {
+        self.matched.values().cloned().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{PeerFilter, PeerPolicy, PeerPolicyCounters, PeerPolicyDecision, PeerPolicyStats};
+
+    #[test]
+    fn peer_filter_parse() {
+        assert_eq!(
+            "version:70001".parse::<PeerFilter>(),
+            Ok(PeerFilter::Versions(70_001, 70_001))
+        );
+        assert_eq!(
+            "version:70001-70005".parse::<PeerFilter>(),
+            Ok(PeerFilter::Versions(70_001, 70_005))
+        );
+        assert_eq!(
+            "agent:/randchaind:0.1".parse::<PeerFilter>(),
+            Ok(PeerFilter::UserAgent("/randchaind:0.1".into()))
+        );
+        assert!("version:70005-70001".parse::<PeerFilter>().is_err());
+        assert!("version:new".parse::<PeerFilter>().is_err());
+        assert!("agent:".parse::<PeerFilter>().is_err());
+        assert!("70001".parse::<PeerFilter>().is_err());
+        assert_eq!(
+            PeerFilter::Versions(70_001, 70_005).to_string(),
+            "version:70001-70005"
+        );
+        assert_eq!(
+            PeerFilter::Versions(70_001, 70_001).to_string(),
+            "version:70001"
+        );
+    }
+
+    #[test]
+    fn peer_policy_rejects_before_deprioritizing() {
+        let buggy = PeerFilter::UserAgent("/randchaind:0.1.".into());
+        let old = PeerFilter::Versions(0, 70_001);
+        let policy = PeerPolicy {
+            reject: vec![buggy.clone()],
+            deprioritize: vec![old.clone()],
+        };
+
+        assert_eq!(
+            policy.check(70_001, "/randchaind:0.1.2/"),
+            PeerPolicyDecision::Reject(buggy.clone())
+        );
+        assert_eq!(
+            policy.check(70_001, "/randchaind:0.2.0/"),
+            PeerPolicyDecision::Deprioritize(old.clone())
+        );
+        assert_eq!(
+            policy.check(70_002, "/randchaind:0.2.0/"),
+            PeerPolicyDecision::Accept
+        );
+
+        let mut counters = PeerPolicyCounters::default();
+        counters.note_decision(&PeerPolicyDecision::Reject(buggy.clone()));
+        counters.note_decision(&PeerPolicyDecision::Reject(buggy));
+        counters.note_decision(&PeerPolicyDecision::Deprioritize(old));
+        counters.note_decision(&PeerPolicyDecision::Accept);
+        assert_eq!(
+            counters.stats(),
+            vec![
+                PeerPolicyStats {
+                    filter: "agent:/randchaind:0.1.".into(),
+                    rejected: 2,
+                    deprioritized: 0,
+                },
+                PeerPolicyStats {
+                    filter: "version:0-70001".into(),
+                    rejected: 0,
+                    deprioritized: 1,
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,90 @@
+use std::collections::BTreeMap;
+
+/// Number of peers with given protocol version.
+#[derive(Debug, Default, PartialEq, Clone)]
+pub struct PeerVersionStats {
+    /// Protocol version.
+    pub version: u32,
+    /// Number of currently connected peers with this version.
+    pub connected: usize,
+    /// Number of peers, which have been disconnected, because this version is below minimum.
+    pub rejected: u64,
+}
+
+/// Counts peers, rejected because of obsolete protocol version.
+#[derive(Debug, Default)]
+pub struct RejectedPeerVersions {
+    rejected: BTreeMap<u32, u64>,
+}
+
+impl RejectedPeerVersions {
+    /// Remember that peer with given version has been rejected.
+    pub fn note_rejected(&mut self, version: u32) {
+        *self.rejected.entry(version).or_insert(0) += 1;
+    }
+
+    /// Combines rejected peers counts with versions of connected peers. Ordered by version.
+    pub fn stats<I>(&self, connected_versions: I) -> Vec<PeerVersionStats>
+    where
+        I: IntoIterator<Item = u32>,
+    {
+        let mut stats: BTreeMap<u32, PeerVersionStats> = self
+            .rejected
+            .iter()
+            .map(|(version, rejected)| {
+                (
+                    *version,
+                    PeerVersionStats {
+                        version: *version,
+                        connected: 0,
+                        rejected: *rejected,
+                    },
+                )
+            })
+            .collect();
+        for version in connected_versions {
+            stats
+                .entry(version)
+                .or_insert_with(|| PeerVersionStats {
+                    version: version,
+                    ..Default::default()
+                })
+                .connected += 1;
+        }
+        stats.into_iter().map(|(_, stats)| stats).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{PeerVersionStats, RejectedPeerVersions};
+
+    #[test]
+    fn peer_version_stats_are_ordered_by_version() {
+        let mut rejected = RejectedPeerVersions::default();
+        rejected.note_rejected(70_001);
+        rejected.note_rejected(70_001);
+        rejected.note_rejected(70_002);
+
+        assert_eq!(
+            rejected.stats(vec![70_014, 70_002, 70_014]),
+            vec![
+                PeerVersionStats {
+                    version: 70_001,
+                    connected: 0,
+                    rejected: 2,
+                },
+                PeerVersionStats {
+                    version: 70_002,
+                    connected: 1,
+                    rejected: 1,
+                },
+                PeerVersionStats {
+                    version: 70_014,
+                    connected: 2,
+                    rejected: 0,
+                },
+            ]
+        );
+    }
+}
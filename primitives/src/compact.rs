@@ -83,6 +83,26 @@ impl Compact {
         Compact(compact | (size << 24) as u32)
     }
 
+    /// Expected number of hashes, computed to find the hash, lower or equal than the target.
+    /// Invalid and zero targets require no work
+    pub fn to_work(&self) -> U256 {
+        match self.to_u256() {
+            Ok(target) if !target.is_zero() => {
+                (U256::max_value() - target) / (target + 1.into()) + 1.into()
+            }
+            _ => U256::zero(),
+        }
+    }
+
+    /// Adds work of the block with these bits to the chain work. Chain work saturates instead
+    /// of overflowing, so that blocks with adversarial bits can't wrap it around
+    pub fn add_work(&self, chain_work: U256) -> U256 {
+        match chain_work.overflowing_add(self.to_work()) {
+            (work, false) => work,
+            (_, true) => U256::max_value(),
+        }
+    }
+
     pub fn to_f64(&self) -> f64 {
         let mut shift = (self.0 >> 24) & 0xff;
         let mut diff = f64::from(0x0000ffffu32) / f64::from(self.0 & 0x00ffffffu32);
@@ -135,6 +155,33 @@ mod tests {
         assert_eq!(compact, compact2);
     }
 
+    #[test]
+    fn work() {
+        assert_eq!(
+            Compact::new(0x1d00ffff).to_work(),
+            U256::from(0x100010001u64)
+        );
+        assert_eq!(Compact::new(0x207fffff).to_work(), U256::from(2));
+        // lower target => more work
+        assert!(Compact::new(0x1c00ffff).to_work() > Compact::new(0x1d00ffff).to_work());
+        // invalid bits
+        assert_eq!(Compact::new(0).to_work(), U256::zero());
+        assert_eq!(Compact::new(0x01003456).to_work(), U256::zero());
+        assert_eq!(Compact::new(0x04923456).to_work(), U256::zero());
+    }
+
+    #[test]
+    fn add_work() {
+        assert_eq!(
+            Compact::new(0x207fffff).add_work(U256::from(3)),
+            U256::from(5)
+        );
+        assert_eq!(
+            Compact::new(0x207fffff).add_work(U256::max_value() - U256::one()),
+            U256::max_value()
+        );
+    }
+
     #[test]
     fn difficulty() {
         fn compare_f64(v1: f64, v2: f64) -> bool {
@@ -0,0 +1,351 @@
+//! Publishes best block changes to subscribers, connected over TCP.
+//!
+//! Notifications mirror ZMQ notifications of bitcoind: every notification is a sequence
+//! of three frames, each prefixed with its length (u32, little-endian):
+//! - topic: `hashblock` or `rawblock`;
+//! - body: hash of the new best block (32 bytes, in RPC byte order) or the serialized block;
+//! - sequence number of the notification on this topic (u32, little-endian).
+//!
+//! Subscribers never send anything. Subscribers, which are not reading notifications,
+//! are disconnected.
+
+use primitives::hash::H256;
+use ser::serialize;
+use std::collections::HashMap;
+use std::io::Write;
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+use storage;
+use sync::SyncListener;
+
+/// Subscriber is disconnected if notification isn't written within this time.
+const WRITE_TIMEOUT_S: u64 = 5;
+
+/// Notification topic.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Topic {
+    /// Hash of the new best block
+    HashBlock,
+    /// New best block, serialized
+    RawBlock,
+}
+
+impl Topic {
+    fn name(&self) -> &'static str {
+        match *self {
+            Topic::HashBlock => "hashblock",
+            Topic::RawBlock => "rawblock",
+        }
+    }
+}
+
+enum BlockPublisherTask {
+    NewBlock(H256),
+    Stop,
+}
+
+/// Listening socket with its subscribers.
+struct Endpoint {
+    topics: Vec<Topic>,
+    subscribers: Arc<Mutex<Vec<TcpStream>>>,
+}
+
+pub struct BlockPublisher {
+    tx: Sender<BlockPublisherTask>,
+    worker_thread: Option<thread::JoinHandle<()>>,
+}
+
+impl BlockPublisher {
+    /// Starts listening for subscribers. Topics with the same address are published to the same subscribers.
+    pub fn new(
+        storage: storage::SharedStore,
+        topics: Vec<(Topic, SocketAddr)>,
+    ) -> Result<Self, String> {
+        let mut addresses: Vec<SocketAddr> = Vec::new();
+        let mut address_topics: HashMap<SocketAddr, Vec<Topic>> = HashMap::new();
+        for (topic, address) in topics {
+            if !address_topics.contains_key(&address) {
+                addresses.push(address);
+            }
+            address_topics
+                .entry(address)
+                .or_insert_with(Vec::new)
+                .push(topic);
+        }
+
+        let mut endpoints = Vec::new();
+        for address in addresses {
+            let listener = TcpListener::bind(&address)
+                .map_err(|err| format!("Failed to bind block publisher to {}: {}", address, err))?;
+            let topics = address_topics.remove(&address).unwrap_or_default();
+            info!(
+                target: "randchaind",
+                "Publishing {} notifications on {}",
+                topics.iter().map(Topic::name).collect::<Vec<_>>().join(", "),
+                address
+            );
+            endpoints.push(Endpoint {
+                topics: topics,
+                subscribers: BlockPublisher::accept_subscribers(listener, address),
+            });
+        }
+
+        let (tx, rx) = channel();
+        Ok(BlockPublisher {
+            tx: tx,
+            worker_thread: Some(
+                thread::Builder::new()
+                    .name("Block publisher thread".to_owned())
+                    .spawn(move || BlockPublisher::worker(rx, storage, endpoints))
+                    .expect("Error creating block publisher thread"),
+            ),
+        })
+    }
+
+    fn accept_subscribers(
+        listener: TcpListener,
+        address: SocketAddr,
+    ) -> Arc<Mutex<Vec<TcpStream>>> {
+        let subscribers = Arc::new(Mutex::new(Vec::new()));
+        let accepted = subscribers.clone();
+        thread::Builder::new()
+            .name(format!("Block publisher listener {}", address))
+            .spawn(move || {
+                for stream in listener.incoming() {
+                    match stream.and_then(|stream| {
+                        stream.set_nodelay(true)?;
+                        stream.set_write_timeout(Some(Duration::from_secs(WRITE_TIMEOUT_S)))?;
+                        Ok(stream)
+                    }) {
+                        Ok(stream) => {
+                            trace!(target: "randchaind", "New block publisher subscriber {:?}", stream.peer_addr());
+                            accepted
+                                .lock()
+                                .expect("subscribers lock is never poisoned")
+                                .push(stream);
+                        }
+                        Err(err) => {
+                            warn!(target: "randchaind", "Failed to accept block publisher subscriber: {}", err)
+                        }
+                    }
+                }
+            })
+            .expect("Error creating block publisher listener thread");
+        subscribers
+    }
+
+    fn worker(
+        rx: Receiver<BlockPublisherTask>,
+        storage: storage::SharedStore,
+        endpoints: Vec<Endpoint>,
+    ) {
+        let mut sequences: HashMap<Topic, u32> = HashMap::new();
+        for task in rx {
+            let hash = match task {
+                BlockPublisherTask::NewBlock(hash) => hash,
+                BlockPublisherTask::Stop => break,
+            };
+
+            // every topic is published once, whatever number of endpoints it is published on
+            let mut notifications: HashMap<Topic, Vec<u8>> = HashMap::new();
+            for endpoint in &endpoints {
+                for topic in &endpoint.topics {
+                    if !notifications.contains_key(topic) {
+                        let body = match *topic {
+                            Topic::HashBlock => Some(hash.reversed().take().to_vec()),
+                            Topic::RawBlock => storage
+                                .block(hash.clone().into())
+                                .map(|block| serialize(&block.to_raw_block()).take()),
+                        };
+                        let body = match body {
+                            Some(body) => body,
+                            None => {
+                                warn!(target: "randchaind", "Block {} is not published: it is not in the storage", hash.to_reversed_str());
+                                continue;
+                            }
+                        };
+                        let sequence = sequences.entry(*topic).or_insert(0);
+                        notifications.insert(*topic, notification(*topic, &body, *sequence));
+                        *sequence = sequence.wrapping_add(1);
+                    }
+
+                    if let Some(notification) = notifications.get(topic) {
+                        publish(&endpoint.subscribers, notification);
+                    }
+                }
+            }
+        }
+        trace!(target: "randchaind", "Block publisher thread stopped");
+    }
+}
+
+impl SyncListener for BlockPublisher {
+    fn synchronization_state_switched(&self, _is_synchronizing: bool) {}
+
+    fn best_storage_block_inserted(&self, block_hash: &H256) {
+        self.tx
+            .send(BlockPublisherTask::NewBlock(block_hash.clone()))
+            .expect("Block publisher thread have the same lifetime as `BlockPublisher`")
+    }
+}
+
+impl Drop for BlockPublisher {
+    fn drop(&mut self) {
+        if let Some(join_handle) = self.worker_thread.take() {
+            let _ = self.tx.send(BlockPublisherTask::Stop);
+            join_handle.join().expect("Clean shutdown.");
+        }
+    }
+}
+
+/// Encodes notification frames.
+fn notification(topic: Topic, body: &[u8], sequence: u32) -> Vec<u8> {
+    let mut notification = Vec::with_capacity(topic.name().len() + body.len() + 16);
+    let sequence = sequence.to_le_bytes();
+    let frames: [&[u8]; 3] = [topic.name().as_bytes(), body, &sequence];
+    for frame in &frames {
+        notification.extend_from_slice(&(frame.len() as u32).to_le_bytes());
+        notification.extend_from_slice(frame);
+    }
+    notification
+}
+
+/// Writes notification to all subscribers, disconnecting those who have failed to receive it.
+fn publish(subscribers: &Mutex<Vec<TcpStream>>, notification: &[u8]) {
+    subscribers
+        .lock()
+        .expect("subscribers lock is never poisoned")
+        .retain(|mut subscriber| match subscriber.write_all(notification) {
+            Ok(()) => true,
+            Err(err) => {
+                debug!(target: "randchaind", "Block publisher subscriber {:?} is disconnected: {}", subscriber.peer_addr(), err);
+                false
+            }
+        });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{notification, publish, BlockPublisher, BlockPublisherTask, Endpoint, Topic};
+    use chain::IndexedBlock;
+    use db::BlockChainDatabase;
+    use primitives::hash::H256;
+    use ser::serialize;
+    use std::io::Read;
+    use std::net::{TcpListener, TcpStream};
+    use std::sync::mpsc::channel;
+    use std::sync::{Arc, Mutex};
+    use std::thread;
+    use std::time::Duration;
+    use test_data;
+
+    fn connected_pair(listener: &TcpListener) -> (TcpStream, TcpStream) {
+        let client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let (server, _) = listener.accept().unwrap();
+        client
+            .set_read_timeout(Some(Duration::from_secs(5)))
+            .unwrap();
+        (server, client)
+    }
+
+    fn read_exact(client: &mut TcpStream, len: usize) -> Vec<u8> {
+        let mut data = vec![0u8; len];
+        client.read_exact(&mut data).unwrap();
+        data
+    }
+
+    #[test]
+    fn notification_is_framed() {
+        assert_eq!(
+            notification(Topic::HashBlock, &[1, 2], 7),
+            vec![
+                9, 0, 0, 0, b'h', b'a', b's', b'h', b'b', b'l', b'o', b'c', b'k', 2, 0, 0, 0, 1, 2,
+                4, 0, 0, 0, 7, 0, 0, 0,
+            ]
+        );
+        assert_eq!(&notification(Topic::RawBlock, &[], 0)[4..12], b"rawblock");
+    }
+
+    #[test]
+    fn notifications_are_published_to_all_subscribers() {
+        let block: IndexedBlock = test_data::block_h1().into();
+        let storage = Arc::new(BlockChainDatabase::init_test_chain(vec![
+            test_data::genesis().into(),
+            block.clone(),
+        ]));
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let (hash_server1, mut hash_client1) = connected_pair(&listener);
+        let (hash_server2, mut hash_client2) = connected_pair(&listener);
+        let (raw_server, mut raw_client) = connected_pair(&listener);
+        let endpoints = vec![
+            Endpoint {
+                topics: vec![Topic::HashBlock],
+                subscribers: Arc::new(Mutex::new(vec![hash_server1, hash_server2])),
+            },
+            Endpoint {
+                topics: vec![Topic::HashBlock, Topic::RawBlock],
+                subscribers: Arc::new(Mutex::new(vec![raw_server])),
+            },
+        ];
+
+        let (tx, rx) = channel();
+        tx.send(BlockPublisherTask::NewBlock(block.hash().clone()))
+            .unwrap();
+        // block, which is not stored, is only published on the hashblock topic
+        tx.send(BlockPublisherTask::NewBlock(H256::from(1)))
+            .unwrap();
+        tx.send(BlockPublisherTask::Stop).unwrap();
+        BlockPublisher::worker(rx, storage, endpoints);
+
+        // topic, published on several endpoints, has the single sequence
+        let mut hash_notifications =
+            notification(Topic::HashBlock, &block.hash().reversed().take(), 0);
+        hash_notifications.extend(notification(
+            Topic::HashBlock,
+            &H256::from(1).reversed().take(),
+            1,
+        ));
+        for client in vec![&mut hash_client1, &mut hash_client2] {
+            assert_eq!(
+                read_exact(client, hash_notifications.len()),
+                hash_notifications
+            );
+        }
+
+        let raw_block = serialize(&block.to_raw_block()).take();
+        let mut raw_notifications = hash_notifications[..hash_notifications.len() / 2].to_vec();
+        raw_notifications.extend(notification(Topic::RawBlock, &raw_block, 0));
+        raw_notifications.extend(&hash_notifications[hash_notifications.len() / 2..]);
+        assert_eq!(
+            read_exact(&mut raw_client, raw_notifications.len()),
+            raw_notifications
+        );
+    }
+
+    #[test]
+    fn disconnected_subscribers_are_removed() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let (server, mut client) = connected_pair(&listener);
+        let (disconnected_server, disconnected_client) = connected_pair(&listener);
+        let subscribers = Mutex::new(vec![server, disconnected_server]);
+        drop(disconnected_client);
+
+        // writes to the closed connection start failing, once the connection is reset
+        let message = notification(Topic::HashBlock, &[1; 32], 0);
+        let mut published = 0;
+        while subscribers.lock().unwrap().len() == 2 {
+            assert!(published < 100, "disconnected subscriber is not removed");
+            publish(&subscribers, &message);
+            published += 1;
+            thread::sleep(Duration::from_millis(10));
+        }
+
+        assert_eq!(
+            read_exact(&mut client, message.len() * published),
+            message.repeat(published)
+        );
+    }
+}
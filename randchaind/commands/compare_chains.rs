@@ -0,0 +1,154 @@
+use chain::IndexedBlockHeader;
+use clap::ArgMatches;
+use db::DatabaseBackend;
+use std::cmp::{max, min};
+use storage::{self, BlockRef};
+use util::open_db;
+
+/// Database cache size (in MB) of every compared database.
+const DB_CACHE: usize = 64;
+/// Default max number of reported differing blocks.
+const DEFAULT_MAX_BLOCKS: u32 = 100;
+
+/// Compares canon chains of two node databases and prints the divergence height, blocks
+/// of both chains above the common ancestor and heights, where randomness outputs differ.
+/// Databases must not be used by running nodes.
+pub fn compare_chains(matches: &ArgMatches) -> Result<(), String> {
+    let dir_a = matches
+        .value_of("DIR_A")
+        .expect("DIR_A is required in cli.yml; qed");
+    let dir_b = matches
+        .value_of("DIR_B")
+        .expect("DIR_B is required in cli.yml; qed");
+    let max_blocks = match matches.value_of("max-blocks") {
+        Some(s) => match s.parse() {
+            Ok(max_blocks) if max_blocks > 0 => max_blocks,
+            _ => return Err("Invalid max-blocks - should be positive number".into()),
+        },
+        None => DEFAULT_MAX_BLOCKS,
+    };
+
+    let db_a = open_chain_db(dir_a)?;
+    let db_b = open_chain_db(dir_b)?;
+    let best_a = db_a.best_block();
+    let best_b = db_b.best_block();
+    println!(
+        "A: {}, best block: {} {}",
+        dir_a,
+        best_a.number,
+        best_a.hash.to_reversed_str()
+    );
+    println!(
+        "B: {}, best block: {} {}",
+        dir_b,
+        best_b.number,
+        best_b.hash.to_reversed_str()
+    );
+
+    if db_a.block_hash(0) != db_b.block_hash(0) {
+        return Err("Chains have different genesis blocks".into());
+    }
+
+    let divergence = match divergence_height(&db_a, &db_b) {
+        Some(divergence) => divergence,
+        None => {
+            println!("Chains are identical");
+            return Ok(());
+        }
+    };
+    println!(
+        "Chains diverge at height {}, common ancestor: {}",
+        divergence,
+        db_a.block_hash(divergence - 1)
+            .expect("divergence is above the common genesis block; qed")
+            .to_reversed_str()
+    );
+
+    let top = max(best_a.number, best_b.number);
+    let last = min(top, divergence.saturating_add(max_blocks - 1));
+    let mut randomness_diverged = None;
+    println!("{:>10} {:>64} {:>64} randomness", "height", "A", "B");
+    for number in divergence..last + 1 {
+        let header_a = canon_header(&db_a, number);
+        let header_b = canon_header(&db_b, number);
+        let same_randomness = match (&header_a, &header_b) {
+            (&Some(ref a), &Some(ref b)) => a.raw.randomness == b.raw.randomness,
+            _ => false,
+        };
+        if !same_randomness && randomness_diverged.is_none() {
+            randomness_diverged = Some(number);
+        }
+
+        println!(
+            "{:>10} {:>64} {:>64} {}",
+            number,
+            header_a
+                .as_ref()
+                .map_or("-".to_owned(), |h| h.hash.to_reversed_str()),
+            header_b
+                .as_ref()
+                .map_or("-".to_owned(), |h| h.hash.to_reversed_str()),
+            if same_randomness { "same" } else { "differs" },
+        );
+        if !same_randomness {
+            for (name, header) in vec![("A", &header_a), ("B", &header_b)] {
+                if let Some(ref header) = *header {
+                    println!(
+                        "{:>10} {}: {}",
+                        "",
+                        name,
+                        header.raw.randomness.to_string_radix(16)
+                    );
+                }
+            }
+        }
+    }
+    if last < top {
+        println!("... {} more heights are not reported", top - last);
+    }
+
+    match randomness_diverged {
+        Some(number) => println!("Randomness outputs diverge at height {}", number),
+        None => println!("Randomness outputs are the same up to height {}", last),
+    }
+    Ok(())
+}
+
+fn open_chain_db(data_dir: &str) -> Result<storage::SharedStore, String> {
+    open_db(
+        &Some(data_dir.to_owned()),
+        DatabaseBackend::RocksDb,
+        DB_CACHE,
+        false,
+        None,
+        None,
+    )
+    .map_err(|err| format!("{}: {}", data_dir, err))
+}
+
+fn canon_header(db: &storage::SharedStore, number: u32) -> Option<IndexedBlockHeader> {
+    db.block_hash(number)
+        .and_then(|hash| db.block_header(BlockRef::Hash(hash)))
+}
+
+/// Returns the lowest height, where canon chains have different blocks. Chains are sharing the
+/// genesis block. Since every block commits to its parent, chains are equal below divergence.
+fn divergence_height(db_a: &storage::SharedStore, db_b: &storage::SharedStore) -> Option<u32> {
+    let best_a = db_a.best_block().number;
+    let best_b = db_b.best_block().number;
+    let (mut same, mut differs) = (0, min(best_a, best_b) + 1);
+    while differs - same > 1 {
+        let middle = same + (differs - same) / 2;
+        if db_a.block_hash(middle) == db_b.block_hash(middle) {
+            same = middle;
+        } else {
+            differs = middle;
+        }
+    }
+
+    if differs > best_a && differs > best_b {
+        None
+    } else {
+        Some(differs)
+    }
+}
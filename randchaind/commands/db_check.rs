@@ -0,0 +1,51 @@
+use clap::ArgMatches;
+use db::ConsistencyChecker;
+use util::db_path;
+
+/// Checks consistency of the database of the stopped node and prints found inconsistencies.
+/// With `--repair`, inconsistencies, which could be fixed without data loss, are repaired.
+/// Remaining inconsistencies require reindex.
+pub fn db_check(matches: &ArgMatches, check_matches: &ArgMatches) -> Result<(), String> {
+    let data_dir = matches
+        .value_of("data-dir")
+        .map(|data_dir| data_dir.to_owned());
+    let path = db_path(&data_dir);
+    let repair = check_matches.is_present("repair");
+
+    let checker = ConsistencyChecker::open_at_path(&path)
+        .map_err(|err| format!("Failed to open database {}: {:?}", path.display(), err))?;
+    let report = checker
+        .check(repair)
+        .map_err(|err| format!("Failed to check database {}: {:?}", path.display(), err))?;
+
+    for inconsistency in &report.inconsistencies {
+        println!(
+            "{}{}",
+            inconsistency,
+            if inconsistency.is_repairable() {
+                ""
+            } else {
+                " (not repairable)"
+            }
+        );
+    }
+    println!(
+        "Checked {} canon blocks: {} inconsistencies found, {} repaired",
+        report.checked_blocks,
+        report.inconsistencies.len(),
+        report.repaired
+    );
+
+    let unrepaired = report.inconsistencies.len() - report.repaired;
+    match unrepaired {
+        0 => Ok(()),
+        _ if report
+            .inconsistencies
+            .iter()
+            .all(|inconsistency| inconsistency.is_repairable()) =>
+        {
+            Err("Database is inconsistent. Run db check --repair to fix it".into())
+        }
+        _ => Err("Database is inconsistent. Run the node with --reindex to rebuild it".into()),
+    }
+}
@@ -0,0 +1,80 @@
+use super::super::rpc;
+use chain;
+use clap::ArgMatches;
+use ethcore_rpc::Authenticator;
+use ser::deserialize_iterator;
+use std::fs::File;
+use std::io::BufReader;
+use std::time::Duration;
+use sync::create_sync_blocks_writer;
+use util::init_db;
+use {config, p2p};
+
+/// Log import progress every `PROGRESS_INTERVAL` blocks.
+const PROGRESS_INTERVAL: usize = 10_000;
+
+/// Imports blocks from the file with serialized blocks.
+///
+/// Import holds the only `BlocksWriter`, but the storage itself is shared, so RPC server
+/// is started in read-only mode to answer height/progress queries while import is running.
+pub fn import(mut cfg: config::Config, matches: &ArgMatches) -> Result<(), String> {
+    init_db(&cfg)?;
+
+    let blocks_path = matches
+        .value_of("PATH")
+        .expect("PATH is required in cli.yml; qed");
+    let blocks_file = File::open(blocks_path)
+        .map_err(|err| format!("Failed to open blocks file {}: {}", blocks_path, err))?;
+    let commit_budget = match matches.value_of("commit-budget") {
+        Some(s) => Some(Duration::from_millis(s.parse().map_err(|_| {
+            "Invalid commit-budget - should be number of milliseconds".to_owned()
+        })?)),
+        None => None,
+    };
+
+    // start RPC server with storage-only APIs: there's no sync node or p2p during import
+    let el = p2p::event_loop();
+    cfg.rpc_config.apis = cfg.rpc_config.apis.read_only();
+    let rpc_deps = rpc::Dependencies {
+        network: cfg.network,
+        storage: cfg.db.clone(),
+        local_sync_node: None,
+        p2p_context: None,
+        mining: None,
+        mining_pubkey: None,
+        remote: el.remote(),
+        config_reloader: None,
+    };
+    let _rpc_server = rpc::new_http(cfg.rpc_config, rpc_deps, &Authenticator::default())?;
+
+    let mut writer =
+        create_sync_blocks_writer(cfg.db.clone(), cfg.network, cfg.verification_params)
+            .with_commit_budget(commit_budget);
+    let mut imported = 0;
+    for block in deserialize_iterator::<_, chain::Block>(BufReader::new(blocks_file)) {
+        let block = block.map_err(|err| format!("Failed to read block: {:?}", err))?;
+        writer
+            .append_block(block.into())
+            .map_err(|err| format!("Failed to import block: {:?}", err))?;
+
+        imported += 1;
+        if imported % PROGRESS_INTERVAL == 0 {
+            info!(
+                "Imported {} blocks, best block height: {}",
+                imported,
+                cfg.db.best_block().number
+            );
+        }
+    }
+    writer
+        .flush()
+        .map_err(|err| format!("Failed to import block: {:?}", err))?;
+
+    info!(
+        "Import finished: {} blocks read, best block height: {}",
+        imported,
+        cfg.db.best_block().number
+    );
+    info!("Import write phases: {}", writer.metrics());
+    Ok(())
+}
@@ -1,3 +1,15 @@
+mod compare_chains;
+mod db_check;
+mod import;
+mod reindex;
+mod selftest;
 mod start;
+mod verifier_state;
 
+pub use self::compare_chains::compare_chains;
+pub use self::db_check::db_check;
+pub use self::import::import;
+pub use self::reindex::reindex;
+pub use self::selftest::selftest;
 pub use self::start::start;
+pub use self::verifier_state::{export_state, import_state};
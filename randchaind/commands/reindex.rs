@@ -0,0 +1,92 @@
+use config;
+use db::StoredBlocks;
+use std::fs::{remove_dir_all, rename};
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+use sync::create_sync_blocks_writer;
+use util::rollback_path;
+
+/// Log reindex progress every `PROGRESS_INTERVAL` blocks.
+const PROGRESS_INTERVAL: usize = 10_000;
+
+/// Rebuilds the database from blocks of the old database at `blocks_path`.
+///
+/// Blocks are read in parent-first order, without using the old (possibly corrupted) indexes,
+/// and are passed through the `BlocksWriter`. So they are verified again and the number→hash
+/// index, best block and side chains are rebuilt the same way as during synchronization.
+/// The old database is removed when reindex is finished. If block backups are enabled,
+/// it is moved to the rollback directory instead.
+pub fn reindex(cfg: &config::Config, blocks_path: &Path) -> Result<(), String> {
+    let stored_blocks = StoredBlocks::open_at_path(blocks_path).map_err(|err| {
+        format!(
+            "Failed to open database {} for reindex: {:?}",
+            blocks_path.display(),
+            err
+        )
+    })?;
+
+    let genesis_hash = cfg.network.genesis_block().hash().clone();
+    let hashes = stored_blocks.descendants_of(&genesis_hash);
+    info!("Reindexing {} blocks", hashes.len());
+
+    let mut writer =
+        create_sync_blocks_writer(cfg.db.clone(), cfg.network, cfg.verification_params.clone());
+    let mut reindexed = 0;
+    for hash in hashes {
+        let block = stored_blocks
+            .block(&hash)
+            .ok_or_else(|| format!("Failed to read block {}", hash.to_reversed_str()))?;
+        if block.proof.is_empty() {
+            return Err(format!(
+                "Body of the block {} is pruned. Only databases with all block bodies can be reindexed",
+                hash.to_reversed_str()
+            ));
+        }
+
+        writer
+            .append_block(block)
+            .map_err(|err| format!("Failed to reindex block: {:?}", err))?;
+
+        reindexed += 1;
+        if reindexed % PROGRESS_INTERVAL == 0 {
+            info!(
+                "Reindexed {} blocks, best block height: {}",
+                reindexed,
+                cfg.db.best_block().number
+            );
+        }
+    }
+
+    info!(
+        "Reindex finished: {} blocks reindexed, best block height: {}",
+        reindexed,
+        cfg.db.best_block().number
+    );
+    info!("Reindex write phases: {}", writer.metrics());
+
+    drop(stored_blocks);
+    if cfg.backup_reorg_depth.is_some() {
+        let time = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        let backup_path = rollback_path(&cfg.data_dir).join(format!("reindex-{}", time));
+        info!("Moving reindexed database to {}", backup_path.display());
+        return rename(blocks_path, &backup_path).map_err(|err| {
+            format!(
+                "Failed to move reindexed database {} to {}: {}",
+                blocks_path.display(),
+                backup_path.display(),
+                err
+            )
+        });
+    }
+
+    remove_dir_all(blocks_path).map_err(|err| {
+        format!(
+            "Failed to remove reindexed database at {}: {}",
+            blocks_path.display(),
+            err
+        )
+    })
+}
@@ -0,0 +1,375 @@
+use super::super::rpc;
+use chain::{Block, BlockHeader, IndexedBlock};
+use config::DEFAULT_DB_CACHE;
+use db::DatabaseBackend;
+use ecvrf::{self, VrfPk};
+use ethcore_rpc::{Meta, RpcStats};
+use miner::{find_solution, BlockTemplate};
+use network::Network;
+use p2p;
+use primitives::hash::H256;
+use rpc_apis::{Api, ApiSet};
+use std::cmp;
+use std::env;
+use std::fs::remove_dir_all;
+use std::path::PathBuf;
+use std::process;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use storage::{self, BlockRef};
+use sync::{
+    create_local_sync_node, create_sync_blocks_writer, create_sync_peers, BlocksWriter,
+    HeaderBranchesLimits, RelayConfig, VerificationParameters, DEFAULT_BLOCK_STALL_TIMEOUT_S,
+    DEFAULT_MAX_REQUESTED_BLOCKS, DEFAULT_MAX_VERIFICATION_QUEUE, DEFAULT_MEMORY_BUDGET,
+    DEFAULT_MISBEHAVIOR_SCORE_TTL_S, DEFAULT_SERVER_LOG_SAMPLING,
+};
+use util::{init_store, open_db};
+use verification::{work_required, BlockVerifier, VerificationLevel};
+
+/// Self-test always runs against regtest parameters.
+const NETWORK: Network = Network::Regtest;
+/// Number of blocks, mined on top of the genesis block.
+const MAIN_CHAIN_BLOCKS: u32 = 3;
+/// Height of the main chain block, which the competing branch is mined on top of.
+const FORK_PARENT_HEIGHT: u32 = 1;
+/// Mining of single regtest block is given up after this number of seconds.
+const MINING_TIMEOUT_S: u64 = 60;
+
+type Check = fn(&mut SelfTest) -> Result<(), String>;
+
+/// Runs the whole local pipeline (storage, miner, verification, reorganization and RPC)
+/// against temporary regtest database and prints pass/fail per subsystem.
+///
+/// The configured database is not touched, so the self-test is safe to run next to
+/// a running node, e.g. right after an upgrade.
+pub fn selftest() -> Result<(), String> {
+    let data_dir = env::temp_dir().join(format!("randchaind-selftest-{}", process::id()));
+    let result = run_checks(&data_dir);
+    if let Err(err) = remove_dir_all(&data_dir) {
+        warn!(target: "randchaind", "Failed to remove self-test data dir {}: {}", data_dir.display(), err);
+    }
+    result
+}
+
+fn run_checks(data_dir: &PathBuf) -> Result<(), String> {
+    let db = open_db(
+        &Some(data_dir.to_string_lossy().into_owned()),
+        DatabaseBackend::RocksDb,
+        DEFAULT_DB_CACHE,
+        false,
+        None,
+        None,
+    )?;
+    let mut test = SelfTest {
+        db: db.clone(),
+        writer: create_sync_blocks_writer(db, NETWORK, verification_params()),
+        main_chain: Vec::new(),
+        fork: Vec::new(),
+    };
+
+    println!("Running self-test in {}", data_dir.display());
+    let checks: [(&str, Check); 5] = [
+        ("storage", SelfTest::check_storage),
+        ("mining", SelfTest::check_mining),
+        ("proofs", SelfTest::check_proofs),
+        ("reorg", SelfTest::check_reorg),
+        ("rpc", SelfTest::check_rpc),
+    ];
+    let total = checks.len();
+    let mut failed = 0;
+    for &(subsystem, check) in checks.iter() {
+        match check(&mut test) {
+            Ok(()) => println!("{:<10} PASS", subsystem),
+            Err(err) => {
+                failed += 1;
+                println!("{:<10} FAIL: {}", subsystem, err);
+            }
+        }
+    }
+
+    match failed {
+        0 => {
+            println!("All {} self-test checks passed", total);
+            Ok(())
+        }
+        failed => Err(format!("{} of {} self-test checks failed", failed, total)),
+    }
+}
+
+struct SelfTest {
+    db: storage::SharedStore,
+    writer: BlocksWriter,
+    /// Blocks, mined on top of the genesis block.
+    main_chain: Vec<IndexedBlock>,
+    /// Blocks of the competing branch, which has replaced the main chain.
+    fork: Vec<IndexedBlock>,
+}
+
+impl SelfTest {
+    /// Genesis block is inserted and read back.
+    fn check_storage(&mut self) -> Result<(), String> {
+        init_store(&self.db, NETWORK)?;
+
+        let genesis_hash = NETWORK.genesis_block().hash().clone();
+        let best_block = self.db.best_block();
+        if best_block.number != 0 || best_block.hash != genesis_hash {
+            return Err(format!(
+                "best block is {} at height {}, expected genesis block {}",
+                best_block.hash.to_reversed_str(),
+                best_block.number,
+                genesis_hash.to_reversed_str()
+            ));
+        }
+        match self.db.block(BlockRef::Number(0)) {
+            Some(ref block) if *block.hash() == genesis_hash => Ok(()),
+            _ => Err("genesis block can not be read back".into()),
+        }
+    }
+
+    /// Blocks are mined on top of the best block and inserted with full verification.
+    fn check_mining(&mut self) -> Result<(), String> {
+        let (_, pubkey) = ecvrf::keygen();
+        for _ in 0..MAIN_CHAIN_BLOCKS {
+            let best_block = self.db.best_block();
+            let block = mine_block(&self.db, &best_block.hash, best_block.number + 1, &pubkey)?;
+            self.append_block(block.clone())?;
+            self.main_chain.push(block);
+        }
+
+        let best_block = self.db.best_block();
+        let expected_best = self.main_chain.last().map(|block| block.hash().clone());
+        if best_block.number != MAIN_CHAIN_BLOCKS || Some(best_block.hash) != expected_best {
+            return Err(format!(
+                "best block height is {} after {} blocks are mined",
+                best_block.number, MAIN_CHAIN_BLOCKS
+            ));
+        }
+        Ok(())
+    }
+
+    /// Proofs of mined blocks are valid and swapped proof is rejected.
+    fn check_proofs(&mut self) -> Result<(), String> {
+        if self.main_chain.len() < 2 {
+            return Err("not enough blocks have been mined".into());
+        }
+
+        for block in &self.main_chain {
+            BlockVerifier::new(block, NETWORK).check().map_err(|err| {
+                format!(
+                    "proof of block {} is rejected: {:?}",
+                    block.hash().to_reversed_str(),
+                    err
+                )
+            })?;
+        }
+
+        let mut invalid_block = self.main_chain[0].clone();
+        invalid_block.proof = self.main_chain[1].proof.clone();
+        match BlockVerifier::new(&invalid_block, NETWORK).check() {
+            Ok(()) => Err("block with proof of another block is accepted".into()),
+            Err(_) => Ok(()),
+        }
+    }
+
+    /// Longer competing branch replaces the main chain.
+    fn check_reorg(&mut self) -> Result<(), String> {
+        let fork_parent = self
+            .main_chain
+            .get(FORK_PARENT_HEIGHT as usize - 1)
+            .map(|block| block.hash().clone())
+            .ok_or_else(|| "not enough blocks have been mined".to_owned())?;
+
+        // competing branch is one block longer than the main chain
+        let (_, pubkey) = ecvrf::keygen();
+        let mut parent = fork_parent;
+        for height in FORK_PARENT_HEIGHT + 1..MAIN_CHAIN_BLOCKS + 2 {
+            let block = mine_block(&self.db, &parent, height, &pubkey)?;
+            self.append_block(block.clone())?;
+            parent = block.hash().clone();
+            self.fork.push(block);
+        }
+
+        let best_block = self.db.best_block();
+        if best_block.hash != parent || best_block.number != MAIN_CHAIN_BLOCKS + 1 {
+            return Err(format!(
+                "best block {} at height {} is not the tip of the longer branch",
+                best_block.hash.to_reversed_str(),
+                best_block.number
+            ));
+        }
+        for block in &self.fork {
+            if self.db.block_number(block.hash()).is_none() {
+                return Err(format!(
+                    "block {} of the longer branch is not canonized",
+                    block.hash().to_reversed_str()
+                ));
+            }
+        }
+        for block in &self.main_chain[FORK_PARENT_HEIGHT as usize..] {
+            if self.db.block_number(block.hash()).is_some() {
+                return Err(format!(
+                    "block {} of the replaced branch is still canonical",
+                    block.hash().to_reversed_str()
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Blockchain and miner RPC methods are answered from the local storage.
+    fn check_rpc(&mut self) -> Result<(), String> {
+        // the event loop is never run: RPC requests are handled synchronously
+        let el = p2p::event_loop();
+        let local_sync_node = create_local_sync_node(
+            NETWORK,
+            self.db.clone(),
+            create_sync_peers(DEFAULT_MISBEHAVIOR_SCORE_TTL_S),
+            verification_params(),
+            1,
+            None,
+            DEFAULT_SERVER_LOG_SAMPLING,
+            DEFAULT_BLOCK_STALL_TIMEOUT_S,
+            DEFAULT_MAX_REQUESTED_BLOCKS,
+            DEFAULT_MAX_VERIFICATION_QUEUE,
+            DEFAULT_MEMORY_BUDGET,
+            None,
+            HeaderBranchesLimits::default(),
+            RelayConfig::default(),
+            false,
+        );
+        let deps = rpc::Dependencies {
+            network: NETWORK,
+            storage: self.db.clone(),
+            local_sync_node: Some(local_sync_node),
+            p2p_context: None,
+            mining: None,
+            mining_pubkey: None,
+            remote: el.remote(),
+            config_reloader: None,
+        };
+        let handler = rpc::setup_rpc_server(
+            ApiSet::List(vec![Api::BlockChain, Api::Miner].into_iter().collect()),
+            RpcStats::default(),
+            deps,
+        );
+        let call = |method: &str, params: &str| -> Result<String, String> {
+            let request = format!(
+                r#"{{"jsonrpc": "2.0", "method": "{}", "params": [{}], "id": 1}}"#,
+                method, params
+            );
+            handler
+                .handle_request_sync(&request, Meta::default())
+                .ok_or_else(|| format!("{} has not returned any response", method))
+        };
+        let expect = |method: &str, params: &str, expected: &str| -> Result<(), String> {
+            let response = call(method, params)?;
+            match response.contains(expected) {
+                true => Ok(()),
+                false => Err(format!(
+                    "{} response {} does not contain {}",
+                    method, response, expected
+                )),
+            }
+        };
+
+        let best_block = self.db.best_block();
+        let best_hash = best_block.hash.to_reversed_str();
+        expect(
+            "getblockcount",
+            "",
+            &format!(r#""result":{},"#, best_block.number),
+        )?;
+        expect(
+            "getbestblockhash",
+            "",
+            &format!(r#""result":"{}""#, best_hash),
+        )?;
+        expect(
+            "getblockhash",
+            &best_block.number.to_string(),
+            &format!(r#""result":"{}""#, best_hash),
+        )?;
+        expect("getblock", &format!(r#""{}""#, best_hash), &best_hash)?;
+        expect(
+            "getblocktemplate",
+            "{}",
+            &format!(r#""previousblockhash":"{}""#, best_hash),
+        )
+    }
+
+    fn append_block(&mut self, block: IndexedBlock) -> Result<(), String> {
+        let hash = block.hash().clone();
+        self.writer.append_block(block).map_err(|err| {
+            format!(
+                "block {} is not inserted: {:?}",
+                hash.to_reversed_str(),
+                err
+            )
+        })
+    }
+}
+
+/// Mines block on top of given parent with the cpu miner.
+fn mine_block(
+    db: &storage::SharedStore,
+    parent_hash: &H256,
+    height: u32,
+    pubkey: &VrfPk,
+) -> Result<IndexedBlock, String> {
+    let parent = db
+        .block_header(BlockRef::Hash(parent_hash.clone()))
+        .ok_or_else(|| format!("parent block {} is unknown", parent_hash.to_reversed_str()))?;
+    let time = cmp::max(unix_time(), parent.raw.time + 1);
+    let template = BlockTemplate {
+        version: parent.raw.version,
+        previous_header_hash: parent_hash.clone(),
+        time: time,
+        bits: work_required(
+            parent_hash.clone(),
+            time,
+            height,
+            db.as_block_header_provider(),
+            &NETWORK,
+        ),
+        height: height,
+    };
+
+    let solution = find_solution(
+        &template,
+        pubkey,
+        NETWORK.vdf_params(),
+        Duration::from_secs(MINING_TIMEOUT_S),
+    )
+    .ok_or_else(|| {
+        format!(
+            "block at height {} is not mined in {}s",
+            height, MINING_TIMEOUT_S
+        )
+    })?;
+    Ok(IndexedBlock::from(Block {
+        block_header: BlockHeader {
+            version: template.version,
+            previous_header_hash: template.previous_header_hash,
+            time: template.time,
+            bits: template.bits,
+            pubkey: pubkey.clone(),
+            iterations: solution.iterations,
+            randomness: solution.randomness,
+        },
+        proof: solution.proof,
+    }))
+}
+
+fn verification_params() -> VerificationParameters {
+    VerificationParameters {
+        verification_level: VerificationLevel::Full,
+        verification_edge: 0u8.into(),
+        verification_rules: Vec::new(),
+    }
+}
+
+fn unix_time() -> u32 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("system time is after unix epoch; qed")
+        .as_secs() as u32
+}
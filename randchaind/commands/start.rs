@@ -1,10 +1,18 @@
 use super::super::rpc;
+use super::reindex;
+use block_publisher::BlockPublisher;
 use chain::{BlockHeader, IndexedBlock};
+use disk_watcher::start_disk_watcher;
 use ecvrf;
+use ethcore_rpc::{Authenticator, Server as RpcServer};
 use hex;
+use logs;
 use miner;
 use primitives::hash::H256;
-use std::net::SocketAddr;
+use prune::start_pruning;
+use reload::{start_reload_watcher, ReloadableChain, Reloader};
+use std::cmp::max;
+use std::mem;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::mpsc::{channel, Receiver, Sender};
 use std::sync::Arc;
@@ -12,7 +20,7 @@ use std::thread;
 use sync::{
     create_local_sync_node, create_sync_connection_factory, create_sync_peers, SyncListener,
 };
-use util::{init_db, node_table_path};
+use util::{db_path, init_db, node_table_path, reopen_db_for_reindex};
 use {config, p2p, PROTOCOL_MINIMUM, PROTOCOL_VERSION};
 
 enum BlockNotifierTask {
@@ -89,102 +97,191 @@ impl Drop for BlockNotifier {
     }
 }
 
-pub fn start(cfg: config::Config) -> Result<(), String> {
+/// Services of the running chain, which are stopped when dropped.
+struct ChainNode {
+    _p2p: p2p::P2P,
+    _rpc_server: Option<RpcServer>,
+}
+
+pub fn start(mut cfg: config::Config, log_filters: Option<logs::LogFilters>) -> Result<(), String> {
     let mut el = p2p::event_loop();
+    let reloader = Arc::new(Reloader::new(cfg.network, cfg.reloadable(), log_filters));
+
+    // all chains share the same event loop, everything else is isolated
+    let extra_chains = mem::replace(&mut cfg.extra_chains, Vec::new());
+    let mut nodes = vec![start_chain(cfg, &el, &reloader)?];
+    for chain_cfg in extra_chains {
+        info!(target: "randchaind", "Starting {:?} chain", chain_cfg.network);
+        nodes.push(start_chain(chain_cfg, &el, &reloader)?);
+    }
+    start_reload_watcher(reloader);
+
+    // Keep the main process running forever
+    el.run(p2p::forever()).unwrap();
+    drop(nodes);
+    Ok(())
+}
 
+fn start_chain(
+    mut cfg: config::Config,
+    el: &p2p::EventLoop,
+    reloader: &Arc<Reloader>,
+) -> Result<ChainNode, String> {
+    // the database is moved aside only when the whole configuration is valid
+    let reindex_from = match cfg.reindex {
+        true => Some(reopen_db_for_reindex(&mut cfg)?),
+        false => None,
+    };
     init_db(&cfg)?;
 
+    if let Some(ref reindex_from) = reindex_from {
+        reindex(&cfg, reindex_from)?;
+    }
+
+    if let Some(max_db_size) = cfg.max_db_size {
+        start_pruning(cfg.db.clone(), db_path(&cfg.data_dir), max_db_size);
+    }
+
     let nodes_path = node_table_path(&cfg);
 
     let p2p_cfg = p2p::Config {
         threads: cfg.p2p_threads,
         inbound_connections: cfg.inbound_connections,
         outbound_connections: cfg.outbound_connections,
+        block_relay_connections: cfg.block_relay_connections,
         connection: p2p::NetConfig {
             protocol_version: PROTOCOL_VERSION,
             protocol_minimum: PROTOCOL_MINIMUM,
+            min_peer_version: cfg.min_peer_version,
+            peer_policy: cfg.peer_policy,
             magic: cfg.network.magic(),
-            local_address: SocketAddr::new(cfg.host, cfg.port),
+            local_address: cfg.announce.unwrap_or(cfg.listen[0]),
             services: cfg.services,
             user_agent: cfg.user_agent,
             start_height: 0,
-            relay: true,
+            relay: !cfg.watch_only,
             network: cfg.network,
+            proxy: cfg.proxy,
         },
+        listen: cfg.listen,
+        port_mapping: cfg.port_mapping,
         peers: cfg.peers,
         seeds: cfg.seednodes,
         node_table_path: nodes_path,
         preferable_services: cfg.services,
         internet_protocol: cfg.internet_protocol,
+        experiments: cfg.experiments,
+        whitelist: cfg.whitelist,
+        bandwidth_limits: cfg.bandwidth_limits,
     };
 
-    let sync_peers = create_sync_peers();
+    let sync_peers = create_sync_peers(cfg.misbehavior_score_ttl);
     let local_sync_node = create_local_sync_node(
         cfg.network,
         cfg.db.clone(),
         sync_peers.clone(),
         cfg.verification_params,
+        cfg.sync_server_threads,
+        cfg.upload_limit,
+        cfg.server_log_sampling,
+        cfg.block_stall_timeout,
+        cfg.download_window,
+        cfg.max_verification_queue,
+        cfg.memory_budget,
+        cfg.max_reorg_depth,
+        cfg.header_branches,
+        cfg.relay,
+        cfg.watch_only,
     );
     let sync_connection_factory =
         create_sync_connection_factory(sync_peers.clone(), local_sync_node.clone());
 
+    if let Some(min_free_disk_space) = cfg.min_free_disk_space {
+        start_disk_watcher(
+            local_sync_node.sync_state(),
+            db_path(&cfg.data_dir),
+            min_free_disk_space,
+        );
+    }
+
     if let Some(block_notify_command) = cfg.block_notify_command {
         local_sync_node.install_sync_listener(Box::new(BlockNotifier::new(block_notify_command)));
     }
 
+    if !cfg.block_publisher.is_empty() {
+        let publisher = BlockPublisher::new(cfg.db.clone(), cfg.block_publisher)?;
+        local_sync_node.install_sync_listener(Box::new(publisher));
+    }
+
     // start P2P server
     let p2p =
         p2p::P2P::new(p2p_cfg, sync_connection_factory, el.handle()).map_err(|x| x.to_string())?;
     p2p.run().map_err(|_| "Failed to start p2p module")?;
 
+    // Miner
+    // target is calculated for the configured number of miners, even if mining is started at runtime
+    let network_target: u32 = (max(cfg.num_miners, 1) * cfg.num_nodes * cfg.blocktime).into();
+    let (_, pk) = ecvrf::keygen();
+    let mining_pubkey = pk.clone();
+    let miner_sync_node = local_sync_node.clone();
+    let vdf_params = cfg.network.vdf_params();
+    // observer never produces blocks
+    let num_miners = if cfg.watch_only { 0 } else { cfg.num_miners };
+    let mining = miner::MiningControl::start(num_miners as usize, move |counters| {
+        let blktpl = miner_sync_node.get_block_template();
+        counters.note_template(blktpl.height);
+        let solution = miner::mock::try_solve_one_shot(&blktpl, &pk, vdf_params, 0, network_target);
+        counters.note_iterations(u64::from(miner::mock::STEP));
+        if let Some(solution) = solution {
+            let blk = chain::Block {
+                block_header: BlockHeader {
+                    version: blktpl.version,
+                    previous_header_hash: blktpl.previous_header_hash,
+                    time: blktpl.time,
+                    bits: blktpl.bits,
+                    pubkey: pk.clone(),
+                    iterations: solution.iterations,
+                    randomness: solution.randomness,
+                },
+                proof: solution.proof,
+            };
+            trace!(
+                "Block {} mined by {}!",
+                blk.hash().to_reversed_str(),
+                hex::encode(pk.to_bytes())
+            );
+            counters.note_block_found();
+            // Let's use PeerIndex=0 to identify the node itself
+            miner_sync_node.on_block(0, IndexedBlock::from(blk));
+        }
+    });
+
     // start RPC server
     let rpc_deps = rpc::Dependencies {
         network: cfg.network,
         storage: cfg.db.clone(),
-        local_sync_node: local_sync_node.clone(),
-        p2p_context: p2p.context().clone(),
+        local_sync_node: Some(local_sync_node.clone()),
+        p2p_context: Some(p2p.context().clone()),
+        mining: if cfg.watch_only { None } else { Some(mining) },
+        mining_pubkey: if cfg.watch_only {
+            None
+        } else {
+            Some(mining_pubkey)
+        },
         remote: el.remote(),
+        config_reloader: Some(reloader.clone()),
     };
-    let _rpc_server = rpc::new_http(cfg.rpc_config, rpc_deps)?;
+    let rpc_authenticator = Authenticator::default();
+    let rpc_server = rpc::new_http(cfg.rpc_config, rpc_deps, &rpc_authenticator)?;
 
-    // Miner
-    let network_target: u32 = (cfg.num_miners * cfg.num_nodes * cfg.blocktime).into();
-    for _ in 0..cfg.num_miners {
-        let (_, pk) = ecvrf::keygen();
-        let local_sync_node = local_sync_node.clone();
-        thread::spawn(move || {
-            let mut iters = 0;
-            loop {
-                let blktpl = local_sync_node.get_block_template();
-                if let Some(solution) =
-                    miner::mock::try_solve_one_shot(&blktpl, &pk, iters, network_target)
-                {
-                    let blk = chain::Block {
-                        block_header: BlockHeader {
-                            version: blktpl.version,
-                            previous_header_hash: blktpl.previous_header_hash,
-                            time: blktpl.time,
-                            bits: blktpl.bits,
-                            pubkey: pk.clone(),
-                            iterations: solution.iterations,
-                            randomness: solution.randomness,
-                        },
-                        proof: solution.proof,
-                    };
-                    trace!(
-                        "Block {} mined by {}!",
-                        blk.hash().to_reversed_str(),
-                        hex::encode(pk.to_bytes())
-                    );
-                    // Let's use PeerIndex=0 to identify the node itself
-                    local_sync_node.on_block(0, IndexedBlock::from(blk));
-                    iters = 0;
-                }
-            }
-        });
-    }
+    reloader.register(ReloadableChain {
+        local_sync_node: local_sync_node,
+        p2p_context: p2p.context().clone(),
+        rpc_authenticator: rpc_authenticator,
+    });
 
-    // Keep the main process running forever
-    el.run(p2p::forever()).unwrap();
-    Ok(())
+    Ok(ChainNode {
+        _p2p: p2p,
+        _rpc_server: rpc_server,
+    })
 }
@@ -0,0 +1,68 @@
+use clap::ArgMatches;
+use config;
+use db::{deserialize_verifier_state, serialize_verifier_state, verifier_state_mmr};
+use std::fs;
+use util::init_db;
+use verification::constants::RETARGETING_INTERVAL;
+
+/// Min number of best headers in the exported state. It is enough to compute work required
+/// and median time past of the next blocks. Randomness of the tail blocks is kept in their headers.
+const MIN_TAIL_LEN: u32 = RETARGETING_INTERVAL;
+/// Hashes of every `CHECKPOINT_INTERVAL`-th block below the tail are exported.
+const CHECKPOINT_INTERVAL: u32 = RETARGETING_INTERVAL;
+
+/// Writes state of the canon chain, which is enough to verify its next blocks, to the file.
+pub fn export_state(cfg: config::Config, matches: &ArgMatches) -> Result<(), String> {
+    init_db(&cfg)?;
+
+    let path = matches
+        .value_of("PATH")
+        .expect("PATH is required in cli.yml; qed");
+    let tail_len = match matches.value_of("tail") {
+        Some(s) => s
+            .parse::<u32>()
+            .map_err(|_| "Invalid tail length".to_owned())?,
+        None => MIN_TAIL_LEN,
+    };
+    if tail_len < MIN_TAIL_LEN {
+        return Err(format!("Tail length can't be less than {}", MIN_TAIL_LEN));
+    }
+
+    let state = cfg.db.verifier_state(tail_len, CHECKPOINT_INTERVAL);
+    fs::write(path, &*serialize_verifier_state(&state))
+        .map_err(|err| format!("Failed to write verifier state to {}: {}", path, err))?;
+
+    info!(
+        "Verifier state exported: {} checkpoints, {} headers, best block height: {}, MMR root: {:?}",
+        state.checkpoints.len(),
+        state.tail.len(),
+        cfg.db.best_block().number,
+        verifier_state_mmr(&state).and_then(|mmr| mmr.root())
+    );
+    Ok(())
+}
+
+/// Initializes the empty database with the exported verifier state.
+///
+/// Only headers of the best blocks are imported, so the node starts verifying and
+/// relaying new blocks right away, but can't serve old blocks to peers.
+pub fn import_state(cfg: config::Config, matches: &ArgMatches) -> Result<(), String> {
+    init_db(&cfg)?;
+
+    let path = matches
+        .value_of("PATH")
+        .expect("PATH is required in cli.yml; qed");
+    let bytes = fs::read(path)
+        .map_err(|err| format!("Failed to read verifier state from {}: {}", path, err))?;
+    let state = deserialize_verifier_state(&bytes)
+        .map_err(|err| format!("Invalid verifier state in {}: {}", path, err))?;
+    let mmr_root = verifier_state_mmr(&state).and_then(|mmr| mmr.root());
+    cfg.db.import_verifier_state(state)?;
+
+    info!(
+        "Verifier state imported, best block height: {}, MMR root: {:?}",
+        cfg.db.best_block().number,
+        mmr_root
+    );
+    Ok(())
+}
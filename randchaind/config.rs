@@ -1,18 +1,34 @@
+use block_publisher::Topic;
 use clap;
+use db::DatabaseBackend;
+use ethcore_rpc::RpcUser;
+use genesis::load_private_network;
 use message::Services;
 use network::Network;
-use p2p::InternetProtocol;
+use network_spec::load_network_spec;
+use p2p::{
+    parse_onion_address, BandwidthLimit, ExperimentsConfig, InternetProtocol, IpRange, PeerFilter,
+    PeerPolicy,
+};
 use primitives::hash::H256;
 use rpc::HttpConfiguration as RpcHttpConfig;
 use rpc_apis::ApiSet;
 use seednodes::{mainnet_seednodes, testnet_seednodes};
 use std::fs;
 use std::net;
+use std::time::Duration;
 use storage;
-use sync::VerificationParameters;
-use util::open_db;
+use sync::{
+    HeaderBranchesLimits, RelayConfig, UploadLimit, VerificationParameters, VerificationRule,
+    DEFAULT_ANNOUNCEMENT_DELAY_MS, DEFAULT_BLOCK_STALL_TIMEOUT_S, DEFAULT_MAX_HEADER_BRANCHES,
+    DEFAULT_MAX_HEADER_BRANCH_LEN, DEFAULT_MAX_HEADER_BRANCH_WORK_DEFICIT,
+    DEFAULT_MAX_VERIFICATION_QUEUE, DEFAULT_MEMORY_BUDGET, DEFAULT_MISBEHAVIOR_SCORE_TTL_S,
+    DEFAULT_SERVER_LOG_SAMPLING, DEFAULT_TRICKLE_INTERVAL_MS, DEFAULT_UPLOAD_WINDOW_S,
+};
+use tuning::{load_or_generate, Tuning};
+use util::{check_genesis, open_db, reinit_db, rpc_cookie_dir};
 use verification::VerificationLevel;
-use {REGTEST_USER_AGENT, USER_AGENT};
+use {LOG_INFO, PROTOCOL_MINIMUM, PROTOCOL_VERSION, REGTEST_USER_AGENT, USER_AGENT};
 
 pub struct Config {
     pub network: Network,
@@ -20,32 +36,146 @@ pub struct Config {
     pub port: u16,
     pub peers: Vec<net::SocketAddr>,
     pub host: net::IpAddr,
+    /// Addresses to listen for incoming connections on.
+    pub listen: Vec<net::SocketAddr>,
+    /// Address, advertised to peers. First listen address, if not set.
+    pub announce: Option<net::SocketAddr>,
+    /// Map listening ports on the gateway with NAT-PMP.
+    pub port_mapping: bool,
+    /// SOCKS5 proxy, outbound connections are made through.
+    pub proxy: Option<net::SocketAddr>,
     pub seednodes: Vec<String>, // we use String rather than SocketAddr as DNS resolver takes String
     pub quiet: bool,
+    /// Log filters in RUST_LOG format.
+    pub log_filters: String,
     pub inbound_connections: u32,
     pub outbound_connections: u32,
+    pub block_relay_connections: u32,
     pub p2p_threads: usize,
+    pub sync_server_threads: usize,
+    /// Max number of bytes, served to single peer in response to 'getdata'. None if unlimited.
+    pub upload_limit: Option<UploadLimit>,
+    /// Bandwidth limits of messages, sent to peers.
+    pub bandwidth_limits: Vec<BandwidthLimit>,
+    /// 1 of this number of served peers requests is fully logged. 0 if serving is not logged.
+    pub server_log_sampling: u32,
+    /// Peer, which has not delivered requested block in this number of seconds, is stalling.
+    pub block_stall_timeout: u32,
+    /// Misbehavior of peer address is remembered for this number of seconds.
+    pub misbehavior_score_ttl: u32,
+    /// Approximate maximal number of blocks, requested from peers at once.
+    pub download_window: u32,
+    /// Number of verifying blocks, after which new blocks are not requested.
+    pub max_verification_queue: u32,
+    /// Bytes, which blocks and requests, held in memory by synchronization, may occupy.
+    pub memory_budget: usize,
+    /// Blocks, buried deeper than this number of blocks, are final. None if unlimited.
+    pub max_reorg_depth: Option<u32>,
+    /// Limits of headers-only side branches, accepted from peers.
+    pub header_branches: HeaderBranchesLimits,
+    /// New blocks relay topology.
+    pub relay: RelayConfig,
+    /// Only headers are synchronized and blocks announcements are recorded. Nothing is served or relayed.
+    pub watch_only: bool,
     pub db_cache: usize,
+    pub db_backend: DatabaseBackend,
+    /// If true, bodies of new blocks are compressed in the database.
+    pub db_block_compression: bool,
+    /// Chain events journal keeps events for this number of seconds. None if unlimited.
+    pub chain_events_retention: Option<u32>,
+    /// If true, canon blocks are indexed by miner public key.
+    pub miner_index: bool,
+    /// Blocks are backed up to the rollback directory before pruning, reindex and reorganizations,
+    /// disconnecting at least this number of blocks. None if backups are disabled.
+    pub backup_reorg_depth: Option<u32>,
+    /// Soft limit of the database size, in bytes.
+    pub max_db_size: Option<u64>,
+    /// Blocks processing is paused when free space on the database volume is below this number of bytes.
+    pub min_free_disk_space: Option<u64>,
+    /// If true, the database is moved aside on start and its blocks are reindexed into the new database.
+    pub reindex: bool,
     pub data_dir: Option<String>,
     pub user_agent: String,
+    /// Peers with lower protocol version are disconnected after handshake.
+    pub min_peer_version: u32,
+    /// Peers, rejected or deprioritized after handshake because of their version or user agent.
+    pub peer_policy: PeerPolicy,
     pub internet_protocol: InternetProtocol,
+    pub experiments: ExperimentsConfig,
+    /// Peers from these ranges are exempt from bans, eviction and upload limits.
+    pub whitelist: Vec<IpRange>,
     pub rpc_config: RpcHttpConfig,
     pub block_notify_command: Option<String>,
+    /// Addresses to publish best block changes on.
+    pub block_publisher: Vec<(Topic, net::SocketAddr)>,
     pub verification_params: VerificationParameters,
     pub db: storage::SharedStore,
     pub num_nodes: u16,
     pub num_miners: u16,
     pub blocktime: u16,
+    /// Isolated chains, which are run in the same process with their own datadirs, ports and RPC servers.
+    pub extra_chains: Vec<Config>,
+}
+
+/// Settings, which are re-read from the configuration and applied to the running node on reload.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReloadableConfig {
+    pub log_filters: String,
+    pub inbound_connections: u32,
+    pub outbound_connections: u32,
+    pub upload_limit: Option<UploadLimit>,
+    pub bandwidth_limits: Vec<BandwidthLimit>,
+    pub server_log_sampling: u32,
+    /// Users of the JSONRPC server. Cookie identity is not included.
+    pub rpc_users: Vec<RpcUser>,
+}
+
+impl Config {
+    /// Returns current values of settings, which could be reloaded.
+    pub fn reloadable(&self) -> ReloadableConfig {
+        ReloadableConfig {
+            log_filters: self.log_filters.clone(),
+            inbound_connections: self.inbound_connections,
+            outbound_connections: self.outbound_connections,
+            upload_limit: self.upload_limit,
+            bandwidth_limits: self.bandwidth_limits.clone(),
+            server_log_sampling: self.server_log_sampling,
+            rpc_users: self.rpc_config.users.clone(),
+        }
+    }
 }
 
 pub const DEFAULT_DB_CACHE: usize = 512;
+pub const DEFAULT_MIN_FREE_DISK_SPACE: u64 = 256;
+pub const DEFAULT_BACKUP_REORG_DEPTH: u32 = 6;
 
 pub fn parse(matches: &clap::ArgMatches) -> Result<Config, String> {
-    let db_cache = match matches.value_of("db-cache") {
+    let db_backend = match matches.value_of("db-backend") {
+        Some(s) => s.parse()?,
+        None => DatabaseBackend::default(),
+    };
+
+    let max_db_size = match matches.value_of("max-db-size") {
+        Some(s) => Some(
+            s.parse::<u64>()
+                .map_err(|_| "Invalid max-db-size - should be number in GB".to_owned())?
+                * 1024
+                * 1024
+                * 1024,
+        ),
+        None => None,
+    };
+
+    let min_free_disk_space = match matches.value_of("min-free-disk-space") {
         Some(s) => s
-            .parse()
-            .map_err(|_| "Invalid cache size - should be number in MB".to_owned())?,
-        None => DEFAULT_DB_CACHE,
+            .parse::<u64>()
+            .map_err(|_| "Invalid min-free-disk-space - should be number in MB".to_owned())?,
+        None => DEFAULT_MIN_FREE_DISK_SPACE,
+    };
+    // zero disables the check
+    let min_free_disk_space = match min_free_disk_space {
+        0 => None,
+        min_free_disk_space => Some(min_free_disk_space * 1024 * 1024),
     };
 
     let data_dir = match matches.value_of("data-dir") {
@@ -53,31 +183,261 @@ pub fn parse(matches: &clap::ArgMatches) -> Result<Config, String> {
         None => None,
     };
 
-    let db = open_db(&data_dir, db_cache);
-
     let quiet = matches.is_present("quiet");
-    let network = match (matches.is_present("testnet"), matches.is_present("regtest")) {
-        (true, false) => Network::Testnet,
-        (false, true) => Network::Regtest,
-        (false, false) => Network::Mainnet,
-        (true, true) => return Err("Only one testnet option can be used".into()),
+    let log_filters = parse_log_filters(matches);
+    let network = match (
+        matches.is_present("testnet"),
+        matches.is_present("regtest"),
+        matches.value_of("network-spec"),
+        matches.value_of("genesis"),
+    ) {
+        (true, false, None, None) => Network::Testnet,
+        (false, true, None, None) => Network::Regtest,
+        (false, false, Some(path), None) => load_network_spec(path)?,
+        (false, false, None, Some(genesis)) => load_private_network(genesis)?,
+        (false, false, None, None) => Network::Mainnet,
+        _ => {
+            return Err(
+                "Only one of testnet, regtest, network-spec and genesis options can be used".into(),
+            )
+        }
     };
+    network
+        .validate()
+        .map_err(|err| format!("Invalid {:?} network parameters: {}", network, err))?;
 
-    let (in_connections, out_connections) = match network {
-        Network::Testnet | Network::Mainnet | Network::Other(_) => (125, 8),
-        Network::Regtest | Network::Unitest => (1, 0),
+    let tuning = default_tuning(network, &data_dir)?;
+
+    let db_cache = match matches.value_of("db-cache") {
+        Some(s) => s
+            .parse()
+            .map_err(|_| "Invalid cache size - should be number in MB".to_owned())?,
+        None => tuning.db_cache,
     };
 
-    let p2p_threads = match network {
-        Network::Testnet | Network::Mainnet | Network::Other(_) => 4,
-        Network::Regtest | Network::Unitest => 1,
+    if !db_backend.is_persistent() {
+        if max_db_size.is_some() {
+            return Err("--max-db-size can not be used with in-memory database".into());
+        }
+        if matches.is_present("reindex") {
+            return Err("--reindex can not be used with in-memory database".into());
+        }
+    }
+
+    let reindex = match (matches.is_present("reindex"), matches.subcommand_name()) {
+        (false, _) => false,
+        (true, None) => true,
+        (true, Some(subcommand)) => {
+            return Err(format!("--reindex can not be used with {}", subcommand))
+        }
     };
 
-    let user_agent = match network {
-        Network::Testnet | Network::Mainnet | Network::Unitest | Network::Other(_) => {
-            USER_AGENT.into()
+    // fail fast instead of syncing into the datadir of another network
+    let db_block_compression = matches.is_present("db-block-compression");
+    let chain_events_retention = match matches.value_of("chain-events-retention") {
+        Some(s) => match s.parse::<u32>() {
+            Ok(hours) if hours > 0 => Some(hours.saturating_mul(60 * 60)),
+            _ => return Err("Invalid chain-events-retention - should be positive number".into()),
+        },
+        None => None,
+    };
+    let backup_reorg_depth = match (
+        matches.is_present("block-backup"),
+        matches.value_of("backup-reorg-depth"),
+    ) {
+        (false, None) => None,
+        (false, Some(_)) => return Err("--backup-reorg-depth requires --block-backup".into()),
+        (true, None) => Some(DEFAULT_BACKUP_REORG_DEPTH),
+        (true, Some(s)) => match s.parse::<u32>() {
+            Ok(depth) if depth > 0 => Some(depth),
+            _ => return Err("Invalid backup-reorg-depth - should be positive number".into()),
+        },
+    };
+    let mut db = open_db(
+        &data_dir,
+        db_backend,
+        db_cache,
+        db_block_compression,
+        chain_events_retention,
+        backup_reorg_depth,
+    )?;
+    if let Err(err) = check_genesis(&db, network) {
+        if !matches.is_present("force-reinit") {
+            return Err(format!(
+                "{}. Use another --data-dir or run with --force-reinit to wipe the database",
+                err
+            ));
         }
-        Network::Regtest => REGTEST_USER_AGENT.into(),
+
+        warn!("{}. Reinitializing the database", err);
+        drop(db);
+        db = reinit_db(
+            &data_dir,
+            db_backend,
+            db_cache,
+            db_block_compression,
+            chain_events_retention,
+            backup_reorg_depth,
+        )?;
+    }
+
+    let (in_connections, out_connections) = parse_connections(network, matches)?;
+    let (_, _, block_relay_connections) = default_connections(network);
+    let p2p_threads = default_p2p_threads(network);
+
+    let sync_server_threads = match matches.value_of("sync-server-threads") {
+        Some(s) => match s.parse() {
+            Ok(threads) if threads > 0 => threads,
+            _ => return Err("Invalid sync-server-threads - should be positive number".into()),
+        },
+        None => tuning.sync_server_threads,
+    };
+
+    let download_window = match matches.value_of("download-window") {
+        Some(s) => match s.parse() {
+            Ok(window) if window > 0 => window,
+            _ => return Err("Invalid download-window - should be positive number".into()),
+        },
+        None => tuning.download_window,
+    };
+
+    let max_verification_queue = match matches.value_of("max-verification-queue") {
+        Some(s) => match s.parse() {
+            Ok(queue) if queue > 0 => queue,
+            _ => return Err("Invalid max-verification-queue - should be positive number".into()),
+        },
+        None => DEFAULT_MAX_VERIFICATION_QUEUE,
+    };
+
+    let memory_budget = match matches.value_of("memory-budget") {
+        Some(s) => match s.parse::<usize>() {
+            Ok(budget) if budget > 0 => budget * 1024 * 1024,
+            _ => return Err("Invalid memory-budget - should be positive number in MB".into()),
+        },
+        None => DEFAULT_MEMORY_BUDGET,
+    };
+
+    let upload_limit = parse_upload_limit(matches)?;
+    let bandwidth_limits = parse_bandwidth_limits(matches)?;
+    let server_log_sampling = parse_server_log_sampling(matches)?;
+
+    let block_stall_timeout = match matches.value_of("block-stall-timeout") {
+        Some(s) => match s.parse() {
+            Ok(timeout) if timeout > 0 => timeout,
+            _ => return Err("Invalid block-stall-timeout - should be positive number".into()),
+        },
+        None => DEFAULT_BLOCK_STALL_TIMEOUT_S,
+    };
+
+    let misbehavior_score_ttl = match matches.value_of("misbehavior-ttl") {
+        Some(s) => match s.parse() {
+            Ok(ttl) if ttl > 0 => ttl,
+            _ => return Err("Invalid misbehavior-ttl - should be positive number".into()),
+        },
+        None => DEFAULT_MISBEHAVIOR_SCORE_TTL_S,
+    };
+
+    let max_reorg_depth = match matches.value_of("max-reorg-depth") {
+        Some(s) => match s.parse() {
+            Ok(depth) if depth > 0 => Some(depth),
+            _ => return Err("Invalid max-reorg-depth - should be positive number".into()),
+        },
+        None => None,
+    };
+
+    let header_branches = HeaderBranchesLimits {
+        max_branches: match matches.value_of("max-header-branches") {
+            Some(s) => s
+                .parse()
+                .map_err(|_| "Invalid max-header-branches - should be number".to_owned())?,
+            None => DEFAULT_MAX_HEADER_BRANCHES,
+        },
+        max_branch_len: match matches.value_of("max-header-branch-length") {
+            Some(s) => s
+                .parse()
+                .map_err(|_| "Invalid max-header-branch-length - should be number".to_owned())?,
+            None => DEFAULT_MAX_HEADER_BRANCH_LEN,
+        },
+        max_work_deficit: match matches.value_of("max-header-branch-work-deficit") {
+            Some(s) => s.parse().map_err(|_| {
+                "Invalid max-header-branch-work-deficit - should be number of blocks".to_owned()
+            })?,
+            None => DEFAULT_MAX_HEADER_BRANCH_WORK_DEFICIT,
+        },
+    };
+
+    let immediate_announcements = match matches.value_of("relay-fanout") {
+        Some(s) => Some(
+            s.parse()
+                .map_err(|_| "Invalid relay-fanout - should be number".to_owned())?,
+        ),
+        None => None,
+    };
+    let announcement_delay_ms = match matches.value_of("relay-delay") {
+        Some(s) => s
+            .parse()
+            .map_err(|_| "Invalid relay-delay - should be number".to_owned())?,
+        None => DEFAULT_ANNOUNCEMENT_DELAY_MS,
+    };
+    let trickle_interval_ms = match matches.value_of("relay-trickle") {
+        Some(s) => s
+            .parse()
+            .map_err(|_| "Invalid relay-trickle - should be number".to_owned())?,
+        None => DEFAULT_TRICKLE_INTERVAL_MS,
+    };
+    let relay = RelayConfig {
+        immediate_announcements: immediate_announcements,
+        announcement_delay_ms: announcement_delay_ms,
+        trickle_interval_ms: trickle_interval_ms,
+        prefer_outbound: matches.is_present("relay-prefer-outbound"),
+    };
+    let watch_only = matches.is_present("watch-only");
+
+    let headers_announcement_percent = match matches.value_of("experiment-headers-announcement") {
+        Some(s) => s
+            .parse::<u8>()
+            .ok()
+            .filter(|percent| *percent <= 100)
+            .ok_or_else(|| {
+                "Invalid experiment-headers-announcement - should be percentage".to_owned()
+            })?,
+        None => 0,
+    };
+    let experiments = ExperimentsConfig {
+        headers_announcement_percent: headers_announcement_percent,
+    };
+
+    let whitelist = match matches.values_of("whitelist") {
+        Some(ranges) => ranges
+            .map(|range| range.parse())
+            .collect::<Result<Vec<IpRange>, _>>()?,
+        None => Vec::new(),
+    };
+
+    let peer_filters = |name| match matches.values_of(name) {
+        Some(filters) => filters
+            .map(|filter| filter.parse())
+            .collect::<Result<Vec<PeerFilter>, _>>(),
+        None => Ok(Vec::new()),
+    };
+    let peer_policy = PeerPolicy {
+        reject: peer_filters("reject-peer")?,
+        deprioritize: peer_filters("deprioritize-peer")?,
+    };
+
+    let user_agent = default_user_agent(network);
+
+    let min_peer_version = match matches.value_of("min-peer-version") {
+        Some(s) => match s.parse() {
+            Ok(version) if version >= PROTOCOL_MINIMUM && version <= PROTOCOL_VERSION => version,
+            _ => {
+                return Err(format!(
+                    "Invalid min-peer-version - should be number in {}..{} range",
+                    PROTOCOL_MINIMUM, PROTOCOL_VERSION
+                ))
+            }
+        },
+        None => default_min_peer_version(network),
     };
 
     let port = match matches.value_of("port") {
@@ -92,17 +452,7 @@ pub fn parse(matches: &clap::ArgMatches) -> Result<Config, String> {
         Some(addrs_cfg) => {
             let mut addrs: Vec<net::SocketAddr> = vec![];
             for addr_str in addrs_cfg.split(",") {
-                match addr_str.parse::<net::SocketAddr>() {
-                    Err(_) => {
-                        // no port given, enforce the default port
-                        let addr = addr_str
-                            .parse::<net::IpAddr>()
-                            .map(|ip| net::SocketAddr::new(ip, network.port()))
-                            .unwrap();
-                        addrs.push(addr);
-                    }
-                    Ok(a) => addrs.push(a), // with port given
-                }
+                addrs.push(parse_peer(addr_str, network.port())?);
             }
             addrs
         }
@@ -113,17 +463,7 @@ pub fn parse(matches: &clap::ArgMatches) -> Result<Config, String> {
         let addrs_cfg: String =
             fs::read_to_string(peers_file_path).expect("Something went wrong reading peers-file");
         for addr_str in addrs_cfg.split('\n') {
-            match addr_str.parse::<net::SocketAddr>() {
-                Err(_) => {
-                    // no port given, enforce the default port
-                    let addr = addr_str
-                        .parse::<net::IpAddr>()
-                        .map(|ip| net::SocketAddr::new(ip, network.port()))
-                        .unwrap();
-                    peers.push(addr);
-                }
-                Ok(a) => peers.push(a), // with port given
-            }
+            peers.push(parse_peer(addr_str, network.port())?);
         }
     }
 
@@ -138,11 +478,7 @@ pub fn parse(matches: &clap::ArgMatches) -> Result<Config, String> {
             }
             addrs
         }
-        None => match network {
-            Network::Mainnet => mainnet_seednodes().into_iter().map(Into::into).collect(),
-            Network::Testnet => testnet_seednodes().into_iter().map(Into::into).collect(),
-            Network::Other(_) | Network::Regtest | Network::Unitest => Vec::new(),
-        },
+        None => default_seednodes(network),
     };
 
     let only_net = match matches.value_of("only-net") {
@@ -160,7 +496,27 @@ pub fn parse(matches: &clap::ArgMatches) -> Result<Config, String> {
         },
     };
 
-    let rpc_config = parse_rpc_config(network, matches)?;
+    // --listen replaces --host/--port listening address
+    let listen = match matches.values_of("listen") {
+        Some(values) => values
+            .map(|s| parse_address(s, port).map_err(|_| format!("Invalid listen address {}", s)))
+            .collect::<Result<Vec<_>, _>>()?,
+        None => vec![net::SocketAddr::new(host, port)],
+    };
+    let announce = match matches.value_of("announce") {
+        Some(s) => Some(parse_address(s, port).map_err(|_| "Invalid announce address".to_owned())?),
+        None => None,
+    };
+
+    let proxy = match matches.value_of("proxy") {
+        Some(s) => Some(
+            s.parse::<net::SocketAddr>()
+                .map_err(|_| "Invalid proxy".to_owned())?,
+        ),
+        None => None,
+    };
+
+    let rpc_config = parse_rpc_config(network, &data_dir, matches)?;
 
     let block_notify_command = match matches.value_of("blocknotify") {
         Some(s) => Some(
@@ -170,16 +526,37 @@ pub fn parse(matches: &clap::ArgMatches) -> Result<Config, String> {
         None => None,
     };
 
-    let services = Services::default().with_network(true);
+    let mut block_publisher = Vec::new();
+    if let Some(s) = matches.value_of("publish-hashblock") {
+        let address = s
+            .parse()
+            .map_err(|_| "Invalid publish-hashblock address".to_owned())?;
+        block_publisher.push((Topic::HashBlock, address));
+    }
+    if let Some(s) = matches.value_of("publish-rawblock") {
+        let address = s
+            .parse()
+            .map_err(|_| "Invalid publish-rawblock address".to_owned())?;
+        block_publisher.push((Topic::RawBlock, address));
+    }
+
+    let services = Services::default()
+        .with_network(true)
+        .with_snappy_compression(matches.is_present("p2p-compression"))
+        .with_compressed_headers(!matches.is_present("no-compressed-headers"));
 
     let verification_level = match matches.value_of("verification-level") {
-        Some(s) if s == "full" => VerificationLevel::Full,
-        Some(s) if s == "header" => VerificationLevel::Header,
-        Some(s) if s == "none" => VerificationLevel::NoVerification,
-        Some(s) => return Err(format!("Invalid verification level: {}", s)),
+        Some(s) => parse_verification_level(s)?,
         None => VerificationLevel::Full,
     };
 
+    let verification_rules = match matches.values_of("verification-rule") {
+        Some(rules) => rules
+            .map(parse_verification_rule)
+            .collect::<Result<Vec<_>, _>>()?,
+        None => Vec::new(),
+    };
+
     // TODO:
     let verification_edge = match matches.value_of("verification-edge") {
         Some(s) if verification_level != VerificationLevel::Full => {
@@ -203,40 +580,404 @@ pub fn parse(matches: &clap::ArgMatches) -> Result<Config, String> {
 
     let blocktime = match matches.value_of("blocktime") {
         Some(s) => s.parse().map_err(|_| "Invalid blocktime".to_owned())?,
-        None => 10, // 10s
+        None => network.block_interval(),
     };
 
-    let config = Config {
+    let mut config = Config {
         quiet: quiet,
+        log_filters: log_filters,
         network: network,
         services: services,
         port: port,
         peers: peers,
         host: host,
+        listen: listen,
+        announce: announce,
+        port_mapping: matches.is_present("port-mapping"),
+        proxy: proxy,
         seednodes: seednodes,
         inbound_connections: in_connections,
         outbound_connections: out_connections,
+        block_relay_connections: block_relay_connections,
         p2p_threads: p2p_threads,
+        sync_server_threads: sync_server_threads,
+        upload_limit: upload_limit,
+        bandwidth_limits: bandwidth_limits,
+        server_log_sampling: server_log_sampling,
+        block_stall_timeout: block_stall_timeout,
+        misbehavior_score_ttl: misbehavior_score_ttl,
+        download_window: download_window,
+        max_verification_queue: max_verification_queue,
+        memory_budget: memory_budget,
+        max_reorg_depth: max_reorg_depth,
+        header_branches: header_branches,
+        relay: relay,
+        watch_only: watch_only,
         db_cache: db_cache,
+        db_backend: db_backend,
+        db_block_compression: db_block_compression,
+        chain_events_retention: chain_events_retention,
+        miner_index: matches.is_present("miner-index"),
+        backup_reorg_depth: backup_reorg_depth,
+        max_db_size: max_db_size,
+        min_free_disk_space: min_free_disk_space,
+        reindex: reindex,
         data_dir: data_dir,
         user_agent: user_agent,
+        min_peer_version: min_peer_version,
+        peer_policy: peer_policy,
         internet_protocol: only_net,
+        experiments: experiments,
+        whitelist: whitelist,
         rpc_config: rpc_config,
         block_notify_command: block_notify_command,
+        block_publisher: block_publisher,
         verification_params: VerificationParameters {
             verification_level: verification_level,
             verification_edge: verification_edge,
+            verification_rules: verification_rules,
         },
         db: db,
         num_nodes: num_nodes,
         num_miners: num_miners,
         blocktime: blocktime,
+        extra_chains: Vec::new(),
     };
 
+    if let Some(chains) = matches.values_of("chain") {
+        if let Some(subcommand) = matches.subcommand_name() {
+            return Err(format!("--chain can not be used with {}", subcommand));
+        }
+
+        config.extra_chains = chains
+            .map(|chain| parse_extra_chain(&config, chain, matches))
+            .collect::<Result<Vec<_>, _>>()?;
+        check_chains_isolation(&config)?;
+    }
+
     Ok(config)
 }
 
-fn parse_rpc_config(network: Network, matches: &clap::ArgMatches) -> Result<RpcHttpConfig, String> {
+/// Parses settings of the running `network` node, which could be changed at runtime.
+/// Unlike `parse`, nothing is opened or created.
+pub fn parse_reloadable(
+    network: Network,
+    matches: &clap::ArgMatches,
+) -> Result<ReloadableConfig, String> {
+    let (inbound_connections, outbound_connections) = parse_connections(network, matches)?;
+    Ok(ReloadableConfig {
+        log_filters: parse_log_filters(matches),
+        inbound_connections: inbound_connections,
+        outbound_connections: outbound_connections,
+        upload_limit: parse_upload_limit(matches)?,
+        bandwidth_limits: parse_bandwidth_limits(matches)?,
+        server_log_sampling: parse_server_log_sampling(matches)?,
+        rpc_users: parse_rpc_users(matches)?,
+    })
+}
+
+/// Parses isolated chain in NETWORK:DATA_DIR[:PORT[:JSONRPC_PORT]] format.
+/// Options, which are not specific to the network, are shared with the main chain.
+/// Defaults, which depend on the network, are derived from the chain network. Verification
+/// options refer to blocks of the main chain, so the chain is fully verified up to its
+/// default verification edge.
+fn parse_extra_chain(main: &Config, s: &str, matches: &clap::ArgMatches) -> Result<Config, String> {
+    let invalid_chain = || format!("Invalid chain: {}", s);
+    let mut parts = s.split(':');
+    let network = match parts.next() {
+        Some("mainnet") => Network::Mainnet,
+        Some("testnet") => Network::Testnet,
+        Some("regtest") => Network::Regtest,
+        _ => return Err(invalid_chain()),
+    };
+    let data_dir = match parts.next() {
+        Some(data_dir) if !data_dir.is_empty() => Some(data_dir.to_owned()),
+        _ => return Err(invalid_chain()),
+    };
+    let port = match parts.next() {
+        Some(port) => port.parse().map_err(|_| invalid_chain())?,
+        None => network.port(),
+    };
+    let mut rpc_config = parse_rpc_config(network, &data_dir, matches)?;
+    rpc_config.port = match parts.next() {
+        Some(port) => port.parse().map_err(|_| invalid_chain())?,
+        None => network.rpc_port(),
+    };
+    if parts.next().is_some() {
+        return Err(invalid_chain());
+    }
+
+    // explicitly given options are shared, defaults are tuned for the chain
+    let tuning = default_tuning(network, &data_dir)?;
+    let db_cache = match matches.is_present("db-cache") {
+        true => main.db_cache,
+        false => tuning.db_cache,
+    };
+    let sync_server_threads = match matches.is_present("sync-server-threads") {
+        true => main.sync_server_threads,
+        false => tuning.sync_server_threads,
+    };
+    let download_window = match matches.is_present("download-window") {
+        true => main.download_window,
+        false => tuning.download_window,
+    };
+    let blocktime = match matches.is_present("blocktime") {
+        true => main.blocktime,
+        false => network.block_interval(),
+    };
+
+    let db = open_db(
+        &data_dir,
+        main.db_backend,
+        db_cache,
+        main.db_block_compression,
+        main.chain_events_retention,
+        main.backup_reorg_depth,
+    )?;
+    check_genesis(&db, network)
+        .map_err(|err| format!("{}. Use another data dir for {}", err, s))?;
+
+    let (in_connections, out_connections, block_relay_connections) = default_connections(network);
+    Ok(Config {
+        quiet: main.quiet,
+        log_filters: main.log_filters.clone(),
+        network: network,
+        services: main.services,
+        port: port,
+        peers: Vec::new(),
+        host: main.host,
+        listen: vec![net::SocketAddr::new(main.host, port)],
+        announce: None,
+        port_mapping: main.port_mapping,
+        proxy: main.proxy,
+        seednodes: default_seednodes(network),
+        inbound_connections: in_connections,
+        outbound_connections: out_connections,
+        block_relay_connections: block_relay_connections,
+        p2p_threads: default_p2p_threads(network),
+        sync_server_threads: sync_server_threads,
+        upload_limit: main.upload_limit,
+        bandwidth_limits: main.bandwidth_limits.clone(),
+        server_log_sampling: main.server_log_sampling,
+        block_stall_timeout: main.block_stall_timeout,
+        misbehavior_score_ttl: main.misbehavior_score_ttl,
+        download_window: download_window,
+        max_verification_queue: main.max_verification_queue,
+        memory_budget: main.memory_budget,
+        max_reorg_depth: main.max_reorg_depth,
+        header_branches: main.header_branches,
+        relay: main.relay.clone(),
+        watch_only: main.watch_only,
+        db_cache: db_cache,
+        db_backend: main.db_backend,
+        db_block_compression: main.db_block_compression,
+        chain_events_retention: main.chain_events_retention,
+        miner_index: main.miner_index,
+        backup_reorg_depth: main.backup_reorg_depth,
+        max_db_size: main.max_db_size,
+        min_free_disk_space: main.min_free_disk_space,
+        reindex: false,
+        data_dir: data_dir,
+        user_agent: default_user_agent(network),
+        min_peer_version: default_min_peer_version(network),
+        peer_policy: main.peer_policy.clone(),
+        internet_protocol: main.internet_protocol,
+        experiments: main.experiments.clone(),
+        whitelist: main.whitelist.clone(),
+        rpc_config: rpc_config,
+        block_notify_command: None,
+        block_publisher: Vec::new(),
+        verification_params: VerificationParameters {
+            verification_level: VerificationLevel::Full,
+            verification_edge: network.default_verification_edge(),
+            verification_rules: Vec::new(),
+        },
+        db: db,
+        num_nodes: main.num_nodes,
+        num_miners: main.num_miners,
+        blocktime: blocktime,
+        extra_chains: Vec::new(),
+    })
+}
+
+/// Checks that chains, running in the same process, do not share datadirs and ports.
+fn check_chains_isolation(main: &Config) -> Result<(), String> {
+    let chains: Vec<_> = Some(main)
+        .into_iter()
+        .chain(main.extra_chains.iter())
+        .collect();
+    for (index, chain) in chains.iter().enumerate() {
+        for other in &chains[..index] {
+            if chain.network == other.network {
+                return Err(format!(
+                    "{:?} chain is configured more than once",
+                    chain.network
+                ));
+            }
+            if chain.data_dir == other.data_dir {
+                return Err(format!(
+                    "{:?} and {:?} chains must use different data dirs",
+                    other.network, chain.network
+                ));
+            }
+            if chain.port == other.port {
+                return Err(format!(
+                    "{:?} and {:?} chains must use different ports",
+                    other.network, chain.network
+                ));
+            }
+            if chain.rpc_config.enabled && chain.rpc_config.port == other.rpc_config.port {
+                return Err(format!(
+                    "{:?} and {:?} chains must use different JSON RPC ports",
+                    other.network, chain.network
+                ));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Parses peer address. `.onion` peers are mapped into the OnionCat range
+/// and are only reachable through the proxy.
+fn parse_peer(s: &str, default_port: u16) -> Result<net::SocketAddr, String> {
+    if let Some(address) = parse_onion_address(s, default_port)? {
+        return Ok(address);
+    }
+
+    match s.parse::<net::SocketAddr>() {
+        Ok(address) => Ok(address), // with port given
+        Err(_) => {
+            // no port given, enforce the default port
+            s.parse::<net::IpAddr>()
+                .map(|ip| net::SocketAddr::new(ip, default_port))
+                .map_err(|_| format!("Invalid peer address {}", s))
+        }
+    }
+}
+
+/// Parses socket address. Port is optional
+fn parse_address(s: &str, default_port: u16) -> Result<net::SocketAddr, net::AddrParseError> {
+    s.parse::<net::SocketAddr>()
+        .or_else(|_| s.parse().map(|ip| net::SocketAddr::new(ip, default_port)))
+}
+
+fn default_tuning(network: Network, data_dir: &Option<String>) -> Result<Tuning, String> {
+    // regtest nodes are short-lived, so benchmarking them is a waste of time
+    match network {
+        Network::Testnet | Network::Mainnet | Network::Other(_) => load_or_generate(data_dir),
+        Network::Regtest | Network::Unitest => Ok(Tuning {
+            sync_server_threads: 1,
+            ..Default::default()
+        }),
+    }
+}
+
+fn default_connections(network: Network) -> (u32, u32, u32) {
+    match network {
+        Network::Testnet | Network::Mainnet | Network::Other(_) => (125, 8, 2),
+        Network::Regtest | Network::Unitest => (1, 0, 0),
+    }
+}
+
+fn parse_log_filters(matches: &clap::ArgMatches) -> String {
+    matches
+        .value_of("log-filters")
+        .unwrap_or(LOG_INFO)
+        .to_owned()
+}
+
+/// Parses max numbers of inbound and outbound connections.
+fn parse_connections(network: Network, matches: &clap::ArgMatches) -> Result<(u32, u32), String> {
+    let (in_connections, out_connections, _) = default_connections(network);
+    let in_connections = match matches.value_of("inbound-connections") {
+        Some(s) => s
+            .parse()
+            .map_err(|_| "Invalid inbound-connections - should be number".to_owned())?,
+        None => in_connections,
+    };
+    let out_connections = match matches.value_of("outbound-connections") {
+        Some(s) => s
+            .parse()
+            .map_err(|_| "Invalid outbound-connections - should be number".to_owned())?,
+        None => out_connections,
+    };
+    Ok((in_connections, out_connections))
+}
+
+fn parse_upload_limit(matches: &clap::ArgMatches) -> Result<Option<UploadLimit>, String> {
+    let upload_window = match matches.value_of("max-upload-window") {
+        Some(s) => match s.parse() {
+            Ok(window) if window > 0 => window,
+            _ => return Err("Invalid max-upload-window - should be positive number".into()),
+        },
+        None => DEFAULT_UPLOAD_WINDOW_S,
+    };
+    match matches.value_of("max-upload") {
+        Some(s) => Ok(Some(UploadLimit {
+            max_bytes: s
+                .parse::<u64>()
+                .map_err(|_| "Invalid max-upload - should be number in MB".to_owned())?
+                * 1024
+                * 1024,
+            window_s: upload_window,
+        })),
+        None => Ok(None),
+    }
+}
+
+fn parse_bandwidth_limits(matches: &clap::ArgMatches) -> Result<Vec<BandwidthLimit>, String> {
+    match matches.values_of("bandwidth-limit") {
+        Some(limits) => limits.map(|limit| limit.parse()).collect(),
+        None => Ok(Vec::new()),
+    }
+}
+
+fn parse_server_log_sampling(matches: &clap::ArgMatches) -> Result<u32, String> {
+    match matches.value_of("server-log-sampling") {
+        Some(s) => s
+            .parse()
+            .map_err(|_| "Invalid server-log-sampling - should be number".to_owned()),
+        None => Ok(DEFAULT_SERVER_LOG_SAMPLING),
+    }
+}
+
+fn default_p2p_threads(network: Network) -> usize {
+    match network {
+        Network::Testnet | Network::Mainnet | Network::Other(_) => 4,
+        Network::Regtest | Network::Unitest => 1,
+    }
+}
+
+fn default_user_agent(network: Network) -> String {
+    match network {
+        Network::Testnet | Network::Mainnet | Network::Unitest | Network::Other(_) => {
+            USER_AGENT.into()
+        }
+        Network::Regtest => REGTEST_USER_AGENT.into(),
+    }
+}
+
+fn default_min_peer_version(network: Network) -> u32 {
+    match network {
+        Network::Testnet | Network::Mainnet | Network::Other(_) => PROTOCOL_MINIMUM,
+        // local networks only have up-to-date peers
+        Network::Regtest | Network::Unitest => PROTOCOL_VERSION,
+    }
+}
+
+fn default_seednodes(network: Network) -> Vec<String> {
+    match network {
+        Network::Mainnet => mainnet_seednodes().into_iter().map(Into::into).collect(),
+        Network::Testnet => testnet_seednodes().into_iter().map(Into::into).collect(),
+        Network::Other(_) | Network::Regtest | Network::Unitest => Vec::new(),
+    }
+}
+
+fn parse_rpc_config(
+    network: Network,
+    data_dir: &Option<String>,
+    matches: &clap::ArgMatches,
+) -> Result<RpcHttpConfig, String> {
     let mut config = RpcHttpConfig::with_port(network.rpc_port());
     config.enabled = !matches.is_present("no-jsonrpc");
     if !config.enabled {
@@ -268,6 +1009,61 @@ fn parse_rpc_config(network: Network, matches: &clap::ArgMatches) -> Result<RpcH
             .parse()
             .map_err(|_| "Invalid JSON RPC hosts".to_owned())?]);
     }
+    config.users = parse_rpc_users(matches)?;
+    if matches.is_present("jsonrpc-cookie") {
+        config.cookie_dir = Some(rpc_cookie_dir(data_dir));
+    }
+    config.status_page = matches.is_present("jsonrpc-status-page");
+    if let Some(ms) = matches.value_of("jsonrpc-slow-call-ms") {
+        config.slow_call_threshold = Some(Duration::from_millis(ms.parse().map_err(|_| {
+            "Invalid jsonrpc-slow-call-ms - should be number of milliseconds".to_owned()
+        })?));
+    }
 
     Ok(config)
 }
+
+fn parse_rpc_users(matches: &clap::ArgMatches) -> Result<Vec<RpcUser>, String> {
+    match matches.values_of("jsonrpc-user") {
+        Some(users) => users.map(|user| user.parse()).collect(),
+        None => Ok(Vec::new()),
+    }
+}
+
+fn parse_verification_level(s: &str) -> Result<VerificationLevel, String> {
+    match s {
+        "paranoid" => Ok(VerificationLevel::Paranoid),
+        "full" => Ok(VerificationLevel::Full),
+        "header" => Ok(VerificationLevel::Header),
+        "none" => Ok(VerificationLevel::NoVerification),
+        _ => Err(format!("Invalid verification level: {}", s)),
+    }
+}
+
+/// Parses verification rule in FROM-[TO]:LEVEL format.
+fn parse_verification_rule(s: &str) -> Result<VerificationRule, String> {
+    let invalid_rule = || format!("Invalid verification rule: {}", s);
+    let mut parts = s.splitn(2, ':');
+    let range = parts.next().ok_or_else(invalid_rule)?;
+    let level = parse_verification_level(parts.next().ok_or_else(invalid_rule)?)?;
+
+    let mut heights = range.splitn(2, '-');
+    let from = heights
+        .next()
+        .and_then(|from| from.parse().ok())
+        .ok_or_else(invalid_rule)?;
+    let to = match heights.next() {
+        Some("") => None,
+        Some(to) => match to.parse() {
+            Ok(to) if to >= from => Some(to),
+            _ => return Err(invalid_rule()),
+        },
+        None => return Err(invalid_rule()),
+    };
+
+    Ok(VerificationRule {
+        from: from,
+        to: to,
+        level: level,
+    })
+}
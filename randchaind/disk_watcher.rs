@@ -0,0 +1,81 @@
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use std::{io, thread};
+use sync;
+
+/// Interval between free disk space checks.
+const CHECK_INTERVAL_S: u64 = 10;
+/// Blocks processing is resumed when free space exceeds the limit by this fraction of the limit.
+const RESUME_MARGIN_DIVISOR: u64 = 10;
+
+/// Starts thread, which periodically checks free space on the database volume and pauses
+/// blocks processing while it is below `min_free_space` bytes.
+pub fn start_disk_watcher(
+    sync_state: sync::SynchronizationStateRef,
+    db_path: PathBuf,
+    min_free_space: u64,
+) {
+    thread::Builder::new()
+        .name("Disk space watcher thread".to_owned())
+        .spawn(move || loop {
+            check_free_space(&sync_state, &db_path, min_free_space);
+            thread::sleep(Duration::from_secs(CHECK_INTERVAL_S));
+        })
+        .expect("Error creating disk space watcher thread");
+}
+
+fn check_free_space(sync_state: &sync::SynchronizationState, db_path: &Path, min_free_space: u64) {
+    let free_space = match free_space(db_path) {
+        Ok(free_space) => free_space,
+        Err(err) => {
+            warn!(target: "randchaind", "Failed to read free disk space: {}", err);
+            return;
+        }
+    };
+
+    let disk_space_low = sync_state.disk_space_low();
+    if !disk_space_low && free_space < min_free_space {
+        error!(
+            target: "randchaind",
+            "Free disk space {} MB is below {} MB. Blocks processing is paused until disk space is freed",
+            free_space / 1024 / 1024,
+            min_free_space / 1024 / 1024
+        );
+        sync_state.update_disk_space_low(true);
+    } else if disk_space_low
+        && free_space >= min_free_space + min_free_space / RESUME_MARGIN_DIVISOR
+    {
+        info!(
+            target: "randchaind",
+            "Free disk space is {} MB. Blocks processing is resumed",
+            free_space / 1024 / 1024
+        );
+        sync_state.update_disk_space_low(false);
+    }
+}
+
+/// Returns number of bytes available to unprivileged users on the volume, containing given path.
+#[cfg(unix)]
+fn free_space(path: &Path) -> Result<u64, io::Error> {
+    use libc;
+    use std::ffi::CString;
+    use std::mem;
+    use std::os::unix::ffi::OsStrExt;
+
+    let c_path = CString::new(path.as_os_str().as_bytes())
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err))?;
+    let mut stat: libc::statvfs = unsafe { mem::zeroed() };
+    if unsafe { libc::statvfs(c_path.as_ptr(), &mut stat) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    Ok(stat.f_bavail as u64 * stat.f_frsize as u64)
+}
+
+#[cfg(not(unix))]
+fn free_space(_path: &Path) -> Result<u64, io::Error> {
+    Err(io::Error::new(
+        io::ErrorKind::Other,
+        "free disk space check is not supported on this platform",
+    ))
+}
@@ -0,0 +1,126 @@
+//! User-supplied genesis blocks of private networks.
+//!
+//! Genesis block is given either as hex of the serialized block, or as JSON specification of
+//! its header (fields, which are omitted, are taken from the mainnet genesis block):
+//!
+//! ```json
+//! {
+//!     "version": 1,
+//!     "time": 1600000000,
+//!     "bits": 545259519,
+//!     "pubkey": "0606060606060606060606060606060606060606060606060606060606060606",
+//!     "iterations": 100000
+//! }
+//! ```
+//!
+//! Randomness and proof of the specified block are computed by evaluating the VDF, which takes
+//! time proportional to the number of iterations.
+//!
+//! Both forms could be also read from the file.
+
+use chain::{Block, IndexedBlock};
+use ecvrf::VrfPk;
+use hex;
+use network::{genesis_magic, register_custom_network, CustomNetwork, Network};
+use ser::deserialize;
+use serde_json::{self, Value};
+use std::fs;
+use std::path::Path;
+use vdf;
+use verification::h_g;
+
+/// Parses genesis block, given as hex, JSON specification or path to the file with any of them.
+pub fn parse_genesis_block(s: &str) -> Result<Block, String> {
+    let s = s.trim();
+    if Path::new(s).is_file() {
+        let contents = fs::read_to_string(s)
+            .map_err(|err| format!("Failed to read genesis block {}: {}", s, err))?;
+        // file can't refer to another file
+        return parse_genesis_contents(contents.trim()).map_err(|err| format!("{}: {}", s, err));
+    }
+
+    parse_genesis_contents(s)
+}
+
+/// Parses genesis block, given as hex or JSON specification.
+fn parse_genesis_contents(s: &str) -> Result<Block, String> {
+    if s.starts_with('{') {
+        return parse_genesis_spec(s);
+    }
+
+    let bytes = hex::decode(s)
+        .map_err(|_| "Invalid genesis - should be hex, JSON or path to the file".to_owned())?;
+    deserialize::<_, Block>(&bytes[..]).map_err(|err| format!("Invalid genesis block: {:?}", err))
+}
+
+/// Registers private network, which differs from the mainnet by the genesis block only.
+/// Network magic is derived from the genesis block.
+pub fn load_private_network(s: &str) -> Result<Network, String> {
+    let genesis_block = parse_genesis_block(s)?;
+    let magic = genesis_magic(&genesis_block);
+    let mainnet = Network::Mainnet;
+    let network = register_custom_network(CustomNetwork {
+        name: format!("private-{:08x}", magic),
+        magic: magic,
+        port: mainnet.port(),
+        rpc_port: mainnet.rpc_port(),
+        block_interval: mainnet.block_interval(),
+        max_bits: mainnet.max_bits(),
+        vdf_params: mainnet.vdf_params().clone(),
+        genesis_block: genesis_block,
+    })?;
+    info!(
+        target: "randchaind",
+        "Using private network with genesis block {} and magic {:#010x}",
+        network.genesis_block().hash().to_reversed_str(),
+        magic
+    );
+    Ok(network)
+}
+
+fn parse_genesis_spec(s: &str) -> Result<Block, String> {
+    let spec: Value =
+        serde_json::from_str(s).map_err(|err| format!("Invalid genesis spec: {}", err))?;
+    let fields = spec
+        .as_object()
+        .ok_or_else(|| "Invalid genesis spec - should be JSON object".to_owned())?;
+    let number = |name: &str, value: &Value| -> Result<u32, String> {
+        value
+            .as_u64()
+            .filter(|value| *value <= u64::from(u32::max_value()))
+            .map(|value| value as u32)
+            .ok_or_else(|| format!("Invalid {} - should be 32-bit number", name))
+    };
+
+    let mut genesis_block = Network::Mainnet.genesis_block().to_raw_block();
+    {
+        let header = &mut genesis_block.block_header;
+        for (name, value) in fields {
+            match name.as_str() {
+                "version" => header.version = number(name, value)?,
+                "time" => header.time = number(name, value)?,
+                "bits" => header.bits = number(name, value)?.into(),
+                "iterations" => header.iterations = number(name, value)?,
+                "pubkey" => {
+                    let invalid = || "Invalid pubkey - should be hex of 32 bytes".to_owned();
+                    let bytes = value
+                        .as_str()
+                        .and_then(|s| hex::decode(s).ok())
+                        .filter(|bytes| bytes.len() == 32)
+                        .ok_or_else(invalid)?;
+                    let mut pubkey = [0u8; 32];
+                    pubkey.copy_from_slice(&bytes);
+                    header.pubkey = VrfPk::from_bytes(&pubkey).map_err(|_| invalid())?
+                }
+                _ => return Err(format!("Unknown genesis spec field: {}", name)),
+            }
+        }
+    }
+
+    // randomness and proof of the mainnet genesis block are not valid for the specified header
+    let g = h_g(&IndexedBlock::from_raw(genesis_block.clone()));
+    let header = &mut genesis_block.block_header;
+    header.randomness = vdf::eval(&g, header.iterations);
+    genesis_block.proof = vdf::prove(&g, &header.randomness, header.iterations);
+    Ok(genesis_block)
+}
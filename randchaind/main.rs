@@ -8,6 +8,8 @@ extern crate app_dirs;
 extern crate env_logger;
 extern crate hex;
 extern crate libc;
+extern crate serde_json;
+extern crate toml;
 
 extern crate chain;
 extern crate db;
@@ -19,15 +21,27 @@ extern crate network;
 extern crate p2p;
 extern crate primitives;
 extern crate rpc as ethcore_rpc;
+extern crate serialization as ser;
 extern crate storage;
 extern crate sync;
+#[cfg(test)]
+extern crate test_data;
+extern crate vdf;
 extern crate verification;
 
+mod block_publisher;
 mod commands;
 mod config;
+mod disk_watcher;
+mod genesis;
+mod network_spec;
+mod node_config;
+mod prune;
+mod reload;
 mod rpc;
 mod rpc_apis;
 mod seednodes;
+mod tuning;
 mod util;
 
 use app_dirs::AppInfo;
@@ -55,17 +69,45 @@ fn main() {
 fn run() -> Result<(), String> {
     let yaml = load_yaml!("cli.yml");
     let matches = clap::App::from_yaml(yaml).get_matches();
+    let matches = match node_config::merged_args(&matches)? {
+        Some(args) => clap::App::from_yaml(yaml).get_matches_from(args),
+        None => matches,
+    };
+    // self-test runs against temporary database, so the configured one is not even opened
+    if matches.subcommand_name() == Some("selftest") {
+        return commands::selftest();
+    }
+    // compared databases could include the configured one, so it is not opened too
+    if let ("compare-chains", Some(compare_matches)) = matches.subcommand() {
+        return commands::compare_chains(compare_matches);
+    }
+    // checked database must not be used by the node
+    if let ("db", Some(db_matches)) = matches.subcommand() {
+        return match db_matches.subcommand() {
+            ("check", Some(check_matches)) => commands::db_check(&matches, check_matches),
+            _ => Err(db_matches.usage().to_owned()),
+        };
+    }
+
     let cfg = config::parse(&matches)?;
 
-    if !cfg.quiet {
+    let log_filters = if !cfg.quiet {
         if cfg!(windows) {
-            logs::init(LOG_INFO, logs::DateLogFormatter);
+            Some(logs::init(&cfg.log_filters, logs::DateLogFormatter))
         } else {
-            logs::init(LOG_INFO, logs::DateAndColorLogFormatter);
+            Some(logs::init(&cfg.log_filters, logs::DateAndColorLogFormatter))
         }
     } else {
         env_logger::init();
-    }
+        None
+    };
+    // sync spans are written to the same logger
+    logs::init_spans();
 
-    commands::start(cfg)
+    match matches.subcommand() {
+        ("import", Some(import_matches)) => commands::import(cfg, import_matches),
+        ("export-state", Some(export_matches)) => commands::export_state(cfg, export_matches),
+        ("import-state", Some(import_matches)) => commands::import_state(cfg, import_matches),
+        _ => commands::start(cfg, log_filters),
+    }
 }
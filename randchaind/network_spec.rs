@@ -0,0 +1,105 @@
+//! Custom network specification file.
+//!
+//! Specification is a TOML document, like the configuration file:
+//!
+//! ```toml
+//! name = "devnet"
+//! # optional, derived from the genesis block by default
+//! magic = "0x1a2b3c4d"
+//! port = 28333
+//! rpc-port = 28332
+//! # target interval between blocks, in seconds
+//! block-interval = 10
+//! # optional, hex of the max block target (mainnet max target by default)
+//! max-bits = "7fffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff"
+//! # optional VDF iterations range (unlimited by default)
+//! min-iterations = 0
+//! max-iterations = 4294967295
+//! # optional, hex of the serialized genesis block, JSON specification of its header or path
+//! # to the file with any of them (mainnet genesis block by default)
+//! genesis = "..."
+//! ```
+
+use genesis::parse_genesis_block;
+use network::{genesis_magic, register_custom_network, CustomNetwork, Network, VdfParams};
+use node_config::{NodeConfig, OptionValue};
+use std::fs;
+
+/// Reads custom network specification from the file and registers the network.
+pub fn load_network_spec(path: &str) -> Result<Network, String> {
+    let contents = fs::read_to_string(path)
+        .map_err(|err| format!("Failed to read network spec {}: {}", path, err))?;
+    let spec = parse_network_spec(&contents).map_err(|err| format!("{}: {}", path, err))?;
+    let name = spec.name.clone();
+    let network = register_custom_network(spec)?;
+    info!(target: "randchaind", "Using custom network {} from {}", name, path);
+    Ok(network)
+}
+
+fn parse_network_spec(contents: &str) -> Result<CustomNetwork, String> {
+    let mut options = NodeConfig::parse(contents)?.options;
+    let mut value = |name: &str| -> Result<Option<String>, String> {
+        match options.remove(name) {
+            Some(OptionValue::Value(value)) => Ok(Some(value)),
+            Some(_) => Err(format!("Invalid {} - should be string or number", name)),
+            None => Ok(None),
+        }
+    };
+    let required =
+        |name: &str, value: Option<String>| value.ok_or_else(|| format!("Missing {}", name));
+
+    let name = required("name", value("name")?)?;
+    let magic = match value("magic")? {
+        Some(magic) => Some(
+            match magic.starts_with("0x") {
+                true => u32::from_str_radix(&magic[2..], 16),
+                false => magic.parse(),
+            }
+            .map_err(|_| "Invalid magic - should be 32-bit number".to_owned())?,
+        ),
+        None => None,
+    };
+    let port = required("port", value("port")?)?
+        .parse()
+        .map_err(|_| "Invalid port".to_owned())?;
+    let rpc_port = required("rpc-port", value("rpc-port")?)?
+        .parse()
+        .map_err(|_| "Invalid rpc-port".to_owned())?;
+    let block_interval = required("block-interval", value("block-interval")?)?
+        .parse()
+        .map_err(|_| "Invalid block-interval - should be number of seconds".to_owned())?;
+    let max_bits = match value("max-bits")? {
+        Some(s) => s.parse().map_err(|_| "Invalid max-bits".to_owned())?,
+        None => Network::Mainnet.max_bits(),
+    };
+
+    let mut vdf_params = VdfParams::default();
+    if let Some(s) = value("min-iterations")? {
+        vdf_params.min_iterations = s.parse().map_err(|_| "Invalid min-iterations".to_owned())?;
+    }
+    if let Some(s) = value("max-iterations")? {
+        vdf_params.max_iterations = s.parse().map_err(|_| "Invalid max-iterations".to_owned())?;
+    }
+
+    let genesis_block = match value("genesis")? {
+        Some(s) => parse_genesis_block(&s)?,
+        None => Network::Mainnet.genesis_block().to_raw_block(),
+    };
+    // networks with different genesis blocks must never be connected
+    let magic = magic.unwrap_or_else(|| genesis_magic(&genesis_block));
+
+    if let Some(name) = options.keys().next() {
+        return Err(format!("Unknown network spec option: {}", name));
+    }
+
+    Ok(CustomNetwork {
+        name: name,
+        magic: magic,
+        port: port,
+        rpc_port: rpc_port,
+        block_interval: block_interval,
+        max_bits: max_bits,
+        vdf_params: vdf_params,
+        genesis_block: genesis_block,
+    })
+}
@@ -0,0 +1,306 @@
+use clap::ArgMatches;
+use std::collections::{BTreeMap, HashSet};
+use std::{env, fs};
+use toml;
+
+/// Prefix of environment variables, which set options: `RANDCHAIND_DB_CACHE=1024` is `--db-cache 1024`.
+const ENV_PREFIX: &'static str = "RANDCHAIND_";
+/// Environment variable with path of the configuration file, used when `--config` is not given.
+const ENV_CONFIG: &'static str = "RANDCHAIND_CONFIG";
+
+/// Value of the option in the configuration file or environment variable.
+#[derive(Debug, PartialEq)]
+pub enum OptionValue {
+    /// Flag option, e.g. `quiet = true`
+    Flag(bool),
+    /// Option with value, e.g. `port = 8333` or `host = "::"`
+    Value(String),
+    /// Option, which can be specified multiple times, e.g. `chain = ["testnet:/data/testnet"]`
+    Values(Vec<String>),
+}
+
+/// Options of the configuration file, named after the command line options.
+#[derive(Debug, Default, PartialEq)]
+pub struct NodeConfig {
+    /// Values of the options. `_` in names is replaced with `-`.
+    pub options: BTreeMap<String, OptionValue>,
+}
+
+impl NodeConfig {
+    /// Parses configuration file, which is a TOML document with top-level keys only.
+    /// Options are named after the command line options, `_` may be used instead of `-`.
+    /// Values are booleans for flags, strings or numbers for options with value and
+    /// arrays of strings or numbers for options, which can be specified multiple times.
+    /// Tables and dates are not allowed.
+    pub fn parse(contents: &str) -> Result<Self, String> {
+        let table = match contents.parse::<toml::Value>() {
+            Ok(toml::Value::Table(table)) => table,
+            Ok(_) => return Err("Config must be a table of options".into()),
+            Err(err) => return Err(format!("Invalid config: {}", err)),
+        };
+
+        let mut options = BTreeMap::new();
+        for (name, value) in table {
+            let invalid_option = || format!("Invalid config option {}", name);
+            let value = match value {
+                toml::Value::Boolean(flag) => OptionValue::Flag(flag),
+                toml::Value::Array(values) => OptionValue::Values(
+                    values
+                        .into_iter()
+                        .map(scalar_value)
+                        .collect::<Option<Vec<_>>>()
+                        .ok_or_else(&invalid_option)?,
+                ),
+                value => OptionValue::Value(scalar_value(value).ok_or_else(&invalid_option)?),
+            };
+
+            let option_name = name.replace('_', "-");
+            if option_name == "config" || options.insert(option_name, value).is_some() {
+                return Err(invalid_option());
+            }
+        }
+
+        Ok(NodeConfig { options: options })
+    }
+
+    /// Reads and parses configuration file.
+    pub fn read(path: &str) -> Result<Self, String> {
+        let contents = fs::read_to_string(path)
+            .map_err(|err| format!("Failed to read config file {}: {}", path, err))?;
+        NodeConfig::parse(&contents).map_err(|err| format!("{}: {}", path, err))
+    }
+}
+
+/// Converts string or number to the option value.
+fn scalar_value(value: toml::Value) -> Option<String> {
+    match value {
+        toml::Value::String(value) => Some(value),
+        toml::Value::Integer(value) => Some(value.to_string()),
+        toml::Value::Float(value) => Some(value.to_string()),
+        _ => None,
+    }
+}
+
+/// Returns command line, extended with options from the configuration file and environment
+/// variables. Command line options take precedence over environment variables, which take
+/// precedence over the configuration file. Returns None if there's nothing to add.
+///
+/// Merged command line is parsed again, so every option is validated the same way,
+/// wherever it comes from.
+pub fn merged_args(matches: &ArgMatches) -> Result<Option<Vec<String>>, String> {
+    let config_path = matches
+        .value_of("config")
+        .map(Into::into)
+        .or_else(|| env::var(ENV_CONFIG).ok());
+    let config = match config_path {
+        Some(path) => NodeConfig::read(&path)?,
+        None => NodeConfig::default(),
+    };
+
+    let args = option_args(matches, config, env::vars());
+    if args.is_empty() {
+        return Ok(None);
+    }
+
+    Ok(Some(insert_option_args(env::args().collect(), args)))
+}
+
+/// Returns names of the top-level command line options.
+fn known_options() -> HashSet<String> {
+    let yaml = load_yaml!("cli.yml");
+    yaml["args"]
+        .as_vec()
+        .into_iter()
+        .flat_map(|args| args.iter())
+        .filter_map(|arg| arg.as_hash())
+        .flat_map(|arg| arg.keys())
+        .filter_map(|name| name.as_str())
+        .map(Into::into)
+        .collect()
+}
+
+/// Returns arguments for options of the configuration file and environment variables,
+/// which are not given on the command line. Environment variables, which do not name
+/// any option, are ignored with a warning.
+fn option_args<I>(matches: &ArgMatches, config: NodeConfig, vars: I) -> Vec<String>
+where
+    I: IntoIterator<Item = (String, String)>,
+{
+    let known_options = known_options();
+    let mut options = config.options;
+    for (key, value) in vars {
+        if key == ENV_CONFIG || !key.starts_with(ENV_PREFIX) {
+            continue;
+        }
+
+        let name = key[ENV_PREFIX.len()..].to_lowercase().replace('_', "-");
+        if !known_options.contains(&name) {
+            eprintln!(
+                "Ignoring environment variable {}: unknown option {}",
+                key, name
+            );
+            continue;
+        }
+        let value = match value.as_str() {
+            "true" => OptionValue::Flag(true),
+            "false" => OptionValue::Flag(false),
+            _ => OptionValue::Value(value.clone()),
+        };
+        options.insert(name, value);
+    }
+
+    let mut args = Vec::new();
+    for (name, value) in options {
+        if matches.occurrences_of(&name) != 0 {
+            continue;
+        }
+
+        match value {
+            OptionValue::Flag(true) => args.push(format!("--{}", name)),
+            OptionValue::Flag(false) => (),
+            OptionValue::Value(value) => {
+                args.push(format!("--{}", name));
+                args.push(value);
+            }
+            OptionValue::Values(values) => {
+                for value in values {
+                    args.push(format!("--{}", name));
+                    args.push(value);
+                }
+            }
+        }
+    }
+    args
+}
+
+/// Inserts option arguments right after the program name, so they go before the subcommand, if any.
+fn insert_option_args(mut command_line: Vec<String>, args: Vec<String>) -> Vec<String> {
+    let user_args = command_line.split_off(1);
+    command_line.extend(args);
+    command_line.extend(user_args);
+    command_line
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{insert_option_args, option_args, NodeConfig, OptionValue};
+    use clap::App;
+
+    fn merged_command_line(config: &str, vars: &[(&str, &str)], args: &[&str]) -> Vec<String> {
+        let yaml = load_yaml!("cli.yml");
+        let mut command_line = vec!["randchaind".to_owned()];
+        command_line.extend(args.iter().map(|arg| arg.to_string()));
+        let matches = App::from_yaml(yaml).get_matches_from(command_line.clone());
+
+        let config = NodeConfig::parse(config).unwrap();
+        let vars = vars
+            .iter()
+            .map(|&(key, value)| (key.to_owned(), value.to_owned()));
+        let option_args = option_args(&matches, config, vars);
+        insert_option_args(command_line, option_args)
+    }
+
+    #[test]
+    fn config_file_is_parsed() {
+        let config = NodeConfig::parse(
+            r#"
+            # comment
+            port = 8333
+            host = "::"
+            quiet = true
+            db_cache = 1024
+            listen = ["127.0.0.1:8333", "[::1]:8333"]
+            "#,
+        )
+        .unwrap();
+
+        let mut options = config.options.into_iter();
+        assert_eq!(
+            options.next(),
+            Some(("db-cache".to_owned(), OptionValue::Value("1024".to_owned())))
+        );
+        assert_eq!(
+            options.next(),
+            Some(("host".to_owned(), OptionValue::Value("::".to_owned())))
+        );
+        assert_eq!(
+            options.next(),
+            Some((
+                "listen".to_owned(),
+                OptionValue::Values(vec!["127.0.0.1:8333".to_owned(), "[::1]:8333".to_owned()])
+            ))
+        );
+        assert_eq!(
+            options.next(),
+            Some(("port".to_owned(), OptionValue::Value("8333".to_owned())))
+        );
+        assert_eq!(
+            options.next(),
+            Some(("quiet".to_owned(), OptionValue::Flag(true)))
+        );
+        assert_eq!(options.next(), None);
+    }
+
+    #[test]
+    fn invalid_config_file_is_rejected() {
+        // not a TOML document
+        assert!(NodeConfig::parse("port 8333").is_err());
+        // config file can't include another one
+        assert!(NodeConfig::parse("config = \"other.toml\"").is_err());
+        // the same option is given twice
+        assert!(NodeConfig::parse("db_cache = 1\ndb-cache = 2").is_err());
+        // tables and nested arrays are not options
+        assert!(NodeConfig::parse("[jsonrpc]\nport = 8332").is_err());
+        assert!(NodeConfig::parse("listen = [[\"127.0.0.1:8333\"]]").is_err());
+    }
+
+    #[test]
+    fn command_line_overrides_environment_and_config_file() {
+        let yaml = load_yaml!("cli.yml");
+        let port = |command_line: Vec<String>| {
+            App::from_yaml(yaml)
+                .get_matches_from(command_line)
+                .value_of("port")
+                .map(str::to_owned)
+        };
+
+        let config = "port = 1";
+        let vars = [("RANDCHAIND_PORT", "2")];
+        assert_eq!(
+            port(merged_command_line(config, &[], &[])),
+            Some("1".to_owned())
+        );
+        assert_eq!(
+            port(merged_command_line(config, &vars, &[])),
+            Some("2".to_owned())
+        );
+        assert_eq!(
+            port(merged_command_line(config, &vars, &["--port", "3"])),
+            Some("3".to_owned())
+        );
+    }
+
+    #[test]
+    fn options_go_before_subcommand() {
+        let command_line = merged_command_line(
+            "quiet = true\nregtest = false",
+            &[
+                ("RANDCHAIND_DB_CACHE", "64"),
+                ("OTHER_VAR", "1"),
+                ("RANDCHAIND_UNKNOWN_OPTION", "1"),
+            ],
+            &["import", "blocks"],
+        );
+        assert_eq!(
+            command_line,
+            vec![
+                "randchaind",
+                "--db-cache",
+                "64",
+                "--quiet",
+                "import",
+                "blocks"
+            ]
+        );
+    }
+}
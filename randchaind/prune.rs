@@ -0,0 +1,76 @@
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use std::{cmp, fs, io, thread};
+use storage;
+
+/// Blocks within this distance from the best block are never pruned.
+/// Storage is unable to switch to forks deeper than that anyway.
+pub const FINALITY_DEPTH: u32 = 2048;
+/// Interval between database size checks.
+const CHECK_INTERVAL_S: u64 = 60;
+/// Max number of block bodies pruned at once.
+const PRUNE_STEP: u32 = 1024;
+
+/// Starts thread, which periodically checks the database size and prunes the oldest
+/// block bodies when it exceeds `max_db_size` bytes.
+pub fn start_pruning(db: storage::SharedStore, db_path: PathBuf, max_db_size: u64) {
+    thread::Builder::new()
+        .name("Storage quota thread".to_owned())
+        .spawn(move || loop {
+            enforce_quota(&db, &db_path, max_db_size);
+            thread::sleep(Duration::from_secs(CHECK_INTERVAL_S));
+        })
+        .expect("Error creating storage quota thread");
+}
+
+fn enforce_quota(db: &storage::SharedStore, db_path: &Path, max_db_size: u64) {
+    let db_size = match dir_size(db_path) {
+        Ok(db_size) => db_size,
+        Err(err) => {
+            warn!(target: "randchaind", "Failed to read database size: {}", err);
+            return;
+        }
+    };
+    if db_size <= max_db_size {
+        return;
+    }
+
+    let pruned_height = db.pruned_height().unwrap_or(0);
+    let max_prune_height = db.best_block().number.saturating_sub(FINALITY_DEPTH);
+    if max_prune_height <= pruned_height {
+        warn!(
+            target: "randchaind",
+            "Database size {} MB exceeds quota of {} MB, but all blocks below finality depth are already pruned",
+            db_size / 1024 / 1024,
+            max_db_size / 1024 / 1024
+        );
+        return;
+    }
+
+    let up_to = cmp::min(pruned_height + PRUNE_STEP, max_prune_height);
+    match db.prune(up_to) {
+        Ok(pruned_height) => info!(
+            target: "randchaind",
+            "Database size {} MB exceeds quota of {} MB. Block bodies are pruned up to {:?}",
+            db_size / 1024 / 1024,
+            max_db_size / 1024 / 1024,
+            pruned_height
+        ),
+        Err(err) => error!(target: "randchaind", "Failed to prune block bodies: {}", err),
+    }
+}
+
+/// Returns total size of files in given directory (recursively).
+fn dir_size(path: &Path) -> Result<u64, io::Error> {
+    let mut size = 0;
+    for entry in fs::read_dir(path)? {
+        let entry = entry?;
+        let metadata = entry.metadata()?;
+        if metadata.is_dir() {
+            size += dir_size(&entry.path())?;
+        } else {
+            size += metadata.len();
+        }
+    }
+    Ok(size)
+}
@@ -0,0 +1,232 @@
+use clap;
+use config::{self, ReloadableConfig};
+use ethcore_rpc::v1::ConfigReloader;
+use ethcore_rpc::Authenticator;
+use logs;
+use network::Network;
+use node_config;
+use p2p;
+use std::env;
+use std::sync::{Arc, Mutex};
+use sync;
+
+/// Services of the running chain, which settings could be changed at runtime.
+pub struct ReloadableChain {
+    pub local_sync_node: sync::LocalNodeRef,
+    pub p2p_context: Arc<p2p::Context>,
+    pub rpc_authenticator: Authenticator,
+}
+
+struct ReloaderState {
+    /// Network of the main chain
+    network: Network,
+    /// Currently applied settings
+    config: ReloadableConfig,
+    /// Running chains. The main chain is registered first
+    chains: Vec<ReloadableChain>,
+}
+
+/// Re-reads command line and configuration file and applies settings, which could be
+/// changed without restarting the node. Everything else is left as it was at startup.
+pub struct Reloader {
+    /// None when logs are not written by the node itself (quiet mode)
+    log_filters: Option<logs::LogFilters>,
+    state: Mutex<ReloaderState>,
+}
+
+impl Reloader {
+    pub fn new(
+        network: Network,
+        config: ReloadableConfig,
+        log_filters: Option<logs::LogFilters>,
+    ) -> Self {
+        Reloader {
+            log_filters: log_filters,
+            state: Mutex::new(ReloaderState {
+                network: network,
+                config: config,
+                chains: Vec::new(),
+            }),
+        }
+    }
+
+    /// Registers started chain, so that reloaded settings are applied to it too.
+    pub fn register(&self, chain: ReloadableChain) {
+        self.state
+            .lock()
+            .expect("reloader lock is never poisoned")
+            .chains
+            .push(chain);
+    }
+
+    fn apply(&self, state: &mut ReloaderState, config: ReloadableConfig) -> Vec<String> {
+        let mut changes = Vec::new();
+
+        if config.log_filters != state.config.log_filters {
+            match self.log_filters {
+                Some(ref log_filters) => {
+                    log_filters.set(&config.log_filters);
+                    changes.push(format!("log filters: {}", config.log_filters));
+                }
+                None => warn!(target: "randchaind", "Log filters are not reloaded in quiet mode"),
+            }
+        }
+
+        // connections limits are only configurable for the main chain
+        if config.inbound_connections != state.config.inbound_connections
+            || config.outbound_connections != state.config.outbound_connections
+        {
+            if let Some(main_chain) = state.chains.first() {
+                main_chain
+                    .p2p_context
+                    .set_max_connections(config.inbound_connections, config.outbound_connections);
+            }
+            changes.push(format!(
+                "max connections: {} inbound, {} outbound",
+                config.inbound_connections, config.outbound_connections
+            ));
+        }
+
+        if config.upload_limit != state.config.upload_limit {
+            for chain in &state.chains {
+                chain.local_sync_node.set_upload_limit(config.upload_limit);
+            }
+            changes.push(match config.upload_limit {
+                Some(limit) => format!(
+                    "upload limit: {} bytes per {} seconds",
+                    limit.max_bytes, limit.window_s
+                ),
+                None => "upload limit: none".to_owned(),
+            });
+        }
+
+        if config.bandwidth_limits != state.config.bandwidth_limits {
+            for chain in &state.chains {
+                chain
+                    .p2p_context
+                    .set_bandwidth_limits(config.bandwidth_limits.clone());
+            }
+            let limits: Vec<_> = config
+                .bandwidth_limits
+                .iter()
+                .map(|limit| limit.to_string())
+                .collect();
+            changes.push(format!("bandwidth limits: [{}]", limits.join(", ")));
+        }
+
+        if config.server_log_sampling != state.config.server_log_sampling {
+            for chain in &state.chains {
+                chain
+                    .local_sync_node
+                    .set_server_log_sampling(config.server_log_sampling);
+            }
+            changes.push(format!(
+                "server log sampling: {}",
+                config.server_log_sampling
+            ));
+        }
+
+        if config.rpc_users != state.config.rpc_users {
+            for chain in &state.chains {
+                chain.rpc_authenticator.set_users(config.rpc_users.clone());
+            }
+            // passwords are never logged
+            let names: Vec<_> = config
+                .rpc_users
+                .iter()
+                .map(|user| user.name.as_str())
+                .collect();
+            changes.push(format!("JSONRPC users: [{}]", names.join(", ")));
+        }
+
+        for change in &changes {
+            info!(target: "randchaind", "Configuration reloaded: {}", change);
+        }
+
+        state.config = config;
+        changes
+    }
+}
+
+impl ConfigReloader for Reloader {
+    fn reload(&self) -> Result<Vec<String>, String> {
+        let mut state = self.state.lock().expect("reloader lock is never poisoned");
+        let config = read_config(state.network)?;
+        Ok(self.apply(&mut state, config))
+    }
+}
+
+/// Parses reloadable settings from the same command line and configuration file, which
+/// have been used to start the node.
+fn read_config(network: Network) -> Result<ReloadableConfig, String> {
+    let yaml = load_yaml!("cli.yml");
+    let matches = clap::App::from_yaml(yaml)
+        .get_matches_from_safe(env::args())
+        .map_err(|err| err.to_string())?;
+    let matches = match node_config::merged_args(&matches)? {
+        Some(args) => clap::App::from_yaml(yaml)
+            .get_matches_from_safe(args)
+            .map_err(|err| err.to_string())?,
+        None => matches,
+    };
+    config::parse_reloadable(network, &matches)
+}
+
+#[cfg(unix)]
+mod signal {
+    use super::Reloader;
+    use ethcore_rpc::v1::ConfigReloader;
+    use libc;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+    use std::thread;
+    use std::time::Duration;
+
+    /// Interval between checks of reload requests.
+    const CHECK_INTERVAL_MS: u64 = 500;
+
+    static RELOAD_REQUESTED: AtomicBool = AtomicBool::new(false);
+
+    extern "C" fn on_sighup(_: libc::c_int) {
+        // only async-signal-safe operations are allowed here
+        RELOAD_REQUESTED.store(true, Ordering::SeqCst);
+    }
+
+    pub fn start_reload_watcher(reloader: Arc<Reloader>) {
+        unsafe {
+            libc::signal(libc::SIGHUP, on_sighup as libc::sighandler_t);
+        }
+
+        thread::Builder::new()
+            .name("Configuration reload thread".to_owned())
+            .spawn(move || loop {
+                if RELOAD_REQUESTED.swap(false, Ordering::SeqCst) {
+                    info!(target: "randchaind", "SIGHUP received, reloading configuration");
+                    match reloader.reload() {
+                        Ok(ref changes) if changes.is_empty() => {
+                            info!(target: "randchaind", "Configuration is not changed")
+                        }
+                        Ok(_) => (),
+                        Err(err) => {
+                            error!(target: "randchaind", "Failed to reload configuration: {}", err)
+                        }
+                    }
+                }
+                thread::sleep(Duration::from_millis(CHECK_INTERVAL_MS));
+            })
+            .expect("Error creating configuration reload thread");
+    }
+}
+
+#[cfg(not(unix))]
+mod signal {
+    use super::Reloader;
+    use std::sync::Arc;
+
+    pub fn start_reload_watcher(_reloader: Arc<Reloader>) {
+        warn!(target: "randchaind", "Configuration is only reloaded with reloadconfig RPC on this platform");
+    }
+}
+
+/// Starts thread, which reloads configuration when SIGHUP is received.
+pub use self::signal::start_reload_watcher;
@@ -1,19 +1,34 @@
-use ethcore_rpc::{start_http, Compatibility, MetaIoHandler, Remote, Server};
+use ethcore_rpc::v1::ConfigReloader;
+use ethcore_rpc::{
+    start_http, Authenticator, Compatibility, Meta, MetaIoHandler, PermissionsMiddleware, Remote,
+    RpcMiddleware, RpcStats, RpcUser, Server, StatusPage,
+};
+use miner;
 use network::Network;
 use p2p;
 use rpc_apis::{self, ApiSet};
 use std::io;
 use std::net::SocketAddr;
+use std::path::PathBuf;
 use std::sync::Arc;
+use std::time::Duration;
 use storage;
 use sync;
 
 pub struct Dependencies {
     pub network: Network,
-    pub local_sync_node: sync::LocalNodeRef,
+    /// Local synchronization node. None when the node is not synchronizing (e.g. during import).
+    pub local_sync_node: Option<sync::LocalNodeRef>,
     pub storage: storage::SharedStore,
-    pub p2p_context: Arc<p2p::Context>,
+    /// P2P context. None when p2p module is not started (e.g. during import).
+    pub p2p_context: Option<Arc<p2p::Context>>,
+    /// Local mining threads. None when the node is not mining.
+    pub mining: Option<miner::MiningControl>,
+    /// Key of the local miner. None when the node never produces blocks.
+    pub mining_pubkey: Option<miner::VrfPk>,
     pub remote: Remote,
+    /// Applies reloaded configuration. None when the node settings could not be reloaded (e.g. during import).
+    pub config_reloader: Option<Arc<dyn ConfigReloader>>,
 }
 
 #[derive(Debug, PartialEq)]
@@ -24,6 +39,14 @@ pub struct HttpConfiguration {
     pub apis: ApiSet,
     pub cors: Option<Vec<String>>,
     pub hosts: Option<Vec<String>>,
+    /// Users allowed to access the server. If empty (and cookie is disabled), no authentication is required.
+    pub users: Vec<RpcUser>,
+    /// Directory to write the authentication cookie to.
+    pub cookie_dir: Option<PathBuf>,
+    /// Calls, slower than this threshold, are logged (without parameters).
+    pub slow_call_threshold: Option<Duration>,
+    /// Serve human-readable node status page on `GET /status`.
+    pub status_page: bool,
 }
 
 impl HttpConfiguration {
@@ -35,11 +58,21 @@ impl HttpConfiguration {
             apis: ApiSet::default(),
             cors: None,
             hosts: Some(Vec::new()),
+            users: Vec::new(),
+            cookie_dir: None,
+            slow_call_threshold: None,
+            status_page: false,
         }
     }
 }
 
-pub fn new_http(conf: HttpConfiguration, deps: Dependencies) -> Result<Option<Server>, String> {
+/// Starts JSONRPC server. Configured users (and cookie identity) are installed into given
+/// authenticator, so that they could be replaced later.
+pub fn new_http(
+    conf: HttpConfiguration,
+    deps: Dependencies,
+    authenticator: &Authenticator,
+) -> Result<Option<Server>, String> {
     if !conf.enabled {
         return Ok(None);
     }
@@ -48,8 +81,39 @@ pub fn new_http(conf: HttpConfiguration, deps: Dependencies) -> Result<Option<Se
     let addr = url
         .parse()
         .map_err(|_| format!("Invalid JSONRPC listen host/port given: {}", url))?;
+
+    let mut users = conf.users;
+    if let Some(ref cookie_dir) = conf.cookie_dir {
+        let cookie = RpcUser::random_cookie();
+        let path = cookie
+            .write_cookie(cookie_dir)
+            .map_err(|err| format!("Failed to write RPC cookie file: {}", err))?;
+        info!("RPC authentication cookie is written to {}", path.display());
+        users.push(cookie);
+    }
+    authenticator.set_users(users);
+
+    let status_page = if conf.status_page {
+        Some(StatusPage::new(
+            deps.network,
+            deps.storage.clone(),
+            deps.local_sync_node.clone(),
+            deps.p2p_context.clone(),
+            authenticator.clone(),
+        ))
+    } else {
+        None
+    };
+
     Ok(Some(setup_http_rpc_server(
-        &addr, conf.cors, conf.hosts, conf.apis, deps,
+        &addr,
+        conf.cors,
+        conf.hosts,
+        conf.apis,
+        authenticator.clone(),
+        RpcStats::new(conf.slow_call_threshold),
+        status_page,
+        deps,
     )?))
 }
 
@@ -58,10 +122,20 @@ pub fn setup_http_rpc_server(
     cors_domains: Option<Vec<String>>,
     allowed_hosts: Option<Vec<String>>,
     apis: ApiSet,
+    authenticator: Authenticator,
+    stats: RpcStats,
+    status_page: Option<StatusPage>,
     deps: Dependencies,
 ) -> Result<Server, String> {
-    let server = setup_rpc_server(apis, deps);
-    let start_result = start_http(url, cors_domains, allowed_hosts, server);
+    let server = setup_rpc_server(apis, stats, deps);
+    let start_result = start_http(
+        url,
+        cors_domains,
+        allowed_hosts,
+        server,
+        authenticator,
+        status_page,
+    );
     match start_result {
 		Err(ref err) if err.kind() == io::ErrorKind::AddrInUse => {
 			Err(format!("RPC address {} is already in use, make sure that another instance of a RandChain node is not running or change the address using the --jsonrpc-port and --jsonrpc-interface options.", url))
@@ -71,10 +145,19 @@ pub fn setup_http_rpc_server(
 	}
 }
 
-fn setup_rpc_server(apis: ApiSet, deps: Dependencies) -> MetaIoHandler<()> {
+/// Creates JSONRPC handler. Statistics of calls are collected into given `stats`.
+pub fn setup_rpc_server(
+    apis: ApiSet,
+    stats: RpcStats,
+    deps: Dependencies,
+) -> MetaIoHandler<Meta, RpcMiddleware> {
     rpc_apis::setup_rpc(
-        MetaIoHandler::with_compatibility(Compatibility::Both),
+        MetaIoHandler::new(
+            Compatibility::Both,
+            RpcMiddleware::new(PermissionsMiddleware, stats.clone()),
+        ),
         apis,
+        stats,
         deps,
     )
 }
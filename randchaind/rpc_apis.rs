@@ -1,4 +1,4 @@
-use ethcore_rpc::MetaIoHandler;
+use ethcore_rpc::{Meta, MetaIoHandler, RpcMiddleware, RpcStats};
 use rpc::Dependencies;
 use std::collections::HashSet;
 use std::str::FromStr;
@@ -11,6 +11,8 @@ pub enum Api {
     BlockChain,
     /// Network
     Network,
+    /// Node management
+    Node,
 }
 
 #[derive(Debug, PartialEq, Eq)]
@@ -21,7 +23,7 @@ pub enum ApiSet {
 impl Default for ApiSet {
     fn default() -> Self {
         ApiSet::List(
-            vec![Api::Miner, Api::BlockChain, Api::Network]
+            vec![Api::Miner, Api::BlockChain, Api::Network, Api::Node]
                 .into_iter()
                 .collect(),
         )
@@ -36,6 +38,7 @@ impl FromStr for Api {
             "miner" => Ok(Api::Miner),
             "blockchain" => Ok(Api::BlockChain),
             "network" => Ok(Api::Network),
+            "node" => Ok(Api::Node),
             api => Err(format!("Unknown api: {}", api)),
         }
     }
@@ -47,27 +50,66 @@ impl ApiSet {
             ApiSet::List(ref apis) => apis.clone(),
         }
     }
+
+    /// Returns subset of APIs, which only read the blockchain storage.
+    pub fn read_only(&self) -> ApiSet {
+        ApiSet::List(
+            self.list_apis()
+                .into_iter()
+                .filter(|api| *api == Api::BlockChain)
+                .collect(),
+        )
+    }
 }
 
 pub fn setup_rpc(
-    mut handler: MetaIoHandler<()>,
+    mut handler: MetaIoHandler<Meta, RpcMiddleware>,
     apis: ApiSet,
+    stats: RpcStats,
     deps: Dependencies,
-) -> MetaIoHandler<()> {
+) -> MetaIoHandler<Meta, RpcMiddleware> {
     use ethcore_rpc::v1::*;
 
     for api in apis.list_apis() {
         match api {
-            Api::Miner => handler.extend_with(
-                MinerClient::new(MinerClientCore::new(deps.local_sync_node.clone())).to_delegate(),
-            ),
-            Api::BlockChain => handler.extend_with(
-                BlockChainClient::new(BlockChainClientCore::new(deps.storage.clone()))
+            Api::Miner => match deps.local_sync_node {
+                Some(ref local_sync_node) => handler.extend_with(
+                    MinerClient::new(MinerClientCore::new(
+                        local_sync_node.clone(),
+                        deps.mining.clone(),
+                        deps.mining_pubkey.clone(),
+                    ))
                     .to_delegate(),
+                ),
+                None => warn!("Miner RPC API is not available: synchronization is not running"),
+            },
+            Api::BlockChain => handler.extend_with(
+                BlockChainClient::new(BlockChainClientCore::new(
+                    deps.storage.clone(),
+                    deps.local_sync_node
+                        .as_ref()
+                        .map(|local_sync_node| local_sync_node.sync_state()),
+                    deps.local_sync_node.clone(),
+                ))
+                .to_delegate(),
             ),
-            Api::Network => handler.extend_with(
-                NetworkClient::new(NetworkClientCore::new(deps.p2p_context.clone())).to_delegate(),
-            ),
+            Api::Network => match deps.p2p_context {
+                Some(ref p2p_context) => handler.extend_with(
+                    NetworkClient::new(NetworkClientCore::new(
+                        p2p_context.clone(),
+                        deps.local_sync_node.clone(),
+                    ))
+                    .to_delegate(),
+                ),
+                None => warn!("Network RPC API is not available: p2p module is not running"),
+            },
+            Api::Node => match deps.config_reloader {
+                Some(ref config_reloader) => handler.extend_with(
+                    NodeClient::new(NodeClientCore::new(config_reloader.clone(), stats.clone()))
+                        .to_delegate(),
+                ),
+                None => warn!("Node RPC API is not available: configuration could not be reloaded"),
+            },
         }
     }
 
@@ -0,0 +1,398 @@
+use config::DEFAULT_DB_CACHE;
+use std::cmp::{max, min};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+use std::time::{Duration, Instant};
+use sync::DEFAULT_MAX_REQUESTED_BLOCKS;
+use util::tuning_path;
+use vdf;
+
+/// Default number of sync server threads, used when the host can not be benchmarked.
+pub const DEFAULT_SYNC_SERVER_THREADS: usize = 4;
+
+/// Every benchmark is stopped after this number of milliseconds.
+const BENCHMARK_DURATION_MS: u64 = 250;
+/// Size of the temporary file, which is read by the disk benchmark.
+const DISK_BENCHMARK_FILE_SIZE: u64 = 32 * 1024 * 1024;
+/// Size of the single random read of the disk benchmark.
+const DISK_BENCHMARK_READ_SIZE: usize = 4096;
+/// Name of the temporary file, which is read by the disk benchmark.
+const DISK_BENCHMARK_FILE_NAME: &'static str = "tuning.bench";
+/// Disks, serving less random reads per second, are considered rotational.
+const SLOW_DISK_READS_PER_S: u64 = 1000;
+/// Number of VDF squarings, computed between checks of the benchmark timer.
+const VDF_BENCHMARK_STEP: u32 = 1000;
+/// Number of VDF squarings per second, for which the default download window is tuned.
+const REFERENCE_VDF_SQUARINGS_PER_S: u64 = 250_000;
+/// Database cache never takes more than this fraction of the physical memory.
+const DB_CACHE_MEMORY_DIVISOR: u64 = 8;
+/// Minimal tuned database cache size, in MB.
+const MIN_DB_CACHE: usize = 64;
+/// Maximal tuned number of sync server threads.
+const MAX_SYNC_SERVER_THREADS: usize = 8;
+/// Minimal tuned download window.
+const MIN_DOWNLOAD_WINDOW: u32 = 32;
+/// Maximal tuned download window.
+const MAX_DOWNLOAD_WINDOW: u32 = 1024;
+
+/// Defaults, derived from benchmarks of the host.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Tuning {
+    /// Database cache size, in MB.
+    pub db_cache: usize,
+    /// Number of sync server threads.
+    pub sync_server_threads: usize,
+    /// Approximate maximal number of blocks, requested from peers at once.
+    pub download_window: u32,
+}
+
+/// Results of the host micro-benchmarks.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Benchmarks {
+    /// Number of online CPUs.
+    pub cpus: Option<usize>,
+    /// Size of the physical memory, in MB.
+    pub memory: Option<u64>,
+    /// Number of random 4KB reads per second, served by the data dir volume.
+    pub disk_reads_per_s: Option<u64>,
+    /// Number of VDF squarings per second.
+    pub vdf_squarings_per_s: u64,
+}
+
+impl Default for Tuning {
+    fn default() -> Self {
+        Tuning {
+            db_cache: DEFAULT_DB_CACHE,
+            sync_server_threads: DEFAULT_SYNC_SERVER_THREADS,
+            download_window: DEFAULT_MAX_REQUESTED_BLOCKS,
+        }
+    }
+}
+
+impl Tuning {
+    /// Parses tuning file. Missing values are replaced with defaults.
+    pub fn parse(contents: &str) -> Result<Self, String> {
+        let mut tuning = Tuning::default();
+        for (index, line) in contents.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let invalid_line = || format!("Invalid tuning line {}: {}", index + 1, line);
+            let mut parts = line.splitn(2, '=');
+            let key = parts.next().map(str::trim).ok_or_else(&invalid_line)?;
+            let value = parts.next().map(str::trim).ok_or_else(&invalid_line)?;
+            match key {
+                "db_cache" => tuning.db_cache = value.parse().map_err(|_| invalid_line())?,
+                "sync_server_threads" => match value.parse() {
+                    Ok(threads) if threads > 0 => tuning.sync_server_threads = threads,
+                    _ => return Err(invalid_line()),
+                },
+                "download_window" => match value.parse() {
+                    Ok(window) if window > 0 => tuning.download_window = window,
+                    _ => return Err(invalid_line()),
+                },
+                _ => return Err(invalid_line()),
+            }
+        }
+        Ok(tuning)
+    }
+
+    /// Serializes tuning into the file contents, commented with benchmarks results.
+    pub fn to_file_contents(&self, benchmarks: &Benchmarks) -> String {
+        let unknown = || "unknown".to_owned();
+        format!(
+            "# Generated by randchaind on first start from benchmarks of this host:\n\
+             #   cpus: {}, memory: {} MB, disk random reads: {}/s, vdf squarings: {}/s\n\
+             # Edit values to change defaults or remove this file to rerun benchmarks.\n\
+             # Command line options take precedence over these values.\n\
+             \n\
+             # Database cache size, in MB.\n\
+             db_cache = {}\n\
+             # Number of threads, serving blocks and headers to peers.\n\
+             sync_server_threads = {}\n\
+             # Approximate maximal number of blocks, requested from peers at once.\n\
+             download_window = {}\n",
+            benchmarks
+                .cpus
+                .map(|cpus| cpus.to_string())
+                .unwrap_or_else(&unknown),
+            benchmarks
+                .memory
+                .map(|memory| memory.to_string())
+                .unwrap_or_else(&unknown),
+            benchmarks
+                .disk_reads_per_s
+                .map(|reads| reads.to_string())
+                .unwrap_or_else(&unknown),
+            benchmarks.vdf_squarings_per_s,
+            self.db_cache,
+            self.sync_server_threads,
+            self.download_window,
+        )
+    }
+}
+
+impl Benchmarks {
+    /// Runs all benchmarks. Disk benchmark is using temporary file in given directory.
+    pub fn run(dir: &Path) -> Self {
+        let disk_reads_per_s = match disk_random_reads_per_s(dir) {
+            Ok(reads) => Some(reads),
+            Err(err) => {
+                warn!(target: "randchaind", "Disk benchmark has failed: {}", err);
+                None
+            }
+        };
+
+        Benchmarks {
+            cpus: online_cpus(),
+            memory: physical_memory().map(|memory| memory / 1024 / 1024),
+            disk_reads_per_s: disk_reads_per_s,
+            vdf_squarings_per_s: vdf_squarings_per_s(),
+        }
+    }
+
+    /// Derives defaults from benchmarks results.
+    pub fn tuning(&self) -> Tuning {
+        let default = Tuning::default();
+
+        // slow disks are compensated with larger cache, but it shouldn't starve the host
+        let db_cache = match self.disk_reads_per_s {
+            Some(reads) if reads < SLOW_DISK_READS_PER_S => default.db_cache * 2,
+            _ => default.db_cache,
+        };
+        let db_cache = match self.memory {
+            Some(memory) => min(db_cache, (memory / DB_CACHE_MEMORY_DIVISOR) as usize),
+            None => db_cache,
+        };
+        let db_cache = max(db_cache, MIN_DB_CACHE);
+
+        // leave half of CPUs to verification and p2p
+        let sync_server_threads = match self.cpus {
+            Some(cpus) => max(1, min(cpus / 2, MAX_SYNC_SERVER_THREADS)),
+            None => default.sync_server_threads,
+        };
+
+        // there's no point in downloading blocks faster than they are verified
+        let download_window = self.vdf_squarings_per_s * default.download_window as u64
+            / REFERENCE_VDF_SQUARINGS_PER_S;
+        let download_window = min(
+            max(download_window, MIN_DOWNLOAD_WINDOW as u64),
+            MAX_DOWNLOAD_WINDOW as u64,
+        ) as u32;
+
+        Tuning {
+            db_cache: db_cache,
+            sync_server_threads: sync_server_threads,
+            download_window: download_window,
+        }
+    }
+}
+
+/// Reads tuning file from the data dir. On first start the file is missing, so the host
+/// is benchmarked and derived defaults are written to the new file.
+pub fn load_or_generate(data_dir: &Option<String>) -> Result<Tuning, String> {
+    let path = tuning_path(data_dir);
+    match fs::read_to_string(&path) {
+        Ok(contents) => {
+            return Tuning::parse(&contents).map_err(|err| format!("{}: {}", path.display(), err))
+        }
+        Err(ref err) if err.kind() == io::ErrorKind::NotFound => (),
+        Err(err) => return Err(format!("Failed to read {}: {}", path.display(), err)),
+    }
+
+    info!(target: "randchaind", "Benchmarking host to tune defaults. This is done only once");
+    let dir = path.parent().unwrap_or_else(|| Path::new("."));
+    let benchmarks = Benchmarks::run(dir);
+    let tuning = benchmarks.tuning();
+    info!(target: "randchaind", "Host benchmarks: {:?}. Tuned defaults: {:?}", benchmarks, tuning);
+
+    // tuned defaults are still used, even if they can't be saved
+    if let Err(err) = fs::write(&path, tuning.to_file_contents(&benchmarks)) {
+        warn!(target: "randchaind", "Failed to write tuning file {}: {}", path.display(), err);
+    }
+    Ok(tuning)
+}
+
+fn disk_random_reads_per_s(dir: &Path) -> Result<u64, io::Error> {
+    let path = dir.join(DISK_BENCHMARK_FILE_NAME);
+    let result = measure_disk_random_reads(&path);
+    let _ = fs::remove_file(&path);
+    result
+}
+
+fn measure_disk_random_reads(path: &Path) -> Result<u64, io::Error> {
+    {
+        let mut file = File::create(path)?;
+        let chunk = vec![0xa5u8; 1024 * 1024];
+        for _ in 0..DISK_BENCHMARK_FILE_SIZE / chunk.len() as u64 {
+            file.write_all(&chunk)?;
+        }
+        file.sync_all()?;
+    }
+
+    let mut file = OpenOptions::new().read(true).open(path)?;
+    drop_page_cache(&file);
+
+    let mut buffer = vec![0u8; DISK_BENCHMARK_READ_SIZE];
+    let positions = DISK_BENCHMARK_FILE_SIZE / DISK_BENCHMARK_READ_SIZE as u64;
+    let mut seed = 0x2545_f491_4f6c_dd1du64;
+    let mut reads = 0u64;
+    let started_at = Instant::now();
+    let duration = Duration::from_millis(BENCHMARK_DURATION_MS);
+    while started_at.elapsed() < duration {
+        // xorshift is enough to defeat readahead
+        seed ^= seed << 13;
+        seed ^= seed >> 7;
+        seed ^= seed << 17;
+        let position = (seed % positions) * DISK_BENCHMARK_READ_SIZE as u64;
+        file.seek(SeekFrom::Start(position))?;
+        file.read_exact(&mut buffer)?;
+        reads += 1;
+    }
+
+    Ok(per_second(reads, started_at.elapsed()))
+}
+
+fn vdf_squarings_per_s() -> u64 {
+    let mut y = vdf::MODULUS.clone() >> 1u32;
+    let mut squarings = 0u64;
+    let started_at = Instant::now();
+    let duration = Duration::from_millis(BENCHMARK_DURATION_MS);
+    while started_at.elapsed() < duration {
+        y = vdf::eval(&y, VDF_BENCHMARK_STEP);
+        squarings += VDF_BENCHMARK_STEP as u64;
+    }
+
+    per_second(squarings, started_at.elapsed())
+}
+
+fn per_second(count: u64, elapsed: Duration) -> u64 {
+    let elapsed_us = max(elapsed.as_micros() as u64, 1);
+    count * 1_000_000 / elapsed_us
+}
+
+/// Asks OS to evict the file from the page cache, so that reads are served by the disk.
+#[cfg(target_os = "linux")]
+fn drop_page_cache(file: &File) {
+    use libc;
+    use std::os::unix::io::AsRawFd;
+
+    unsafe {
+        libc::posix_fadvise(file.as_raw_fd(), 0, 0, libc::POSIX_FADV_DONTNEED);
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn drop_page_cache(_file: &File) {}
+
+#[cfg(unix)]
+fn online_cpus() -> Option<usize> {
+    use libc;
+
+    match unsafe { libc::sysconf(libc::_SC_NPROCESSORS_ONLN) } {
+        cpus if cpus > 0 => Some(cpus as usize),
+        _ => None,
+    }
+}
+
+#[cfg(not(unix))]
+fn online_cpus() -> Option<usize> {
+    None
+}
+
+/// Returns size of the physical memory, in bytes.
+#[cfg(unix)]
+fn physical_memory() -> Option<u64> {
+    use libc;
+
+    let pages = unsafe { libc::sysconf(libc::_SC_PHYS_PAGES) };
+    let page_size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) };
+    match pages > 0 && page_size > 0 {
+        true => Some(pages as u64 * page_size as u64),
+        false => None,
+    }
+}
+
+#[cfg(not(unix))]
+fn physical_memory() -> Option<u64> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Benchmarks, Tuning};
+
+    #[test]
+    fn tuning_file_is_parsed() {
+        let tuning = Tuning::parse(
+            "
+            # comment
+            db_cache = 512
+            sync_server_threads = 2
+
+            download_window = 256
+            ",
+        )
+        .unwrap();
+        assert_eq!(
+            tuning,
+            Tuning {
+                db_cache: 512,
+                sync_server_threads: 2,
+                download_window: 256,
+            }
+        );
+    }
+
+    #[test]
+    fn tuning_file_missing_values_are_defaults() {
+        let tuning = Tuning::parse("sync_server_threads = 3").unwrap();
+        assert_eq!(
+            tuning,
+            Tuning {
+                sync_server_threads: 3,
+                ..Tuning::default()
+            }
+        );
+        assert_eq!(Tuning::parse("").unwrap(), Tuning::default());
+    }
+
+    #[test]
+    fn tuning_file_contents_are_parsed_back() {
+        let tuning = Tuning {
+            db_cache: 128,
+            sync_server_threads: 1,
+            download_window: 64,
+        };
+        let benchmarks = Benchmarks {
+            cpus: Some(2),
+            memory: None,
+            disk_reads_per_s: Some(500),
+            vdf_squarings_per_s: 100_000,
+        };
+        assert_eq!(
+            Tuning::parse(&tuning.to_file_contents(&benchmarks)),
+            Ok(tuning)
+        );
+    }
+
+    #[test]
+    fn malformed_tuning_file_is_rejected() {
+        assert_eq!(
+            Tuning::parse("db_cache"),
+            Err("Invalid tuning line 1: db_cache".to_owned())
+        );
+        assert_eq!(
+            Tuning::parse("# comment\ndb_cache = lots"),
+            Err("Invalid tuning line 2: db_cache = lots".to_owned())
+        );
+        assert!(Tuning::parse("db_cache = -1").is_err());
+        assert!(Tuning::parse("sync_server_threads = 0").is_err());
+        assert!(Tuning::parse("download_window = 0").is_err());
+        assert!(Tuning::parse("download_window = 1.5").is_err());
+        assert!(Tuning::parse("unknown = 1").is_err());
+    }
+}
@@ -1,21 +1,163 @@
-use app_dirs::{app_dir, AppDataType};
+use app_dirs::{app_dir, app_root, AppDataType};
+use chain::IndexedBlock;
 use config::Config;
-use db;
-use std::fs::create_dir_all;
+use db::{BlockFilesBackup, DatabaseBackend};
+use network::Network;
+use std::fs::{create_dir_all, remove_dir_all, rename};
 use std::path::PathBuf;
 use std::sync::Arc;
+use verification::{is_valid_proof_of_work, BlockVerifier};
 use {storage, APP_INFO};
 
-pub fn open_db(data_dir: &Option<String>, db_cache: usize) -> storage::SharedStore {
-    let db_path = match *data_dir {
+pub fn db_path(data_dir: &Option<String>) -> PathBuf {
+    match *data_dir {
         Some(ref data_dir) => custom_path(&data_dir, "db"),
         None => app_dir(AppDataType::UserData, &APP_INFO, "db").expect("Failed to get app dir"),
-    };
-    Arc::new(
-        db::BlockChainDatabase::open_at_path(db_path, db_cache).expect("Failed to open database"),
+    }
+}
+
+/// Directory, where blocks are backed up before destructive database operations.
+pub fn rollback_path(data_dir: &Option<String>) -> PathBuf {
+    match *data_dir {
+        Some(ref data_dir) => custom_path(&data_dir, "rollback"),
+        None => {
+            app_dir(AppDataType::UserData, &APP_INFO, "rollback").expect("Failed to get app dir")
+        }
+    }
+}
+
+/// Opens the database. If `backup_reorg_depth` is set, blocks are backed up to the
+/// rollback directory before pruning and before reorganizations of at least this depth.
+pub fn open_db(
+    data_dir: &Option<String>,
+    backend: DatabaseBackend,
+    db_cache: usize,
+    block_compression: bool,
+    chain_events_retention_s: Option<u32>,
+    backup_reorg_depth: Option<u32>,
+) -> Result<storage::SharedStore, String> {
+    let backup_hook = backup_reorg_depth.map(|_| {
+        Arc::new(BlockFilesBackup::new(rollback_path(data_dir))) as storage::BackupHookRef
+    });
+    backend
+        .open(
+            db_path(data_dir),
+            db_cache,
+            block_compression,
+            chain_events_retention_s,
+            backup_hook,
+            backup_reorg_depth.unwrap_or_default(),
+        )
+        .map_err(|err| format!("Failed to open database: {:?}", err))
+}
+
+/// Removes the database directory and opens an empty database in its place.
+/// The database must not be opened elsewhere.
+pub fn reinit_db(
+    data_dir: &Option<String>,
+    backend: DatabaseBackend,
+    db_cache: usize,
+    block_compression: bool,
+    chain_events_retention_s: Option<u32>,
+    backup_reorg_depth: Option<u32>,
+) -> Result<storage::SharedStore, String> {
+    let path = db_path(data_dir);
+    remove_dir_all(&path)
+        .map_err(|err| format!("Failed to remove database at {}: {}", path.display(), err))?;
+    open_db(
+        data_dir,
+        backend,
+        db_cache,
+        block_compression,
+        chain_events_retention_s,
+        backup_reorg_depth,
     )
 }
 
+/// Moves the database aside, so that its blocks can be reindexed into the new database.
+/// If the previous reindex has been interrupted, its partial result is removed and the
+/// database, which has been moved aside then, is reindexed again.
+/// Returns path of the database to reindex. The database must not be opened elsewhere.
+pub fn prepare_reindex(data_dir: &Option<String>) -> Result<PathBuf, String> {
+    let path = db_path(data_dir);
+    let reindex_path = path.with_file_name("db-reindex");
+    if reindex_path.exists() {
+        warn!(
+            "Resuming interrupted reindex of database at {}",
+            reindex_path.display()
+        );
+        remove_dir_all(&path)
+            .map_err(|err| format!("Failed to remove database at {}: {}", path.display(), err))?;
+    } else {
+        rename(&path, &reindex_path).map_err(|err| {
+            format!(
+                "Failed to move database {} to {}: {}",
+                path.display(),
+                reindex_path.display(),
+                err
+            )
+        })?;
+    }
+    Ok(reindex_path)
+}
+
+/// Moves the database of the node aside with `prepare_reindex` and opens an empty database in
+/// its place. Returns path of the database to reindex. The database must not be opened elsewhere.
+pub fn reopen_db_for_reindex(cfg: &mut Config) -> Result<PathBuf, String> {
+    // database is closed when the last reference is dropped => temporarily replace it with
+    // the in-memory one
+    cfg.db = DatabaseBackend::Memory
+        .open(db_path(&cfg.data_dir), 0, false, None, None, 0)
+        .map_err(|err| format!("Failed to open database: {:?}", err))?;
+    let reindex_from = prepare_reindex(&cfg.data_dir)?;
+    cfg.db = open_db(
+        &cfg.data_dir,
+        cfg.db_backend,
+        cfg.db_cache,
+        cfg.db_block_compression,
+        cfg.chain_events_retention,
+        cfg.backup_reorg_depth,
+    )?;
+    Ok(reindex_from)
+}
+
+/// Checks that genesis block of the database (if any) is the genesis block of the network.
+pub fn check_genesis(db: &storage::SharedStore, network: Network) -> Result<(), String> {
+    let genesis_block = network.genesis_block();
+    match db.block_hash(0) {
+        Some(ref db_genesis_block_hash) if db_genesis_block_hash != genesis_block.hash() => {
+            Err(format!(
+                "Database genesis block {} does not match genesis block {} of the {:?} network",
+                db_genesis_block_hash.to_reversed_str(),
+                genesis_block.hash().to_reversed_str(),
+                network
+            ))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Verifies proof-of-work and VDF proof of the genesis block.
+fn verify_genesis(genesis_block: &IndexedBlock, network: Network) -> Result<(), String> {
+    let invalid = |reason: String| {
+        format!(
+            "Invalid genesis block {}: {}",
+            genesis_block.hash().to_reversed_str(),
+            reason
+        )
+    };
+    if !is_valid_proof_of_work(
+        network.max_bits().into(),
+        genesis_block.header.raw.bits,
+        genesis_block.hash(),
+    ) {
+        return Err(invalid("hash does not satisfy bits".into()));
+    }
+    BlockVerifier::new(genesis_block, network)
+        .check()
+        .map_err(|err| invalid(format!("{:?}", err)))
+}
+
 pub fn node_table_path(cfg: &Config) -> PathBuf {
     let mut node_table = match cfg.data_dir {
         Some(ref data_dir) => custom_path(&data_dir, "p2p"),
@@ -25,25 +167,77 @@ pub fn node_table_path(cfg: &Config) -> PathBuf {
     node_table
 }
 
+pub fn rpc_cookie_dir(data_dir: &Option<String>) -> PathBuf {
+    match *data_dir {
+        Some(ref data_dir) => custom_path(&data_dir, "rpc"),
+        None => app_dir(AppDataType::UserData, &APP_INFO, "rpc").expect("Failed to get app dir"),
+    }
+}
+
+/// Path of the file with defaults, tuned on first start.
+pub fn tuning_path(data_dir: &Option<String>) -> PathBuf {
+    let mut path = match *data_dir {
+        Some(ref data_dir) => custom_path(&data_dir, ""),
+        None => app_root(AppDataType::UserData, &APP_INFO).expect("Failed to get app dir"),
+    };
+    path.push("tuning.toml");
+    path
+}
+
 pub fn init_db(cfg: &Config) -> Result<(), String> {
-    // insert genesis block if db is empty
-    let genesis_block = cfg.network.genesis_block();
-    match cfg.db.block_hash(0) {
-        Some(ref db_genesis_block_hash) if db_genesis_block_hash != genesis_block.hash() => {
-            Err("Trying to open database with incompatible genesis block".into())
-        }
-        Some(_) => Ok(()),
-        None => {
-            let hash = genesis_block.hash().clone();
-            cfg.db
-                .insert(genesis_block)
-                .expect("Failed to insert genesis block to the database");
-            cfg.db
-                .canonize(&hash)
-                .expect("Failed to canonize genesis block");
-            Ok(())
+    init_store(&cfg.db, cfg.network)?;
+    if cfg.miner_index {
+        let indexed = cfg
+            .db
+            .enable_miner_index()
+            .map_err(|err| format!("Failed to build miner index: {:?}", err))?;
+        if indexed != 0 {
+            info!("{} blocks are indexed by miner", indexed);
         }
     }
+    Ok(())
+}
+
+/// Checks genesis block of the database and inserts it if the database is empty.
+pub fn init_store(db: &storage::SharedStore, network: Network) -> Result<(), String> {
+    check_genesis(db, network)?;
+
+    // insert genesis block if db is empty
+    let genesis_block = network.genesis_block();
+    let genesis_hash = genesis_block.hash().clone();
+    // genesis blocks of custom networks are user-supplied, so they are verified before
+    // they are written
+    if db.block_hash(0).is_none() && network.custom().is_some() {
+        verify_genesis(&genesis_block, network)?;
+    }
+    if db
+        .init_genesis(genesis_block)
+        .map_err(|err| format!("Failed to write genesis block: {}", err))?
+    {
+        info!(
+            "Genesis block {} is written to the database",
+            genesis_hash.to_reversed_str()
+        );
+    }
+
+    // children of blocks, stored by previous versions, are not indexed
+    if !db.block_children_indexed() {
+        info!("Indexing children of stored blocks. It could take a while");
+        let updated = db
+            .index_block_children()
+            .map_err(|err| format!("Failed to index children of blocks: {:?}", err))?;
+        info!("Children of {} blocks are indexed", updated);
+    }
+
+    // chain work of blocks, stored by previous versions, is unknown
+    if !db.chain_work_computed() {
+        info!("Computing chain work of stored blocks. It could take a while");
+        let updated = db
+            .recompute_chain_work()
+            .map_err(|err| format!("Failed to compute chain work: {:?}", err))?;
+        info!("Chain work of {} blocks is computed", updated);
+    }
+    Ok(())
 }
 
 fn custom_path(data_dir: &str, sub_dir: &str) -> PathBuf {
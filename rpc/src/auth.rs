@@ -0,0 +1,593 @@
+//! RPC authentication and method permissions.
+//!
+//! Every RPC method belongs to one or more `MethodGroup`s. Callers authenticate with HTTP basic
+//! auth (either configured user/password pairs or the generated cookie) and are allowed to call
+//! only the methods from the groups granted to their identity, directly or with an `RpcRole`.
+
+use hex::ToHex;
+use jsonrpc_core::futures::future::{self, Either};
+use jsonrpc_core::{Call, FutureResponse, Metadata, Middleware, Output, Request, Response};
+use jsonrpc_http_server::hyper::header::{Authorization, Basic};
+use jsonrpc_http_server::{hyper, MetaExtractor};
+use rand::{thread_rng, RngCore};
+use std::collections::HashSet;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::{Arc, RwLock};
+use v1::helpers::errors;
+
+/// Name of the cookie file, stored in the data directory.
+pub const COOKIE_FILE_NAME: &'static str = ".cookie";
+/// User name of the cookie identity.
+pub const COOKIE_USER: &'static str = "__cookie__";
+
+/// Group of RPC methods sharing the same permission.
+#[derive(Debug, PartialEq, Eq, Hash, Copy, Clone)]
+pub enum MethodGroup {
+    /// Read-only methods.
+    Public,
+    /// Read-only methods, serving canon chain blocks and their randomness. Subset of `Public`.
+    Beacon,
+    /// Mining-related methods.
+    Miner,
+    /// Node administration methods.
+    Admin,
+}
+
+impl MethodGroup {
+    /// Returns groups of given method. Unknown methods are treated as administrative.
+    pub fn of_method(method: &str) -> &'static [MethodGroup] {
+        match method {
+            "getbestblockhash"
+            | "getblockcount"
+            | "getblockhash"
+            | "getblocklocator"
+            | "getdifficulty"
+            | "getblock"
+            | "getblockheaders"
+            | "listblockheaders"
+            | "getblockheaderbyheight"
+            | "getblockchaininfo"
+            | "listchainevents"
+            | "getfinalizedblock"
+            | "getbestblockat"
+            | "getbestblockatevent" => &[MethodGroup::Public, MethodGroup::Beacon],
+            "getblockstats"
+            | "getpruneheight"
+            | "getstaleblocks"
+            | "getblockannouncements"
+            | "getblockpropagationstats"
+            | "getminerblocks"
+            | "getorphanblocks"
+            | "getmemoryinfo"
+            | "getserverlogstats"
+            | "getaddednodeinfo"
+            | "getconnectioncount"
+            | "getexperimentstats"
+            | "getpeerversions"
+            | "getpeerpolicystats"
+            | "getcompressionstats"
+            | "getnetworkinfo"
+            | "getpeerinfo" => &[MethodGroup::Public],
+            "getblocktemplate" | "submitblock" | "generate" | "getmininginfo" => {
+                &[MethodGroup::Miner]
+            }
+            // "addnode", "stop", "invalidateblock", "reconsiderblock", "rollbacklastreorg", "setserverlogsampling", "reloadconfig", "getrpcinfo", "verifychain" (it is CPU-heavy),
+            // "getdbinfo" (it scans the whole database), "compactdb",
+            // mining threads control
+            // ("setminingthreads", "pausemining", "resumemining") and everything not listed above
+            _ => &[MethodGroup::Admin],
+        }
+    }
+
+    /// Returns set of all groups.
+    pub fn all() -> HashSet<MethodGroup> {
+        vec![
+            MethodGroup::Public,
+            MethodGroup::Beacon,
+            MethodGroup::Miner,
+            MethodGroup::Admin,
+        ]
+        .into_iter()
+        .collect()
+    }
+}
+
+impl FromStr for MethodGroup {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "public" => Ok(MethodGroup::Public),
+            "beacon" => Ok(MethodGroup::Beacon),
+            "miner" => Ok(MethodGroup::Miner),
+            "admin" => Ok(MethodGroup::Admin),
+            group => Err(format!("Unknown method group: {}", group)),
+        }
+    }
+}
+
+/// Typical set of method groups, granted to a credential of the shared node.
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum RpcRole {
+    /// May call all read-only methods.
+    ReadOnly,
+    /// May only read canon chain blocks and their randomness.
+    BeaconConsumer,
+    /// May call read-only and node administration methods, but not mining methods.
+    Operator,
+    /// May call read-only and mining methods, but not node administration methods.
+    MinerAdmin,
+}
+
+impl RpcRole {
+    /// Returns method groups of the role.
+    pub fn groups(&self) -> HashSet<MethodGroup> {
+        let groups = match *self {
+            RpcRole::ReadOnly => vec![MethodGroup::Public],
+            RpcRole::BeaconConsumer => vec![MethodGroup::Beacon],
+            RpcRole::Operator => vec![MethodGroup::Public, MethodGroup::Admin],
+            RpcRole::MinerAdmin => vec![MethodGroup::Public, MethodGroup::Miner],
+        };
+        groups.into_iter().collect()
+    }
+}
+
+impl FromStr for RpcRole {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "read-only" => Ok(RpcRole::ReadOnly),
+            "beacon-consumer" => Ok(RpcRole::BeaconConsumer),
+            "operator" => Ok(RpcRole::Operator),
+            "miner-admin" => Ok(RpcRole::MinerAdmin),
+            role => Err(format!("Unknown RPC role: {}", role)),
+        }
+    }
+}
+
+/// Authenticated RPC identity.
+#[derive(Debug, PartialEq, Clone)]
+pub struct RpcUser {
+    pub name: String,
+    pub password: String,
+    /// Method groups this user is allowed to call.
+    pub groups: HashSet<MethodGroup>,
+}
+
+impl FromStr for RpcUser {
+    type Err = String;
+
+    /// Parses `USER:PASSWORD[:PERMISSION,PERMISSION,...]`, where every permission is either
+    /// a role or a method group. When no permissions are given, all groups are allowed.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = s.splitn(3, ':');
+        let name = match parts.next() {
+            Some(name) if !name.is_empty() => name.to_owned(),
+            _ => return Err(format!("Invalid RPC user: {}", s)),
+        };
+        let password = match parts.next() {
+            Some(password) if !password.is_empty() => password.to_owned(),
+            _ => return Err(format!("Missing password for RPC user: {}", name)),
+        };
+        let groups = match parts.next() {
+            Some(permissions) => {
+                let mut groups = HashSet::new();
+                for permission in permissions.split(',') {
+                    match permission.parse::<RpcRole>() {
+                        Ok(role) => groups.extend(role.groups()),
+                        Err(_) => {
+                            groups.insert(permission.parse::<MethodGroup>().map_err(|_| {
+                                format!("Unknown RPC role or method group: {}", permission)
+                            })?);
+                        }
+                    }
+                }
+                groups
+            }
+            None => MethodGroup::all(),
+        };
+
+        Ok(RpcUser {
+            name: name,
+            password: password,
+            groups: groups,
+        })
+    }
+}
+
+impl RpcUser {
+    /// Creates identity with random password, which is allowed to call any method.
+    pub fn random_cookie() -> Self {
+        let mut secret = [0u8; 32];
+        thread_rng().fill_bytes(&mut secret);
+        RpcUser {
+            name: COOKIE_USER.into(),
+            password: secret.to_hex(),
+            groups: MethodGroup::all(),
+        }
+    }
+
+    /// Writes `USER:PASSWORD` to the cookie file in given directory.
+    pub fn write_cookie(&self, dir: &Path) -> Result<PathBuf, io::Error> {
+        let mut path = dir.to_path_buf();
+        path.push(COOKIE_FILE_NAME);
+        let mut file = create_cookie_file(&path)?;
+        file.write_all(format!("{}:{}", self.name, self.password).as_bytes())?;
+        Ok(path)
+    }
+}
+
+/// Creates (or truncates) the cookie file, readable by the owner only.
+#[cfg(unix)]
+fn create_cookie_file(path: &Path) -> Result<fs::File, io::Error> {
+    use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+
+    let file = fs::OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .mode(0o600)
+        .open(path)?;
+    // mode is only applied to the new file => also restrict the file, left by the previous run
+    file.set_permissions(fs::Permissions::from_mode(0o600))?;
+    Ok(file)
+}
+
+#[cfg(not(unix))]
+fn create_cookie_file(path: &Path) -> Result<fs::File, io::Error> {
+    fs::File::create(path)
+}
+
+/// Request metadata, filled by `Authenticator`.
+#[derive(Debug, Clone, Default)]
+pub struct Meta {
+    /// Method groups the caller is allowed to use. `None` if authentication is disabled.
+    pub groups: Option<HashSet<MethodGroup>>,
+}
+
+impl Metadata for Meta {}
+
+impl Meta {
+    /// Returns true if caller is allowed to call given method.
+    pub fn is_allowed(&self, method: &str) -> bool {
+        match self.groups {
+            Some(ref groups) => MethodGroup::of_method(method)
+                .iter()
+                .any(|group| groups.contains(group)),
+            None => true,
+        }
+    }
+}
+
+/// Reads credentials of incoming HTTP requests. Clones are sharing the same set of users.
+#[derive(Debug, Default, Clone)]
+pub struct Authenticator {
+    users: Arc<RwLock<Vec<RpcUser>>>,
+}
+
+impl Authenticator {
+    pub fn new(users: Vec<RpcUser>) -> Self {
+        Authenticator {
+            users: Arc::new(RwLock::new(users)),
+        }
+    }
+
+    /// Replaces configured users at runtime. The cookie identity, if any, is kept.
+    pub fn set_users(&self, users: Vec<RpcUser>) {
+        let mut current = self.users.write().expect("users lock is never poisoned");
+        let cookie: Vec<_> = current
+            .iter()
+            .filter(|user| user.name == COOKIE_USER)
+            .cloned()
+            .collect();
+        *current = users.into_iter().chain(cookie).collect();
+    }
+
+    /// Authentication is enabled when at least one user is configured.
+    pub fn is_enabled(&self) -> bool {
+        !self
+            .users
+            .read()
+            .expect("users lock is never poisoned")
+            .is_empty()
+    }
+
+    /// Returns user with given credentials.
+    pub fn authenticate(&self, name: &str, password: &str) -> Option<RpcUser> {
+        self.users
+            .read()
+            .expect("users lock is never poisoned")
+            .iter()
+            .find(|user| user.name == name && constant_time_eq(&user.password, password))
+            .cloned()
+    }
+
+    /// Returns metadata for the caller with given credentials.
+    pub fn meta(&self, name: &str, password: &str) -> Meta {
+        if !self.is_enabled() {
+            return Meta::default();
+        }
+
+        Meta {
+            groups: Some(
+                self.authenticate(name, password)
+                    .map(|user| user.groups)
+                    .unwrap_or_default(),
+            ),
+        }
+    }
+}
+
+impl MetaExtractor<Meta> for Authenticator {
+    fn read_metadata(&self, req: &hyper::Request) -> Meta {
+        match req.headers().get::<Authorization<Basic>>() {
+            Some(auth) => self.meta(
+                &auth.username,
+                auth.password.as_ref().map(String::as_str).unwrap_or(""),
+            ),
+            None => self.meta("", ""),
+        }
+    }
+}
+
+/// Rejects calls to methods which are not allowed for the caller.
+#[derive(Debug, Default)]
+pub struct PermissionsMiddleware;
+
+impl Middleware<Meta> for PermissionsMiddleware {
+    type Future = FutureResponse;
+
+    fn on_request<F, X>(&self, request: Request, meta: Meta, next: F) -> Either<Self::Future, X>
+    where
+        F: FnOnce(Request, Meta) -> X + Send,
+        X: future::Future<Item = Option<Response>, Error = ()> + Send + 'static,
+    {
+        let is_allowed = match request {
+            Request::Single(ref call) => is_call_allowed(call, &meta),
+            Request::Batch(ref calls) => calls.iter().all(|call| is_call_allowed(call, &meta)),
+        };
+
+        if is_allowed {
+            return Either::B(next(request, meta));
+        }
+
+        let response = match request {
+            Request::Single(call) => deny_call(call).map(Response::Single),
+            Request::Batch(calls) => Some(Response::Batch(
+                calls.into_iter().filter_map(deny_call).collect(),
+            )),
+        };
+        Either::A(Box::new(future::ok(response)))
+    }
+}
+
+fn is_call_allowed(call: &Call, meta: &Meta) -> bool {
+    match *call {
+        Call::MethodCall(ref call) => meta.is_allowed(&call.method),
+        Call::Notification(ref notification) => meta.is_allowed(&notification.method),
+        Call::Invalid(_) => true,
+    }
+}
+
+fn deny_call(call: Call) -> Option<Output> {
+    match call {
+        Call::MethodCall(call) => Some(Output::from(
+            Err(errors::access_denied(&call.method)),
+            call.id,
+            call.jsonrpc,
+        )),
+        Call::Notification(_) | Call::Invalid(_) => None,
+    }
+}
+
+/// Compares strings in time independent of the position of the first mismatch.
+fn constant_time_eq(a: &str, b: &str) -> bool {
+    let (a, b) = (a.as_bytes(), b.as_bytes());
+    if a.len() != b.len() {
+        return false;
+    }
+
+    a.iter()
+        .zip(b.iter())
+        .fold(0u8, |acc, (x, y)| acc | (x ^ y))
+        == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Authenticator, MethodGroup, RpcRole, RpcUser, COOKIE_FILE_NAME, COOKIE_USER};
+
+    #[test]
+    fn method_groups() {
+        assert_eq!(
+            MethodGroup::of_method("getblockcount"),
+            &[MethodGroup::Public, MethodGroup::Beacon]
+        );
+        assert_eq!(
+            MethodGroup::of_method("getblockheaderbyheight"),
+            &[MethodGroup::Public, MethodGroup::Beacon]
+        );
+        assert_eq!(
+            MethodGroup::of_method("getpeerversions"),
+            &[MethodGroup::Public]
+        );
+        assert_eq!(
+            MethodGroup::of_method("getpeerpolicystats"),
+            &[MethodGroup::Public]
+        );
+        assert_eq!(
+            MethodGroup::of_method("getnetworkinfo"),
+            &[MethodGroup::Public]
+        );
+        assert_eq!(
+            MethodGroup::of_method("getmemoryinfo"),
+            &[MethodGroup::Public]
+        );
+        assert_eq!(
+            MethodGroup::of_method("getblockpropagationstats"),
+            &[MethodGroup::Public]
+        );
+        assert_eq!(MethodGroup::of_method("submitblock"), &[MethodGroup::Miner]);
+        assert_eq!(MethodGroup::of_method("generate"), &[MethodGroup::Miner]);
+        assert_eq!(
+            MethodGroup::of_method("listblockheaders"),
+            &[MethodGroup::Public, MethodGroup::Beacon]
+        );
+        assert_eq!(
+            MethodGroup::of_method("getmininginfo"),
+            &[MethodGroup::Miner]
+        );
+        assert_eq!(MethodGroup::of_method("addnode"), &[MethodGroup::Admin]);
+        assert_eq!(MethodGroup::of_method("stop"), &[MethodGroup::Admin]);
+        assert_eq!(MethodGroup::of_method("getrpcinfo"), &[MethodGroup::Admin]);
+        assert_eq!(MethodGroup::of_method("getdbinfo"), &[MethodGroup::Admin]);
+        assert_eq!(MethodGroup::of_method("compactdb"), &[MethodGroup::Admin]);
+        assert_eq!(
+            MethodGroup::of_method("rollbacklastreorg"),
+            &[MethodGroup::Admin]
+        );
+        assert_eq!(MethodGroup::of_method("pausemining"), &[MethodGroup::Admin]);
+        assert_eq!(
+            MethodGroup::of_method("unknownmethod"),
+            &[MethodGroup::Admin]
+        );
+    }
+
+    #[test]
+    fn parse_rpc_user() {
+        let user: RpcUser = "alice:secret".parse().unwrap();
+        assert_eq!(user.name, "alice");
+        assert_eq!(user.password, "secret");
+        assert_eq!(user.groups, MethodGroup::all());
+
+        let user: RpcUser = "bob:secret:public,miner".parse().unwrap();
+        assert_eq!(
+            user.groups,
+            vec![MethodGroup::Public, MethodGroup::Miner]
+                .into_iter()
+                .collect()
+        );
+
+        let user: RpcUser = "carol:secret:beacon-consumer,miner".parse().unwrap();
+        assert_eq!(
+            user.groups,
+            vec![MethodGroup::Beacon, MethodGroup::Miner]
+                .into_iter()
+                .collect()
+        );
+
+        assert!("bob".parse::<RpcUser>().is_err());
+        assert!("bob:".parse::<RpcUser>().is_err());
+        assert!("bob:secret:root".parse::<RpcUser>().is_err());
+    }
+
+    #[test]
+    fn role_permissions() {
+        let authenticator = Authenticator::new(vec![
+            "reader:secret:read-only".parse().unwrap(),
+            "beacon:secret:beacon-consumer".parse().unwrap(),
+            "operator:secret:operator".parse().unwrap(),
+            "miner:secret:miner-admin".parse().unwrap(),
+        ]);
+        assert_eq!("operator".parse(), Ok(RpcRole::Operator));
+
+        let reader = authenticator.meta("reader", "secret");
+        assert!(reader.is_allowed("getblock"));
+        assert!(reader.is_allowed("getpeerversions"));
+        assert!(reader.is_allowed("getminerblocks"));
+        assert!(!reader.is_allowed("submitblock"));
+        assert!(!reader.is_allowed("addnode"));
+
+        let beacon = authenticator.meta("beacon", "secret");
+        assert!(beacon.is_allowed("getblock"));
+        assert!(beacon.is_allowed("listchainevents"));
+        assert!(!beacon.is_allowed("getpeerversions"));
+        assert!(!beacon.is_allowed("getblocktemplate"));
+        assert!(!beacon.is_allowed("stop"));
+
+        let operator = authenticator.meta("operator", "secret");
+        assert!(operator.is_allowed("getblock"));
+        assert!(operator.is_allowed("invalidateblock"));
+        assert!(!operator.is_allowed("submitblock"));
+
+        let miner = authenticator.meta("miner", "secret");
+        assert!(miner.is_allowed("getblocktemplate"));
+        assert!(miner.is_allowed("getblockcount"));
+        assert!(!miner.is_allowed("addnode"));
+    }
+
+    #[test]
+    fn authenticator_permissions() {
+        let authenticator = Authenticator::new(vec![
+            "alice:secret".parse().unwrap(),
+            "bob:secret:public".parse().unwrap(),
+        ]);
+
+        let alice = authenticator.meta("alice", "secret");
+        assert!(alice.is_allowed("getblockcount"));
+        assert!(alice.is_allowed("addnode"));
+
+        let bob = authenticator.meta("bob", "secret");
+        assert!(bob.is_allowed("getblockcount"));
+        assert!(!bob.is_allowed("getblocktemplate"));
+        assert!(!bob.is_allowed("addnode"));
+
+        let anonymous = authenticator.meta("alice", "wrong");
+        assert!(!anonymous.is_allowed("getblockcount"));
+    }
+
+    #[test]
+    fn authenticator_users_are_replaced() {
+        let cookie = RpcUser::random_cookie();
+        let authenticator =
+            Authenticator::new(vec!["alice:secret".parse().unwrap(), cookie.clone()]);
+        let shared = authenticator.clone();
+
+        shared.set_users(vec!["bob:secret:public".parse().unwrap()]);
+        assert!(!authenticator
+            .meta("alice", "secret")
+            .is_allowed("getblockcount"));
+        assert!(authenticator
+            .meta("bob", "secret")
+            .is_allowed("getblockcount"));
+        assert!(!authenticator.meta("bob", "secret").is_allowed("addnode"));
+        // cookie identity survives reload
+        assert!(authenticator
+            .meta(COOKIE_USER, &cookie.password)
+            .is_allowed("addnode"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn cookie_file_is_readable_by_owner_only() {
+        use std::os::unix::fs::PermissionsExt;
+        use std::{env, fs, process};
+
+        let dir = env::temp_dir().join(format!("randchain-cookie-{}", process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        // cookie file, left by the previous run, is readable by everyone
+        let stale = dir.join(COOKIE_FILE_NAME);
+        fs::write(&stale, "stale").unwrap();
+        fs::set_permissions(&stale, fs::Permissions::from_mode(0o644)).unwrap();
+
+        let cookie = RpcUser::random_cookie();
+        let path = cookie.write_cookie(&dir).unwrap();
+        let mode = fs::metadata(&path).unwrap().permissions().mode();
+        let contents = fs::read_to_string(&path).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(mode & 0o777, 0o600);
+        assert_eq!(contents, format!("{}:{}", COOKIE_USER, cookie.password));
+    }
+
+    #[test]
+    fn disabled_authenticator_allows_everything() {
+        let authenticator = Authenticator::default();
+        assert!(!authenticator.is_enabled());
+        assert!(authenticator.meta("", "").is_allowed("addnode"));
+    }
+}
@@ -1,10 +1,13 @@
+#[macro_use]
 extern crate log;
+extern crate rand;
 extern crate rug;
 extern crate rustc_hex as hex;
 extern crate serde;
 extern crate serde_json;
 #[macro_use]
 extern crate serde_derive;
+extern crate futures_cpupool;
 extern crate jsonrpc_core;
 #[macro_use]
 extern crate jsonrpc_macros;
@@ -21,11 +24,17 @@ extern crate sync;
 extern crate tokio_core;
 extern crate verification;
 
+pub mod auth;
 pub mod rpc_server;
+pub mod stats;
+pub mod status;
 pub mod v1;
 
 pub use jsonrpc_core::{Compatibility, Error, MetaIoHandler};
 pub use jsonrpc_http_server::tokio_core::reactor::Remote;
 
+pub use auth::{Authenticator, Meta, MethodGroup, PermissionsMiddleware, RpcRole, RpcUser};
 pub use jsonrpc_http_server::Server;
 pub use rpc_server::start_http;
+pub use stats::{RpcMiddleware, RpcStats, StatsMiddleware};
+pub use status::StatusPage;
@@ -1,16 +1,26 @@
 // TODO: panic handler
 use jsonrpc_core;
-use jsonrpc_http_server::{self, Host, Server, ServerBuilder};
+use jsonrpc_http_server::{self, Host, MetaExtractor, Server, ServerBuilder};
+use status::StatusPage;
 use std::io;
 use std::net::SocketAddr;
 
 /// Start http server asynchronously and returns result with `Server` handle on success or an error.
-pub fn start_http<M: jsonrpc_core::Metadata>(
+/// Request metadata (e.g. caller credentials) is read by `extractor`. Human-readable status page
+/// is served next to JSON-RPC, if `status_page` is given.
+pub fn start_http<M, S, E>(
     addr: &SocketAddr,
     cors_domains: Option<Vec<String>>,
     allowed_hosts: Option<Vec<String>>,
-    handler: jsonrpc_core::MetaIoHandler<M>,
-) -> Result<Server, io::Error> {
+    handler: jsonrpc_core::MetaIoHandler<M, S>,
+    extractor: E,
+    status_page: Option<StatusPage>,
+) -> Result<Server, io::Error>
+where
+    M: jsonrpc_core::Metadata,
+    S: jsonrpc_core::Middleware<M>,
+    E: MetaExtractor<M>,
+{
     let cors_domains = cors_domains.map(|domains| {
         domains
             .into_iter()
@@ -22,12 +32,16 @@ pub fn start_http<M: jsonrpc_core::Metadata>(
             .collect()
     });
 
-    ServerBuilder::new(handler)
+    let mut builder = ServerBuilder::new(handler)
+        .meta_extractor(extractor)
         .cors(cors_domains.into())
         .allowed_hosts(
             allowed_hosts
                 .map(|hosts| hosts.into_iter().map(Host::from).collect())
                 .into(),
-        )
-        .start_http(addr)
+        );
+    if let Some(status_page) = status_page {
+        builder = builder.request_middleware(status_page);
+    }
+    builder.start_http(addr)
 }
@@ -0,0 +1,268 @@
+//! RPC server statistics.
+//!
+//! `StatsMiddleware` counts calls and errors of every method and collects histogram of call
+//! latencies. Calls, which are slower than the configured threshold, are logged. Parameters of
+//! slow calls are never logged, because they could contain secrets (e.g. node addresses).
+
+use auth::{Meta, PermissionsMiddleware};
+use jsonrpc_core::futures::future::{self, Either};
+use jsonrpc_core::futures::Future;
+use jsonrpc_core::{Call, FutureResponse, Id, Middleware, Output, Request, Response};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Upper bounds of latency histogram buckets, in milliseconds. Slower calls are counted
+/// in the last bucket.
+pub const LATENCY_BUCKETS_MS: [u64; 5] = [1, 10, 100, 1_000, 10_000];
+/// Number of latency histogram buckets.
+pub const NUM_LATENCY_BUCKETS: usize = 6;
+/// Max number of methods with separate statistics. Calls of other (e.g. unknown) methods are
+/// accounted under `OTHER_METHODS`, so that callers can't grow statistics without bound.
+const MAX_TRACKED_METHODS: usize = 256;
+/// Name, under which calls of untracked methods are accounted.
+pub const OTHER_METHODS: &'static str = "<other>";
+
+/// Middleware of the node RPC server: checks permissions and collects statistics.
+pub type RpcMiddleware = StatsMiddleware<PermissionsMiddleware>;
+
+/// Statistics of single RPC method calls.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MethodStats {
+    /// Number of calls
+    pub calls: u64,
+    /// Number of calls, which have failed
+    pub errors: u64,
+    /// Total time of calls, in microseconds
+    pub total_time_us: u64,
+    /// Time of the slowest call, in microseconds
+    pub max_time_us: u64,
+    /// Number of calls by latency bucket (see `LATENCY_BUCKETS_MS`)
+    pub latency_histogram: [u64; NUM_LATENCY_BUCKETS],
+}
+
+/// Statistics of RPC method calls since node start. Clones are sharing the same statistics.
+#[derive(Debug, Clone, Default)]
+pub struct RpcStats {
+    /// Calls, slower than this threshold, are logged
+    slow_call_threshold: Option<Duration>,
+    /// Statistics by method name
+    methods: Arc<Mutex<HashMap<String, MethodStats>>>,
+}
+
+impl MethodStats {
+    fn record(&mut self, elapsed: Duration, is_error: bool) {
+        let elapsed_us = elapsed.as_micros() as u64;
+        let elapsed_ms = elapsed_us / 1_000;
+        let bucket = LATENCY_BUCKETS_MS
+            .iter()
+            .position(|bound| elapsed_ms < *bound)
+            .unwrap_or(LATENCY_BUCKETS_MS.len());
+
+        self.calls += 1;
+        if is_error {
+            self.errors += 1;
+        }
+        self.total_time_us = self.total_time_us.saturating_add(elapsed_us);
+        if elapsed_us > self.max_time_us {
+            self.max_time_us = elapsed_us;
+        }
+        self.latency_histogram[bucket] += 1;
+    }
+}
+
+impl RpcStats {
+    pub fn new(slow_call_threshold: Option<Duration>) -> Self {
+        RpcStats {
+            slow_call_threshold: slow_call_threshold,
+            methods: Default::default(),
+        }
+    }
+
+    /// Calls, slower than this threshold, are logged
+    pub fn slow_call_threshold(&self) -> Option<Duration> {
+        self.slow_call_threshold
+    }
+
+    /// Record completed call of the method
+    pub fn record(&self, method: &str, elapsed: Duration, is_error: bool) {
+        if let Some(threshold) = self.slow_call_threshold {
+            if elapsed >= threshold {
+                warn!(target: "rpc", "Slow RPC call {}(<redacted>) has taken {} ms{}",
+                    method, elapsed.as_millis(), if is_error { " and failed" } else { "" });
+            }
+        }
+
+        let mut methods = self.methods.lock().expect("stats lock is never poisoned");
+        let method = match methods.contains_key(method) || methods.len() < MAX_TRACKED_METHODS {
+            true => method,
+            false => OTHER_METHODS,
+        };
+        methods
+            .entry(method.to_owned())
+            .or_insert_with(MethodStats::default)
+            .record(elapsed, is_error);
+    }
+
+    /// Statistics of all called methods, ordered by method name
+    pub fn methods(&self) -> Vec<(String, MethodStats)> {
+        let mut methods: Vec<_> = self
+            .methods
+            .lock()
+            .expect("stats lock is never poisoned")
+            .iter()
+            .map(|(method, stats)| (method.clone(), stats.clone()))
+            .collect();
+        methods.sort_by(|a, b| a.0.cmp(&b.0));
+        methods
+    }
+}
+
+/// Collects statistics of calls, passed to the inner middleware.
+#[derive(Debug)]
+pub struct StatsMiddleware<T> {
+    inner: T,
+    stats: RpcStats,
+}
+
+impl<T> StatsMiddleware<T> {
+    pub fn new(inner: T, stats: RpcStats) -> Self {
+        StatsMiddleware {
+            inner: inner,
+            stats: stats,
+        }
+    }
+}
+
+impl<T> Middleware<Meta> for StatsMiddleware<T>
+where
+    T: Middleware<Meta>,
+    T::Future: Send + 'static,
+{
+    type Future = FutureResponse;
+
+    fn on_request<F, X>(&self, request: Request, meta: Meta, next: F) -> Either<Self::Future, X>
+    where
+        F: FnOnce(Request, Meta) -> X + Send,
+        X: future::Future<Item = Option<Response>, Error = ()> + Send + 'static,
+    {
+        let calls: Vec<(String, Option<Id>)> = match request {
+            Request::Single(ref call) => call_method(call).into_iter().collect(),
+            Request::Batch(ref calls) => calls.iter().filter_map(call_method).collect(),
+        };
+        let started = Instant::now();
+        let stats = self.stats.clone();
+        Either::A(Box::new(self.inner.on_request(request, meta, next).then(
+            move |response| {
+                let elapsed = started.elapsed();
+                let outputs: &[Output] = match response {
+                    Ok(Some(Response::Single(ref output))) => ::std::slice::from_ref(output),
+                    Ok(Some(Response::Batch(ref outputs))) => outputs,
+                    _ => &[],
+                };
+                for (method, id) in calls {
+                    let is_error = id.map_or(false, |id| is_failed(outputs, &id));
+                    stats.record(&method, elapsed, is_error);
+                }
+                response
+            },
+        )))
+    }
+}
+
+/// Name of the called method and id of the call (None for notifications)
+fn call_method(call: &Call) -> Option<(String, Option<Id>)> {
+    match *call {
+        Call::MethodCall(ref call) => Some((call.method.clone(), Some(call.id.clone()))),
+        Call::Notification(ref notification) => Some((notification.method.clone(), None)),
+        Call::Invalid(_) => None,
+    }
+}
+
+/// Is the call with given id failed?
+fn is_failed(outputs: &[Output], id: &Id) -> bool {
+    outputs.iter().any(|output| match *output {
+        Output::Failure(ref failure) => failure.id == *id,
+        Output::Success(_) => false,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{RpcStats, StatsMiddleware, MAX_TRACKED_METHODS, OTHER_METHODS};
+    use auth::{Meta, PermissionsMiddleware};
+    use jsonrpc_core::{Compatibility, Error, MetaIoHandler, Value};
+    use std::time::Duration;
+
+    #[test]
+    fn rpc_stats_are_collected() {
+        let stats = RpcStats::default();
+        let mut handler = MetaIoHandler::new(
+            Compatibility::Both,
+            StatsMiddleware::new(PermissionsMiddleware, stats.clone()),
+        );
+        handler.add_method("ok", |_| Ok(Value::Bool(true)));
+        handler.add_method("fail", |_| Err(Error::internal_error()));
+
+        let call = |method: &str, id: u32| {
+            format!(
+                r#"{{"jsonrpc": "2.0", "method": "{}", "params": [], "id": {}}}"#,
+                method, id
+            )
+        };
+        handler.handle_request_sync(&call("ok", 1), Meta::default());
+        handler.handle_request_sync(&call("fail", 2), Meta::default());
+        handler.handle_request_sync(
+            &format!("[{}, {}]", call("ok", 3), call("fail", 4)),
+            Meta::default(),
+        );
+
+        let methods = stats.methods();
+        assert_eq!(methods.len(), 2);
+        assert_eq!(methods[0].0, "fail");
+        assert_eq!(methods[0].1.calls, 2);
+        assert_eq!(methods[0].1.errors, 2);
+        assert_eq!(methods[1].0, "ok");
+        assert_eq!(methods[1].1.calls, 2);
+        assert_eq!(methods[1].1.errors, 0);
+        assert_eq!(methods[1].1.latency_histogram.iter().sum::<u64>(), 2);
+    }
+
+    #[test]
+    fn rpc_stats_latency_histogram() {
+        let stats = RpcStats::new(Some(Duration::from_secs(1)));
+        stats.record("getblock", Duration::from_micros(500), false);
+        stats.record("getblock", Duration::from_millis(50), false);
+        stats.record("getblock", Duration::from_secs(60), true);
+
+        let methods = stats.methods();
+        let getblock = &methods[0].1;
+        assert_eq!(getblock.calls, 3);
+        assert_eq!(getblock.errors, 1);
+        assert_eq!(getblock.max_time_us, 60_000_000);
+        assert_eq!(getblock.total_time_us, 60_050_500);
+        assert_eq!(getblock.latency_histogram, [1, 0, 1, 0, 0, 1]);
+    }
+
+    #[test]
+    fn rpc_stats_track_limited_number_of_methods() {
+        let stats = RpcStats::default();
+        for i in 0..MAX_TRACKED_METHODS + 10 {
+            stats.record(&format!("method{}", i), Duration::from_millis(1), true);
+        }
+        stats.record("method0", Duration::from_millis(1), false);
+
+        let methods = stats.methods();
+        assert_eq!(methods.len(), MAX_TRACKED_METHODS + 1);
+        let other = methods
+            .iter()
+            .find(|&&(ref method, _)| method == OTHER_METHODS)
+            .unwrap();
+        assert_eq!(other.1.calls, 10);
+        let method0 = methods
+            .iter()
+            .find(|&&(ref method, _)| method == "method0")
+            .unwrap();
+        assert_eq!(method0.1.calls, 2);
+    }
+}
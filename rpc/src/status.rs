@@ -0,0 +1,372 @@
+use auth::{Authenticator, MethodGroup};
+use jsonrpc_core::futures::future;
+use jsonrpc_http_server::hyper::header::ContentType;
+use jsonrpc_http_server::hyper::{self, Method, StatusCode};
+use jsonrpc_http_server::{MetaExtractor, RequestMiddleware, RequestMiddlewareAction};
+use network::Network;
+use p2p;
+use primitives::hash::H256;
+use std::fmt::Write;
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+use storage;
+use sync;
+
+/// Path of the status page.
+pub const STATUS_PAGE_PATH: &'static str = "/status";
+/// Reorganizations during this number of last seconds are shown on the status page.
+const RECENT_REORGS_S: u32 = 24 * 60 * 60;
+/// Status page is reloaded by the browser every `REFRESH_INTERVAL_S` seconds.
+const REFRESH_INTERVAL_S: u32 = 10;
+
+/// Connected peer, as shown on the status page.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StatusPeer {
+    pub id: usize,
+    pub address: String,
+    pub inbound: bool,
+    pub version: u32,
+    pub user_agent: String,
+}
+
+/// Node state, shown on the status page. Parts, which are not available (e.g. there's no
+/// synchronization during import), are omitted.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NodeStatus {
+    /// Local time, when the status has been collected
+    pub time: u32,
+    pub network: Network,
+    pub best_block_height: u32,
+    pub best_block_hash: H256,
+    /// Time of the best block header. None if the header could not be read
+    pub best_block_time: Option<u32>,
+    pub sync: Option<sync::SynchronizationStateSnapshot>,
+    pub peers: Option<Vec<StatusPeer>>,
+    pub memory: Option<sync::MemoryUsage>,
+    /// Blocks, which have been reorged out recently. Ordered from oldest to newest
+    pub reorgs: Option<Vec<sync::StaleBlock>>,
+}
+
+/// Serves human-readable node status page on `GET /status`, passing other requests to the
+/// JSON-RPC handler. When authentication is enabled, caller must be allowed to use public methods.
+#[derive(Clone)]
+pub struct StatusPage {
+    network: Network,
+    storage: storage::SharedStore,
+    local_sync_node: Option<sync::LocalNodeRef>,
+    p2p_context: Option<Arc<p2p::Context>>,
+    authenticator: Authenticator,
+}
+
+impl StatusPage {
+    pub fn new(
+        network: Network,
+        storage: storage::SharedStore,
+        local_sync_node: Option<sync::LocalNodeRef>,
+        p2p_context: Option<Arc<p2p::Context>>,
+        authenticator: Authenticator,
+    ) -> Self {
+        StatusPage {
+            network: network,
+            storage: storage,
+            local_sync_node: local_sync_node,
+            p2p_context: p2p_context,
+            authenticator: authenticator,
+        }
+    }
+
+    /// Collects current node status.
+    pub fn status(&self) -> NodeStatus {
+        let time = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs() as u32)
+            .unwrap_or(0);
+        let best_block = self.storage.best_block();
+        let best_block_time = self
+            .storage
+            .block_header(storage::BlockRef::Hash(best_block.hash.clone()))
+            .map(|header| header.raw.time);
+        let sync_state = self
+            .local_sync_node
+            .as_ref()
+            .map(|local_sync_node| local_sync_node.sync_state());
+
+        NodeStatus {
+            time: time,
+            network: self.network,
+            best_block_height: best_block.number,
+            best_block_hash: best_block.hash,
+            best_block_time: best_block_time,
+            sync: sync_state.as_ref().map(|sync_state| sync_state.snapshot()),
+            peers: self.p2p_context.as_ref().map(|p2p| {
+                p2p.connections()
+                    .info()
+                    .into_iter()
+                    .map(|peer| StatusPeer {
+                        id: peer.id,
+                        address: peer.address.to_string(),
+                        inbound: peer.direction == p2p::Direction::Inbound,
+                        version: peer.version,
+                        user_agent: peer.user_agent,
+                    })
+                    .collect()
+            }),
+            memory: self
+                .local_sync_node
+                .as_ref()
+                .map(|local_sync_node| local_sync_node.memory_usage()),
+            reorgs: sync_state
+                .map(|sync_state| sync_state.stale_blocks(time.saturating_sub(RECENT_REORGS_S))),
+        }
+    }
+
+    fn respond(response: hyper::Response) -> RequestMiddlewareAction {
+        RequestMiddlewareAction::Respond {
+            should_validate_hosts: true,
+            response: Box::new(future::ok(response)),
+        }
+    }
+}
+
+impl RequestMiddleware for StatusPage {
+    fn on_request(&self, request: hyper::Request) -> RequestMiddlewareAction {
+        if *request.method() != Method::Get || request.path() != STATUS_PAGE_PATH {
+            return request.into();
+        }
+
+        let allowed = self
+            .authenticator
+            .read_metadata(&request)
+            .groups
+            .map_or(true, |groups| groups.contains(&MethodGroup::Public));
+        if !allowed {
+            let mut response = hyper::Response::new()
+                .with_status(StatusCode::Unauthorized)
+                .with_header(ContentType::plaintext())
+                .with_body("Unauthorized");
+            response
+                .headers_mut()
+                .set_raw("WWW-Authenticate", "Basic realm=\"randchaind\"");
+            return StatusPage::respond(response);
+        }
+
+        StatusPage::respond(
+            hyper::Response::new()
+                .with_header(ContentType::html())
+                .with_body(render_status(&self.status())),
+        )
+    }
+}
+
+/// Escapes text, inserted into HTML. User agents are chosen by peers, so they are never trusted.
+fn escape_html(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Formats number of bytes in MiB.
+fn mib(bytes: usize) -> String {
+    format!("{:.1} MiB", bytes as f64 / (1024 * 1024) as f64)
+}
+
+/// Renders status page.
+pub fn render_status(status: &NodeStatus) -> String {
+    let mut html = String::new();
+    // writes to String never fail
+    let _ = writeln!(
+        html,
+        "<!DOCTYPE html>\n<html><head><meta charset=\"utf-8\">\
+         <meta http-equiv=\"refresh\" content=\"{}\">\
+         <title>randchaind status</title></head><body>",
+        REFRESH_INTERVAL_S
+    );
+    let _ = writeln!(html, "<h1>randchaind: {:?}</h1>", status.network);
+
+    let _ = writeln!(
+        html,
+        "<h2>Chain tip</h2>\n<table>\n<tr><th>Height</th><td>{}</td></tr>\n\
+         <tr><th>Hash</th><td>{}</td></tr>",
+        status.best_block_height,
+        status.best_block_hash.to_reversed_str()
+    );
+    if let Some(block_time) = status.best_block_time {
+        let _ = writeln!(
+            html,
+            "<tr><th>Age</th><td>{} s</td></tr>",
+            i64::from(status.time) - i64::from(block_time)
+        );
+    }
+    html.push_str("</table>\n");
+
+    if let Some(ref sync) = status.sync {
+        let _ = writeln!(
+            html,
+            "<h2>Synchronization</h2>\n<table>\n<tr><th>State</th><td>{}</td></tr>\n\
+             <tr><th>Best header</th><td>{}</td></tr>\n\
+             <tr><th>Progress</th><td>{:.2}%</td></tr>\n\
+             <tr><th>Disk space</th><td>{}</td></tr>\n</table>",
+            if sync.synchronizing {
+                "synchronizing"
+            } else {
+                "saturated"
+            },
+            sync.best_header_height,
+            sync.sync_progress() * 100f64,
+            if sync.disk_space_low { "low" } else { "ok" }
+        );
+    }
+
+    if let Some(ref peers) = status.peers {
+        let _ = writeln!(
+            html,
+            "<h2>Peers ({})</h2>\n<table>\n\
+             <tr><th>Id</th><th>Address</th><th>Direction</th><th>Version</th><th>User agent</th></tr>",
+            peers.len()
+        );
+        for peer in peers {
+            let _ = writeln!(
+                html,
+                "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>",
+                peer.id,
+                escape_html(&peer.address),
+                if peer.inbound { "inbound" } else { "outbound" },
+                peer.version,
+                escape_html(&peer.user_agent)
+            );
+        }
+        html.push_str("</table>\n");
+    }
+
+    if let Some(ref memory) = status.memory {
+        let _ = writeln!(
+            html,
+            "<h2>Memory</h2>\n<table>\n<tr><th>Used</th><td>{} of {}</td></tr>\n\
+             <tr><th>Orphan blocks</th><td>{}</td></tr>\n\
+             <tr><th>Verification queue</th><td>{}</td></tr>\n\
+             <tr><th>Server queue</th><td>{}</td></tr>\n</table>",
+            mib(memory.used()),
+            mib(memory.limit),
+            mib(memory.orphan_blocks),
+            mib(memory.verification_queue),
+            mib(memory.server_queue)
+        );
+    }
+
+    if let Some(ref reorgs) = status.reorgs {
+        let _ = writeln!(
+            html,
+            "<h2>Reorganizations (last {} hours)</h2>\n<table>\n\
+             <tr><th>Height</th><th>Stale block</th><th>Replaced by</th><th>Age</th></tr>",
+            RECENT_REORGS_S / 3600
+        );
+        // newest first
+        for block in reorgs.iter().rev() {
+            let _ = writeln!(
+                html,
+                "<tr><td>{}</td><td>{}</td><td>{}</td><td>{} s</td></tr>",
+                block.height,
+                block.hash.to_reversed_str(),
+                block.replaced_by.to_reversed_str(),
+                status.time.saturating_sub(block.time)
+            );
+        }
+        html.push_str("</table>\n");
+    }
+
+    html.push_str("</body></html>\n");
+    html
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{escape_html, render_status, NodeStatus, StatusPeer};
+    use network::Network;
+    use sync;
+
+    fn status() -> NodeStatus {
+        NodeStatus {
+            time: 1_000_000,
+            network: Network::Regtest,
+            best_block_height: 10,
+            best_block_hash: 1.into(),
+            best_block_time: Some(999_990),
+            sync: None,
+            peers: None,
+            memory: None,
+            reorgs: None,
+        }
+    }
+
+    #[test]
+    fn escape_html_escapes_markup() {
+        assert_eq!(
+            escape_html("<script>alert('1' & \"2\")</script>"),
+            "&lt;script&gt;alert(&#39;1&#39; &amp; &quot;2&quot;)&lt;/script&gt;"
+        );
+        assert_eq!(escape_html("/randchaind:0.1.0/"), "/randchaind:0.1.0/");
+    }
+
+    #[test]
+    fn render_status_omits_unavailable_parts() {
+        let html = render_status(&status());
+        assert!(html.contains("<h1>randchaind: Regtest</h1>"));
+        assert!(html.contains("<tr><th>Height</th><td>10</td></tr>"));
+        assert!(html.contains("<tr><th>Age</th><td>10 s</td></tr>"));
+        assert!(!html.contains("<h2>Synchronization</h2>"));
+        assert!(!html.contains("<h2>Peers"));
+        assert!(!html.contains("<h2>Memory</h2>"));
+        assert!(!html.contains("<h2>Reorganizations"));
+    }
+
+    #[test]
+    fn render_status_contents() {
+        let mut status = status();
+        status.sync = Some(sync::SynchronizationStateSnapshot {
+            synchronizing: true,
+            best_storage_block_height: 10,
+            best_header_height: 40,
+            disk_space_low: false,
+        });
+        status.peers = Some(vec![StatusPeer {
+            id: 3,
+            address: "10.0.0.1:8333".into(),
+            inbound: true,
+            version: 70014,
+            user_agent: "<b>evil</b>".into(),
+        }]);
+        status.memory = Some(sync::MemoryUsage {
+            limit: 64 * 1024 * 1024,
+            orphan_blocks: 1024 * 1024,
+            verification_queue: 0,
+            server_queue: 0,
+        });
+        status.reorgs = Some(vec![sync::StaleBlock {
+            hash: 2.into(),
+            height: 9,
+            replaced_by: 3.into(),
+            time: 999_900,
+        }]);
+
+        let html = render_status(&status);
+        assert!(html.contains("<tr><th>State</th><td>synchronizing</td></tr>"));
+        assert!(html.contains("<tr><th>Progress</th><td>25.00%</td></tr>"));
+        assert!(html.contains("<h2>Peers (1)</h2>"));
+        assert!(html.contains(
+            "<tr><td>3</td><td>10.0.0.1:8333</td><td>inbound</td><td>70014</td><td>&lt;b&gt;evil&lt;/b&gt;</td></tr>"
+        ));
+        assert!(!html.contains("<b>evil</b>"));
+        assert!(html.contains("<tr><th>Used</th><td>1.0 MiB of 64.0 MiB</td></tr>"));
+        assert!(html.contains("<h2>Reorganizations (last 24 hours)</h2>"));
+        assert!(html.contains("<td>100 s</td>"));
+    }
+}
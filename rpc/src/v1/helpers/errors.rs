@@ -5,8 +5,10 @@ mod codes {
     pub const UNKNOWN: i64 = -32000;
     pub const EXECUTION_ERROR: i64 = -32015;
     pub const BLOCK_NOT_FOUND: i64 = -32099;
+    pub const STATE_NOT_RETAINED: i64 = -32098;
     pub const NODE_ALREADY_ADDED: i64 = -32150;
     pub const NODE_NOT_ADDED: i64 = -32151;
+    pub const ACCESS_DENIED: i64 = -32160;
 }
 
 use jsonrpc_core::{Error, ErrorCode, Value};
@@ -53,6 +55,14 @@ pub fn block_at_height_not_found<T: fmt::Debug>(data: T) -> Error {
     }
 }
 
+pub fn state_not_retained<T: fmt::Debug>(data: T) -> Error {
+    Error {
+        code: ErrorCode::ServerError(codes::STATE_NOT_RETAINED),
+        message: "Chain state at given point is not retained".into(),
+        data: Some(Value::String(format!("{:?}", data))),
+    }
+}
+
 pub fn node_already_added() -> Error {
     Error {
         code: ErrorCode::ServerError(codes::NODE_ALREADY_ADDED),
@@ -69,6 +79,14 @@ pub fn node_not_added() -> Error {
     }
 }
 
+pub fn access_denied(method: &str) -> Error {
+    Error {
+        code: ErrorCode::ServerError(codes::ACCESS_DENIED),
+        message: "Access to the requested method is denied".into(),
+        data: Some(Value::String(method.into())),
+    }
+}
+
 pub fn unknown() -> Error {
     Error {
         code: ErrorCode::ServerError(codes::UNKNOWN),
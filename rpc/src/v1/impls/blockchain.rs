@@ -1,36 +1,150 @@
+use chain::{BlockHeader, IndexedBlockHeader};
+use futures_cpupool::{Builder as CpuPoolBuilder, CpuPool};
 use hex::ToHex;
-use jsonrpc_core::Error;
+use jsonrpc_core::{BoxFuture, Error};
 use jsonrpc_macros::Trailing;
 use primitives::hash::H256 as GlobalH256;
-use ser::serialize;
+use ser::{deserialize, serialize};
+use std::cmp;
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
 use storage;
-use v1::helpers::errors::{block_at_height_not_found, block_not_found};
+use sync;
+use v1::helpers::errors::{
+    block_at_height_not_found, block_not_found, execution, invalid_params, state_not_retained,
+};
 use v1::traits::BlockChain;
-use v1::types::H256;
-use v1::types::U256;
-use v1::types::{GetBlockResponse, RawBlock, VerboseBlock};
+use v1::types::{
+    BlockAnnouncement, BlockAnnouncer, BlockChainInfo, BlockCursor, BlockHeadersPage,
+    BlockHeightOrHash, BlockPropagation, BlockStats, ChainEvent, ChainEventKind, ChainEventsCursor,
+    ChainEventsFilter, ChainEventsPage, ChainEventsPosition, ChainEventsResponse, DbColumnInfo,
+    DbInfo, FinalizedBlock, GetBlockHeaderResponse, GetBlockResponse, GetOrphanBlocksResponse,
+    HistoricalBestBlock, MemoryInfo, MinerBlocks, OrphanBlock, RawBlock, RawBlockHeader,
+    ServerLogStats, StaleBlock, VerboseBlock, VerboseBlockHeader, VerifyChainFailure,
+    VerifyChainResult,
+};
+use v1::types::{Bytes, H256};
 use verification;
 
+/// Max number of headers, returned by single `getblockheaders` call.
+pub const MAX_BLOCK_HEADERS_PER_REQUEST: u32 = 2000;
+/// Max number of events, returned by single `listchainevents` call.
+pub const MAX_CHAIN_EVENTS_PER_REQUEST: usize = 1000;
+/// Default number of threads, executing storage queries.
+pub const DEFAULT_STORAGE_THREADS: usize = 4;
+/// Number of best blocks, verified by `verifychain` call by default.
+pub const DEFAULT_VERIFY_CHAIN_DEPTH: u32 = 6;
+
 pub struct BlockChainClient<T: BlockChainClientCoreApi> {
-    core: T,
+    core: Arc<T>,
+    /// Pool, storage queries are executed on
+    pool: CpuPool,
 }
 
 pub trait BlockChainClientCoreApi: Send + Sync + 'static {
     fn best_block_hash(&self) -> GlobalH256;
     fn block_count(&self) -> u32;
     fn block_hash(&self, height: u32) -> Option<GlobalH256>;
+    /// Height and hash of the highest canonical ancestor of the block (the block itself if it
+    /// is canonical). None if block is unknown
+    fn fork_point(&self, hash: &GlobalH256) -> Option<(u32, GlobalH256)>;
+    fn block_locator(&self, height: Option<u32>) -> Option<Vec<GlobalH256>>;
     fn difficulty(&self) -> f64;
     fn raw_block(&self, hash: GlobalH256) -> Option<RawBlock>;
     fn verbose_block(&self, hash: GlobalH256) -> Option<VerboseBlock>;
+    fn raw_block_headers(&self, start: u32, count: u32) -> Vec<RawBlockHeader>;
+    fn verbose_block_headers(&self, start: u32, count: u32) -> Vec<VerboseBlockHeader>;
+    fn block_stats(&self, hash: GlobalH256) -> Option<BlockStats>;
+    fn pruned_height(&self) -> Option<u32>;
+    fn blockchain_info(&self) -> BlockChainInfo;
+    fn stale_blocks(&self, hours: u32) -> Vec<StaleBlock>;
+    fn block_announcements(&self, hours: u32) -> Vec<BlockAnnouncement>;
+    fn block_propagations(&self, hours: u32) -> Vec<BlockPropagation>;
+    fn chain_events(&self, since_seq: u64) -> Vec<ChainEvent>;
+    /// Public key of the block miner. None if block is unknown
+    fn block_miner(&self, hash: &GlobalH256) -> Option<Bytes>;
+    fn miner_blocks(
+        &self,
+        miner: Bytes,
+        start: u32,
+        count: Option<u32>,
+    ) -> Result<MinerBlocks, Error>;
+    fn invalidate_block(&self, hash: GlobalH256) -> Result<(), Error>;
+    fn reconsider_block(&self, hash: GlobalH256) -> Result<(), Error>;
+    fn rollback_last_reorg(&self) -> Result<GlobalH256, Error>;
+    fn finalized_block(&self) -> Result<Option<FinalizedBlock>, Error>;
+    fn orphan_blocks(&self) -> Result<Vec<OrphanBlock>, Error>;
+    fn memory_info(&self) -> Result<MemoryInfo, Error>;
+    fn server_log_stats(&self) -> Result<ServerLogStats, Error>;
+    fn set_server_log_sampling(&self, sampling: u32) -> Result<(), Error>;
+    fn chain_event_seq_at(&self, time: u32) -> Option<u64>;
+    fn best_block_at_seq(&self, seq: u64) -> Option<HistoricalBestBlock>;
+    fn verify_chain(
+        &self,
+        level: verification::VerificationLevel,
+        depth: u32,
+    ) -> Result<VerifyChainResult, Error>;
+    fn db_info(&self) -> Result<DbInfo, Error>;
+    fn compact_db(&self) -> Result<(), Error>;
 }
 
 pub struct BlockChainClientCore {
     storage: storage::SharedStore,
+    /// Synchronization state. None if synchronization is not running (e.g. during import)
+    sync_state: Option<sync::SynchronizationStateRef>,
+    /// Local synchronization node. None if synchronization is not running
+    local_sync_node: Option<sync::LocalNodeRef>,
 }
 
 impl BlockChainClientCore {
-    pub fn new(storage: storage::SharedStore) -> Self {
-        BlockChainClientCore { storage: storage }
+    pub fn new(
+        storage: storage::SharedStore,
+        sync_state: Option<sync::SynchronizationStateRef>,
+        local_sync_node: Option<sync::LocalNodeRef>,
+    ) -> Self {
+        BlockChainClientCore {
+            storage: storage,
+            sync_state: sync_state,
+            local_sync_node: local_sync_node,
+        }
+    }
+
+    fn local_sync_node(&self) -> Result<&sync::LocalNodeRef, Error> {
+        self.local_sync_node
+            .as_ref()
+            .ok_or_else(|| execution("synchronization is not running"))
+    }
+
+    /// Keeps body of the canon block unpruned while the request is served.
+    fn retain_block_body(&self, hash: &GlobalH256) -> Option<storage::PruneGuard> {
+        self.storage
+            .block_number(hash)
+            .map(|number| self.storage.retain_bodies(number))
+    }
+
+    /// Reads up to `count` consecutive headers of the snapshot chain, starting at given height.
+    /// Headers are read from the snapshot, so they never mix forks, even if chain is reorganized meanwhile.
+    fn canon_block_headers(
+        &self,
+        snapshot: &storage::ChainSnapshot,
+        start: u32,
+        count: u32,
+    ) -> Vec<(u32, IndexedBlockHeader)> {
+        snapshot
+            .block_hashes(
+                self.storage.as_block_provider(),
+                start..start.saturating_add(count),
+            )
+            .into_iter()
+            .zip(start..)
+            .map(|(hash, height)| {
+                self.storage
+                    .block_header(hash.into())
+                    .map(|header| (height, header))
+            })
+            .take_while(Option::is_some)
+            .filter_map(|header| header)
+            .collect()
     }
 }
 
@@ -47,17 +161,46 @@ impl BlockChainClientCoreApi for BlockChainClientCore {
         self.storage.block_hash(height)
     }
 
+    fn fork_point(&self, hash: &GlobalH256) -> Option<(u32, GlobalH256)> {
+        let mut hash = hash.clone();
+        loop {
+            if let Some(number) = self.storage.block_number(&hash) {
+                return Some((number, hash));
+            }
+            hash = self
+                .storage
+                .block_header(hash.into())?
+                .raw
+                .previous_header_hash;
+        }
+    }
+
+    fn block_locator(&self, height: Option<u32>) -> Option<Vec<GlobalH256>> {
+        let best_block_height = self.storage.best_block().number;
+        let height = height.unwrap_or(best_block_height);
+        if height > best_block_height {
+            return None;
+        }
+
+        Some(sync::build_block_locator(
+            self.storage.as_block_provider(),
+            height,
+        ))
+    }
+
     fn difficulty(&self) -> f64 {
         self.storage.difficulty()
     }
 
     fn raw_block(&self, hash: GlobalH256) -> Option<RawBlock> {
+        let _guard = self.retain_block_body(&hash);
         self.storage
             .block(hash.into())
             .map(|block| serialize(&block.to_raw_block()).into())
     }
 
     fn verbose_block(&self, hash: GlobalH256) -> Option<VerboseBlock> {
+        let _guard = self.retain_block_body(&hash);
         self.storage.block(hash.into()).map(|block| {
             let height = self.storage.block_number(block.hash());
             let confirmations = match height {
@@ -76,10 +219,15 @@ impl BlockChainClientCoreApi for BlockChainClientCore {
                 height: height,
                 mediantime: Some(median_time),
                 difficulty: block.header.raw.bits.to_f64(),
-                chainwork: U256::default(), // TODO: read from storage
+                chainwork: self
+                    .storage
+                    .chain_work(block.hash())
+                    .map(Into::into)
+                    .unwrap_or_default(),
                 previousblockhash: Some(block.header.raw.previous_header_hash.clone().into()),
                 nextblockhash: height
-                    .and_then(|h| self.storage.block_hash(h + 1).map(|h| h.into())),
+                    .and_then(|_| self.storage.next_block_hash(block.hash()))
+                    .map(Into::into),
                 bits: block.header.raw.bits.into(),
                 hash: block.hash().clone().into(),
                 pubkey_hex: block.header.raw.pubkey.to_bytes().to_hex(),
@@ -91,14 +239,706 @@ impl BlockChainClientCoreApi for BlockChainClientCore {
             }
         })
     }
+
+    fn raw_block_headers(&self, start: u32, count: u32) -> Vec<RawBlockHeader> {
+        let snapshot = self.storage.chain_snapshot();
+        self.canon_block_headers(&snapshot, start, count)
+            .into_iter()
+            .map(|(_, header)| serialize(&header.raw).into())
+            .collect()
+    }
+
+    fn verbose_block_headers(&self, start: u32, count: u32) -> Vec<VerboseBlockHeader> {
+        let snapshot = self.storage.chain_snapshot();
+        let best_block_number = snapshot.tip().number;
+        let headers = self.storage.as_block_header_provider();
+        self.canon_block_headers(&snapshot, start, count)
+            .into_iter()
+            .map(|(height, header)| VerboseBlockHeader {
+                hash: header.hash.clone().into(),
+                confirmations: best_block_number.saturating_sub(height) as i64 + 1,
+                height: height,
+                version: header.raw.version,
+                version_hex: format!("{:x}", &header.raw.version),
+                pubkey_hex: header.raw.pubkey.to_bytes().to_hex(),
+                randomness_hex: header.raw.randomness.to_string_radix(16),
+                time: header.raw.time,
+                mediantime: verification::median_timestamp(&header.raw, headers),
+                iterations: header.raw.iterations,
+                bits: header.raw.bits.into(),
+                difficulty: header.raw.bits.to_f64(),
+                previousblockhash: match height {
+                    0 => None,
+                    _ => Some(header.raw.previous_header_hash.clone().into()),
+                },
+                nextblockhash: snapshot
+                    .block_hash(self.storage.as_block_provider(), height + 1)
+                    .map(Into::into),
+            })
+            .collect()
+    }
+
+    fn block_stats(&self, hash: GlobalH256) -> Option<BlockStats> {
+        let _guard = self.retain_block_body(&hash);
+        self.storage.block(hash.into()).map(|block| {
+            let headers = self.storage.as_block_header_provider();
+            let parent = headers.block_header(block.header.raw.previous_header_hash.clone().into());
+            let median_time = verification::median_timestamp(&block.header.raw, headers);
+
+            BlockStats {
+                hash: block.hash().clone().into(),
+                height: self.storage.block_number(block.hash()),
+                size: block.size() as u32,
+                time: block.header.raw.time,
+                timedelta: parent
+                    .as_ref()
+                    .map(|parent| block.header.raw.time as i64 - parent.raw.time as i64),
+                iterations: block.header.raw.iterations,
+                interval: parent.map(|parent| {
+                    let parent_median_time = verification::median_timestamp(&parent.raw, headers);
+                    median_time as i64 - parent_median_time as i64
+                }),
+            }
+        })
+    }
+
+    fn pruned_height(&self) -> Option<u32> {
+        self.storage.pruned_height()
+    }
+
+    fn blockchain_info(&self) -> BlockChainInfo {
+        let best_block = self.storage.best_block();
+        let median_time = verification::median_timestamp_inclusive(
+            best_block.hash.clone(),
+            self.storage.as_block_header_provider(),
+        );
+        let sync_state = self
+            .sync_state
+            .as_ref()
+            .map(|sync_state| sync_state.snapshot());
+        let (headers, verification_progress, initial_block_download, disk_space_low) =
+            match sync_state {
+                Some(snapshot) => (
+                    ::std::cmp::max(snapshot.best_header_height, best_block.number),
+                    snapshot.sync_progress(),
+                    snapshot.synchronizing,
+                    snapshot.disk_space_low,
+                ),
+                None => (best_block.number, 1f64, false, false),
+            };
+        let warnings = if disk_space_low {
+            "Disk space is low. Blocks processing is paused".into()
+        } else {
+            String::new()
+        };
+        let pruned_height = self.storage.pruned_height();
+        let chain_work = self
+            .storage
+            .chain_work(&best_block.hash)
+            .map(Into::into)
+            .unwrap_or_default();
+
+        BlockChainInfo {
+            blocks: best_block.number,
+            headers: headers,
+            bestblockhash: best_block.hash.into(),
+            difficulty: self.storage.difficulty(),
+            chainwork: chain_work,
+            mediantime: median_time,
+            verificationprogress: verification_progress,
+            initialblockdownload: initial_block_download,
+            pruned: pruned_height.is_some(),
+            pruneheight: pruned_height,
+            compressionratio: self.storage.stats().compression_ratio(),
+            warnings: warnings,
+        }
+    }
+
+    fn stale_blocks(&self, hours: u32) -> Vec<StaleBlock> {
+        // stale blocks are only tracked by the synchronization
+        let sync_state = match self.sync_state {
+            Some(ref sync_state) => sync_state,
+            None => return Vec::new(),
+        };
+
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs() as u32)
+            .unwrap_or(0);
+        sync_state
+            .stale_blocks(now.saturating_sub(hours.saturating_mul(60 * 60)))
+            .into_iter()
+            .map(|block| StaleBlock {
+                hash: block.hash.into(),
+                height: block.height,
+                replacedby: block.replaced_by.into(),
+                time: block.time,
+            })
+            .collect()
+    }
+
+    fn block_announcements(&self, hours: u32) -> Vec<BlockAnnouncement> {
+        // announcements are only tracked by the synchronization
+        let sync_state = match self.sync_state {
+            Some(ref sync_state) => sync_state,
+            None => return Vec::new(),
+        };
+
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs() as u32)
+            .unwrap_or(0);
+        sync_state
+            .block_announcements(now.saturating_sub(hours.saturating_mul(60 * 60)))
+            .into_iter()
+            .map(|announcement| BlockAnnouncement {
+                hash: announcement.hash.into(),
+                time: announcement.time,
+                announcers: announcement
+                    .announcers
+                    .into_iter()
+                    .map(|(peer, delay_ms)| BlockAnnouncer {
+                        peer: peer,
+                        delayms: delay_ms,
+                    })
+                    .collect(),
+            })
+            .collect()
+    }
+
+    fn block_propagations(&self, hours: u32) -> Vec<BlockPropagation> {
+        // propagation is only tracked by the synchronization
+        let sync_state = match self.sync_state {
+            Some(ref sync_state) => sync_state,
+            None => return Vec::new(),
+        };
+
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs() as u32)
+            .unwrap_or(0);
+        sync_state
+            .block_propagations(now.saturating_sub(hours.saturating_mul(60 * 60)))
+            .into_iter()
+            .map(|propagation| BlockPropagation {
+                hash: propagation.hash.into(),
+                time: propagation.time,
+                peer: propagation.first_peer,
+                announced: propagation.announced,
+                receivedms: propagation.received_ms,
+                verifiedms: propagation.verified_ms,
+                connectedms: propagation.connected_ms,
+            })
+            .collect()
+    }
+
+    fn chain_events(&self, since_seq: u64) -> Vec<ChainEvent> {
+        self.storage
+            .chain_events(since_seq, MAX_CHAIN_EVENTS_PER_REQUEST)
+            .into_iter()
+            .map(|event| ChainEvent {
+                seq: event.seq,
+                event: match event.kind {
+                    storage::ChainEventKind::BlockConnected => ChainEventKind::Connected,
+                    storage::ChainEventKind::BlockDisconnected => ChainEventKind::Disconnected,
+                    storage::ChainEventKind::ReorgStarted => ChainEventKind::ReorgStarted,
+                    storage::ChainEventKind::ReorgFinished => ChainEventKind::ReorgFinished,
+                },
+                hash: event.hash.into(),
+                height: event.number,
+                time: event.time,
+            })
+            .collect()
+    }
+
+    fn block_miner(&self, hash: &GlobalH256) -> Option<Bytes> {
+        self.storage
+            .block_header(storage::BlockRef::Hash(hash.clone()))
+            .map(|header| Bytes::new(header.raw.pubkey.to_bytes().to_vec()))
+    }
+
+    fn miner_blocks(
+        &self,
+        miner: Bytes,
+        start: u32,
+        count: Option<u32>,
+    ) -> Result<MinerBlocks, Error> {
+        let end = self.storage.best_block().number.saturating_add(1);
+        let end = match count {
+            Some(count) => cmp::min(start.saturating_add(count), end),
+            None => end,
+        };
+        let heights = self
+            .storage
+            .blocks_by_miner(&miner.to_vec().into(), start..end)
+            .ok_or_else(|| execution("miner index is disabled. Start node with --miner-index"))?;
+        Ok(MinerBlocks {
+            total: end.saturating_sub(start),
+            heights: heights,
+        })
+    }
+
+    fn invalidate_block(&self, hash: GlobalH256) -> Result<(), Error> {
+        self.local_sync_node()?
+            .invalidate_block(&hash)
+            .map_err(|err| sync_error(hash, err))
+    }
+
+    fn reconsider_block(&self, hash: GlobalH256) -> Result<(), Error> {
+        self.local_sync_node()?
+            .reconsider_block(&hash)
+            .map_err(|err| sync_error(hash, err))
+    }
+
+    fn rollback_last_reorg(&self) -> Result<GlobalH256, Error> {
+        let local_sync_node = self.local_sync_node()?;
+        let reorg = self
+            .storage
+            .last_reorg()
+            .ok_or_else(|| execution("no reorganization is recorded"))?;
+        let first_connected = match reorg.connected.first() {
+            Some(hash) if self.storage.block_number(hash) == Some(reorg.ancestor + 1) => {
+                hash.clone()
+            }
+            _ => {
+                return Err(execution(
+                    "blocks, connected by the last reorganization, are not canon anymore",
+                ))
+            }
+        };
+
+        info!(target: "rpc", "Rolling back reorganization at height {}: invalidating block {}", reorg.ancestor + 1, first_connected.to_reversed_str());
+        local_sync_node
+            .invalidate_block(&first_connected)
+            .map_err(|err| sync_error(first_connected.clone(), err))?;
+        Ok(first_connected)
+    }
+
+    fn finalized_block(&self) -> Result<Option<FinalizedBlock>, Error> {
+        Ok(self
+            .local_sync_node()?
+            .finalized_block()
+            .map(|block| FinalizedBlock {
+                hash: block.hash.into(),
+                height: block.number,
+            }))
+    }
+
+    fn orphan_blocks(&self) -> Result<Vec<OrphanBlock>, Error> {
+        Ok(self
+            .local_sync_node()?
+            .orphan_blocks()
+            .into_iter()
+            .map(|entry| OrphanBlock {
+                hash: entry.hash.into(),
+                parent: entry.parent.into(),
+                size: entry.size as u32,
+                time: entry.time,
+            })
+            .collect())
+    }
+
+    fn memory_info(&self) -> Result<MemoryInfo, Error> {
+        Ok(self.local_sync_node()?.memory_usage().into())
+    }
+
+    fn server_log_stats(&self) -> Result<ServerLogStats, Error> {
+        Ok(self.local_sync_node()?.server_log_stats().into())
+    }
+
+    fn set_server_log_sampling(&self, sampling: u32) -> Result<(), Error> {
+        self.local_sync_node()?.set_server_log_sampling(sampling);
+        Ok(())
+    }
+
+    fn verify_chain(
+        &self,
+        level: verification::VerificationLevel,
+        depth: u32,
+    ) -> Result<VerifyChainResult, Error> {
+        let check = self.local_sync_node()?.verify_chain(level, depth);
+        Ok(VerifyChainResult {
+            checked: check.checked,
+            failure: check.failure.map(|failure| VerifyChainFailure {
+                height: failure.height,
+                hash: failure.hash.into(),
+                error: format!("{:?}", failure.error),
+            }),
+        })
+    }
+
+    fn db_info(&self) -> Result<DbInfo, Error> {
+        let size = self.storage.size().map_err(execution)?;
+        Ok(DbInfo {
+            disksize: size.disk_size,
+            datasize: size.data_size(),
+            overhead: size.overhead(),
+            columns: size
+                .columns
+                .into_iter()
+                .map(|column| DbColumnInfo {
+                    name: column.name,
+                    keys: column.keys,
+                    size: column.size,
+                })
+                .collect(),
+        })
+    }
+
+    fn compact_db(&self) -> Result<(), Error> {
+        info!(target: "rpc", "Compacting database");
+        self.storage.compact().map_err(execution)?;
+        info!(target: "rpc", "Database has been compacted");
+        Ok(())
+    }
+
+    fn chain_event_seq_at(&self, time: u32) -> Option<u64> {
+        self.storage.chain_event_seq_at(time)
+    }
+
+    fn best_block_at_seq(&self, seq: u64) -> Option<HistoricalBestBlock> {
+        let best_block = self.storage.best_block_at_seq(seq)?;
+        let event = self.storage.chain_events(seq - 1, 1).pop()?;
+        let header = self
+            .storage
+            .block_header(storage::BlockRef::Hash(best_block.hash.clone()))?;
+        Some(HistoricalBestBlock {
+            seq: seq,
+            eventtime: event.time,
+            hash: best_block.hash.into(),
+            height: best_block.number,
+            randomness_hex: header.raw.randomness.to_string_radix(16),
+        })
+    }
+}
+
+fn sync_error(hash: GlobalH256, err: sync::Error) -> Error {
+    match err {
+        sync::Error::UnknownBlock => block_not_found(H256::from(hash.reversed())),
+        err => execution(err),
+    }
 }
 
 impl<T> BlockChainClient<T>
 where
     T: BlockChainClientCoreApi,
 {
+    /// Creates client with its own pool of `DEFAULT_STORAGE_THREADS` threads for storage queries
     pub fn new(core: T) -> Self {
-        BlockChainClient { core: core }
+        let pool = CpuPoolBuilder::new()
+            .name_prefix("RPC storage")
+            .pool_size(DEFAULT_STORAGE_THREADS)
+            .create();
+        BlockChainClient::with_pool(core, pool)
+    }
+
+    /// Creates client, which runs storage queries on given pool
+    pub fn with_pool(core: T, pool: CpuPool) -> Self {
+        BlockChainClient {
+            core: Arc::new(core),
+            pool: pool,
+        }
+    }
+
+    /// Runs storage query on the storage pool, so that slow disk reads are not blocking RPC threads.
+    fn blocking<R, F>(&self, query: F) -> BoxFuture<R>
+    where
+        R: Send + 'static,
+        F: FnOnce(&T) -> Result<R, Error> + Send + 'static,
+    {
+        let core = self.core.clone();
+        Box::new(self.pool.spawn_fn(move || query(&*core)))
+    }
+
+    /// Returns hash of the referenced block, in storage byte order
+    fn resolve_block(core: &T, block: BlockHeightOrHash) -> Result<GlobalH256, Error> {
+        match block {
+            BlockHeightOrHash::Height(height) => core
+                .block_hash(height)
+                .ok_or(block_at_height_not_found(height)),
+            BlockHeightOrHash::Hash(hash) => {
+                let global_hash: GlobalH256 = hash.into();
+                Ok(global_hash.reversed())
+            }
+        }
+    }
+
+    fn read_block(
+        core: &T,
+        block: BlockHeightOrHash,
+        verbose: bool,
+    ) -> Result<GetBlockResponse, Error> {
+        let global_hash = Self::resolve_block(core, block)?;
+        let hash = H256::from(global_hash.reversed());
+        if verbose {
+            let verbose_block = core.verbose_block(global_hash);
+            if let Some(mut verbose_block) = verbose_block {
+                verbose_block.previousblockhash =
+                    verbose_block.previousblockhash.map(|h| h.reversed());
+                verbose_block.nextblockhash = verbose_block.nextblockhash.map(|h| h.reversed());
+                verbose_block.hash = verbose_block.hash.reversed();
+                verbose_block.randomness_hex = verbose_block.randomness_hex;
+                Some(GetBlockResponse::Verbose(verbose_block))
+            } else {
+                None
+            }
+        } else {
+            core.raw_block(global_hash)
+                .map(|block| GetBlockResponse::Raw(block))
+        }
+        .ok_or(block_not_found(hash))
+    }
+
+    fn read_block_headers(
+        core: &T,
+        start: u32,
+        count: u32,
+        verbose: bool,
+    ) -> Result<Vec<GetBlockHeaderResponse>, Error> {
+        if count == 0 || count > MAX_BLOCK_HEADERS_PER_REQUEST {
+            return Err(invalid_params(
+                "count",
+                format!("expected 1..{}", MAX_BLOCK_HEADERS_PER_REQUEST),
+            ));
+        }
+        if start > core.block_count() {
+            return Err(block_at_height_not_found(start));
+        }
+
+        if verbose {
+            Ok(core
+                .verbose_block_headers(start, count)
+                .into_iter()
+                .map(|mut header| {
+                    header.hash = header.hash.reversed();
+                    header.previousblockhash = header.previousblockhash.map(|h| h.reversed());
+                    header.nextblockhash = header.nextblockhash.map(|h| h.reversed());
+                    GetBlockHeaderResponse::Verbose(header)
+                })
+                .collect())
+        } else {
+            Ok(core
+                .raw_block_headers(start, count)
+                .into_iter()
+                .map(GetBlockHeaderResponse::Raw)
+                .collect())
+        }
+    }
+
+    /// Returns None if cursor block is canonical, or cursor of its highest canonical ancestor
+    /// if the block has been reorged
+    fn cursor_fork_point(core: &T, cursor: &BlockCursor) -> Result<Option<BlockCursor>, Error> {
+        match core.fork_point(&cursor.hash) {
+            Some((height, ref hash)) if *hash == cursor.hash => match height == cursor.height {
+                true => Ok(None),
+                false => Err(invalid_params("cursor", "height mismatch")),
+            },
+            Some((height, hash)) => Ok(Some(BlockCursor {
+                height: height,
+                hash: hash,
+            })),
+            None => Err(invalid_params("cursor", "unknown block")),
+        }
+    }
+
+    fn read_block_headers_page(
+        core: &T,
+        cursor: Option<BlockCursor>,
+        count: u32,
+        verbose: bool,
+    ) -> Result<BlockHeadersPage, Error> {
+        let start = match cursor {
+            Some(ref cursor) => match Self::cursor_fork_point(core, cursor)? {
+                None => cursor.height + 1,
+                Some(forkpoint) => {
+                    return Ok(BlockHeadersPage {
+                        headers: Vec::new(),
+                        next: forkpoint.clone(),
+                        reorged: true,
+                        forkpoint: Some(forkpoint),
+                    });
+                }
+            },
+            None => 0,
+        };
+
+        let headers = if start > core.block_count() {
+            Vec::new()
+        } else {
+            Self::read_block_headers(core, start, count, verbose)?
+        };
+        // cursor refers to the last returned header, so that reorg of this page is detected later
+        let next = match headers.last() {
+            Some(header) => BlockCursor {
+                height: start + headers.len() as u32 - 1,
+                hash: match *header {
+                    GetBlockHeaderResponse::Raw(ref raw) => deserialize::<_, BlockHeader>(&raw[..])
+                        .map_err(execution)?
+                        .hash(),
+                    GetBlockHeaderResponse::Verbose(ref header) => {
+                        let hash: GlobalH256 = header.hash.clone().into();
+                        hash.reversed()
+                    }
+                },
+            },
+            None => match cursor {
+                Some(cursor) => cursor,
+                None => return Err(block_at_height_not_found(start)),
+            },
+        };
+        Ok(BlockHeadersPage {
+            headers: headers,
+            next: next,
+            reorged: false,
+            forkpoint: None,
+        })
+    }
+
+    fn read_chain_events(
+        core: &T,
+        since_seq: u64,
+        filter: &ChainEventsFilter,
+    ) -> Result<Vec<ChainEvent>, Error> {
+        Self::filter_chain_events(core, core.chain_events(since_seq), filter)
+    }
+
+    /// Drops events, which do not satisfy the filter, and reverses hashes of the rest
+    fn filter_chain_events(
+        core: &T,
+        events: Vec<ChainEvent>,
+        filter: &ChainEventsFilter,
+    ) -> Result<Vec<ChainEvent>, Error> {
+        let finalized_height = match filter.finalized {
+            Some(true) => Some(core.finalized_block()?.map(|block| block.height)),
+            _ => None,
+        };
+
+        let mut filtered = Vec::with_capacity(events.len());
+        for mut event in events {
+            if let Some(ref miner) = filter.miner {
+                let is_block_event = event.event == ChainEventKind::Connected
+                    || event.event == ChainEventKind::Disconnected;
+                if !is_block_event
+                    || core.block_miner(&event.hash.clone().into()).as_ref() != Some(miner)
+                {
+                    continue;
+                }
+            }
+            if let Some(min_depth) = filter.minreorgdepth {
+                // reorg starts at the common ancestor, while the best block is not changed yet
+                let is_deep_reorg = event.event == ChainEventKind::ReorgStarted
+                    && core
+                        .best_block_at_seq(event.seq)
+                        .map_or(false, |best_block| {
+                            best_block.height.saturating_sub(event.height) >= min_depth
+                        });
+                if !is_deep_reorg {
+                    continue;
+                }
+            }
+            if let Some(finalized_height) = finalized_height {
+                let is_finalized = event.event == ChainEventKind::Connected
+                    && finalized_height.map_or(false, |height| event.height <= height)
+                    && core.block_hash(event.height) == Some(event.hash.clone().into());
+                if !is_finalized {
+                    continue;
+                }
+            }
+
+            event.hash = event.hash.reversed();
+            filtered.push(event);
+        }
+        Ok(filtered)
+    }
+
+    fn read_chain_events_page(
+        core: &T,
+        cursor: Option<ChainEventsCursor>,
+        filter: &ChainEventsFilter,
+    ) -> Result<ChainEventsPage, Error> {
+        let forkpoint = match cursor {
+            Some(ref cursor) => Self::cursor_fork_point(core, &cursor.block)?,
+            None => None,
+        };
+        let since_seq = cursor.as_ref().map_or(0, |cursor| cursor.seq);
+        let events = core.chain_events(since_seq);
+
+        // cursor refers to the best block after the last returned event, so that its reorg is
+        // detected later. Events of the reorg itself are returned along with the fork point
+        let next = match events.last() {
+            Some(event) => {
+                let best_block = core
+                    .best_block_at_seq(event.seq)
+                    .ok_or(state_not_retained(event.seq))?;
+                ChainEventsCursor {
+                    seq: event.seq,
+                    block: BlockCursor {
+                        height: best_block.height,
+                        hash: best_block.hash.into(),
+                    },
+                }
+            }
+            None => match cursor {
+                Some(cursor) => ChainEventsCursor {
+                    seq: cursor.seq,
+                    block: forkpoint.clone().unwrap_or(cursor.block),
+                },
+                None => ChainEventsCursor {
+                    seq: since_seq,
+                    block: BlockCursor {
+                        height: core.block_count(),
+                        hash: core.best_block_hash(),
+                    },
+                },
+            },
+        };
+        // cursor skips events, which are filtered out
+        Ok(ChainEventsPage {
+            events: Self::filter_chain_events(core, events, filter)?,
+            next: next,
+            reorged: forkpoint.is_some(),
+            forkpoint: forkpoint,
+        })
+    }
+
+    fn read_block_header_by_height(
+        core: &T,
+        height: u32,
+        verbose: bool,
+    ) -> Result<GetBlockHeaderResponse, Error> {
+        Self::read_block_headers(core, height, 1, verbose)?
+            .pop()
+            .ok_or(block_at_height_not_found(height))
+    }
+
+    fn read_block_stats(core: &T, block: BlockHeightOrHash) -> Result<BlockStats, Error> {
+        let global_hash = Self::resolve_block(core, block)?;
+        core.block_stats(global_hash.clone())
+            .map(|mut stats| {
+                stats.hash = stats.hash.reversed();
+                stats
+            })
+            .ok_or(block_not_found(H256::from(global_hash.reversed())))
+    }
+
+    fn parse_verification_level(
+        level: Option<String>,
+    ) -> Result<verification::VerificationLevel, Error> {
+        match level.as_ref().map(String::as_str) {
+            Some("paranoid") => Ok(verification::VerificationLevel::Paranoid),
+            Some("full") | None => Ok(verification::VerificationLevel::Full),
+            Some("header") => Ok(verification::VerificationLevel::Header),
+            Some(_) => Err(invalid_params("level", "expected paranoid, full or header")),
+        }
+    }
+
+    fn read_best_block_at_event(core: &T, seq: u64) -> Result<HistoricalBestBlock, Error> {
+        core.best_block_at_seq(seq)
+            .map(|mut block| {
+                block.hash = block.hash.reversed();
+                block
+            })
+            .ok_or(state_not_retained(seq))
     }
 }
 
@@ -121,30 +961,233 @@ where
             .ok_or(block_at_height_not_found(height))
     }
 
+    fn block_locator(&self, height: Trailing<Option<u32>>) -> Result<Vec<H256>, Error> {
+        let height = height.unwrap_or_default();
+        self.core
+            .block_locator(height)
+            .map(|hashes| {
+                hashes
+                    .into_iter()
+                    .map(|hash| hash.reversed().into())
+                    .collect()
+            })
+            // locator of the best block is always available => only explicitly requested height could be missing
+            .ok_or(block_at_height_not_found(height.unwrap_or_default()))
+    }
+
     fn difficulty(&self) -> Result<f64, Error> {
         Ok(self.core.difficulty())
     }
 
-    fn block(&self, hash: H256, verbose: Trailing<bool>) -> Result<GetBlockResponse, Error> {
-        let global_hash: GlobalH256 = hash.clone().into();
-        if verbose.unwrap_or_default() {
-            let verbose_block = self.core.verbose_block(global_hash.reversed());
-            if let Some(mut verbose_block) = verbose_block {
-                verbose_block.previousblockhash =
-                    verbose_block.previousblockhash.map(|h| h.reversed());
-                verbose_block.nextblockhash = verbose_block.nextblockhash.map(|h| h.reversed());
-                verbose_block.hash = verbose_block.hash.reversed();
-                verbose_block.randomness_hex = verbose_block.randomness_hex;
-                Some(GetBlockResponse::Verbose(verbose_block))
-            } else {
-                None
+    fn block(
+        &self,
+        block: BlockHeightOrHash,
+        verbose: Trailing<bool>,
+    ) -> BoxFuture<GetBlockResponse> {
+        let verbose = verbose.unwrap_or_default();
+        self.blocking(move |core| Self::read_block(core, block, verbose))
+    }
+
+    fn block_headers(
+        &self,
+        start: u32,
+        count: u32,
+        verbose: Trailing<bool>,
+    ) -> BoxFuture<Vec<GetBlockHeaderResponse>> {
+        let verbose = verbose.unwrap_or_default();
+        self.blocking(move |core| Self::read_block_headers(core, start, count, verbose))
+    }
+
+    fn block_headers_page(
+        &self,
+        cursor: Option<BlockCursor>,
+        count: u32,
+        verbose: Trailing<bool>,
+    ) -> BoxFuture<BlockHeadersPage> {
+        let verbose = verbose.unwrap_or_default();
+        self.blocking(move |core| Self::read_block_headers_page(core, cursor, count, verbose))
+    }
+
+    fn block_header_by_height(
+        &self,
+        height: u32,
+        verbose: Trailing<bool>,
+    ) -> BoxFuture<GetBlockHeaderResponse> {
+        let verbose = verbose.unwrap_or_default();
+        self.blocking(move |core| Self::read_block_header_by_height(core, height, verbose))
+    }
+
+    fn block_stats(&self, block: BlockHeightOrHash) -> BoxFuture<BlockStats> {
+        self.blocking(move |core| Self::read_block_stats(core, block))
+    }
+
+    fn prune_height(&self) -> Result<Option<u32>, Error> {
+        Ok(self.core.pruned_height())
+    }
+
+    fn blockchain_info(&self) -> BoxFuture<BlockChainInfo> {
+        self.blocking(|core| {
+            let mut info = core.blockchain_info();
+            info.bestblockhash = info.bestblockhash.reversed();
+            Ok(info)
+        })
+    }
+
+    fn stale_blocks(&self, hours: u32) -> Result<Vec<StaleBlock>, Error> {
+        Ok(self
+            .core
+            .stale_blocks(hours)
+            .into_iter()
+            .map(|mut block| {
+                block.hash = block.hash.reversed();
+                block.replacedby = block.replacedby.reversed();
+                block
+            })
+            .collect())
+    }
+
+    fn block_announcements(&self, hours: u32) -> Result<Vec<BlockAnnouncement>, Error> {
+        Ok(self
+            .core
+            .block_announcements(hours)
+            .into_iter()
+            .map(|mut announcement| {
+                announcement.hash = announcement.hash.reversed();
+                announcement
+            })
+            .collect())
+    }
+
+    fn block_propagations(&self, hours: u32) -> Result<Vec<BlockPropagation>, Error> {
+        Ok(self
+            .core
+            .block_propagations(hours)
+            .into_iter()
+            .map(|mut propagation| {
+                propagation.hash = propagation.hash.reversed();
+                propagation
+            })
+            .collect())
+    }
+
+    fn chain_events(
+        &self,
+        position: Option<ChainEventsPosition>,
+        filter: Trailing<ChainEventsFilter>,
+    ) -> BoxFuture<ChainEventsResponse> {
+        let filter = filter.unwrap_or_default();
+        self.blocking(move |core| match position {
+            Some(ChainEventsPosition::Seq(since_seq)) => {
+                Self::read_chain_events(core, since_seq, &filter).map(ChainEventsResponse::Events)
             }
+            Some(ChainEventsPosition::Cursor(cursor)) => {
+                Self::read_chain_events_page(core, Some(cursor), &filter)
+                    .map(ChainEventsResponse::Page)
+            }
+            None => {
+                Self::read_chain_events_page(core, None, &filter).map(ChainEventsResponse::Page)
+            }
+        })
+    }
+
+    fn miner_blocks(
+        &self,
+        miner: Bytes,
+        start: Trailing<u32>,
+        count: Trailing<Option<u32>>,
+    ) -> BoxFuture<MinerBlocks> {
+        let (start, count) = (start.unwrap_or_default(), count.unwrap_or_default());
+        self.blocking(move |core| {
+            if miner.0.len() != 32 {
+                return Err(invalid_params("pubkey", "public key should be 32 bytes"));
+            }
+            core.miner_blocks(miner, start, count)
+        })
+    }
+
+    fn invalidate_block(&self, hash: H256) -> Result<(), Error> {
+        let global_hash: GlobalH256 = hash.into();
+        self.core.invalidate_block(global_hash.reversed())
+    }
+
+    fn reconsider_block(&self, hash: H256) -> Result<(), Error> {
+        let global_hash: GlobalH256 = hash.into();
+        self.core.reconsider_block(global_hash.reversed())
+    }
+
+    fn rollback_last_reorg(&self) -> Result<H256, Error> {
+        Ok(self.core.rollback_last_reorg()?.reversed().into())
+    }
+
+    fn finalized_block(&self) -> Result<Option<FinalizedBlock>, Error> {
+        Ok(self.core.finalized_block()?.map(|mut block| {
+            block.hash = block.hash.reversed();
+            block
+        }))
+    }
+
+    fn orphan_blocks(&self, verbose: Trailing<bool>) -> Result<GetOrphanBlocksResponse, Error> {
+        let entries = self.core.orphan_blocks()?.into_iter().map(|mut entry| {
+            entry.hash = entry.hash.reversed();
+            entry.parent = entry.parent.reversed();
+            entry
+        });
+        Ok(if verbose.unwrap_or_default() {
+            GetOrphanBlocksResponse::Verbose(entries.collect())
         } else {
-            self.core
-                .raw_block(global_hash.reversed())
-                .map(|block| GetBlockResponse::Raw(block))
-        }
-        .ok_or(block_not_found(hash))
+            GetOrphanBlocksResponse::Raw(entries.map(|entry| entry.hash).collect())
+        })
+    }
+
+    fn memory_info(&self) -> Result<MemoryInfo, Error> {
+        self.core.memory_info()
+    }
+
+    fn server_log_stats(&self) -> Result<ServerLogStats, Error> {
+        self.core.server_log_stats()
+    }
+
+    fn set_server_log_sampling(&self, sampling: u32) -> Result<(), Error> {
+        self.core.set_server_log_sampling(sampling)
+    }
+
+    fn best_block_at(&self, time: u32) -> BoxFuture<HistoricalBestBlock> {
+        self.blocking(move |core| {
+            let seq = core
+                .chain_event_seq_at(time)
+                .ok_or(state_not_retained(time))?;
+            Self::read_best_block_at_event(core, seq)
+        })
+    }
+
+    fn best_block_at_event(&self, seq: u64) -> BoxFuture<HistoricalBestBlock> {
+        self.blocking(move |core| Self::read_best_block_at_event(core, seq))
+    }
+
+    fn verify_chain(
+        &self,
+        level: Trailing<Option<String>>,
+        depth: Trailing<Option<u32>>,
+    ) -> BoxFuture<VerifyChainResult> {
+        let depth = depth
+            .unwrap_or_default()
+            .unwrap_or(DEFAULT_VERIFY_CHAIN_DEPTH);
+        let level = level.unwrap_or_default();
+        self.blocking(move |core| {
+            let mut result = core.verify_chain(Self::parse_verification_level(level)?, depth)?;
+            if let Some(ref mut failure) = result.failure {
+                failure.hash = failure.hash.reversed();
+            }
+            Ok(result)
+        })
+    }
+
+    fn db_info(&self) -> BoxFuture<DbInfo> {
+        self.blocking(|core| core.db_info())
+    }
+
+    fn compact_db(&self) -> BoxFuture<()> {
+        self.blocking(|core| core.compact_db())
     }
 }
 
@@ -158,7 +1201,7 @@ pub mod tests {
     use primitives::hash::H256 as GlobalH256;
     use std::sync::Arc;
     use v1::traits::BlockChain;
-    use v1::types::{RawBlock, VerboseBlock};
+    use v1::types::{RawBlock, RawBlockHeader, VerboseBlock, VerboseBlockHeader};
 
     #[derive(Default)]
     struct SuccessBlockChainClientCore;
@@ -178,6 +1221,17 @@ pub mod tests {
             Some(test_data::genesis().hash())
         }
 
+        fn fork_point(&self, _hash: &GlobalH256) -> Option<(u32, GlobalH256)> {
+            Some((0, test_data::genesis().hash()))
+        }
+
+        fn block_locator(&self, _height: Option<u32>) -> Option<Vec<GlobalH256>> {
+            Some(vec![
+                test_data::block_h1().hash(),
+                test_data::genesis().hash(),
+            ])
+        }
+
         fn difficulty(&self) -> f64 {
             1f64
         }
@@ -210,19 +1264,245 @@ pub mod tests {
                 nextblockhash: None,
             })
         }
-    }
-
-    impl BlockChainClientCoreApi for ErrorBlockChainClientCore {
-        fn best_block_hash(&self) -> GlobalH256 {
-            test_data::genesis().hash()
-        }
-
-        fn block_count(&self) -> u32 {
-            1
-        }
 
-        fn block_hash(&self, _height: u32) -> Option<GlobalH256> {
-            None
+        fn raw_block_headers(&self, _start: u32, _count: u32) -> Vec<RawBlockHeader> {
+            vec![RawBlockHeader::from(serialize(
+                test_data::block_h2().header(),
+            ))]
+        }
+
+        fn verbose_block_headers(&self, _start: u32, _count: u32) -> Vec<VerboseBlockHeader> {
+            vec![VerboseBlockHeader {
+                hash: test_data::block_h2().hash().into(),
+                confirmations: 1,
+                height: 2,
+                version: 1,
+                version_hex: "1".to_owned(),
+                pubkey_hex: "00".to_owned(),
+                randomness_hex: "7788".to_owned(),
+                time: 1002,
+                mediantime: 1001,
+                iterations: 4,
+                bits: 545259519,
+                difficulty: 1.0,
+                previousblockhash: Some(test_data::block_h1().hash().into()),
+                nextblockhash: None,
+            }]
+        }
+
+        fn block_stats(&self, _hash: GlobalH256) -> Option<BlockStats> {
+            Some(BlockStats {
+                hash: test_data::block_h2().hash().into(),
+                height: Some(2),
+                size: serialize(&test_data::block_h2()).len() as u32,
+                time: test_data::block_h2().header().time,
+                timedelta: Some(1),
+                iterations: test_data::block_h2().header().iterations,
+                interval: Some(1),
+            })
+        }
+
+        fn pruned_height(&self) -> Option<u32> {
+            Some(1)
+        }
+
+        fn blockchain_info(&self) -> BlockChainInfo {
+            BlockChainInfo {
+                blocks: 1,
+                headers: 4,
+                bestblockhash: test_data::genesis().hash().into(),
+                difficulty: 1f64,
+                chainwork: 4.into(),
+                mediantime: 1000,
+                verificationprogress: 0.25,
+                initialblockdownload: true,
+                pruned: true,
+                pruneheight: Some(1),
+                compressionratio: Some(1.5),
+                warnings: "".into(),
+            }
+        }
+
+        fn stale_blocks(&self, _hours: u32) -> Vec<StaleBlock> {
+            vec![StaleBlock {
+                hash: GlobalH256::from(1).into(),
+                height: 1,
+                replacedby: test_data::genesis().hash().into(),
+                time: 1000,
+            }]
+        }
+
+        fn block_announcements(&self, _hours: u32) -> Vec<BlockAnnouncement> {
+            vec![BlockAnnouncement {
+                hash: GlobalH256::from(1).into(),
+                time: 1000,
+                announcers: vec![BlockAnnouncer {
+                    peer: 2,
+                    delayms: 0,
+                }],
+            }]
+        }
+
+        fn block_propagations(&self, _hours: u32) -> Vec<BlockPropagation> {
+            vec![BlockPropagation {
+                hash: GlobalH256::from(1).into(),
+                time: 1000,
+                peer: 2,
+                announced: true,
+                receivedms: Some(150),
+                verifiedms: Some(200),
+                connectedms: Some(210),
+            }]
+        }
+
+        fn chain_events(&self, _since_seq: u64) -> Vec<ChainEvent> {
+            vec![ChainEvent {
+                seq: 2,
+                event: ChainEventKind::Connected,
+                hash: test_data::genesis().hash().into(),
+                height: 0,
+                time: 1000,
+            }]
+        }
+
+        fn block_miner(&self, _hash: &GlobalH256) -> Option<Bytes> {
+            Some(Bytes::new(vec![0; 32]))
+        }
+
+        fn miner_blocks(
+            &self,
+            _miner: Bytes,
+            _start: u32,
+            _count: Option<u32>,
+        ) -> Result<MinerBlocks, Error> {
+            Ok(MinerBlocks {
+                total: 10,
+                heights: vec![2, 5],
+            })
+        }
+
+        fn invalidate_block(&self, _hash: GlobalH256) -> Result<(), Error> {
+            Ok(())
+        }
+
+        fn reconsider_block(&self, _hash: GlobalH256) -> Result<(), Error> {
+            Ok(())
+        }
+
+        fn rollback_last_reorg(&self) -> Result<GlobalH256, Error> {
+            Ok(test_data::block_h1().hash())
+        }
+
+        fn finalized_block(&self) -> Result<Option<FinalizedBlock>, Error> {
+            Ok(Some(FinalizedBlock {
+                hash: test_data::genesis().hash().into(),
+                height: 0,
+            }))
+        }
+
+        fn orphan_blocks(&self) -> Result<Vec<OrphanBlock>, Error> {
+            Ok(vec![OrphanBlock {
+                hash: GlobalH256::from(1).into(),
+                parent: test_data::genesis().hash().into(),
+                size: 100,
+                time: 1000,
+            }])
+        }
+
+        fn memory_info(&self) -> Result<MemoryInfo, Error> {
+            Ok(MemoryInfo {
+                limit: 1000,
+                used: 310,
+                orphanblocks: 100,
+                verificationqueue: 200,
+                serverqueue: 10,
+            })
+        }
+
+        fn server_log_stats(&self) -> Result<ServerLogStats, Error> {
+            Ok(ServerLogStats {
+                sampling: 10,
+                tasks: 100,
+                loggedtasks: 10,
+                blocksserved: 50,
+                notfound: 1,
+                throttled: 2,
+                inventoriesserved: 3,
+                headersserved: 40,
+                ignored: 4,
+            })
+        }
+
+        fn set_server_log_sampling(&self, _sampling: u32) -> Result<(), Error> {
+            Ok(())
+        }
+
+        fn chain_event_seq_at(&self, _time: u32) -> Option<u64> {
+            Some(1)
+        }
+
+        fn best_block_at_seq(&self, seq: u64) -> Option<HistoricalBestBlock> {
+            Some(HistoricalBestBlock {
+                seq: seq,
+                eventtime: 1000,
+                hash: test_data::genesis().hash().into(),
+                height: 0,
+                randomness_hex: "7788".to_owned(),
+            })
+        }
+
+        fn verify_chain(
+            &self,
+            _level: verification::VerificationLevel,
+            _depth: u32,
+        ) -> Result<VerifyChainResult, Error> {
+            Ok(VerifyChainResult {
+                checked: 5,
+                failure: Some(VerifyChainFailure {
+                    height: 1,
+                    hash: test_data::genesis().hash().into(),
+                    error: "Vdf".to_owned(),
+                }),
+            })
+        }
+
+        fn db_info(&self) -> Result<DbInfo, Error> {
+            Ok(DbInfo {
+                disksize: Some(1000),
+                datasize: 900,
+                overhead: Some(100),
+                columns: vec![DbColumnInfo {
+                    name: "blocks".to_owned(),
+                    keys: 2,
+                    size: 900,
+                }],
+            })
+        }
+
+        fn compact_db(&self) -> Result<(), Error> {
+            Ok(())
+        }
+    }
+
+    impl BlockChainClientCoreApi for ErrorBlockChainClientCore {
+        fn best_block_hash(&self) -> GlobalH256 {
+            test_data::genesis().hash()
+        }
+
+        fn block_count(&self) -> u32 {
+            1
+        }
+
+        fn block_hash(&self, _height: u32) -> Option<GlobalH256> {
+            None
+        }
+
+        fn fork_point(&self, _hash: &GlobalH256) -> Option<(u32, GlobalH256)> {
+            None
+        }
+
+        fn block_locator(&self, _height: Option<u32>) -> Option<Vec<GlobalH256>> {
+            None
         }
 
         fn difficulty(&self) -> f64 {
@@ -236,10 +1516,1454 @@ pub mod tests {
         fn verbose_block(&self, _hash: GlobalH256) -> Option<VerboseBlock> {
             None
         }
+
+        fn raw_block_headers(&self, _start: u32, _count: u32) -> Vec<RawBlockHeader> {
+            Vec::new()
+        }
+
+        fn verbose_block_headers(&self, _start: u32, _count: u32) -> Vec<VerboseBlockHeader> {
+            Vec::new()
+        }
+
+        fn block_stats(&self, _hash: GlobalH256) -> Option<BlockStats> {
+            None
+        }
+
+        fn pruned_height(&self) -> Option<u32> {
+            None
+        }
+
+        fn blockchain_info(&self) -> BlockChainInfo {
+            BlockChainInfo::default()
+        }
+
+        fn stale_blocks(&self, _hours: u32) -> Vec<StaleBlock> {
+            Vec::new()
+        }
+
+        fn block_announcements(&self, _hours: u32) -> Vec<BlockAnnouncement> {
+            Vec::new()
+        }
+
+        fn block_propagations(&self, _hours: u32) -> Vec<BlockPropagation> {
+            Vec::new()
+        }
+
+        fn chain_events(&self, _since_seq: u64) -> Vec<ChainEvent> {
+            Vec::new()
+        }
+
+        fn block_miner(&self, _hash: &GlobalH256) -> Option<Bytes> {
+            None
+        }
+
+        fn miner_blocks(
+            &self,
+            _miner: Bytes,
+            _start: u32,
+            _count: Option<u32>,
+        ) -> Result<MinerBlocks, Error> {
+            Err(execution(
+                "miner index is disabled. Start node with --miner-index",
+            ))
+        }
+
+        fn invalidate_block(&self, hash: GlobalH256) -> Result<(), Error> {
+            Err(block_not_found(H256::from(hash.reversed())))
+        }
+
+        fn reconsider_block(&self, hash: GlobalH256) -> Result<(), Error> {
+            Err(block_not_found(H256::from(hash.reversed())))
+        }
+
+        fn rollback_last_reorg(&self) -> Result<GlobalH256, Error> {
+            Err(execution("no reorganization is recorded"))
+        }
+
+        fn finalized_block(&self) -> Result<Option<FinalizedBlock>, Error> {
+            Err(execution("synchronization is not running"))
+        }
+
+        fn orphan_blocks(&self) -> Result<Vec<OrphanBlock>, Error> {
+            Err(execution("synchronization is not running"))
+        }
+
+        fn memory_info(&self) -> Result<MemoryInfo, Error> {
+            Err(execution("synchronization is not running"))
+        }
+
+        fn server_log_stats(&self) -> Result<ServerLogStats, Error> {
+            Err(execution("synchronization is not running"))
+        }
+
+        fn set_server_log_sampling(&self, _sampling: u32) -> Result<(), Error> {
+            Err(execution("synchronization is not running"))
+        }
+
+        fn chain_event_seq_at(&self, _time: u32) -> Option<u64> {
+            None
+        }
+
+        fn best_block_at_seq(&self, _seq: u64) -> Option<HistoricalBestBlock> {
+            None
+        }
+
+        fn verify_chain(
+            &self,
+            _level: verification::VerificationLevel,
+            _depth: u32,
+        ) -> Result<VerifyChainResult, Error> {
+            Err(execution("synchronization is not running"))
+        }
+
+        fn db_info(&self) -> Result<DbInfo, Error> {
+            Err(execution("IO error"))
+        }
+
+        fn compact_db(&self) -> Result<(), Error> {
+            Err(execution("IO error"))
+        }
+    }
+
+    #[test]
+    fn best_block_hash_success() {
+        let client = BlockChainClient::new(SuccessBlockChainClientCore::default());
+        let mut handler = IoHandler::new();
+        handler.extend_with(client.to_delegate());
+
+        let sample = handler
+            .handle_request_sync(
+                &(r#"
+			{
+				"jsonrpc": "2.0",
+				"method": "getbestblockhash",
+				"params": [],
+				"id": 1
+			}"#),
+            )
+            .unwrap();
+
+        // direct hash is 0e02cf1de23136c83e2aa473e226c02f5f2d4a7dffd16e49fafdfb5e7d6f5971
+        // but client expects reverse hash
+        assert_eq!(
+            &sample,
+            r#"{"jsonrpc":"2.0","result":"71596f7d5efbfdfa496ed1ff7d4a2d5f2fc026e273a42a3ec83631e21dcf020e","id":1}"#
+        );
+    }
+
+    #[test]
+    fn block_count_success() {
+        let client = BlockChainClient::new(SuccessBlockChainClientCore::default());
+        let mut handler = IoHandler::new();
+        handler.extend_with(client.to_delegate());
+
+        let sample = handler
+            .handle_request_sync(
+                &(r#"
+                    {
+                    	"jsonrpc": "2.0",
+                    	"method": "getblockcount",
+                    	"params": [],
+                    	"id": 1
+                    }"#),
+            )
+            .unwrap();
+
+        assert_eq!(&sample, r#"{"jsonrpc":"2.0","result":1,"id":1}"#);
+    }
+
+    #[test]
+    fn block_locator_success() {
+        let client = BlockChainClient::new(SuccessBlockChainClientCore::default());
+        let mut handler = IoHandler::new();
+        handler.extend_with(client.to_delegate());
+
+        let sample = handler
+            .handle_request_sync(
+                &(r#"
+                    {
+                    	"jsonrpc": "2.0",
+                    	"method": "getblocklocator",
+                    	"params": [],
+                    	"id": 1
+                    }"#),
+            )
+            .unwrap();
+
+        assert_eq!(
+            &sample,
+            &format!(
+                r#"{{"jsonrpc":"2.0","result":["{}","{}"],"id":1}}"#,
+                test_data::block_h1().hash().to_reversed_str(),
+                test_data::genesis().hash().to_reversed_str()
+            )
+        );
+    }
+
+    #[test]
+    fn block_locator_error() {
+        let client = BlockChainClient::new(ErrorBlockChainClientCore::default());
+        let mut handler = IoHandler::new();
+        handler.extend_with(client.to_delegate());
+
+        let sample = handler
+            .handle_request_sync(
+                &(r#"
+                    {
+                    	"jsonrpc": "2.0",
+                    	"method": "getblocklocator",
+                    	"params": [100],
+                    	"id": 1
+                    }"#),
+            )
+            .unwrap();
+
+        assert_eq!(
+            &sample,
+            r#"{"jsonrpc":"2.0","error":{"code":-32099,"message":"Block at given height is not found","data":"100"},"id":1}"#
+        );
+    }
+
+    #[test]
+    fn block_hash_success() {
+        let client = BlockChainClient::new(SuccessBlockChainClientCore::default());
+        let mut handler = IoHandler::new();
+        handler.extend_with(client.to_delegate());
+
+        let sample = handler
+            .handle_request_sync(
+                &(r#"
+                    {
+                    	"jsonrpc": "2.0",
+                    	"method": "getblockhash",
+                    	"params": [0],
+                    	"id": 1
+                    }"#),
+            )
+            .unwrap();
+
+        // direct hash is 0e02cf1de23136c83e2aa473e226c02f5f2d4a7dffd16e49fafdfb5e7d6f5971
+        // but client expects reverse hash
+        assert_eq!(
+            &sample,
+            r#"{"jsonrpc":"2.0","result":"71596f7d5efbfdfa496ed1ff7d4a2d5f2fc026e273a42a3ec83631e21dcf020e","id":1}"#
+        );
+    }
+
+    #[test]
+    fn block_hash_error() {
+        let client = BlockChainClient::new(ErrorBlockChainClientCore::default());
+        let mut handler = IoHandler::new();
+        handler.extend_with(client.to_delegate());
+
+        let sample = handler
+            .handle_request_sync(
+                &(r#"
+                    {
+                    	"jsonrpc": "2.0",
+                    	"method": "getblockhash",
+                    	"params": [0],
+                    	"id": 1
+                    }"#),
+            )
+            .unwrap();
+
+        assert_eq!(
+            &sample,
+            r#"{"jsonrpc":"2.0","error":{"code":-32099,"message":"Block at given height is not found","data":"0"},"id":1}"#
+        );
+    }
+
+    #[test]
+    fn miner_blocks_success() {
+        let client = BlockChainClient::new(SuccessBlockChainClientCore::default());
+        let mut handler = IoHandler::new();
+        handler.extend_with(client.to_delegate());
+
+        let sample = handler
+            .handle_request_sync(
+                &(r#"
+                    {
+                    	"jsonrpc": "2.0",
+                    	"method": "getminerblocks",
+                    	"params": ["6969696969696969696969696969696969696969696969696969696969696969", 0, 10],
+                    	"id": 1
+                    }"#),
+            )
+            .unwrap();
+
+        assert_eq!(
+            &sample,
+            r#"{"jsonrpc":"2.0","result":{"total":10,"heights":[2,5]},"id":1}"#
+        );
+
+        let sample = handler
+            .handle_request_sync(
+                &(r#"
+                    {
+                    	"jsonrpc": "2.0",
+                    	"method": "getminerblocks",
+                    	"params": ["6969"],
+                    	"id": 1
+                    }"#),
+            )
+            .unwrap();
+
+        assert_eq!(
+            &sample,
+            r#"{"jsonrpc":"2.0","error":{"code":-32602,"message":"Couldn't parse parameters: pubkey","data":"\"public key should be 32 bytes\""},"id":1}"#
+        );
+    }
+
+    #[test]
+    fn miner_blocks_error() {
+        let client = BlockChainClient::new(ErrorBlockChainClientCore::default());
+        let mut handler = IoHandler::new();
+        handler.extend_with(client.to_delegate());
+
+        let sample = handler
+            .handle_request_sync(
+                &(r#"
+                    {
+                    	"jsonrpc": "2.0",
+                    	"method": "getminerblocks",
+                    	"params": ["6969696969696969696969696969696969696969696969696969696969696969"],
+                    	"id": 1
+                    }"#),
+            )
+            .unwrap();
+
+        assert_eq!(
+            &sample,
+            r#"{"jsonrpc":"2.0","error":{"code":-32015,"message":"Execution error.","data":"\"miner index is disabled. Start node with --miner-index\""},"id":1}"#
+        );
+    }
+
+    #[test]
+    fn invalidate_block_success() {
+        let client = BlockChainClient::new(SuccessBlockChainClientCore::default());
+        let mut handler = IoHandler::new();
+        handler.extend_with(client.to_delegate());
+
+        let sample = handler
+            .handle_request_sync(
+                &(r#"
+                    {
+                    	"jsonrpc": "2.0",
+                    	"method": "invalidateblock",
+                    	"params": ["000000006a625f06636b8bb6ac7b960a8d03705d1ace08b1a19da3fdcc99ddbd"],
+                    	"id": 1
+                    }"#),
+            )
+            .unwrap();
+
+        assert_eq!(&sample, r#"{"jsonrpc":"2.0","result":null,"id":1}"#);
+    }
+
+    #[test]
+    fn reconsider_block_error() {
+        let client = BlockChainClient::new(ErrorBlockChainClientCore::default());
+        let mut handler = IoHandler::new();
+        handler.extend_with(client.to_delegate());
+
+        let sample = handler
+            .handle_request_sync(
+                &(r#"
+                    {
+                    	"jsonrpc": "2.0",
+                    	"method": "reconsiderblock",
+                    	"params": ["000000006a625f06636b8bb6ac7b960a8d03705d1ace08b1a19da3fdcc99ddbd"],
+                    	"id": 1
+                    }"#),
+            )
+            .unwrap();
+
+        assert_eq!(
+            &sample,
+            r#"{"jsonrpc":"2.0","error":{"code":-32099,"message":"Block with given hash is not found","data":"000000006a625f06636b8bb6ac7b960a8d03705d1ace08b1a19da3fdcc99ddbd"},"id":1}"#
+        );
+    }
+
+    #[test]
+    fn rollback_last_reorg_success() {
+        let client = BlockChainClient::new(SuccessBlockChainClientCore::default());
+        let mut handler = IoHandler::new();
+        handler.extend_with(client.to_delegate());
+
+        let sample = handler
+            .handle_request_sync(
+                &(r#"
+                    {
+                    	"jsonrpc": "2.0",
+                    	"method": "rollbacklastreorg",
+                    	"params": [],
+                    	"id": 1
+                    }"#),
+            )
+            .unwrap();
+
+        assert_eq!(
+            &sample,
+            &format!(
+                r#"{{"jsonrpc":"2.0","result":"{}","id":1}}"#,
+                test_data::block_h1().hash().to_reversed_str()
+            )
+        );
+    }
+
+    #[test]
+    fn rollback_last_reorg_error() {
+        let client = BlockChainClient::new(ErrorBlockChainClientCore::default());
+        let mut handler = IoHandler::new();
+        handler.extend_with(client.to_delegate());
+
+        let sample = handler
+            .handle_request_sync(
+                &(r#"
+                    {
+                    	"jsonrpc": "2.0",
+                    	"method": "rollbacklastreorg",
+                    	"params": [],
+                    	"id": 1
+                    }"#),
+            )
+            .unwrap();
+
+        assert_eq!(
+            &sample,
+            r#"{"jsonrpc":"2.0","error":{"code":-32015,"message":"Execution error.","data":"\"no reorganization is recorded\""},"id":1}"#
+        );
+    }
+
+    #[test]
+    fn difficulty_success() {
+        let client = BlockChainClient::new(SuccessBlockChainClientCore::default());
+        let mut handler = IoHandler::new();
+        handler.extend_with(client.to_delegate());
+
+        let sample = handler
+            .handle_request_sync(
+                &(r#"
+                    {
+                    	"jsonrpc": "2.0",
+                    	"method": "getdifficulty",
+                    	"params": [],
+                    	"id": 1
+                    }"#),
+            )
+            .unwrap();
+
+        assert_eq!(&sample, r#"{"jsonrpc":"2.0","result":1.0,"id":1}"#);
+    }
+
+    #[test]
+    fn verbose_block_contents() {
+        let storage = Arc::new(BlockChainDatabase::init_test_chain(vec![
+            test_data::genesis().into(),
+            test_data::block_h1().into(),
+            test_data::block_h2().into(),
+        ]));
+
+        let core = BlockChainClientCore::new(storage, None, None);
+
+        // get info on block #1:
+        let verbose_block = core.verbose_block(test_data::block_h1().hash().into());
+        assert_eq!(
+            verbose_block,
+            Some(VerboseBlock {
+                hash: test_data::block_h1().hash().into(),
+                confirmations: 2, // h1 + h2
+                size: 859,
+                height: Some(1),
+                version: 1,
+                version_hex: "1".to_owned(),
+                pubkey_hex: "0000000000000000000000000000000000000000000000000000000000000000"
+                    .to_owned(),
+                randomness_hex: "966e8f70aa11fdb48b7d463c57db4722d1ac994640879f98a616b165c3a5df9db31c0dee6cbcebf46c427049ead7b7edbe39970ce7b0fecf2a9b07c4381e466055f1e8fc152f304f3cfdbb475bdaa93a9f95de38ff1d091d175bfea579574f444c1650a6ab4bfd97d2b19f6653549517d91b882a5560da1b6b0188ae67d016dfe2dfe795593e844b2d32e29b985b425038c83c7aaf9b9ea3d73065767f234da6f25e43137a8d91265d16afdb9ab728f619385a31734ba769bfb5ff1bea3869043534d1697efc3148a0867ceacb6d2d24508ea1b201abfbb23604aa7cc3dd7dbfe9c002f2f26199aea18e9c24e870bfa090505eecc307d895caf7bdadccbdfbb4".to_owned(),
+                time: 1001,
+                mediantime: Some(1000),
+                iterations: 4,
+                bits: 545259519,
+                difficulty: 0.00000000046565423739069247,
+                chainwork: 4.into(),
+                previousblockhash: Some(
+                    test_data::genesis().hash().into()
+                ),
+                nextblockhash: Some(
+                    test_data::block_h2().hash().into()
+                ),
+            })
+        );
+
+        // get info on block #2:
+        let verbose_block = core.verbose_block(test_data::block_h2().hash().into());
+        assert_eq!(
+            verbose_block,
+            Some(VerboseBlock {
+                hash: test_data::block_h2().hash().into(),
+                confirmations: 1, // h2
+                size: 859,
+                height: Some(2),
+                version: 1,
+                version_hex: "1".to_owned(),
+                pubkey_hex: "0000000000000000000000000000000000000000000000000000000000000000"
+                    .to_owned(),
+                randomness_hex: "5ced562ee6e1c95c2e034d3ed4fc38508fd4cf0a1faa466303c79b7bc9fcaf4d60d1dc53884698e891e5ee1f661f79e58631b8003d0fd18d526fcc3f95e97597c125573895ffc3a6a9b1458b1a383cdb1ea151f2b01f62980d6e92aaef8d0c5a06e56995176a75cb8aa37c94a5e677e1dd91e1fb3874b72f614507b5e9b29bc5e43264123e8fb29664558dad1aa4e350843262effaffe63685765fa7028ce10b81d13059a3dfc8207b7bc37ce4e8e23d22a797b454abf7777c641a534a2bfd5d67f5d182c75a4a7fe9f3a31eb85afe533cc55edca86b9dfe6e8d66c2c00a90097ac0ff8ca06f00dd524018fb422ad68994ec537cd12ab10f4144a8f3290dae1a".to_owned(),
+                time: 1002,
+                mediantime: Some(1001),
+                iterations: 4,
+                bits: 545259519,
+                difficulty: 0.00000000046565423739069247,
+                chainwork: 6.into(),
+                previousblockhash: Some(
+                    test_data::block_h1().hash().into()
+                ),
+                nextblockhash: None,
+            })
+        );
+    }
+
+    #[test]
+    fn raw_block_success() {
+        let client = BlockChainClient::new(SuccessBlockChainClientCore::default());
+        let mut handler = IoHandler::new();
+        handler.extend_with(client.to_delegate());
+
+        let expected = r#"{"jsonrpc":"2.0","result":"0100000027cf1cfd394338d0af68c0bd84960c8cb1becb8a10991df558a8fb167ef65e63ea030000ffff7f2020000000000000000000000000000000000000000000000000000000000000000004000000fd00015ced562ee6e1c95c2e034d3ed4fc38508fd4cf0a1faa466303c79b7bc9fcaf4d60d1dc53884698e891e5ee1f661f79e58631b8003d0fd18d526fcc3f95e97597c125573895ffc3a6a9b1458b1a383cdb1ea151f2b01f62980d6e92aaef8d0c5a06e56995176a75cb8aa37c94a5e677e1dd91e1fb3874b72f614507b5e9b29bc5e43264123e8fb29664558dad1aa4e350843262effaffe63685765fa7028ce10b81d13059a3dfc8207b7bc37ce4e8e23d22a797b454abf7777c641a534a2bfd5d67f5d182c75a4a7fe9f3a31eb85afe533cc55edca86b9dfe6e8d66c2c00a90097ac0ff8ca06f00dd524018fb422ad68994ec537cd12ab10f4144a8f3290dae1a02fd000113f6cd3e8a5010bd7383bc1540735721fa5f6b433e67c5dfb9a4d9e30559ae3c08627385820f34535edcad1d1b36019e5f615e70661c3e9cb553643fd8003096a3079c67ac289eb122abb1d7cace1ae8f4a3ec6959b89c43a16ffe785fb166ebe94c2609d65795fc58f6faac12e6e00a41099b44f6a53344db5f2846bf2c5782a503234822f8d75257d5a04d352cc85fb569157e8c1195f77208359759229f1c3e81ae82275b6362350a7fb430abf99c1a0d054eaa4aaf90e63fb1028740af75ff05c8286338aef128f6fb75d93a78c08dd4e05f0b91516cb15b1832d473f5539daf6b6c1176ff460ace9049029278640be9bc5a1893030387d72760e1ff3532fd00014be8ac242b610c307dee148c2d3cbf0e460e6dbf00aadaf1ee202c87a630ec96b74d6caedf9932c98d35949cdfbca69e32ae72b8b200dc41f52ea5a890c3fc64370c124f9fa9478fcaaa8da260ffdb07492ebc60ee642f459fa75a7e9839fb8442f03610a807a662a45364f729352682d02b4a2c353c11d0dde3a802bceb8af7149f499152d1e7b51bc627abaa63e270442cdf0a3d9d12aa2590abd0ab9209faca4a2b3c8cb1ff673e282f3d89fcee7d28b1139b1c0646ad2248a628fbc71cb1228a4ced677e589b17b8f688032921237cf2d723c204a89259d321a8c88cb18303669ef278f6480ea1bad355cc86d431157bff750d4918af83c47f1889efa05e","id":1}"#;
+
+        let sample = handler
+             .handle_request_sync(
+                 &(r#"
+                    {
+                    	"jsonrpc": "2.0",
+                    	"method": "getblock",
+                    	"params": ["29483ce82fad9d817f3ac76b8bd8f221cd5a6aa882523da8fcf19df6c0f60d40", false],
+                    	"id": 1
+                    }"#),
+             )
+             .unwrap();
+        assert_eq!(&sample, expected);
+
+        // try without optional parameter
+        let sample = handler
+            .handle_request_sync(
+                &(r#"
+                    {
+                    	"jsonrpc": "2.0",
+                    	"method": "getblock",
+                    	"params": ["29483ce82fad9d817f3ac76b8bd8f221cd5a6aa882523da8fcf19df6c0f60d40"],
+                    	"id": 1
+                    }"#),
+            )
+            .unwrap();
+        assert_eq!(&sample, expected);
+    }
+
+    #[test]
+    fn raw_block_error() {
+        let client = BlockChainClient::new(ErrorBlockChainClientCore::default());
+        let mut handler = IoHandler::new();
+        handler.extend_with(client.to_delegate());
+
+        let sample = handler
+            .handle_request_sync(
+                &(r#"
+                    {
+                    	"jsonrpc": "2.0",
+                    	"method": "getblock",
+                    	"params": ["000000006a625f06636b8bb6ac7b960a8d03705d1ace08b1a19da3fdcc99ddbd", false],
+                    	"id": 1
+                    }"#),
+            )
+            .unwrap();
+
+        assert_eq!(
+            &sample,
+            r#"{"jsonrpc":"2.0","error":{"code":-32099,"message":"Block with given hash is not found","data":"000000006a625f06636b8bb6ac7b960a8d03705d1ace08b1a19da3fdcc99ddbd"},"id":1}"#
+        );
+    }
+
+    #[test]
+    fn verbose_block_success() {
+        let client = BlockChainClient::new(SuccessBlockChainClientCore::default());
+        let mut handler = IoHandler::new();
+        handler.extend_with(client.to_delegate());
+
+        let sample = handler
+             .handle_request_sync(
+                 &(r#"
+                    {
+                    	"jsonrpc": "2.0",
+                    	"method": "getblock",
+                    	"params": ["29483ce82fad9d817f3ac76b8bd8f221cd5a6aa882523da8fcf19df6c0f60d40",true],
+                    	"id": 1
+                    }"#),
+             )
+             .unwrap();
+
+        assert_eq!(
+            &sample,
+            r#"{"jsonrpc":"2.0","result":{"bits":545259519,"chainwork":"0","confirmations":1,"difficulty":1.0,"hash":"29483ce82fad9d817f3ac76b8bd8f221cd5a6aa882523da8fcf19df6c0f60d40","height":2,"iterations":4,"mediantime":null,"nextblockhash":null,"previousblockhash":"635ef67e16fba858f51d99108acbbeb18c0c9684bdc068afd0384339fd1ccf27","pubkeyHex":"0000000000000000000000000000000000000000000000000000000000000000","randomnessHex":"5ced562ee6e1c95c2e034d3ed4fc38508fd4cf0a1faa466303c79b7bc9fcaf4d60d1dc53884698e891e5ee1f661f79e58631b8003d0fd18d526fcc3f95e97597c125573895ffc3a6a9b1458b1a383cdb1ea151f2b01f62980d6e92aaef8d0c5a06e56995176a75cb8aa37c94a5e677e1dd91e1fb3874b72f614507b5e9b29bc5e43264123e8fb29664558dad1aa4e350843262effaffe63685765fa7028ce10b81d13059a3dfc8207b7bc37ce4e8e23d22a797b454abf7777c641a534a2bfd5d67f5d182c75a4a7fe9f3a31eb85afe533cc55edca86b9dfe6e8d66c2c00a90097ac0ff8ca06f00dd524018fb422ad68994ec537cd12ab10f4144a8f3290dae1a","size":859,"time":1002,"version":1,"versionHex":"1"},"id":1}"#
+        );
+    }
+
+    #[test]
+    fn verbose_block_error() {
+        let client = BlockChainClient::new(ErrorBlockChainClientCore::default());
+        let mut handler = IoHandler::new();
+        handler.extend_with(client.to_delegate());
+
+        let sample = handler
+             .handle_request_sync(
+                 &(r#"
+                    {
+                    	"jsonrpc": "2.0",
+                    	"method": "getblock",
+                    	"params": ["000000006a625f06636b8bb6ac7b960a8d03705d1ace08b1a19da3fdcc99ddbd", true],
+                    	"id": 1
+                    }"#),
+             )
+             .unwrap();
+
+        assert_eq!(
+            &sample,
+            r#"{"jsonrpc":"2.0","error":{"code":-32099,"message":"Block with given hash is not found","data":"000000006a625f06636b8bb6ac7b960a8d03705d1ace08b1a19da3fdcc99ddbd"},"id":1}"#
+        );
+    }
+
+    #[test]
+    fn block_by_height_success() {
+        let client = BlockChainClient::new(SuccessBlockChainClientCore::default());
+        let mut handler = IoHandler::new();
+        handler.extend_with(client.to_delegate());
+
+        let by_hash = handler
+            .handle_request_sync(
+                &(r#"
+                    {
+                    	"jsonrpc": "2.0",
+                    	"method": "getblock",
+                    	"params": ["29483ce82fad9d817f3ac76b8bd8f221cd5a6aa882523da8fcf19df6c0f60d40", true],
+                    	"id": 1
+                    }"#),
+            )
+            .unwrap();
+        let by_height = handler
+            .handle_request_sync(
+                &(r#"
+                    {
+                    	"jsonrpc": "2.0",
+                    	"method": "getblock",
+                    	"params": [2, true],
+                    	"id": 1
+                    }"#),
+            )
+            .unwrap();
+        assert_eq!(by_height, by_hash);
+    }
+
+    #[test]
+    fn block_by_height_error() {
+        let client = BlockChainClient::new(ErrorBlockChainClientCore::default());
+        let mut handler = IoHandler::new();
+        handler.extend_with(client.to_delegate());
+
+        let sample = handler
+            .handle_request_sync(
+                &(r#"
+                    {
+                    	"jsonrpc": "2.0",
+                    	"method": "getblock",
+                    	"params": [2],
+                    	"id": 1
+                    }"#),
+            )
+            .unwrap();
+
+        assert_eq!(
+            &sample,
+            r#"{"jsonrpc":"2.0","error":{"code":-32099,"message":"Block at given height is not found","data":"2"},"id":1}"#
+        );
+    }
+
+    #[test]
+    fn block_headers_success() {
+        let client = BlockChainClient::new(SuccessBlockChainClientCore::default());
+        let mut handler = IoHandler::new();
+        handler.extend_with(client.to_delegate());
+
+        let sample = handler
+            .handle_request_sync(
+                &(r#"
+                    {
+                    	"jsonrpc": "2.0",
+                    	"method": "getblockheaders",
+                    	"params": [1, 10, true],
+                    	"id": 1
+                    }"#),
+            )
+            .unwrap();
+
+        assert_eq!(
+            &sample,
+            r#"{"jsonrpc":"2.0","result":[{"hash":"29483ce82fad9d817f3ac76b8bd8f221cd5a6aa882523da8fcf19df6c0f60d40","confirmations":1,"height":2,"version":1,"versionHex":"1","pubkeyHex":"00","randomnessHex":"7788","time":1002,"mediantime":1001,"iterations":4,"bits":545259519,"difficulty":1.0,"previousblockhash":"635ef67e16fba858f51d99108acbbeb18c0c9684bdc068afd0384339fd1ccf27","nextblockhash":null}],"id":1}"#
+        );
+
+        let sample = handler
+            .handle_request_sync(
+                &(r#"
+                    {
+                    	"jsonrpc": "2.0",
+                    	"method": "getblockheaders",
+                    	"params": [1, 10],
+                    	"id": 1
+                    }"#),
+            )
+            .unwrap();
+
+        let expected_header: RawBlockHeader = serialize(test_data::block_h2().header()).into();
+        assert_eq!(
+            sample,
+            format!(
+                r#"{{"jsonrpc":"2.0","result":[{}],"id":1}}"#,
+                ::serde_json::to_string(&expected_header).unwrap()
+            )
+        );
+    }
+
+    #[test]
+    fn block_headers_error() {
+        let client = BlockChainClient::new(ErrorBlockChainClientCore::default());
+        let mut handler = IoHandler::new();
+        handler.extend_with(client.to_delegate());
+
+        let sample = handler
+            .handle_request_sync(
+                &(r#"
+                    {
+                    	"jsonrpc": "2.0",
+                    	"method": "getblockheaders",
+                    	"params": [2, 10],
+                    	"id": 1
+                    }"#),
+            )
+            .unwrap();
+        assert_eq!(
+            &sample,
+            r#"{"jsonrpc":"2.0","error":{"code":-32099,"message":"Block at given height is not found","data":"2"},"id":1}"#
+        );
+
+        let sample = handler
+            .handle_request_sync(
+                &(r#"
+                    {
+                    	"jsonrpc": "2.0",
+                    	"method": "getblockheaders",
+                    	"params": [0, 2001],
+                    	"id": 1
+                    }"#),
+            )
+            .unwrap();
+        assert_eq!(
+            &sample,
+            r#"{"jsonrpc":"2.0","error":{"code":-32602,"message":"Couldn't parse parameters: count","data":"\"expected 1..2000\""},"id":1}"#
+        );
+    }
+
+    #[test]
+    fn block_header_by_height_success() {
+        let client = BlockChainClient::new(SuccessBlockChainClientCore::default());
+        let mut handler = IoHandler::new();
+        handler.extend_with(client.to_delegate());
+
+        let sample = handler
+            .handle_request_sync(
+                &(r#"
+                    {
+                    	"jsonrpc": "2.0",
+                    	"method": "getblockheaderbyheight",
+                    	"params": [1, true],
+                    	"id": 1
+                    }"#),
+            )
+            .unwrap();
+
+        assert_eq!(
+            &sample,
+            r#"{"jsonrpc":"2.0","result":{"hash":"29483ce82fad9d817f3ac76b8bd8f221cd5a6aa882523da8fcf19df6c0f60d40","confirmations":1,"height":2,"version":1,"versionHex":"1","pubkeyHex":"00","randomnessHex":"7788","time":1002,"mediantime":1001,"iterations":4,"bits":545259519,"difficulty":1.0,"previousblockhash":"635ef67e16fba858f51d99108acbbeb18c0c9684bdc068afd0384339fd1ccf27","nextblockhash":null},"id":1}"#
+        );
+    }
+
+    #[test]
+    fn block_header_by_height_error() {
+        let client = BlockChainClient::new(ErrorBlockChainClientCore::default());
+        let mut handler = IoHandler::new();
+        handler.extend_with(client.to_delegate());
+
+        let sample = handler
+            .handle_request_sync(
+                &(r#"
+                    {
+                    	"jsonrpc": "2.0",
+                    	"method": "getblockheaderbyheight",
+                    	"params": [1],
+                    	"id": 1
+                    }"#),
+            )
+            .unwrap();
+
+        assert_eq!(
+            &sample,
+            r#"{"jsonrpc":"2.0","error":{"code":-32099,"message":"Block at given height is not found","data":"1"},"id":1}"#
+        );
+    }
+
+    #[test]
+    fn block_headers_contents() {
+        let storage = Arc::new(BlockChainDatabase::init_test_chain(vec![
+            test_data::genesis().into(),
+            test_data::block_h1().into(),
+            test_data::block_h2().into(),
+        ]));
+
+        let core = BlockChainClientCore::new(storage, None, None);
+
+        let raw_headers = core.raw_block_headers(1, 10);
+        assert_eq!(
+            raw_headers,
+            vec![
+                RawBlockHeader::from(serialize(test_data::block_h1().header())),
+                RawBlockHeader::from(serialize(test_data::block_h2().header())),
+            ]
+        );
+        assert!(core.raw_block_headers(3, 10).is_empty());
+
+        let verbose_headers = core.verbose_block_headers(0, 2);
+        assert_eq!(verbose_headers.len(), 2);
+        assert_eq!(verbose_headers[0].hash, test_data::genesis().hash().into());
+        assert_eq!(verbose_headers[0].confirmations, 3);
+        assert_eq!(verbose_headers[0].previousblockhash, None);
+        assert_eq!(
+            verbose_headers[0].nextblockhash,
+            Some(test_data::block_h1().hash().into())
+        );
+        assert_eq!(verbose_headers[1].height, 1);
+        assert_eq!(verbose_headers[1].confirmations, 2);
+        assert_eq!(verbose_headers[1].mediantime, 1000);
+        assert_eq!(
+            verbose_headers[1].previousblockhash,
+            Some(test_data::genesis().hash().into())
+        );
+    }
+
+    #[test]
+    fn finalized_block_success() {
+        let client = BlockChainClient::new(SuccessBlockChainClientCore::default());
+        let mut handler = IoHandler::new();
+        handler.extend_with(client.to_delegate());
+
+        let sample = handler
+            .handle_request_sync(
+                &(r#"
+                    {
+                    	"jsonrpc": "2.0",
+                    	"method": "getfinalizedblock",
+                    	"params": [],
+                    	"id": 1
+                    }"#),
+            )
+            .unwrap();
+
+        assert_eq!(
+            &sample,
+            r#"{"jsonrpc":"2.0","result":{"hash":"71596f7d5efbfdfa496ed1ff7d4a2d5f2fc026e273a42a3ec83631e21dcf020e","height":0},"id":1}"#
+        );
+    }
+
+    #[test]
+    fn finalized_block_error() {
+        let client = BlockChainClient::new(ErrorBlockChainClientCore::default());
+        let mut handler = IoHandler::new();
+        handler.extend_with(client.to_delegate());
+
+        let sample = handler
+            .handle_request_sync(
+                &(r#"
+                    {
+                    	"jsonrpc": "2.0",
+                    	"method": "getfinalizedblock",
+                    	"params": [],
+                    	"id": 1
+                    }"#),
+            )
+            .unwrap();
+
+        assert_eq!(
+            &sample,
+            r#"{"jsonrpc":"2.0","error":{"code":-32015,"message":"Execution error.","data":"\"synchronization is not running\""},"id":1}"#
+        );
+    }
+
+    #[test]
+    fn orphan_blocks_success() {
+        let client = BlockChainClient::new(SuccessBlockChainClientCore::default());
+        let mut handler = IoHandler::new();
+        handler.extend_with(client.to_delegate());
+
+        let sample = handler
+            .handle_request_sync(
+                &(r#"
+                    {
+                    	"jsonrpc": "2.0",
+                    	"method": "getorphanblocks",
+                    	"params": [],
+                    	"id": 1
+                    }"#),
+            )
+            .unwrap();
+
+        assert_eq!(
+            &sample,
+            r#"{"jsonrpc":"2.0","result":["0000000000000000000000000000000000000000000000000000000000000001"],"id":1}"#
+        );
+
+        let sample = handler
+            .handle_request_sync(
+                &(r#"
+                    {
+                    	"jsonrpc": "2.0",
+                    	"method": "getorphanblocks",
+                    	"params": [true],
+                    	"id": 1
+                    }"#),
+            )
+            .unwrap();
+
+        assert_eq!(
+            &sample,
+            r#"{"jsonrpc":"2.0","result":[{"hash":"0000000000000000000000000000000000000000000000000000000000000001","parent":"71596f7d5efbfdfa496ed1ff7d4a2d5f2fc026e273a42a3ec83631e21dcf020e","size":100,"time":1000}],"id":1}"#
+        );
+    }
+
+    #[test]
+    fn orphan_blocks_error() {
+        let client = BlockChainClient::new(ErrorBlockChainClientCore::default());
+        let mut handler = IoHandler::new();
+        handler.extend_with(client.to_delegate());
+
+        let sample = handler
+            .handle_request_sync(
+                &(r#"
+                    {
+                    	"jsonrpc": "2.0",
+                    	"method": "getorphanblocks",
+                    	"params": [],
+                    	"id": 1
+                    }"#),
+            )
+            .unwrap();
+
+        assert_eq!(
+            &sample,
+            r#"{"jsonrpc":"2.0","error":{"code":-32015,"message":"Execution error.","data":"\"synchronization is not running\""},"id":1}"#
+        );
+    }
+
+    #[test]
+    fn memory_info_success() {
+        let client = BlockChainClient::new(SuccessBlockChainClientCore::default());
+        let mut handler = IoHandler::new();
+        handler.extend_with(client.to_delegate());
+
+        let sample = handler
+            .handle_request_sync(
+                &(r#"
+                    {
+                    	"jsonrpc": "2.0",
+                    	"method": "getmemoryinfo",
+                    	"params": [],
+                    	"id": 1
+                    }"#),
+            )
+            .unwrap();
+
+        assert_eq!(
+            &sample,
+            r#"{"jsonrpc":"2.0","result":{"limit":1000,"used":310,"orphanblocks":100,"verificationqueue":200,"serverqueue":10},"id":1}"#
+        );
+    }
+
+    #[test]
+    fn memory_info_error() {
+        let client = BlockChainClient::new(ErrorBlockChainClientCore::default());
+        let mut handler = IoHandler::new();
+        handler.extend_with(client.to_delegate());
+
+        let sample = handler
+            .handle_request_sync(
+                &(r#"
+                    {
+                    	"jsonrpc": "2.0",
+                    	"method": "getmemoryinfo",
+                    	"params": [],
+                    	"id": 1
+                    }"#),
+            )
+            .unwrap();
+
+        assert_eq!(
+            &sample,
+            r#"{"jsonrpc":"2.0","error":{"code":-32015,"message":"Execution error.","data":"\"synchronization is not running\""},"id":1}"#
+        );
+    }
+
+    #[test]
+    fn server_log_stats_success() {
+        let client = BlockChainClient::new(SuccessBlockChainClientCore::default());
+        let mut handler = IoHandler::new();
+        handler.extend_with(client.to_delegate());
+
+        let sample = handler
+            .handle_request_sync(
+                &(r#"
+                    {
+                    	"jsonrpc": "2.0",
+                    	"method": "getserverlogstats",
+                    	"params": [],
+                    	"id": 1
+                    }"#),
+            )
+            .unwrap();
+
+        assert_eq!(
+            &sample,
+            r#"{"jsonrpc":"2.0","result":{"sampling":10,"tasks":100,"loggedtasks":10,"blocksserved":50,"notfound":1,"throttled":2,"inventoriesserved":3,"headersserved":40,"ignored":4},"id":1}"#
+        );
+
+        let sample = handler
+            .handle_request_sync(
+                &(r#"
+                    {
+                    	"jsonrpc": "2.0",
+                    	"method": "setserverlogsampling",
+                    	"params": [100],
+                    	"id": 1
+                    }"#),
+            )
+            .unwrap();
+
+        assert_eq!(&sample, r#"{"jsonrpc":"2.0","result":null,"id":1}"#);
+    }
+
+    #[test]
+    fn set_server_log_sampling_error() {
+        let client = BlockChainClient::new(ErrorBlockChainClientCore::default());
+        let mut handler = IoHandler::new();
+        handler.extend_with(client.to_delegate());
+
+        let sample = handler
+            .handle_request_sync(
+                &(r#"
+                    {
+                    	"jsonrpc": "2.0",
+                    	"method": "setserverlogsampling",
+                    	"params": [100],
+                    	"id": 1
+                    }"#),
+            )
+            .unwrap();
+
+        assert_eq!(
+            &sample,
+            r#"{"jsonrpc":"2.0","error":{"code":-32015,"message":"Execution error.","data":"\"synchronization is not running\""},"id":1}"#
+        );
+    }
+
+    #[test]
+    fn best_block_at_success() {
+        let client = BlockChainClient::new(SuccessBlockChainClientCore::default());
+        let mut handler = IoHandler::new();
+        handler.extend_with(client.to_delegate());
+
+        let sample = handler
+            .handle_request_sync(
+                &(r#"
+                    {
+                    	"jsonrpc": "2.0",
+                    	"method": "getbestblockat",
+                    	"params": [1500],
+                    	"id": 1
+                    }"#),
+            )
+            .unwrap();
+
+        assert_eq!(
+            &sample,
+            r#"{"jsonrpc":"2.0","result":{"seq":1,"eventtime":1000,"hash":"71596f7d5efbfdfa496ed1ff7d4a2d5f2fc026e273a42a3ec83631e21dcf020e","height":0,"randomnessHex":"7788"},"id":1}"#
+        );
+    }
+
+    #[test]
+    fn best_block_at_not_retained() {
+        let client = BlockChainClient::new(ErrorBlockChainClientCore::default());
+        let mut handler = IoHandler::new();
+        handler.extend_with(client.to_delegate());
+
+        let sample = handler
+            .handle_request_sync(
+                &(r#"
+                    {
+                    	"jsonrpc": "2.0",
+                    	"method": "getbestblockatevent",
+                    	"params": [7],
+                    	"id": 1
+                    }"#),
+            )
+            .unwrap();
+
+        assert_eq!(
+            &sample,
+            r#"{"jsonrpc":"2.0","error":{"code":-32098,"message":"Chain state at given point is not retained","data":"7"},"id":1}"#
+        );
+    }
+
+    #[test]
+    fn best_block_at_contents() {
+        let storage = Arc::new(BlockChainDatabase::init_test_chain(vec![
+            test_data::genesis().into(),
+            test_data::block_h1().into(),
+        ]));
+        let core = BlockChainClientCore::new(storage, None, None);
+
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs() as u32)
+            .unwrap_or(0);
+        assert_eq!(core.chain_event_seq_at(now + 1000), Some(2));
+        assert_eq!(core.chain_event_seq_at(0), None);
+
+        let block = core.best_block_at_seq(1).unwrap();
+        assert_eq!(block.seq, 1);
+        assert_eq!(block.height, 0);
+        assert_eq!(block.hash, test_data::genesis().hash().into());
+        assert_eq!(
+            block.randomness_hex,
+            test_data::genesis()
+                .block_header
+                .randomness
+                .to_string_radix(16)
+        );
+        assert_eq!(core.best_block_at_seq(2).unwrap().height, 1);
+        assert_eq!(core.best_block_at_seq(3), None);
+    }
+
+    #[test]
+    fn prune_height_success() {
+        let client = BlockChainClient::new(SuccessBlockChainClientCore::default());
+        let mut handler = IoHandler::new();
+        handler.extend_with(client.to_delegate());
+
+        let sample = handler
+            .handle_request_sync(
+                &(r#"
+                    {
+                    	"jsonrpc": "2.0",
+                    	"method": "getpruneheight",
+                    	"params": [],
+                    	"id": 1
+                    }"#),
+            )
+            .unwrap();
+
+        assert_eq!(&sample, r#"{"jsonrpc":"2.0","result":1,"id":1}"#);
+    }
+
+    #[test]
+    fn prune_height_not_pruned() {
+        let client = BlockChainClient::new(ErrorBlockChainClientCore::default());
+        let mut handler = IoHandler::new();
+        handler.extend_with(client.to_delegate());
+
+        let sample = handler
+            .handle_request_sync(
+                &(r#"
+                    {
+                    	"jsonrpc": "2.0",
+                    	"method": "getpruneheight",
+                    	"params": [],
+                    	"id": 1
+                    }"#),
+            )
+            .unwrap();
+
+        assert_eq!(&sample, r#"{"jsonrpc":"2.0","result":null,"id":1}"#);
+    }
+
+    #[test]
+    fn block_stats_success() {
+        let client = BlockChainClient::new(SuccessBlockChainClientCore::default());
+        let mut handler = IoHandler::new();
+        handler.extend_with(client.to_delegate());
+
+        let sample = handler
+            .handle_request_sync(
+                &(r#"
+                    {
+                    	"jsonrpc": "2.0",
+                    	"method": "getblockstats",
+                    	"params": [2],
+                    	"id": 1
+                    }"#),
+            )
+            .unwrap();
+
+        assert_eq!(
+            &sample,
+            r#"{"jsonrpc":"2.0","result":{"hash":"29483ce82fad9d817f3ac76b8bd8f221cd5a6aa882523da8fcf19df6c0f60d40","height":2,"size":859,"time":1002,"timedelta":1,"iterations":4,"interval":1},"id":1}"#
+        );
+    }
+
+    #[test]
+    fn block_stats_error() {
+        let client = BlockChainClient::new(ErrorBlockChainClientCore::default());
+        let mut handler = IoHandler::new();
+        handler.extend_with(client.to_delegate());
+
+        let sample = handler
+            .handle_request_sync(
+                &(r#"
+                    {
+                    	"jsonrpc": "2.0",
+                    	"method": "getblockstats",
+                    	"params": ["000000006a625f06636b8bb6ac7b960a8d03705d1ace08b1a19da3fdcc99ddbd"],
+                    	"id": 1
+                    }"#),
+            )
+            .unwrap();
+
+        assert_eq!(
+            &sample,
+            r#"{"jsonrpc":"2.0","error":{"code":-32099,"message":"Block with given hash is not found","data":"000000006a625f06636b8bb6ac7b960a8d03705d1ace08b1a19da3fdcc99ddbd"},"id":1}"#
+        );
+    }
+
+    #[test]
+    fn block_stats_contents() {
+        let storage = Arc::new(BlockChainDatabase::init_test_chain(vec![
+            test_data::genesis().into(),
+            test_data::block_h1().into(),
+            test_data::block_h2().into(),
+        ]));
+
+        let core = BlockChainClientCore::new(storage, None, None);
+
+        let stats = core
+            .block_stats(test_data::block_h2().hash().into())
+            .unwrap();
+        assert_eq!(stats.hash, test_data::block_h2().hash().into());
+        assert_eq!(stats.height, Some(2));
+        assert_eq!(stats.size, 859);
+        assert_eq!(stats.time, 1002);
+        assert_eq!(stats.timedelta, Some(1));
+        assert_eq!(stats.iterations, 4);
+        assert_eq!(stats.interval, Some(1));
+
+        let stats = core
+            .block_stats(test_data::genesis().hash().into())
+            .unwrap();
+        assert_eq!(stats.height, Some(0));
+        assert_eq!(stats.timedelta, None);
+        assert_eq!(stats.interval, None);
+    }
+
+    #[test]
+    fn blockchain_info_success() {
+        let client = BlockChainClient::new(SuccessBlockChainClientCore::default());
+        let mut handler = IoHandler::new();
+        handler.extend_with(client.to_delegate());
+
+        let sample = handler
+            .handle_request_sync(
+                &(r#"
+                    {
+                    	"jsonrpc": "2.0",
+                    	"method": "getblockchaininfo",
+                    	"params": [],
+                    	"id": 1
+                    }"#),
+            )
+            .unwrap();
+
+        assert_eq!(
+            &sample,
+            r#"{"jsonrpc":"2.0","result":{"blocks":1,"headers":4,"bestblockhash":"71596f7d5efbfdfa496ed1ff7d4a2d5f2fc026e273a42a3ec83631e21dcf020e","difficulty":1.0,"chainwork":"4","mediantime":1000,"verificationprogress":0.25,"initialblockdownload":true,"pruned":true,"pruneheight":1,"compressionratio":1.5,"warnings":""},"id":1}"#
+        );
+    }
+
+    #[test]
+    fn blockchain_info_contents() {
+        let storage = Arc::new(BlockChainDatabase::init_test_chain(vec![
+            test_data::genesis().into(),
+            test_data::block_h1().into(),
+            test_data::block_h2().into(),
+        ]));
+        let sync_state = Arc::new(sync::SynchronizationState::with_storage(storage.clone()));
+        let core = BlockChainClientCore::new(storage, Some(sync_state.clone()), None);
+
+        let info = core.blockchain_info();
+        assert_eq!(info.blocks, 2);
+        assert_eq!(info.headers, 2);
+        assert_eq!(info.bestblockhash, test_data::block_h2().hash().into());
+        assert_eq!(info.chainwork, 6.into());
+        assert_eq!(info.verificationprogress, 1f64);
+        assert_eq!(info.initialblockdownload, false);
+        assert_eq!(info.pruned, false);
+
+        sync_state.update_best_header_height(8);
+        sync_state.update_synchronizing(true);
+        let info = core.blockchain_info();
+        assert_eq!(info.headers, 8);
+        assert_eq!(info.verificationprogress, 0.25f64);
+        assert_eq!(info.initialblockdownload, true);
+        assert_eq!(info.warnings, "");
+
+        sync_state.update_disk_space_low(true);
+        let info = core.blockchain_info();
+        assert_eq!(
+            info.warnings,
+            "Disk space is low. Blocks processing is paused"
+        );
+    }
+
+    #[test]
+    fn stale_blocks_success() {
+        let client = BlockChainClient::new(SuccessBlockChainClientCore::default());
+        let mut handler = IoHandler::new();
+        handler.extend_with(client.to_delegate());
+
+        let sample = handler
+            .handle_request_sync(
+                &(r#"
+                    {
+                    	"jsonrpc": "2.0",
+                    	"method": "getstaleblocks",
+                    	"params": [24],
+                    	"id": 1
+                    }"#),
+            )
+            .unwrap();
+
+        assert_eq!(
+            &sample,
+            r#"{"jsonrpc":"2.0","result":[{"hash":"0000000000000000000000000000000000000000000000000000000000000001","height":1,"replacedby":"71596f7d5efbfdfa496ed1ff7d4a2d5f2fc026e273a42a3ec83631e21dcf020e","time":1000}],"id":1}"#
+        );
+    }
+
+    #[test]
+    fn stale_blocks_contents() {
+        let storage = Arc::new(BlockChainDatabase::init_test_chain(vec![
+            test_data::genesis().into(),
+        ]));
+        let sync_state = Arc::new(sync::SynchronizationState::with_storage(storage.clone()));
+        let core = BlockChainClientCore::new(storage.clone(), Some(sync_state.clone()), None);
+        assert!(core.stale_blocks(1).is_empty());
+
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs() as u32;
+        sync_state.note_stale_blocks(vec![
+            sync::StaleBlock {
+                hash: GlobalH256::from(1),
+                height: 1,
+                replaced_by: GlobalH256::from(2),
+                time: now - 2 * 60 * 60,
+            },
+            sync::StaleBlock {
+                hash: GlobalH256::from(3),
+                height: 2,
+                replaced_by: GlobalH256::from(4),
+                time: now,
+            },
+        ]);
+        assert_eq!(core.stale_blocks(3).len(), 2);
+        assert_eq!(
+            core.stale_blocks(1),
+            vec![StaleBlock {
+                hash: GlobalH256::from(3).into(),
+                height: 2,
+                replacedby: GlobalH256::from(4).into(),
+                time: now,
+            }]
+        );
+
+        // stale blocks are only tracked by synchronization
+        let core = BlockChainClientCore::new(storage, None, None);
+        assert!(core.stale_blocks(3).is_empty());
+    }
+
+    #[test]
+    fn block_announcements_success() {
+        let client = BlockChainClient::new(SuccessBlockChainClientCore::default());
+        let mut handler = IoHandler::new();
+        handler.extend_with(client.to_delegate());
+
+        let sample = handler
+            .handle_request_sync(
+                &(r#"
+                    {
+                    	"jsonrpc": "2.0",
+                    	"method": "getblockannouncements",
+                    	"params": [1],
+                    	"id": 1
+                    }"#),
+            )
+            .unwrap();
+
+        assert_eq!(
+            &sample,
+            r#"{"jsonrpc":"2.0","result":[{"hash":"0000000000000000000000000000000000000000000000000000000000000001","time":1000,"announcers":[{"peer":2,"delayms":0}]}],"id":1}"#
+        );
+    }
+
+    #[test]
+    fn block_announcements_contents() {
+        let storage = Arc::new(BlockChainDatabase::init_test_chain(vec![
+            test_data::genesis().into(),
+        ]));
+        let sync_state = Arc::new(sync::SynchronizationState::with_storage(storage.clone()));
+        let core = BlockChainClientCore::new(storage.clone(), Some(sync_state.clone()), None);
+        assert!(core.block_announcements(1).is_empty());
+
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs() as u32;
+        sync_state.note_block_announcements(1, vec![GlobalH256::from(1)], now - 2 * 60 * 60, 10.0);
+        sync_state.note_block_announcements(2, vec![GlobalH256::from(2)], now, 20.0);
+        sync_state.note_block_announcements(1, vec![GlobalH256::from(2)], now, 20.1);
+        assert_eq!(core.block_announcements(3).len(), 2);
+        assert_eq!(
+            core.block_announcements(1),
+            vec![BlockAnnouncement {
+                hash: GlobalH256::from(2).into(),
+                time: now,
+                announcers: vec![
+                    BlockAnnouncer {
+                        peer: 2,
+                        delayms: 0,
+                    },
+                    BlockAnnouncer {
+                        peer: 1,
+                        delayms: 100,
+                    },
+                ],
+            }]
+        );
     }
 
     #[test]
-    fn best_block_hash_success() {
+    fn block_propagations_success() {
         let client = BlockChainClient::new(SuccessBlockChainClientCore::default());
         let mut handler = IoHandler::new();
         handler.extend_with(client.to_delegate());
@@ -247,25 +2971,79 @@ pub mod tests {
         let sample = handler
             .handle_request_sync(
                 &(r#"
-			{
-				"jsonrpc": "2.0",
-				"method": "getbestblockhash",
-				"params": [],
-				"id": 1
-			}"#),
+                    {
+                    	"jsonrpc": "2.0",
+                    	"method": "getblockpropagationstats",
+                    	"params": [1],
+                    	"id": 1
+                    }"#),
             )
             .unwrap();
 
-        // direct hash is 0e02cf1de23136c83e2aa473e226c02f5f2d4a7dffd16e49fafdfb5e7d6f5971
-        // but client expects reverse hash
         assert_eq!(
             &sample,
-            r#"{"jsonrpc":"2.0","result":"71596f7d5efbfdfa496ed1ff7d4a2d5f2fc026e273a42a3ec83631e21dcf020e","id":1}"#
+            r#"{"jsonrpc":"2.0","result":[{"hash":"0000000000000000000000000000000000000000000000000000000000000001","time":1000,"peer":2,"announced":true,"receivedms":150,"verifiedms":200,"connectedms":210}],"id":1}"#
         );
     }
 
     #[test]
-    fn block_count_success() {
+    fn block_propagations_contents() {
+        let storage = Arc::new(BlockChainDatabase::init_test_chain(vec![
+            test_data::genesis().into(),
+        ]));
+        let sync_state = Arc::new(sync::SynchronizationState::with_storage(storage.clone()));
+        let core = BlockChainClientCore::new(storage.clone(), Some(sync_state.clone()), None);
+        assert!(core.block_propagations(1).is_empty());
+
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs() as u32;
+        sync_state.note_block_propagation(
+            vec![GlobalH256::from(1)],
+            sync::PropagationStage::Received,
+            Some(1),
+            now - 2 * 60 * 60,
+            10.0,
+        );
+        sync_state.note_block_propagation(
+            vec![GlobalH256::from(2)],
+            sync::PropagationStage::Announced,
+            Some(2),
+            now,
+            20.0,
+        );
+        sync_state.note_block_propagation(
+            vec![GlobalH256::from(2)],
+            sync::PropagationStage::Received,
+            Some(1),
+            now,
+            20.1,
+        );
+        sync_state.note_block_propagation(
+            vec![GlobalH256::from(2)],
+            sync::PropagationStage::Verified,
+            None,
+            now,
+            20.3,
+        );
+        assert_eq!(core.block_propagations(3).len(), 2);
+        assert_eq!(
+            core.block_propagations(1),
+            vec![BlockPropagation {
+                hash: GlobalH256::from(2).into(),
+                time: now,
+                peer: 2,
+                announced: true,
+                receivedms: Some(100),
+                verifiedms: Some(300),
+                connectedms: None,
+            }]
+        );
+    }
+
+    #[test]
+    fn chain_events_success() {
         let client = BlockChainClient::new(SuccessBlockChainClientCore::default());
         let mut handler = IoHandler::new();
         handler.extend_with(client.to_delegate());
@@ -275,18 +3053,21 @@ pub mod tests {
                 &(r#"
                     {
                     	"jsonrpc": "2.0",
-                    	"method": "getblockcount",
-                    	"params": [],
+                    	"method": "listchainevents",
+                    	"params": [1],
                     	"id": 1
                     }"#),
             )
             .unwrap();
 
-        assert_eq!(&sample, r#"{"jsonrpc":"2.0","result":1,"id":1}"#);
+        assert_eq!(
+            &sample,
+            r#"{"jsonrpc":"2.0","result":[{"seq":2,"event":"connected","hash":"71596f7d5efbfdfa496ed1ff7d4a2d5f2fc026e273a42a3ec83631e21dcf020e","height":0,"time":1000}],"id":1}"#
+        );
     }
 
     #[test]
-    fn block_hash_success() {
+    fn chain_events_page_success() {
         let client = BlockChainClient::new(SuccessBlockChainClientCore::default());
         let mut handler = IoHandler::new();
         handler.extend_with(client.to_delegate());
@@ -296,24 +3077,22 @@ pub mod tests {
                 &(r#"
                     {
                     	"jsonrpc": "2.0",
-                    	"method": "getblockhash",
-                    	"params": [0],
+                    	"method": "listchainevents",
+                    	"params": [null],
                     	"id": 1
                     }"#),
             )
             .unwrap();
 
-        // direct hash is 0e02cf1de23136c83e2aa473e226c02f5f2d4a7dffd16e49fafdfb5e7d6f5971
-        // but client expects reverse hash
         assert_eq!(
             &sample,
-            r#"{"jsonrpc":"2.0","result":"71596f7d5efbfdfa496ed1ff7d4a2d5f2fc026e273a42a3ec83631e21dcf020e","id":1}"#
+            r#"{"jsonrpc":"2.0","result":{"events":[{"seq":2,"event":"connected","hash":"71596f7d5efbfdfa496ed1ff7d4a2d5f2fc026e273a42a3ec83631e21dcf020e","height":0,"time":1000}],"next":"0000000000000002000000000e02cf1de23136c83e2aa473e226c02f5f2d4a7dffd16e49fafdfb5e7d6f5971","reorged":false,"forkpoint":null},"id":1}"#
         );
     }
 
     #[test]
-    fn block_hash_error() {
-        let client = BlockChainClient::new(ErrorBlockChainClientCore::default());
+    fn chain_events_filter_success() {
+        let client = BlockChainClient::new(SuccessBlockChainClientCore::default());
         let mut handler = IoHandler::new();
         handler.extend_with(client.to_delegate());
 
@@ -322,8 +3101,8 @@ pub mod tests {
                 &(r#"
                     {
                     	"jsonrpc": "2.0",
-                    	"method": "getblockhash",
-                    	"params": [0],
+                    	"method": "listchainevents",
+                    	"params": [1, {"miner": "0000000000000000000000000000000000000000000000000000000000000000"}],
                     	"id": 1
                     }"#),
             )
@@ -331,137 +3110,79 @@ pub mod tests {
 
         assert_eq!(
             &sample,
-            r#"{"jsonrpc":"2.0","error":{"code":-32099,"message":"Block at given height is not found","data":"0"},"id":1}"#
+            r#"{"jsonrpc":"2.0","result":[{"seq":2,"event":"connected","hash":"71596f7d5efbfdfa496ed1ff7d4a2d5f2fc026e273a42a3ec83631e21dcf020e","height":0,"time":1000}],"id":1}"#
         );
-    }
-
-    #[test]
-    fn difficulty_success() {
-        let client = BlockChainClient::new(SuccessBlockChainClientCore::default());
-        let mut handler = IoHandler::new();
-        handler.extend_with(client.to_delegate());
 
+        // cursor skips filtered out events
         let sample = handler
             .handle_request_sync(
                 &(r#"
                     {
                     	"jsonrpc": "2.0",
-                    	"method": "getdifficulty",
-                    	"params": [],
+                    	"method": "listchainevents",
+                    	"params": [null, {"minreorgdepth": 1}],
                     	"id": 1
                     }"#),
             )
             .unwrap();
 
-        assert_eq!(&sample, r#"{"jsonrpc":"2.0","result":1.0,"id":1}"#);
-    }
-
-    #[test]
-    fn verbose_block_contents() {
-        let storage = Arc::new(BlockChainDatabase::init_test_chain(vec![
-            test_data::genesis().into(),
-            test_data::block_h1().into(),
-            test_data::block_h2().into(),
-        ]));
-
-        let core = BlockChainClientCore::new(storage);
-
-        // get info on block #1:
-        let verbose_block = core.verbose_block(test_data::block_h1().hash().into());
-        assert_eq!(
-            verbose_block,
-            Some(VerboseBlock {
-                hash: test_data::block_h1().hash().into(),
-                confirmations: 2, // h1 + h2
-                size: 859,
-                height: Some(1),
-                version: 1,
-                version_hex: "1".to_owned(),
-                pubkey_hex: "0000000000000000000000000000000000000000000000000000000000000000"
-                    .to_owned(),
-                randomness_hex: "966e8f70aa11fdb48b7d463c57db4722d1ac994640879f98a616b165c3a5df9db31c0dee6cbcebf46c427049ead7b7edbe39970ce7b0fecf2a9b07c4381e466055f1e8fc152f304f3cfdbb475bdaa93a9f95de38ff1d091d175bfea579574f444c1650a6ab4bfd97d2b19f6653549517d91b882a5560da1b6b0188ae67d016dfe2dfe795593e844b2d32e29b985b425038c83c7aaf9b9ea3d73065767f234da6f25e43137a8d91265d16afdb9ab728f619385a31734ba769bfb5ff1bea3869043534d1697efc3148a0867ceacb6d2d24508ea1b201abfbb23604aa7cc3dd7dbfe9c002f2f26199aea18e9c24e870bfa090505eecc307d895caf7bdadccbdfbb4".to_owned(),
-                time: 1001,
-                mediantime: Some(1000),
-                iterations: 4,
-                bits: 545259519,
-                difficulty: 0.00000000046565423739069247,
-                chainwork: 0.into(),
-                previousblockhash: Some(
-                    test_data::genesis().hash().into()
-                ),
-                nextblockhash: Some(
-                    test_data::block_h2().hash().into()
-                ),
-            })
-        );
-
-        // get info on block #2:
-        let verbose_block = core.verbose_block(test_data::block_h2().hash().into());
         assert_eq!(
-            verbose_block,
-            Some(VerboseBlock {
-                hash: test_data::block_h2().hash().into(),
-                confirmations: 1, // h2
-                size: 859,
-                height: Some(2),
-                version: 1,
-                version_hex: "1".to_owned(),
-                pubkey_hex: "0000000000000000000000000000000000000000000000000000000000000000"
-                    .to_owned(),
-                randomness_hex: "5ced562ee6e1c95c2e034d3ed4fc38508fd4cf0a1faa466303c79b7bc9fcaf4d60d1dc53884698e891e5ee1f661f79e58631b8003d0fd18d526fcc3f95e97597c125573895ffc3a6a9b1458b1a383cdb1ea151f2b01f62980d6e92aaef8d0c5a06e56995176a75cb8aa37c94a5e677e1dd91e1fb3874b72f614507b5e9b29bc5e43264123e8fb29664558dad1aa4e350843262effaffe63685765fa7028ce10b81d13059a3dfc8207b7bc37ce4e8e23d22a797b454abf7777c641a534a2bfd5d67f5d182c75a4a7fe9f3a31eb85afe533cc55edca86b9dfe6e8d66c2c00a90097ac0ff8ca06f00dd524018fb422ad68994ec537cd12ab10f4144a8f3290dae1a".to_owned(),
-                time: 1002,
-                mediantime: Some(1001),
-                iterations: 4,
-                bits: 545259519,
-                difficulty: 0.00000000046565423739069247,
-                chainwork: 0.into(),
-                previousblockhash: Some(
-                    test_data::block_h1().hash().into()
-                ),
-                nextblockhash: None,
-            })
+            &sample,
+            r#"{"jsonrpc":"2.0","result":{"events":[],"next":"0000000000000002000000000e02cf1de23136c83e2aa473e226c02f5f2d4a7dffd16e49fafdfb5e7d6f5971","reorged":false,"forkpoint":null},"id":1}"#
         );
     }
 
     #[test]
-    fn raw_block_success() {
+    fn verify_chain_success() {
         let client = BlockChainClient::new(SuccessBlockChainClientCore::default());
         let mut handler = IoHandler::new();
         handler.extend_with(client.to_delegate());
 
-        let expected = r#"{"jsonrpc":"2.0","result":"0100000027cf1cfd394338d0af68c0bd84960c8cb1becb8a10991df558a8fb167ef65e63ea030000ffff7f2020000000000000000000000000000000000000000000000000000000000000000004000000fd00015ced562ee6e1c95c2e034d3ed4fc38508fd4cf0a1faa466303c79b7bc9fcaf4d60d1dc53884698e891e5ee1f661f79e58631b8003d0fd18d526fcc3f95e97597c125573895ffc3a6a9b1458b1a383cdb1ea151f2b01f62980d6e92aaef8d0c5a06e56995176a75cb8aa37c94a5e677e1dd91e1fb3874b72f614507b5e9b29bc5e43264123e8fb29664558dad1aa4e350843262effaffe63685765fa7028ce10b81d13059a3dfc8207b7bc37ce4e8e23d22a797b454abf7777c641a534a2bfd5d67f5d182c75a4a7fe9f3a31eb85afe533cc55edca86b9dfe6e8d66c2c00a90097ac0ff8ca06f00dd524018fb422ad68994ec537cd12ab10f4144a8f3290dae1a02fd000113f6cd3e8a5010bd7383bc1540735721fa5f6b433e67c5dfb9a4d9e30559ae3c08627385820f34535edcad1d1b36019e5f615e70661c3e9cb553643fd8003096a3079c67ac289eb122abb1d7cace1ae8f4a3ec6959b89c43a16ffe785fb166ebe94c2609d65795fc58f6faac12e6e00a41099b44f6a53344db5f2846bf2c5782a503234822f8d75257d5a04d352cc85fb569157e8c1195f77208359759229f1c3e81ae82275b6362350a7fb430abf99c1a0d054eaa4aaf90e63fb1028740af75ff05c8286338aef128f6fb75d93a78c08dd4e05f0b91516cb15b1832d473f5539daf6b6c1176ff460ace9049029278640be9bc5a1893030387d72760e1ff3532fd00014be8ac242b610c307dee148c2d3cbf0e460e6dbf00aadaf1ee202c87a630ec96b74d6caedf9932c98d35949cdfbca69e32ae72b8b200dc41f52ea5a890c3fc64370c124f9fa9478fcaaa8da260ffdb07492ebc60ee642f459fa75a7e9839fb8442f03610a807a662a45364f729352682d02b4a2c353c11d0dde3a802bceb8af7149f499152d1e7b51bc627abaa63e270442cdf0a3d9d12aa2590abd0ab9209faca4a2b3c8cb1ff673e282f3d89fcee7d28b1139b1c0646ad2248a628fbc71cb1228a4ced677e589b17b8f688032921237cf2d723c204a89259d321a8c88cb18303669ef278f6480ea1bad355cc86d431157bff750d4918af83c47f1889efa05e","id":1}"#;
-
         let sample = handler
-             .handle_request_sync(
-                 &(r#"
+            .handle_request_sync(
+                &(r#"
                     {
                     	"jsonrpc": "2.0",
-                    	"method": "getblock",
-                    	"params": ["29483ce82fad9d817f3ac76b8bd8f221cd5a6aa882523da8fcf19df6c0f60d40", false],
+                    	"method": "verifychain",
+                    	"params": ["paranoid", 10],
                     	"id": 1
                     }"#),
-             )
-             .unwrap();
-        assert_eq!(&sample, expected);
+            )
+            .unwrap();
+
+        assert_eq!(
+            &sample,
+            r#"{"jsonrpc":"2.0","result":{"checked":5,"failure":{"height":1,"hash":"71596f7d5efbfdfa496ed1ff7d4a2d5f2fc026e273a42a3ec83631e21dcf020e","error":"Vdf"}},"id":1}"#
+        );
+    }
+
+    #[test]
+    fn verify_chain_error() {
+        let client = BlockChainClient::new(ErrorBlockChainClientCore::default());
+        let mut handler = IoHandler::new();
+        handler.extend_with(client.to_delegate());
 
-        // try without optional parameter
         let sample = handler
             .handle_request_sync(
                 &(r#"
                     {
                     	"jsonrpc": "2.0",
-                    	"method": "getblock",
-                    	"params": ["29483ce82fad9d817f3ac76b8bd8f221cd5a6aa882523da8fcf19df6c0f60d40"],
+                    	"method": "verifychain",
+                    	"params": [],
                     	"id": 1
                     }"#),
             )
             .unwrap();
-        assert_eq!(&sample, expected);
+
+        assert_eq!(
+            &sample,
+            r#"{"jsonrpc":"2.0","error":{"code":-32015,"message":"Execution error.","data":"\"synchronization is not running\""},"id":1}"#
+        );
     }
 
     #[test]
-    fn raw_block_error() {
-        let client = BlockChainClient::new(ErrorBlockChainClientCore::default());
+    fn db_info_success() {
+        let client = BlockChainClient::new(SuccessBlockChainClientCore::default());
         let mut handler = IoHandler::new();
         handler.extend_with(client.to_delegate());
 
@@ -470,8 +3191,8 @@ pub mod tests {
                 &(r#"
                     {
                     	"jsonrpc": "2.0",
-                    	"method": "getblock",
-                    	"params": ["000000006a625f06636b8bb6ac7b960a8d03705d1ace08b1a19da3fdcc99ddbd", false],
+                    	"method": "getdbinfo",
+                    	"params": [],
                     	"id": 1
                     }"#),
             )
@@ -479,55 +3200,284 @@ pub mod tests {
 
         assert_eq!(
             &sample,
-            r#"{"jsonrpc":"2.0","error":{"code":-32099,"message":"Block with given hash is not found","data":"000000006a625f06636b8bb6ac7b960a8d03705d1ace08b1a19da3fdcc99ddbd"},"id":1}"#
+            r#"{"jsonrpc":"2.0","result":{"columns":[{"keys":2,"name":"blocks","size":900}],"datasize":900,"disksize":1000,"overhead":100},"id":1}"#
         );
     }
 
     #[test]
-    fn verbose_block_success() {
-        let client = BlockChainClient::new(SuccessBlockChainClientCore::default());
+    fn compact_db_error() {
+        let client = BlockChainClient::new(ErrorBlockChainClientCore::default());
         let mut handler = IoHandler::new();
         handler.extend_with(client.to_delegate());
 
         let sample = handler
-             .handle_request_sync(
-                 &(r#"
+            .handle_request_sync(
+                &(r#"
                     {
                     	"jsonrpc": "2.0",
-                    	"method": "getblock",
-                    	"params": ["29483ce82fad9d817f3ac76b8bd8f221cd5a6aa882523da8fcf19df6c0f60d40",true],
+                    	"method": "compactdb",
+                    	"params": [],
                     	"id": 1
                     }"#),
-             )
-             .unwrap();
+            )
+            .unwrap();
 
         assert_eq!(
             &sample,
-            r#"{"jsonrpc":"2.0","result":{"bits":545259519,"chainwork":"0","confirmations":1,"difficulty":1.0,"hash":"29483ce82fad9d817f3ac76b8bd8f221cd5a6aa882523da8fcf19df6c0f60d40","height":2,"iterations":4,"mediantime":null,"nextblockhash":null,"previousblockhash":"635ef67e16fba858f51d99108acbbeb18c0c9684bdc068afd0384339fd1ccf27","pubkeyHex":"0000000000000000000000000000000000000000000000000000000000000000","randomnessHex":"5ced562ee6e1c95c2e034d3ed4fc38508fd4cf0a1faa466303c79b7bc9fcaf4d60d1dc53884698e891e5ee1f661f79e58631b8003d0fd18d526fcc3f95e97597c125573895ffc3a6a9b1458b1a383cdb1ea151f2b01f62980d6e92aaef8d0c5a06e56995176a75cb8aa37c94a5e677e1dd91e1fb3874b72f614507b5e9b29bc5e43264123e8fb29664558dad1aa4e350843262effaffe63685765fa7028ce10b81d13059a3dfc8207b7bc37ce4e8e23d22a797b454abf7777c641a534a2bfd5d67f5d182c75a4a7fe9f3a31eb85afe533cc55edca86b9dfe6e8d66c2c00a90097ac0ff8ca06f00dd524018fb422ad68994ec537cd12ab10f4144a8f3290dae1a","size":859,"time":1002,"version":1,"versionHex":"1"},"id":1}"#
+            r#"{"jsonrpc":"2.0","error":{"code":-32015,"message":"Execution error.","data":"\"IO error\""},"id":1}"#
         );
     }
 
     #[test]
-    fn verbose_block_error() {
+    fn db_info_of_memory_database() {
+        let storage = Arc::new(BlockChainDatabase::init_test_chain(vec![
+            test_data::genesis().into(),
+        ]));
+        let core = BlockChainClientCore::new(storage, None, None);
+
+        let info = core.db_info().unwrap();
+        assert_eq!(info.disksize, None);
+        assert_eq!(info.overhead, None);
+        assert!(core.compact_db().is_ok());
+    }
+
+    #[test]
+    fn miner_blocks_contents() {
+        let storage = Arc::new(BlockChainDatabase::init_test_chain(vec![
+            test_data::genesis().into(),
+            test_data::block_h1().into(),
+        ]));
+        let miner: Bytes = test_data::block_h1()
+            .header()
+            .pubkey
+            .to_bytes()
+            .to_vec()
+            .into();
+        let core = BlockChainClientCore::new(storage.clone(), None, None);
+        assert!(core.miner_blocks(miner.clone(), 0, None).is_err());
+
+        storage.enable_miner_index().unwrap();
+        let blocks = core.miner_blocks(miner.clone(), 1, None).unwrap();
+        assert_eq!(blocks.total, 1);
+        assert_eq!(blocks.heights, vec![1]);
+        let blocks = core.miner_blocks(miner, 2, Some(10)).unwrap();
+        assert_eq!(blocks.total, 0);
+        assert!(blocks.heights.is_empty());
+    }
+
+    #[test]
+    fn block_headers_page_contents() {
+        let storage = Arc::new(BlockChainDatabase::init_test_chain(vec![
+            test_data::genesis().into(),
+            test_data::block_h1().into(),
+            test_data::block_h2().into(),
+        ]));
+        let core = BlockChainClientCore::new(storage.clone(), None, None);
+        let read_page = |cursor, verbose| {
+            BlockChainClient::<BlockChainClientCore>::read_block_headers_page(
+                &core, cursor, 2, verbose,
+            )
+            .unwrap()
+        };
+        let cursor = |height, hash| BlockCursor {
+            height: height,
+            hash: hash,
+        };
+
+        let page = read_page(None, false);
+        assert_eq!(page.headers.len(), 2);
+        assert_eq!(page.next, cursor(1, test_data::block_h1().hash()));
+        let page = read_page(Some(page.next), true);
+        assert_eq!(page.headers.len(), 1);
+        assert_eq!(page.next, cursor(2, test_data::block_h2().hash()));
+        assert!(!page.reorged);
+        // nothing is returned until new block is appended
+        let page = read_page(Some(page.next), false);
+        assert!(page.headers.is_empty());
+        assert_eq!(page.next, cursor(2, test_data::block_h2().hash()));
+
+        // last page has been reorged
+        storage.decanonize().unwrap();
+        let page = read_page(Some(page.next), false);
+        assert!(page.headers.is_empty());
+        assert!(page.reorged);
+        assert_eq!(page.next, cursor(1, test_data::block_h1().hash()));
+        assert_eq!(page.forkpoint, Some(page.next.clone()));
+
+        // cursor must refer to the known block at its height
+        for invalid in vec![cursor(0, test_data::block_h1().hash()), cursor(1, 5.into())] {
+            assert!(
+                BlockChainClient::<BlockChainClientCore>::read_block_headers_page(
+                    &core,
+                    Some(invalid),
+                    2,
+                    false
+                )
+                .is_err()
+            );
+        }
+    }
+
+    #[test]
+    fn block_headers_page_error() {
         let client = BlockChainClient::new(ErrorBlockChainClientCore::default());
         let mut handler = IoHandler::new();
         handler.extend_with(client.to_delegate());
 
         let sample = handler
-             .handle_request_sync(
-                 &(r#"
+            .handle_request_sync(
+                &(r#"
                     {
                     	"jsonrpc": "2.0",
-                    	"method": "getblock",
-                    	"params": ["000000006a625f06636b8bb6ac7b960a8d03705d1ace08b1a19da3fdcc99ddbd", true],
+                    	"method": "listblockheaders",
+                    	"params": ["000000010100000000000000000000000000000000000000000000000000000000000000", 10],
                     	"id": 1
                     }"#),
-             )
-             .unwrap();
+            )
+            .unwrap();
+
+        assert!(sample.contains("unknown block"));
+    }
+
+    #[test]
+    fn chain_events_contents() {
+        let storage = Arc::new(BlockChainDatabase::init_test_chain(vec![
+            test_data::genesis().into(),
+            test_data::block_h1().into(),
+        ]));
+        let core = BlockChainClientCore::new(storage, None, None);
+
+        let events = core.chain_events(0);
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0].seq, 1);
+        assert_eq!(events[0].event, ChainEventKind::Connected);
+        assert_eq!(events[0].hash, test_data::genesis().hash().into());
+        assert_eq!(events[1].seq, 2);
+        assert_eq!(events[1].height, 1);
+        assert_eq!(events[1].hash, test_data::block_h1().hash().into());
+        assert!(core.chain_events(2).is_empty());
+    }
 
+    #[test]
+    fn chain_events_page_contents() {
+        let storage = Arc::new(BlockChainDatabase::init_test_chain(vec![
+            test_data::genesis().into(),
+            test_data::block_h1().into(),
+            test_data::block_h2().into(),
+        ]));
+        let core = BlockChainClientCore::new(storage.clone(), None, None);
+        let read_page = |cursor| {
+            BlockChainClient::<BlockChainClientCore>::read_chain_events_page(
+                &core,
+                cursor,
+                &ChainEventsFilter::default(),
+            )
+            .unwrap()
+        };
+        let cursor = |seq, height, hash| ChainEventsCursor {
+            seq: seq,
+            block: BlockCursor {
+                height: height,
+                hash: hash,
+            },
+        };
+
+        let page = read_page(None);
+        assert_eq!(page.events.len(), 3);
+        assert_eq!(page.next, cursor(3, 2, test_data::block_h2().hash()));
+        assert!(!page.reorged);
+        // nothing is returned until chain is changed
+        let page = read_page(Some(page.next));
+        assert!(page.events.is_empty());
+        assert_eq!(page.next, cursor(3, 2, test_data::block_h2().hash()));
+
+        // best block of the cursor has been reorged
+        storage.decanonize().unwrap();
+        let page = read_page(Some(page.next));
+        assert!(page.reorged);
         assert_eq!(
-            &sample,
-            r#"{"jsonrpc":"2.0","error":{"code":-32099,"message":"Block with given hash is not found","data":"000000006a625f06636b8bb6ac7b960a8d03705d1ace08b1a19da3fdcc99ddbd"},"id":1}"#
+            page.forkpoint,
+            Some(BlockCursor {
+                height: 1,
+                hash: test_data::block_h1().hash(),
+            })
+        );
+        assert_eq!(page.events.len(), 1);
+        assert_eq!(page.events[0].event, ChainEventKind::Disconnected);
+        assert_eq!(page.next, cursor(4, 1, test_data::block_h1().hash()));
+        let page = read_page(Some(page.next));
+        assert!(!page.reorged);
+        assert!(page.events.is_empty());
+
+        // events of the reorg are not retained, so cursor is moved to the fork point
+        let page = read_page(Some(cursor(4, 2, test_data::block_h2().hash())));
+        assert!(page.reorged);
+        assert_eq!(page.next, cursor(4, 1, test_data::block_h1().hash()));
+
+        // cursor must refer to the known block at its height
+        assert!(
+            BlockChainClient::<BlockChainClientCore>::read_chain_events_page(
+                &core,
+                Some(cursor(3, 0, test_data::block_h1().hash())),
+                &ChainEventsFilter::default(),
+            )
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn chain_events_filter_contents() {
+        let storage = Arc::new(BlockChainDatabase::init_test_chain(vec![
+            test_data::genesis().into(),
+            test_data::block_h1().into(),
+            test_data::block_h2().into(),
+        ]));
+        storage.decanonize().unwrap();
+        let core = BlockChainClientCore::new(storage, None, None);
+        let read_events =
+            |filter| BlockChainClient::<BlockChainClientCore>::read_chain_events(&core, 0, &filter);
+
+        // all test blocks are mined with the zero key
+        let events = read_events(ChainEventsFilter {
+            miner: Some(Bytes::new(vec![0; 32])),
+            ..Default::default()
+        })
+        .unwrap();
+        assert_eq!(events.len(), 4);
+        assert_eq!(events[3].event, ChainEventKind::Disconnected);
+        assert_eq!(
+            events[3].hash,
+            test_data::block_h2().hash().reversed().into()
+        );
+        assert!(read_events(ChainEventsFilter {
+            miner: Some(Bytes::new(vec![1; 32])),
+            ..Default::default()
+        })
+        .unwrap()
+        .is_empty());
+
+        // block has been disconnected without reorganization
+        assert!(read_events(ChainEventsFilter {
+            minreorgdepth: Some(1),
+            ..Default::default()
+        })
+        .unwrap()
+        .is_empty());
+
+        // finality is tracked by synchronization
+        assert!(read_events(ChainEventsFilter {
+            finalized: Some(true),
+            ..Default::default()
+        })
+        .is_err());
+        assert_eq!(
+            read_events(ChainEventsFilter {
+                finalized: Some(false),
+                ..Default::default()
+            })
+            .unwrap()
+            .len(),
+            4
         );
     }
 }
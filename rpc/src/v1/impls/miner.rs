@@ -1,25 +1,59 @@
-use jsonrpc_core::Error;
+use chain::{Block, IndexedBlock};
+use futures_cpupool::{Builder as CpuPoolBuilder, CpuPool};
+use jsonrpc_core::futures::future;
+use jsonrpc_core::{BoxFuture, Error};
 use miner;
+use primitives::hash::H256 as GlobalH256;
+use ser::deserialize;
+use std::sync::Arc;
+use std::time::Duration;
 use sync;
+use v1::helpers::errors::{execution, invalid_params};
 use v1::traits::Miner;
-use v1::types::{BlockTemplate, BlockTemplateRequest};
+use v1::types::{BlockTemplate, BlockTemplateRequest, MiningInfo, RawBlock, H256};
+
+/// Long-polling block template request is answered after this time, even if the best block is the same.
+const LONGPOLL_TIMEOUT_S: u64 = 60;
+/// Max number of concurrently waiting long-polling requests. Others are waiting in the queue.
+const LONGPOLL_THREADS: usize = 4;
 
 pub struct MinerClient<T: MinerClientCoreApi> {
-    core: T,
+    core: Arc<T>,
+    /// Blocks are generated on this pool, so that RPC threads are not blocked by mining
+    pool: CpuPool,
+    /// Long-polling requests are waiting for the best block change on this pool
+    longpoll_pool: CpuPool,
 }
 
 pub trait MinerClientCoreApi: Send + Sync + 'static {
     fn get_block_template(&self) -> miner::BlockTemplate;
+    /// Wait (up to timeout) until the best block isn't the block with given hash
+    fn wait_best_block_change(&self, best_block_hash: &GlobalH256, timeout: Duration);
+    /// Local mining threads. None if node is not mining
+    fn mining(&self) -> Option<&miner::MiningControl>;
+    /// Mine `count` blocks on top of the best block
+    fn generate(&self, count: u32) -> Result<Vec<GlobalH256>, String>;
+    /// Verify and insert block, mined outside of the node
+    fn submit_block(&self, block: IndexedBlock) -> Result<(), sync::Error>;
 }
 
 pub struct MinerClientCore {
     local_sync_node: sync::LocalNodeRef,
+    mining: Option<miner::MiningControl>,
+    /// Key of the local miner. None if node never produces blocks
+    mining_pubkey: Option<miner::VrfPk>,
 }
 
 impl MinerClientCore {
-    pub fn new(local_sync_node: sync::LocalNodeRef) -> Self {
+    pub fn new(
+        local_sync_node: sync::LocalNodeRef,
+        mining: Option<miner::MiningControl>,
+        mining_pubkey: Option<miner::VrfPk>,
+    ) -> Self {
         MinerClientCore {
             local_sync_node: local_sync_node,
+            mining: mining,
+            mining_pubkey: mining_pubkey,
         }
     }
 }
@@ -28,6 +62,27 @@ impl MinerClientCoreApi for MinerClientCore {
     fn get_block_template(&self) -> miner::BlockTemplate {
         self.local_sync_node.get_block_template()
     }
+
+    fn wait_best_block_change(&self, best_block_hash: &GlobalH256, timeout: Duration) {
+        self.local_sync_node
+            .wait_best_block_change(best_block_hash, timeout);
+    }
+
+    fn mining(&self) -> Option<&miner::MiningControl> {
+        self.mining.as_ref()
+    }
+
+    fn generate(&self, count: u32) -> Result<Vec<GlobalH256>, String> {
+        let pubkey = self
+            .mining_pubkey
+            .as_ref()
+            .ok_or_else(|| "blocks are never produced by watch-only node".to_owned())?;
+        self.local_sync_node.generate_blocks(count, pubkey)
+    }
+
+    fn submit_block(&self, block: IndexedBlock) -> Result<(), sync::Error> {
+        self.local_sync_node.submit_block(block)
+    }
 }
 
 impl<T> MinerClient<T>
@@ -35,7 +90,23 @@ where
     T: MinerClientCoreApi,
 {
     pub fn new(core: T) -> Self {
-        MinerClient { core: core }
+        MinerClient {
+            core: Arc::new(core),
+            pool: CpuPoolBuilder::new()
+                .name_prefix("RPC miner")
+                .pool_size(1)
+                .create(),
+            longpoll_pool: CpuPoolBuilder::new()
+                .name_prefix("RPC long-poll")
+                .pool_size(LONGPOLL_THREADS)
+                .create(),
+        }
+    }
+
+    fn mining(&self) -> Result<&miner::MiningControl, Error> {
+        self.core
+            .mining()
+            .ok_or_else(|| execution("mining is not running"))
     }
 }
 
@@ -43,21 +114,102 @@ impl<T> Miner for MinerClient<T>
 where
     T: MinerClientCoreApi,
 {
-    fn get_block_template(&self, _request: BlockTemplateRequest) -> Result<BlockTemplate, Error> {
-        Ok(self.core.get_block_template().into())
+    fn get_block_template(&self, request: BlockTemplateRequest) -> BoxFuture<BlockTemplate> {
+        let longpollid: GlobalH256 = match request.longpollid {
+            Some(longpollid) => longpollid.into(),
+            None => return Box::new(future::ok(self.core.get_block_template().into())),
+        };
+
+        // longpollid is the hash of the best block, the previous template has been built on
+        let best_block_hash = longpollid.reversed();
+        let core = self.core.clone();
+        Box::new(self.longpoll_pool.spawn_fn(move || {
+            core.wait_best_block_change(&best_block_hash, Duration::from_secs(LONGPOLL_TIMEOUT_S));
+            Ok(core.get_block_template().into())
+        }))
+    }
+
+    fn submit_block(&self, block: RawBlock) -> BoxFuture<Option<String>> {
+        let block: Block = match deserialize(&block.0[..]) {
+            Ok(block) => block,
+            Err(err) => return Box::new(future::err(invalid_params("block", err))),
+        };
+        let core = self.core.clone();
+        Box::new(
+            self.pool
+                .spawn_fn(move || match core.submit_block(block.into()) {
+                    Ok(()) => Ok(None),
+                    Err(sync::Error::Verification(err)) => Ok(Some(err.reason().to_owned())),
+                    Err(err) => Err(execution(err)),
+                }),
+        )
+    }
+
+    fn set_mining_threads(&self, threads: u32) -> Result<(), Error> {
+        self.mining()?.set_threads(threads as usize);
+        Ok(())
+    }
+
+    fn pause_mining(&self) -> Result<(), Error> {
+        self.mining()?.pause();
+        Ok(())
+    }
+
+    fn resume_mining(&self) -> Result<(), Error> {
+        self.mining()?.resume();
+        Ok(())
+    }
+
+    fn mining_info(&self) -> Result<MiningInfo, Error> {
+        Ok(self
+            .core
+            .mining()
+            .map(|mining| mining.stats().into())
+            .unwrap_or_default())
+    }
+
+    fn generate(&self, count: u32) -> BoxFuture<Vec<H256>> {
+        let core = self.core.clone();
+        Box::new(self.pool.spawn_fn(move || {
+            core.generate(count)
+                .map(|hashes| {
+                    hashes
+                        .into_iter()
+                        .map(|hash| hash.reversed().into())
+                        .collect()
+                })
+                .map_err(execution)
+        }))
     }
 }
 
 #[cfg(test)]
 pub mod tests {
+    extern crate test_data;
+
     use super::*;
+    use hex::ToHex;
     use jsonrpc_core::IoHandler;
     use miner;
     use primitives::hash::H256;
+    use ser::serialize;
+    use std::sync::Mutex;
     use v1::traits::Miner;
 
-    #[derive(Default)]
-    struct SuccessMinerClientCore;
+    struct SuccessMinerClientCore {
+        mining: Option<miner::MiningControl>,
+        /// Hashes, passed to `wait_best_block_change`
+        awaited_blocks: Arc<Mutex<Vec<H256>>>,
+    }
+
+    impl Default for SuccessMinerClientCore {
+        fn default() -> Self {
+            SuccessMinerClientCore {
+                mining: Some(miner::MiningControl::start(0, |_| ())),
+                awaited_blocks: Default::default(),
+            }
+        }
+    }
 
     impl MinerClientCoreApi for SuccessMinerClientCore {
         fn get_block_template(&self) -> miner::BlockTemplate {
@@ -69,6 +221,28 @@ pub mod tests {
                 height: 55,
             }
         }
+
+        fn wait_best_block_change(&self, best_block_hash: &H256, _timeout: Duration) {
+            self.awaited_blocks
+                .lock()
+                .unwrap()
+                .push(best_block_hash.clone());
+        }
+
+        fn mining(&self) -> Option<&miner::MiningControl> {
+            self.mining.as_ref()
+        }
+
+        fn generate(&self, count: u32) -> Result<Vec<H256>, String> {
+            match self.mining {
+                Some(_) => Ok((0..count).map(|i| H256::from(i as u8 + 1)).collect()),
+                None => Err("blocks are never produced by watch-only node".to_owned()),
+            }
+        }
+
+        fn submit_block(&self, _block: IndexedBlock) -> Result<(), sync::Error> {
+            Err(sync::Error::Verification(sync::VerificationError::BadProof))
+        }
     }
 
     #[test]
@@ -93,7 +267,213 @@ pub mod tests {
         // but client expects reverse hash
         assert_eq!(
             &sample,
-            r#"{"jsonrpc":"2.0","result":{"bits":44,"coinbaseaux":null,"curtime":33,"height":55,"mintime":null,"mutable":null,"previousblockhash":"0000000000000000000000000000000000000000000000000000000000000001","rules":null,"target":"0000000000000000000000000000000000000000000000000000000000000000","vbavailable":null,"vbrequired":null,"version":777,"weightlimit":null},"id":1}"#
+            r#"{"jsonrpc":"2.0","result":{"bits":44,"coinbaseaux":null,"curtime":33,"height":55,"longpollid":"0000000000000000000000000000000000000000000000000000000000000001","mintime":null,"mutable":null,"previousblockhash":"0000000000000000000000000000000000000000000000000000000000000001","rules":null,"target":"0000000000000000000000000000000000000000000000000000000000000000","vbavailable":null,"vbrequired":null,"version":777,"weightlimit":null},"id":1}"#
+        );
+    }
+
+    #[test]
+    fn submitblock_reports_rejection_reason() {
+        let client = MinerClient::new(SuccessMinerClientCore::default());
+        let mut handler = IoHandler::new();
+        handler.extend_with(client.to_delegate());
+
+        let request = |block: &str| {
+            handler
+                .handle_request_sync(&format!(
+                    r#"{{"jsonrpc": "2.0", "method": "submitblock", "params": ["{}"], "id": 1}}"#,
+                    block
+                ))
+                .unwrap()
+        };
+
+        let genesis: String = serialize(&test_data::genesis()).to_hex();
+        assert_eq!(
+            &request(&genesis),
+            r#"{"jsonrpc":"2.0","result":"bad-proof","id":1}"#
+        );
+        assert!(request("00").contains("Couldn't parse parameters: block"));
+    }
+
+    #[test]
+    fn getblocktemplate_waits_for_best_block_change() {
+        let core = SuccessMinerClientCore::default();
+        let awaited_blocks = core.awaited_blocks.clone();
+        let client = MinerClient::new(core);
+        let mut handler = IoHandler::new();
+        handler.extend_with(client.to_delegate());
+
+        let sample = handler
+            .handle_request_sync(
+                &(r#"
+			{
+				"jsonrpc": "2.0",
+				"method": "getblocktemplate",
+				"params": [{"longpollid": "0000000000000000000000000000000000000000000000000000000000000001"}],
+				"id": 1
+			}"#),
+            )
+            .unwrap();
+
+        assert!(sample.contains(r#""height":55"#));
+        assert_eq!(*awaited_blocks.lock().unwrap(), vec![H256::from(1)]);
+    }
+
+    #[test]
+    fn mining_is_controlled() {
+        let core = SuccessMinerClientCore::default();
+        let mining = core.mining.clone().unwrap();
+        let client = MinerClient::new(core);
+        let mut handler = IoHandler::new();
+        handler.extend_with(client.to_delegate());
+
+        let request = |method: &str, params: &str| {
+            handler
+                .handle_request_sync(&format!(
+                    r#"{{"jsonrpc": "2.0", "method": "{}", "params": {}, "id": 1}}"#,
+                    method, params
+                ))
+                .unwrap()
+        };
+
+        assert_eq!(
+            &request("pausemining", "[]"),
+            r#"{"jsonrpc":"2.0","result":null,"id":1}"#
+        );
+        assert!(mining.is_paused());
+        assert_eq!(
+            &request("resumemining", "[]"),
+            r#"{"jsonrpc":"2.0","result":null,"id":1}"#
+        );
+        assert!(!mining.is_paused());
+        assert_eq!(
+            &request("setminingthreads", "[0]"),
+            r#"{"jsonrpc":"2.0","result":null,"id":1}"#
+        );
+        assert_eq!(mining.threads(), 0);
+    }
+
+    #[test]
+    fn mining_info_is_reported() {
+        let core = SuccessMinerClientCore::default();
+        let mining = core.mining.clone().unwrap();
+        let client = MinerClient::new(core);
+        let mut handler = IoHandler::new();
+        handler.extend_with(client.to_delegate());
+
+        let request = r#"{"jsonrpc": "2.0", "method": "getmininginfo", "params": [], "id": 1}"#;
+        assert_eq!(
+            &handler.handle_request_sync(request).unwrap(),
+            r#"{"jsonrpc":"2.0","result":{"active":false,"threads":0,"iterationspersecond":0,"templateheight":null,"blocksfound":0},"id":1}"#
+        );
+
+        mining.pause();
+        mining.set_threads(1);
+        assert!(handler
+            .handle_request_sync(request)
+            .unwrap()
+            .contains(r#""active":false,"threads":1"#));
+        mining.resume();
+        assert!(handler
+            .handle_request_sync(request)
+            .unwrap()
+            .contains(r#""active":true,"threads":1"#));
+        mining.set_threads(0);
+    }
+
+    #[test]
+    fn mining_info_is_inactive_when_not_mining() {
+        let client = MinerClient::new(SuccessMinerClientCore {
+            mining: None,
+            ..Default::default()
+        });
+        let mut handler = IoHandler::new();
+        handler.extend_with(client.to_delegate());
+
+        assert_eq!(
+            &handler
+                .handle_request_sync(
+                    r#"{"jsonrpc": "2.0", "method": "getmininginfo", "params": [], "id": 1}"#
+                )
+                .unwrap(),
+            r#"{"jsonrpc":"2.0","result":{"active":false,"threads":0,"iterationspersecond":0,"templateheight":null,"blocksfound":0},"id":1}"#
+        );
+    }
+
+    #[test]
+    fn mining_control_fails_when_not_mining() {
+        let client = MinerClient::new(SuccessMinerClientCore {
+            mining: None,
+            ..Default::default()
+        });
+        let mut handler = IoHandler::new();
+        handler.extend_with(client.to_delegate());
+
+        let sample = handler
+            .handle_request_sync(
+                &(r#"
+			{
+				"jsonrpc": "2.0",
+				"method": "pausemining",
+				"params": [],
+				"id": 1
+			}"#),
+            )
+            .unwrap();
+
+        assert_eq!(
+            &sample,
+            r#"{"jsonrpc":"2.0","error":{"code":-32015,"message":"Execution error.","data":"\"mining is not running\""},"id":1}"#
+        );
+    }
+
+    #[test]
+    fn generate_accepted() {
+        let client = MinerClient::new(SuccessMinerClientCore::default());
+        let mut handler = IoHandler::new();
+        handler.extend_with(client.to_delegate());
+
+        let sample = handler
+            .handle_request_sync(
+                &(r#"
+			{
+				"jsonrpc": "2.0",
+				"method": "generate",
+				"params": [2],
+				"id": 1
+			}"#),
+            )
+            .unwrap();
+
+        assert_eq!(
+            &sample,
+            r#"{"jsonrpc":"2.0","result":["0000000000000000000000000000000000000000000000000000000000000001","0000000000000000000000000000000000000000000000000000000000000002"],"id":1}"#
+        );
+    }
+
+    #[test]
+    fn generate_fails_on_watch_only_node() {
+        let client = MinerClient::new(SuccessMinerClientCore {
+            mining: None,
+            ..Default::default()
+        });
+        let mut handler = IoHandler::new();
+        handler.extend_with(client.to_delegate());
+
+        let sample = handler
+            .handle_request_sync(
+                &(r#"
+			{
+				"jsonrpc": "2.0",
+				"method": "generate",
+				"params": [1],
+				"id": 1
+			}"#),
+            )
+            .unwrap();
+
+        assert_eq!(
+            &sample,
+            r#"{"jsonrpc":"2.0","error":{"code":-32015,"message":"Execution error.","data":"\"blocks are never produced by watch-only node\""},"id":1}"#
         );
     }
 }
@@ -1,7 +1,9 @@
 mod blockchain;
 mod miner;
 mod network;
+mod node;
 
 pub use self::blockchain::{BlockChainClient, BlockChainClientCore};
 pub use self::miner::{MinerClient, MinerClientCore};
 pub use self::network::{NetworkClient, NetworkClientCore};
+pub use self::node::{ConfigReloader, NodeClient, NodeClientCore};
@@ -3,9 +3,13 @@ use jsonrpc_macros::Trailing;
 use p2p;
 use std::net::{IpAddr, SocketAddr};
 use std::sync::Arc;
+use sync;
 use v1::helpers::errors;
 use v1::traits::Network as NetworkRpc;
-use v1::types::{AddNodeOperation, NodeInfo};
+use v1::types::{
+    AddNodeOperation, ExperimentStats, GetConnectionCountResponse, NetworkInfo, NodeInfo,
+    PeerCompressionStats, PeerInfo, PeerPolicyStats, PeerVersionStats,
+};
 
 pub trait NetworkApi: Send + Sync + 'static {
     fn add_node(&self, socket_addr: SocketAddr) -> Result<(), p2p::NodeTableError>;
@@ -14,6 +18,13 @@ pub trait NetworkApi: Send + Sync + 'static {
     fn node_info(&self, node_addr: IpAddr) -> Result<NodeInfo, p2p::NodeTableError>;
     fn nodes_info(&self) -> Vec<NodeInfo>;
     fn connection_count(&self) -> usize;
+    fn connection_count_by_direction(&self) -> p2p::ConnectionCount;
+    fn experiment_stats(&self) -> Vec<p2p::ExperimentStats>;
+    fn peer_versions(&self) -> Vec<p2p::PeerVersionStats>;
+    fn peer_policy_stats(&self) -> Vec<p2p::PeerPolicyStats>;
+    fn compression_stats(&self) -> Vec<p2p::PeerCompressionStats>;
+    fn network_info(&self) -> NetworkInfo;
+    fn peers_info(&self) -> Vec<PeerInfo>;
 }
 
 impl<T> NetworkRpc for NetworkClient<T>
@@ -63,8 +74,60 @@ where
         })
     }
 
-    fn connection_count(&self) -> Result<usize, Error> {
-        Ok(self.api.connection_count())
+    fn connection_count(
+        &self,
+        verbose: Trailing<bool>,
+    ) -> Result<GetConnectionCountResponse, Error> {
+        Ok(match verbose.unwrap_or_default() {
+            true => {
+                GetConnectionCountResponse::Verbose(self.api.connection_count_by_direction().into())
+            }
+            false => GetConnectionCountResponse::Total(self.api.connection_count()),
+        })
+    }
+
+    fn experiment_stats(&self) -> Result<Vec<ExperimentStats>, Error> {
+        Ok(self
+            .api
+            .experiment_stats()
+            .into_iter()
+            .map(Into::into)
+            .collect())
+    }
+
+    fn peer_versions(&self) -> Result<Vec<PeerVersionStats>, Error> {
+        Ok(self
+            .api
+            .peer_versions()
+            .into_iter()
+            .map(Into::into)
+            .collect())
+    }
+
+    fn peer_policy_stats(&self) -> Result<Vec<PeerPolicyStats>, Error> {
+        Ok(self
+            .api
+            .peer_policy_stats()
+            .into_iter()
+            .map(Into::into)
+            .collect())
+    }
+
+    fn compression_stats(&self) -> Result<Vec<PeerCompressionStats>, Error> {
+        Ok(self
+            .api
+            .compression_stats()
+            .into_iter()
+            .map(Into::into)
+            .collect())
+    }
+
+    fn network_info(&self) -> Result<NetworkInfo, Error> {
+        Ok(self.api.network_info())
+    }
+
+    fn peer_info(&self) -> Result<Vec<PeerInfo>, Error> {
+        Ok(self.api.peers_info())
     }
 }
 
@@ -83,11 +146,15 @@ where
 
 pub struct NetworkClientCore {
     p2p: Arc<p2p::Context>,
+    local_sync_node: Option<sync::LocalNodeRef>,
 }
 
 impl NetworkClientCore {
-    pub fn new(p2p: Arc<p2p::Context>) -> Self {
-        NetworkClientCore { p2p: p2p }
+    pub fn new(p2p: Arc<p2p::Context>, local_sync_node: Option<sync::LocalNodeRef>) -> Self {
+        NetworkClientCore {
+            p2p: p2p,
+            local_sync_node: local_sync_node,
+        }
     }
 }
 
@@ -152,4 +219,76 @@ impl NetworkApi for NetworkClientCore {
     fn connection_count(&self) -> usize {
         self.p2p.connections().count()
     }
+
+    fn connection_count_by_direction(&self) -> p2p::ConnectionCount {
+        self.p2p.connections().count_by_direction()
+    }
+
+    fn experiment_stats(&self) -> Vec<p2p::ExperimentStats> {
+        self.p2p.experiment_stats()
+    }
+
+    fn peer_versions(&self) -> Vec<p2p::PeerVersionStats> {
+        self.p2p.peer_version_stats()
+    }
+
+    fn peer_policy_stats(&self) -> Vec<p2p::PeerPolicyStats> {
+        self.p2p.peer_policy_stats()
+    }
+
+    fn compression_stats(&self) -> Vec<p2p::PeerCompressionStats> {
+        self.p2p.compression_stats()
+    }
+
+    fn network_info(&self) -> NetworkInfo {
+        let p2p_config = self.p2p.config();
+        let config = &p2p_config.connection;
+        let mut localaddresses = vec![config.local_address.to_string()];
+        for address in &p2p_config.listen {
+            let address = address.to_string();
+            if !localaddresses.contains(&address) {
+                localaddresses.push(address);
+            }
+        }
+        let mut info = NetworkInfo {
+            protocolversion: config.protocol_version,
+            minpeerversion: config.min_peer_version,
+            subversion: config.user_agent.clone(),
+            localservices: format!("{:016x}", u64::from(config.services)),
+            localrelay: config.relay,
+            connections: self.p2p.connections().count_by_direction().into(),
+            localaddresses: localaddresses,
+            proxy: config.proxy.map(|proxy| proxy.to_string()),
+            ..Default::default()
+        };
+        if let Some(ref local_sync_node) = self.local_sync_node {
+            info.watchonly = local_sync_node.is_watch_only();
+            info.synchronizing = local_sync_node.sync_state().synchronizing();
+            info.timeoffset = local_sync_node.time_offset();
+        }
+        info
+    }
+
+    fn peers_info(&self) -> Vec<PeerInfo> {
+        self.p2p
+            .connections()
+            .info()
+            .into_iter()
+            .map(|peer| PeerInfo {
+                id: peer.id,
+                addr: peer.address.to_string(),
+                inbound: peer.direction == p2p::Direction::Inbound,
+                blockrelayonly: peer.block_relay_only,
+                whitelisted: peer.whitelisted,
+                deprioritized: peer.deprioritized,
+                version: peer.version,
+                subver: peer.user_agent,
+                misbehaviorscore: self
+                    .local_sync_node
+                    .as_ref()
+                    .map(|local_sync_node| local_sync_node.misbehavior_score(&peer.address.ip()))
+                    .unwrap_or(0),
+            })
+            .collect()
+    }
 }
@@ -0,0 +1,188 @@
+use jsonrpc_core::Error;
+use stats::RpcStats;
+use std::sync::Arc;
+use v1::helpers::errors::execution;
+use v1::traits::Node;
+use v1::types::RpcInfo;
+
+/// Applies configuration changes to the running node
+pub trait ConfigReloader: Send + Sync + 'static {
+    /// Re-reads configuration and applies changed settings. Returns descriptions of applied changes
+    fn reload(&self) -> Result<Vec<String>, String>;
+}
+
+pub struct NodeClient<T: NodeClientCoreApi> {
+    core: T,
+}
+
+pub trait NodeClientCoreApi: Send + Sync + 'static {
+    fn reload_config(&self) -> Result<Vec<String>, String>;
+    fn rpc_info(&self) -> RpcInfo;
+}
+
+pub struct NodeClientCore {
+    reloader: Arc<dyn ConfigReloader>,
+    rpc_stats: RpcStats,
+}
+
+impl NodeClientCore {
+    pub fn new(reloader: Arc<dyn ConfigReloader>, rpc_stats: RpcStats) -> Self {
+        NodeClientCore {
+            reloader: reloader,
+            rpc_stats: rpc_stats,
+        }
+    }
+}
+
+impl NodeClientCoreApi for NodeClientCore {
+    fn reload_config(&self) -> Result<Vec<String>, String> {
+        self.reloader.reload()
+    }
+
+    fn rpc_info(&self) -> RpcInfo {
+        RpcInfo::from(&self.rpc_stats)
+    }
+}
+
+impl<T> NodeClient<T>
+where
+    T: NodeClientCoreApi,
+{
+    pub fn new(core: T) -> Self {
+        NodeClient { core: core }
+    }
+}
+
+impl<T> Node for NodeClient<T>
+where
+    T: NodeClientCoreApi,
+{
+    fn reload_config(&self) -> Result<Vec<String>, Error> {
+        self.core.reload_config().map_err(execution)
+    }
+
+    fn rpc_info(&self) -> Result<RpcInfo, Error> {
+        Ok(self.core.rpc_info())
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use jsonrpc_core::IoHandler;
+    use v1::traits::Node;
+    use v1::types::RpcMethodInfo;
+
+    #[derive(Default)]
+    struct SuccessNodeClientCore;
+
+    impl NodeClientCoreApi for SuccessNodeClientCore {
+        fn reload_config(&self) -> Result<Vec<String>, String> {
+            Ok(vec!["server-log-sampling: 1 -> 10".into()])
+        }
+
+        fn rpc_info(&self) -> RpcInfo {
+            RpcInfo {
+                slowcallthresholdms: None,
+                latencybucketsms: vec![1, 10],
+                methods: vec![RpcMethodInfo {
+                    method: "getblock".into(),
+                    calls: 3,
+                    errors: 1,
+                    totaltimeus: 1500,
+                    maxtimeus: 1000,
+                    latencyhistogram: vec![2, 1, 0],
+                }],
+            }
+        }
+    }
+
+    #[derive(Default)]
+    struct ErrorNodeClientCore;
+
+    impl NodeClientCoreApi for ErrorNodeClientCore {
+        fn reload_config(&self) -> Result<Vec<String>, String> {
+            Err("Invalid max-upload - should be number in MB".into())
+        }
+
+        fn rpc_info(&self) -> RpcInfo {
+            RpcInfo {
+                slowcallthresholdms: None,
+                latencybucketsms: vec![],
+                methods: vec![],
+            }
+        }
+    }
+
+    #[test]
+    fn reload_config_success() {
+        let client = NodeClient::new(SuccessNodeClientCore::default());
+        let mut handler = IoHandler::new();
+        handler.extend_with(client.to_delegate());
+
+        let sample = handler
+            .handle_request_sync(
+                &(r#"
+			{
+				"jsonrpc": "2.0",
+				"method": "reloadconfig",
+				"params": [],
+				"id": 1
+			}"#),
+            )
+            .unwrap();
+
+        assert_eq!(
+            &sample,
+            r#"{"jsonrpc":"2.0","result":["server-log-sampling: 1 -> 10"],"id":1}"#
+        );
+    }
+
+    #[test]
+    fn reload_config_error() {
+        let client = NodeClient::new(ErrorNodeClientCore::default());
+        let mut handler = IoHandler::new();
+        handler.extend_with(client.to_delegate());
+
+        let sample = handler
+            .handle_request_sync(
+                &(r#"
+			{
+				"jsonrpc": "2.0",
+				"method": "reloadconfig",
+				"params": [],
+				"id": 1
+			}"#),
+            )
+            .unwrap();
+
+        assert_eq!(
+            &sample,
+            r#"{"jsonrpc":"2.0","error":{"code":-32015,"message":"Execution error.","data":"\"Invalid max-upload - should be number in MB\""},"id":1}"#
+        );
+    }
+
+    #[test]
+    fn rpc_info_success() {
+        let client = NodeClient::new(SuccessNodeClientCore::default());
+        let mut handler = IoHandler::new();
+        handler.extend_with(client.to_delegate());
+
+        let sample = handler
+            .handle_request_sync(
+                &(r#"
+			{
+				"jsonrpc": "2.0",
+				"method": "getrpcinfo",
+				"params": [],
+				"id": 1
+			}"#),
+            )
+            .unwrap();
+
+        assert_eq!(
+            &sample,
+            r#"{"jsonrpc":"2.0","result":{"latencybucketsms":[1,10],"methods":[{"calls":3,"errors":1,"latencyhistogram":[2,1,0],"maxtimeus":1000,"method":"getblock","totaltimeus":1500}],"slowcallthresholdms":null},"id":1}"#
+        );
+    }
+}
@@ -5,8 +5,10 @@ pub mod traits;
 pub mod types;
 
 pub use self::impls::{BlockChainClient, BlockChainClientCore};
+pub use self::impls::{ConfigReloader, NodeClient, NodeClientCore};
 pub use self::impls::{MinerClient, MinerClientCore};
 pub use self::impls::{NetworkClient, NetworkClientCore};
 pub use self::traits::BlockChain;
 pub use self::traits::Miner;
 pub use self::traits::Network;
+pub use self::traits::Node;
@@ -1,8 +1,14 @@
-use jsonrpc_core::Error;
+use jsonrpc_core::{BoxFuture, Error};
 use jsonrpc_macros::Trailing;
 
 use v1::types::GetBlockResponse;
-use v1::types::H256;
+use v1::types::{
+    BlockAnnouncement, BlockChainInfo, BlockCursor, BlockHeadersPage, BlockHeightOrHash,
+    BlockPropagation, BlockStats, ChainEventsFilter, ChainEventsPosition, ChainEventsResponse,
+    DbInfo, FinalizedBlock, GetBlockHeaderResponse, GetOrphanBlocksResponse, HistoricalBestBlock,
+    MemoryInfo, MinerBlocks, ServerLogStats, StaleBlock, VerifyChainResult,
+};
+use v1::types::{Bytes, H256};
 
 build_rpc_trait! {
     /// Parity-randchain blockchain data interface.
@@ -19,13 +25,141 @@ build_rpc_trait! {
         /// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "method": "getblockhash", "params": [0], "id":1 }' -H 'content-type: application/json' http://127.0.0.1:8332/
         #[rpc(name = "getblockhash")]
         fn block_hash(&self, u32) -> Result<H256, Error>;
+        /// Get block locator of the best chain, ending at the block with given height (best block by default).
+        /// Returned hashes could be used to build `getheaders` or `getblocks` request.
+        /// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "method": "getblocklocator", "params": [], "id":1 }' -H 'content-type: application/json' http://127.0.0.1:8332/
+        /// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "method": "getblocklocator", "params": [1000], "id":1 }' -H 'content-type: application/json' http://127.0.0.1:8332/
+        #[rpc(name = "getblocklocator")]
+        fn block_locator(&self, Trailing<Option<u32>>) -> Result<Vec<H256>, Error>;
         /// Get proof-of-work difficulty as a multiple of the minimum difficulty
         /// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "method": "getdifficulty", "params": [], "id":1 }' -H 'content-type: application/json' http://127.0.0.1:8332/
         #[rpc(name = "getdifficulty")]
         fn difficulty(&self) -> Result<f64, Error>;
-        /// Get information on given block.
+        /// Get information on block with given hash or height.
         /// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "method": "getblock", "params": ["000000000019d6689c085ae165831e934ff763ae46a2a6c172b3f1b60a8ce26f"], "id":1 }' -H 'content-type: application/json' http://127.0.0.1:8332/
+        /// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "method": "getblock", "params": [1000, true], "id":1 }' -H 'content-type: application/json' http://127.0.0.1:8332/
         #[rpc(name = "getblock")]
-        fn block(&self, H256, Trailing<bool>) -> Result<GetBlockResponse, Error>;
+        fn block(&self, BlockHeightOrHash, Trailing<bool>) -> BoxFuture<GetBlockResponse>;
+        /// Get up to `count` consecutive canonical block headers, starting at given height.
+        /// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "method": "getblockheaders", "params": [0, 2000, false], "id":1 }' -H 'content-type: application/json' http://127.0.0.1:8332/
+        #[rpc(name = "getblockheaders")]
+        fn block_headers(&self, u32, u32, Trailing<bool>) -> BoxFuture<Vec<GetBlockHeaderResponse>>;
+        /// Get up to `count` canonical block headers, following the block of the cursor (starting at genesis if cursor
+        /// is null). Returned `next` cursor should be passed to the next call. If cursor block has left the canonical
+        /// chain, no headers are returned, `reorged` is set and `forkpoint` refers to its highest canonical ancestor.
+        /// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "method": "listblockheaders", "params": [null, 2000, false], "id":1 }' -H 'content-type: application/json' http://127.0.0.1:8332/
+        #[rpc(name = "listblockheaders")]
+        fn block_headers_page(&self, Option<BlockCursor>, u32, Trailing<bool>) -> BoxFuture<BlockHeadersPage>;
+        /// Get canonical block header at given height.
+        /// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "method": "getblockheaderbyheight", "params": [1000, true], "id":1 }' -H 'content-type: application/json' http://127.0.0.1:8332/
+        #[rpc(name = "getblockheaderbyheight")]
+        fn block_header_by_height(&self, u32, Trailing<bool>) -> BoxFuture<GetBlockHeaderResponse>;
+        /// Get statistics of block with given height or hash.
+        /// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "method": "getblockstats", "params": [1], "id":1 }' -H 'content-type: application/json' http://127.0.0.1:8332/
+        #[rpc(name = "getblockstats")]
+        fn block_stats(&self, BlockHeightOrHash) -> BoxFuture<BlockStats>;
+        /// Get height of the highest block with pruned body, or null if nothing is pruned.
+        /// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "method": "getpruneheight", "params": [], "id":1 }' -H 'content-type: application/json' http://127.0.0.1:8332/
+        #[rpc(name = "getpruneheight")]
+        fn prune_height(&self) -> Result<Option<u32>, Error>;
+        /// Get blockchain state, including estimated synchronization progress.
+        /// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "method": "getblockchaininfo", "params": [], "id":1 }' -H 'content-type: application/json' http://127.0.0.1:8332/
+        #[rpc(name = "getblockchaininfo")]
+        fn blockchain_info(&self) -> BoxFuture<BlockChainInfo>;
+        /// Get blocks, which have been reorged out of the canonical chain during last N hours, with their replacements.
+        /// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "method": "getstaleblocks", "params": [24], "id":1 }' -H 'content-type: application/json' http://127.0.0.1:8332/
+        #[rpc(name = "getstaleblocks")]
+        fn stale_blocks(&self, u32) -> Result<Vec<StaleBlock>, Error>;
+        /// Get blocks, which have been announced by peers during last N hours, with announcement delays of every peer. Only tracked in watch-only mode.
+        /// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "method": "getblockannouncements", "params": [1], "id":1 }' -H 'content-type: application/json' http://127.0.0.1:8332/
+        #[rpc(name = "getblockannouncements")]
+        fn block_announcements(&self, u32) -> Result<Vec<BlockAnnouncement>, Error>;
+        /// Get blocks, which have been first seen from peers during last N hours, with delays of their receipt, verification and connection to the canonical chain.
+        /// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "method": "getblockpropagationstats", "params": [1], "id":1 }' -H 'content-type: application/json' http://127.0.0.1:8332/
+        #[rpc(name = "getblockpropagationstats")]
+        fn block_propagations(&self, u32) -> Result<Vec<BlockPropagation>, Error>;
+        /// Get canonical chain events (block connects, disconnects and reorganization boundaries), recorded after event with given sequence number.
+        /// If cursor (or null) is passed instead of the sequence number, the page of events, recorded after the event of the cursor
+        /// (starting at the oldest retained event if cursor is null), is returned. Returned `next` cursor should be passed
+        /// to the next call. If the best block of the cursor has left the canonical chain, `reorged` is set and `forkpoint`
+        /// refers to its highest canonical ancestor. Events of the reorganization are returned, unless they are not retained.
+        /// Optional filter limits returned events to those, satisfying all of its conditions: `miner` - connects and disconnects
+        /// of blocks, mined with given public key, `minreorgdepth` - reorganization starts, which have disconnected at least
+        /// given number of blocks, `finalized` - connects of blocks, which are still canonical and finalized. Filtered out
+        /// events are skipped by the returned cursor.
+        /// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "method": "listchainevents", "params": [0], "id":1 }' -H 'content-type: application/json' http://127.0.0.1:8332/
+        /// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "method": "listchainevents", "params": [null], "id":1 }' -H 'content-type: application/json' http://127.0.0.1:8332/
+        /// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "method": "listchainevents", "params": [null, {"minreorgdepth": 2}], "id":1 }' -H 'content-type: application/json' http://127.0.0.1:8332/
+        #[rpc(name = "listchainevents")]
+        fn chain_events(&self, Option<ChainEventsPosition>, Trailing<ChainEventsFilter>) -> BoxFuture<ChainEventsResponse>;
+        /// Get heights of canon blocks, mined with given public key (hex), in the range of `count` heights,
+        /// starting at given height (the whole chain by default). Requires node to run with `--miner-index`.
+        /// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "method": "getminerblocks", "params": ["6969696969696969696969696969696969696969696969696969696969696969"], "id":1 }' -H 'content-type: application/json' http://127.0.0.1:8332/
+        /// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "method": "getminerblocks", "params": ["6969696969696969696969696969696969696969696969696969696969696969", 1000, 100], "id":1 }' -H 'content-type: application/json' http://127.0.0.1:8332/
+        #[rpc(name = "getminerblocks")]
+        fn miner_blocks(&self, Bytes, Trailing<u32>, Trailing<Option<u32>>) -> BoxFuture<MinerBlocks>;
+        /// Mark block and all its descendants invalid, reorganizing to the best remaining valid chain.
+        /// Marks are kept in the database, so blocks stay invalid after restart.
+        /// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "method": "invalidateblock", "params": ["000000000019d6689c085ae165831e934ff763ae46a2a6c172b3f1b60a8ce26f"], "id":1 }' -H 'content-type: application/json' http://127.0.0.1:8332/
+        #[rpc(name = "invalidateblock")]
+        fn invalidate_block(&self, H256) -> Result<(), Error>;
+        /// Remove invalidity mark from block, its descendants and ancestors, previously set by `invalidateblock`.
+        /// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "method": "reconsiderblock", "params": ["000000000019d6689c085ae165831e934ff763ae46a2a6c172b3f1b60a8ce26f"], "id":1 }' -H 'content-type: application/json' http://127.0.0.1:8332/
+        #[rpc(name = "reconsiderblock")]
+        fn reconsider_block(&self, H256) -> Result<(), Error>;
+        /// Roll back the last reorganization: invalidate the first block, it has connected, reorganizing to the
+        /// best remaining valid chain (usually the disconnected one). Returns hash of the invalidated block,
+        /// which could be passed to `reconsiderblock` to undo the rollback.
+        /// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "method": "rollbacklastreorg", "params": [], "id":1 }' -H 'content-type: application/json' http://127.0.0.1:8332/
+        #[rpc(name = "rollbacklastreorg")]
+        fn rollback_last_reorg(&self) -> Result<H256, Error>;
+        /// Get latest finalized block, or null if reorganizations depth is not limited.
+        /// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "method": "getfinalizedblock", "params": [], "id":1 }' -H 'content-type: application/json' http://127.0.0.1:8332/
+        #[rpc(name = "getfinalizedblock")]
+        fn finalized_block(&self) -> Result<Option<FinalizedBlock>, Error>;
+        /// Get orphan blocks, which are held in memory until their parents are received.
+        /// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "method": "getorphanblocks", "params": [], "id":1 }' -H 'content-type: application/json' http://127.0.0.1:8332/
+        /// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "method": "getorphanblocks", "params": [true], "id":1 }' -H 'content-type: application/json' http://127.0.0.1:8332/
+        #[rpc(name = "getorphanblocks")]
+        fn orphan_blocks(&self, Trailing<bool>) -> Result<GetOrphanBlocksResponse, Error>;
+        /// Get usage of the memory budget, shared by orphan blocks pool, verification queue and
+        /// queue of peers requests.
+        /// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "method": "getmemoryinfo", "params": [], "id":1 }' -H 'content-type: application/json' http://127.0.0.1:8332/
+        #[rpc(name = "getmemoryinfo")]
+        fn memory_info(&self) -> Result<MemoryInfo, Error>;
+        /// Get outcomes of serving synchronization requests of peers. Outcomes are counted even when
+        /// serving is not logged.
+        /// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "method": "getserverlogstats", "params": [], "id":1 }' -H 'content-type: application/json' http://127.0.0.1:8332/
+        #[rpc(name = "getserverlogstats")]
+        fn server_log_stats(&self) -> Result<ServerLogStats, Error>;
+        /// Log only 1 of N served synchronization requests of peers (at trace level). 0 disables logging.
+        /// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "method": "setserverlogsampling", "params": [100], "id":1 }' -H 'content-type: application/json' http://127.0.0.1:8332/
+        #[rpc(name = "setserverlogsampling")]
+        fn set_server_log_sampling(&self, u32) -> Result<(), Error>;
+        /// Get best block and its randomness, as they were at given time (in seconds since epoch).
+        /// Only times within the chain events retention window are answered.
+        /// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "method": "getbestblockat", "params": [1546300800], "id":1 }' -H 'content-type: application/json' http://127.0.0.1:8332/
+        #[rpc(name = "getbestblockat")]
+        fn best_block_at(&self, u32) -> BoxFuture<HistoricalBestBlock>;
+        /// Get best block and its randomness, as they were right after given chain event has been recorded.
+        /// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "method": "getbestblockatevent", "params": [100], "id":1 }' -H 'content-type: application/json' http://127.0.0.1:8332/
+        #[rpc(name = "getbestblockatevent")]
+        fn best_block_at_event(&self, u64) -> BoxFuture<HistoricalBestBlock>;
+        /// Verify best canon blocks again at given verification level (paranoid, full or header; full by default),
+        /// to detect storage corruption. Last 6 blocks are verified by default, 0 depth means the whole chain.
+        /// Verification stops at the first invalid block.
+        /// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "method": "verifychain", "params": [], "id":1 }' -H 'content-type: application/json' http://127.0.0.1:8332/
+        /// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "method": "verifychain", "params": ["header", 1000], "id":1 }' -H 'content-type: application/json' http://127.0.0.1:8332/
+        #[rpc(name = "verifychain")]
+        fn verify_chain(&self, Trailing<Option<String>>, Trailing<Option<u32>>) -> BoxFuture<VerifyChainResult>;
+        /// Get sizes of the database files and columns. Every column is scanned, so it could take a while.
+        /// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "method": "getdbinfo", "params": [], "id":1 }' -H 'content-type: application/json' http://127.0.0.1:8332/
+        #[rpc(name = "getdbinfo")]
+        fn db_info(&self) -> BoxFuture<DbInfo>;
+        /// Compact the database, reclaiming space of deleted and overwritten data. Node keeps running
+        /// while the database is compacted. Returns when compaction is finished.
+        /// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "method": "compactdb", "params": [], "id":1 }' -H 'content-type: application/json' http://127.0.0.1:8332/
+        #[rpc(name = "compactdb")]
+        fn compact_db(&self) -> BoxFuture<()>;
     }
 }
@@ -1,13 +1,42 @@
-use jsonrpc_core::Error;
+use jsonrpc_core::{BoxFuture, Error};
 
-use v1::types::{BlockTemplate, BlockTemplateRequest};
+use v1::types::{BlockTemplate, BlockTemplateRequest, MiningInfo, RawBlock, H256};
 
 build_rpc_trait! {
     /// Parity-randchain miner data interface.
     pub trait Miner {
-        /// Get block template for mining.
+        /// Get block template for mining. If `longpollid` of the previous template is passed, response
+        /// is delayed until the best block changes (or until long-polling timeout).
         /// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "method": "getblocktemplate", "params": [{"capabilities": ["coinbasetxn", "workid", "coinbase/append"]}], "id":1 }' -H 'content-type: application/json' http://127.0.0.1:8332/
+        /// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "method": "getblocktemplate", "params": [{"longpollid": "0000000000000000000000000000000000000000000000000000000000000000"}], "id":1 }' -H 'content-type: application/json' http://127.0.0.1:8332/
         #[rpc(name = "getblocktemplate")]
-        fn get_block_template(&self, BlockTemplateRequest) -> Result<BlockTemplate, Error>;
+        fn get_block_template(&self, BlockTemplateRequest) -> BoxFuture<BlockTemplate>;
+        /// Submit mined block. Returns null if block is accepted, or the reason of rejection otherwise:
+        /// "duplicate", "bad-parent", "bad-proof", "bad-timestamp", "bad-difficulty", "bad-version",
+        /// "non-final", "too-deep-reorg" or "database-error".
+        /// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "method": "submitblock", "params": ["00000000..."], "id":1 }' -H 'content-type: application/json' http://127.0.0.1:8332/
+        #[rpc(name = "submitblock")]
+        fn submit_block(&self, RawBlock) -> BoxFuture<Option<String>>;
+        /// Change number of local mining threads. Zero threads stop mining.
+        /// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "method": "setminingthreads", "params": [2], "id":1 }' -H 'content-type: application/json' http://127.0.0.1:8332/
+        #[rpc(name = "setminingthreads")]
+        fn set_mining_threads(&self, u32) -> Result<(), Error>;
+        /// Pause local mining threads.
+        /// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "method": "pausemining", "params": [], "id":1 }' -H 'content-type: application/json' http://127.0.0.1:8332/
+        #[rpc(name = "pausemining")]
+        fn pause_mining(&self) -> Result<(), Error>;
+        /// Resume paused local mining threads.
+        /// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "method": "resumemining", "params": [], "id":1 }' -H 'content-type: application/json' http://127.0.0.1:8332/
+        #[rpc(name = "resumemining")]
+        fn resume_mining(&self) -> Result<(), Error>;
+        /// Get state of the local miner: number of threads, achieved iterations rate, height of the
+        /// last block template and number of found blocks. Miner is reported inactive on watch-only node.
+        /// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "method": "getmininginfo", "params": [], "id":1 }' -H 'content-type: application/json' http://127.0.0.1:8332/
+        #[rpc(name = "getmininginfo")]
+        fn mining_info(&self) -> Result<MiningInfo, Error>;
+        /// Mine given number of blocks immediately (regtest only). Returns hashes of generated blocks.
+        /// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "method": "generate", "params": [10], "id":1 }' -H 'content-type: application/json' http://127.0.0.1:18443/
+        #[rpc(name = "generate")]
+        fn generate(&self, u32) -> BoxFuture<Vec<H256>>;
     }
 }
@@ -1,7 +1,9 @@
 mod blockchain;
 mod miner;
 mod network;
+mod node;
 
 pub use self::blockchain::BlockChain;
 pub use self::miner::Miner;
 pub use self::network::Network;
+pub use self::node::Node;
@@ -1,6 +1,9 @@
 use jsonrpc_core::Error;
 use jsonrpc_macros::Trailing;
-use v1::types::{AddNodeOperation, NodeInfo};
+use v1::types::{
+    AddNodeOperation, ExperimentStats, GetConnectionCountResponse, NetworkInfo, NodeInfo,
+    PeerCompressionStats, PeerInfo, PeerPolicyStats, PeerVersionStats,
+};
 
 build_rpc_trait! {
     /// Parity-bitcoin network interface
@@ -16,9 +19,34 @@ build_rpc_trait! {
         /// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "id":"1", "method": "getaddednodeinfo", "params": [true, "192.168.0.201"] }' -H 'content-type: application/json' http://127.0.0.1:8332/
         #[rpc(name = "getaddednodeinfo")]
         fn node_info(&self, bool, Trailing<String>) -> Result<Vec<NodeInfo>, Error>;
-        /// Query node(s) info
+        /// Query number of connections, optionally broken down by direction
         /// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "id":"1", "method": "getconnectioncount", "params": [] }' -H 'content-type: application/json' http://127.0.0.1:8332/
+        /// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "id":"1", "method": "getconnectioncount", "params": [true] }' -H 'content-type: application/json' http://127.0.0.1:8332/
         #[rpc(name = "getconnectioncount")]
-        fn connection_count(&self) -> Result<usize, Error>;
+        fn connection_count(&self, Trailing<bool>) -> Result<GetConnectionCountResponse, Error>;
+        /// Query outcomes of protocol experiments
+        /// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "id":"1", "method": "getexperimentstats", "params": [] }' -H 'content-type: application/json' http://127.0.0.1:8332/
+        #[rpc(name = "getexperimentstats")]
+        fn experiment_stats(&self) -> Result<Vec<ExperimentStats>, Error>;
+        /// Query numbers of connected and rejected peers by protocol version
+        /// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "id":"1", "method": "getpeerversions", "params": [] }' -H 'content-type: application/json' http://127.0.0.1:8332/
+        #[rpc(name = "getpeerversions")]
+        fn peer_versions(&self) -> Result<Vec<PeerVersionStats>, Error>;
+        /// Query numbers of peers, rejected or deprioritized by connection policy filters
+        /// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "id":"1", "method": "getpeerpolicystats", "params": [] }' -H 'content-type: application/json' http://127.0.0.1:8332/
+        #[rpc(name = "getpeerpolicystats")]
+        fn peer_policy_stats(&self) -> Result<Vec<PeerPolicyStats>, Error>;
+        /// Query outcomes of messages compression for connected peers
+        /// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "id":"1", "method": "getcompressionstats", "params": [] }' -H 'content-type: application/json' http://127.0.0.1:8332/
+        #[rpc(name = "getcompressionstats")]
+        fn compression_stats(&self) -> Result<Vec<PeerCompressionStats>, Error>;
+        /// Query protocol version, connections, relay and proxy settings of the node
+        /// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "id":"1", "method": "getnetworkinfo", "params": [] }' -H 'content-type: application/json' http://127.0.0.1:8332/
+        #[rpc(name = "getnetworkinfo")]
+        fn network_info(&self) -> Result<NetworkInfo, Error>;
+        /// Query connected peers, with misbehavior scores of their addresses
+        /// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "id":"1", "method": "getpeerinfo", "params": [] }' -H 'content-type: application/json' http://127.0.0.1:8332/
+        #[rpc(name = "getpeerinfo")]
+        fn peer_info(&self) -> Result<Vec<PeerInfo>, Error>;
     }
 }
@@ -0,0 +1,18 @@
+use jsonrpc_core::Error;
+use v1::types::RpcInfo;
+
+build_rpc_trait! {
+    /// RandChain node administration interface
+    pub trait Node {
+        /// Re-read the configuration and apply settings, which could be changed without restart:
+        /// log filters, upload limit, server log sampling, JSONRPC users and connection targets.
+        /// Returns descriptions of applied changes
+        /// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "method": "reloadconfig", "params": [], "id":1 }' -H 'content-type: application/json' http://127.0.0.1:8332/
+        #[rpc(name = "reloadconfig")]
+        fn reload_config(&self) -> Result<Vec<String>, Error>;
+        /// Get statistics of RPC method calls: number of calls and errors, and histogram of call latencies
+        /// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "method": "getrpcinfo", "params": [], "id":1 }' -H 'content-type: application/json' http://127.0.0.1:8332/
+        #[rpc(name = "getrpcinfo")]
+        fn rpc_info(&self) -> Result<RpcInfo, Error>;
+    }
+}
@@ -0,0 +1,50 @@
+use super::hash::H256;
+
+/// Peer, which has announced the block
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct BlockAnnouncer {
+    /// Peer index
+    pub peer: usize,
+    /// Delay in milliseconds since the first announcement of the block
+    pub delayms: u32,
+}
+
+/// Block announcement, observed from peers
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct BlockAnnouncement {
+    /// Hash of the announced block
+    pub hash: H256,
+    /// Time in seconds since epoch (Jan 1 1970 GMT), when block has been announced for the first time
+    pub time: u32,
+    /// Peers, which have announced the block, ordered by delay
+    pub announcers: Vec<BlockAnnouncer>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::hash::H256;
+    use super::*;
+    use serde_json;
+
+    #[test]
+    fn block_announcement_serialize() {
+        let announcement = BlockAnnouncement {
+            hash: H256::from(1),
+            time: 1000,
+            announcers: vec![
+                BlockAnnouncer {
+                    peer: 3,
+                    delayms: 0,
+                },
+                BlockAnnouncer {
+                    peer: 5,
+                    delayms: 120,
+                },
+            ],
+        };
+        assert_eq!(
+            serde_json::to_string(&announcement).unwrap(),
+            r#"{"hash":"0100000000000000000000000000000000000000000000000000000000000000","time":1000,"announcers":[{"peer":3,"delayms":0},{"peer":5,"delayms":120}]}"#
+        );
+    }
+}
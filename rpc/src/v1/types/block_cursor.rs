@@ -0,0 +1,174 @@
+use super::block_header::GetBlockHeaderResponse;
+use hex::{FromHex, ToHex};
+use primitives::hash::H256 as GlobalH256;
+use serde::de::{Error, Visitor};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+use std::marker::PhantomData;
+
+/// Opaque position in the canonical chain, returned by paginated calls. Refers to the last
+/// block of the page, so that the next call could detect that the page has been reorged.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BlockCursor {
+    /// Height of the block
+    pub height: u32,
+    /// Hash of the block
+    pub hash: GlobalH256,
+}
+
+/// Page of canonical block headers
+#[derive(Debug, Serialize)]
+pub struct BlockHeadersPage {
+    /// Headers, following the cursor block. Empty if cursor block has been reorged
+    pub headers: Vec<GetBlockHeaderResponse>,
+    /// Cursor to pass to the next call
+    pub next: BlockCursor,
+    /// True if cursor block is not in the canonical chain anymore
+    pub reorged: bool,
+    /// Cursor of the highest canonical ancestor of the reorged cursor block. Pages after it
+    /// should be requested again
+    pub forkpoint: Option<BlockCursor>,
+}
+
+/// Opaque cursor, serialized as a fixed-length hex string
+pub trait HexCursor: Sized {
+    /// Length of the serialized cursor in bytes
+    const LEN: usize;
+    /// Name of the cursor, used in error messages
+    const NAME: &'static str;
+
+    /// Appends serialized cursor to `bytes`
+    fn write_bytes(&self, bytes: &mut Vec<u8>);
+    /// Reads cursor from `bytes` of exactly `LEN` bytes
+    fn read_bytes(bytes: &[u8]) -> Self;
+}
+
+/// Serializes cursor as a hex string
+pub fn serialize_cursor<C, S>(cursor: &C, serializer: S) -> Result<S::Ok, S::Error>
+where
+    C: HexCursor,
+    S: Serializer,
+{
+    let mut bytes = Vec::with_capacity(C::LEN);
+    cursor.write_bytes(&mut bytes);
+    serializer.serialize_str(&bytes.to_hex::<String>())
+}
+
+/// Deserializes cursor from a hex string
+pub fn deserialize_cursor<'a, C, D>(deserializer: D) -> Result<C, D::Error>
+where
+    C: HexCursor,
+    D: Deserializer<'a>,
+{
+    deserializer.deserialize_identifier(HexCursorVisitor(PhantomData))
+}
+
+struct HexCursorVisitor<C>(PhantomData<C>);
+
+impl<'a, C: HexCursor> Visitor<'a> for HexCursorVisitor<C> {
+    type Value = C;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(formatter, "a {}", C::NAME)
+    }
+
+    fn visit_str<E>(self, value: &str) -> Result<Self::Value, E>
+    where
+        E: Error,
+    {
+        let bytes: Vec<u8> = FromHex::from_hex(value).map_err(|_| Error::custom("invalid hex"))?;
+        if bytes.len() != C::LEN {
+            return Err(Error::custom("invalid cursor length"));
+        }
+        Ok(C::read_bytes(&bytes))
+    }
+
+    fn visit_string<E>(self, value: String) -> Result<Self::Value, E>
+    where
+        E: Error,
+    {
+        self.visit_str(value.as_ref())
+    }
+}
+
+impl HexCursor for BlockCursor {
+    const LEN: usize = 36;
+    const NAME: &'static str = "block cursor";
+
+    fn write_bytes(&self, bytes: &mut Vec<u8>) {
+        bytes.extend_from_slice(&self.height.to_be_bytes());
+        bytes.extend_from_slice(&self.hash[..]);
+    }
+
+    fn read_bytes(bytes: &[u8]) -> Self {
+        let mut height = [0u8; 4];
+        height.copy_from_slice(&bytes[..4]);
+        BlockCursor {
+            height: u32::from_be_bytes(height),
+            hash: GlobalH256::from(&bytes[4..]),
+        }
+    }
+}
+
+impl Serialize for BlockCursor {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serialize_cursor(self, serializer)
+    }
+}
+
+impl<'a> Deserialize<'a> for BlockCursor {
+    fn deserialize<D>(deserializer: D) -> Result<BlockCursor, D::Error>
+    where
+        D: Deserializer<'a>,
+    {
+        deserialize_cursor(deserializer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json;
+
+    #[test]
+    fn block_cursor_roundtrip() {
+        let cursor = BlockCursor {
+            height: 258,
+            hash: GlobalH256::from(7),
+        };
+        let serialized = serde_json::to_string(&cursor).unwrap();
+        assert_eq!(
+            serialized,
+            r#""000001020700000000000000000000000000000000000000000000000000000000000000""#
+        );
+        assert_eq!(
+            serde_json::from_str::<BlockCursor>(&serialized).unwrap(),
+            cursor
+        );
+        assert!(serde_json::from_str::<BlockCursor>(r#""00000102""#).is_err());
+        assert!(serde_json::from_str::<BlockCursor>(r#""zz""#).is_err());
+    }
+
+    #[test]
+    fn block_headers_page_serialize() {
+        let page = BlockHeadersPage {
+            headers: Vec::new(),
+            next: BlockCursor {
+                height: 1,
+                hash: GlobalH256::from(1),
+            },
+            reorged: true,
+            forkpoint: Some(BlockCursor {
+                height: 1,
+                hash: GlobalH256::from(1),
+            }),
+        };
+        assert_eq!(
+            serde_json::to_string(&page).unwrap(),
+            r#"{"headers":[],"next":"000000010100000000000000000000000000000000000000000000000000000000000000","reorged":true,"forkpoint":"000000010100000000000000000000000000000000000000000000000000000000000000"}"#
+        );
+    }
+}
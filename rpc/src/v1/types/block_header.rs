@@ -0,0 +1,100 @@
+use super::bytes::Bytes;
+use super::hash::H256;
+use serde::{Serialize, Serializer};
+
+/// Hex-encoded block header
+pub type RawBlockHeader = Bytes;
+
+/// Response to getblockheaders RPC request
+#[derive(Debug)]
+pub enum GetBlockHeaderResponse {
+    /// When asking for short response
+    Raw(RawBlockHeader),
+    /// When asking for verbose response
+    Verbose(VerboseBlockHeader),
+}
+
+/// Verbose information on canonical block header
+#[derive(Debug, Default, Serialize, Deserialize, PartialEq)]
+pub struct VerboseBlockHeader {
+    /// Block hash
+    pub hash: H256,
+    /// Number of confirmations
+    pub confirmations: i64,
+    /// Block height
+    pub height: u32,
+    /// Block version
+    pub version: u32,
+    /// Block version as hex
+    #[serde(rename = "versionHex")]
+    pub version_hex: String,
+    /// Pubkey as hex
+    #[serde(rename = "pubkeyHex")]
+    pub pubkey_hex: String,
+    /// Randomness as hex
+    #[serde(rename = "randomnessHex")]
+    pub randomness_hex: String,
+    /// Block time in seconds since epoch (Jan 1 1970 GMT)
+    pub time: u32,
+    /// Median block time in seconds since epoch (Jan 1 1970 GMT)
+    pub mediantime: u32,
+    /// Block iterations
+    pub iterations: u32,
+    /// Block nbits
+    pub bits: u32,
+    /// Block difficulty
+    pub difficulty: f64,
+    /// Hash of previous block
+    pub previousblockhash: Option<H256>,
+    /// Hash of next block
+    pub nextblockhash: Option<H256>,
+}
+
+impl Serialize for GetBlockHeaderResponse {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match *self {
+            GetBlockHeaderResponse::Raw(ref raw_header) => raw_header.serialize(serializer),
+            GetBlockHeaderResponse::Verbose(ref verbose_header) => {
+                verbose_header.serialize(serializer)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::bytes::Bytes;
+    use super::super::hash::H256;
+    use super::*;
+    use serde_json;
+
+    #[test]
+    fn block_header_response_serialize() {
+        let response = vec![
+            GetBlockHeaderResponse::Raw(Bytes::new(vec![1, 2])),
+            GetBlockHeaderResponse::Verbose(VerboseBlockHeader {
+                hash: H256::from(1),
+                confirmations: 1,
+                height: 2,
+                version: 1,
+                version_hex: "1".to_owned(),
+                pubkey_hex: "69".to_owned(),
+                randomness_hex: "7788".to_owned(),
+                time: 111,
+                mediantime: 100,
+                iterations: 124,
+                bits: 13513,
+                difficulty: 555.555,
+                previousblockhash: Some(H256::from(4)),
+                nextblockhash: None,
+            }),
+        ];
+        assert_eq!(
+            serde_json::to_string(&response).unwrap(),
+            r#"["0102",{"hash":"0100000000000000000000000000000000000000000000000000000000000000","confirmations":1,"height":2,"version":1,"versionHex":"1","pubkeyHex":"69","randomnessHex":"7788","time":111,"mediantime":100,"iterations":124,"bits":13513,"difficulty":555.555,"previousblockhash":"0400000000000000000000000000000000000000000000000000000000000000","nextblockhash":null}]"#
+        );
+    }
+}
@@ -0,0 +1,34 @@
+use super::hash::H256;
+
+/// Block reference: either height of the canonical block or block hash
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+#[serde(untagged)]
+pub enum BlockHeightOrHash {
+    /// Block height
+    Height(u32),
+    /// Block hash
+    Hash(H256),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::hash::H256;
+    use super::*;
+    use serde_json;
+
+    #[test]
+    fn block_height_or_hash_deserialize() {
+        assert_eq!(
+            serde_json::from_str::<BlockHeightOrHash>("10").unwrap(),
+            BlockHeightOrHash::Height(10)
+        );
+        assert_eq!(
+            serde_json::from_str::<BlockHeightOrHash>(
+                r#""0100000000000000000000000000000000000000000000000000000000000000""#
+            )
+            .unwrap(),
+            BlockHeightOrHash::Hash(H256::from(1))
+        );
+        assert!(serde_json::from_str::<BlockHeightOrHash>("-1").is_err());
+    }
+}
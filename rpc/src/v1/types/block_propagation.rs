@@ -0,0 +1,45 @@
+use super::hash::H256;
+
+/// Propagation of the block through the node. Delays are in milliseconds since the block
+/// has been first seen (announced or received); null if the block has not reached the stage
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct BlockPropagation {
+    /// Hash of the block
+    pub hash: H256,
+    /// Time in seconds since epoch (Jan 1 1970 GMT), when block has been seen for the first time
+    pub time: u32,
+    /// Peer, which has announced (or sent) the block first
+    pub peer: usize,
+    /// True if block has been announced before it has been received
+    pub announced: bool,
+    /// Delay of the first receipt of the block
+    pub receivedms: Option<u32>,
+    /// Delay of the block verification
+    pub verifiedms: Option<u32>,
+    /// Delay of connecting block to the canonical chain
+    pub connectedms: Option<u32>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::hash::H256;
+    use super::*;
+    use serde_json;
+
+    #[test]
+    fn block_propagation_serialize() {
+        let propagation = BlockPropagation {
+            hash: H256::from(1),
+            time: 1000,
+            peer: 3,
+            announced: true,
+            receivedms: Some(120),
+            verifiedms: Some(180),
+            connectedms: None,
+        };
+        assert_eq!(
+            serde_json::to_string(&propagation).unwrap(),
+            r#"{"hash":"0100000000000000000000000000000000000000000000000000000000000000","time":1000,"peer":3,"announced":true,"receivedms":120,"verifiedms":180,"connectedms":null}"#
+        );
+    }
+}
@@ -0,0 +1,45 @@
+use super::hash::H256;
+
+/// Per-block statistics
+#[derive(Debug, Default, Serialize, Deserialize, PartialEq)]
+pub struct BlockStats {
+    /// Block hash
+    pub hash: H256,
+    /// Block height. None if block is on the side chain
+    pub height: Option<u32>,
+    /// Block size
+    pub size: u32,
+    /// Block time in seconds since epoch (Jan 1 1970 GMT)
+    pub time: u32,
+    /// Difference between block time and parent block time, in seconds. None for genesis block
+    pub timedelta: Option<i64>,
+    /// Number of VDF iterations
+    pub iterations: u32,
+    /// Difference between median time of the block and median time of the parent block, in seconds.
+    /// Unlike `timedelta`, it is never negative. None for genesis block
+    pub interval: Option<i64>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::hash::H256;
+    use super::*;
+    use serde_json;
+
+    #[test]
+    fn block_stats_serialize() {
+        let stats = BlockStats {
+            hash: H256::from(1),
+            height: Some(2),
+            size: 859,
+            time: 1002,
+            timedelta: Some(-1),
+            iterations: 4,
+            interval: Some(0),
+        };
+        assert_eq!(
+            serde_json::to_string(&stats).unwrap(),
+            r#"{"hash":"0100000000000000000000000000000000000000000000000000000000000000","height":2,"size":859,"time":1002,"timedelta":-1,"iterations":4,"interval":0}"#
+        );
+    }
+}
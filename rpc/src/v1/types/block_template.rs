@@ -36,6 +36,8 @@ pub struct BlockTemplate {
     pub bits: u32,
     /// The height of the next block
     pub height: u32,
+    /// Identifier of the template for long-polling: new template is returned when the best block changes
+    pub longpollid: Option<H256>,
 }
 
 impl From<miner::BlockTemplate> for BlockTemplate {
@@ -43,6 +45,7 @@ impl From<miner::BlockTemplate> for BlockTemplate {
         BlockTemplate {
             version: block.version,
             previousblockhash: block.previous_header_hash.reversed().into(),
+            longpollid: Some(block.previous_header_hash.reversed().into()),
             curtime: block.time,
             bits: block.bits.into(),
             height: block.height,
@@ -74,9 +77,10 @@ mod tests {
                 curtime: 100,
                 bits: 200,
                 height: 300,
+                longpollid: None,
             })
             .unwrap(),
-            r#"{"version":0,"rules":null,"vbavailable":null,"vbrequired":null,"previousblockhash":"0000000000000000000000000000000000000000000000000000000000000000","coinbaseaux":null,"target":"0000000000000000000000000000000000000000000000000000000000000000","mintime":null,"mutable":null,"weightlimit":null,"curtime":100,"bits":200,"height":300}"#
+            r#"{"version":0,"rules":null,"vbavailable":null,"vbrequired":null,"previousblockhash":"0000000000000000000000000000000000000000000000000000000000000000","coinbaseaux":null,"target":"0000000000000000000000000000000000000000000000000000000000000000","mintime":null,"mutable":null,"weightlimit":null,"curtime":100,"bits":200,"height":300,"longpollid":null}"#
         );
         assert_eq!(
             serde_json::to_string(&BlockTemplate {
@@ -93,9 +97,10 @@ mod tests {
                 curtime: 100,
                 bits: 200,
                 height: 300,
+                longpollid: None,
             })
             .unwrap(),
-            r#"{"version":0,"rules":["a"],"vbavailable":{"b":5},"vbrequired":10,"previousblockhash":"0a00000000000000000000000000000000000000000000000000000000000000","coinbaseaux":{"c":"d"},"target":"6400000000000000000000000000000000000000000000000000000000000000","mintime":7,"mutable":["afg"],"weightlimit":523,"curtime":100,"bits":200,"height":300}"#
+            r#"{"version":0,"rules":["a"],"vbavailable":{"b":5},"vbrequired":10,"previousblockhash":"0a00000000000000000000000000000000000000000000000000000000000000","coinbaseaux":{"c":"d"},"target":"6400000000000000000000000000000000000000000000000000000000000000","mintime":7,"mutable":["afg"],"weightlimit":523,"curtime":100,"bits":200,"height":300,"longpollid":null}"#
         );
     }
 
@@ -117,6 +122,7 @@ mod tests {
 				curtime: 100,
 				bits: 200,
 				height: 300,
+				longpollid: None,
 			});
         assert_eq!(
 			serde_json::from_str::<BlockTemplate>(r#"{"version":0,"rules":["a"],"vbavailable":{"b":5},"vbrequired":10,"previousblockhash":"0a00000000000000000000000000000000000000000000000000000000000000","transactions":[{"data":"00010203","txid":null,"hash":null,"depends":null,"fee":null,"sigops":null,"weight":null,"required":false}],"coinbaseaux":{"c":"d"},"coinbasevalue":30,"coinbasetxn":{"data":"555555","txid":"2c00000000000000000000000000000000000000000000000000000000000000","hash":"3700000000000000000000000000000000000000000000000000000000000000","depends":[1],"fee":300,"sigops":400,"weight":500,"required":true},"target":"6400000000000000000000000000000000000000000000000000000000000000","mintime":7,"mutable":["afg"],"noncerange":"00000000ffffffff","sigoplimit":45,"sizelimit":449,"weightlimit":523,"curtime":100,"bits":200,"height":300}"#).unwrap(),
@@ -134,6 +140,7 @@ mod tests {
 				curtime: 100,
 				bits: 200,
 				height: 300,
+				longpollid: None,
 			});
     }
 }
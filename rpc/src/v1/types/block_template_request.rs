@@ -1,3 +1,4 @@
+use super::hash::H256;
 use std::collections::HashSet;
 
 /// Block template request mode
@@ -28,6 +29,8 @@ pub struct BlockTemplateRequest {
     pub capabilities: Option<HashSet<String>>,
     /// Softfork deployments, supported by client
     pub rules: Option<HashSet<String>>,
+    /// Long-polling: `longpollid` of the previously received template. Response is delayed until the best block changes
+    pub longpollid: Option<H256>,
 }
 
 #[cfg(test)]
@@ -63,16 +66,17 @@ mod tests {
     fn block_template_request_serialize() {
         assert_eq!(
             serde_json::to_string(&BlockTemplateRequest::default()).unwrap(),
-            r#"{"mode":null,"capabilities":null,"rules":null}"#
+            r#"{"mode":null,"capabilities":null,"rules":null,"longpollid":null}"#
         );
         assert_eq!(
             serde_json::to_string(&BlockTemplateRequest {
                 mode: Some(BlockTemplateRequestMode::Template),
                 capabilities: Some(vec!["a".to_owned()].into_iter().collect()),
                 rules: Some(vec!["b".to_owned()].into_iter().collect()),
+                longpollid: None,
             })
             .unwrap(),
-            r#"{"mode":"template","capabilities":["a"],"rules":["b"]}"#
+            r#"{"mode":"template","capabilities":["a"],"rules":["b"],"longpollid":null}"#
         );
     }
 
@@ -87,6 +91,7 @@ mod tests {
                 mode: None,
                 capabilities: None,
                 rules: None,
+                longpollid: None,
             }
         );
         assert_eq!(
@@ -98,6 +103,21 @@ mod tests {
                 mode: Some(BlockTemplateRequestMode::Template),
                 capabilities: Some(vec!["a".to_owned()].into_iter().collect()),
                 rules: Some(vec!["b".to_owned()].into_iter().collect()),
+                longpollid: None,
+            }
+        );
+        assert_eq!(
+            serde_json::from_str::<BlockTemplateRequest>(
+                r#"{"longpollid":"0000000000000000000000000000000000000000000000000000000000000001"}"#
+            )
+            .unwrap(),
+            BlockTemplateRequest {
+                longpollid: Some(
+                    "0000000000000000000000000000000000000000000000000000000000000001"
+                        .parse()
+                        .unwrap()
+                ),
+                ..Default::default()
             }
         );
     }
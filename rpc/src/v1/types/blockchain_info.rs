@@ -0,0 +1,62 @@
+use super::hash::H256;
+use super::uint::U256;
+
+/// Blockchain state information
+#[derive(Debug, Default, Serialize, Deserialize, PartialEq)]
+pub struct BlockChainInfo {
+    /// Height of best block in the storage
+    pub blocks: u32,
+    /// Height of best known block header
+    pub headers: u32,
+    /// Hash of best block in the storage
+    pub bestblockhash: H256,
+    /// Difficulty of best block
+    pub difficulty: f64,
+    /// Cumulative work of the best chain. Zero if it is not computed yet
+    pub chainwork: U256,
+    /// Median time of best block
+    pub mediantime: u32,
+    /// Estimated synchronization progress in [0; 1] range
+    pub verificationprogress: f64,
+    /// Is node in initial block download mode?
+    pub initialblockdownload: bool,
+    /// Are old block bodies pruned?
+    pub pruned: bool,
+    /// Height of the highest block with pruned body. Only present if `pruned` is true
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pruneheight: Option<u32>,
+    /// Ratio of raw to stored size of compressed block bodies. Only present if some bodies are compressed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub compressionratio: Option<f64>,
+    /// Node alerts. Empty if there are no alerts
+    pub warnings: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::hash::H256;
+    use super::*;
+    use serde_json;
+
+    #[test]
+    fn blockchain_info_serialize() {
+        let info = BlockChainInfo {
+            blocks: 10,
+            headers: 20,
+            bestblockhash: H256::from(1),
+            difficulty: 1.0,
+            chainwork: U256::from(0x200),
+            mediantime: 1000,
+            verificationprogress: 0.5,
+            initialblockdownload: true,
+            pruned: false,
+            pruneheight: None,
+            compressionratio: None,
+            warnings: "".into(),
+        };
+        assert_eq!(
+            serde_json::to_string(&info).unwrap(),
+            r#"{"blocks":10,"headers":20,"bestblockhash":"0100000000000000000000000000000000000000000000000000000000000000","difficulty":1.0,"chainwork":"200","mediantime":1000,"verificationprogress":0.5,"initialblockdownload":true,"pruned":false,"warnings":""}"#
+        );
+    }
+}
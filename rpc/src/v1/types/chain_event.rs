@@ -0,0 +1,222 @@
+use super::block_cursor::{deserialize_cursor, serialize_cursor, BlockCursor, HexCursor};
+use super::bytes::Bytes;
+use super::hash::H256;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/// Kind of the canonical chain change
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone, Copy)]
+pub enum ChainEventKind {
+    /// Block has been appended to the canonical chain
+    #[serde(rename = "connected")]
+    Connected,
+    /// Block has been removed from the canonical chain
+    #[serde(rename = "disconnected")]
+    Disconnected,
+    /// Reorganization has started at the common ancestor block
+    #[serde(rename = "reorgstarted")]
+    ReorgStarted,
+    /// Reorganization has finished at the new best block
+    #[serde(rename = "reorgfinished")]
+    ReorgFinished,
+}
+
+/// Record of the chain events journal
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct ChainEvent {
+    /// Sequence number of the event
+    pub seq: u64,
+    /// Kind of the event
+    pub event: ChainEventKind,
+    /// Hash of the event block
+    pub hash: H256,
+    /// Height of the event block
+    pub height: u32,
+    /// Time in seconds since epoch (Jan 1 1970 GMT), when event has been recorded
+    pub time: u32,
+}
+
+/// Opaque position in the chain events journal, returned by `listchainevents`. Refers to the
+/// last returned event and to the best block right after it, so that the next call could detect
+/// that the best block has been reorged.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChainEventsCursor {
+    /// Sequence number of the event
+    pub seq: u64,
+    /// Best block after the event
+    pub block: BlockCursor,
+}
+
+/// Page of chain events
+#[derive(Debug, Serialize)]
+pub struct ChainEventsPage {
+    /// Events, recorded after the cursor event
+    pub events: Vec<ChainEvent>,
+    /// Cursor to pass to the next call
+    pub next: ChainEventsCursor,
+    /// True if cursor block is not in the canonical chain anymore
+    pub reorged: bool,
+    /// Cursor of the highest canonical ancestor of the reorged cursor block. Blocks above it
+    /// should be dropped
+    pub forkpoint: Option<BlockCursor>,
+}
+
+/// Position in the chain events journal, accepted by `listchainevents`
+#[derive(Debug, Deserialize, PartialEq)]
+#[serde(untagged)]
+pub enum ChainEventsPosition {
+    /// Sequence number of the last known event
+    Seq(u64),
+    /// Cursor, returned by the previous call
+    Cursor(ChainEventsCursor),
+}
+
+/// Conditions on chain events, returned by `listchainevents`. Event is returned only if it
+/// satisfies all given conditions
+#[derive(Debug, Default, Deserialize, PartialEq, Clone)]
+pub struct ChainEventsFilter {
+    /// Only connects and disconnects of blocks, mined with given public key (hex)
+    pub miner: Option<Bytes>,
+    /// Only starts of reorganizations, which have disconnected at least given number of blocks
+    pub minreorgdepth: Option<u32>,
+    /// Only connects of blocks, which are still canonical and finalized
+    pub finalized: Option<bool>,
+}
+
+/// Response of `listchainevents`
+#[derive(Debug, Serialize)]
+#[serde(untagged)]
+pub enum ChainEventsResponse {
+    /// Events, recorded after the requested sequence number
+    Events(Vec<ChainEvent>),
+    /// Page of events, following the requested cursor
+    Page(ChainEventsPage),
+}
+
+impl HexCursor for ChainEventsCursor {
+    const LEN: usize = 8 + BlockCursor::LEN;
+    const NAME: &'static str = "chain events cursor";
+
+    fn write_bytes(&self, bytes: &mut Vec<u8>) {
+        bytes.extend_from_slice(&self.seq.to_be_bytes());
+        self.block.write_bytes(bytes);
+    }
+
+    fn read_bytes(bytes: &[u8]) -> Self {
+        let mut seq = [0u8; 8];
+        seq.copy_from_slice(&bytes[..8]);
+        ChainEventsCursor {
+            seq: u64::from_be_bytes(seq),
+            block: BlockCursor::read_bytes(&bytes[8..]),
+        }
+    }
+}
+
+impl Serialize for ChainEventsCursor {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serialize_cursor(self, serializer)
+    }
+}
+
+impl<'a> Deserialize<'a> for ChainEventsCursor {
+    fn deserialize<D>(deserializer: D) -> Result<ChainEventsCursor, D::Error>
+    where
+        D: Deserializer<'a>,
+    {
+        deserialize_cursor(deserializer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::hash::H256;
+    use super::*;
+    use primitives::hash::H256 as GlobalH256;
+    use serde_json;
+
+    #[test]
+    fn chain_event_serialize() {
+        let event = ChainEvent {
+            seq: 5,
+            event: ChainEventKind::ReorgStarted,
+            hash: H256::from(1),
+            height: 10,
+            time: 1000,
+        };
+        assert_eq!(
+            serde_json::to_string(&event).unwrap(),
+            r#"{"seq":5,"event":"reorgstarted","hash":"0100000000000000000000000000000000000000000000000000000000000000","height":10,"time":1000}"#
+        );
+    }
+
+    #[test]
+    fn chain_events_cursor_roundtrip() {
+        let cursor = ChainEventsCursor {
+            seq: 3,
+            block: BlockCursor {
+                height: 258,
+                hash: GlobalH256::from(7),
+            },
+        };
+        let serialized = serde_json::to_string(&cursor).unwrap();
+        assert_eq!(
+            serialized,
+            r#""0000000000000003000001020700000000000000000000000000000000000000000000000000000000000000""#
+        );
+        assert_eq!(
+            serde_json::from_str::<ChainEventsCursor>(&serialized).unwrap(),
+            cursor
+        );
+        // block cursor is not a chain events cursor
+        assert!(serde_json::from_str::<ChainEventsCursor>(
+            r#""000001020700000000000000000000000000000000000000000000000000000000000000""#
+        )
+        .is_err());
+        assert!(serde_json::from_str::<ChainEventsCursor>(r#""zz""#).is_err());
+    }
+
+    #[test]
+    fn chain_events_position_deserialize() {
+        assert_eq!(
+            serde_json::from_str::<ChainEventsPosition>("7").unwrap(),
+            ChainEventsPosition::Seq(7)
+        );
+        assert_eq!(
+            serde_json::from_str::<ChainEventsPosition>(
+                r#""0000000000000003000001020700000000000000000000000000000000000000000000000000000000000000""#
+            )
+            .unwrap(),
+            ChainEventsPosition::Cursor(ChainEventsCursor {
+                seq: 3,
+                block: BlockCursor {
+                    height: 258,
+                    hash: GlobalH256::from(7),
+                },
+            })
+        );
+        assert!(serde_json::from_str::<ChainEventsPosition>(r#""zz""#).is_err());
+        assert!(serde_json::from_str::<ChainEventsPosition>("-1").is_err());
+    }
+
+    #[test]
+    fn chain_events_filter_deserialize() {
+        assert_eq!(
+            serde_json::from_str::<ChainEventsFilter>("{}").unwrap(),
+            ChainEventsFilter::default()
+        );
+        assert_eq!(
+            serde_json::from_str::<ChainEventsFilter>(
+                r#"{"miner":"0102","minreorgdepth":3,"finalized":true}"#
+            )
+            .unwrap(),
+            ChainEventsFilter {
+                miner: Some(Bytes::new(vec![1, 2])),
+                minreorgdepth: Some(3),
+                finalized: Some(true),
+            }
+        );
+        assert!(serde_json::from_str::<ChainEventsFilter>(r#"{"miner":"zz"}"#).is_err());
+    }
+}
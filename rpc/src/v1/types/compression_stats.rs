@@ -0,0 +1,60 @@
+use p2p;
+
+/// Outcomes of messages compression for single connection
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct PeerCompressionStats {
+    /// Peer id
+    pub id: usize,
+    /// Peer address
+    pub addr: String,
+    /// Negotiated compression: none or snappy
+    pub compression: String,
+    /// Total number of bytes sent to the peer
+    pub bytessent: u64,
+    /// Total number of bytes received from the peer
+    pub bytesrecv: u64,
+    /// Number of bytes, saved by compression of sent messages
+    pub bytessentsaved: u64,
+    /// Number of bytes, saved by compression of received messages
+    pub bytesrecvsaved: u64,
+}
+
+impl From<p2p::PeerCompressionStats> for PeerCompressionStats {
+    fn from(stats: p2p::PeerCompressionStats) -> Self {
+        PeerCompressionStats {
+            id: stats.id,
+            addr: stats.address.to_string(),
+            compression: match stats.compression {
+                p2p::Compression::None => "none".into(),
+                p2p::Compression::Snappy => "snappy".into(),
+            },
+            bytessent: stats.bytes_sent,
+            bytesrecv: stats.bytes_received,
+            bytessentsaved: stats.saved_sent,
+            bytesrecvsaved: stats.saved_received,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json;
+
+    #[test]
+    fn peer_compression_stats_serialize() {
+        let stats = PeerCompressionStats {
+            id: 1,
+            addr: "127.0.0.1:8333".into(),
+            compression: "snappy".into(),
+            bytessent: 100,
+            bytesrecv: 200,
+            bytessentsaved: 50,
+            bytesrecvsaved: 0,
+        };
+        assert_eq!(
+            serde_json::to_string(&stats).unwrap(),
+            r#"{"id":1,"addr":"127.0.0.1:8333","compression":"snappy","bytessent":100,"bytesrecv":200,"bytessentsaved":50,"bytesrecvsaved":0}"#
+        );
+    }
+}
@@ -0,0 +1,76 @@
+use p2p;
+use serde::{Serialize, Serializer};
+
+/// Response to getconnectioncount RPC request
+#[derive(Debug)]
+pub enum GetConnectionCountResponse {
+    /// When asking for short response
+    Total(usize),
+    /// When asking for verbose response
+    Verbose(ConnectionCount),
+}
+
+/// Numbers of open connections by direction
+#[derive(Debug, Default, Serialize, Deserialize, PartialEq)]
+pub struct ConnectionCount {
+    /// Total number of connections
+    pub total: usize,
+    /// Number of inbound connections
+    pub inbound: usize,
+    /// Number of outbound connections, including block-relay-only connections
+    pub outbound: usize,
+    /// Number of outbound block-relay-only connections
+    pub blockrelay: usize,
+    /// Number of connections with whitelisted peers
+    pub whitelisted: usize,
+}
+
+impl From<p2p::ConnectionCount> for ConnectionCount {
+    fn from(count: p2p::ConnectionCount) -> Self {
+        ConnectionCount {
+            total: count.total,
+            inbound: count.inbound,
+            outbound: count.outbound,
+            blockrelay: count.block_relay,
+            whitelisted: count.whitelisted,
+        }
+    }
+}
+
+impl Serialize for GetConnectionCountResponse {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match *self {
+            GetConnectionCountResponse::Total(ref total) => total.serialize(serializer),
+            GetConnectionCountResponse::Verbose(ref count) => count.serialize(serializer),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json;
+
+    #[test]
+    fn connection_count_serialize() {
+        assert_eq!(
+            serde_json::to_string(&GetConnectionCountResponse::Total(5)).unwrap(),
+            "5"
+        );
+
+        let count = ConnectionCount {
+            total: 5,
+            inbound: 2,
+            outbound: 3,
+            blockrelay: 1,
+            whitelisted: 1,
+        };
+        assert_eq!(
+            serde_json::to_string(&GetConnectionCountResponse::Verbose(count)).unwrap(),
+            r#"{"total":5,"inbound":2,"outbound":3,"blockrelay":1,"whitelisted":1}"#
+        );
+    }
+}
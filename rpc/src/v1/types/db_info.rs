@@ -0,0 +1,48 @@
+/// Size of the database column
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct DbColumnInfo {
+    /// Column name
+    pub name: String,
+    /// Number of keys in the column
+    pub keys: u64,
+    /// Total size of keys and values in the column, in bytes
+    pub size: u64,
+}
+
+/// Response to getdbinfo RPC request
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct DbInfo {
+    /// Total size of database files, in bytes. Null if database isn't stored on disk
+    pub disksize: Option<u64>,
+    /// Total size of keys and values in all columns, in bytes
+    pub datasize: u64,
+    /// Estimated size of database files, which isn't occupied by the live data, in bytes.
+    /// Null if database isn't stored on disk
+    pub overhead: Option<u64>,
+    /// Sizes of database columns
+    pub columns: Vec<DbColumnInfo>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json;
+
+    #[test]
+    fn db_info_serialize() {
+        let info = DbInfo {
+            disksize: Some(1000),
+            datasize: 900,
+            overhead: Some(100),
+            columns: vec![DbColumnInfo {
+                name: "blocks".to_owned(),
+                keys: 2,
+                size: 900,
+            }],
+        };
+        assert_eq!(
+            serde_json::to_string(&info).unwrap(),
+            r#"{"disksize":1000,"datasize":900,"overhead":100,"columns":[{"name":"blocks","keys":2,"size":900}]}"#
+        );
+    }
+}
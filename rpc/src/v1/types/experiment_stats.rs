@@ -0,0 +1,74 @@
+use p2p;
+
+/// Outcomes of protocol experiment for a group of connections
+#[derive(Debug, Default, Serialize, Deserialize, PartialEq)]
+pub struct ExperimentGroupStats {
+    /// Number of connections in the group
+    pub peers: usize,
+    /// Total number of bytes sent to peers of the group
+    pub bytessent: u64,
+    /// Total number of bytes received from peers of the group
+    pub bytesrecv: u64,
+    /// Average ping of peers in the group, in seconds
+    pub pingtime: f64,
+}
+
+/// Outcomes of protocol experiment
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct ExperimentStats {
+    /// Experiment name
+    pub name: String,
+    /// Percentage of connections, for which experiment is enabled
+    pub percent: u8,
+    /// Stats of connections with experiment enabled
+    pub enabled: ExperimentGroupStats,
+    /// Stats of connections with experiment disabled
+    pub control: ExperimentGroupStats,
+}
+
+impl From<p2p::ExperimentGroupStats> for ExperimentGroupStats {
+    fn from(stats: p2p::ExperimentGroupStats) -> Self {
+        ExperimentGroupStats {
+            peers: stats.peers,
+            bytessent: stats.bytes_sent,
+            bytesrecv: stats.bytes_received,
+            pingtime: stats.avg_ping,
+        }
+    }
+}
+
+impl From<p2p::ExperimentStats> for ExperimentStats {
+    fn from(stats: p2p::ExperimentStats) -> Self {
+        ExperimentStats {
+            name: stats.name.into(),
+            percent: stats.percent,
+            enabled: stats.enabled.into(),
+            control: stats.disabled.into(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json;
+
+    #[test]
+    fn experiment_stats_serialize() {
+        let stats = ExperimentStats {
+            name: "headers_announcement".into(),
+            percent: 10,
+            enabled: ExperimentGroupStats {
+                peers: 1,
+                bytessent: 100,
+                bytesrecv: 200,
+                pingtime: 0.5,
+            },
+            control: ExperimentGroupStats::default(),
+        };
+        assert_eq!(
+            serde_json::to_string(&stats).unwrap(),
+            r#"{"name":"headers_announcement","percent":10,"enabled":{"peers":1,"bytessent":100,"bytesrecv":200,"pingtime":0.5},"control":{"peers":0,"bytessent":0,"bytesrecv":0,"pingtime":0.0}}"#
+        );
+    }
+}
@@ -0,0 +1,29 @@
+use super::hash::H256;
+
+/// Latest canon block, which could not be reorganized anymore
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct FinalizedBlock {
+    /// Hash of the finalized block
+    pub hash: H256,
+    /// Height of the finalized block
+    pub height: u32,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::hash::H256;
+    use super::*;
+    use serde_json;
+
+    #[test]
+    fn finalized_block_serialize() {
+        let block = FinalizedBlock {
+            hash: H256::from(1),
+            height: 10,
+        };
+        assert_eq!(
+            serde_json::to_string(&block).unwrap(),
+            r#"{"hash":"0100000000000000000000000000000000000000000000000000000000000000","height":10}"#
+        );
+    }
+}
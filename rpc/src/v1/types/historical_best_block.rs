@@ -0,0 +1,39 @@
+use super::hash::H256;
+
+/// Best block of the canon chain, as it was in the past
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct HistoricalBestBlock {
+    /// Sequence number of the chain event, after which this block has been the best block
+    pub seq: u64,
+    /// Time in seconds since epoch (Jan 1 1970 GMT), when chain event has been recorded
+    pub eventtime: u32,
+    /// Hash of the best block
+    pub hash: H256,
+    /// Height of the best block
+    pub height: u32,
+    /// Randomness of the best block as hex
+    #[serde(rename = "randomnessHex")]
+    pub randomness_hex: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::hash::H256;
+    use super::*;
+    use serde_json;
+
+    #[test]
+    fn historical_best_block_serialize() {
+        let block = HistoricalBestBlock {
+            seq: 5,
+            eventtime: 1000,
+            hash: H256::from(1),
+            height: 10,
+            randomness_hex: "7788".to_owned(),
+        };
+        assert_eq!(
+            serde_json::to_string(&block).unwrap(),
+            r#"{"seq":5,"eventtime":1000,"hash":"0100000000000000000000000000000000000000000000000000000000000000","height":10,"randomnessHex":"7788"}"#
+        );
+    }
+}
@@ -0,0 +1,50 @@
+use sync;
+
+/// Usage of the memory budget, shared by synchronization components. All sizes are in bytes
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct MemoryInfo {
+    /// Overall budget
+    pub limit: usize,
+    /// Bytes, used by all components
+    pub used: usize,
+    /// Blocks, waiting for their parents
+    pub orphanblocks: usize,
+    /// Blocks, waiting for verification
+    pub verificationqueue: usize,
+    /// Peers requests, waiting to be served
+    pub serverqueue: usize,
+}
+
+impl From<sync::MemoryUsage> for MemoryInfo {
+    fn from(usage: sync::MemoryUsage) -> Self {
+        MemoryInfo {
+            limit: usage.limit,
+            used: usage.used(),
+            orphanblocks: usage.orphan_blocks,
+            verificationqueue: usage.verification_queue,
+            serverqueue: usage.server_queue,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json;
+    use sync;
+
+    #[test]
+    fn memory_info_serialize() {
+        let info: MemoryInfo = sync::MemoryUsage {
+            limit: 1000,
+            orphan_blocks: 100,
+            verification_queue: 200,
+            server_queue: 10,
+        }
+        .into();
+        assert_eq!(
+            serde_json::to_string(&info).unwrap(),
+            r#"{"limit":1000,"used":310,"orphanblocks":100,"verificationqueue":200,"serverqueue":10}"#
+        );
+    }
+}
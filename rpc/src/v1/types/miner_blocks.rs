@@ -0,0 +1,26 @@
+/// Canon blocks in the range of heights, mined with given public key
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct MinerBlocks {
+    /// Number of canon blocks in the range
+    pub total: u32,
+    /// Heights of blocks, mined with the key
+    pub heights: Vec<u32>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json;
+
+    #[test]
+    fn miner_blocks_serialize() {
+        let blocks = MinerBlocks {
+            total: 10,
+            heights: vec![2, 5],
+        };
+        assert_eq!(
+            serde_json::to_string(&blocks).unwrap(),
+            r#"{"total":10,"heights":[2,5]}"#
+        );
+    }
+}
@@ -0,0 +1,55 @@
+use miner;
+
+/// State and progress of the local miner
+#[derive(Debug, Serialize, Deserialize, PartialEq, Default)]
+pub struct MiningInfo {
+    /// True if the local miner is running, is not paused and has at least one thread
+    pub active: bool,
+    /// Number of mining threads
+    pub threads: usize,
+    /// Iterations per second, achieved by the local miner within the last minute
+    pub iterationspersecond: u64,
+    /// Height of the last block template, the local miner has been mining on
+    pub templateheight: Option<u32>,
+    /// Number of blocks, found by the local miner
+    pub blocksfound: u64,
+}
+
+impl From<miner::MiningStats> for MiningInfo {
+    fn from(stats: miner::MiningStats) -> Self {
+        MiningInfo {
+            active: !stats.paused && stats.threads != 0,
+            threads: stats.threads,
+            iterationspersecond: stats.iterations_per_second,
+            templateheight: stats.template_height,
+            blocksfound: stats.blocks_found,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use miner;
+    use serde_json;
+
+    #[test]
+    fn mining_info_serialize() {
+        let info: MiningInfo = miner::MiningStats {
+            threads: 2,
+            paused: false,
+            iterations_per_second: 233_868,
+            template_height: Some(100),
+            blocks_found: 3,
+        }
+        .into();
+        assert_eq!(
+            serde_json::to_string(&info).unwrap(),
+            r#"{"active":true,"threads":2,"iterationspersecond":233868,"templateheight":100,"blocksfound":3}"#
+        );
+        assert_eq!(
+            serde_json::to_string(&MiningInfo::default()).unwrap(),
+            r#"{"active":false,"threads":0,"iterationspersecond":0,"templateheight":null,"blocksfound":0}"#
+        );
+    }
+}
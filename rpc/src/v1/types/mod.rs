@@ -1,17 +1,72 @@
 mod block;
+mod block_announcement;
+mod block_cursor;
+mod block_header;
+mod block_height_or_hash;
+mod block_propagation;
+mod block_stats;
 mod block_template;
 mod block_template_request;
+mod blockchain_info;
 mod bytes;
+mod chain_event;
+mod compression_stats;
+mod connection_count;
+mod db_info;
+mod experiment_stats;
+mod finalized_block;
 mod get_block_response;
 mod hash;
+mod historical_best_block;
+mod memory_info;
+mod miner_blocks;
+mod mining_info;
+mod network_info;
 mod nodes;
+mod orphan_blocks;
+mod peer_info;
+mod peer_policy_stats;
+mod peer_version_stats;
+mod rpc_info;
+mod server_log_stats;
+mod stale_block;
 mod uint;
+mod verify_chain;
 
 pub use self::block::RawBlock;
+pub use self::block_announcement::{BlockAnnouncement, BlockAnnouncer};
+pub use self::block_cursor::{BlockCursor, BlockHeadersPage};
+pub use self::block_header::{GetBlockHeaderResponse, RawBlockHeader, VerboseBlockHeader};
+pub use self::block_height_or_hash::BlockHeightOrHash;
+pub use self::block_propagation::BlockPropagation;
+pub use self::block_stats::BlockStats;
 pub use self::block_template::BlockTemplate;
 pub use self::block_template_request::{BlockTemplateRequest, BlockTemplateRequestMode};
+pub use self::blockchain_info::BlockChainInfo;
 pub use self::bytes::Bytes;
+pub use self::chain_event::{
+    ChainEvent, ChainEventKind, ChainEventsCursor, ChainEventsFilter, ChainEventsPage,
+    ChainEventsPosition, ChainEventsResponse,
+};
+pub use self::compression_stats::PeerCompressionStats;
+pub use self::connection_count::{ConnectionCount, GetConnectionCountResponse};
+pub use self::db_info::{DbColumnInfo, DbInfo};
+pub use self::experiment_stats::{ExperimentGroupStats, ExperimentStats};
+pub use self::finalized_block::FinalizedBlock;
 pub use self::get_block_response::{GetBlockResponse, VerboseBlock};
 pub use self::hash::{H160, H256};
+pub use self::historical_best_block::HistoricalBestBlock;
+pub use self::memory_info::MemoryInfo;
+pub use self::miner_blocks::MinerBlocks;
+pub use self::mining_info::MiningInfo;
+pub use self::network_info::NetworkInfo;
 pub use self::nodes::{AddNodeOperation, NodeInfo};
+pub use self::orphan_blocks::{GetOrphanBlocksResponse, OrphanBlock};
+pub use self::peer_info::PeerInfo;
+pub use self::peer_policy_stats::PeerPolicyStats;
+pub use self::peer_version_stats::PeerVersionStats;
+pub use self::rpc_info::{RpcInfo, RpcMethodInfo};
+pub use self::server_log_stats::ServerLogStats;
+pub use self::stale_block::StaleBlock;
 pub use self::uint::U256;
+pub use self::verify_chain::{VerifyChainFailure, VerifyChainResult};
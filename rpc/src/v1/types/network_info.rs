@@ -0,0 +1,61 @@
+use super::connection_count::ConnectionCount;
+
+/// Response to getnetworkinfo RPC request
+#[derive(Debug, Default, Serialize, Deserialize, PartialEq)]
+pub struct NetworkInfo {
+    /// Protocol version of the node
+    pub protocolversion: u32,
+    /// Peers with lower protocol version are disconnected after handshake
+    pub minpeerversion: u32,
+    /// User agent of the node
+    pub subversion: String,
+    /// Services, offered by the node (hex-encoded)
+    pub localservices: String,
+    /// True if peers are asked to relay everything, not only blocks
+    pub localrelay: bool,
+    /// True if the node never serves nor relays anything
+    pub watchonly: bool,
+    /// True if the node is synchronizing with peers
+    pub synchronizing: bool,
+    /// Adjustment of the local time, computed from peers time offsets (in seconds)
+    pub timeoffset: i64,
+    /// Numbers of open connections by direction
+    pub connections: ConnectionCount,
+    /// Addresses, the node is listening on
+    pub localaddresses: Vec<String>,
+    /// SOCKS5 proxy, all outbound connections are made through
+    pub proxy: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json;
+
+    #[test]
+    fn network_info_serialize() {
+        let info = NetworkInfo {
+            protocolversion: 70014,
+            minpeerversion: 70001,
+            subversion: "/randchain:0.1.0/".to_owned(),
+            localservices: "0000000000000001".to_owned(),
+            localrelay: true,
+            watchonly: false,
+            synchronizing: true,
+            timeoffset: -2,
+            connections: ConnectionCount {
+                total: 3,
+                inbound: 1,
+                outbound: 2,
+                blockrelay: 1,
+                whitelisted: 0,
+            },
+            localaddresses: vec!["0.0.0.0:8333".to_owned()],
+            proxy: None,
+        };
+        assert_eq!(
+            serde_json::to_string(&info).unwrap(),
+            r#"{"protocolversion":70014,"minpeerversion":70001,"subversion":"/randchain:0.1.0/","localservices":"0000000000000001","localrelay":true,"watchonly":false,"synchronizing":true,"timeoffset":-2,"connections":{"total":3,"inbound":1,"outbound":2,"blockrelay":1,"whitelisted":0},"localaddresses":["0.0.0.0:8333"],"proxy":null}"#
+        );
+    }
+}
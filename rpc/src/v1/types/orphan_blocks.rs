@@ -0,0 +1,60 @@
+use super::hash::H256;
+use serde::{Serialize, Serializer};
+
+/// Response to getorphanblocks RPC request
+#[derive(Debug, PartialEq)]
+pub enum GetOrphanBlocksResponse {
+    /// When asking for short response: hashes of blocks in the pool
+    Raw(Vec<H256>),
+    /// When asking for verbose response
+    Verbose(Vec<OrphanBlock>),
+}
+
+/// Block, held in node memory until its parent is received
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct OrphanBlock {
+    /// Block hash
+    pub hash: H256,
+    /// Hash of the parent block
+    pub parent: H256,
+    /// Block size
+    pub size: u32,
+    /// Time in seconds since epoch (Jan 1 1970 GMT), when block has been received
+    pub time: u32,
+}
+
+impl Serialize for GetOrphanBlocksResponse {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match *self {
+            GetOrphanBlocksResponse::Raw(ref hashes) => hashes.serialize(serializer),
+            GetOrphanBlocksResponse::Verbose(ref entries) => entries.serialize(serializer),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::hash::H256;
+    use super::*;
+    use serde_json;
+
+    #[test]
+    fn orphan_blocks_response_serialize() {
+        let response = vec![
+            GetOrphanBlocksResponse::Raw(vec![H256::from(1)]),
+            GetOrphanBlocksResponse::Verbose(vec![OrphanBlock {
+                hash: H256::from(1),
+                parent: H256::from(2),
+                size: 100,
+                time: 1000,
+            }]),
+        ];
+        assert_eq!(
+            serde_json::to_string(&response).unwrap(),
+            r#"[["0100000000000000000000000000000000000000000000000000000000000000"],[{"hash":"0100000000000000000000000000000000000000000000000000000000000000","parent":"0200000000000000000000000000000000000000000000000000000000000000","size":100,"time":1000}]]"#
+        );
+    }
+}
@@ -0,0 +1,47 @@
+/// Connected peer, as reported by getpeerinfo RPC request
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct PeerInfo {
+    /// Peer index
+    pub id: usize,
+    /// Peer address
+    pub addr: String,
+    /// True if peer has connected to the node
+    pub inbound: bool,
+    /// True if only blocks are relayed over this connection
+    pub blockrelayonly: bool,
+    /// True if peer is never banned or evicted
+    pub whitelisted: bool,
+    /// True if peer matches connection policy deprioritize filter, so it is evicted first
+    pub deprioritized: bool,
+    /// Protocol version of the peer
+    pub version: u32,
+    /// User agent of the peer
+    pub subver: String,
+    /// Misbehavior score of the peer address. Score isn't reset when peer reconnects
+    pub misbehaviorscore: u32,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json;
+
+    #[test]
+    fn peer_info_serialize() {
+        let info = PeerInfo {
+            id: 1,
+            addr: "10.0.0.1:8333".to_owned(),
+            inbound: true,
+            blockrelayonly: false,
+            whitelisted: false,
+            deprioritized: true,
+            version: 70014,
+            subver: "/randchain:0.1.0/".to_owned(),
+            misbehaviorscore: 20,
+        };
+        assert_eq!(
+            serde_json::to_string(&info).unwrap(),
+            r#"{"id":1,"addr":"10.0.0.1:8333","inbound":true,"blockrelayonly":false,"whitelisted":false,"deprioritized":true,"version":70014,"subver":"/randchain:0.1.0/","misbehaviorscore":20}"#
+        );
+    }
+}
@@ -0,0 +1,41 @@
+use p2p;
+
+/// Number of peers, matched by connection policy filter
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct PeerPolicyStats {
+    /// Filter, e.g. `agent:/randchaind:0.1.`
+    pub filter: String,
+    /// Number of peers, disconnected after handshake because of this filter
+    pub rejected: u64,
+    /// Number of peers, evicted first because of this filter
+    pub deprioritized: u64,
+}
+
+impl From<p2p::PeerPolicyStats> for PeerPolicyStats {
+    fn from(stats: p2p::PeerPolicyStats) -> Self {
+        PeerPolicyStats {
+            filter: stats.filter,
+            rejected: stats.rejected,
+            deprioritized: stats.deprioritized,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json;
+
+    #[test]
+    fn peer_policy_stats_serialize() {
+        let stats = PeerPolicyStats {
+            filter: "version:70001-70002".to_owned(),
+            rejected: 3,
+            deprioritized: 0,
+        };
+        assert_eq!(
+            serde_json::to_string(&stats).unwrap(),
+            r#"{"filter":"version:70001-70002","rejected":3,"deprioritized":0}"#
+        );
+    }
+}
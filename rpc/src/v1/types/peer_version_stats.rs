@@ -0,0 +1,41 @@
+use p2p;
+
+/// Number of peers with given protocol version
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct PeerVersionStats {
+    /// Protocol version
+    pub version: u32,
+    /// Number of connected peers with this version
+    pub connected: usize,
+    /// Number of peers, disconnected because this version is below minimum
+    pub rejected: u64,
+}
+
+impl From<p2p::PeerVersionStats> for PeerVersionStats {
+    fn from(stats: p2p::PeerVersionStats) -> Self {
+        PeerVersionStats {
+            version: stats.version,
+            connected: stats.connected,
+            rejected: stats.rejected,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json;
+
+    #[test]
+    fn peer_version_stats_serialize() {
+        let stats = PeerVersionStats {
+            version: 70_001,
+            connected: 2,
+            rejected: 5,
+        };
+        assert_eq!(
+            serde_json::to_string(&stats).unwrap(),
+            r#"{"version":70001,"connected":2,"rejected":5}"#
+        );
+    }
+}
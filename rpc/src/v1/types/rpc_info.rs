@@ -0,0 +1,69 @@
+use stats;
+
+/// Statistics of RPC method calls, since node start
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct RpcMethodInfo {
+    /// Method name
+    pub method: String,
+    /// Number of calls
+    pub calls: u64,
+    /// Number of failed calls
+    pub errors: u64,
+    /// Total time of calls, in microseconds
+    pub totaltimeus: u64,
+    /// Time of the slowest call, in microseconds
+    pub maxtimeus: u64,
+    /// Number of calls by latency, bucket bounds are in `RpcInfo::latencybucketsms`
+    pub latencyhistogram: Vec<u64>,
+}
+
+/// RPC server statistics
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct RpcInfo {
+    /// Calls, slower than this threshold, are logged. None if slow calls are not logged
+    pub slowcallthresholdms: Option<u64>,
+    /// Upper bounds of latency histogram buckets, in milliseconds. Last bucket counts slower calls
+    pub latencybucketsms: Vec<u64>,
+    /// Statistics by method, ordered by method name
+    pub methods: Vec<RpcMethodInfo>,
+}
+
+impl<'a> From<&'a stats::RpcStats> for RpcInfo {
+    fn from(stats: &'a stats::RpcStats) -> Self {
+        RpcInfo {
+            slowcallthresholdms: stats
+                .slow_call_threshold()
+                .map(|threshold| threshold.as_millis() as u64),
+            latencybucketsms: stats::LATENCY_BUCKETS_MS.to_vec(),
+            methods: stats
+                .methods()
+                .into_iter()
+                .map(|(method, stats)| RpcMethodInfo {
+                    method: method,
+                    calls: stats.calls,
+                    errors: stats.errors,
+                    totaltimeus: stats.total_time_us,
+                    maxtimeus: stats.max_time_us,
+                    latencyhistogram: stats.latency_histogram.to_vec(),
+                })
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json;
+    use std::time::Duration;
+
+    #[test]
+    fn rpc_info_serialize() {
+        let stats = stats::RpcStats::new(Some(Duration::from_secs(2)));
+        stats.record("getblock", Duration::from_millis(5), false);
+        assert_eq!(
+            serde_json::to_string(&RpcInfo::from(&stats)).unwrap(),
+            r#"{"slowcallthresholdms":2000,"latencybucketsms":[1,10,100,1000,10000],"methods":[{"method":"getblock","calls":1,"errors":0,"totaltimeus":5000,"maxtimeus":5000,"latencyhistogram":[0,1,0,0,0,0]}]}"#
+        );
+    }
+}
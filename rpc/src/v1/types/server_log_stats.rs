@@ -0,0 +1,65 @@
+use sync;
+
+/// Outcomes of serving synchronization requests of peers, since node start
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct ServerLogStats {
+    /// 1 of `sampling` served requests is fully logged. 0 if serving is not logged
+    pub sampling: u32,
+    /// Number of executed server tasks
+    pub tasks: usize,
+    /// Number of server tasks, which have been fully logged
+    pub loggedtasks: usize,
+    /// Number of blocks, sent in response to getdata
+    pub blocksserved: usize,
+    /// Number of notfound responses
+    pub notfound: usize,
+    /// Number of getdata items, throttled by upload limit
+    pub throttled: usize,
+    /// Number of inventories, sent in response to getblocks
+    pub inventoriesserved: usize,
+    /// Number of headers messages, sent in response to getheaders
+    pub headersserved: usize,
+    /// Number of requests, ignored because there was nothing to respond with
+    pub ignored: usize,
+}
+
+impl From<sync::ServerLogStats> for ServerLogStats {
+    fn from(stats: sync::ServerLogStats) -> Self {
+        ServerLogStats {
+            sampling: stats.sampling,
+            tasks: stats.tasks,
+            loggedtasks: stats.logged_tasks,
+            blocksserved: stats.blocks_served,
+            notfound: stats.not_found,
+            throttled: stats.throttled,
+            inventoriesserved: stats.inventories_served,
+            headersserved: stats.headers_served,
+            ignored: stats.ignored,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json;
+
+    #[test]
+    fn server_log_stats_serialize() {
+        let stats = ServerLogStats {
+            sampling: 10,
+            tasks: 100,
+            loggedtasks: 10,
+            blocksserved: 50,
+            notfound: 1,
+            throttled: 2,
+            inventoriesserved: 3,
+            headersserved: 40,
+            ignored: 4,
+        };
+        assert_eq!(
+            serde_json::to_string(&stats).unwrap(),
+            r#"{"sampling":10,"tasks":100,"loggedtasks":10,"blocksserved":50,"notfound":1,"throttled":2,"inventoriesserved":3,"headersserved":40,"ignored":4}"#
+        );
+    }
+}
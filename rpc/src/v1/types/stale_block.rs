@@ -0,0 +1,35 @@
+use super::hash::H256;
+
+/// Block, which has been canonical, but has been reorged out
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct StaleBlock {
+    /// Hash of the stale block
+    pub hash: H256,
+    /// Height of the stale block
+    pub height: u32,
+    /// Hash of the block, which has replaced the stale block in the canonical chain
+    pub replacedby: H256,
+    /// Time in seconds since epoch (Jan 1 1970 GMT), when block has been reorged out
+    pub time: u32,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::hash::H256;
+    use super::*;
+    use serde_json;
+
+    #[test]
+    fn stale_block_serialize() {
+        let block = StaleBlock {
+            hash: H256::from(1),
+            height: 10,
+            replacedby: H256::from(2),
+            time: 1000,
+        };
+        assert_eq!(
+            serde_json::to_string(&block).unwrap(),
+            r#"{"hash":"0100000000000000000000000000000000000000000000000000000000000000","height":10,"replacedby":"0200000000000000000000000000000000000000000000000000000000000000","time":1000}"#
+        );
+    }
+}
@@ -0,0 +1,44 @@
+use super::hash::H256;
+
+/// Canon block, which has failed re-verification
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct VerifyChainFailure {
+    /// Height of the block
+    pub height: u32,
+    /// Hash of the block
+    pub hash: H256,
+    /// Verification error
+    pub error: String,
+}
+
+/// Response to verifychain RPC request
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct VerifyChainResult {
+    /// Number of blocks, which have passed verification
+    pub checked: u32,
+    /// The first block, which has failed verification. Null if all blocks are valid
+    pub failure: Option<VerifyChainFailure>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::hash::H256;
+    use super::*;
+    use serde_json;
+
+    #[test]
+    fn verify_chain_result_serialize() {
+        let result = VerifyChainResult {
+            checked: 5,
+            failure: Some(VerifyChainFailure {
+                height: 10,
+                hash: H256::from(1),
+                error: "Vdf".to_owned(),
+            }),
+        };
+        assert_eq!(
+            serde_json::to_string(&result).unwrap(),
+            r#"{"checked":5,"failure":{"height":10,"hash":"0100000000000000000000000000000000000000000000000000000000000000","error":"Vdf"}}"#
+        );
+    }
+}
@@ -0,0 +1,35 @@
+use chain::Block;
+use hash::H256;
+use std::sync::Arc;
+use Error;
+
+/// Destructive operation, which is about to remove blocks data from the canon chain.
+#[derive(Debug, Clone, Copy, PartialEq, Display)]
+pub enum BackupReason {
+    /// Block bodies are about to be pruned
+    #[display(fmt = "prune")]
+    Prune,
+    /// Canon blocks are about to be disconnected by the deep reorganization
+    #[display(fmt = "reorg")]
+    Reorg,
+}
+
+/// Hook, which copies blocks data to the rollback area before it is removed
+/// or disconnected from the canon chain.
+pub trait BackupHook: Send + Sync {
+    /// Copies given blocks. Blocks are ordered from oldest to newest
+    fn backup_blocks(&self, reason: BackupReason, blocks: &[Block]) -> Result<(), Error>;
+}
+
+pub type BackupHookRef = Arc<dyn BackupHook>;
+
+/// Last reorganization of the canon chain.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Reorg {
+    /// Number of the common ancestor of the old and new chains
+    pub ancestor: u32,
+    /// Hashes of the disconnected blocks. Ordered from oldest to newest
+    pub disconnected: Vec<H256>,
+    /// Hashes of the connected blocks. Ordered from oldest to newest
+    pub connected: Vec<H256>,
+}
@@ -38,6 +38,15 @@ impl fmt::Debug for SideChainOrigin {
     }
 }
 
+/// Position of the known block relative to the canonical chain.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ChainMembership {
+    /// block is in the canonical chain
+    CanonChain { block_number: u32 },
+    /// block is in the side chain, forking from the canonical block with given number
+    SideChain { fork_block_number: u32 },
+}
+
 #[derive(Debug)]
 pub enum BlockOrigin {
     KnownBlock,
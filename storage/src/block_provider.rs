@@ -1,7 +1,9 @@
 use bytes::Bytes;
 use chain::{IndexedBlock, IndexedBlockHeader};
 use hash::H256;
-use BlockRef;
+use primitives::bigint::U256;
+use std::ops::Range;
+use {BlockRangeIterator, BlockRef, ChainMembership};
 
 pub trait BlockHeaderProvider {
     /// resolves header bytes by block reference (number/hash)
@@ -21,8 +23,47 @@ pub trait BlockProvider: BlockHeaderProvider {
     /// resolves deserialized block body by block reference (number/hash)
     fn block(&self, block_ref: BlockRef) -> Option<IndexedBlock>;
 
+    /// streams canonical blocks with numbers from given range, in order.
+    /// Blocks with pruned bodies are yielded with empty proof
+    fn block_iter(&self, range: Range<u32>) -> BlockRangeIterator;
+
     /// returns true if store contains given block
     fn contains_block(&self, block_ref: BlockRef) -> bool {
         self.block_header_bytes(block_ref).is_some()
     }
+
+    /// resolves position of the known block relative to the canonical chain.
+    /// Side chain is walked back until its newest canonical ancestor is found
+    fn chain_membership(&self, hash: &H256) -> Option<ChainMembership> {
+        if let Some(block_number) = self.block_number(hash) {
+            return Some(ChainMembership::CanonChain {
+                block_number: block_number,
+            });
+        }
+
+        let mut hash = hash.clone();
+        loop {
+            let parent_hash = self.block_header(hash.into())?.raw.previous_header_hash;
+            if let Some(block_number) = self.block_number(&parent_hash) {
+                return Some(ChainMembership::SideChain {
+                    fork_block_number: block_number,
+                });
+            }
+            hash = parent_hash;
+        }
+    }
+
+    /// resolves hashes of all known (canonical and side) children of the block, in insertion order
+    fn block_children(&self, hash: &H256) -> Vec<H256>;
+
+    /// resolves cumulative work of the chain, ending with given block.
+    /// None if block is unknown or it has been stored before work has been tracked
+    fn chain_work(&self, hash: &H256) -> Option<U256>;
+
+    /// resolves hash of the canonical child of the canonical block. Canonical index is used,
+    /// so that children of blocks, stored before children have been indexed, are resolved too
+    fn next_block_hash(&self, hash: &H256) -> Option<H256> {
+        let block_number = self.block_number(hash)?;
+        self.block_hash(block_number + 1)
+    }
 }
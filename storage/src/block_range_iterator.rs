@@ -0,0 +1,64 @@
+use chain::IndexedBlock;
+use std::collections::VecDeque;
+use std::ops::Range;
+use {BlockProvider, BlockRef};
+
+/// Number of blocks, which are read from the storage at once.
+const READ_AHEAD_BLOCKS: u32 = 16;
+
+/// Streams canonical blocks by height, reading them from the storage in batches.
+/// Blocks with pruned bodies are yielded with empty proof.
+/// Iteration stops at the end of range or at the first height without canonical block.
+pub struct BlockRangeIterator<'a> {
+    range: Range<u32>,
+    buffer: VecDeque<IndexedBlock>,
+    blocks: &'a dyn BlockProvider,
+}
+
+impl<'a> BlockRangeIterator<'a> {
+    pub fn new(range: Range<u32>, blocks: &'a dyn BlockProvider) -> Self {
+        BlockRangeIterator {
+            range: range,
+            buffer: VecDeque::with_capacity(READ_AHEAD_BLOCKS as usize),
+            blocks: blocks,
+        }
+    }
+
+    fn read_ahead(&mut self) {
+        let end = self
+            .range
+            .end
+            .min(self.range.start.saturating_add(READ_AHEAD_BLOCKS));
+        while self.range.start < end {
+            match self.read_block(self.range.start) {
+                Some(block) => self.buffer.push_back(block),
+                None => {
+                    // no canonical block at this height => no blocks above
+                    self.range.start = self.range.end;
+                    return;
+                }
+            }
+            self.range.start += 1;
+        }
+    }
+
+    fn read_block(&self, number: u32) -> Option<IndexedBlock> {
+        let hash = self.blocks.block_hash(number)?;
+        self.blocks.block(BlockRef::Hash(hash.clone())).or_else(|| {
+            self.blocks
+                .block_header(BlockRef::Hash(hash))
+                .map(|header| IndexedBlock::new(header, Vec::new()))
+        })
+    }
+}
+
+impl<'a> Iterator for BlockRangeIterator<'a> {
+    type Item = IndexedBlock;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.buffer.is_empty() {
+            self.read_ahead();
+        }
+        self.buffer.pop_front()
+    }
+}
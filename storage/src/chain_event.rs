@@ -0,0 +1,53 @@
+use hash::H256;
+use {BestBlock, Reorg};
+
+/// Kind of the canon chain change
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ChainEventKind {
+    /// Block has been appended to the canon chain
+    BlockConnected,
+    /// Best block has been removed from the canon chain
+    BlockDisconnected,
+    /// Reorganization has started. Event block is the common ancestor of the old and new chains
+    ReorgStarted,
+    /// Reorganization has finished. Event block is the new best block
+    ReorgFinished,
+}
+
+/// Record of the chain events journal
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChainEvent {
+    /// Sequence number of the event. Events are numbered consecutively, starting from 1
+    pub seq: u64,
+    /// Kind of the event
+    pub kind: ChainEventKind,
+    /// Hash of the event block
+    pub hash: H256,
+    /// Number of the event block
+    pub number: u32,
+    /// Time when event has been recorded, in seconds since epoch
+    pub time: u32,
+}
+
+/// Append-only journal of canon chain changes
+pub trait ChainEventStore {
+    /// Returns sequence number of the last recorded event, or 0 if nothing is recorded
+    fn last_chain_event_seq(&self) -> u64;
+
+    /// Returns up to `max` events with sequence numbers greater than `since_seq`, in order
+    fn chain_events(&self, since_seq: u64, max: usize) -> Vec<ChainEvent>;
+
+    /// Returns sequence number of the oldest retained event. Older events are removed from the journal
+    fn first_chain_event_seq(&self) -> u64;
+
+    /// Returns sequence number of the last event, recorded at or before given time.
+    /// Returns None if there are no retained events, recorded at or before given time
+    fn chain_event_seq_at(&self, time: u32) -> Option<u64>;
+
+    /// Returns best block of the canon chain, as it was right after the event with given
+    /// sequence number has been recorded. Returns None if the event is not retained
+    fn best_block_at_seq(&self, seq: u64) -> Option<BestBlock>;
+
+    /// Returns the last reorganization of the canon chain, if any
+    fn last_reorg(&self) -> Option<Reorg>;
+}
@@ -0,0 +1,76 @@
+use hash::H256;
+use std::cmp;
+use std::ops::Range;
+use {BestBlock, BlockProvider};
+
+/// Immutable view of the canonical chain, as it was when the snapshot has been taken.
+/// Snapshot answers consistently even if chain is reorganized concurrently: all answers
+/// are about the chain, ending with the snapshot tip, so range queries never mix blocks
+/// from different forks.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChainSnapshot {
+    /// Tip of the snapshot chain
+    tip: BestBlock,
+}
+
+impl ChainSnapshot {
+    pub fn new(tip: BestBlock) -> Self {
+        ChainSnapshot { tip: tip }
+    }
+
+    /// Tip of the snapshot chain
+    pub fn tip(&self) -> &BestBlock {
+        &self.tip
+    }
+
+    /// Hash of the snapshot chain block with given number
+    pub fn block_hash(&self, provider: &dyn BlockProvider, number: u32) -> Option<H256> {
+        self.block_hashes(provider, number..number.saturating_add(1))
+            .pop()
+    }
+
+    /// Hashes of the snapshot chain blocks with numbers from given range.
+    /// Range is truncated to the snapshot tip
+    pub fn block_hashes(&self, provider: &dyn BlockProvider, range: Range<u32>) -> Vec<H256> {
+        let end = cmp::min(range.end, self.tip.number.saturating_add(1));
+        if range.start >= end {
+            return Vec::new();
+        }
+
+        // while the snapshot tip is canonical, its ancestors are canonical too
+        let hashes: Vec<_> = (range.start..end)
+            .map(|number| provider.block_hash(number))
+            .take_while(Option::is_some)
+            .map(Option::unwrap)
+            .collect();
+        if hashes.len() == (end - range.start) as usize && self.is_canonical(provider) {
+            return hashes;
+        }
+
+        // chain has been reorganized => walk back from the snapshot tip
+        let mut hashes = Vec::with_capacity((end - range.start) as usize);
+        let mut hash = self.tip.hash.clone();
+        let mut number = self.tip.number;
+        loop {
+            if number < end {
+                hashes.push(hash.clone());
+            }
+            if number == range.start {
+                break;
+            }
+            hash = match provider.block_header(hash.into()) {
+                Some(header) => header.raw.previous_header_hash,
+                // snapshot tip is always in the storage, as blocks are never removed
+                None => return Vec::new(),
+            };
+            number -= 1;
+        }
+        hashes.reverse();
+        hashes
+    }
+
+    /// Is the snapshot tip still on the canonical chain?
+    pub fn is_canonical(&self, provider: &dyn BlockProvider) -> bool {
+        provider.block_number(&self.tip.hash) == Some(self.tip.number)
+    }
+}
@@ -0,0 +1,55 @@
+/// Blockchain database statistics
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct DbStats {
+    /// Number of blocks with compressed body
+    pub compressed_blocks: u64,
+    /// Total size of compressed bodies before compression
+    pub compressed_bodies_raw_size: u64,
+    /// Total size of compressed bodies in the database
+    pub compressed_bodies_size: u64,
+}
+
+impl DbStats {
+    /// Ratio of raw to stored size of compressed bodies. None if no body is compressed
+    pub fn compression_ratio(&self) -> Option<f64> {
+        match self.compressed_bodies_size {
+            0 => None,
+            size => Some(self.compressed_bodies_raw_size as f64 / size as f64),
+        }
+    }
+}
+
+/// Size of the database column
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct ColumnSize {
+    /// Column name
+    pub name: String,
+    /// Number of keys in the column
+    pub keys: u64,
+    /// Total size of keys and values in the column
+    pub size: u64,
+}
+
+/// Sizes of the stored data
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct DbSize {
+    /// Total size of database files on disk. None if database isn't stored on disk
+    pub disk_size: Option<u64>,
+    /// Sizes of database columns
+    pub columns: Vec<ColumnSize>,
+}
+
+impl DbSize {
+    /// Total size of keys and values in all columns
+    pub fn data_size(&self) -> u64 {
+        self.columns.iter().map(|column| column.size).sum()
+    }
+
+    /// Estimated size of database files, which isn't occupied by the live data (deleted and
+    /// overwritten values, logs, indexes). Files are compressed, so it could be underestimated.
+    /// None if database isn't stored on disk
+    pub fn overhead(&self) -> Option<u64> {
+        self.disk_size
+            .map(|disk_size| disk_size.saturating_sub(self.data_size()))
+    }
+}
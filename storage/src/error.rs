@@ -15,6 +15,15 @@ pub enum Error {
     /// Ancient fork
     #[display(fmt = "Fork is too long to proceed")]
     AncientFork,
+    /// Invalid verifier state or database is not empty
+    #[display(fmt = "Cannot import verifier state: {}", _0)]
+    CannotImportVerifierState(String),
+    /// Fork point is below the finalized block
+    #[display(fmt = "Fork reorganizes finalized block {}", _0)]
+    FinalizedFork(u32),
+    /// Block could not be the genesis block of the database
+    #[display(fmt = "Invalid genesis block: {}", _0)]
+    InvalidGenesis(String),
 }
 
 impl From<Error> for String {
@@ -10,26 +10,42 @@ extern crate network;
 extern crate primitives;
 extern crate serialization as ser;
 
+mod backup;
 mod best_block;
 mod block_ancestors;
 mod block_chain;
 mod block_iterator;
 mod block_origin;
 mod block_provider;
+mod block_range_iterator;
 mod block_ref;
+mod chain_event;
+mod chain_snapshot;
+mod db_stats;
 mod duplex_store;
 mod error;
+mod miner_index;
+mod prune_guard;
 mod store;
+mod verifier_state;
 
 pub use primitives::{bytes, hash};
 
+pub use backup::{BackupHook, BackupHookRef, BackupReason, Reorg};
 pub use best_block::BestBlock;
 pub use block_ancestors::BlockAncestors;
 pub use block_chain::{BlockChain, ForkChain, Forkable};
 pub use block_iterator::BlockIterator;
-pub use block_origin::{BlockOrigin, SideChainOrigin};
+pub use block_origin::{BlockOrigin, ChainMembership, SideChainOrigin};
 pub use block_provider::{BlockHeaderProvider, BlockProvider};
+pub use block_range_iterator::BlockRangeIterator;
 pub use block_ref::BlockRef;
+pub use chain_event::{ChainEvent, ChainEventKind, ChainEventStore};
+pub use chain_snapshot::ChainSnapshot;
+pub use db_stats::{ColumnSize, DbSize, DbStats};
 pub use duplex_store::NoopStore;
 pub use error::Error;
-pub use store::{AsSubstore, CanonStore, ConfigStore, SharedStore, Store};
+pub use miner_index::MinerIndexStore;
+pub use prune_guard::{PruneGuard, RetainedBodies};
+pub use store::{AsSubstore, CanonStore, ConfigStore, PruneStore, SharedStore, Store};
+pub use verifier_state::{Checkpoint, VerifierState, VerifierStateStore};
@@ -0,0 +1,15 @@
+use bytes::Bytes;
+use std::ops::Range;
+use Error;
+
+/// Optional secondary index of canon blocks by miner public key
+pub trait MinerIndexStore {
+    /// Starts indexing canon blocks by miner public key. If the index does not cover the whole
+    /// canon chain (e.g. it has been disabled for a while), it is rebuilt. Returns number of
+    /// indexed blocks
+    fn enable_miner_index(&self) -> Result<u32, Error>;
+
+    /// Returns numbers of canon blocks in the range, mined with given public key, in order.
+    /// Returns None if the index is disabled
+    fn blocks_by_miner(&self, miner: &Bytes, range: Range<u32>) -> Option<Vec<u32>>;
+}
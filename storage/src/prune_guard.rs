@@ -0,0 +1,54 @@
+use parking_lot::Mutex;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+/// Lowest heights of block bodies, which are still used by in-flight requests.
+/// Bodies at these heights and above must not be pruned until requests are finished.
+#[derive(Debug, Default)]
+pub struct RetainedBodies {
+    /// Retained height => number of requests, retaining bodies from this height
+    heights: Mutex<BTreeMap<u32, usize>>,
+}
+
+/// Retains block bodies from given height until dropped.
+#[derive(Debug)]
+pub struct PruneGuard {
+    bodies: Arc<RetainedBodies>,
+    from: u32,
+}
+
+impl RetainedBodies {
+    /// Retains bodies of blocks at `from` height and above until returned guard is dropped.
+    pub fn retain(bodies: &Arc<RetainedBodies>, from: u32) -> PruneGuard {
+        *bodies.heights.lock().entry(from).or_insert(0) += 1;
+        PruneGuard {
+            bodies: bodies.clone(),
+            from: from,
+        }
+    }
+
+    /// Calls `f` with the lowest retained height. No bodies can be retained until `f` returns.
+    pub fn with_lowest<F, R>(&self, f: F) -> R
+    where
+        F: FnOnce(Option<u32>) -> R,
+    {
+        let heights = self.heights.lock();
+        f(heights.keys().next().cloned())
+    }
+}
+
+impl Drop for PruneGuard {
+    fn drop(&mut self) {
+        let mut heights = self.bodies.heights.lock();
+        let is_last = match heights.get_mut(&self.from) {
+            Some(count) => {
+                *count -= 1;
+                *count == 0
+            }
+            None => false,
+        };
+        if is_last {
+            heights.remove(&self.from);
+        }
+    }
+}
@@ -1,16 +1,67 @@
-use chain::IndexedBlockHeader;
+use chain::{IndexedBlock, IndexedBlockHeader};
+use hash::H256;
 use std::sync::Arc;
-use {BestBlock, BlockChain, BlockHeaderProvider, BlockProvider, Forkable};
+use {
+    BestBlock, BlockChain, BlockHeaderProvider, BlockProvider, ChainEventStore, ChainSnapshot,
+    DbSize, DbStats, Error, Forkable, MinerIndexStore, PruneGuard, VerifierStateStore,
+};
 
-pub trait CanonStore: Store + Forkable + ConfigStore {
+pub trait CanonStore:
+    Store + Forkable + ConfigStore + PruneStore + ChainEventStore + VerifierStateStore + MinerIndexStore
+{
     fn as_store(&self) -> &dyn Store;
+
+    /// Returns database statistics
+    fn stats(&self) -> DbStats;
+
+    /// Returns sizes of the database files and columns. Columns are scanned, so it could take
+    /// a while on large databases
+    fn size(&self) -> Result<DbSize, Error>;
+
+    /// Compacts the database, reclaiming space of deleted and overwritten values.
+    /// Database stays readable and writable while it is compacted
+    fn compact(&self) -> Result<(), Error>;
+
+    /// Returns true if children of all stored blocks are indexed
+    fn block_children_indexed(&self) -> bool;
+
+    /// Indexes children of blocks, which have been stored before children have been indexed.
+    /// Returns number of updated parents
+    fn index_block_children(&self) -> Result<u32, Error>;
+
+    /// Returns true if chain work of stored blocks has been computed
+    fn chain_work_computed(&self) -> bool;
+
+    /// Computes chain work of blocks, which have been stored before it has been tracked.
+    /// Returns number of updated blocks
+    fn recompute_chain_work(&self) -> Result<u32, Error>;
+
+    /// Writes genesis block to the empty database. Returns true if the block has been written,
+    /// or false if the database already has the same genesis block
+    fn init_genesis(&self, genesis: IndexedBlock) -> Result<bool, Error>;
 }
 
 /// Configuration storage interface
 pub trait ConfigStore {
-    // TODO:
-    // + get something
-    // + set something
+    /// Returns blocks, which have been manually invalidated
+    fn invalidated_blocks(&self) -> Vec<H256>;
+
+    /// Replaces the set of manually invalidated blocks
+    fn set_invalidated_blocks(&self, blocks: &[H256]) -> Result<(), Error>;
+}
+
+/// Block bodies pruning interface
+pub trait PruneStore {
+    /// Returns number of the highest canon block with pruned body, if any
+    fn pruned_height(&self) -> Option<u32>;
+
+    /// Prunes bodies of canon blocks up to given number (inclusive), keeping their headers.
+    /// Genesis block is never pruned. Returns new pruned height
+    fn prune(&self, up_to: u32) -> Result<Option<u32>, Error>;
+
+    /// Keeps bodies of canon blocks at given number and above unpruned until returned guard is dropped.
+    /// Bodies, which are already pruned, are not restored
+    fn retain_bodies(&self, from: u32) -> PruneGuard;
 }
 
 /// Blockchain storage interface
@@ -23,6 +74,11 @@ pub trait Store: AsSubstore {
 
     /// get blockchain difficulty
     fn difficulty(&self) -> f64;
+
+    /// get immutable view of the canonical chain, ending with the current best block
+    fn chain_snapshot(&self) -> ChainSnapshot {
+        ChainSnapshot::new(self.best_block())
+    }
 }
 
 /// Allows casting Arc<Store> to reference to any substore type
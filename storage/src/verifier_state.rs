@@ -0,0 +1,52 @@
+use chain::IndexedBlockHeader;
+use hash::H256;
+use primitives::bigint::U256;
+use {BestBlock, Error};
+
+/// Hash of the canon block at given height
+#[derive(Debug, Clone, PartialEq)]
+pub struct Checkpoint {
+    /// Number of the block
+    pub number: u32,
+    /// Hash of the block
+    pub hash: H256,
+}
+
+/// Compact state of the canon chain, which is enough to verify its next blocks
+#[derive(Debug, Clone, PartialEq)]
+pub struct VerifierState {
+    /// Hashes of canon blocks below the tail, including genesis block
+    pub checkpoints: Vec<Checkpoint>,
+    /// Peaks of the merkle mountain range over hashes of canon blocks below the tail.
+    /// With hashes of the tail headers, they commit to the whole canon chain
+    pub mmr_peaks: Vec<H256>,
+    /// Number of the first tail header
+    pub tail_number: u32,
+    /// Chain work of the block before the tail. Chain work of the tail headers is computed from it
+    pub tail_parent_work: U256,
+    /// Headers of the best canon blocks, in order. Last header is the best block header.
+    /// Randomness of these blocks is kept in headers
+    pub tail: Vec<IndexedBlockHeader>,
+}
+
+impl VerifierState {
+    /// Returns best block of the state
+    pub fn best_block(&self) -> Option<BestBlock> {
+        self.tail.last().map(|header| BestBlock {
+            number: self.tail_number + self.tail.len() as u32 - 1,
+            hash: header.hash.clone(),
+        })
+    }
+}
+
+/// Export and import of the verifier state
+pub trait VerifierStateStore {
+    /// Returns headers of `tail_len` best canon blocks, hashes of every
+    /// `checkpoint_interval`-th canon block below them and peaks of the merkle mountain range
+    /// over canon blocks below them
+    fn verifier_state(&self, tail_len: u32, checkpoint_interval: u32) -> VerifierState;
+
+    /// Initializes database, which only has genesis block, with the verifier state.
+    /// Imported blocks have no bodies and blocks between checkpoints are unknown
+    fn import_verifier_state(&self, state: VerifierState) -> Result<(), Error>;
+}
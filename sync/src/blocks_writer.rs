@@ -4,11 +4,14 @@ use network::Network;
 use parking_lot::Mutex;
 use primitives::hash::H256;
 use std::collections::VecDeque;
+use std::fmt;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 use storage;
 use synchronization_chain::Chain;
 use synchronization_verifier::{
-    BlockVerificationSink, SyncVerifier, VerificationSink, VerificationTask, Verifier,
+    BlockVerificationSink, SyncVerifier, VerificationError, VerificationSink, VerificationTask,
+    Verifier,
 };
 use types::StorageRef;
 use utils::OrphanBlocksPool;
@@ -27,6 +30,38 @@ pub struct BlocksWriter {
     verifier: SyncVerifier<BlocksWriterSink>,
     /// Verification events receiver
     sink: Arc<Mutex<BlocksWriterSinkData>>,
+    /// Blocks, which parents are stored (or queued before them), waiting to be verified and committed
+    commit_queue: VecDeque<chain::IndexedBlock>,
+    /// Max time, spent on committing queued blocks by single `append_block` call. None if unlimited
+    commit_budget: Option<Duration>,
+    /// Time, spent on every phase of blocks writing
+    metrics: BlocksWriterMetrics,
+}
+
+/// Time, spent on single phase of blocks writing
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct PhaseTiming {
+    /// Number of times the phase has been executed
+    pub count: u64,
+    /// Overall time, spent on the phase
+    pub total: Duration,
+    /// Max time, spent on single execution of the phase
+    pub max: Duration,
+}
+
+/// Time, spent by blocks writer on every phase of blocks writing
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct BlocksWriterMetrics {
+    /// Moving blocks to and from orphaned blocks pool
+    pub orphans: PhaseTiming,
+    /// Verifying blocks (excluding storage writes)
+    pub verification: PhaseTiming,
+    /// Inserting verified blocks to the storage
+    pub insert: PhaseTiming,
+    /// Canonizing inserted blocks, including reorganizations
+    pub canonize: PhaseTiming,
+    /// Number of times committing has been stopped because the commit budget has been exhausted
+    pub budget_exhausted: u64,
 }
 
 /// Verification events receiver
@@ -58,24 +93,44 @@ impl BlocksWriter {
             orphaned_blocks_pool: OrphanBlocksPool::new(),
             verifier: verifier,
             sink: sink_data,
+            commit_queue: VecDeque::new(),
+            commit_budget: None,
+            metrics: BlocksWriterMetrics::default(),
         }
     }
 
+    /// Limit time, spent on committing blocks by single `append_block` call. When a block
+    /// connects many orphaned blocks, the rest of them is committed by the following calls
+    /// (or by `flush`), so that canonize operations are spread over batches of bounded latency
+    pub fn with_commit_budget(mut self, commit_budget: Option<Duration>) -> Self {
+        self.commit_budget = commit_budget;
+        self
+    }
+
+    /// Time, spent on every phase of blocks writing
+    pub fn metrics(&self) -> BlocksWriterMetrics {
+        self.metrics
+    }
+
+    /// Number of blocks, waiting to be committed
+    pub fn pending_blocks(&self) -> usize {
+        self.commit_queue.len()
+    }
+
     /// Append new block
     pub fn append_block(&mut self, block: chain::IndexedBlock) -> Result<(), Error> {
+        let started = Instant::now();
+
         // do not append block if it is already there
-        if self
-            .storage
-            .contains_block(storage::BlockRef::Hash(block.hash().clone()))
-        {
+        if self.is_known(block.hash()) {
             return Ok(());
         }
 
-        // verify && insert only if parent block is already in the storage
-        if !self.storage.contains_block(storage::BlockRef::Hash(
-            block.header.raw.previous_header_hash.clone(),
-        )) {
+        // verify && insert only if parent block is already in the storage (or is about to be)
+        let parent_hash = block.header.raw.previous_header_hash.clone();
+        if !self.is_known(&parent_hash) {
             self.orphaned_blocks_pool.insert_orphaned_block(block);
+            self.metrics.orphans.add(started.elapsed());
             // we can't hold many orphaned blocks in memory during import
             if self.orphaned_blocks_pool.len() > MAX_ORPHANED_BLOCKS {
                 return Err(Error::TooManyOrphanBlocks);
@@ -84,21 +139,110 @@ impl BlocksWriter {
         }
 
         // verify && insert block && all its orphan children
-        let mut verification_queue: VecDeque<chain::IndexedBlock> = self
+        let orphans = self
             .orphaned_blocks_pool
             .remove_blocks_for_parent(block.hash());
-        verification_queue.push_front(block);
-        while let Some(block) = verification_queue.pop_front() {
+        self.metrics.orphans.add(started.elapsed());
+        self.commit_queue.push_back(block);
+        self.commit_queue.extend(orphans);
+
+        let deadline = self.commit_budget.map(|budget| started + budget);
+        self.commit(deadline)
+    }
+
+    /// Commit all queued blocks
+    pub fn flush(&mut self) -> Result<(), Error> {
+        self.commit(None)
+    }
+
+    /// Is block stored or queued for commit?
+    fn is_known(&self, hash: &H256) -> bool {
+        self.storage
+            .contains_block(storage::BlockRef::Hash(hash.clone()))
+            || self.commit_queue.iter().any(|block| block.hash() == hash)
+    }
+
+    /// Verify && insert queued blocks until the deadline
+    fn commit(&mut self, deadline: Option<Instant>) -> Result<(), Error> {
+        while let Some(block) = self.commit_queue.pop_front() {
+            let started = Instant::now();
             self.verifier.verify_block(block);
-            if let Some(err) = self.sink.lock().error() {
+            let elapsed = started.elapsed();
+
+            let mut sink = self.sink.lock();
+            let write_timings = sink.chain.take_write_timings();
+            let writes = write_timings.insert + write_timings.canonize;
+            self.metrics
+                .verification
+                .add(elapsed.checked_sub(writes).unwrap_or_default());
+            if write_timings.insert != Duration::default() {
+                self.metrics.insert.add(write_timings.insert);
+            }
+            if write_timings.canonize != Duration::default() {
+                self.metrics.canonize.add(write_timings.canonize);
+            }
+            if let Some(err) = sink.error() {
+                // queued blocks are descendants of the invalid block
+                self.commit_queue.clear();
                 return Err(err);
             }
+
+            match deadline {
+                Some(deadline) if !self.commit_queue.is_empty() && Instant::now() >= deadline => {
+                    self.metrics.budget_exhausted += 1;
+                    break;
+                }
+                _ => (),
+            }
         }
 
         Ok(())
     }
 }
 
+impl PhaseTiming {
+    /// Account single execution of the phase
+    pub fn add(&mut self, elapsed: Duration) {
+        self.count += 1;
+        self.total += elapsed;
+        if elapsed > self.max {
+            self.max = elapsed;
+        }
+    }
+
+    /// Average time of single execution of the phase
+    pub fn average(&self) -> Duration {
+        if self.count == 0 {
+            Duration::default()
+        } else {
+            self.total / self.count as u32
+        }
+    }
+}
+
+impl fmt::Display for PhaseTiming {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} in {:.3}s (avg {:.3}ms, max {:.3}ms)",
+            self.count,
+            self.total.as_secs_f64(),
+            self.average().as_secs_f64() * 1000f64,
+            self.max.as_secs_f64() * 1000f64
+        )
+    }
+}
+
+impl fmt::Display for BlocksWriterMetrics {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "orphans: {}, verification: {}, insert: {}, canonize: {}, budget exhausted: {}",
+            self.orphans, self.verification, self.insert, self.canonize, self.budget_exhausted
+        )
+    }
+}
+
 impl BlocksWriterSink {
     /// Create new verification events receiver
     pub fn new(data: Arc<Mutex<BlocksWriterSinkData>>) -> Self {
@@ -136,8 +280,13 @@ impl BlockVerificationSink for BlocksWriterSink {
         None
     }
 
-    fn on_block_verification_error(&self, err: &str, _hash: &H256) {
-        self.data.lock().err = Some(Error::Verification(err.into()));
+    fn on_block_verification_error(
+        &self,
+        err: &VerificationError,
+        _hash: &H256,
+    ) -> Option<Vec<VerificationTask>> {
+        self.data.lock().err = Some(Error::Verification(err.clone()));
+        None
     }
 }
 
@@ -150,6 +299,7 @@ mod tests {
     use db::BlockChainDatabase;
     use network::Network;
     use std::sync::Arc;
+    use std::time::Duration;
     use verification::VerificationLevel;
     use VerificationParameters;
 
@@ -157,6 +307,7 @@ mod tests {
         VerificationParameters {
             verification_level: VerificationLevel::Full,
             verification_edge: 0u8.into(),
+            verification_rules: Vec::new(),
         }
     }
 
@@ -262,10 +413,112 @@ mod tests {
             VerificationParameters {
                 verification_level: VerificationLevel::NoVerification,
                 verification_edge: 0u8.into(),
+                verification_rules: Vec::new(),
             },
         );
         assert_eq!(blocks_target.append_block(b1.into()), Ok(()));
         assert_eq!(blocks_target.append_block(b2.into()), Ok(()));
         assert_eq!(blocks_target.append_block(b3.into()), Ok(()));
     }
+
+    #[test]
+    fn blocks_writer_reorganizes_to_longest_branch_in_any_order() {
+        let genesis = test_data::genesis();
+        let tree = test_data::fork_tree_builder(&genesis.block_header)
+            .branch(6, 100)
+            .fork(0, 2, 5, 200)
+            .fork(1, 4, 4, 300)
+            .fork(0, 5, 2, 400)
+            .build();
+        let longest = tree.longest_branch();
+        assert_eq!(tree.height(longest), 8);
+
+        for seed in 0..16 {
+            let db = Arc::new(BlockChainDatabase::init_test_chain(vec![genesis
+                .clone()
+                .into()]));
+            let mut blocks_target = BlocksWriter::new(
+                db.clone(),
+                Network::Testnet,
+                VerificationParameters {
+                    verification_level: VerificationLevel::NoVerification,
+                    verification_edge: 0u8.into(),
+                    verification_rules: Vec::new(),
+                },
+            );
+            tree.append_shuffled(seed, |block| blocks_target.append_block(block.into()))
+                .expect("Expecting no error");
+            assert_eq!(db.best_block().number, 8);
+            assert_eq!(db.best_block().hash, tree.tip(longest));
+        }
+    }
+
+    #[test]
+    fn blocks_writer_reports_phase_metrics() {
+        let db = Arc::new(BlockChainDatabase::init_test_chain(vec![
+            test_data::genesis().into(),
+        ]));
+        let blocks = test_data::build_n_empty_blocks_from_genesis(3, 1);
+        let mut blocks_target = BlocksWriter::new(
+            db.clone(),
+            Network::Testnet,
+            VerificationParameters {
+                verification_level: VerificationLevel::NoVerification,
+                verification_edge: 0u8.into(),
+                verification_rules: Vec::new(),
+            },
+        );
+        // b2 is orphaned, b1 connects it
+        assert_eq!(blocks_target.append_block(blocks[1].clone().into()), Ok(()));
+        assert_eq!(blocks_target.append_block(blocks[0].clone().into()), Ok(()));
+        assert_eq!(db.best_block().number, 2);
+
+        let metrics = blocks_target.metrics();
+        assert_eq!(metrics.orphans.count, 2);
+        assert_eq!(metrics.verification.count, 2);
+        assert_eq!(metrics.insert.count, 2);
+        assert_eq!(metrics.canonize.count, 2);
+        assert_eq!(metrics.budget_exhausted, 0);
+        assert!(metrics.insert.max <= metrics.insert.total);
+    }
+
+    #[test]
+    fn blocks_writer_commits_in_batches_within_budget() {
+        let db = Arc::new(BlockChainDatabase::init_test_chain(vec![
+            test_data::genesis().into(),
+        ]));
+        let blocks = test_data::build_n_empty_blocks_from_genesis(6, 1);
+        let mut blocks_target = BlocksWriter::new(
+            db.clone(),
+            Network::Testnet,
+            VerificationParameters {
+                verification_level: VerificationLevel::NoVerification,
+                verification_edge: 0u8.into(),
+                verification_rules: Vec::new(),
+            },
+        )
+        .with_commit_budget(Some(Duration::default()));
+
+        // b2..b5 are orphaned
+        for block in blocks.iter().skip(1).take(4) {
+            assert_eq!(blocks_target.append_block(block.clone().into()), Ok(()));
+        }
+        assert_eq!(db.best_block().number, 0);
+
+        // b1 connects them, but only one block fits into the budget
+        assert_eq!(blocks_target.append_block(blocks[0].clone().into()), Ok(()));
+        assert_eq!(db.best_block().number, 1);
+        assert_eq!(blocks_target.pending_blocks(), 4);
+        assert_eq!(blocks_target.metrics().budget_exhausted, 1);
+
+        // queued blocks are known: duplicates are ignored, children are queued after them
+        assert_eq!(blocks_target.append_block(blocks[2].clone().into()), Ok(()));
+        assert_eq!(blocks_target.append_block(blocks[5].clone().into()), Ok(()));
+        assert_eq!(db.best_block().number, 2);
+        assert_eq!(blocks_target.pending_blocks(), 4);
+
+        assert_eq!(blocks_target.flush(), Ok(()));
+        assert_eq!(db.best_block().number, 6);
+        assert_eq!(blocks_target.pending_blocks(), 0);
+    }
 }
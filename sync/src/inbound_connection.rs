@@ -1,4 +1,5 @@
 use chain::{IndexedBlock, IndexedBlockHeader};
+use message::common::InventoryType;
 use message::types;
 use p2p::{InboundSyncConnection, InboundSyncConnectionRef, InboundSyncConnectionStateRef};
 use types::{LocalNodeRef, PeerIndex, PeersRef, RequestId};
@@ -58,6 +59,14 @@ impl InboundSyncConnection for InboundConnection {
             return;
         }
 
+        // peer knows blocks it announces => they are never announced back
+        for item in &message.inventory {
+            if item.inv_type == InventoryType::MessageBlock {
+                self.peers
+                    .hash_known_as(self.peer_index, item.hash.clone(), KnownHashType::Block);
+            }
+        }
+
         self.node.on_inventory(self.peer_index, message);
     }
 
@@ -91,16 +100,16 @@ impl InboundSyncConnection for InboundConnection {
 
     fn on_block(&self, message: types::Block) {
         let block = IndexedBlock::from_raw(message.block);
+        // the span is carried with the block into the verification thread, so scheduling,
+        // verification and commit of the block are traced within it
+        let span = debug_span!(target: "sync", "block", hash = %block.hash().to_reversed_str(), peer = self.peer_index);
+        let _enter = span.enter();
         self.peers
             .hash_known_as(self.peer_index, block.hash().clone(), KnownHashType::Block);
         self.node.on_block(self.peer_index, block);
     }
 
     fn on_headers(&self, message: types::Headers) {
-        // if headers are empty - just ignore this message
-        if message.headers.is_empty() {
-            return;
-        }
         // if there are too many headers => possible DOS
         if message.headers.len() > types::HEADERS_MAX_HEADERS_LEN {
             self.peers.dos(
@@ -113,11 +122,15 @@ impl InboundSyncConnection for InboundConnection {
             return;
         }
 
-        let headers = message
+        let headers: Vec<_> = message
             .headers
             .into_iter()
             .map(IndexedBlockHeader::from_raw)
             .collect();
+        for header in &headers {
+            self.peers
+                .hash_known_as(self.peer_index, header.hash.clone(), KnownHashType::Block);
+        }
         self.node.on_headers(self.peer_index, headers);
     }
 
@@ -128,25 +141,81 @@ impl InboundSyncConnection for InboundConnection {
     fn on_notfound(&self, message: types::NotFound) {
         self.node.on_notfound(self.peer_index, message);
     }
+
+    fn on_latency(&self, rtt: f64) {
+        self.peers.note_latency(self.peer_index, rtt);
+    }
 }
 
 #[cfg(test)]
 pub mod tests {
-    use message::types;
-    use p2p::OutboundSyncConnection;
+    use message::{types, SharedPayload};
+    use p2p::{Direction, ExperimentFlags, OutboundSyncConnection};
     use parking_lot::Mutex;
     use std::collections::HashMap;
+    use std::net::{Ipv4Addr, SocketAddr};
+    use std::sync::atomic::{AtomicUsize, Ordering};
     use std::sync::Arc;
     use types::RequestId;
 
+    /// Every dummy connection has its own address, unless it is explicitly set
+    static NEXT_DUMMY_ADDRESS: AtomicUsize = AtomicUsize::new(1);
+
+    fn next_dummy_address() -> SocketAddr {
+        let index = NEXT_DUMMY_ADDRESS.fetch_add(1, Ordering::Relaxed) as u32;
+        SocketAddr::new(Ipv4Addr::from(0x0a00_0000 + index).into(), 8333)
+    }
+
     pub struct DummyOutboundSyncConnection {
         pub messages: Mutex<HashMap<String, usize>>,
+        experiments: ExperimentFlags,
+        direction: Direction,
+        whitelisted: bool,
+        address: SocketAddr,
     }
 
     impl DummyOutboundSyncConnection {
         pub fn new() -> Arc<DummyOutboundSyncConnection> {
+            DummyOutboundSyncConnection::with_experiments(ExperimentFlags::default())
+        }
+
+        pub fn with_experiments(experiments: ExperimentFlags) -> Arc<DummyOutboundSyncConnection> {
+            Arc::new(DummyOutboundSyncConnection {
+                messages: Mutex::new(HashMap::new()),
+                experiments: experiments,
+                direction: Direction::Outbound,
+                whitelisted: false,
+                address: next_dummy_address(),
+            })
+        }
+
+        pub fn with_direction(direction: Direction) -> Arc<DummyOutboundSyncConnection> {
             Arc::new(DummyOutboundSyncConnection {
                 messages: Mutex::new(HashMap::new()),
+                experiments: ExperimentFlags::default(),
+                direction: direction,
+                whitelisted: false,
+                address: next_dummy_address(),
+            })
+        }
+
+        pub fn whitelisted() -> Arc<DummyOutboundSyncConnection> {
+            Arc::new(DummyOutboundSyncConnection {
+                messages: Mutex::new(HashMap::new()),
+                experiments: ExperimentFlags::default(),
+                direction: Direction::Inbound,
+                whitelisted: true,
+                address: next_dummy_address(),
+            })
+        }
+
+        pub fn with_address(address: SocketAddr) -> Arc<DummyOutboundSyncConnection> {
+            Arc::new(DummyOutboundSyncConnection {
+                messages: Mutex::new(HashMap::new()),
+                experiments: ExperimentFlags::default(),
+                direction: Direction::Inbound,
+                whitelisted: false,
+                address: address,
             })
         }
     }
@@ -183,6 +252,9 @@ pub mod tests {
         fn send_block(&self, _message: &types::Block) {
             *self.messages.lock().entry("block".to_owned()).or_insert(0) += 1;
         }
+        fn send_serialized_block(&self, _block: &SharedPayload) {
+            *self.messages.lock().entry("block".to_owned()).or_insert(0) += 1;
+        }
         fn send_headers(&self, _message: &types::Headers) {
             *self
                 .messages
@@ -213,5 +285,17 @@ pub mod tests {
         }
         fn ignored(&self, _id: RequestId) {}
         fn close(&self) {}
+        fn experiments(&self) -> ExperimentFlags {
+            self.experiments
+        }
+        fn direction(&self) -> Direction {
+            self.direction
+        }
+        fn is_whitelisted(&self) -> bool {
+            self.whitelisted
+        }
+        fn address(&self) -> SocketAddr {
+            self.address
+        }
     }
 }
@@ -7,6 +7,7 @@ extern crate storage;
 extern crate log;
 extern crate bit_vec;
 extern crate futures;
+extern crate futures_cpupool;
 extern crate linked_hash_map;
 extern crate message;
 extern crate miner;
@@ -18,12 +19,17 @@ extern crate primitives;
 extern crate rand;
 extern crate serialization as ser;
 extern crate time;
+extern crate tokio_core;
+#[macro_use(debug_span, trace_span)]
+extern crate tracing;
 extern crate verification;
 
 mod blocks_writer;
 mod inbound_connection;
 mod inbound_connection_factory;
 mod local_node;
+#[cfg(test)]
+mod simulation;
 mod synchronization_chain;
 mod synchronization_client;
 mod synchronization_client_core;
@@ -36,12 +42,31 @@ mod synchronization_verifier;
 mod types;
 mod utils;
 
+pub use blocks_writer::{BlocksWriter, BlocksWriterMetrics, PhaseTiming};
+pub use synchronization_chain::{block_locator_heights, build_block_locator};
+pub use synchronization_client_core::{
+    DEFAULT_MAX_REQUESTED_BLOCKS, DEFAULT_MAX_VERIFICATION_QUEUE,
+};
+pub use synchronization_executor::{
+    RelayConfig, DEFAULT_ANNOUNCEMENT_DELAY_MS, DEFAULT_TRICKLE_INTERVAL_MS,
+};
+pub use synchronization_manager::DEFAULT_BLOCK_STALL_TIMEOUT_S;
+pub use synchronization_verifier::{ChainCheck, ChainCheckFailure, VerificationError};
 pub use types::LocalNodeRef;
 pub use types::PeersRef;
+pub use types::SynchronizationStateRef;
+pub use utils::{
+    BlockAnnouncement, BlockPropagation, HeaderBranchesLimits, MemoryUsage, OrphanBlockEntry,
+    PropagationStage, ServerLogStats, StaleBlock, SynchronizationState,
+    SynchronizationStateSnapshot, UploadLimit, DEFAULT_MAX_HEADER_BRANCHES,
+    DEFAULT_MAX_HEADER_BRANCH_LEN, DEFAULT_MAX_HEADER_BRANCH_WORK_DEFICIT, DEFAULT_MEMORY_BUDGET,
+    DEFAULT_MISBEHAVIOR_SCORE_TTL_S, DEFAULT_SERVER_LOG_SAMPLING, DEFAULT_UPLOAD_WINDOW_S,
+};
 
 use network::Network;
 use primitives::hash::H256;
 use std::sync::Arc;
+use types::BlockHeight;
 use verification::BackwardsCompatibleChainVerifier as ChainVerifier;
 
 /// Sync errors.
@@ -52,10 +77,18 @@ pub enum Error {
     /// Database error.
     Database(storage::Error),
     /// Block verification error.
-    Verification(String),
+    Verification(VerificationError),
+    /// Block is not in the storage.
+    UnknownBlock,
+    /// Genesis block can not be invalidated.
+    GenesisBlock,
+    /// Block has not been processed in time.
+    Timeout,
+    /// Blocks are not inserted, because free disk space is low.
+    DiskSpaceLow,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 /// Verification parameters.
 pub struct VerificationParameters {
     /// Blocks verification level.
@@ -63,6 +96,29 @@ pub struct VerificationParameters {
     /// Blocks verification edge: all blocks before this are validated using verification_level.
     /// All blocks after this (inclusive) are validated using VerificationLevel::Full level.
     pub verification_edge: H256,
+    /// Verification levels of height ranges. Take precedence over verification_level and verification_edge.
+    pub verification_rules: Vec<VerificationRule>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+/// Verification level of blocks in the height range.
+pub struct VerificationRule {
+    /// First height of the range.
+    pub from: u32,
+    /// Last height of the range (inclusive). None if the range is not bounded.
+    pub to: Option<u32>,
+    /// Blocks verification level.
+    pub level: verification::VerificationLevel,
+}
+
+impl VerificationParameters {
+    /// Returns level of the first rule, covering given height.
+    pub fn rule_level(&self, height: u32) -> Option<verification::VerificationLevel> {
+        self.verification_rules
+            .iter()
+            .find(|rule| rule.from <= height && rule.to.map_or(true, |to| height <= to))
+            .map(|rule| rule.level)
+    }
 }
 
 /// Synchronization events listener
@@ -71,6 +127,14 @@ pub trait SyncListener: Send + 'static {
     fn synchronization_state_switched(&self, is_synchronizing: bool);
     /// Called when new best storage block is inserted
     fn best_storage_block_inserted(&self, block_hash: &H256);
+    /// Called when synchronization session with the peer is started
+    fn on_peer_connected(&self, _peer_index: types::PeerIndex) {}
+    /// Called when peer has no headers we don't know. Called again only after peer has provided new headers
+    fn on_peer_synced(&self, _peer_index: types::PeerIndex) {}
+    /// Called when node starts synchronizing blocks, which headers are provided by the peer, up to target height
+    fn on_started_syncing(&self, _peer_index: types::PeerIndex, _target_height: u32) {}
+    /// Called when node switches to saturated state, i.e. all known blocks are synchronized
+    fn on_switched_to_saturated(&self) {}
 }
 
 /// Create blocks writer.
@@ -82,48 +146,80 @@ pub fn create_sync_blocks_writer(
     blocks_writer::BlocksWriter::new(db, network, verification_params)
 }
 
-/// Create synchronization peers
-pub fn create_sync_peers() -> PeersRef {
+/// Create synchronization peers, which misbehavior is remembered for given number of seconds
+pub fn create_sync_peers(misbehavior_score_ttl_s: u32) -> PeersRef {
     use synchronization_peers::PeersImpl;
 
-    Arc::new(PeersImpl::default())
+    Arc::new(PeersImpl::new(misbehavior_score_ttl_s))
 }
 
 /// Creates local sync node for given `db`
+#[cfg_attr(feature = "cargo-clippy", allow(too_many_arguments))]
 pub fn create_local_sync_node(
     network: Network,
     db: storage::SharedStore,
     peers: PeersRef,
     verification_params: VerificationParameters,
+    server_threads: usize,
+    upload_limit: Option<UploadLimit>,
+    server_log_sampling: u32,
+    block_stall_timeout_s: u32,
+    max_requested_blocks: BlockHeight,
+    max_verification_queue: BlockHeight,
+    memory_budget: usize,
+    max_reorg_depth: Option<BlockHeight>,
+    header_branches: HeaderBranchesLimits,
+    relay_config: RelayConfig,
+    watch_only: bool,
 ) -> LocalNodeRef {
     use local_node::LocalNode as SyncNode;
     use synchronization_chain::Chain as SyncChain;
-    use synchronization_client::SynchronizationClient;
+    use synchronization_client::{Client, SynchronizationClient};
     use synchronization_client_core::{
         Config as SynchronizationConfig, CoreVerificationSink, SynchronizationClientCore,
     };
     use synchronization_executor::LocalSynchronizationTaskExecutor as SyncExecutor;
-    use synchronization_server::ServerImpl;
+    use synchronization_server::{Server, ServerImpl};
     use synchronization_verifier::AsyncVerifier;
-    use types::SynchronizationStateRef;
-    use utils::SynchronizationState;
+    use types::{MemoryBudgetRef, SerializedBlockCacheRef, SynchronizationStateRef};
+    use utils::{
+        MemoryBudget, NetworkTime, RecentBlocksSerializer, SynchronizationState,
+        DEFAULT_RECENT_BLOCKS,
+    };
+
+    let memory_budget = MemoryBudgetRef::new(MemoryBudget::new(memory_budget));
 
     let sync_client_config = SynchronizationConfig {
         // during regtests, peer is providing us with bad blocks => we shouldn't close connection because of this
         // close_connection_on_bad_block: network != Network::Regtest,
         close_connection_on_bad_block: false,
+        block_stall_timeout_s: block_stall_timeout_s,
+        max_requested_blocks: max_requested_blocks,
+        header_branches: header_branches,
+        watch_only: watch_only,
+        max_verification_queue: max_verification_queue,
+        memory_budget: memory_budget.clone(),
     };
 
     let sync_state = SynchronizationStateRef::new(SynchronizationState::with_storage(db.clone()));
-    let sync_chain = SyncChain::new(db.clone());
+    let sync_chain = SyncChain::new(db.clone()).with_max_reorg_depth(max_reorg_depth);
 
-    let chain_verifier = Arc::new(ChainVerifier::new(db.clone(), network.clone()));
-    let sync_executor = SyncExecutor::new(peers.clone());
+    let network_time = Arc::new(NetworkTime::default());
+    let chain_verifier = Arc::new(
+        ChainVerifier::new(db.clone(), network.clone()).with_time_source(network_time.clone()),
+    );
+    let sync_executor = SyncExecutor::new(peers.clone(), relay_config);
+    let block_cache = SerializedBlockCacheRef::default();
     let sync_server = Arc::new(ServerImpl::new(
         peers.clone(),
         db.clone(),
         sync_executor.clone(),
+        server_threads,
+        upload_limit,
+        block_cache.clone(),
+        memory_budget.clone(),
     ));
+    sync_server.set_log_sampling(server_log_sampling);
     let sync_client_core = SynchronizationClientCore::new(
         sync_client_config,
         sync_state.clone(),
@@ -140,14 +236,18 @@ pub fn create_local_sync_node(
         verification_params,
     );
     let sync_client = SynchronizationClient::new(sync_client_core, verifier);
-    Arc::new(SyncNode::new(
-        network,
-        db,
-        peers,
-        sync_state,
-        sync_client,
-        sync_server,
-    ))
+    // new blocks are served to peers from the cache
+    sync_client.install_sync_listener(Box::new(RecentBlocksSerializer::new(
+        db.clone(),
+        block_cache,
+        DEFAULT_RECENT_BLOCKS,
+    )));
+    Arc::new(
+        SyncNode::new(network, db, peers, sync_state, sync_client, sync_server)
+            .with_watch_only(watch_only)
+            .with_network_time(network_time)
+            .with_memory_budget(memory_budget),
+    )
 }
 
 /// Create inbound synchronization connections factory for given local sync node.
@@ -3,16 +3,35 @@ use futures::{finished, lazy};
 use message::types;
 use miner::BlockAssembler;
 use miner::BlockTemplate;
+use miner::{mine_block, VrfPk};
 use network::Network;
+use primitives::hash::H256;
+use std::net::IpAddr;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
+use std::{cmp, thread};
+use storage::{self, BlockHeaderProvider, BlockProvider, BlockRef};
 use synchronization_client::Client;
 use synchronization_peers::{BlockAnnouncementType, TransactionAnnouncementType};
 use synchronization_server::{Server, ServerTask};
+use synchronization_verifier::{verify_canon_chain, ChainCheck, VerificationError};
 use time;
 use types::{
-    ClientRef, PeerIndex, PeersRef, RequestId, ServerRef, StorageRef, SyncListenerRef,
-    SynchronizationStateRef,
+    ClientRef, MemoryBudgetRef, PeerIndex, PeersRef, RequestId, ServerRef, StorageRef,
+    SyncListenerRef, SynchronizationStateRef,
 };
+use utils::{
+    BestBlockWatcher, MemoryUsage, NetworkTime, OrphanBlockEntry, ServerLogStats, UploadLimit,
+};
+use verification::{BackwardsCompatibleChainVerifier as ChainVerifier, VerificationLevel};
+use Error;
+
+/// Generated block must be mined and inserted into the storage within this time.
+const GENERATE_BLOCK_TIMEOUT_S: u64 = 60;
+/// Submitted block must be verified within this time.
+const SUBMIT_BLOCK_TIMEOUT_S: u64 = 60;
+/// Interval between checks of the generated or submitted block insertion.
+const BLOCK_INSERTION_CHECK_INTERVAL_MS: u64 = 10;
 
 /// Local synchronization node
 pub struct LocalNode<U: Server, V: Client> {
@@ -28,6 +47,14 @@ pub struct LocalNode<U: Server, V: Client> {
     client: ClientRef<V>,
     /// Synchronization server
     server: ServerRef<U>,
+    /// If true, peers requests are not served
+    watch_only: bool,
+    /// Network-adjusted time
+    network_time: Arc<NetworkTime>,
+    /// Best block changes, awaited by block template requests
+    best_block_watcher: BestBlockWatcher,
+    /// Memory budget, shared by synchronization components
+    memory_budget: MemoryBudgetRef,
 }
 
 impl<U, V> LocalNode<U, V>
@@ -45,6 +72,8 @@ where
         client: ClientRef<V>,
         server: ServerRef<U>,
     ) -> Self {
+        let best_block_watcher = BestBlockWatcher::new(storage.best_block().hash);
+        client.install_sync_listener(Box::new(best_block_watcher.clone()));
         LocalNode {
             network: network,
             storage: storage,
@@ -52,9 +81,46 @@ where
             state: state,
             client: client,
             server: server,
+            watch_only: false,
+            network_time: Arc::new(NetworkTime::default()),
+            best_block_watcher: best_block_watcher,
+            memory_budget: Default::default(),
         }
     }
 
+    /// Share network-adjusted time with other components (e.g. with verifier)
+    pub fn with_network_time(mut self, network_time: Arc<NetworkTime>) -> Self {
+        self.network_time = network_time;
+        self
+    }
+
+    /// Share memory budget of synchronization components, so that its usage could be reported
+    pub fn with_memory_budget(mut self, memory_budget: MemoryBudgetRef) -> Self {
+        self.memory_budget = memory_budget;
+        self
+    }
+
+    /// Never serve peers requests (used by network observers)
+    pub fn with_watch_only(mut self, watch_only: bool) -> Self {
+        self.watch_only = watch_only;
+        self
+    }
+
+    /// Are peers requests never served?
+    pub fn is_watch_only(&self) -> bool {
+        self.watch_only
+    }
+
+    /// Misbehavior score of the peer address
+    pub fn misbehavior_score(&self, address: &IpAddr) -> u32 {
+        self.peers.misbehavior_score(address)
+    }
+
+    /// Current adjustment of the local time (in seconds), computed from peers time offsets
+    pub fn time_offset(&self) -> i64 {
+        self.network_time.offset()
+    }
+
     /// Return shared reference to synchronization state.
     pub fn sync_state(&self) -> SynchronizationStateRef {
         self.state.clone()
@@ -64,7 +130,14 @@ where
     pub fn on_connect(&self, peer_index: PeerIndex, peer_name: String, version: types::Version) {
         trace!(target: "sync", "Starting new sync session with peer#{}: {}", peer_index, peer_name);
 
+        // zero timestamp means that peer hasn't advertised its time
+        if version.timestamp() != 0 {
+            self.network_time
+                .add_sample(peer_index, version.timestamp());
+        }
+
         // light clients may not want transactions broadcasting until filter for connection is set
+        // block-relay-only peers are also asking to not relay anything but blocks
         if !version.relay_transactions() {
             self.peers.set_transaction_announcement_type(
                 peer_index,
@@ -80,6 +153,8 @@ where
     pub fn on_disconnect(&self, peer_index: PeerIndex) {
         trace!(target: "sync", "Stopping sync session with peer#{}", peer_index);
 
+        self.network_time.remove_sample(peer_index);
+
         // stop synchronization session with peer
         self.client.on_disconnect(peer_index);
     }
@@ -111,6 +186,10 @@ where
     /// When peer is requesting for items
     pub fn on_getdata(&self, peer_index: PeerIndex, message: types::GetData) {
         trace!(target: "sync", "Got `getdata` message from peer#{}. Inventory len: {}", peer_index, message.inventory.len());
+        if self.watch_only {
+            return;
+        }
+
         self.server
             .execute(ServerTask::GetData(peer_index, message));
     }
@@ -118,6 +197,10 @@ where
     /// When peer is requesting for known blocks hashes
     pub fn on_getblocks(&self, peer_index: PeerIndex, message: types::GetBlocks) {
         trace!(target: "sync", "Got `getblocks` message from peer#{}", peer_index);
+        if self.watch_only {
+            return;
+        }
+
         self.server
             .execute(ServerTask::GetBlocks(peer_index, message));
     }
@@ -125,6 +208,13 @@ where
     /// When peer is requesting for known blocks headers
     pub fn on_getheaders(&self, peer_index: PeerIndex, message: types::GetHeaders, id: RequestId) {
         trace!(target: "sync", "Got `getheaders` message from peer#{}", peer_index);
+        if self.watch_only {
+            // let connection send responses to later requests
+            if let Some(connection) = self.peers.connection(peer_index) {
+                connection.ignored(id);
+            }
+            return;
+        }
 
         // simulating randchaind for passing tests: if we are in nearly-saturated state
         // and peer, which has just provided a new blocks to us, is asking for headers
@@ -152,10 +242,153 @@ where
         block_assembler.create_new_block(&self.storage, time::get_time().sec as u32, &self.network)
     }
 
+    /// Wait (up to timeout) until the best block isn't the block with given hash. Returns hash of the best block
+    pub fn wait_best_block_change(&self, best_block_hash: &H256, timeout: Duration) -> H256 {
+        self.best_block_watcher
+            .wait_for_change(best_block_hash, timeout)
+    }
+
     /// Install synchronization events listener
     pub fn install_sync_listener(&self, listener: SyncListenerRef) {
         self.client.install_sync_listener(listener);
     }
+
+    /// Mark stored block and all its descendants invalid
+    pub fn invalidate_block(&self, hash: &H256) -> Result<(), Error> {
+        self.client.invalidate_block(hash)
+    }
+
+    /// Remove invalidity mark from the stored block
+    pub fn reconsider_block(&self, hash: &H256) -> Result<(), Error> {
+        self.client.reconsider_block(hash)
+    }
+
+    /// Get latest finalized block
+    pub fn finalized_block(&self) -> Option<storage::BestBlock> {
+        self.client.finalized_block()
+    }
+
+    /// Get blocks, which are held in memory until their parents are received
+    pub fn orphan_blocks(&self) -> Vec<OrphanBlockEntry> {
+        self.client.orphan_blocks()
+    }
+
+    /// Get current usage of the memory budget, shared by synchronization components
+    pub fn memory_usage(&self) -> MemoryUsage {
+        self.memory_budget.usage()
+    }
+
+    /// Change sampling of serving decisions logs: 1 of `sampling` served requests is fully logged
+    pub fn set_server_log_sampling(&self, sampling: u32) {
+        self.server.set_log_sampling(sampling)
+    }
+
+    /// Get counters of serving outcomes
+    pub fn server_log_stats(&self) -> ServerLogStats {
+        self.server.log_stats()
+    }
+
+    /// Change max number of bytes, served to single peer. None if uploads are not limited
+    pub fn set_upload_limit(&self, limit: Option<UploadLimit>) {
+        self.server.set_upload_limit(limit)
+    }
+
+    /// Verify up to `depth` best canon blocks (all blocks if `depth` is 0) again, to detect storage corruption
+    pub fn verify_chain(&self, verification_level: VerificationLevel, depth: u32) -> ChainCheck {
+        let verifier = ChainVerifier::new(self.storage.clone(), self.network)
+            .with_time_source(self.network_time.clone());
+        verify_canon_chain(&verifier, &self.storage, verification_level, depth)
+    }
+
+    /// Mine `count` blocks on top of the best block and wait until they are inserted into the storage.
+    /// Blocks are only generated on demand on regtest, where the difficulty is trivial
+    pub fn generate_blocks(&self, count: u32, pubkey: &VrfPk) -> Result<Vec<H256>, String> {
+        if self.network != Network::Regtest {
+            return Err("blocks are only generated on regtest".into());
+        }
+        if self.state.disk_space_low() {
+            return Err("blocks are not inserted while free disk space is low".into());
+        }
+
+        let mut hashes = Vec::with_capacity(count as usize);
+        for _ in 0..count {
+            let mut template = self.get_block_template();
+            // blocks, generated within the same second, must still have increasing timestamps
+            if let Some(parent) = self
+                .storage
+                .block_header(BlockRef::Hash(template.previous_header_hash.clone()))
+            {
+                template.time = cmp::max(template.time, parent.raw.time + 1);
+            }
+
+            let timeout = Duration::from_secs(GENERATE_BLOCK_TIMEOUT_S);
+            let block = mine_block(&template, pubkey, self.network.vdf_params(), timeout)
+                .ok_or_else(|| {
+                    format!(
+                        "block at height {} is not mined in {}s",
+                        template.height, GENERATE_BLOCK_TIMEOUT_S
+                    )
+                })?;
+            let block = IndexedBlock::from(block);
+            let hash = block.header.hash.clone();
+            // Let's use PeerIndex=0 to identify the node itself
+            self.on_block(0, block);
+
+            self.wait_block_insertion(&hash, timeout)
+                .map_err(|err| match err {
+                    Error::Verification(err) => format!(
+                        "generated block {} is rejected: {}",
+                        hash.to_reversed_str(),
+                        err
+                    ),
+                    _ => format!(
+                        "generated block {} is not accepted in {}s",
+                        hash.to_reversed_str(),
+                        GENERATE_BLOCK_TIMEOUT_S
+                    ),
+                })?;
+            hashes.push(hash);
+        }
+        Ok(hashes)
+    }
+
+    /// Verify block, produced outside of the node, and wait until it is inserted into the storage.
+    /// Returns the reason, if block is rejected
+    pub fn submit_block(&self, block: IndexedBlock) -> Result<(), Error> {
+        let hash = block.header.hash.clone();
+        if self.storage.contains_block(BlockRef::Hash(hash.clone())) {
+            return Err(Error::Verification(VerificationError::Duplicate));
+        }
+        // submitted orphan would wait for its parent forever
+        if !self.storage.contains_block(BlockRef::Hash(
+            block.header.raw.previous_header_hash.clone(),
+        )) {
+            return Err(Error::Verification(VerificationError::BadParent));
+        }
+        // blocks are ignored while disk space is low, so submitted block would never be inserted
+        if self.state.disk_space_low() {
+            return Err(Error::DiskSpaceLow);
+        }
+
+        // Let's use PeerIndex=0 to identify the node itself
+        self.on_block(0, block);
+        self.wait_block_insertion(&hash, Duration::from_secs(SUBMIT_BLOCK_TIMEOUT_S))
+    }
+
+    /// Wait (up to timeout) until the block is inserted into the storage or rejected
+    fn wait_block_insertion(&self, hash: &H256, timeout: Duration) -> Result<(), Error> {
+        let started = Instant::now();
+        while !self.storage.contains_block(BlockRef::Hash(hash.clone())) {
+            if let Some(err) = self.client.block_rejection(hash) {
+                return Err(Error::Verification(err));
+            }
+            if started.elapsed() > timeout {
+                return Err(Error::Timeout);
+            }
+            thread::sleep(Duration::from_millis(BLOCK_INSERTION_CHECK_INTERVAL_MS));
+        }
+        Ok(())
+    }
 }
 
 #[cfg(test)]
@@ -166,19 +399,26 @@ pub mod tests {
     use db::BlockChainDatabase;
     use message::common::{InventoryType, InventoryVector};
     use message::types;
+    use miner::VrfPk;
     use network::Network;
     use std::sync::Arc;
     use synchronization_chain::Chain;
     use synchronization_client::SynchronizationClient;
-    use synchronization_client_core::{Config, CoreVerificationSink, SynchronizationClientCore};
+    use synchronization_client_core::{
+        Config, CoreVerificationSink, SynchronizationClientCore, DEFAULT_MAX_REQUESTED_BLOCKS,
+        DEFAULT_MAX_VERIFICATION_QUEUE,
+    };
     use synchronization_executor::tests::DummyTaskExecutor;
+    use synchronization_manager::DEFAULT_BLOCK_STALL_TIMEOUT_S;
     use synchronization_peers::PeersImpl;
     use synchronization_server::tests::DummyServer;
     use synchronization_server::ServerTask;
     use synchronization_verifier::tests::DummyVerifier;
+    use synchronization_verifier::VerificationError;
     use types::SynchronizationStateRef;
-    use utils::SynchronizationState;
+    use utils::{HeaderBranchesLimits, SynchronizationState};
     use verification::BackwardsCompatibleChainVerifier as ChainVerifier;
+    use Error;
 
     fn create_local_node(
         verifier: Option<DummyVerifier>,
@@ -199,6 +439,12 @@ pub mod tests {
         let config = Config {
             // close_connection_on_bad_block: true,
             close_connection_on_bad_block: false,
+            block_stall_timeout_s: DEFAULT_BLOCK_STALL_TIMEOUT_S,
+            max_requested_blocks: DEFAULT_MAX_REQUESTED_BLOCKS,
+            header_branches: HeaderBranchesLimits::default(),
+            watch_only: false,
+            max_verification_queue: DEFAULT_MAX_VERIFICATION_QUEUE,
+            memory_budget: Default::default(),
         };
         let chain_verifier = Arc::new(ChainVerifier::new(storage.clone(), Network::Mainnet));
         let client_core = SynchronizationClientCore::new(
@@ -253,4 +499,88 @@ pub mod tests {
             )]
         );
     }
+
+    #[test]
+    fn watch_only_local_node_does_not_serve_blocks() {
+        let (_, server, local_node) = create_local_node(None);
+        let local_node = local_node.with_watch_only(true);
+        let peer_index = 0;
+        local_node.on_connect(peer_index, "test".into(), types::Version::default());
+        local_node.on_getdata(
+            peer_index,
+            types::GetData::with_inventory(vec![InventoryVector {
+                inv_type: InventoryType::MessageBlock,
+                hash: test_data::genesis().hash(),
+            }]),
+        );
+        local_node.on_getblocks(
+            peer_index,
+            types::GetBlocks {
+                version: 0,
+                block_locator_hashes: vec![test_data::genesis().hash()],
+                hash_stop: 0.into(),
+            },
+        );
+        assert!(server.take_tasks().is_empty());
+    }
+
+    #[test]
+    fn blocks_are_only_generated_on_regtest() {
+        let (_, _, local_node) = create_local_node(None);
+        let pubkey = VrfPk::from_bytes(&[6; 32]).unwrap();
+        assert_eq!(
+            local_node.generate_blocks(1, &pubkey),
+            Err("blocks are only generated on regtest".to_owned())
+        );
+    }
+
+    #[test]
+    fn submitted_block_rejection_is_reported() {
+        let b1 = test_data::block_h1();
+        let mut verifier = DummyVerifier::default();
+        verifier.error_when_verifying(b1.hash(), VerificationError::BadProof);
+        let (_, _, local_node) = create_local_node(Some(verifier));
+
+        assert_eq!(
+            local_node.submit_block(test_data::genesis().into()),
+            Err(Error::Verification(VerificationError::Duplicate))
+        );
+        assert_eq!(
+            local_node.submit_block(test_data::block_h2().into()),
+            Err(Error::Verification(VerificationError::BadParent))
+        );
+        assert_eq!(
+            local_node.submit_block(b1.into()),
+            Err(Error::Verification(VerificationError::BadProof))
+        );
+    }
+
+    #[test]
+    fn submitted_block_is_inserted() {
+        let (_, _, local_node) = create_local_node(None);
+        assert_eq!(
+            local_node.submit_block(test_data::block_h1().into()),
+            Ok(())
+        );
+        assert_eq!(
+            local_node.submit_block(test_data::block_h1().into()),
+            Err(Error::Verification(VerificationError::Duplicate))
+        );
+    }
+
+    #[test]
+    fn submitted_block_is_rejected_when_disk_space_is_low() {
+        let (_, _, local_node) = create_local_node(None);
+        local_node.state.update_disk_space_low(true);
+        assert_eq!(
+            local_node.submit_block(test_data::block_h1().into()),
+            Err(Error::DiskSpaceLow)
+        );
+
+        local_node.state.update_disk_space_low(false);
+        assert_eq!(
+            local_node.submit_block(test_data::block_h1().into()),
+            Ok(())
+        );
+    }
 }
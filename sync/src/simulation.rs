@@ -0,0 +1,563 @@
+//! Deterministic simulation of the synchronization state machine.
+//!
+//! Full `SynchronizationClient` is driven by scripted peers, which respond to
+//! the client tasks after configured latency. Client tasks could also be delayed,
+//! reordered or lost by the simulated network. All events are processed in the order
+//! of their virtual time and random choices are seeded, so every scenario is completely reproducible.
+extern crate test_data;
+
+use chain::{Block, IndexedBlock, IndexedBlockHeader};
+use db::BlockChainDatabase;
+use inbound_connection::tests::DummyOutboundSyncConnection;
+use message::common::{InventoryType, InventoryVector};
+use message::{types, Services};
+use network::Network;
+use primitives::hash::H256;
+use std::cmp::min;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::Arc;
+use storage;
+use synchronization_chain::Chain;
+use synchronization_client::{Client, SynchronizationClient};
+use synchronization_client_core::{
+    Config, CoreVerificationSink, SynchronizationClientCore, DEFAULT_MAX_REQUESTED_BLOCKS,
+    DEFAULT_MAX_VERIFICATION_QUEUE,
+};
+use synchronization_executor::tests::{DummyTaskExecutor, LatencyTaskExecutor, NetworkConditions};
+use synchronization_executor::Task;
+use synchronization_manager::{
+    manage_synchronization, ManagePeersConfig, ManageUnknownBlocksConfig,
+    DEFAULT_BLOCK_STALL_TIMEOUT_S, MANAGEMENT_INTERVAL_MS,
+};
+use synchronization_peers::{PeersContainer, PeersImpl};
+use synchronization_verifier::tests::DummyVerifier;
+use synchronization_verifier::VerificationError;
+use types::{ClientCoreRef, PeerIndex, PeersRef, SynchronizationStateRef};
+use utils::{set_virtual_time, HeaderBranchesLimits, SynchronizationState};
+use verification::BackwardsCompatibleChainVerifier as ChainVerifier;
+
+/// Virtual time (in seconds) at which every simulation starts.
+const SIMULATION_START_TIME_S: f64 = 1_000_000f64;
+/// Max number of headers, sent by scripted peer in single message.
+const MAX_HEADERS_IN_RESPONSE: usize = 2000;
+/// Seed of the simulated network random choices.
+const NETWORK_SEED: u64 = 42;
+
+/// Tasks executor of the simulated client.
+type SimulationExecutor = LatencyTaskExecutor<DummyTaskExecutor>;
+
+/// How scripted peer responds to blocks requests.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BlocksResponse {
+    /// Requested blocks are sent after peer latency.
+    Deliver,
+    /// Requested blocks are sent after peer latency + given number of milliseconds.
+    Delay(u64),
+    /// Requested blocks are never sent.
+    Withhold,
+}
+
+/// Peer with predefined chain and behavior.
+pub struct ScriptedPeer {
+    /// Blocks of the peer chain, following the genesis block.
+    chain: Vec<IndexedBlock>,
+    /// Delay (in milliseconds) of every peer response.
+    latency_ms: u64,
+    /// Response to blocks requests.
+    blocks_response: BlocksResponse,
+}
+
+/// Message, sent by scripted peer to the client.
+enum PeerMessage {
+    Headers(Vec<IndexedBlockHeader>),
+    Block(IndexedBlock),
+    NotFound(types::NotFound),
+}
+
+/// Simulation event.
+enum Event {
+    /// Peer connects to the client.
+    Connect(PeerIndex),
+    /// Peer message is delivered to the client.
+    Message(PeerIndex, PeerMessage),
+    /// Synchronization management round.
+    Manage,
+}
+
+/// Simulated network of scripted peers around single synchronization client.
+pub struct Simulation {
+    /// Current virtual time (in milliseconds since simulation start).
+    now_ms: u64,
+    /// Sequence number of the next scheduled event. Orders events with the same time.
+    next_seq: u64,
+    /// Scheduled events, ordered by (time, sequence number).
+    events: BTreeMap<(u64, u64), Event>,
+    /// All scripted peers.
+    scripted_peers: HashMap<PeerIndex, ScriptedPeer>,
+    /// Currently connected peers.
+    connected: HashSet<PeerIndex>,
+    /// Synchronization peers of the client.
+    peers: PeersRef,
+    /// Tasks executor of the client.
+    executor: Arc<SimulationExecutor>,
+    /// Client core.
+    core: ClientCoreRef<SynchronizationClientCore<SimulationExecutor>>,
+    /// Client.
+    client: Arc<SynchronizationClient<SimulationExecutor, DummyVerifier>>,
+}
+
+impl ScriptedPeer {
+    /// Create honest peer with given chain and latency.
+    pub fn new(chain: Vec<IndexedBlock>, latency_ms: u64) -> Self {
+        ScriptedPeer {
+            chain: chain,
+            latency_ms: latency_ms,
+            blocks_response: BlocksResponse::Deliver,
+        }
+    }
+
+    /// Change response to blocks requests.
+    pub fn with_blocks_response(mut self, blocks_response: BlocksResponse) -> Self {
+        self.blocks_response = blocks_response;
+        self
+    }
+
+    /// Headers, following the best known locator block.
+    fn headers_after(&self, locator: &[H256]) -> Vec<IndexedBlockHeader> {
+        let start = locator
+            .iter()
+            .filter_map(|hash| {
+                if *hash == test_data::genesis().hash() {
+                    Some(0)
+                } else {
+                    self.chain
+                        .iter()
+                        .position(|block| block.hash() == hash)
+                        .map(|position| position + 1)
+                }
+            })
+            .next()
+            .unwrap_or(0);
+        self.chain
+            .iter()
+            .skip(start)
+            .take(MAX_HEADERS_IN_RESPONSE)
+            .map(|block| block.header.clone())
+            .collect()
+    }
+
+    /// Block with given hash.
+    fn block(&self, hash: &H256) -> Option<IndexedBlock> {
+        self.chain
+            .iter()
+            .find(|block| block.hash() == hash)
+            .cloned()
+    }
+}
+
+impl Simulation {
+    /// Create simulation with test chain, containing genesis block only.
+    pub fn new(verifier: DummyVerifier) -> Self {
+        let storage = Arc::new(BlockChainDatabase::init_test_chain(vec![
+            test_data::genesis().into(),
+        ]));
+        let peers = Arc::new(PeersImpl::default());
+        let sync_state =
+            SynchronizationStateRef::new(SynchronizationState::with_storage(storage.clone()));
+        let chain = Chain::new(storage.clone());
+        let executor = LatencyTaskExecutor::new(DummyTaskExecutor::new(), NETWORK_SEED);
+        let config = Config {
+            close_connection_on_bad_block: true,
+            block_stall_timeout_s: DEFAULT_BLOCK_STALL_TIMEOUT_S,
+            max_requested_blocks: DEFAULT_MAX_REQUESTED_BLOCKS,
+            header_branches: HeaderBranchesLimits::default(),
+            watch_only: false,
+            max_verification_queue: DEFAULT_MAX_VERIFICATION_QUEUE,
+            memory_budget: Default::default(),
+        };
+
+        let chain_verifier = Arc::new(ChainVerifier::new(storage.clone(), Network::Unitest));
+        let core = SynchronizationClientCore::new(
+            config,
+            sync_state,
+            peers.clone(),
+            executor.clone(),
+            chain,
+            chain_verifier.clone(),
+        );
+        core.lock().set_verify_headers(false);
+
+        let mut verifier = verifier;
+        verifier.set_sink(Arc::new(CoreVerificationSink::new(core.clone())));
+        verifier.set_storage(storage);
+        verifier.set_verifier(chain_verifier);
+        let client = SynchronizationClient::new(core.clone(), verifier);
+
+        let mut simulation = Simulation {
+            now_ms: 0,
+            next_seq: 0,
+            events: BTreeMap::new(),
+            scripted_peers: HashMap::new(),
+            connected: HashSet::new(),
+            peers: peers,
+            executor: executor,
+            core: core,
+            client: client,
+        };
+        simulation.schedule(MANAGEMENT_INTERVAL_MS, Event::Manage);
+        simulation
+    }
+
+    /// Connect scripted peer at given virtual time.
+    pub fn connect(&mut self, at_ms: u64, peer_index: PeerIndex, peer: ScriptedPeer) {
+        assert!(self.scripted_peers.insert(peer_index, peer).is_none());
+        let delay_ms = at_ms.saturating_sub(self.now_ms);
+        self.schedule(delay_ms, Event::Connect(peer_index));
+    }
+
+    /// Change conditions of the network path, which client tasks are passing to the peer.
+    pub fn set_network_conditions(&mut self, peer_index: PeerIndex, conditions: NetworkConditions) {
+        self.executor.set_conditions(peer_index, conditions);
+    }
+
+    /// Process all events and deliver all client tasks, scheduled before or at given virtual time.
+    pub fn run_until(&mut self, time_ms: u64) {
+        loop {
+            let next_event = self.events.keys().next().cloned();
+            let next_delivery_ms = self.executor.next_delivery_ms();
+            let next_ms = match (next_event, next_delivery_ms) {
+                (Some(key), Some(delivery_ms)) => min(key.0, delivery_ms),
+                (Some(key), None) => key.0,
+                (None, Some(delivery_ms)) => delivery_ms,
+                (None, None) => break,
+            };
+            if next_ms > time_ms {
+                break;
+            }
+
+            // tasks, delivered by the network, are processed before events, scheduled at the same time
+            self.advance_time(next_ms);
+            match next_event {
+                Some(key) if next_delivery_ms.map_or(true, |delivery_ms| key.0 < delivery_ms) => {
+                    let event = self
+                        .events
+                        .remove(&key)
+                        .expect("key is read from the map above");
+                    self.process_event(event);
+                }
+                _ => self.process_tasks(),
+            }
+            self.process_disconnects();
+        }
+
+        self.advance_time(time_ms);
+    }
+
+    /// Best block, stored by the client.
+    pub fn best_storage_block(&self) -> storage::BestBlock {
+        self.core.lock().chain().best_storage_block()
+    }
+
+    /// Is peer still connected to the client?
+    pub fn is_connected(&self, peer_index: PeerIndex) -> bool {
+        self.connected.contains(&peer_index)
+    }
+
+    fn advance_time(&mut self, time_ms: u64) {
+        self.now_ms = time_ms;
+        set_virtual_time(Some(self.virtual_time_s()));
+        self.executor.advance_to(time_ms);
+    }
+
+    fn virtual_time_s(&self) -> f64 {
+        SIMULATION_START_TIME_S + self.now_ms as f64 / 1000f64
+    }
+
+    fn schedule(&mut self, delay_ms: u64, event: Event) {
+        let seq = self.next_seq;
+        self.next_seq += 1;
+        self.events.insert((self.now_ms + delay_ms, seq), event);
+    }
+
+    fn process_event(&mut self, event: Event) {
+        match event {
+            Event::Connect(peer_index) => {
+                self.connected.insert(peer_index);
+                self.peers.insert(
+                    peer_index,
+                    Services::default(),
+                    DummyOutboundSyncConnection::new(),
+                );
+                self.client.on_connect(peer_index);
+            }
+            // messages of disconnected peers are dropped
+            Event::Message(peer_index, _) if !self.connected.contains(&peer_index) => (),
+            Event::Message(peer_index, PeerMessage::Headers(headers)) => {
+                self.client.on_headers(peer_index, headers)
+            }
+            Event::Message(peer_index, PeerMessage::Block(block)) => {
+                self.client.on_block(peer_index, block)
+            }
+            Event::Message(peer_index, PeerMessage::NotFound(notfound)) => {
+                self.client.on_notfound(peer_index, notfound)
+            }
+            Event::Manage => {
+                manage_synchronization(
+                    &mut self.core.lock(),
+                    &ManagePeersConfig::default(),
+                    &ManageUnknownBlocksConfig::default(),
+                );
+                self.schedule(MANAGEMENT_INTERVAL_MS, Event::Manage);
+            }
+        }
+
+        self.process_tasks();
+    }
+
+    /// Disconnect peers, which have been removed by the client (i.e. due to misbehavior).
+    fn process_disconnects(&mut self) {
+        let mut removed: Vec<_> = self
+            .connected
+            .iter()
+            .filter(|peer_index| self.peers.connection(**peer_index).is_none())
+            .cloned()
+            .collect();
+        removed.sort();
+
+        for peer_index in removed {
+            self.connected.remove(&peer_index);
+            self.client.on_disconnect(peer_index);
+            self.process_tasks();
+        }
+    }
+
+    /// Pass client tasks to the scripted peers.
+    fn process_tasks(&mut self) {
+        for task in self.executor.inner().take_tasks() {
+            match task {
+                Task::GetHeaders(peer_index, getheaders) => {
+                    self.respond_headers(peer_index, getheaders)
+                }
+                Task::GetData(peer_index, getdata) => self.respond_data(peer_index, getdata),
+                // scripted peers are not interested in our blocks
+                _ => (),
+            }
+        }
+    }
+
+    fn respond_headers(&mut self, peer_index: PeerIndex, getheaders: types::GetHeaders) {
+        let (latency_ms, headers) = match self.scripted_peers.get(&peer_index) {
+            Some(peer) => (
+                peer.latency_ms,
+                peer.headers_after(&getheaders.block_locator_hashes),
+            ),
+            None => return,
+        };
+
+        // empty headers messages are filtered by the incoming connection
+        if !headers.is_empty() {
+            self.schedule(
+                latency_ms,
+                Event::Message(peer_index, PeerMessage::Headers(headers)),
+            );
+        }
+    }
+
+    fn respond_data(&mut self, peer_index: PeerIndex, getdata: types::GetData) {
+        let (latency_ms, blocks_response, blocks, notfound) =
+            match self.scripted_peers.get(&peer_index) {
+                Some(peer) => {
+                    let mut blocks = Vec::new();
+                    let mut notfound = Vec::new();
+                    for item in getdata.inventory {
+                        match peer.block(&item.hash) {
+                            Some(block) if item.inv_type == InventoryType::MessageBlock => {
+                                blocks.push(block)
+                            }
+                            _ => notfound.push(InventoryVector::block(item.hash)),
+                        }
+                    }
+                    (peer.latency_ms, peer.blocks_response, blocks, notfound)
+                }
+                None => return,
+            };
+
+        let blocks_delay_ms = match blocks_response {
+            BlocksResponse::Deliver => Some(latency_ms),
+            BlocksResponse::Delay(delay_ms) => Some(latency_ms + delay_ms),
+            BlocksResponse::Withhold => None,
+        };
+        if let Some(blocks_delay_ms) = blocks_delay_ms {
+            for block in blocks {
+                self.schedule(
+                    blocks_delay_ms,
+                    Event::Message(peer_index, PeerMessage::Block(block)),
+                );
+            }
+        }
+        if !notfound.is_empty() {
+            self.schedule(
+                latency_ms,
+                Event::Message(
+                    peer_index,
+                    PeerMessage::NotFound(types::NotFound::with_inventory(notfound)),
+                ),
+            );
+        }
+    }
+}
+
+impl Drop for Simulation {
+    fn drop(&mut self) {
+        set_virtual_time(None);
+    }
+}
+
+fn indexed_blocks(blocks: Vec<Block>) -> Vec<IndexedBlock> {
+    blocks.into_iter().map(Into::into).collect()
+}
+
+#[test]
+fn simulation_synchronizes_with_single_peer() {
+    let chain = indexed_blocks(test_data::build_n_empty_blocks_from_genesis(10, 1));
+    let mut simulation = Simulation::new(DummyVerifier::default());
+    simulation.connect(0, 1, ScriptedPeer::new(chain.clone(), 100));
+
+    simulation.run_until(5_000);
+    let best_block = simulation.best_storage_block();
+    assert_eq!(best_block.number, 10);
+    assert_eq!(best_block.hash, *chain[9].hash());
+    assert!(simulation.is_connected(1));
+}
+
+#[test]
+fn simulation_switches_to_longest_of_parallel_forks() {
+    let genesis = test_data::genesis();
+    let common_block = test_data::block_builder()
+        .header()
+        .parent(genesis.hash())
+        .build()
+        .build();
+    let mut fork1 = vec![common_block.clone()];
+    fork1.extend(test_data::build_n_empty_blocks_from(
+        2,
+        100,
+        &common_block.block_header,
+    ));
+    let mut fork2 = vec![common_block.clone()];
+    fork2.extend(test_data::build_n_empty_blocks_from(
+        3,
+        200,
+        &common_block.block_header,
+    ));
+    let fork2 = indexed_blocks(fork2);
+
+    let mut simulation = Simulation::new(DummyVerifier::default());
+    simulation.connect(0, 1, ScriptedPeer::new(indexed_blocks(fork1), 50));
+    simulation.connect(0, 2, ScriptedPeer::new(fork2.clone(), 80));
+
+    simulation.run_until(5_000);
+    let best_block = simulation.best_storage_block();
+    assert_eq!(best_block.number, 4);
+    assert_eq!(best_block.hash, *fork2[3].hash());
+}
+
+#[test]
+fn simulation_disconnects_peer_providing_invalid_block() {
+    let bad_chain = indexed_blocks(test_data::build_n_empty_blocks_from_genesis(5, 100));
+    let good_chain = indexed_blocks(test_data::build_n_empty_blocks_from_genesis(5, 200));
+
+    let mut verifier = DummyVerifier::default();
+    verifier.error_when_verifying(bad_chain[2].hash().clone(), VerificationError::BadProof);
+    let mut simulation = Simulation::new(verifier);
+    simulation.connect(0, 1, ScriptedPeer::new(bad_chain.clone(), 100));
+
+    simulation.run_until(1_000);
+    assert!(!simulation.is_connected(1));
+    assert_eq!(simulation.best_storage_block().hash, *bad_chain[1].hash());
+
+    // honest peer is still able to switch us to its chain
+    simulation.connect(1_000, 2, ScriptedPeer::new(good_chain.clone(), 100));
+    simulation.run_until(5_000);
+    assert!(simulation.is_connected(2));
+    let best_block = simulation.best_storage_block();
+    assert_eq!(best_block.number, 5);
+    assert_eq!(best_block.hash, *good_chain[4].hash());
+}
+
+#[test]
+fn simulation_waits_for_delayed_blocks() {
+    let chain = indexed_blocks(test_data::build_n_empty_blocks_from_genesis(8, 1));
+    let mut simulation = Simulation::new(DummyVerifier::default());
+    simulation.connect(
+        0,
+        1,
+        ScriptedPeer::new(chain.clone(), 100).with_blocks_response(BlocksResponse::Delay(3_000)),
+    );
+
+    simulation.run_until(1_000);
+    assert_eq!(simulation.best_storage_block().number, 0);
+
+    // blocks are received before the request is considered failed
+    simulation.run_until(5_000);
+    assert!(simulation.is_connected(1));
+    assert_eq!(simulation.best_storage_block().hash, *chain[7].hash());
+}
+
+#[test]
+fn simulation_rerequests_blocks_withheld_by_peer() {
+    let chain = indexed_blocks(test_data::build_n_empty_blocks_from_genesis(8, 1));
+    let mut simulation = Simulation::new(DummyVerifier::default());
+    simulation.connect(
+        0,
+        1,
+        ScriptedPeer::new(chain.clone(), 50).with_blocks_response(BlocksResponse::Withhold),
+    );
+    simulation.connect(0, 2, ScriptedPeer::new(chain.clone(), 100));
+
+    // management round detects stalled request => peer is disconnected && blocks are requested from other peer
+    simulation.run_until(3 * MANAGEMENT_INTERVAL_MS);
+    assert!(!simulation.is_connected(1));
+    assert!(simulation.is_connected(2));
+    assert_eq!(simulation.best_storage_block().hash, *chain[7].hash());
+}
+
+#[test]
+fn simulation_synchronizes_over_jittery_network() {
+    let chain = indexed_blocks(test_data::build_n_empty_blocks_from_genesis(20, 1));
+    let mut simulation = Simulation::new(DummyVerifier::default());
+    simulation.set_network_conditions(
+        1,
+        NetworkConditions {
+            latency_ms: 100,
+            jitter_ms: 400,
+            reorder_rate: 0.3,
+            drop_rate: 0f64,
+        },
+    );
+    simulation.connect(0, 1, ScriptedPeer::new(chain.clone(), 100));
+
+    simulation.run_until(10_000);
+    assert!(simulation.is_connected(1));
+    assert_eq!(simulation.best_storage_block().hash, *chain[19].hash());
+}
+
+#[test]
+fn simulation_rerequests_blocks_lost_by_network() {
+    let chain = indexed_blocks(test_data::build_n_empty_blocks_from_genesis(8, 1));
+    let mut simulation = Simulation::new(DummyVerifier::default());
+    // every request to the first peer is lost
+    simulation.set_network_conditions(
+        1,
+        NetworkConditions {
+            drop_rate: 1f64,
+            ..Default::default()
+        },
+    );
+    simulation.connect(0, 1, ScriptedPeer::new(chain.clone(), 50));
+    simulation.connect(0, 2, ScriptedPeer::new(chain.clone(), 100));
+
+    simulation.run_until(5 * MANAGEMENT_INTERVAL_MS);
+    assert!(simulation.is_connected(2));
+    assert_eq!(simulation.best_storage_block().hash, *chain[7].hash());
+}
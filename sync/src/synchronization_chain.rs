@@ -1,8 +1,10 @@
 use chain::{IndexedBlock, IndexedBlockHeader};
+use primitives::bigint::U256;
 use primitives::bytes::Bytes;
 use primitives::hash::H256;
 use std::collections::{HashSet, VecDeque};
 use std::fmt;
+use std::time::{Duration, Instant};
 use storage;
 use types::{BlockHeight, StorageRef};
 use utils::{BestHeadersChain, BestHeadersChainInformation, HashPosition, HashQueueChain};
@@ -15,12 +17,27 @@ const REQUESTED_QUEUE: usize = 1;
 const SCHEDULED_QUEUE: usize = 2;
 /// Number of hash queues
 const NUMBER_OF_QUEUES: usize = 3;
+/// Max number of blocks below the best valid block, searched for the side branches
+const MAX_BRANCH_SEARCH_DEPTH: BlockHeight = 2048;
+/// Number of block locator entries, after which step between heights starts to grow
+const BLOCK_LOCATOR_DENSE_ENTRIES: usize = 10;
 
 /// Block insertion result
 #[derive(Default, PartialEq)]
 pub struct BlockInsertionResult {
     /// Hashes of blocks, which were canonized during this insertion procedure. Order matters
     pub canonized_blocks_hashes: Vec<H256>,
+    /// Hashes of blocks, which were decanonized during this insertion procedure. Ordered from oldest to newest
+    pub decanonized_blocks_hashes: Vec<H256>,
+}
+
+/// Time, spent on writing blocks to the storage
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct WriteTimings {
+    /// Time, spent on inserting blocks
+    pub insert: Duration,
+    /// Time, spent on canonizing blocks, including switching to forks
+    pub canonize: Duration,
 }
 
 impl fmt::Debug for BlockInsertionResult {
@@ -34,6 +51,14 @@ impl fmt::Debug for BlockInsertionResult {
                     .map(H256::reversed)
                     .collect::<Vec<_>>(),
             )
+            .field(
+                "decanonized_blocks_hashes",
+                &self
+                    .decanonized_blocks_hashes
+                    .iter()
+                    .map(H256::reversed)
+                    .collect::<Vec<_>>(),
+            )
             .finish()
     }
 }
@@ -43,6 +68,7 @@ impl BlockInsertionResult {
     pub fn with_canonized_blocks(canonized_blocks_hashes: Vec<H256>) -> Self {
         BlockInsertionResult {
             canonized_blocks_hashes: canonized_blocks_hashes,
+            decanonized_blocks_hashes: Vec::new(),
         }
     }
 }
@@ -96,6 +122,12 @@ pub struct Chain {
     headers_chain: BestHeadersChain,
     /// Blocks that have been marked as dead-ends
     dead_end_blocks: HashSet<H256>,
+    /// Stored blocks that have been manually invalidated (with all their stored descendants)
+    invalidated_blocks: HashSet<H256>,
+    /// Max number of canon blocks, which could be reorganized. Deeper blocks are final
+    max_reorg_depth: Option<BlockHeight>,
+    /// Time, spent on writing blocks since the last `take_write_timings` call
+    write_timings: WriteTimings,
 }
 
 impl BlockState {
@@ -127,6 +159,7 @@ impl Chain {
             .expect("storage with genesis block is required");
         let best_storage_block = storage.best_block();
         let best_storage_block_hash = best_storage_block.hash.clone();
+        let invalidated_blocks = storage.invalidated_blocks().into_iter().collect();
 
         Chain {
             genesis_block_hash: genesis_block_hash,
@@ -135,9 +168,23 @@ impl Chain {
             hash_chain: HashQueueChain::with_number_of_queues(NUMBER_OF_QUEUES),
             headers_chain: BestHeadersChain::new(best_storage_block_hash),
             dead_end_blocks: HashSet::new(),
+            invalidated_blocks: invalidated_blocks,
+            max_reorg_depth: None,
+            write_timings: WriteTimings::default(),
         }
     }
 
+    /// Limit number of canon blocks, which could be reorganized
+    pub fn with_max_reorg_depth(mut self, max_reorg_depth: Option<BlockHeight>) -> Self {
+        self.max_reorg_depth = max_reorg_depth;
+        self
+    }
+
+    /// Take time, spent on writing blocks since the last call
+    pub fn take_write_timings(&mut self) -> WriteTimings {
+        ::std::mem::replace(&mut self.write_timings, WriteTimings::default())
+    }
+
     /// Get information on current blockchain state
     pub fn information(&self) -> Information {
         Information {
@@ -204,6 +251,67 @@ impl Chain {
         }
     }
 
+    /// Get latest finalized block (None if reorganizations depth is not limited)
+    pub fn finalized_block(&self) -> Option<storage::BestBlock> {
+        let max_reorg_depth = self.max_reorg_depth?;
+        let number = self
+            .best_storage_block
+            .number
+            .saturating_sub(max_reorg_depth);
+        self.storage
+            .block_hash(number)
+            .map(|hash| storage::BestBlock {
+                number: number,
+                hash: hash,
+            })
+    }
+
+    /// Check that block, built on top of given stored block, won't reorganize finalized blocks
+    pub fn verify_fork_point(&self, parent_hash: &H256) -> Result<(), storage::Error> {
+        if self.max_reorg_depth.is_none() {
+            return Ok(());
+        }
+
+        match self.canon_ancestor(parent_hash) {
+            Some((ancestor, _)) => self.verify_fork_ancestor(ancestor),
+            // parent is not stored yet => it is checked when parent is inserted
+            None => Ok(()),
+        }
+    }
+
+    /// Get number of the stored block, which could be either canon or side chain block
+    pub fn stored_block_number(&self, hash: &H256) -> Option<BlockHeight> {
+        self.canon_ancestor(hash)
+            .map(|(ancestor, distance)| ancestor + distance)
+    }
+
+    /// Find the canon ancestor of the stored block. Returns ancestor number and distance to it
+    fn canon_ancestor(&self, hash: &H256) -> Option<(BlockHeight, BlockHeight)> {
+        let mut hash = *hash;
+        let mut distance = 0;
+        loop {
+            if let Some(number) = self.storage.block_number(&hash) {
+                return Some((number, distance));
+            }
+            hash = self
+                .storage
+                .block_header(storage::BlockRef::Hash(hash))?
+                .raw
+                .previous_header_hash;
+            distance += 1;
+        }
+    }
+
+    /// Check that canon chain could be reorganized starting from given ancestor
+    fn verify_fork_ancestor(&self, ancestor: BlockHeight) -> Result<(), storage::Error> {
+        match self.finalized_block() {
+            Some(ref finalized) if ancestor < finalized.number => {
+                Err(storage::Error::FinalizedFork(finalized.number))
+            }
+            _ => Ok(()),
+        }
+    }
+
     /// Get block header by hash
     pub fn block_hash(&self, number: BlockHeight) -> Option<H256> {
         if number <= self.best_storage_block.number {
@@ -247,7 +355,9 @@ impl Chain {
         match self.hash_chain.contains_in(hash) {
             Some(queue_index) => BlockState::from_queue_index(queue_index),
             None => {
-                if self.storage.contains_block(storage::BlockRef::Hash(*hash)) {
+                if self.invalidated_blocks.contains(hash) {
+                    BlockState::DeadEnd
+                } else if self.storage.contains_block(storage::BlockRef::Hash(*hash)) {
                     BlockState::Stored
                 } else if self.dead_end_blocks.contains(hash) {
                     BlockState::DeadEnd
@@ -344,8 +454,12 @@ impl Chain {
             }
             // case 1: block has been added to the main branch
             storage::BlockOrigin::CanonChain { .. } => {
+                let started = Instant::now();
                 self.storage.insert(block.clone())?;
+                let inserted = Instant::now();
                 self.storage.canonize(block.hash())?;
+                self.write_timings.insert += inserted - started;
+                self.write_timings.canonize += inserted.elapsed();
 
                 // remember new best block hash
                 self.best_storage_block = self.storage.as_store().best_block();
@@ -360,14 +474,20 @@ impl Chain {
 
                 Ok(BlockInsertionResult {
                     canonized_blocks_hashes: vec![*block.hash()],
+                    decanonized_blocks_hashes: Vec::new(),
                 })
             }
             // case 2: block has been added to the side branch with reorganization to this branch
             storage::BlockOrigin::SideChainBecomingCanonChain(origin) => {
+                self.verify_fork_ancestor(origin.ancestor)?;
                 let fork = self.storage.fork(origin.clone())?;
+                let started = Instant::now();
                 fork.store().insert(block.clone())?;
+                let inserted = Instant::now();
                 fork.store().canonize(block.hash())?;
                 self.storage.switch_to_fork(fork)?;
+                self.write_timings.insert += inserted - started;
+                self.write_timings.canonize += inserted.elapsed();
 
                 // remember new best block hash
                 self.best_storage_block = self.storage.best_block();
@@ -381,6 +501,7 @@ impl Chain {
                 canonized_blocks_hashes.push(*block.hash());
                 let result = BlockInsertionResult {
                     canonized_blocks_hashes: canonized_blocks_hashes,
+                    decanonized_blocks_hashes: origin.decanonized_route,
                 };
 
                 trace!(target: "sync", "result: {:?}", result);
@@ -388,9 +509,12 @@ impl Chain {
                 Ok(result)
             }
             // case 3: block has been added to the side branch without reorganization to this branch
-            storage::BlockOrigin::SideChain(_origin) => {
+            storage::BlockOrigin::SideChain(origin) => {
+                self.verify_fork_ancestor(origin.ancestor)?;
                 let block_hash = block.hash().clone();
+                let started = Instant::now();
                 self.storage.insert(block)?;
+                self.write_timings.insert += started.elapsed();
 
                 // remove inserted block + handle possible reorganization in headers chain
                 // TODO: mk, not sure if it's needed here at all
@@ -404,6 +528,205 @@ impl Chain {
         }
     }
 
+    /// Mark stored block and all its stored descendants invalid. Marks are saved in the storage.
+    /// If the best block becomes invalid, switches to the best remaining valid branch.
+    /// Marks are removed if the branch can't be switched
+    pub fn invalidate_block(
+        &mut self,
+        hash: &H256,
+    ) -> Result<BlockInsertionResult, storage::Error> {
+        let mut newly_invalidated = Vec::new();
+        let mut invalidation_queue: VecDeque<H256> = VecDeque::new();
+        invalidation_queue.push_back(*hash);
+        while let Some(hash) = invalidation_queue.pop_front() {
+            invalidation_queue.extend(self.storage.block_children(&hash));
+            if self.invalidated_blocks.insert(hash) {
+                newly_invalidated.push(hash);
+            }
+        }
+
+        let result = self
+            .save_invalidated_blocks()
+            .and_then(|_| self.activate_best_branch());
+        if result.is_err() {
+            for hash in &newly_invalidated {
+                self.invalidated_blocks.remove(hash);
+            }
+            // the original error is reported, even if marks can't be restored in the storage
+            let _ = self.save_invalidated_blocks();
+        }
+        result
+    }
+
+    /// Remove invalidity mark from the block, its descendants and its ancestors.
+    /// Switches to the best valid branch, which could now contain reconsidered blocks
+    pub fn reconsider_block(
+        &mut self,
+        hash: &H256,
+    ) -> Result<BlockInsertionResult, storage::Error> {
+        let mut reconsider_queue: VecDeque<H256> = VecDeque::new();
+        reconsider_queue.push_back(*hash);
+        while let Some(hash) = reconsider_queue.pop_front() {
+            reconsider_queue.extend(self.storage.block_children(&hash));
+            self.invalidated_blocks.remove(&hash);
+        }
+
+        let mut parent_hash = self
+            .storage
+            .block_header(storage::BlockRef::Hash(*hash))
+            .map(|header| header.raw.previous_header_hash);
+        while let Some(hash) = parent_hash {
+            if !self.invalidated_blocks.remove(&hash) {
+                break;
+            }
+            parent_hash = self
+                .storage
+                .block_header(storage::BlockRef::Hash(hash))
+                .map(|header| header.raw.previous_header_hash);
+        }
+
+        self.save_invalidated_blocks()?;
+        self.activate_best_branch()
+    }
+
+    /// Write invalidity marks to the storage, so blocks stay invalid after restart
+    fn save_invalidated_blocks(&self) -> Result<(), storage::Error> {
+        let blocks: Vec<H256> = self.invalidated_blocks.iter().cloned().collect();
+        self.storage.set_invalidated_blocks(&blocks)
+    }
+
+    /// Switch storage to the branch with the most chain work, which has no invalidated blocks
+    fn activate_best_branch(&mut self) -> Result<BlockInsertionResult, storage::Error> {
+        let best_block = self.storage.best_block();
+
+        // find best canon block, which is still valid (genesis block can't be invalidated).
+        // Canon blocks below the tail of the imported verifier state are unknown,
+        // so the chain can't be rolled back below the tail
+        let mut base = best_block.clone();
+        while base.number != 0 && self.invalidated_blocks.contains(&base.hash) {
+            base.number -= 1;
+            base.hash = self
+                .storage
+                .block_hash(base.number)
+                .ok_or(storage::Error::CannotDecanonize)?;
+        }
+
+        // search for the valid tip with the most work among branches, forked from recent canon
+        // blocks. The search stops at the first unknown canon block
+        let mut best_tip = base.clone();
+        let mut best_work = self.block_chain_work(&base.hash);
+        let search_from = base.number.saturating_sub(MAX_BRANCH_SEARCH_DEPTH);
+        for number in (search_from..base.number + 1).rev() {
+            let ancestor_hash = match self.storage.block_hash(number) {
+                Some(hash) => hash,
+                None => break,
+            };
+            for child in self.storage.block_children(&ancestor_hash) {
+                if number + 1 <= base.number && self.storage.block_number(&child).is_some() {
+                    continue;
+                }
+                if let Some((tip, work)) = self.best_valid_descendant(child, number + 1) {
+                    if work > best_work {
+                        best_tip = tip;
+                        best_work = work;
+                    }
+                }
+            }
+        }
+
+        if best_tip.hash == best_block.hash {
+            return Ok(BlockInsertionResult::default());
+        }
+
+        // route from the new tip back to the canon chain
+        let mut canonized_route = Vec::new();
+        let mut route_hash = best_tip.hash;
+        let ancestor = loop {
+            if let Some(number) = self.storage.block_number(&route_hash) {
+                break number;
+            }
+            canonized_route.push(route_hash);
+            route_hash = self
+                .storage
+                .block_header(storage::BlockRef::Hash(route_hash))
+                .expect("side chain blocks are stored with their parents; qed")
+                .raw
+                .previous_header_hash;
+        };
+        canonized_route.reverse();
+        let decanonized_route: Vec<H256> = (ancestor + 1..best_block.number + 1)
+            .filter_map(|number| self.storage.block_hash(number))
+            .collect();
+
+        let origin = storage::SideChainOrigin {
+            ancestor: ancestor,
+            canonized_route: canonized_route.clone(),
+            decanonized_route: decanonized_route.clone(),
+            block_number: best_tip.number,
+        };
+        let fork = self.storage.fork(origin)?;
+        self.storage.switch_to_fork(fork)?;
+        self.best_storage_block = self.storage.best_block();
+        assert_eq!(self.best_storage_block.hash, best_tip.hash);
+
+        // scheduled and requested blocks could be built on top of the invalidated branch
+        self.forget_all_blocks_with_state(BlockState::Requested);
+        self.forget_all_blocks_with_state(BlockState::Scheduled);
+        self.headers_chain.block_inserted_to_storage(
+            &self.best_storage_block.hash,
+            &self.best_storage_block.hash,
+        );
+
+        let result = BlockInsertionResult {
+            canonized_blocks_hashes: canonized_route,
+            decanonized_blocks_hashes: decanonized_route,
+        };
+        trace!(target: "sync", "activate_best_branch result: {:?}", result);
+
+        Ok(result)
+    }
+
+    /// Find the stored descendant of the block with the most chain work, which is not invalidated
+    fn best_valid_descendant(
+        &self,
+        hash: H256,
+        number: BlockHeight,
+    ) -> Option<(storage::BestBlock, U256)> {
+        if self.invalidated_blocks.contains(&hash) {
+            return None;
+        }
+
+        let mut best = storage::BestBlock {
+            number: number,
+            hash: hash,
+        };
+        let mut best_work = self.block_chain_work(&hash);
+        let mut search_stack = vec![(hash, number)];
+        while let Some((hash, number)) = search_stack.pop() {
+            for child in self.storage.block_children(&hash) {
+                if self.invalidated_blocks.contains(&child) {
+                    continue;
+                }
+                let work = self.block_chain_work(&child);
+                if work > best_work {
+                    best = storage::BestBlock {
+                        number: number + 1,
+                        hash: child,
+                    };
+                    best_work = work;
+                }
+                search_stack.push((child, number + 1));
+            }
+        }
+        Some((best, best_work))
+    }
+
+    /// Chain work of the stored block. Work of blocks below the imported verifier state is unknown,
+    /// so they're never preferred
+    fn block_chain_work(&self, hash: &H256) -> U256 {
+        self.storage.chain_work(hash).unwrap_or_else(U256::zero)
+    }
+
     /// Forget in-memory block
     pub fn forget_block(&mut self, hash: &H256) -> HashPosition {
         self.headers_chain.remove(hash);
@@ -487,7 +810,7 @@ impl Chain {
             let block_hash = self.hash_chain[index].clone();
             hashes.push(block_hash);
 
-            if hashes.len() >= 10 {
+            if hashes.len() >= BLOCK_LOCATOR_DENSE_ENTRIES {
                 step <<= 1;
             }
             if index < step {
@@ -505,13 +828,12 @@ impl Chain {
         hashes: &mut Vec<H256>,
     ) {
         loop {
-            let block_hash = self
-                .storage
-                .block_hash(index)
-                .expect("private function; index calculated in `block_locator_hashes`; qed");
-            hashes.push(block_hash);
+            // blocks between checkpoints of the imported verifier state are unknown
+            if let Some(block_hash) = self.storage.block_hash(index) {
+                hashes.push(block_hash);
+            }
 
-            if hashes.len() >= 10 {
+            if hashes.len() >= BLOCK_LOCATOR_DENSE_ENTRIES {
                 step <<= 1;
             }
             if index < step {
@@ -527,6 +849,43 @@ impl Chain {
     }
 }
 
+/// Heights of blocks, included in the block locator of the chain with given best block height,
+/// as described in protocol documentation: https://en.bitcoin.it/wiki/Protocol_documentation#getblocks
+/// Steps between heights are growing exponentially after first 10 blocks. Genesis is always included.
+pub fn block_locator_heights(best_block_height: BlockHeight) -> Vec<BlockHeight> {
+    let mut heights = Vec::new();
+    let mut height = best_block_height;
+    let mut step = 1;
+    loop {
+        heights.push(height);
+
+        if heights.len() >= BLOCK_LOCATOR_DENSE_ENTRIES {
+            step <<= 1;
+        }
+        if height < step {
+            // always include genesis height
+            if height != 0 {
+                heights.push(0);
+            }
+
+            return heights;
+        }
+        height -= step;
+    }
+}
+
+/// Build block locator of the canonical chain, ending at the block with given height.
+/// Resulting hashes could be passed to `getheaders` or `getblocks` as is.
+pub fn build_block_locator(
+    storage: &dyn storage::BlockProvider,
+    best_block_height: BlockHeight,
+) -> Vec<H256> {
+    block_locator_heights(best_block_height)
+        .into_iter()
+        .filter_map(|height| storage.block_hash(height))
+        .collect()
+}
+
 impl storage::BlockHeaderProvider for Chain {
     fn block_header_bytes(&self, block_ref: storage::BlockRef) -> Option<Bytes> {
         use ser::serialize;
@@ -601,11 +960,13 @@ impl fmt::Debug for Chain {
 mod tests {
     extern crate test_data;
 
-    use super::{BlockState, Chain};
-    use chain::IndexedBlockHeader;
+    use super::{block_locator_heights, build_block_locator, BlockState, Chain};
+    use chain::{IndexedBlock, IndexedBlockHeader};
     use db::BlockChainDatabase;
+    use primitives::compact::Compact;
     use primitives::hash::H256;
     use std::sync::Arc;
+    use storage;
     use utils::HashPosition;
 
     #[test]
@@ -733,6 +1094,38 @@ mod tests {
         assert_eq!(db.best_block().number, 1);
     }
 
+    #[test]
+    fn block_locator_heights_back_off_exponentially() {
+        assert_eq!(block_locator_heights(0), vec![0]);
+        assert_eq!(block_locator_heights(5), vec![5, 4, 3, 2, 1, 0]);
+        assert_eq!(
+            block_locator_heights(100),
+            vec![100, 99, 98, 97, 96, 95, 94, 93, 92, 91, 89, 85, 77, 61, 29, 0]
+        );
+    }
+
+    #[test]
+    fn build_block_locator_of_stored_chain() {
+        let blocks = test_data::build_n_empty_blocks_from_genesis(3, 0);
+        let mut stored_blocks: Vec<IndexedBlock> = vec![test_data::genesis().into()];
+        stored_blocks.extend(blocks.iter().cloned().map(Into::into));
+        let db = BlockChainDatabase::init_test_chain(stored_blocks);
+
+        assert_eq!(
+            build_block_locator(&db, 3),
+            vec![
+                blocks[2].hash(),
+                blocks[1].hash(),
+                blocks[0].hash(),
+                test_data::genesis().hash(),
+            ]
+        );
+        assert_eq!(
+            build_block_locator(&db, 1),
+            vec![blocks[0].hash(), test_data::genesis().hash()]
+        );
+    }
+
     #[test]
     fn chain_block_locator_hashes() {
         let db = Arc::new(BlockChainDatabase::init_test_chain(vec![
@@ -845,4 +1238,185 @@ mod tests {
             ]
         );
     }
+
+    #[test]
+    fn chain_insert_best_block_reports_decanonized_blocks() {
+        let db = Arc::new(BlockChainDatabase::init_test_chain(vec![
+            test_data::genesis().into(),
+        ]));
+        let mut chain = Chain::new(db);
+
+        let canon = test_data::build_n_empty_blocks_from_genesis(1, 0);
+        let fork = test_data::build_n_empty_blocks_from_genesis(2, 100);
+        let result = chain
+            .insert_best_block(canon[0].clone().into())
+            .expect("Error inserting new block");
+        assert!(result.decanonized_blocks_hashes.is_empty());
+        let result = chain
+            .insert_best_block(fork[0].clone().into())
+            .expect("Error inserting new block");
+        assert!(result.canonized_blocks_hashes.is_empty());
+
+        let result = chain
+            .insert_best_block(fork[1].clone().into())
+            .expect("Error inserting new block");
+        assert_eq!(
+            result.canonized_blocks_hashes,
+            vec![fork[0].hash(), fork[1].hash()]
+        );
+        assert_eq!(result.decanonized_blocks_hashes, vec![canon[0].hash()]);
+    }
+    #[test]
+    fn chain_invalidate_and_reconsider_block() {
+        let db = Arc::new(BlockChainDatabase::init_test_chain(vec![
+            test_data::genesis().into(),
+        ]));
+        let mut chain = Chain::new(db.clone());
+
+        let canon = test_data::build_n_empty_blocks_from_genesis(2, 0);
+        let fork = test_data::build_n_empty_blocks_from_genesis(1, 100);
+        for block in canon.iter().chain(fork.iter()) {
+            chain
+                .insert_best_block(block.clone().into())
+                .expect("Error inserting new block");
+        }
+        assert_eq!(db.best_block().hash, canon[1].hash());
+
+        // invalidating first canon block activates the side branch
+        let result = chain
+            .invalidate_block(&canon[0].hash())
+            .expect("Error invalidating block");
+        assert_eq!(result.canonized_blocks_hashes, vec![fork[0].hash()]);
+        assert_eq!(
+            result.decanonized_blocks_hashes,
+            vec![canon[0].hash(), canon[1].hash()]
+        );
+        assert_eq!(db.best_block().hash, fork[0].hash());
+        assert_eq!(chain.best_storage_block().hash, fork[0].hash());
+        assert_eq!(chain.block_state(&canon[0].hash()), BlockState::DeadEnd);
+        assert_eq!(chain.block_state(&canon[1].hash()), BlockState::DeadEnd);
+        assert_eq!(chain.block_state(&fork[0].hash()), BlockState::Stored);
+
+        // marks are restored from the storage after restart
+        let mut chain = Chain::new(db.clone());
+        assert_eq!(chain.block_state(&canon[0].hash()), BlockState::DeadEnd);
+        assert_eq!(chain.block_state(&canon[1].hash()), BlockState::DeadEnd);
+
+        // reconsidering the descendant also reconsiders its invalidated ancestor
+        let result = chain
+            .reconsider_block(&canon[1].hash())
+            .expect("Error reconsidering block");
+        assert_eq!(
+            result.canonized_blocks_hashes,
+            vec![canon[0].hash(), canon[1].hash()]
+        );
+        assert_eq!(result.decanonized_blocks_hashes, vec![fork[0].hash()]);
+        assert_eq!(db.best_block().hash, canon[1].hash());
+        assert_eq!(chain.block_state(&canon[0].hash()), BlockState::Stored);
+        assert_eq!(chain.block_state(&canon[1].hash()), BlockState::Stored);
+        assert!(db.invalidated_blocks().is_empty());
+    }
+
+    #[test]
+    fn chain_invalidate_block_activates_branch_with_most_work() {
+        let db = Arc::new(BlockChainDatabase::init_test_chain(vec![
+            test_data::genesis().into(),
+        ]));
+        let mut chain = Chain::new(db.clone());
+
+        let mut heavy = test_data::build_n_empty_blocks_from_genesis(1, 200);
+        heavy[0].block_header.bits = Compact::new(0x1f7fffff);
+        heavy.extend(test_data::build_n_empty_blocks_from(
+            1,
+            300,
+            &heavy[0].block_header,
+        ));
+        let long = test_data::build_n_empty_blocks_from_genesis(3, 100);
+        for block in heavy.iter().chain(long.iter()) {
+            chain
+                .insert_best_block(block.clone().into())
+                .expect("Error inserting new block");
+        }
+        assert_eq!(db.best_block().hash, heavy[1].hash());
+
+        // longer side branch has less work than the remaining canon block
+        let result = chain
+            .invalidate_block(&heavy[1].hash())
+            .expect("Error invalidating block");
+        assert!(result.canonized_blocks_hashes.is_empty());
+        assert_eq!(result.decanonized_blocks_hashes, vec![heavy[1].hash()]);
+        assert_eq!(db.best_block().hash, heavy[0].hash());
+    }
+
+    #[test]
+    fn chain_invalidate_block_of_imported_verifier_state() {
+        let canon = test_data::build_n_empty_blocks_from_genesis(4, 0);
+        let mut blocks = vec![test_data::genesis().into()];
+        blocks.extend(canon.iter().cloned().map(Into::into));
+        let state = BlockChainDatabase::init_test_chain(blocks).verifier_state(2, 1000);
+        assert_eq!(state.tail_number, 3);
+
+        let db = Arc::new(BlockChainDatabase::init_test_chain(vec![
+            test_data::genesis().into(),
+        ]));
+        db.import_verifier_state(state)
+            .expect("Error importing verifier state");
+        let mut chain = Chain::new(db.clone());
+
+        // branch search stops at the unknown canon block below the tail
+        let result = chain
+            .invalidate_block(&canon[3].hash())
+            .expect("Error invalidating block");
+        assert_eq!(result.decanonized_blocks_hashes, vec![canon[3].hash()]);
+        assert_eq!(db.best_block().hash, canon[2].hash());
+
+        // chain can't be rolled back below the tail, so the block stays valid
+        assert_eq!(
+            chain.invalidate_block(&canon[2].hash()),
+            Err(storage::Error::CannotDecanonize)
+        );
+        assert_eq!(db.best_block().hash, canon[2].hash());
+        assert_eq!(chain.block_state(&canon[2].hash()), BlockState::Stored);
+        assert_eq!(chain.block_state(&canon[3].hash()), BlockState::DeadEnd);
+        assert_eq!(db.invalidated_blocks(), vec![canon[3].hash()]);
+    }
+
+    #[test]
+    fn chain_rejects_fork_below_finalized_block() {
+        let db = Arc::new(BlockChainDatabase::init_test_chain(vec![
+            test_data::genesis().into(),
+        ]));
+        let mut chain = Chain::new(db.clone());
+        assert_eq!(chain.finalized_block(), None);
+        chain = chain.with_max_reorg_depth(Some(2));
+        assert_eq!(chain.finalized_block().unwrap().number, 0);
+
+        let canon = test_data::build_n_empty_blocks_from_genesis(3, 0);
+        let fork = test_data::build_n_empty_blocks_from_genesis(1, 100);
+        for block in &canon {
+            chain
+                .insert_best_block(block.clone().into())
+                .expect("Error inserting new block");
+        }
+        assert_eq!(
+            chain.finalized_block(),
+            Some(storage::BestBlock {
+                number: 1,
+                hash: canon[0].hash(),
+            })
+        );
+
+        // forks from the finalized block are still allowed
+        assert_eq!(chain.verify_fork_point(&canon[0].hash()), Ok(()));
+        assert_eq!(
+            chain.verify_fork_point(&test_data::genesis().hash()),
+            Err(storage::Error::FinalizedFork(1))
+        );
+        assert_eq!(
+            chain.insert_best_block(fork[0].clone().into()),
+            Err(storage::Error::FinalizedFork(1))
+        );
+        assert_eq!(db.best_block().hash, canon[2].hash());
+        assert_eq!(chain.block_state(&fork[0].hash()), BlockState::Unknown);
+    }
 }
@@ -1,11 +1,15 @@
 use chain::{IndexedBlock, IndexedBlockHeader};
 use message::types;
 use parking_lot::Mutex;
+use primitives::hash::H256;
 use std::sync::Arc;
+use storage;
 use synchronization_client_core::{ClientCore, SynchronizationClientCore};
 use synchronization_executor::TaskExecutor;
-use synchronization_verifier::Verifier;
+use synchronization_verifier::{VerificationError, Verifier};
 use types::{ClientCoreRef, EmptyBoxFuture, PeerIndex, SyncListenerRef};
+use utils::OrphanBlockEntry;
+use Error;
 
 #[cfg_attr(feature = "cargo-clippy", allow(doc_markdown))]
 ///! TODO: update with headers-first corrections
@@ -129,6 +133,11 @@ pub trait Client: Send + Sync + 'static {
     fn on_notfound(&self, peer_index: PeerIndex, message: types::NotFound);
     fn after_peer_nearly_blocks_verified(&self, peer_index: PeerIndex, future: EmptyBoxFuture);
     fn install_sync_listener(&self, listener: SyncListenerRef);
+    fn invalidate_block(&self, hash: &H256) -> Result<(), Error>;
+    fn reconsider_block(&self, hash: &H256) -> Result<(), Error>;
+    fn finalized_block(&self) -> Option<storage::BestBlock>;
+    fn orphan_blocks(&self) -> Vec<OrphanBlockEntry>;
+    fn block_rejection(&self, hash: &H256) -> Option<VerificationError>;
 }
 
 /// Synchronization client facade
@@ -201,6 +210,26 @@ where
     fn install_sync_listener(&self, listener: SyncListenerRef) {
         self.core.lock().install_sync_listener(listener);
     }
+
+    fn invalidate_block(&self, hash: &H256) -> Result<(), Error> {
+        self.core.lock().invalidate_block(hash)
+    }
+
+    fn reconsider_block(&self, hash: &H256) -> Result<(), Error> {
+        self.core.lock().reconsider_block(hash)
+    }
+
+    fn finalized_block(&self) -> Option<storage::BestBlock> {
+        self.core.lock().chain().finalized_block()
+    }
+
+    fn orphan_blocks(&self) -> Vec<OrphanBlockEntry> {
+        self.core.lock().orphaned_blocks_pool().entries()
+    }
+
+    fn block_rejection(&self, hash: &H256) -> Option<VerificationError> {
+        self.core.lock().block_rejection(hash)
+    }
 }
 
 impl<T, U> SynchronizationClient<T, U>
@@ -1,5 +1,6 @@
 use chain::{IndexedBlock, IndexedBlockHeader};
 use futures::Future;
+use linked_hash_map::LinkedHashMap;
 use message::common::{InventoryType, InventoryVector};
 use message::types;
 use parking_lot::Mutex;
@@ -8,27 +9,36 @@ use std::cmp::{max, min};
 use std::collections::hash_map::Entry;
 use std::collections::{HashMap, HashSet, VecDeque};
 use std::sync::Arc;
+use storage;
 #[cfg(test)]
 use synchronization_chain::Information as ChainInformation;
 use synchronization_chain::{BlockInsertionResult, BlockState, Chain};
 use synchronization_executor::{Task, TaskExecutor};
-use synchronization_manager::ManagementWorker;
+use synchronization_manager::{ManagePeersConfig, ManagementWorker};
 #[cfg(test)]
 use synchronization_peers_tasks::Information as PeersTasksInformation;
 use synchronization_peers_tasks::PeersTasks;
-use synchronization_verifier::{BlockVerificationSink, VerificationSink, VerificationTask};
-use time::precise_time_s;
+use synchronization_verifier::{
+    BlockVerificationSink, VerificationError, VerificationSink, VerificationTask,
+};
+use tracing::{debug, info, trace, warn};
 use types::{
-    BlockHeight, ClientCoreRef, EmptyBoxFuture, PeerIndex, PeersRef, SyncListenerRef,
-    SynchronizationStateRef,
+    BlockHeight, ClientCoreRef, EmptyBoxFuture, MemoryBudgetRef, PeerIndex, PeersRef,
+    SyncListenerRef, SynchronizationStateRef,
+};
+use utils::{
+    precise_time_s, AverageSpeedMeter, HashPosition, HeaderBranches, HeaderBranchesLimits,
+    HeadersRequests, HeadersResponse, MemoryConsumer, OrphanBlocksPool, PropagationStage,
+    StaleBlock, TimestampAlert, TimestampStats,
 };
-use utils::{AverageSpeedMeter, HashPosition, MessageBlockHeadersProvider, OrphanBlocksPool};
+use verification::bigint::U256;
 use verification::BackwardsCompatibleChainVerifier as ChainVerifier;
+use Error;
 
 /// Approximate maximal number of blocks hashes in scheduled queue.
 const MAX_SCHEDULED_HASHES: BlockHeight = 4 * 1024;
-/// Approximate maximal number of blocks hashes in requested queue.
-const MAX_REQUESTED_BLOCKS: BlockHeight = 256;
+/// Default approximate maximal number of blocks hashes in requested queue (download window).
+pub const DEFAULT_MAX_REQUESTED_BLOCKS: BlockHeight = 256;
 /// Approximate maximal number of blocks in verifying queue.
 const MAX_VERIFYING_BLOCKS: BlockHeight = 256;
 /// Minimum number of blocks to request from peer
@@ -49,6 +59,12 @@ const MIN_BLOCK_DUPLICATION_INTERVAL_S: f64 = 10_f64;
 const MAX_BLOCKS_IN_DUPLICATE_REQUEST: BlockHeight = 4;
 /// Minimal number of blocks in duplicate requests.
 const MIN_BLOCKS_IN_DUPLICATE_REQUEST: BlockHeight = 8;
+/// Default number of verifying blocks, after which new blocks are not requested.
+pub const DEFAULT_MAX_VERIFICATION_QUEUE: BlockHeight = 1024;
+/// Maximal number of unsolicited blocks, deferred until verification queue is drained.
+const MAX_DEFERRED_BLOCKS: usize = 256;
+/// Max number of recently rejected blocks, which rejection reasons are remembered
+const MAX_REJECTED_BLOCKS: usize = 256;
 
 /// Information on current synchronization state.
 #[cfg(test)]
@@ -62,13 +78,15 @@ pub struct Information {
     pub chain: ChainInformation,
     /// Number of currently orphaned blocks.
     pub orphaned_blocks: usize,
+    /// Number of blocks, deferred until verification queue is drained.
+    pub deferred_blocks: usize,
 }
 
 /// Synchronization client trait
 pub trait ClientCore {
     fn on_connect(&mut self, peer_index: PeerIndex);
     fn on_disconnect(&mut self, peer_index: PeerIndex);
-    fn on_inventory(&self, peer_index: PeerIndex, message: types::Inv);
+    fn on_inventory(&mut self, peer_index: PeerIndex, message: types::Inv);
     fn on_headers(&mut self, peer_index: PeerIndex, message: Vec<IndexedBlockHeader>);
     fn on_block(
         &mut self,
@@ -91,6 +109,21 @@ pub trait ClientCore {
 pub struct Config {
     /// If true, connection to peer who has provided us with bad block is closed
     pub close_connection_on_bad_block: bool,
+    /// Peer, which has not delivered requested block in this number of seconds, is stalling
+    pub block_stall_timeout_s: u32,
+    /// Approximate maximal number of blocks, requested from peers at once (download window)
+    pub max_requested_blocks: BlockHeight,
+    /// Limits of headers-only side branches, accepted from peers
+    pub header_branches: HeaderBranchesLimits,
+    /// If true, only headers are synchronized and blocks announcements are recorded.
+    /// Blocks are neither requested, nor verified
+    pub watch_only: bool,
+    /// When verification queue has this number of blocks, new blocks are not requested
+    /// and unsolicited blocks are deferred
+    pub max_verification_queue: BlockHeight,
+    /// Memory budget, shared by orphan blocks pool, verification queue and server queue.
+    /// When it is exhausted, verification queue is considered full and unknown blocks are ignored
+    pub memory_budget: MemoryBudgetRef,
 }
 
 /// Synchronization client.
@@ -111,12 +144,14 @@ pub struct SynchronizationClientCore<T: TaskExecutor> {
     chain: Chain,
     /// Orphaned blocks pool.
     orphaned_blocks_pool: OrphanBlocksPool,
+    /// Unsolicited blocks, received while verification queue is full.
+    deferred_blocks: VecDeque<(PeerIndex, IndexedBlock)>,
     /// Chain verifier
     chain_verifier: Arc<ChainVerifier>,
     /// Verify block headers?
     verify_headers: bool,
-    /// Verifying blocks by peer
-    verifying_blocks_by_peer: HashMap<H256, PeerIndex>,
+    /// Verifying blocks by peer, with their sizes
+    verifying_blocks_by_peer: HashMap<H256, (PeerIndex, usize)>,
     /// Verifying blocks futures
     verifying_blocks_futures: HashMap<PeerIndex, (HashSet<H256>, Vec<EmptyBoxFuture>)>,
     /// Hashes of items we do not want to relay after verification is completed
@@ -127,10 +162,20 @@ pub struct SynchronizationClientCore<T: TaskExecutor> {
     sync_speed_meter: AverageSpeedMeter,
     /// Configuration
     config: Config,
-    /// Synchronization events listener
-    listener: Option<SyncListenerRef>,
+    /// Synchronization events listeners
+    listeners: Vec<SyncListenerRef>,
     /// Time of last duplicated blocks request.
     last_dup_time: f64,
+    /// Block timestamps statistics.
+    timestamp_stats: TimestampStats,
+    /// Headers-only side branches, received from peers.
+    header_branches: HeaderBranches,
+    /// Reasons of recent blocks rejections.
+    rejected_blocks: LinkedHashMap<H256, VerificationError>,
+    /// Pending `getheaders` requests.
+    headers_requests: HeadersRequests,
+    /// Peers, which have no headers we don't know.
+    synced_peers: HashSet<PeerIndex>,
 }
 
 /// Verification sink for synchronization client core
@@ -204,23 +249,55 @@ where
     fn on_connect(&mut self, peer_index: PeerIndex) {
         // ask peer for its block headers to find our best common block
         let block_locator_hashes = self.chain.block_locator_hashes();
-        self.executor.execute(Task::GetHeaders(
-            peer_index,
-            types::GetHeaders::with_block_locator_hashes(block_locator_hashes),
-        ));
+        let task = self.headers_request(peer_index, block_locator_hashes);
+        self.executor.execute(task);
         // unuseful until respond with headers message
         self.peers_tasks.unuseful_peer(peer_index);
         self.peers_tasks.on_headers_requested(peer_index);
+
+        for listener in &self.listeners {
+            listener.on_peer_connected(peer_index);
+        }
     }
 
     fn on_disconnect(&mut self, peer_index: PeerIndex) {
         // sync tasks from this peers must be executed by other peers
         let peer_tasks = self.peers_tasks.reset_blocks_tasks(peer_index);
         self.peers_tasks.disconnect(peer_index);
+        self.headers_requests.remove_peer(peer_index);
+        self.synced_peers.remove(&peer_index);
+        self.timestamp_stats.on_peer_disconnected(peer_index);
         self.execute_synchronization_tasks(Some(peer_tasks), None);
     }
 
-    fn on_inventory(&self, peer_index: PeerIndex, message: types::Inv) {
+    fn on_inventory(&mut self, peer_index: PeerIndex, message: types::Inv) {
+        if self.config.watch_only {
+            self.note_block_announcements(
+                peer_index,
+                message
+                    .inventory
+                    .iter()
+                    .filter(|item| item.inv_type == InventoryType::MessageBlock)
+                    .map(|item| item.hash.clone())
+                    .collect(),
+            );
+        }
+
+        let announced_blocks = message
+            .inventory
+            .iter()
+            .filter(|item| {
+                item.inv_type == InventoryType::MessageBlock
+                    && self.chain.block_state(&item.hash) == BlockState::Unknown
+            })
+            .map(|item| item.hash.clone())
+            .collect();
+        self.note_block_propagation(
+            announced_blocks,
+            PropagationStage::Announced,
+            Some(peer_index),
+        );
+
         // else ask for all unknown transactions and blocks
         let unknown_inventory: Vec<_> = message
             .inventory
@@ -266,6 +343,14 @@ where
             return;
         }
 
+        // when watching, ask for headers of unknown blocks instead of blocks themselves
+        if self.config.watch_only {
+            let block_locator_hashes = self.chain.block_locator_hashes();
+            let task = self.headers_request(peer_index, block_locator_hashes);
+            self.executor.execute(task);
+            return;
+        }
+
         // ask for unknown items
         let message = types::GetData::with_inventory(unknown_inventory);
         self.executor.execute(Task::GetData(peer_index, message));
@@ -273,14 +358,66 @@ where
 
     /// Try to queue synchronization of unknown blocks when blocks headers are received.
     fn on_headers(&mut self, peer_index: PeerIndex, mut headers: Vec<IndexedBlockHeader>) {
-        assert!(
-            !headers.is_empty(),
-            "This must be checked in incoming connection"
-        );
+        // empty message => peer has no headers after our block locator
+        if headers.is_empty() {
+            self.note_peer_synced(peer_index);
+            return;
+        }
 
         // update peers to select next tasks
         self.peers_tasks.on_headers_received(peer_index);
 
+        // attribute headers to our request
+        let is_parent_known =
+            self.chain.block_state(&headers[0].raw.previous_header_hash) != BlockState::Unknown;
+        match self
+            .headers_requests
+            .on_response(peer_index, &headers, is_parent_known)
+        {
+            HeadersResponse::Expected(request_id) => {
+                trace!(target: "sync", "Got response to `getheaders` request#{} from peer#{}", request_id, peer_index);
+            }
+            HeadersResponse::Duplicate(request_id) => {
+                debug!(target: "sync", "Got duplicate response to `getheaders` request#{} from peer#{}", request_id, peer_index);
+            }
+            HeadersResponse::Unsolicited => {
+                trace!(target: "sync", "Got {} unsolicited headers from peer#{}", headers.len(), peer_index);
+            }
+            HeadersResponse::Mismatched(request_id) => {
+                warn!(
+                    target: "sync",
+                    "Response to `getheaders` request#{} from peer#{} is not connected to known blocks. First: {}",
+                    request_id,
+                    peer_index,
+                    headers[0].hash.to_reversed_str(),
+                );
+                if self.peers_tasks.penalize(peer_index) {
+                    self.peers.misbehaving(peer_index, "Too many failures.");
+                }
+                return;
+            }
+        }
+
+        // short headers messages are new blocks announcements
+        if self.config.watch_only && headers.len() <= MAX_ANNOUNCED_HEADERS {
+            self.note_block_announcements(
+                peer_index,
+                headers.iter().map(|header| header.hash.clone()).collect(),
+            );
+        }
+        if headers.len() <= MAX_ANNOUNCED_HEADERS {
+            let announced_blocks = headers
+                .iter()
+                .filter(|header| self.chain.block_state(&header.hash) == BlockState::Unknown)
+                .map(|header| header.hash.clone())
+                .collect();
+            self.note_block_propagation(
+                announced_blocks,
+                PropagationStage::Announced,
+                Some(peer_index),
+            );
+        }
+
         // headers are ordered
         // => if we know nothing about headers[0].parent
         // => all headers are also unknown to us
@@ -320,6 +457,7 @@ where
 						trace!(target: "sync", "Ignoring {} known headers from peer#{}", headers.len(), peer_index);
 						// but this peer is still useful for synchronization
 						self.peers_tasks.useful_peer(peer_index);
+						self.note_peer_synced(peer_index);
 						return;
 					},
 				}
@@ -344,6 +482,29 @@ where
             );
             return;
         }
+        if let Err(error) = self.chain.verify_fork_point(&last_known_hash) {
+            warn!(
+                target: "sync",
+                "Ignoring headers from peer#{}, starting with {}: {}",
+                peer_index,
+                headers[first_unknown_index].hash.to_reversed_str(),
+                error,
+            );
+            self.chain
+                .mark_dead_end_block(&headers[first_unknown_index].hash);
+            return;
+        }
+        // reject cheap side branches before verifying them, so that peer can't flood us with fake headers
+        if last_known_hash != self.chain.best_block_header().hash
+            && self.is_low_work_side_branch(
+                &last_known_hash,
+                &headers[first_unknown_index..num_headers],
+            )
+        {
+            warn!(target: "sync", "Ignoring {} side branch headers from peer#{}, starting with {}: branch work is too low",
+                num_headers - first_unknown_index, peer_index, headers[first_unknown_index].hash.to_reversed_str());
+            return;
+        }
         match self.verify_headers(
             peer_index,
             last_known_hash,
@@ -363,19 +524,38 @@ where
                     headers[num_headers - 1].hash.to_reversed_str()
                 );
 
+                // limit headers-only side branches, so that peer can't flood us with cheap fake headers
+                if last_known_hash != self.chain.best_block_header().hash {
+                    let num_accepted = self.accept_side_branch_headers(
+                        &last_known_hash,
+                        &headers[first_unknown_index..num_headers],
+                    );
+                    if num_accepted == 0 {
+                        warn!(target: "sync", "Ignoring {} side branch headers from peer#{}: too many headers-only branches",
+                            num_new_headers, peer_index);
+                        return;
+                    }
+                    headers.truncate(first_unknown_index + num_accepted);
+                }
+
                 // prepare new headers array
                 let new_headers = headers.split_off(first_unknown_index);
                 self.chain.schedule_blocks_headers(new_headers);
+                self.shared_state
+                    .update_best_header_height(self.chain.best_block_header().number);
+                // peer has more headers than we know
+                self.synced_peers.remove(&peer_index);
 
-                // switch to synchronization state
-                if !self.state.is_synchronizing() {
+                // switch to synchronization state (blocks are never synchronized when watching)
+                if !self.config.watch_only && !self.state.is_synchronizing() {
                     if self.chain.length_of_blocks_state(BlockState::Scheduled)
                         + self.chain.length_of_blocks_state(BlockState::Requested)
                         == 1
                     {
                         self.switch_to_nearly_saturated_state();
                     } else {
-                        self.switch_to_synchronization_state();
+                        let target_height = self.chain.best_block_header().number;
+                        self.switch_to_synchronization_state(peer_index, target_height);
                     }
                 }
 
@@ -392,6 +572,42 @@ where
         peer_index: PeerIndex,
         block: IndexedBlock,
     ) -> Option<VecDeque<IndexedBlock>> {
+        let span = debug_span!(target: "sync", "schedule_block", hash = %block.hash().to_reversed_str(), peer = peer_index);
+        let _enter = span.enter();
+
+        // remember the first receipt of the block, unless it has been processed already
+        match self.chain.block_state(&block.header.hash) {
+            BlockState::Verifying | BlockState::Stored => (),
+            _ => self.note_block_propagation(
+                vec![block.header.hash.clone()],
+                PropagationStage::Received,
+                Some(peer_index),
+            ),
+        }
+
+        // blocks are never requested when watching => unsolicited block is an announcement
+        if self.config.watch_only {
+            trace!(target: "sync", "Ignoring block {} from peer#{}: watch-only mode", block.hash().to_reversed_str(), peer_index);
+            self.note_block_announcements(peer_index, vec![block.header.hash]);
+            return None;
+        }
+
+        // there's not enough disk space to store the block => ignore it
+        // requested block will be requested again after disk space is freed
+        if self.shared_state.disk_space_low() {
+            trace!(target: "sync", "Ignoring block {} from peer#{}: disk space is low", block.hash().to_reversed_str(), peer_index);
+            return None;
+        }
+
+        // verification can't keep up with the network => do not let unsolicited blocks
+        // pile up in memory, but defer them until verification queue is drained
+        if self.is_verification_queue_full()
+            && self.chain.block_state(&block.header.hash) == BlockState::Unknown
+        {
+            self.defer_block(peer_index, block);
+            return None;
+        }
+
         // update peers to select next tasks
         self.peers_tasks
             .on_block_received(peer_index, &block.header.hash);
@@ -399,6 +615,9 @@ where
         // prepare list of blocks to verify + make all required changes to the chain
         let mut result: Option<VecDeque<IndexedBlock>> = None;
         let block_state = self.chain.block_state(&block.header.hash);
+        if block_state != BlockState::Verifying && block_state != BlockState::Stored {
+            self.update_timestamp_stats(peer_index, &block);
+        }
         match block_state {
             BlockState::Verifying | BlockState::Stored => {
                 // remember peer as useful
@@ -470,7 +689,10 @@ where
                                 .orphaned_blocks_pool
                                 .contains_unknown_block(&block.header.hash)
                             {
-                                self.orphaned_blocks_pool.insert_unknown_block(block);
+                                let block_hash = block.header.hash.clone();
+                                if !self.orphaned_blocks_pool.insert_unknown_block(block) {
+                                    trace!(target: "sync", "Ignoring block {} from peer#{}: memory budget is exhausted", block_hash.to_reversed_str(), peer_index);
+                                }
                             }
                         }
                     }
@@ -496,11 +718,20 @@ where
                             blocks_to_verify.iter().map(|b| b.header.clone()).collect();
                         self.chain.verify_blocks(blocks_headers_to_verify);
                         // remember that we are verifying block from this peer
-                        for verifying_block_hash in
-                            blocks_to_verify.iter().map(|b| b.hash().clone())
-                        {
-                            self.verifying_blocks_by_peer
-                                .insert(verifying_block_hash, peer_index);
+                        // blocks are already in memory => they are accounted even if budget is exhausted
+                        for verifying_block in &blocks_to_verify {
+                            let block_size = verifying_block.size();
+                            self.config
+                                .memory_budget
+                                .force_allocate(MemoryConsumer::VerificationQueue, block_size);
+                            if let Some((_, replaced_size)) = self
+                                .verifying_blocks_by_peer
+                                .insert(verifying_block.hash().clone(), (peer_index, block_size))
+                            {
+                                self.config
+                                    .memory_budget
+                                    .release(MemoryConsumer::VerificationQueue, replaced_size);
+                            }
                         }
                         match self.verifying_blocks_futures.entry(peer_index) {
                             Entry::Occupied(mut entry) => {
@@ -592,9 +823,7 @@ where
     }
 
     fn install_sync_listener(&mut self, listener: SyncListenerRef) {
-        // currently single, single-setup listener is supported
-        assert!(self.listener.is_none());
-        self.listener = Some(listener);
+        self.listeners.push(listener);
     }
 
     /// Schedule new synchronization tasks, if any.
@@ -608,8 +837,15 @@ where
         // display information if processed many blocks || enough time has passed since sync start
         self.print_synchronization_information();
 
+        // synchronization is paused until disk space is freed
+        if self.shared_state.disk_space_low() {
+            trace!(target: "sync", "Synchronization tasks are not executed: disk space is low");
+            return;
+        }
+
         // prepare limits. TODO: must be updated using current retrieval && verification speed && blocks size
         let mut limits = BlocksRequestLimits::default();
+        limits.max_requested_blocks = self.config.max_requested_blocks;
         if self.chain.length_of_blocks_state(BlockState::Stored) > 150_000 {
             limits.min_blocks_in_request = 8;
             limits.max_blocks_in_request = 16;
@@ -618,7 +854,8 @@ where
         // if some blocks requests are forced => we should ask peers even if there are no idle peers
         let verifying_hashes_len = self.chain.length_of_blocks_state(BlockState::Verifying);
         if let Some(forced_blocks_requests) = forced_blocks_requests {
-            let useful_peers = self.peers_tasks.useful_peers();
+            // do not ask stalling peers for blocks, which they have failed to deliver
+            let useful_peers = self.peers_tasks.useful_peers_for_blocks();
             // if we have to request blocks && there are no useful peers at all => switch to saturated state
             if useful_peers.is_empty() {
                 warn!(target: "sync", "Last peer was marked as non-useful. Moving to saturated state.");
@@ -661,27 +898,24 @@ where
                 .cloned()
                 .collect();
             if !headers_idle_peers.is_empty() {
+                // when watching, scheduled headers are never requested => do not limit headers
                 let scheduled_hashes_len = self.chain.length_of_blocks_state(BlockState::Scheduled);
-                if scheduled_hashes_len < MAX_SCHEDULED_HASHES {
+                if self.config.watch_only || scheduled_hashes_len < MAX_SCHEDULED_HASHES {
+                    let block_locator_hashes = self.chain.block_locator_hashes();
                     for header_peer in &headers_idle_peers {
                         self.peers_tasks.on_headers_requested(*header_peer);
+                        tasks
+                            .push(self.headers_request(*header_peer, block_locator_hashes.clone()));
                     }
-
-                    let block_locator_hashes = self.chain.block_locator_hashes();
-                    let headers_tasks = headers_idle_peers.iter().map(move |peer_index| {
-                        Task::GetHeaders(
-                            *peer_index,
-                            types::GetHeaders::with_block_locator_hashes(
-                                block_locator_hashes.clone(),
-                            ),
-                        )
-                    });
-                    tasks.extend(headers_tasks);
                 }
             }
 
             let blocks_idle_peers_len = blocks_idle_peers.len() as BlockHeight;
-            if blocks_idle_peers_len != 0 {
+            // when verification queue is full, new blocks are not requested
+            if blocks_idle_peers_len != 0
+                && !self.config.watch_only
+                && !self.is_verification_queue_full()
+            {
                 // check if verification queue is empty/almost empty
                 // && there are pending blocks requests
                 // && there are idle block peers
@@ -770,7 +1004,7 @@ where
                     let scheduled_hashes_len =
                         self.chain.length_of_blocks_state(BlockState::Scheduled);
                     if requested_hashes_len + verifying_hashes_len
-                        < MAX_REQUESTED_BLOCKS + MAX_VERIFYING_BLOCKS
+                        < limits.max_requested_blocks + limits.max_verifying_blocks
                         && scheduled_hashes_len != 0
                     {
                         let chunk_size = min(
@@ -845,7 +1079,11 @@ where
     }
 
     /// Process failed block verification
-    fn on_block_verification_error(&self, err: &str, hash: &H256) {
+    fn on_block_verification_error(
+        &self,
+        err: &VerificationError,
+        hash: &H256,
+    ) -> Option<Vec<VerificationTask>> {
         self.core.lock().on_block_verification_error(err, hash)
     }
 }
@@ -863,6 +1101,7 @@ where
         chain: Chain,
         chain_verifier: Arc<ChainVerifier>,
     ) -> ClientCoreRef<Self> {
+        let header_branches = HeaderBranches::new(config.header_branches);
         let sync = Arc::new(Mutex::new(SynchronizationClientCore {
             shared_state: shared_state,
             state: State::Saturated,
@@ -871,7 +1110,9 @@ where
             management_worker: None,
             executor: executor,
             chain: chain,
-            orphaned_blocks_pool: OrphanBlocksPool::new(),
+            orphaned_blocks_pool: OrphanBlocksPool::new()
+                .with_memory_budget(config.memory_budget.clone()),
+            deferred_blocks: VecDeque::new(),
             chain_verifier: chain_verifier,
             verify_headers: true,
             verifying_blocks_by_peer: HashMap::new(),
@@ -880,19 +1121,116 @@ where
             block_speed_meter: AverageSpeedMeter::with_inspect_items(SYNC_SPEED_BLOCKS_TO_INSPECT),
             sync_speed_meter: AverageSpeedMeter::with_inspect_items(BLOCKS_SPEED_BLOCKS_TO_INSPECT),
             config: config,
-            listener: None,
+            listeners: Vec::new(),
             last_dup_time: 0f64,
+            timestamp_stats: TimestampStats::default(),
+            header_branches: header_branches,
+            rejected_blocks: LinkedHashMap::new(),
+            headers_requests: HeadersRequests::default(),
+            synced_peers: HashSet::new(),
         }));
 
         {
             let csync = Arc::downgrade(&sync);
             let mut lsync = sync.lock();
-            lsync.management_worker = Some(ManagementWorker::new(csync));
+            let peers_config =
+                ManagePeersConfig::with_block_stall_timeout(lsync.config.block_stall_timeout_s);
+            lsync.management_worker = Some(ManagementWorker::new(csync, peers_config));
         }
 
         sync
     }
 
+    /// Remember `getheaders` request to the peer and return task to send it.
+    fn headers_request(&mut self, peer_index: PeerIndex, block_locator_hashes: Vec<H256>) -> Task {
+        let request_id = self
+            .headers_requests
+            .on_requested(peer_index, block_locator_hashes.clone());
+        trace!(target: "sync", "Requesting headers from peer#{} by `getheaders` request#{}", peer_index, request_id);
+        Task::GetHeaders(
+            peer_index,
+            types::GetHeaders::with_block_locator_hashes(block_locator_hashes),
+        )
+    }
+
+    /// Accept headers of the side branch, following the block with given hash.
+    /// Returns number of leading headers, which are within side branches limits.
+    fn accept_side_branch_headers(
+        &mut self,
+        parent_hash: &H256,
+        headers: &[IndexedBlockHeader],
+    ) -> usize {
+        {
+            let chain = &self.chain;
+            self.header_branches
+                .retain(|hash| match chain.block_state(hash) {
+                    BlockState::Scheduled | BlockState::Requested | BlockState::Verifying => true,
+                    _ => false,
+                });
+        }
+
+        let parent_number = self
+            .chain
+            .block_number(parent_hash)
+            .or_else(|| self.chain.stored_block_number(parent_hash));
+        let best_header_number = self.chain.best_block_header().number;
+        self.header_branches
+            .accept(parent_hash, parent_number, headers, best_header_number)
+    }
+
+    /// Check if work of the side branch, formed by given headers, is below the best headers chain work
+    /// minus the max work deficit. Walks the best headers chain only until deficit is exceeded
+    fn is_low_work_side_branch(&self, parent_hash: &H256, headers: &[IndexedBlockHeader]) -> bool {
+        let best_header = self.chain.best_block_header();
+        let deficit = match self.chain.block_header_by_hash(&best_header.hash) {
+            Some(header) => match header
+                .raw
+                .bits
+                .to_work()
+                .overflowing_mul(self.config.header_branches.max_work_deficit.into())
+            {
+                (deficit, false) => deficit,
+                (_, true) => return false,
+            },
+            None => return false,
+        };
+
+        // work of the side branch since the fork point
+        let mut branch_work = headers
+            .iter()
+            .fold(U256::zero(), |work, header| header.raw.bits.add_work(work));
+        let mut hash = parent_hash.clone();
+        let fork_number = loop {
+            if let Some(number) = self.chain.block_number(&hash) {
+                break number;
+            }
+            match self.chain.block_header_by_hash(&hash) {
+                Some(header) => {
+                    branch_work = header.raw.bits.add_work(branch_work);
+                    hash = header.raw.previous_header_hash;
+                }
+                None => return false,
+            }
+        };
+
+        // work of the best headers chain since the fork point
+        let (max_best_work, overflow) = branch_work.overflowing_add(deficit);
+        if overflow {
+            return false;
+        }
+        let mut best_work = U256::zero();
+        for number in fork_number + 1..=best_header.number {
+            match self.chain.block_header_by_number(number) {
+                Some(header) => best_work = header.raw.bits.add_work(best_work),
+                None => return false,
+            }
+            if best_work > max_best_work {
+                return true;
+            }
+        }
+        false
+    }
+
     /// Get information on current synchronization state.
     #[cfg(test)]
     pub fn information(&self) -> Information {
@@ -901,6 +1239,7 @@ where
             peers_tasks: self.peers_tasks.information(),
             chain: self.chain.information(),
             orphaned_blocks: self.orphaned_blocks_pool.len(),
+            deferred_blocks: self.deferred_blocks.len(),
         }
     }
 
@@ -929,6 +1268,11 @@ where
         &mut self.orphaned_blocks_pool
     }
 
+    /// Get reason of the recent block rejection
+    pub fn block_rejection(&self, hash: &H256) -> Option<VerificationError> {
+        self.rejected_blocks.get(hash).cloned()
+    }
+
     /// Verify block headers or not?
     #[cfg(test)]
     pub fn set_verify_headers(&mut self, verify: bool) {
@@ -970,6 +1314,147 @@ where
         }
     }
 
+    /// Mark stored block and all its descendants invalid and switch to the best valid branch
+    pub fn invalidate_block(&mut self, hash: &H256) -> Result<(), Error> {
+        if !self.chain.storage().contains_block(hash.clone().into()) {
+            return Err(Error::UnknownBlock);
+        }
+        if self.chain.storage().block_number(hash) == Some(0) {
+            return Err(Error::GenesisBlock);
+        }
+
+        warn!(target: "sync", "Block {} is manually invalidated", hash.to_reversed_str());
+        let insert_result = self.chain.invalidate_block(hash).map_err(Error::Database)?;
+        self.on_best_branch_activated(insert_result);
+        Ok(())
+    }
+
+    /// Remove invalidity mark from the stored block and switch to the best valid branch
+    pub fn reconsider_block(&mut self, hash: &H256) -> Result<(), Error> {
+        if !self.chain.storage().contains_block(hash.clone().into()) {
+            return Err(Error::UnknownBlock);
+        }
+
+        warn!(target: "sync", "Block {} is manually reconsidered", hash.to_reversed_str());
+        let insert_result = self.chain.reconsider_block(hash).map_err(Error::Database)?;
+        self.on_best_branch_activated(insert_result);
+        Ok(())
+    }
+
+    /// Update state after storage has been switched to another branch
+    fn on_best_branch_activated(&mut self, insert_result: BlockInsertionResult) {
+        if insert_result == BlockInsertionResult::default() {
+            return;
+        }
+
+        let best_storage_block = self.chain.best_storage_block();
+        info!(target: "sync", "Best block is now {} at height {}", best_storage_block.hash.to_reversed_str(), best_storage_block.number);
+
+        // update shared state
+        self.shared_state.update_best_heights(
+            best_storage_block.number,
+            self.chain.best_block_header().number,
+        );
+        self.note_stale_blocks(&insert_result);
+
+        // notify listeners
+        for listener in &self.listeners {
+            listener.best_storage_block_inserted(&best_storage_block.hash);
+        }
+
+        // continue with synchronization
+        self.execute_synchronization_tasks(None, None);
+    }
+
+    /// Remember blocks, announced by the peer
+    fn note_block_announcements(&self, peer_index: PeerIndex, hashes: Vec<H256>) {
+        if hashes.is_empty() {
+            return;
+        }
+
+        let time = ::time::get_time().sec as u32;
+        self.shared_state
+            .note_block_announcements(peer_index, hashes, time, precise_time_s());
+    }
+
+    /// Remember that blocks have reached given propagation stage
+    fn note_block_propagation(
+        &self,
+        hashes: Vec<H256>,
+        stage: PropagationStage,
+        peer_index: Option<PeerIndex>,
+    ) {
+        if hashes.is_empty() {
+            return;
+        }
+
+        let time = ::time::get_time().sec as u32;
+        self.shared_state
+            .note_block_propagation(hashes, stage, peer_index, time, precise_time_s());
+    }
+
+    /// Remember blocks, which were reorged out during block insertion
+    fn note_stale_blocks(&self, insert_result: &BlockInsertionResult) {
+        if insert_result.decanonized_blocks_hashes.is_empty() {
+            return;
+        }
+
+        // canonized route starts at the same height as decanonized route
+        let fork_height = self.chain.best_storage_block().number + 1
+            - insert_result.canonized_blocks_hashes.len() as BlockHeight;
+        let time = ::time::get_time().sec as u32;
+        let stale_blocks: Vec<_> = insert_result
+            .decanonized_blocks_hashes
+            .iter()
+            .zip(insert_result.canonized_blocks_hashes.iter())
+            .enumerate()
+            .map(|(index, (hash, replaced_by))| StaleBlock {
+                hash: hash.clone(),
+                height: fork_height + index as BlockHeight,
+                replaced_by: replaced_by.clone(),
+                time: time,
+            })
+            .collect();
+        info!(target: "sync", "Reorganization: {} blocks starting from height {} are reorged out", stale_blocks.len(), fork_height);
+        self.shared_state.note_stale_blocks(stale_blocks);
+    }
+
+    /// Update block timestamps statistics && raise alerts if required
+    fn update_timestamp_stats(&mut self, peer_index: PeerIndex, block: &IndexedBlock) {
+        let parent_time = self
+            .chain
+            .block_header_by_hash(&block.header.raw.previous_header_hash)
+            .map(|parent| parent.raw.time);
+        // old blocks are received while synchronizing => do not compare their timestamps with local time
+        let local_time = if self.state.is_synchronizing() {
+            None
+        } else {
+            Some(::time::get_time().sec as u32)
+        };
+        let producer = block.header.raw.pubkey.to_bytes().to_vec().into();
+        let alerts = self.timestamp_stats.on_block(
+            peer_index,
+            producer,
+            block.header.raw.time,
+            parent_time,
+            local_time,
+        );
+        for alert in alerts {
+            match alert {
+                TimestampAlert::Producer(producer, counters) => warn!(
+                    target: "sync",
+                    "Block producer {:?} is creating blocks with bad timestamps: {} of {} are earlier than parent, {} are far from local time",
+                    producer, counters.earlier_than_parent, counters.blocks, counters.skewed
+                ),
+                TimestampAlert::Peer(peer_index, counters) => warn!(
+                    target: "sync",
+                    "Peer#{} is relaying blocks with bad timestamps: {} of {} are earlier than parent, {} are far from local time",
+                    peer_index, counters.earlier_than_parent, counters.blocks, counters.skewed
+                ),
+            }
+        }
+    }
+
     /// Verify and select unknown headers for scheduling
     fn verify_headers(
         &mut self,
@@ -977,10 +1462,14 @@ where
         last_known_hash: H256,
         headers: &[IndexedBlockHeader],
     ) -> BlocksHeadersVerificationResult {
+        // context-free rules are verified in parallel, before headers are linked
+        let first_invalid_header = match self.verify_headers {
+            true => self.chain_verifier.verify_block_headers(headers).err(),
+            false => None,
+        };
+
         // validate blocks headers before scheduling
         let mut last_known_hash = &last_known_hash;
-        let mut headers_provider =
-            MessageBlockHeadersProvider::new(&self.chain, self.chain.best_block_header().number);
         for (header_index, header) in headers.iter().enumerate() {
             // check that this header is direct child of previous header
             if &header.raw.previous_header_hash != last_known_hash {
@@ -1013,13 +1502,9 @@ where
                 }
             }
 
-            // verify header
-            if self.verify_headers {
-                if let Err(error) = self.chain_verifier.verify_block_header(
-                    &headers_provider,
-                    &header.hash,
-                    &header.raw,
-                ) {
+            // report the first invalid header
+            if let Some((invalid_index, ref error)) = first_invalid_header {
+                if invalid_index == header_index {
                     if self.config.close_connection_on_bad_block {
                         self.peers.misbehaving(
                             peer_index,
@@ -1037,7 +1522,6 @@ where
             }
 
             last_known_hash = &header.hash;
-            headers_provider.append_header(header.hash.clone(), header.clone());
         }
 
         BlocksHeadersVerificationResult::Success
@@ -1052,7 +1536,9 @@ where
         use std::mem::swap;
 
         // ask fastest peers for hashes at the beginning of `hashes`
-        self.peers_tasks.sort_peers_for_blocks(&mut peers);
+        let all_peers = &self.peers;
+        self.peers_tasks
+            .sort_peers_for_blocks(&mut peers, |peer| all_peers.latency(peer));
 
         let chunk_size = min(
             limits.max_blocks_in_request,
@@ -1095,27 +1581,43 @@ where
         tasks
     }
 
-    /// Switch to synchronization state
-    fn switch_to_synchronization_state(&mut self) {
+    /// Switch to synchronization state, started by headers from the peer
+    fn switch_to_synchronization_state(
+        &mut self,
+        peer_index: PeerIndex,
+        target_height: BlockHeight,
+    ) {
         if self.state.is_synchronizing() {
             return;
         }
 
-        if let Some(ref listener) = self.listener {
+        for listener in &self.listeners {
             listener.synchronization_state_switched(true);
+            listener.on_started_syncing(peer_index, target_height);
         }
 
         self.shared_state.update_synchronizing(true);
         self.state = State::Synchronizing(precise_time_s(), self.chain.best_storage_block().number);
     }
 
+    /// Remember that peer has no headers we don't know and notify listeners, if it is new for the peer
+    fn note_peer_synced(&mut self, peer_index: PeerIndex) {
+        if !self.synced_peers.insert(peer_index) {
+            return;
+        }
+
+        for listener in &self.listeners {
+            listener.on_peer_synced(peer_index);
+        }
+    }
+
     /// Switch to nearly saturated state
     fn switch_to_nearly_saturated_state(&mut self) {
         if self.state.is_nearly_saturated() {
             return;
         }
 
-        if let Some(ref listener) = self.listener {
+        for listener in &self.listeners {
             listener.synchronization_state_switched(false);
         }
 
@@ -1129,8 +1631,9 @@ where
             return;
         }
 
-        if let Some(ref listener) = self.listener {
+        for listener in &self.listeners {
             listener.synchronization_state_switched(false);
+            listener.on_switched_to_saturated();
         }
 
         self.shared_state.update_synchronizing(false);
@@ -1169,6 +1672,9 @@ where
         &mut self,
         block: IndexedBlock,
     ) -> Option<Vec<VerificationTask>> {
+        let span = debug_span!(target: "sync", "commit_block", hash = %block.hash().to_reversed_str(), peer = ?self.verifying_blocks_by_peer.get(block.hash()).map(|v| v.0));
+        let _enter = span.enter();
+
         // update block processing speed
         self.block_speed_meter.checkpoint();
 
@@ -1176,6 +1682,8 @@ where
         let needs_relay = !self.do_not_relay.remove(block.hash());
 
         let block_hash = block.hash().clone();
+        self.note_block_propagation(vec![block_hash.clone()], PropagationStage::Verified, None);
+
         // insert block to the storage
         match {
             // remove block from verification queue
@@ -1194,12 +1702,20 @@ where
         } {
             Ok(insert_result) => {
                 // update shared state
-                self.shared_state
-                    .update_best_storage_block_height(self.chain.best_storage_block().number);
+                self.shared_state.update_best_heights(
+                    self.chain.best_storage_block().number,
+                    self.chain.best_block_header().number,
+                );
+                self.note_stale_blocks(&insert_result);
+                self.note_block_propagation(
+                    insert_result.canonized_blocks_hashes.clone(),
+                    PropagationStage::Connected,
+                    None,
+                );
 
-                // notify listener
+                // notify listeners
                 if let Some(best_block_hash) = insert_result.canonized_blocks_hashes.last() {
-                    if let Some(ref listener) = self.listener {
+                    for listener in &self.listeners {
                         listener.best_storage_block_inserted(best_block_hash);
                     }
                 }
@@ -1219,10 +1735,12 @@ where
                     }
                 }
 
-                // deal with block transactions
-                let verification_tasks: Vec<VerificationTask> = Vec::with_capacity(0);
-                // Vec::with_capacity(insert_result.transactions_to_reverify.len());
-                Some(verification_tasks)
+                // verification queue is shorter now => deferred blocks could be processed
+                Some(self.release_deferred_blocks())
+            }
+            Err(storage::Error::FinalizedFork(_)) => {
+                // competing fork is not a storage failure => reject the block
+                self.on_block_verification_error(&VerificationError::TooDeepReorg, &block_hash)
             }
             Err(e) => {
                 // process as irrecoverable failure
@@ -1235,17 +1753,27 @@ where
         }
     }
 
-    fn on_block_verification_error(&mut self, err: &str, hash: &H256) {
-        warn!(target: "sync", "Block {:?} verification failed with error {:?}", hash.to_reversed_str(), err);
+    fn on_block_verification_error(
+        &mut self,
+        err: &VerificationError,
+        hash: &H256,
+    ) -> Option<Vec<VerificationTask>> {
+        warn!(target: "sync", "Block {:?} verification failed with error {}", hash.to_reversed_str(), err);
+
+        // remember the reason, so that block submitter could learn it
+        self.rejected_blocks.insert(hash.clone(), err.clone());
+        if self.rejected_blocks.len() > MAX_REJECTED_BLOCKS {
+            self.rejected_blocks.pop_front();
+        }
 
         // remove flags
         self.do_not_relay.remove(hash);
 
         // close connection with this peer
-        if let Some(peer_index) = self.verifying_blocks_by_peer.get(hash) {
+        if let Some(&(peer_index, _)) = self.verifying_blocks_by_peer.get(hash) {
             if self.config.close_connection_on_bad_block {
                 self.peers.dos(
-                    *peer_index,
+                    peer_index,
                     &format!("Provided wrong block {}", hash.to_reversed_str()),
                 )
             } else {
@@ -1259,19 +1787,100 @@ where
 
         // mark failed block as dead end (this branch won't be synchronized)
         self.chain.mark_dead_end_block(hash);
+        // deferred children of the failed block are also invalid
+        self.forget_deferred_children(hash);
 
         // awake threads, waiting for this block insertion
         self.awake_waiting_threads(hash);
 
         // start new tasks
         self.execute_synchronization_tasks(None, None);
+
+        // verification queue is shorter now => deferred blocks could be processed
+        Some(self.release_deferred_blocks())
+    }
+
+    /// Is verification queue too long to accept more blocks? Queue is also full when
+    /// the shared memory budget is exhausted
+    fn is_verification_queue_full(&self) -> bool {
+        self.chain.length_of_blocks_state(BlockState::Verifying)
+            >= self.config.max_verification_queue
+            || self.config.memory_budget.is_exhausted()
+    }
+
+    /// Remember unsolicited block until verification queue is drained
+    fn defer_block(&mut self, peer_index: PeerIndex, block: IndexedBlock) {
+        if self
+            .deferred_blocks
+            .iter()
+            .any(|&(_, ref deferred)| deferred.hash() == block.hash())
+        {
+            return;
+        }
+
+        if self.deferred_blocks.len() >= MAX_DEFERRED_BLOCKS {
+            trace!(target: "sync", "Ignoring block {} from peer#{}: verification queue is full and too many blocks are deferred", block.hash().to_reversed_str(), peer_index);
+            return;
+        }
+
+        trace!(target: "sync", "Deferring block {} from peer#{}: verification queue is full", block.hash().to_reversed_str(), peer_index);
+        self.deferred_blocks.push_back((peer_index, block));
+    }
+
+    /// Forget deferred descendants of given block
+    fn forget_deferred_children(&mut self, hash: &H256) {
+        let mut parents = vec![hash.clone()];
+        while let Some(parent) = parents.pop() {
+            let (children, deferred_blocks): (Vec<_>, VecDeque<_>) = self
+                .deferred_blocks
+                .drain(..)
+                .partition(|&(_, ref block)| block.header.raw.previous_header_hash == parent);
+            self.deferred_blocks = deferred_blocks;
+            for (peer_index, block) in children {
+                trace!(target: "sync", "Forgetting deferred block {} from peer#{}: its parent is invalid", block.hash().to_reversed_str(), peer_index);
+                parents.push(block.hash().clone());
+            }
+        }
+    }
+
+    /// Process deferred blocks while verification queue is not full. Returned tasks are executed
+    /// before already queued verification tasks => blocks, which parents are still verifying,
+    /// are left deferred
+    fn release_deferred_blocks(&mut self) -> Vec<VerificationTask> {
+        let mut verification_tasks = Vec::new();
+        while !self.is_verification_queue_full() {
+            let position = {
+                let chain = &self.chain;
+                self.deferred_blocks.iter().position(|&(_, ref block)| {
+                    chain.block_state(&block.header.raw.previous_header_hash)
+                        != BlockState::Verifying
+                })
+            };
+            let (peer_index, block) =
+                match position.and_then(|position| self.deferred_blocks.remove(position)) {
+                    Some(deferred) => deferred,
+                    None => break,
+                };
+
+            if let Some(blocks_to_verify) = self.on_block(peer_index, block) {
+                verification_tasks.extend(
+                    blocks_to_verify
+                        .into_iter()
+                        .map(VerificationTask::verify_block),
+                );
+            }
+        }
+        verification_tasks
     }
 
     /// Execute futures, which were waiting for this block verification
     fn awake_waiting_threads(&mut self, hash: &H256) {
         // find a peer, which has supplied us with this block
         if let Entry::Occupied(block_entry) = self.verifying_blocks_by_peer.entry(hash.clone()) {
-            let peer_index = *block_entry.get();
+            let (peer_index, block_size) = *block_entry.get();
+            self.config
+                .memory_budget
+                .release(MemoryConsumer::VerificationQueue, block_size);
             // find a # of blocks, which this thread has supplied
             if let Entry::Occupied(mut entry) = self.verifying_blocks_futures.entry(peer_index) {
                 let is_last_block = {
@@ -1300,7 +1909,7 @@ impl Default for BlocksRequestLimits {
     fn default() -> Self {
         BlocksRequestLimits {
             max_scheduled_hashes: MAX_SCHEDULED_HASHES,
-            max_requested_blocks: MAX_REQUESTED_BLOCKS,
+            max_requested_blocks: DEFAULT_MAX_REQUESTED_BLOCKS,
             max_verifying_blocks: MAX_VERIFYING_BLOCKS,
             min_blocks_in_request: MIN_BLOCKS_IN_REQUEST,
             max_blocks_in_request: MAX_BLOCKS_IN_REQUEST,
@@ -1313,8 +1922,11 @@ pub mod tests {
     extern crate test_data;
 
     use super::super::SyncListener;
-    use super::{ClientCore, Config, CoreVerificationSink, SynchronizationClientCore};
-    use chain::Block;
+    use super::{
+        ClientCore, Config, CoreVerificationSink, SynchronizationClientCore,
+        DEFAULT_MAX_REQUESTED_BLOCKS, DEFAULT_MAX_VERIFICATION_QUEUE,
+    };
+    use chain::{Block, IndexedBlock, IndexedBlockHeader};
     use db::BlockChainDatabase;
     use inbound_connection::tests::DummyOutboundSyncConnection;
     use message::common::InventoryVector;
@@ -1323,20 +1935,26 @@ pub mod tests {
     use parking_lot::Mutex;
     use primitives::hash::H256;
     use std::sync::Arc;
-    use synchronization_chain::Chain;
+    use synchronization_chain::{BlockState, Chain};
     use synchronization_client::{Client, SynchronizationClient};
     use synchronization_executor::tests::DummyTaskExecutor;
     use synchronization_executor::Task;
+    use synchronization_manager::DEFAULT_BLOCK_STALL_TIMEOUT_S;
     use synchronization_peers::PeersImpl;
     use synchronization_verifier::tests::DummyVerifier;
+    use synchronization_verifier::VerificationError;
     use types::{ClientCoreRef, PeerIndex, StorageRef, SynchronizationStateRef};
-    use utils::SynchronizationState;
+    use utils::{set_virtual_time, MemoryBudget, OrphanBlocksPool, SynchronizationState};
     use verification::BackwardsCompatibleChainVerifier as ChainVerifier;
 
     #[derive(Default)]
     struct DummySyncListenerData {
         pub is_synchronizing: bool,
         pub best_blocks: Vec<H256>,
+        pub connected_peers: Vec<PeerIndex>,
+        pub synced_peers: Vec<PeerIndex>,
+        pub started_syncing: Vec<(PeerIndex, u32)>,
+        pub switched_to_saturated: usize,
     }
 
     struct DummySyncListener {
@@ -1357,6 +1975,25 @@ pub mod tests {
         fn best_storage_block_inserted(&self, block_hash: &H256) {
             self.data.lock().best_blocks.push(block_hash.clone());
         }
+
+        fn on_peer_connected(&self, peer_index: PeerIndex) {
+            self.data.lock().connected_peers.push(peer_index);
+        }
+
+        fn on_peer_synced(&self, peer_index: PeerIndex) {
+            self.data.lock().synced_peers.push(peer_index);
+        }
+
+        fn on_started_syncing(&self, peer_index: PeerIndex, target_height: u32) {
+            self.data
+                .lock()
+                .started_syncing
+                .push((peer_index, target_height));
+        }
+
+        fn on_switched_to_saturated(&self) {
+            self.data.lock().switched_to_saturated += 1;
+        }
     }
 
     fn create_sync(
@@ -1380,6 +2017,12 @@ pub mod tests {
         let executor = DummyTaskExecutor::new();
         let config = Config {
             close_connection_on_bad_block: true,
+            block_stall_timeout_s: DEFAULT_BLOCK_STALL_TIMEOUT_S,
+            max_requested_blocks: DEFAULT_MAX_REQUESTED_BLOCKS,
+            header_branches: HeaderBranchesLimits::default(),
+            watch_only: false,
+            max_verification_queue: DEFAULT_MAX_VERIFICATION_QUEUE,
+            memory_budget: Default::default(),
         };
 
         let chain_verifier = Arc::new(ChainVerifier::new(storage.clone(), Network::Unitest));
@@ -1437,6 +2080,166 @@ pub mod tests {
         assert_eq!(tasks, vec![request_block_headers_genesis(0)]);
     }
 
+    #[test]
+    fn synchronization_paused_when_disk_space_is_low() {
+        let (executor, core, sync) = create_sync(None, None);
+        core.lock().shared_state.update_disk_space_low(true);
+
+        let block1: Block = test_data::block_h1();
+        sync.on_headers(5, vec![block1.block_header.clone().into()]);
+        // => nothing is requested
+        assert_eq!(executor.take_tasks(), vec![]);
+        // => block is ignored
+        sync.on_block(5, block1.clone().into());
+        assert_eq!(core.lock().information().chain.stored, 1);
+
+        // when disk space is freed => synchronization continues
+        core.lock().shared_state.update_disk_space_low(false);
+        core.lock().execute_synchronization_tasks(None, None);
+        assert_eq!(
+            executor.take_tasks(),
+            vec![
+                request_block_headers_genesis_and(5, vec![block1.hash()]),
+                request_blocks(5, vec![block1.hash()])
+            ]
+        );
+        sync.on_block(5, block1.into());
+        assert_eq!(core.lock().information().chain.stored, 2);
+    }
+
+    #[test]
+    fn synchronization_defers_unsolicited_blocks_when_verification_queue_is_full() {
+        let (executor, core, _) = create_sync(None, None);
+        let mut core = core.lock();
+        core.config.max_verification_queue = 1;
+
+        let block1: IndexedBlock = test_data::block_h1().into();
+        let block2: IndexedBlock = test_data::block_h2().into();
+        // when block1 is verifying => verification queue is full
+        assert_eq!(
+            core.on_block(0, block1.clone()).map(|blocks| blocks.len()),
+            Some(1)
+        );
+        // => unsolicited block2 is deferred
+        assert!(core.on_block(1, block2.clone()).is_none());
+        assert_eq!(core.information().deferred_blocks, 1);
+        assert_eq!(core.chain().block_state(block2.hash()), BlockState::Unknown);
+        // => and new blocks are not requested
+        let fork = test_data::build_n_empty_blocks_from_genesis(1, 100);
+        core.on_headers(1, vec![fork[0].block_header.clone().into()]);
+        assert_eq!(
+            core.chain().block_state(&fork[0].hash()),
+            BlockState::Scheduled
+        );
+        assert!(executor.take_tasks().iter().all(|task| match *task {
+            Task::GetData(_, _) => false,
+            _ => true,
+        }));
+
+        // when block1 is verified => block2 is released for verification
+        let verification_tasks = core.on_block_verification_success(block1).unwrap();
+        assert_eq!(verification_tasks.len(), 1);
+        assert_eq!(core.information().deferred_blocks, 0);
+        assert_eq!(
+            core.chain().block_state(block2.hash()),
+            BlockState::Verifying
+        );
+    }
+
+    #[test]
+    fn synchronization_releases_deferred_blocks_when_verification_fails() {
+        let (_, core, _) = create_sync(None, None);
+        let mut core = core.lock();
+        core.config.max_verification_queue = 1;
+
+        let block1: IndexedBlock = test_data::block_h1().into();
+        let block2: IndexedBlock = test_data::block_h2().into();
+        let fork: IndexedBlock = test_data::build_n_empty_blocks_from_genesis(1, 100)[0]
+            .clone()
+            .into();
+        assert!(core.on_block(0, block1.clone()).is_some());
+        // => both child of block1 and unrelated block are deferred
+        assert!(core.on_block(1, block2.clone()).is_none());
+        assert!(core.on_block(1, fork.clone()).is_none());
+        assert_eq!(core.information().deferred_blocks, 2);
+
+        // when block1 verification fails => its child is forgotten, unrelated block is released
+        let verification_tasks = core
+            .on_block_verification_error(&VerificationError::TooDeepReorg, block1.hash())
+            .unwrap();
+        assert_eq!(verification_tasks.len(), 1);
+        assert_eq!(core.information().deferred_blocks, 0);
+        assert_eq!(core.chain().block_state(block2.hash()), BlockState::Unknown);
+        assert_eq!(core.chain().block_state(fork.hash()), BlockState::Verifying);
+    }
+
+    #[test]
+    fn synchronization_defers_unsolicited_blocks_when_memory_budget_is_exhausted() {
+        let (_, core, _) = create_sync(None, None);
+        let mut core = core.lock();
+
+        let block1: IndexedBlock = test_data::block_h1().into();
+        let block2: IndexedBlock = test_data::block_h2().into();
+        let budget = Arc::new(MemoryBudget::new(block1.size()));
+        core.config.memory_budget = budget.clone();
+        core.orphaned_blocks_pool = OrphanBlocksPool::new().with_memory_budget(budget.clone());
+
+        // when block1 is verifying => memory budget is exhausted
+        assert_eq!(
+            core.on_block(0, block1.clone()).map(|blocks| blocks.len()),
+            Some(1)
+        );
+        assert_eq!(budget.usage().verification_queue, block1.size());
+        // => unsolicited block2 is deferred
+        assert!(core.on_block(1, block2.clone()).is_none());
+        assert_eq!(core.information().deferred_blocks, 1);
+
+        // when block1 is verified => budget is released and block2 is verified
+        let verification_tasks = core.on_block_verification_success(block1).unwrap();
+        assert_eq!(verification_tasks.len(), 1);
+        assert_eq!(budget.usage().verification_queue, block2.size());
+    }
+
+    #[test]
+    fn low_work_side_branch_headers_are_ignored() {
+        let (_, core, sync) = create_sync(None, None);
+        core.lock().config.header_branches.max_work_deficit = 1;
+        let build_headers = |parent: H256, n: usize, bits: u32| {
+            let mut headers: Vec<IndexedBlockHeader> = Vec::new();
+            for _ in 0..n {
+                let parent = headers.last().map(|h| h.hash.clone()).unwrap_or(parent);
+                let block = test_data::block_builder()
+                    .header()
+                    .parent(parent)
+                    .bits(bits.into())
+                    .build()
+                    .build();
+                headers.push(block.block_header.into());
+            }
+            headers
+        };
+
+        let best_chain = build_headers(test_data::genesis().hash(), 3, 0x1d00ffff);
+        sync.on_headers(1, best_chain.clone());
+        assert_eq!(core.lock().chain().best_block_header().number, 3);
+
+        // longer branch with lower work is ignored
+        let low_work_branch = build_headers(test_data::genesis().hash(), 5, 0x207fffff);
+        sync.on_headers(2, low_work_branch.clone());
+        assert_eq!(
+            core.lock().chain().block_state(&low_work_branch[0].hash),
+            BlockState::Unknown
+        );
+
+        // shorter branch, which work is within the deficit, is accepted
+        let branch = build_headers(test_data::genesis().hash(), 2, 0x1d00ffff);
+        sync.on_headers(2, branch.clone());
+        assert_ne!(
+            core.lock().chain().block_state(&branch[1].hash),
+            BlockState::Unknown
+        );
+    }
+
     #[test]
     fn synchronization_saturated_on_start() {
         let (_, core, _) = create_sync(None, None);
@@ -1772,6 +2575,46 @@ pub mod tests {
         assert_eq!(chain.information().headers.total, 0);
     }
 
+    #[test]
+    fn collection_closed_on_headers_not_connected_to_known_blocks() {
+        let (_, core, sync) = create_sync(None, None);
+        core.lock()
+            .peers
+            .insert(0, Services::default(), DummyOutboundSyncConnection::new());
+        // locator of this request only contains genesis
+        sync.on_connect(0);
+
+        let headers: Vec<IndexedBlockHeader> =
+            test_data::build_n_empty_blocks_from(10, 0, &test_data::block_h1().block_header)
+                .into_iter()
+                .map(|block| block.block_header.into())
+                .collect();
+        sync.on_headers(0, headers);
+
+        assert!(!core.lock().peers.enumerate().contains(&0));
+        assert_eq!(core.lock().chain().information().headers.total, 0);
+    }
+
+    #[test]
+    fn headers_following_known_block_out_of_requested_locator_are_accepted() {
+        let (_, core, sync) = create_sync(None, None);
+        core.lock()
+            .peers
+            .insert(0, Services::default(), DummyOutboundSyncConnection::new());
+        // locator of this request only contains genesis
+        sync.on_connect(0);
+        sync.on_block(1, test_data::block_h1().into());
+
+        let headers: Vec<IndexedBlockHeader> =
+            test_data::build_n_empty_blocks_from(10, 0, &test_data::block_h1().block_header)
+                .into_iter()
+                .map(|block| block.block_header.into())
+                .collect();
+        sync.on_headers(0, headers);
+
+        assert!(core.lock().peers.enumerate().contains(&0));
+    }
+
     #[test]
     fn synchronization_works_for_forks_from_db_best_block() {
         let genesis = test_data::genesis();
@@ -2201,7 +3044,7 @@ pub mod tests {
 
         // simulate verification during b21 verification
         let mut dummy_verifier = DummyVerifier::default();
-        dummy_verifier.error_when_verifying(b21.hash(), "simulated");
+        dummy_verifier.error_when_verifying(b21.hash(), VerificationError::BadProof);
 
         let (_, _, sync) = create_sync(None, Some(dummy_verifier));
 
@@ -2334,7 +3177,7 @@ pub mod tests {
 
         // simulate verification error during b0 verification
         let mut dummy_verifier = DummyVerifier::default();
-        dummy_verifier.error_when_verifying(b0.hash(), "simulated");
+        dummy_verifier.error_when_verifying(b0.hash(), VerificationError::BadProof);
 
         let (_, core, sync) = create_sync(None, Some(dummy_verifier));
 
@@ -2343,9 +3186,14 @@ pub mod tests {
             .insert(0, Services::default(), DummyOutboundSyncConnection::new());
         assert!(core.lock().peers.enumerate().contains(&0));
 
+        let b0_hash = b0.hash();
         sync.on_block(0, b0.into());
 
         assert!(!core.lock().peers.enumerate().contains(&0));
+        assert_eq!(
+            sync.block_rejection(&b0_hash),
+            Some(VerificationError::BadProof)
+        );
     }
 
     #[test]
@@ -2589,4 +3437,123 @@ pub mod tests {
         assert_eq!(data.lock().is_synchronizing, false);
         assert_eq!(data.lock().best_blocks.len(), 3);
     }
+
+    #[test]
+    fn sync_listener_peer_state_transitions() {
+        let (_, _, sync) = create_sync(None, None);
+        let data = Arc::new(Mutex::new(DummySyncListenerData::default()));
+        sync.install_sync_listener(Box::new(DummySyncListener::new(data.clone())));
+
+        sync.on_connect(0);
+        sync.on_connect(1);
+        assert_eq!(data.lock().connected_peers, vec![0, 1]);
+
+        // peer has nothing new => it is synced, but this is reported once
+        sync.on_headers(0, vec![]);
+        sync.on_headers(0, vec![]);
+        assert_eq!(data.lock().synced_peers, vec![0]);
+
+        // peer provides new headers => synchronization is started
+        sync.on_headers(
+            0,
+            vec![
+                test_data::block_h1().block_header.into(),
+                test_data::block_h2().block_header.into(),
+            ],
+        );
+        assert_eq!(data.lock().started_syncing, vec![(0, 2)]);
+
+        sync.on_block(0, test_data::block_h1().into());
+        sync.on_block(0, test_data::block_h2().into());
+        assert_eq!(data.lock().switched_to_saturated, 1);
+
+        // all headers are known => peer is synced again
+        sync.on_headers(0, vec![test_data::block_h2().block_header.into()]);
+        sync.on_headers(1, vec![test_data::block_h2().block_header.into()]);
+        assert_eq!(data.lock().synced_peers, vec![0, 0, 1]);
+    }
+
+    #[test]
+    fn sync_listeners_are_all_called() {
+        let (_, _, sync) = create_sync(None, None);
+
+        let data1 = Arc::new(Mutex::new(DummySyncListenerData::default()));
+        let data2 = Arc::new(Mutex::new(DummySyncListenerData::default()));
+        sync.install_sync_listener(Box::new(DummySyncListener::new(data1.clone())));
+        sync.install_sync_listener(Box::new(DummySyncListener::new(data2.clone())));
+
+        sync.on_headers(0, vec![test_data::block_h1().block_header.into()]);
+        sync.on_block(0, test_data::block_h1().into());
+        assert_eq!(data1.lock().best_blocks, vec![test_data::block_h1().hash()]);
+        assert_eq!(data2.lock().best_blocks, vec![test_data::block_h1().hash()]);
+    }
+
+    #[test]
+    fn watch_only_synchronizes_headers_and_records_announcements() {
+        let (executor, core, sync) = create_sync(None, None);
+        core.lock().config.watch_only = true;
+        sync.on_connect(0);
+        sync.on_connect(1);
+        executor.take_tasks();
+
+        // block announcement => headers are requested instead of block
+        set_virtual_time(Some(100.0));
+        sync.on_inventory(
+            0,
+            types::Inv::with_inventory(vec![InventoryVector::block(test_data::block_h1().hash())]),
+        );
+        assert_eq!(
+            executor.take_tasks(),
+            vec![request_block_headers_genesis(0)]
+        );
+
+        // announced header is remembered, but block is never requested
+        set_virtual_time(Some(100.5));
+        sync.on_headers(1, vec![test_data::block_h1().block_header.into()]);
+        assert!(executor.take_tasks().iter().all(|task| match *task {
+            Task::GetData(_, _) => false,
+            _ => true,
+        }));
+        assert_eq!(core.lock().information().chain.scheduled, 1);
+        assert!(core.lock().information().state.is_saturated());
+
+        // unsolicited block is not verified
+        sync.on_block(1, test_data::block_h2().into());
+        set_virtual_time(None);
+        assert_eq!(core.lock().information().chain.verifying, 0);
+        assert_eq!(core.lock().information().orphaned_blocks, 0);
+
+        let announcements = core.lock().shared_state.block_announcements(0);
+        assert_eq!(announcements.len(), 2);
+        assert_eq!(announcements[0].hash, test_data::block_h1().hash());
+        assert_eq!(announcements[0].announcers, vec![(0, 0), (1, 500)]);
+        assert_eq!(announcements[1].hash, test_data::block_h2().hash());
+        assert_eq!(announcements[1].announcers, vec![(1, 0)]);
+    }
+
+    #[test]
+    fn block_propagation_is_recorded() {
+        let (_, core, sync) = create_sync(None, None);
+        sync.on_connect(0);
+        sync.on_connect(1);
+
+        set_virtual_time(Some(100.0));
+        sync.on_inventory(
+            0,
+            types::Inv::with_inventory(vec![InventoryVector::block(test_data::block_h1().hash())]),
+        );
+        set_virtual_time(Some(100.25));
+        sync.on_block(1, test_data::block_h1().into());
+        set_virtual_time(None);
+
+        let propagations = core.lock().shared_state.block_propagations(0);
+        assert_eq!(propagations.len(), 1);
+        assert_eq!(propagations[0].hash, test_data::block_h1().hash());
+        assert_eq!(propagations[0].first_peer, 0);
+        assert!(propagations[0].announced);
+        assert_eq!(propagations[0].received_ms, Some(250));
+        // dummy verifier verifies and connects block immediately
+        assert_eq!(propagations[0].verified_ms, Some(250));
+        assert_eq!(propagations[0].connected_ms, Some(250));
+    }
 }
@@ -1,7 +1,16 @@
 use chain::IndexedBlock;
 use message::common::InventoryVector;
-use message::types;
-use std::sync::Arc;
+use message::{types, SharedPayload};
+use p2p::Direction;
+use parking_lot::Mutex;
+use primitives::hash::H256;
+use rand::seq::SliceRandom;
+use rand::{thread_rng, Rng};
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+use std::sync::{mpsc, Arc};
+use std::thread;
+use std::time::{Duration, Instant};
 use synchronization_peers::BlockAnnouncementType;
 use types::{PeerIndex, PeersRef, RequestId};
 use utils::KnownHashType;
@@ -20,8 +29,8 @@ pub enum Task {
     GetData(PeerIndex, types::GetData),
     /// Get headers
     GetHeaders(PeerIndex, types::GetHeaders),
-    /// Send block
-    Block(PeerIndex, IndexedBlock),
+    /// Send block, serialized once for all peers it is sent to
+    Block(PeerIndex, H256, SharedPayload),
     /// Send notfound
     NotFound(PeerIndex, types::NotFound),
     /// Send inventory
@@ -32,15 +41,93 @@ pub enum Task {
     RelayNewBlock(IndexedBlock),
 }
 
+/// Default delay of new block announcement to peers, which are not announced immediately.
+pub const DEFAULT_ANNOUNCEMENT_DELAY_MS: u64 = 2000;
+/// Default interval, over which delayed announcements are spread.
+pub const DEFAULT_TRICKLE_INTERVAL_MS: u64 = 4000;
+
+/// New blocks relay topology.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RelayConfig {
+    /// Number of peers, to which new block is announced immediately. None if it is announced to all peers immediately.
+    pub immediate_announcements: Option<usize>,
+    /// Remaining peers are announced new block after this delay.
+    pub announcement_delay_ms: u64,
+    /// Every remaining peer is announced at random moment of this interval, following the delay.
+    pub trickle_interval_ms: u64,
+    /// Outbound peers are chosen for immediate announcement before inbound peers.
+    pub prefer_outbound: bool,
+}
+
+impl Default for RelayConfig {
+    fn default() -> Self {
+        RelayConfig {
+            immediate_announcements: None,
+            announcement_delay_ms: DEFAULT_ANNOUNCEMENT_DELAY_MS,
+            trickle_interval_ms: DEFAULT_TRICKLE_INTERVAL_MS,
+            prefer_outbound: false,
+        }
+    }
+}
+
+impl RelayConfig {
+    /// Random delay of the announcement to the peer, which is not announced immediately
+    fn announcement_delay<R: Rng>(&self, rng: &mut R) -> Duration {
+        Duration::from_millis(
+            self.announcement_delay_ms + rng.gen_range(0, self.trickle_interval_ms + 1),
+        )
+    }
+}
+
+/// Block announcement to the peer, postponed until deadline.
+struct DelayedAnnouncement {
+    deadline: Instant,
+    peer_index: PeerIndex,
+    block: Arc<IndexedBlock>,
+}
+
+impl PartialEq for DelayedAnnouncement {
+    fn eq(&self, other: &Self) -> bool {
+        self.deadline == other.deadline
+    }
+}
+
+impl Eq for DelayedAnnouncement {}
+
+impl PartialOrd for DelayedAnnouncement {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for DelayedAnnouncement {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // earliest deadline is on top of the heap
+        other.deadline.cmp(&self.deadline)
+    }
+}
+
 /// Synchronization tasks executor
 pub struct LocalSynchronizationTaskExecutor {
     /// Active synchronization peers
     peers: PeersRef,
+    /// New blocks relay topology
+    relay_config: RelayConfig,
+    /// Delayed announcements queue. None if all announcements are immediate
+    delayed_announcements: Option<Mutex<mpsc::Sender<Vec<DelayedAnnouncement>>>>,
 }
 
 impl LocalSynchronizationTaskExecutor {
-    pub fn new(peers: PeersRef) -> Arc<Self> {
-        Arc::new(LocalSynchronizationTaskExecutor { peers: peers })
+    pub fn new(peers: PeersRef, relay_config: RelayConfig) -> Arc<Self> {
+        let delayed_announcements = match relay_config.immediate_announcements {
+            Some(_) => Some(Mutex::new(start_delayed_announcements(peers.clone()))),
+            None => None,
+        };
+        Arc::new(LocalSynchronizationTaskExecutor {
+            peers: peers,
+            relay_config: relay_config,
+            delayed_announcements: delayed_announcements,
+        })
     }
 
     fn execute_ignore(&self, peer_index: PeerIndex, request_id: RequestId) {
@@ -66,15 +153,12 @@ impl LocalSynchronizationTaskExecutor {
         }
     }
 
-    fn execute_block(&self, peer_index: PeerIndex, block: IndexedBlock) {
+    fn execute_block(&self, peer_index: PeerIndex, hash: H256, block: SharedPayload) {
         if let Some(connection) = self.peers.connection(peer_index) {
-            trace!(target: "sync", "Sending block {} to peer#{}", block.hash().to_reversed_str(), peer_index);
+            trace!(target: "sync", "Sending block {} to peer#{}", hash.to_reversed_str(), peer_index);
             self.peers
-                .hash_known_as(peer_index, block.hash().clone(), KnownHashType::Block);
-            let block = types::Block {
-                block: block.to_raw_block(),
-            };
-            connection.send_block(&block);
+                .hash_known_as(peer_index, hash, KnownHashType::Block);
+            connection.send_serialized_block(&block);
         }
     }
 
@@ -87,7 +171,13 @@ impl LocalSynchronizationTaskExecutor {
 
     fn execute_inventory(&self, peer_index: PeerIndex, inventory: types::Inv) {
         if let Some(connection) = self.peers.connection(peer_index) {
+            // inventory is a response to the `getblocks` request, so blocks, known by the peer,
+            // are sent too. Only relay of new blocks skips them
             trace!(target: "sync", "Sending inventory to peer#{} with {} items", peer_index, inventory.inventory.len());
+            for item in &inventory.inventory {
+                self.peers
+                    .hash_known_as(peer_index, item.hash.clone(), KnownHashType::Block);
+            }
             connection.send_inventory(&inventory);
         }
     }
@@ -100,6 +190,10 @@ impl LocalSynchronizationTaskExecutor {
     ) {
         if let Some(connection) = self.peers.connection(peer_index) {
             trace!(target: "sync", "Sending headers to peer#{} with {} items", peer_index, headers.headers.len());
+            for header in &headers.headers {
+                self.peers
+                    .hash_known_as(peer_index, header.hash(), KnownHashType::Block);
+            }
             match request_id {
                 Some(request_id) => connection.respond_headers(&headers, request_id),
                 None => connection.send_headers(&headers),
@@ -108,29 +202,114 @@ impl LocalSynchronizationTaskExecutor {
     }
 
     fn execute_relay_block(&self, block: IndexedBlock) {
-        for peer_index in self.peers.enumerate() {
-            match self.peers.filter_block(peer_index, &block) {
-                BlockAnnouncementType::SendInventory => {
-                    self.execute_inventory(
-                        peer_index,
-                        types::Inv::with_inventory(vec![InventoryVector::block(
-                            block.hash().clone(),
-                        )]),
-                    );
-                }
-                BlockAnnouncementType::SendHeaders => {
-                    self.execute_headers(
-                        peer_index,
-                        types::Headers::with_headers(vec![block.header.raw.clone()]),
-                        None,
-                    );
-                }
-                BlockAnnouncementType::DoNotAnnounce => (),
+        // peers, announced immediately, are chosen randomly, so that block source is harder to find
+        let mut rng = thread_rng();
+        let mut peers = self.peers.enumerate();
+        peers.shuffle(&mut rng);
+        if self.relay_config.prefer_outbound {
+            // outbound peers are selected by us => they are announced first
+            peers.sort_by_key(|peer_index| {
+                self.peers
+                    .connection(*peer_index)
+                    .map(|connection| connection.direction() != Direction::Outbound)
+                    .unwrap_or(true)
+            });
+        }
+
+        let immediate_announcements = self
+            .relay_config
+            .immediate_announcements
+            .unwrap_or(peers.len())
+            .min(peers.len());
+        let delayed_peers = peers.split_off(immediate_announcements);
+        for peer_index in peers {
+            announce_block(&self.peers, peer_index, &block);
+        }
+
+        if delayed_peers.is_empty() {
+            return;
+        }
+        if let Some(ref delayed_announcements) = self.delayed_announcements {
+            trace!(target: "sync", "Delaying announcement of block {} to {} peers", block.hash().to_reversed_str(), delayed_peers.len());
+            let now = Instant::now();
+            let block = Arc::new(block);
+            let announcements = delayed_peers
+                .into_iter()
+                .map(|peer_index| DelayedAnnouncement {
+                    deadline: now + self.relay_config.announcement_delay(&mut rng),
+                    peer_index: peer_index,
+                    block: block.clone(),
+                })
+                .collect();
+            if delayed_announcements.lock().send(announcements).is_err() {
+                warn!(target: "sync", "Delayed block announcements thread has stopped");
             }
         }
     }
 }
 
+/// Announce new block to the peer, using announcement type, preferred by the peer.
+fn announce_block(peers: &PeersRef, peer_index: PeerIndex, block: &IndexedBlock) {
+    let connection = match peers.connection(peer_index) {
+        Some(connection) => connection,
+        None => return,
+    };
+
+    match peers.filter_block(peer_index, block) {
+        // new blocks are announced to the peer using headers as a part of the experiment
+        BlockAnnouncementType::SendInventory if !connection.experiments().headers_announcement => {
+            trace!(target: "sync", "Sending inventory to peer#{} with 1 items", peer_index);
+            connection.send_inventory(&types::Inv::with_inventory(vec![InventoryVector::block(
+                block.hash().clone(),
+            )]));
+        }
+        BlockAnnouncementType::SendInventory | BlockAnnouncementType::SendHeaders => {
+            trace!(target: "sync", "Sending headers to peer#{} with 1 items", peer_index);
+            connection.send_headers(&types::Headers::with_headers(vec![block
+                .header
+                .raw
+                .clone()]));
+        }
+        BlockAnnouncementType::DoNotAnnounce => return,
+    }
+    peers.hash_known_as(peer_index, block.hash().clone(), KnownHashType::Block);
+}
+
+/// Start thread, which announces blocks to peers when announcement delay expires.
+fn start_delayed_announcements(peers: PeersRef) -> mpsc::Sender<Vec<DelayedAnnouncement>> {
+    let (sender, receiver) = mpsc::channel::<Vec<DelayedAnnouncement>>();
+    thread::Builder::new()
+        .name("Delayed block announcements thread".to_owned())
+        .spawn(move || {
+            let mut queue = BinaryHeap::new();
+            loop {
+                let now = Instant::now();
+                while queue
+                    .peek()
+                    .map_or(false, |next: &DelayedAnnouncement| next.deadline <= now)
+                {
+                    let announcement = queue.pop().expect("checked above; qed");
+                    announce_block(&peers, announcement.peer_index, &announcement.block);
+                }
+
+                let announcements = match queue.peek() {
+                    Some(next) => match receiver.recv_timeout(next.deadline - now) {
+                        Ok(announcements) => announcements,
+                        Err(mpsc::RecvTimeoutError::Timeout) => continue,
+                        Err(mpsc::RecvTimeoutError::Disconnected) => break,
+                    },
+                    None => match receiver.recv() {
+                        Ok(announcements) => announcements,
+                        Err(_) => break,
+                    },
+                };
+                queue.extend(announcements);
+            }
+        })
+        .expect("Error creating delayed block announcements thread");
+    sender
+}
+
 impl TaskExecutor for LocalSynchronizationTaskExecutor {
     fn execute(&self, task: Task) {
         match task {
@@ -139,7 +318,7 @@ impl TaskExecutor for LocalSynchronizationTaskExecutor {
             Task::GetHeaders(peer_index, getheaders) => {
                 self.execute_getheaders(peer_index, getheaders)
             }
-            Task::Block(peer_index, block) => self.execute_block(peer_index, block),
+            Task::Block(peer_index, hash, block) => self.execute_block(peer_index, hash, block),
             Task::NotFound(peer_index, notfound) => self.execute_notfound(peer_index, notfound),
             Task::Inventory(peer_index, inventory) => self.execute_inventory(peer_index, inventory),
             Task::Headers(peer_index, headers, request_id) => {
@@ -157,10 +336,16 @@ pub mod tests {
     use super::*;
     use inbound_connection::tests::DummyOutboundSyncConnection;
     use message::Services;
+    use p2p::ExperimentFlags;
     use parking_lot::{Condvar, Mutex};
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+    use std::collections::{BTreeMap, HashMap};
     use std::sync::Arc;
     use std::time;
-    use synchronization_peers::{BlockAnnouncementType, PeersContainer, PeersImpl, PeersOptions};
+    use synchronization_peers::{
+        BlockAnnouncementType, PeersContainer, PeersFilters, PeersImpl, PeersOptions,
+    };
 
     pub struct DummyTaskExecutor {
         tasks: Mutex<Vec<Task>>,
@@ -206,10 +391,134 @@ pub mod tests {
         }
     }
 
+    /// Conditions of the simulated network path to the peer.
+    #[derive(Debug, Default, Clone, Copy, PartialEq)]
+    pub struct NetworkConditions {
+        /// Delay of every task, in milliseconds
+        pub latency_ms: u64,
+        /// Max random delay, added to the latency, in milliseconds
+        pub jitter_ms: u64,
+        /// Probability that the task is held back behind tasks, executed later
+        pub reorder_rate: f64,
+        /// Probability that the task is lost
+        pub drop_rate: f64,
+    }
+
+    /// Task executor, which passes tasks to the inner executor after simulated network delay.
+    /// Time is virtual and is advanced by the test. Random choices are the same for the same seed.
+    pub struct LatencyTaskExecutor<T: TaskExecutor> {
+        inner: Arc<T>,
+        state: Mutex<LatencyState>,
+    }
+
+    struct LatencyState {
+        rng: StdRng,
+        now_ms: u64,
+        next_seq: u64,
+        conditions: HashMap<PeerIndex, NetworkConditions>,
+        /// Delayed tasks, ordered by (delivery time, sequence number)
+        pending: BTreeMap<(u64, u64), Task>,
+        dropped: usize,
+    }
+
+    impl<T: TaskExecutor> LatencyTaskExecutor<T> {
+        pub fn new(inner: Arc<T>, seed: u64) -> Arc<Self> {
+            Arc::new(LatencyTaskExecutor {
+                inner: inner,
+                state: Mutex::new(LatencyState {
+                    rng: StdRng::seed_from_u64(seed),
+                    now_ms: 0,
+                    next_seq: 0,
+                    conditions: HashMap::new(),
+                    pending: BTreeMap::new(),
+                    dropped: 0,
+                }),
+            })
+        }
+
+        pub fn inner(&self) -> &Arc<T> {
+            &self.inner
+        }
+
+        /// Change conditions of the network path to the peer. Tasks of other peers are not delayed
+        pub fn set_conditions(&self, peer_index: PeerIndex, conditions: NetworkConditions) {
+            self.state.lock().conditions.insert(peer_index, conditions);
+        }
+
+        /// Advance virtual time and pass all delivered tasks to the inner executor
+        pub fn advance_to(&self, now_ms: u64) {
+            let delivered = {
+                let mut state = self.state.lock();
+                state.now_ms = now_ms;
+                let not_delivered = state.pending.split_off(&(now_ms + 1, 0));
+                ::std::mem::replace(&mut state.pending, not_delivered)
+            };
+
+            for (_, task) in delivered {
+                self.inner.execute(task);
+            }
+        }
+
+        /// Time of the next task delivery
+        pub fn next_delivery_ms(&self) -> Option<u64> {
+            self.state.lock().pending.keys().next().map(|key| key.0)
+        }
+
+        /// Number of lost tasks
+        pub fn dropped(&self) -> usize {
+            self.state.lock().dropped
+        }
+    }
+
+    impl<T: TaskExecutor> TaskExecutor for LatencyTaskExecutor<T> {
+        fn execute(&self, task: Task) {
+            {
+                let mut state = self.state.lock();
+                let conditions = task_peer(&task)
+                    .and_then(|peer_index| state.conditions.get(&peer_index).cloned())
+                    .unwrap_or_default();
+                if conditions.drop_rate > 0f64 && state.rng.gen_bool(conditions.drop_rate) {
+                    state.dropped += 1;
+                    return;
+                }
+
+                let mut delay_ms = conditions.latency_ms;
+                if conditions.jitter_ms != 0 {
+                    delay_ms += state.rng.gen_range(0, conditions.jitter_ms + 1);
+                }
+                if conditions.reorder_rate > 0f64 && state.rng.gen_bool(conditions.reorder_rate) {
+                    // held back task is delivered after any task, executed right after it
+                    delay_ms += conditions.latency_ms + conditions.jitter_ms + 1;
+                }
+                if delay_ms != 0 {
+                    let key = (state.now_ms + delay_ms, state.next_seq);
+                    state.next_seq += 1;
+                    state.pending.insert(key, task);
+                    return;
+                }
+            }
+
+            self.inner.execute(task);
+        }
+    }
+
+    fn task_peer(task: &Task) -> Option<PeerIndex> {
+        match *task {
+            Task::Ignore(peer_index, _)
+            | Task::GetData(peer_index, _)
+            | Task::GetHeaders(peer_index, _)
+            | Task::Block(peer_index, _, _)
+            | Task::NotFound(peer_index, _)
+            | Task::Inventory(peer_index, _)
+            | Task::Headers(peer_index, _, _) => Some(peer_index),
+            Task::RelayNewBlock(_) => None,
+        }
+    }
+
     #[test]
     fn relay_new_block_after_sendheaders() {
         let peers = Arc::new(PeersImpl::default());
-        let executor = LocalSynchronizationTaskExecutor::new(peers.clone());
+        let executor = LocalSynchronizationTaskExecutor::new(peers.clone(), RelayConfig::default());
 
         let c1 = DummyOutboundSyncConnection::new();
         peers.insert(1, Services::default(), c1.clone());
@@ -230,4 +539,232 @@ pub mod tests {
             1
         );
     }
+
+    #[test]
+    fn relay_new_block_with_headers_announcement_experiment() {
+        let peers = Arc::new(PeersImpl::default());
+        let executor = LocalSynchronizationTaskExecutor::new(peers.clone(), RelayConfig::default());
+
+        let c1 = DummyOutboundSyncConnection::with_experiments(ExperimentFlags {
+            headers_announcement: true,
+        });
+        peers.insert(1, Services::default(), c1.clone());
+
+        executor.execute(Task::RelayNewBlock(test_data::genesis().into()));
+        assert_eq!(c1.messages.lock().get("inventory"), None);
+        assert_eq!(c1.messages.lock().get("headers"), Some(&1));
+    }
+
+    #[test]
+    fn relay_new_block_skips_known_blocks() {
+        let peers = Arc::new(PeersImpl::default());
+        let executor = LocalSynchronizationTaskExecutor::new(peers.clone(), RelayConfig::default());
+
+        let c1 = DummyOutboundSyncConnection::new();
+        peers.insert(1, Services::default(), c1.clone());
+        let c2 = DummyOutboundSyncConnection::new();
+        peers.insert(2, Services::default(), c2.clone());
+        // block has been announced by the second peer
+        peers.hash_known_as(2, test_data::genesis().hash(), KnownHashType::Block);
+
+        executor.execute(Task::RelayNewBlock(test_data::genesis().into()));
+        executor.execute(Task::RelayNewBlock(test_data::genesis().into()));
+        assert_eq!(c1.messages.lock().get("inventory"), Some(&1));
+        assert_eq!(c2.messages.lock().get("inventory"), None);
+    }
+
+    #[test]
+    fn inventory_includes_known_blocks() {
+        let peers = Arc::new(PeersImpl::default());
+        let executor = LocalSynchronizationTaskExecutor::new(peers.clone(), RelayConfig::default());
+
+        let c1 = DummyOutboundSyncConnection::new();
+        peers.insert(1, Services::default(), c1.clone());
+        peers.hash_known_as(1, test_data::genesis().hash(), KnownHashType::Block);
+
+        let inventory = || {
+            types::Inv::with_inventory(vec![
+                InventoryVector::block(test_data::genesis().hash()),
+                InventoryVector::block(test_data::block_h1().hash()),
+            ])
+        };
+        // response to `getblocks` is sent, even if blocks are known by the peer
+        executor.execute(Task::Inventory(1, inventory()));
+        assert_eq!(c1.messages.lock().get("inventory"), Some(&1));
+        assert!(peers.is_hash_known_as(1, &test_data::block_h1().hash(), KnownHashType::Block));
+
+        executor.execute(Task::Inventory(1, inventory()));
+        assert_eq!(c1.messages.lock().get("inventory"), Some(&2));
+
+        // relayed block, known by the peer, is not announced
+        executor.execute(Task::RelayNewBlock(test_data::block_h1().into()));
+        assert_eq!(c1.messages.lock().get("inventory"), Some(&2));
+    }
+
+    #[test]
+    fn relay_new_block_with_limited_fanout() {
+        use std::thread::sleep;
+        let peers = Arc::new(PeersImpl::default());
+        let executor = LocalSynchronizationTaskExecutor::new(
+            peers.clone(),
+            RelayConfig {
+                immediate_announcements: Some(1),
+                announcement_delay_ms: 10,
+                trickle_interval_ms: 0,
+                prefer_outbound: true,
+            },
+        );
+
+        let c1 = DummyOutboundSyncConnection::with_direction(Direction::Inbound);
+        peers.insert(1, Services::default(), c1.clone());
+        let c2 = DummyOutboundSyncConnection::with_direction(Direction::Outbound);
+        peers.insert(2, Services::default(), c2.clone());
+
+        // outbound peer is announced immediately, inbound peer - after delay
+        executor.execute(Task::RelayNewBlock(test_data::genesis().into()));
+        assert_eq!(c1.messages.lock().get("inventory"), None);
+        assert_eq!(c2.messages.lock().get("inventory"), Some(&1));
+
+        sleep(time::Duration::from_millis(200));
+        assert_eq!(c1.messages.lock().get("inventory"), Some(&1));
+        assert_eq!(c2.messages.lock().get("inventory"), Some(&1));
+    }
+
+    #[test]
+    fn relay_new_block_trickles_delayed_announcements() {
+        use std::thread::sleep;
+        let peers = Arc::new(PeersImpl::default());
+        let executor = LocalSynchronizationTaskExecutor::new(
+            peers.clone(),
+            RelayConfig {
+                immediate_announcements: Some(1),
+                announcement_delay_ms: 10,
+                trickle_interval_ms: 50,
+                prefer_outbound: false,
+            },
+        );
+
+        let connections: Vec<_> = (0..4)
+            .map(|peer_index| {
+                let connection = DummyOutboundSyncConnection::new();
+                peers.insert(peer_index, Services::default(), connection.clone());
+                connection
+            })
+            .collect();
+        let announced = || {
+            connections
+                .iter()
+                .filter(|connection| connection.messages.lock().get("inventory") == Some(&1))
+                .count()
+        };
+
+        // single random peer is announced immediately, remaining peers - during trickle interval
+        executor.execute(Task::RelayNewBlock(test_data::genesis().into()));
+        assert_eq!(announced(), 1);
+
+        sleep(time::Duration::from_millis(300));
+        assert_eq!(announced(), 4);
+    }
+
+    #[test]
+    fn relay_announcement_delays_are_randomized() {
+        let config = RelayConfig {
+            immediate_announcements: Some(1),
+            announcement_delay_ms: 100,
+            trickle_interval_ms: 1000,
+            prefer_outbound: false,
+        };
+        let mut rng = StdRng::seed_from_u64(0);
+        let delays: Vec<_> = (0..100)
+            .map(|_| config.announcement_delay(&mut rng))
+            .collect();
+        assert!(delays
+            .iter()
+            .all(|delay| *delay >= time::Duration::from_millis(100)
+                && *delay <= time::Duration::from_millis(1100)));
+        assert!(delays.iter().any(|delay| *delay != delays[0]));
+
+        let config = RelayConfig {
+            trickle_interval_ms: 0,
+            ..config
+        };
+        assert_eq!(
+            config.announcement_delay(&mut rng),
+            time::Duration::from_millis(100)
+        );
+    }
+
+    #[test]
+    fn latency_executor_delays_tasks() {
+        let executor = LatencyTaskExecutor::new(DummyTaskExecutor::new(), 0);
+        executor.set_conditions(
+            1,
+            NetworkConditions {
+                latency_ms: 50,
+                ..Default::default()
+            },
+        );
+        executor.set_conditions(
+            2,
+            NetworkConditions {
+                latency_ms: 10,
+                ..Default::default()
+            },
+        );
+
+        executor.execute(Task::Ignore(1, 1));
+        executor.execute(Task::Ignore(2, 2));
+        executor.execute(Task::RelayNewBlock(test_data::genesis().into()));
+        assert_eq!(
+            executor.inner().take_tasks(),
+            vec![Task::RelayNewBlock(test_data::genesis().into())]
+        );
+
+        executor.advance_to(10);
+        assert_eq!(executor.inner().take_tasks(), vec![Task::Ignore(2, 2)]);
+        assert_eq!(executor.next_delivery_ms(), Some(50));
+        executor.advance_to(50);
+        assert_eq!(executor.inner().take_tasks(), vec![Task::Ignore(1, 1)]);
+        assert_eq!(executor.next_delivery_ms(), None);
+    }
+
+    #[test]
+    fn latency_executor_reorders_and_drops_tasks() {
+        let executor = LatencyTaskExecutor::new(DummyTaskExecutor::new(), 0);
+        executor.set_conditions(
+            1,
+            NetworkConditions {
+                latency_ms: 10,
+                reorder_rate: 1f64,
+                ..Default::default()
+            },
+        );
+        executor.execute(Task::Ignore(1, 1));
+        executor.set_conditions(
+            1,
+            NetworkConditions {
+                latency_ms: 10,
+                ..Default::default()
+            },
+        );
+        executor.execute(Task::Ignore(1, 2));
+
+        executor.advance_to(100);
+        assert_eq!(
+            executor.inner().take_tasks(),
+            vec![Task::Ignore(1, 2), Task::Ignore(1, 1)]
+        );
+
+        executor.set_conditions(
+            1,
+            NetworkConditions {
+                drop_rate: 1f64,
+                ..Default::default()
+            },
+        );
+        executor.execute(Task::Ignore(1, 3));
+        executor.advance_to(200);
+        assert_eq!(executor.dropped(), 1);
+        assert_eq!(executor.inner().take_tasks(), vec![]);
+    }
 }
@@ -7,18 +7,17 @@ use std::time::Duration;
 use synchronization_client_core::{ClientCore, SynchronizationClientCore};
 use synchronization_executor::TaskExecutor;
 use synchronization_peers_tasks::{PeersTasks, TrustLevel};
-use time::precise_time_s;
 use types::PeersRef;
-use utils::OrphanBlocksPool;
+use utils::{precise_time_s, OrphanBlocksPool};
 
 /// Management interval (in ms)
-const MANAGEMENT_INTERVAL_MS: u64 = 10 * 1000;
-/// Response time before getting block to decrease peer score
-const DEFAULT_NEW_PEER_BLOCK_FAILURE_INTERVAL_MS: u32 = 5 * 1000;
+pub const MANAGEMENT_INTERVAL_MS: u64 = 10 * 1000;
+/// Response time before getting block to mark trusted peer as stalling
+pub const DEFAULT_BLOCK_STALL_TIMEOUT_S: u32 = 20;
+/// New peers are marked as stalling after this fraction of block stall timeout
+const NEW_PEER_BLOCK_STALL_TIMEOUT_DIVISOR: u32 = 4;
 /// Response time before getting headers to decrease peer score
 const DEFAULT_NEW_PEER_HEADERS_FAILURE_INTERVAL_MS: u32 = 5 * 1000;
-/// Response time before getting block to decrease peer score
-const DEFAULT_TRUSTED_PEER_BLOCK_FAILURE_INTERVAL_MS: u32 = 20 * 1000;
 /// Response time before getting headers to decrease peer score
 const DEFAULT_TRUSTED_PEER_HEADERS_FAILURE_INTERVAL_MS: u32 = 20 * 1000;
 /// Unknown orphan block removal time
@@ -37,7 +36,10 @@ pub struct ManagementWorker {
 }
 
 impl ManagementWorker {
-    pub fn new<T: TaskExecutor>(core: Weak<Mutex<SynchronizationClientCore<T>>>) -> Self {
+    pub fn new<T: TaskExecutor>(
+        core: Weak<Mutex<SynchronizationClientCore<T>>>,
+        peers_config: ManagePeersConfig,
+    ) -> Self {
         let is_stopping = Arc::new(Mutex::new(false));
         let stopping_event = Arc::new(Condvar::new());
         ManagementWorker {
@@ -46,7 +48,14 @@ impl ManagementWorker {
             thread: Some(
                 thread::Builder::new()
                     .name("Sync management thread".to_string())
-                    .spawn(move || ManagementWorker::worker_proc(is_stopping, stopping_event, core))
+                    .spawn(move || {
+                        ManagementWorker::worker_proc(
+                            is_stopping,
+                            stopping_event,
+                            core,
+                            peers_config,
+                        )
+                    })
                     .expect("Error creating management thread"),
             ),
         }
@@ -56,8 +65,8 @@ impl ManagementWorker {
         is_stopping: Arc<Mutex<bool>>,
         stopping_event: Arc<Condvar>,
         core: Weak<Mutex<SynchronizationClientCore<T>>>,
+        peers_config: ManagePeersConfig,
     ) {
-        let peers_config = ManagePeersConfig::default();
         let unknown_config = ManageUnknownBlocksConfig::default();
 
         loop {
@@ -85,45 +94,7 @@ impl ManagementWorker {
                 Some(core) => core,
             };
 
-            let mut core = core.lock();
-            // trace synchronization state
-            core.print_synchronization_information();
-            // execute management tasks if not saturated
-            if core.state().is_synchronizing() || core.state().is_nearly_saturated() {
-                let (blocks_to_request, blocks_to_forget) = manage_synchronization_peers_blocks(
-                    &peers_config,
-                    core.peers(),
-                    core.peers_tasks(),
-                );
-                core.forget_failed_blocks(&blocks_to_forget);
-                core.execute_synchronization_tasks(
-                    if blocks_to_request.is_empty() {
-                        None
-                    } else {
-                        Some(blocks_to_request)
-                    },
-                    if blocks_to_forget.is_empty() {
-                        None
-                    } else {
-                        Some(blocks_to_forget)
-                    },
-                );
-
-                manage_synchronization_peers_headers(
-                    &peers_config,
-                    core.peers(),
-                    core.peers_tasks(),
-                );
-            } else {
-                // only remove orphaned blocks when not in synchronization state
-                if let Some(orphans_to_remove) =
-                    manage_unknown_orphaned_blocks(&unknown_config, core.orphaned_blocks_pool())
-                {
-                    for orphan_to_remove in orphans_to_remove {
-                        core.chain().forget_block(&orphan_to_remove);
-                    }
-                }
-            }
+            manage_synchronization(&mut core.lock(), &peers_config, &unknown_config);
         }
 
         trace!(target: "sync", "Stopping sync management thread");
@@ -140,8 +111,53 @@ impl Drop for ManagementWorker {
     }
 }
 
+/// Execute single round of synchronization management
+pub fn manage_synchronization<T: TaskExecutor>(
+    core: &mut SynchronizationClientCore<T>,
+    peers_config: &ManagePeersConfig,
+    unknown_config: &ManageUnknownBlocksConfig,
+) {
+    // trace synchronization state
+    core.print_synchronization_information();
+    // blocks could also be requested in saturated state (after inventory announcement)
+    // => reschedule requests of stalling peers in any state
+    let (blocks_to_request, blocks_to_forget) =
+        manage_synchronization_peers_blocks(peers_config, core.peers(), core.peers_tasks());
+    core.forget_failed_blocks(&blocks_to_forget);
+    let is_synchronizing = core.state().is_synchronizing() || core.state().is_nearly_saturated();
+    if is_synchronizing || !blocks_to_request.is_empty() || !blocks_to_forget.is_empty() {
+        core.execute_synchronization_tasks(
+            if blocks_to_request.is_empty() {
+                None
+            } else {
+                Some(blocks_to_request)
+            },
+            if blocks_to_forget.is_empty() {
+                None
+            } else {
+                Some(blocks_to_forget)
+            },
+        );
+    }
+
+    // execute management tasks if not saturated
+    if is_synchronizing {
+        manage_synchronization_peers_headers(peers_config, core.peers(), core.peers_tasks());
+    } else {
+        // only remove orphaned blocks when not in synchronization state
+        if let Some(orphans_to_remove) =
+            manage_unknown_orphaned_blocks(unknown_config, core.orphaned_blocks_pool())
+        {
+            for orphan_to_remove in orphans_to_remove {
+                core.chain().forget_block(&orphan_to_remove);
+            }
+        }
+    }
+}
+
 /// Peers management configuration
 pub struct ManagePeersConfig {
+    /// Time interval (in milliseconds) to wait block from the new peer before marking it as stalling && reexecuting tasks
     pub new_block_failure_interval_ms: u32,
     /// Time interval (in milliseconds) to wait headers from the peer before penalizing && reexecuting tasks
     pub new_headers_failure_interval_ms: u32,
@@ -151,17 +167,27 @@ pub struct ManagePeersConfig {
     pub trusted_headers_failure_interval_ms: u32,
 }
 
-impl Default for ManagePeersConfig {
-    fn default() -> Self {
+impl ManagePeersConfig {
+    /// Create configuration, where trusted peer is marked as stalling if it has not delivered
+    /// requested block in `block_stall_timeout_s` seconds.
+    pub fn with_block_stall_timeout(block_stall_timeout_s: u32) -> Self {
+        let block_stall_timeout_ms = block_stall_timeout_s.saturating_mul(1000);
         ManagePeersConfig {
-            new_block_failure_interval_ms: DEFAULT_NEW_PEER_BLOCK_FAILURE_INTERVAL_MS,
+            new_block_failure_interval_ms: block_stall_timeout_ms
+                / NEW_PEER_BLOCK_STALL_TIMEOUT_DIVISOR,
             new_headers_failure_interval_ms: DEFAULT_NEW_PEER_HEADERS_FAILURE_INTERVAL_MS,
-            trusted_block_failure_interval_ms: DEFAULT_TRUSTED_PEER_BLOCK_FAILURE_INTERVAL_MS,
+            trusted_block_failure_interval_ms: block_stall_timeout_ms,
             trusted_headers_failure_interval_ms: DEFAULT_TRUSTED_PEER_HEADERS_FAILURE_INTERVAL_MS,
         }
     }
 }
 
+impl Default for ManagePeersConfig {
+    fn default() -> Self {
+        ManagePeersConfig::with_block_stall_timeout(DEFAULT_BLOCK_STALL_TIMEOUT_S)
+    }
+}
+
 /// Unknown blocks management configuration
 pub struct ManageUnknownBlocksConfig {
     /// Time interval (in milliseconds) to wait before removing unknown blocks from in-memory pool
@@ -179,7 +205,8 @@ impl Default for ManageUnknownBlocksConfig {
     }
 }
 
-/// Manage stalled synchronization peers blocks tasks
+/// Manage stalled synchronization peers blocks tasks.
+/// Blocks, requested from stalling peers, are returned to be requested from other peers
 pub fn manage_synchronization_peers_blocks(
     config: &ManagePeersConfig,
     peers: PeersRef,
@@ -211,9 +238,16 @@ pub fn manage_synchronization_peers_blocks(
             break;
         }
 
-        // decrease score && move to the idle queue
-        warn!(target: "sync", "Failed to get requested block from peer#{} in {:.2} seconds.", worst_peer_index, time_diff);
+        // mark as stalling && decrease score && move to the idle queue
         let failed_blocks = peers_tasks.reset_blocks_tasks(worst_peer_index);
+        let too_many_stalls = peers_tasks.on_peer_block_stall(worst_peer_index);
+        warn!(
+            target: "sync",
+            "Failed to get requested block from peer#{} in {:.2} seconds. Peer is stalling ({} times)",
+            worst_peer_index,
+            time_diff,
+            peers_tasks.get_peer_stats(worst_peer_index).map(|s| s.stalls()).unwrap_or_default()
+        );
 
         // mark blocks as failed
         let (normal_blocks, failed_blocks) = peers_tasks.on_blocks_failure(failed_blocks);
@@ -221,7 +255,7 @@ pub fn manage_synchronization_peers_blocks(
         blocks_to_forget.extend(failed_blocks);
 
         // if peer failed many times => forget it
-        if peers_tasks.on_peer_block_failure(worst_peer_index) {
+        if too_many_stalls {
             warn!(target: "sync", "Too many failures for peer#{}. Excluding from synchronization.", worst_peer_index);
             peers_tasks.unuseful_peer(worst_peer_index);
             peers.misbehaving(worst_peer_index, &format!("Too many failures."));
@@ -389,6 +423,31 @@ mod tests {
         assert!(idle_peers.contains(&2));
     }
 
+    #[test]
+    fn manage_stalling_peer() {
+        use std::thread::sleep;
+        use std::time::Duration;
+        let config = ManagePeersConfig::with_block_stall_timeout(0);
+        let mut peers = PeersTasks::default();
+        peers.useful_peer(1);
+        peers.useful_peer(2);
+        peers.on_blocks_requested(1, &vec![H256::from(0), H256::from(1)]);
+        peers.on_block_received(1, &H256::from(0));
+        sleep(Duration::from_millis(1));
+
+        // outstanding block is rescheduled && peer is not asked for it again
+        let (blocks_to_request, blocks_to_forget) = manage_synchronization_peers_blocks(
+            &config,
+            Arc::new(PeersImpl::default()),
+            &mut peers,
+        );
+        assert_eq!(blocks_to_request, vec![H256::from(1)]);
+        assert_eq!(blocks_to_forget, vec![]);
+        assert_eq!(peers.get_peer_stats(1).unwrap().stalls(), 1);
+        assert!(peers.is_stalling(1));
+        assert_eq!(peers.useful_peers_for_blocks(), vec![2]);
+    }
+
     #[test]
     fn manage_unknown_blocks_good() {
         let config = ManageUnknownBlocksConfig {
@@ -1,11 +1,17 @@
 use chain::IndexedBlock;
 use message::Services;
 use p2p::OutboundSyncConnectionRef;
-use parking_lot::RwLock;
+use parking_lot::{Mutex, RwLock};
 use primitives::hash::H256;
 use std::collections::HashMap;
+use std::net::IpAddr;
 use types::PeerIndex;
-use utils::{ConnectionFilter, KnownHashType};
+use utils::{
+    ConnectionFilter, KnownHashType, MisbehaviorScores, BAN_SCORE, DOS_SCORE, MISBEHAVING_SCORE,
+};
+
+/// Weight of the new round-trip time sample in the rolling latency estimate
+const LATENCY_SAMPLE_WEIGHT: f64 = 0.25;
 
 /// Block announcement type
 #[derive(Debug, Clone, Copy)]
@@ -52,6 +58,12 @@ pub trait PeersContainer {
     fn misbehaving(&self, peer_index: PeerIndex, reason: &str);
     /// Close and remove peer connection due to detected DOS attempt
     fn dos(&self, peer_index: PeerIndex, reason: &str);
+    /// Misbehavior score of the peer address. Score is kept after peer disconnects
+    fn misbehavior_score(&self, address: &IpAddr) -> u32;
+    /// Update rolling latency estimate with measured round-trip time (in seconds)
+    fn note_latency(&self, peer_index: PeerIndex, rtt: f64);
+    /// Rolling latency estimate (in seconds). None if round-trip time has never been measured
+    fn latency(&self, peer_index: PeerIndex) -> Option<f64>;
 }
 
 /// Filters for peers connections
@@ -97,6 +109,8 @@ struct Peer {
     pub block_announcement_type: BlockAnnouncementType,
     /// Transaction announcement type
     pub transaction_announcement_type: TransactionAnnouncementType,
+    /// Rolling estimate of round-trip time
+    pub latency: Option<f64>,
 }
 
 /// Default implementation of connectd peers container
@@ -105,6 +119,8 @@ pub struct PeersImpl {
     /// All connected peers. Most of times this field is accessed, it is accessed in read mode.
     /// So this lock shouldn't be a performance problem.
     peers: RwLock<HashMap<PeerIndex, Peer>>,
+    /// Misbehavior scores of connected and recently disconnected peers.
+    scores: Mutex<MisbehaviorScores>,
 }
 
 impl Peer {
@@ -115,8 +131,33 @@ impl Peer {
             filter: ConnectionFilter::default(),
             block_announcement_type: BlockAnnouncementType::SendInventory,
             transaction_announcement_type: TransactionAnnouncementType::SendInventory,
+            latency: None,
+        }
+    }
+}
+
+impl PeersImpl {
+    /// Create peers container, which remembers misbehavior for given number of seconds
+    pub fn new(misbehavior_score_ttl_s: u32) -> Self {
+        PeersImpl {
+            peers: RwLock::default(),
+            scores: Mutex::new(MisbehaviorScores::new(misbehavior_score_ttl_s)),
         }
     }
+
+    /// Removes peer, which is going to be disconnected. Whitelisted peers are never disconnected
+    fn remove_unless_whitelisted(&self, peer_index: PeerIndex, reason: &str) -> Option<Peer> {
+        let mut peers = self.peers.write();
+        let is_whitelisted = match peers.get(&peer_index) {
+            Some(peer) => peer.connection.is_whitelisted(),
+            None => return None,
+        };
+        if is_whitelisted {
+            warn!(target: "sync", "Not disconnecting from whitelisted peer#{}: {}", peer_index, reason);
+            return None;
+        }
+        peers.remove(&peer_index)
+    }
 }
 
 impl Peers for PeersImpl {
@@ -159,6 +200,16 @@ impl PeersContainer for PeersImpl {
         services: Services,
         connection: OutboundSyncConnectionRef,
     ) {
+        // peer can't reset its score by reconnecting
+        if !connection.is_whitelisted() {
+            let score = self.misbehavior_score(&connection.address().ip());
+            if score >= BAN_SCORE {
+                warn!(target: "sync", "Disconnecting from peer#{} due to misbehavior score {}", peer_index, score);
+                connection.close();
+                return;
+            }
+        }
+
         trace!(target: "sync", "Connected to peer#{}", peer_index);
         assert!(self
             .peers
@@ -174,18 +225,46 @@ impl PeersContainer for PeersImpl {
     }
 
     fn misbehaving(&self, peer_index: PeerIndex, reason: &str) {
-        if let Some(peer) = self.peers.write().remove(&peer_index) {
-            warn!(target: "sync", "Disconnecting from peer#{} due to misbehavior: {}", peer_index, reason);
+        if let Some(peer) = self.remove_unless_whitelisted(peer_index, reason) {
+            let score = self
+                .scores
+                .lock()
+                .add(&peer.connection.address().ip(), MISBEHAVING_SCORE);
+            warn!(target: "sync", "Disconnecting from peer#{} due to misbehavior (score {}): {}", peer_index, score, reason);
             peer.connection.close();
         }
     }
 
     fn dos(&self, peer_index: PeerIndex, reason: &str) {
-        if let Some(peer) = self.peers.write().remove(&peer_index) {
-            warn!(target: "sync", "Disconnecting from peer#{} due to DoS: {}", peer_index, reason);
+        if let Some(peer) = self.remove_unless_whitelisted(peer_index, reason) {
+            let score = self
+                .scores
+                .lock()
+                .add(&peer.connection.address().ip(), DOS_SCORE);
+            warn!(target: "sync", "Disconnecting from peer#{} due to DoS (score {}): {}", peer_index, score, reason);
             peer.connection.close();
         }
     }
+
+    fn misbehavior_score(&self, address: &IpAddr) -> u32 {
+        self.scores.lock().score(address)
+    }
+
+    fn note_latency(&self, peer_index: PeerIndex, rtt: f64) {
+        if let Some(peer) = self.peers.write().get_mut(&peer_index) {
+            peer.latency = Some(match peer.latency {
+                Some(latency) => latency + (rtt - latency) * LATENCY_SAMPLE_WEIGHT,
+                None => rtt,
+            });
+        }
+    }
+
+    fn latency(&self, peer_index: PeerIndex) -> Option<f64> {
+        self.peers
+            .read()
+            .get(&peer_index)
+            .and_then(|peer| peer.latency)
+    }
 }
 
 impl PeersFilters for PeersImpl {
@@ -240,3 +319,80 @@ impl PeersOptions for PeersImpl {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{Peers, PeersContainer, PeersImpl};
+    use inbound_connection::tests::DummyOutboundSyncConnection;
+    use message::Services;
+    use p2p::OutboundSyncConnection;
+    use utils::MISBEHAVING_SCORE;
+
+    #[test]
+    fn whitelisted_peer_is_not_disconnected() {
+        let peers = PeersImpl::default();
+        peers.insert(1, Services::default(), DummyOutboundSyncConnection::new());
+        peers.insert(
+            2,
+            Services::default(),
+            DummyOutboundSyncConnection::whitelisted(),
+        );
+
+        peers.misbehaving(1, "test");
+        peers.misbehaving(2, "test");
+        peers.dos(2, "test");
+        assert!(peers.connection(1).is_none());
+        assert!(peers.connection(2).is_some());
+    }
+
+    #[test]
+    fn latency_is_rolling_average_of_round_trip_times() {
+        let peers = PeersImpl::default();
+        peers.insert(1, Services::default(), DummyOutboundSyncConnection::new());
+        assert_eq!(peers.latency(1), None);
+
+        peers.note_latency(1, 1.0);
+        assert_eq!(peers.latency(1), Some(1.0));
+        peers.note_latency(1, 3.0);
+        assert_eq!(peers.latency(1), Some(1.5));
+
+        // latency of unknown peers is not remembered
+        peers.note_latency(2, 1.0);
+        assert_eq!(peers.latency(2), None);
+        peers.remove(1);
+        assert_eq!(peers.latency(1), None);
+    }
+
+    #[test]
+    fn misbehavior_score_is_kept_after_reconnect() {
+        let peers = PeersImpl::default();
+        let address = "10.1.0.1:8333".parse().unwrap();
+        for peer_index in 1..6 {
+            peers.insert(
+                peer_index,
+                Services::default(),
+                DummyOutboundSyncConnection::with_address(address),
+            );
+            assert!(peers.connection(peer_index).is_some());
+            peers.misbehaving(peer_index, "test");
+        }
+        assert_eq!(
+            peers.misbehavior_score(&address.ip()),
+            5 * MISBEHAVING_SCORE
+        );
+
+        // banned peer is disconnected right after connecting
+        peers.insert(
+            6,
+            Services::default(),
+            DummyOutboundSyncConnection::with_address(address),
+        );
+        assert!(peers.connection(6).is_none());
+
+        // whitelisted peers are never scored
+        let whitelisted = DummyOutboundSyncConnection::whitelisted();
+        peers.insert(7, Services::default(), whitelisted.clone());
+        peers.misbehaving(7, "test");
+        assert_eq!(peers.misbehavior_score(&whitelisted.address().ip()), 0);
+    }
+}
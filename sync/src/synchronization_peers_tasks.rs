@@ -3,9 +3,8 @@ use primitives::hash::H256;
 use std::cmp::Ordering;
 use std::collections::{HashMap, HashSet};
 use std::fmt;
-use time::precise_time_s;
 use types::PeerIndex;
-use utils::AverageSpeedMeter;
+use utils::{precise_time_s, AverageSpeedMeter};
 
 /// Max peer failures # before excluding from sync process
 const MAX_PEER_FAILURES: usize = 4;
@@ -33,6 +32,8 @@ pub struct PeersTasks {
     all: HashSet<PeerIndex>,
     /// All unuseful peers
     unuseful: HashSet<PeerIndex>,
+    /// Peers, which have not delivered requested blocks in time (until they deliver any requested block)
+    stalling: HashSet<PeerIndex>,
     /// All peers without pending headers requests
     idle_for_headers: HashSet<PeerIndex>,
     /// All peers without pending blocks requests
@@ -77,6 +78,8 @@ pub enum TrustLevel {
 pub struct PeerStats {
     /// Number of blocks requests failures
     failures: usize,
+    /// Number of times peer has been stalling blocks download
+    stalls: usize,
     /// Average block response time meter
     speed: AverageSpeedMeter,
     /// Peer trust level.
@@ -114,6 +117,28 @@ impl PeersTasks {
         self.all.difference(&self.unuseful).cloned().collect()
     }
 
+    /// Get useful peers, which are not stalling blocks download.
+    /// If all useful peers are stalling, returns all useful peers.
+    pub fn useful_peers_for_blocks(&self) -> Vec<PeerIndex> {
+        let useful_peers = self.useful_peers();
+        let responsive_peers: Vec<_> = useful_peers
+            .iter()
+            .filter(|peer_index| !self.stalling.contains(peer_index))
+            .cloned()
+            .collect();
+        if responsive_peers.is_empty() {
+            useful_peers
+        } else {
+            responsive_peers
+        }
+    }
+
+    /// Is peer stalling blocks download?
+    #[cfg(test)]
+    pub fn is_stalling(&self, peer_index: PeerIndex) -> bool {
+        self.stalling.contains(&peer_index)
+    }
+
     /// Get idle peers for headers request.
     pub fn idle_peers_for_headers(&self) -> &HashSet<PeerIndex> {
         &self.idle_for_headers
@@ -124,8 +149,12 @@ impl PeersTasks {
         &self.idle_for_blocks
     }
 
-    /// Sort peers for blocks request
-    pub fn sort_peers_for_blocks(&self, peers: &mut Vec<PeerIndex>) {
+    /// Sort peers for blocks request. Peers with the same speed (e.g. peers, which haven't
+    /// delivered any blocks yet) are sorted by latency, peers with unknown latency go last.
+    pub fn sort_peers_for_blocks<F>(&self, peers: &mut Vec<PeerIndex>, latency: F)
+    where
+        F: Fn(PeerIndex) -> Option<f64>,
+    {
         peers.sort_by(|left, right| {
             let left_speed = self
                 .stats
@@ -141,6 +170,15 @@ impl PeersTasks {
             right_speed
                 .partial_cmp(&left_speed)
                 .unwrap_or(Ordering::Equal)
+                // lower latency => better
+                .then_with(|| match (latency(*left), latency(*right)) {
+                    (Some(left), Some(right)) => {
+                        left.partial_cmp(&right).unwrap_or(Ordering::Equal)
+                    }
+                    (Some(_), None) => Ordering::Less,
+                    (None, Some(_)) => Ordering::Greater,
+                    (None, None) => Ordering::Equal,
+                })
         })
     }
 
@@ -201,6 +239,7 @@ impl PeersTasks {
 
         self.all.remove(&peer_index);
         self.unuseful.remove(&peer_index);
+        self.stalling.remove(&peer_index);
         self.idle_for_headers.remove(&peer_index);
         self.idle_for_blocks.remove(&peer_index);
         self.headers_requests.remove(&peer_index);
@@ -226,7 +265,8 @@ impl PeersTasks {
                 return;
             };
 
-        // it was requested block => update block response time
+        // it was requested block => peer is not stalling anymore && update block response time
+        self.stalling.remove(&peer_index);
         self.stats.get_mut(&peer_index).map(|br| {
             if br.failures > 0 {
                 br.failures -= 1;
@@ -333,6 +373,16 @@ impl PeersTasks {
         self.penalize(peer_index)
     }
 
+    /// Peer has not delivered any of requested blocks in time.
+    /// Returns true if the peer has been stalling too many times to keep connection.
+    pub fn on_peer_block_stall(&mut self, peer_index: PeerIndex) -> bool {
+        self.stalling.insert(peer_index);
+        if let Some(stats) = self.stats.get_mut(&peer_index) {
+            stats.stalls += 1;
+        }
+        self.on_peer_block_failure(peer_index)
+    }
+
     /// We have failed to get headers from peer during given period
     pub fn on_peer_headers_failure(&mut self, peer_index: PeerIndex) -> bool {
         // we never penalize peers for header requests failures
@@ -399,6 +449,7 @@ impl PeerStats {
     pub fn new() -> Self {
         PeerStats {
             failures: 0,
+            stalls: 0,
             speed: AverageSpeedMeter::with_inspect_items(BLOCKS_TO_INSPECT),
             trust: TrustLevel::Suspicious,
         }
@@ -408,6 +459,10 @@ impl PeerStats {
         self.trust
     }
 
+    pub fn stalls(&self) -> usize {
+        self.stalls
+    }
+
     #[cfg(test)]
     pub fn set_trust(&mut self, trust: TrustLevel) {
         self.trust = trust;
@@ -643,6 +698,33 @@ mod tests {
         assert_eq!(blocks_to_forget, vec![H256::from(1)]);
     }
 
+    #[test]
+    fn peer_block_stalls() {
+        let mut peers = PeersTasks::default();
+        peers.useful_peer(1);
+        peers.useful_peer(2);
+        peers.on_blocks_requested(1, &vec![H256::from(1), H256::from(2)]);
+        peers.on_block_received(1, &H256::from(1));
+
+        // stalling peer is not used for blocks while other peers are available
+        assert_eq!(peers.reset_blocks_tasks(1), vec![H256::from(2)]);
+        assert!(!peers.on_peer_block_stall(1));
+        assert!(peers.is_stalling(1));
+        assert_eq!(peers.get_peer_stats(1).unwrap().stalls(), 1);
+        assert_eq!(peers.useful_peers_for_blocks(), vec![2]);
+
+        // peer is not stalling after it delivers requested block
+        peers.on_blocks_requested(1, &vec![H256::from(2)]);
+        peers.on_block_received(1, &H256::from(2));
+        assert!(!peers.is_stalling(1));
+
+        // repeated stalls exclude peer
+        for _ in 0..MAX_PEER_FAILURES {
+            peers.on_peer_block_stall(1);
+        }
+        assert!(peers.on_peer_block_stall(1));
+    }
+
     #[test]
     fn peer_sort_peers_for_blocks() {
         let mut peers = PeersTasks::default();
@@ -659,8 +741,23 @@ mod tests {
         peers.on_block_received(1, &H256::from(2));
 
         let mut peers_for_blocks: Vec<PeerIndex> = vec![1, 2];
-        peers.sort_peers_for_blocks(&mut peers_for_blocks);
+        peers.sort_peers_for_blocks(&mut peers_for_blocks, |_| None);
         assert_eq!(peers_for_blocks[0], 2);
         assert_eq!(peers_for_blocks[1], 1);
+
+        // speed is preferred to latency
+        peers.sort_peers_for_blocks(&mut peers_for_blocks, |peer| Some(peer as f64));
+        assert_eq!(peers_for_blocks, vec![2, 1]);
+
+        // peers without known speed are sorted by latency
+        let mut peers_for_blocks: Vec<PeerIndex> = vec![3, 4, 5, 6];
+        let latencies = |peer| match peer {
+            3 => Some(0.5),
+            4 => None,
+            5 => Some(0.1),
+            _ => Some(0.3),
+        };
+        peers.sort_peers_for_blocks(&mut peers_for_blocks, latencies);
+        assert_eq!(peers_for_blocks, vec![5, 6, 3, 4]);
     }
 }
@@ -1,21 +1,40 @@
-use message::{common, types};
-use parking_lot::{Condvar, Mutex};
+use futures::sync::{mpsc, oneshot};
+use futures::{Async, Future, Poll, Stream};
+use futures_cpupool::{Builder as CpuPoolBuilder, CpuFuture, CpuPool};
+use linked_hash_map::LinkedHashMap;
+use message::{common, types, SharedPayload};
+use parking_lot::Mutex;
 use primitives::hash::H256;
-use std::collections::hash_map::Entry;
-use std::collections::{HashMap, VecDeque};
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::collections::VecDeque;
 use std::sync::Arc;
 use std::thread;
+use std::{cmp, mem};
+use storage::ChainMembership;
 use synchronization_executor::{Task, TaskExecutor};
-use types::{BlockHeight, ExecutorRef, PeerIndex, PeersRef, RequestId, StorageRef};
+use tokio_core::reactor::{Core, Remote};
+use tracing::trace;
+use types::{
+    BlockHeight, ExecutorRef, MemoryBudgetRef, PeerIndex, PeersRef, RequestId,
+    SerializedBlockCacheRef, StorageRef,
+};
+use utils::{
+    precise_time_s, serialize_block, LocatorCache, MemoryConsumer, ServeOutcome, ServerLogSampler,
+    ServerLogStats, UploadBudget, UploadDecision, UploadLimit, DEFAULT_LOCATOR_CACHE_SIZE,
+};
+
+/// Max number of high-priority tasks of single peer, served while its normal-priority tasks are waiting.
+const MAX_HIGH_PRIORITY_TASKS_IN_ROW: usize = 8;
+/// Max time (in seconds) single 'getdata' response stream is served. The rest of the stream is
+/// answered with 'notfound', so that peer could request remaining blocks from other peers.
+pub const SERVER_STREAM_TIMEOUT_S: f64 = 60.0;
 
 /// Synchronization server task
 #[derive(Debug, PartialEq)]
 pub enum ServerTask {
     /// Serve 'getdata' request
     GetData(PeerIndex, types::GetData),
-    /// Serve reversed 'getdata' request
-    ReversedGetData(PeerIndex, types::GetData, types::NotFound),
+    /// Serve reversed 'getdata' request, until given deadline
+    ReversedGetData(PeerIndex, types::GetData, types::NotFound, f64),
     /// Serve 'getblocks' request
     GetBlocks(PeerIndex, types::GetBlocks),
     /// Serve 'getheaders' request
@@ -24,27 +43,128 @@ pub enum ServerTask {
     Mempool(PeerIndex),
 }
 
+/// Synchronization server task priority
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ServerTaskPriority {
+    /// Cheap, latency-sensitive requests
+    High,
+    /// Everything else, including long-running 'getdata' streams
+    Normal,
+}
+
 /// Synchronization server
 pub trait Server: Send + Sync + 'static {
     /// Execute single synchronization task
     fn execute(&self, task: ServerTask);
     /// Called when connection is closed
     fn on_disconnect(&self, peer_index: PeerIndex);
+    /// Change sampling of serving decisions logs: 1 of `sampling` tasks is fully logged
+    fn set_log_sampling(&self, sampling: u32);
+    /// Counters of serving outcomes
+    fn log_stats(&self) -> ServerLogStats;
+    /// Change max number of bytes, served to single peer. None if uploads are not limited
+    fn set_upload_limit(&self, limit: Option<UploadLimit>);
+    /// Peers with queued tasks, in order they have sent their first request
+    fn queue_state(&self) -> Vec<PeerQueueState>;
+}
+
+/// Queued tasks of single peer, as seen by scheduling policy
+#[derive(Debug, Clone, PartialEq)]
+pub struct PeerQueueState {
+    /// Peer index
+    pub peer_index: PeerIndex,
+    /// Number of queued high-priority tasks
+    pub high: usize,
+    /// Number of queued normal-priority tasks
+    pub normal: usize,
+    /// Number of high-priority tasks served since last normal-priority task
+    pub high_in_row: usize,
+    /// Is peer task currently served by some worker?
+    pub busy: bool,
 }
 
+/// Decides in which order queued tasks of single peer are served
+pub trait SchedulingPolicy: Send + Sync + 'static {
+    /// Selects priority of the next task of the peer. Task of other priority is served
+    /// if there are no tasks of selected priority
+    fn select_priority(&self, peer: &PeerQueueState) -> ServerTaskPriority;
+}
+
+/// Serves high-priority tasks of every peer first, but normal-priority tasks make progress
+/// when there's a long stream of high-priority tasks. Peers are served in round-robin order,
+/// because every peer has at most one task step waiting for the worker
+#[derive(Debug, Default)]
+pub struct RoundRobinPolicy;
+
 /// Synchronization requests server
 pub struct ServerImpl {
-    queue_ready: Arc<Condvar>,
-    queue: Arc<Mutex<ServerQueue>>,
-    worker_thread: Option<thread::JoinHandle<()>>,
+    /// Queues of peers, in order peers have sent their first request
+    peers: Mutex<LinkedHashMap<PeerIndex, PeerHandle>>,
+    workers: ServerWorkers,
+    policy: Arc<dyn SchedulingPolicy>,
+    memory_budget: MemoryBudgetRef,
+    upload_budget: Arc<Mutex<UploadBudget>>,
+    log_sampler: Arc<ServerLogSampler>,
+}
+
+/// Queue of single peer and the sender, waking up its worker
+struct PeerHandle {
+    queue: Arc<PeerQueue>,
+    /// When dropped, the worker completes after its current task step is served
+    wakeup: mpsc::UnboundedSender<()>,
 }
 
-/// Server tasks queue
-struct ServerQueue {
-    is_stopping: AtomicBool,
-    queue_ready: Arc<Condvar>,
-    peers_queue: VecDeque<usize>,
-    tasks_queue: HashMap<usize, VecDeque<ServerTask>>,
+type ServeFn = Arc<dyn Fn(ServerTask) -> Option<ServerTask> + Send + Sync>;
+
+/// Every peer has its own async tasks queue, served by the peer worker on the event loop.
+/// Worker serves single step of the task on the thread pool and only then moves to the next
+/// step, so responses are sent in order, while tasks of different peers never wait for each other
+/// in a shared queue.
+struct ServerWorkers {
+    remote: Remote,
+    pool: CpuPool,
+    serve: ServeFn,
+    /// Event loop runs while this sender or any of peer workers is alive
+    alive: Option<mpsc::UnboundedSender<()>>,
+    event_loop: Option<thread::JoinHandle<()>>,
+}
+
+/// Serves tasks of single peer, until peer is disconnected
+struct PeerWorker {
+    queue: Arc<PeerQueue>,
+    wakeups: mpsc::UnboundedReceiver<()>,
+    pool: CpuPool,
+    serve: ServeFn,
+    /// Task step, served on the thread pool
+    serving: Option<CpuFuture<Option<ServerTask>, ()>>,
+    _alive: mpsc::UnboundedSender<()>,
+}
+
+/// Tasks queue of single peer
+struct PeerQueue {
+    peer_index: PeerIndex,
+    tasks: Mutex<PeerTasks>,
+    /// Decides which task is served next
+    policy: Arc<dyn SchedulingPolicy>,
+    /// Memory budget, queued tasks are accounted in. Tasks are requests of peers, which are
+    /// never dropped, so they are accounted even when budget is exhausted
+    memory_budget: MemoryBudgetRef,
+}
+
+/// Queued tasks of single peer
+#[derive(Default)]
+struct PeerTasks {
+    /// High-priority tasks
+    high: VecDeque<ServerTask>,
+    /// Normal-priority tasks
+    normal: VecDeque<ServerTask>,
+    /// Number of high-priority tasks served since last normal-priority task
+    high_in_row: usize,
+    /// Is peer task currently served by the worker?
+    busy: bool,
+    /// Set when peer has disconnected or server is stopping. Queued tasks are dropped and
+    /// continuation of the served task (e.g. the rest of 'getdata' stream) is not queued
+    cancelled: bool,
 }
 
 /// Server tasks executor
@@ -58,15 +178,71 @@ where
     executor: ExecutorRef<T>,
     /// Storage reference
     storage: StorageRef,
+    /// Recently resolved block locators
+    locator_cache: Mutex<LocatorCache>,
+    /// Recently served and best chain blocks, shared by all peers
+    block_cache: SerializedBlockCacheRef,
+    /// Bytes, served to peers in response to 'getdata'
+    upload_budget: Arc<Mutex<UploadBudget>>,
+    /// Decides which tasks are fully logged
+    log_sampler: Arc<ServerLogSampler>,
 }
 
 impl Server for ServerImpl {
     fn execute(&self, task: ServerTask) {
-        self.queue.lock().add_task(task);
+        let peer_index = task.peer_index();
+        let mut peers = self.peers.lock();
+        if !peers.contains_key(&peer_index) {
+            let queue = Arc::new(PeerQueue::new(
+                peer_index,
+                self.policy.clone(),
+                self.memory_budget.clone(),
+            ));
+            let wakeup = self.workers.spawn(queue.clone());
+            peers.insert(
+                peer_index,
+                PeerHandle {
+                    queue: queue,
+                    wakeup: wakeup,
+                },
+            );
+        }
+
+        let peer = peers
+            .get(&peer_index)
+            .expect("peer handle is inserted above; qed");
+        peer.queue.add_task(task);
+        // worker is alive while its wakeup sender is alive
+        let _ = peer.wakeup.unbounded_send(());
     }
 
     fn on_disconnect(&self, peer_index: PeerIndex) {
-        self.queue.lock().remove_peer_tasks(peer_index);
+        // task, which is currently served, can't be interrupted => cancel its continuation
+        if let Some(peer) = self.peers.lock().remove(&peer_index) {
+            peer.queue.cancel();
+        }
+        self.upload_budget.lock().remove_peer(peer_index);
+    }
+
+    fn set_log_sampling(&self, sampling: u32) {
+        self.log_sampler.set_sampling(sampling);
+    }
+
+    fn log_stats(&self) -> ServerLogStats {
+        self.log_sampler.stats()
+    }
+
+    fn set_upload_limit(&self, limit: Option<UploadLimit>) {
+        self.upload_budget.lock().set_limit(limit);
+    }
+
+    fn queue_state(&self) -> Vec<PeerQueueState> {
+        self.peers
+            .lock()
+            .values()
+            .map(|peer| peer.queue.state())
+            .filter(|state| state.high != 0 || state.normal != 0)
+            .collect()
     }
 }
 
@@ -74,145 +250,323 @@ impl ServerTask {
     pub fn peer_index(&self) -> PeerIndex {
         match *self {
             ServerTask::GetData(peer_index, _)
-            | ServerTask::ReversedGetData(peer_index, _, _)
+            | ServerTask::ReversedGetData(peer_index, _, _, _)
             | ServerTask::GetBlocks(peer_index, _)
             | ServerTask::GetHeaders(peer_index, _, _)
             | ServerTask::Mempool(peer_index) => peer_index,
         }
     }
+
+    /// Approximate number of bytes, queued task occupies in memory
+    pub fn memory_size(&self) -> usize {
+        let inventory_size = |inventory: &[common::InventoryVector]| {
+            inventory.len() * mem::size_of::<common::InventoryVector>()
+        };
+        let locator_size = |locator: &[H256]| locator.len() * mem::size_of::<H256>();
+        mem::size_of::<ServerTask>()
+            + match *self {
+                ServerTask::GetData(_, ref message) => inventory_size(&message.inventory),
+                ServerTask::ReversedGetData(_, ref message, ref notfound, _) => {
+                    inventory_size(&message.inventory) + inventory_size(&notfound.inventory)
+                }
+                ServerTask::GetBlocks(_, ref message) => {
+                    locator_size(&message.block_locator_hashes)
+                }
+                ServerTask::GetHeaders(_, ref message, _) => {
+                    locator_size(&message.block_locator_hashes)
+                }
+                ServerTask::Mempool(_) => 0,
+            }
+    }
+
+    pub fn priority(&self) -> ServerTaskPriority {
+        match *self {
+            ServerTask::GetHeaders(_, _, _) | ServerTask::Mempool(_) => ServerTaskPriority::High,
+            ServerTask::GetData(_, _)
+            | ServerTask::ReversedGetData(_, _, _, _)
+            | ServerTask::GetBlocks(_, _) => ServerTaskPriority::Normal,
+        }
+    }
+}
+
+impl SchedulingPolicy for RoundRobinPolicy {
+    fn select_priority(&self, peer: &PeerQueueState) -> ServerTaskPriority {
+        let starving = peer.normal != 0 && peer.high_in_row >= MAX_HIGH_PRIORITY_TASKS_IN_ROW;
+        if starving {
+            ServerTaskPriority::Normal
+        } else {
+            ServerTaskPriority::High
+        }
+    }
 }
 
 impl ServerImpl {
-    pub fn new<T: TaskExecutor>(peers: PeersRef, storage: StorageRef, executor: Arc<T>) -> Self {
-        let executor = ServerTaskExecutor::new(peers, storage, executor);
-        let queue_ready = Arc::new(Condvar::new());
-        let queue = Arc::new(Mutex::new(ServerQueue::new(queue_ready.clone())));
-        let mut server = ServerImpl {
-            queue_ready: queue_ready.clone(),
-            queue: queue.clone(),
-            worker_thread: None,
+    pub fn new<T: TaskExecutor>(
+        peers: PeersRef,
+        storage: StorageRef,
+        executor: Arc<T>,
+        threads: usize,
+        upload_limit: Option<UploadLimit>,
+        block_cache: SerializedBlockCacheRef,
+        memory_budget: MemoryBudgetRef,
+    ) -> Self {
+        let upload_budget = Arc::new(Mutex::new(UploadBudget::new(upload_limit)));
+        let log_sampler = Arc::new(ServerLogSampler::default());
+        let executor = Arc::new(ServerTaskExecutor::new(
+            peers,
+            storage,
+            executor,
+            upload_budget.clone(),
+            log_sampler.clone(),
+            block_cache,
+        ));
+        let workers = ServerWorkers::new(
+            cmp::max(threads, 1),
+            Arc::new(move |task: ServerTask| executor.execute(task)),
+        );
+        ServerImpl {
+            peers: Mutex::new(LinkedHashMap::new()),
+            workers: workers,
+            policy: Arc::new(RoundRobinPolicy),
+            memory_budget: memory_budget,
+            upload_budget: upload_budget,
+            log_sampler: log_sampler,
+        }
+    }
+}
+
+impl Drop for ServerImpl {
+    fn drop(&mut self) {
+        // queued tasks are dropped and continuations of served tasks are not queued
+        {
+            let mut peers = self.peers.lock();
+            for peer in peers.values() {
+                peer.queue.cancel();
+            }
+            peers.clear();
+        }
+        // wait until every worker has completed its served task step
+        self.workers.stop();
+    }
+}
+
+impl ServerWorkers {
+    fn new(threads: usize, serve: ServeFn) -> Self {
+        let (alive, alive_receiver) = mpsc::unbounded::<()>();
+        let (remote_sender, remote_receiver) = oneshot::channel();
+        let event_loop = thread::Builder::new()
+            .name("Sync server event loop".into())
+            .spawn(move || {
+                let mut core = Core::new().expect("Error creating sync server event loop");
+                let _ = remote_sender.send(core.remote());
+                // nothing is ever sent => runs until server and all peer workers are dropped
+                let _ = core.run(alive_receiver.for_each(|_| Ok(())));
+            })
+            .expect("Error creating sync server event loop thread");
+        let remote = remote_receiver
+            .wait()
+            .expect("event loop thread sends remote before running the loop; qed");
+
+        ServerWorkers {
+            remote: remote,
+            pool: CpuPoolBuilder::new()
+                .pool_size(threads)
+                .name_prefix("Sync server worker #")
+                .create(),
+            serve: serve,
+            alive: Some(alive),
+            event_loop: Some(event_loop),
+        }
+    }
+
+    /// Start serving tasks of the peer queue. Returns sender, which wakes up the worker
+    fn spawn(&self, queue: Arc<PeerQueue>) -> mpsc::UnboundedSender<()> {
+        let (wakeup, wakeups) = mpsc::unbounded();
+        let worker = PeerWorker {
+            queue: queue,
+            wakeups: wakeups,
+            pool: self.pool.clone(),
+            serve: self.serve.clone(),
+            serving: None,
+            _alive: self
+                .alive
+                .clone()
+                .expect("workers are spawned by the server, which is not stopped yet; qed"),
         };
-        server.worker_thread = Some(thread::spawn(move || {
-            ServerImpl::server_worker(queue_ready, queue, executor);
-        }));
-        server
+        self.remote.spawn(move |_| worker);
+        wakeup
     }
 
-    fn server_worker<T: TaskExecutor>(
-        queue_ready: Arc<Condvar>,
-        queue: Arc<Mutex<ServerQueue>>,
-        executor: ServerTaskExecutor<T>,
-    ) {
+    /// Wait until all peer workers are completed and stop the event loop
+    fn stop(&mut self) {
+        self.alive = None;
+        if let Some(event_loop) = self.event_loop.take() {
+            event_loop.join().expect("Clean shutdown.");
+        }
+    }
+}
+
+impl Future for PeerWorker {
+    type Item = ();
+    type Error = ();
+
+    fn poll(&mut self) -> Poll<(), ()> {
         loop {
-            let task = {
-                let mut queue = queue.lock();
-                if queue.is_stopping.load(Ordering::SeqCst) {
-                    break;
+            if let Some(mut serving) = self.serving.take() {
+                match serving.poll() {
+                    Ok(Async::NotReady) => {
+                        self.serving = Some(serving);
+                        return Ok(Async::NotReady);
+                    }
+                    Ok(Async::Ready(continuation)) => self.queue.task_done(continuation),
+                    Err(_) => self.queue.task_done(None),
                 }
+            }
 
-                queue.next_task().or_else(|| {
-                    queue_ready.wait(&mut queue);
-                    queue.next_task()
-                })
+            // wakeups only tell that the queue has changed => consume all of them
+            let is_closed = loop {
+                match self.wakeups.poll() {
+                    Ok(Async::Ready(Some(_))) => continue,
+                    Ok(Async::NotReady) => break false,
+                    Ok(Async::Ready(None)) | Err(_) => break true,
+                }
             };
 
-            if let Some(task) = task {
-                if let Some(task) = executor.execute(task) {
-                    queue.lock().add_task_front(task);
+            match self.queue.next_task() {
+                Some(task) => {
+                    let serve = self.serve.clone();
+                    self.serving = Some(
+                        self.pool
+                            .spawn_fn(move || -> Result<_, ()> { Ok(serve(task)) }),
+                    );
                 }
+                // peer has disconnected or server is stopping
+                None if is_closed => return Ok(Async::Ready(())),
+                None => return Ok(Async::NotReady),
             }
         }
     }
 }
 
-impl Drop for ServerImpl {
-    fn drop(&mut self) {
-        if let Some(join_handle) = self.worker_thread.take() {
-            self.queue.lock().is_stopping.store(true, Ordering::SeqCst);
-            self.queue_ready.notify_one();
-            join_handle.join().expect("Clean shutdown.");
+impl PeerQueue {
+    pub fn new(
+        peer_index: PeerIndex,
+        policy: Arc<dyn SchedulingPolicy>,
+        memory_budget: MemoryBudgetRef,
+    ) -> Self {
+        PeerQueue {
+            peer_index: peer_index,
+            tasks: Mutex::new(PeerTasks::default()),
+            policy: policy,
+            memory_budget: memory_budget,
         }
     }
-}
 
-impl ServerQueue {
-    pub fn new(queue_ready: Arc<Condvar>) -> Self {
-        ServerQueue {
-            is_stopping: AtomicBool::new(false),
-            queue_ready: queue_ready,
-            peers_queue: VecDeque::new(),
-            tasks_queue: HashMap::new(),
-        }
+    /// Queued tasks of the peer
+    pub fn state(&self) -> PeerQueueState {
+        self.peer_state(&self.tasks.lock())
     }
 
-    pub fn next_task(&mut self) -> Option<ServerTask> {
-        self.peers_queue.pop_front()
-			.map(|peer_index| {
-				let (peer_task, is_last_peer_task) = {
-					let peer_tasks = self.tasks_queue.get_mut(&peer_index)
-						.expect("entry from tasks_queue is removed when empty; when empty, peer is removed from peers_queue; qed");
-					let peer_task = peer_tasks.pop_front()
-						.expect("entry from peer_tasks is removed when empty; when empty, peer is removed from peers_queue; qed");
-					(peer_task, peer_tasks.is_empty())
-				};
+    fn peer_state(&self, tasks: &PeerTasks) -> PeerQueueState {
+        PeerQueueState {
+            peer_index: self.peer_index,
+            high: tasks.high.len(),
+            normal: tasks.normal.len(),
+            high_in_row: tasks.high_in_row,
+            busy: tasks.busy,
+        }
+    }
 
-				// remove if no tasks left || schedule otherwise
-				if !is_last_peer_task {
-					self.peers_queue.push_back(peer_index);
-				} else {
-					self.tasks_queue.remove(&peer_index);
-				}
+    /// Take the next task to serve, unless previous task is still served
+    pub fn next_task(&self) -> Option<ServerTask> {
+        let mut tasks = self.tasks.lock();
+        if tasks.busy || tasks.cancelled {
+            return None;
+        }
 
-				peer_task
-			})
+        let priority = self.policy.select_priority(&self.peer_state(&tasks));
+        let task = tasks.pop(priority)?;
+        tasks.busy = true;
+        self.memory_budget
+            .release(MemoryConsumer::ServerQueue, task.memory_size());
+        Some(task)
     }
 
-    pub fn add_task(&mut self, task: ServerTask) {
-        let peer_index = task.peer_index();
-        match self.tasks_queue.entry(peer_index) {
-            Entry::Occupied(mut entry) => {
-                let add_to_peers_queue = entry.get().is_empty();
-                entry.get_mut().push_back(task);
-                if add_to_peers_queue {
-                    self.peers_queue.push_back(peer_index);
-                }
-            }
-            Entry::Vacant(entry) => {
-                let mut new_tasks = VecDeque::new();
-                new_tasks.push_back(task);
-                entry.insert(new_tasks);
-                self.peers_queue.push_back(peer_index);
+    /// Called when task is served. Continuation of the task is queued in front of other peer
+    /// tasks, unless peer has disconnected while the task was served
+    pub fn task_done(&self, continuation: Option<ServerTask>) {
+        let mut tasks = self.tasks.lock();
+        tasks.busy = false;
+        if let Some(task) = continuation {
+            if tasks.cancelled {
+                trace!(target: "sync", "Dropping response stream to disconnected peer#{}", self.peer_index);
+                return;
             }
+
+            self.memory_budget
+                .force_allocate(MemoryConsumer::ServerQueue, task.memory_size());
+            tasks.push_front(task);
         }
-        self.queue_ready.notify_one();
     }
 
-    pub fn add_task_front(&mut self, task: ServerTask) {
-        let peer_index = task.peer_index();
-        match self.tasks_queue.entry(peer_index) {
-            Entry::Occupied(mut entry) => {
-                let add_to_peers_queue = entry.get().is_empty();
-                entry.get_mut().push_front(task);
-                if add_to_peers_queue {
-                    self.peers_queue.push_back(peer_index);
-                }
-            }
-            Entry::Vacant(entry) => {
-                let mut new_tasks = VecDeque::new();
-                new_tasks.push_back(task);
-                entry.insert(new_tasks);
-                self.peers_queue.push_back(peer_index);
-            }
+    pub fn add_task(&self, task: ServerTask) {
+        let mut tasks = self.tasks.lock();
+        if tasks.cancelled {
+            return;
         }
-        self.queue_ready.notify_one();
+
+        self.memory_budget
+            .force_allocate(MemoryConsumer::ServerQueue, task.memory_size());
+        tasks.push_back(task);
     }
 
-    pub fn remove_peer_tasks(&mut self, peer_index: PeerIndex) {
-        if self.tasks_queue.remove(&peer_index).is_some() {
-            let position = self.peers_queue.iter().position(|p| p == &peer_index)
-				.expect("there are tasks for peer in tasks_queue; all tasks from tasks_queue are queued in peers_queue; qed");
-            self.peers_queue.remove(position);
+    /// Drop queued tasks and continuation of the served task
+    pub fn cancel(&self) {
+        let mut tasks = self.tasks.lock();
+        tasks.cancelled = true;
+        self.memory_budget
+            .release(MemoryConsumer::ServerQueue, tasks.memory_size());
+        tasks.high.clear();
+        tasks.normal.clear();
+    }
+}
+
+impl PeerTasks {
+    /// Approximate number of bytes, queued tasks occupy in memory
+    pub fn memory_size(&self) -> usize {
+        self.high
+            .iter()
+            .chain(self.normal.iter())
+            .map(ServerTask::memory_size)
+            .sum()
+    }
+
+    pub fn push_back(&mut self, task: ServerTask) {
+        match task.priority() {
+            ServerTaskPriority::High => self.high.push_back(task),
+            ServerTaskPriority::Normal => self.normal.push_back(task),
+        }
+    }
+
+    pub fn push_front(&mut self, task: ServerTask) {
+        match task.priority() {
+            ServerTaskPriority::High => self.high.push_front(task),
+            ServerTaskPriority::Normal => self.normal.push_front(task),
+        }
+    }
+
+    /// Pop task of given priority, or of other priority if there are no such tasks
+    pub fn pop(&mut self, priority: ServerTaskPriority) -> Option<ServerTask> {
+        let prefer_high = priority == ServerTaskPriority::High || self.normal.is_empty();
+        if prefer_high {
+            if let Some(task) = self.high.pop_front() {
+                self.high_in_row += 1;
+                return Some(task);
+            }
         }
+
+        self.high_in_row = 0;
+        self.normal.pop_front()
     }
 }
 
@@ -220,29 +574,45 @@ impl<TExecutor> ServerTaskExecutor<TExecutor>
 where
     TExecutor: TaskExecutor,
 {
-    pub fn new(peers: PeersRef, storage: StorageRef, executor: ExecutorRef<TExecutor>) -> Self {
+    pub fn new(
+        peers: PeersRef,
+        storage: StorageRef,
+        executor: ExecutorRef<TExecutor>,
+        upload_budget: Arc<Mutex<UploadBudget>>,
+        log_sampler: Arc<ServerLogSampler>,
+        block_cache: SerializedBlockCacheRef,
+    ) -> Self {
         ServerTaskExecutor {
             peers: peers,
             storage: storage,
             executor: executor,
+            locator_cache: Mutex::new(LocatorCache::new(DEFAULT_LOCATOR_CACHE_SIZE)),
+            block_cache: block_cache,
+            upload_budget: upload_budget,
+            log_sampler: log_sampler,
         }
     }
 
     pub fn execute(&self, task: ServerTask) -> Option<ServerTask> {
+        let span = trace_span!(target: "sync", "server_task", peer = task.peer_index());
+        let _enter = span.enter();
+
+        // outcomes of all tasks are counted, but only sampled tasks are logged
+        let log = self.log_sampler.start_task();
         match task {
             ServerTask::GetData(peer_index, message) => {
                 return self.serve_get_data(peer_index, message)
             }
-            ServerTask::ReversedGetData(peer_index, message, notfound) => {
-                return self.serve_reversed_get_data(peer_index, message, notfound)
+            ServerTask::ReversedGetData(peer_index, message, notfound, deadline) => {
+                return self.serve_reversed_get_data(peer_index, message, notfound, deadline, log)
             }
             ServerTask::GetBlocks(peer_index, message) => {
-                self.serve_get_blocks(peer_index, message)
+                self.serve_get_blocks(peer_index, message, log)
             }
             ServerTask::GetHeaders(peer_index, message, request_id) => {
-                self.serve_get_headers(peer_index, message, request_id)
+                self.serve_get_headers(peer_index, message, request_id, log)
             }
-            ServerTask::Mempool(peer_index) => self.serve_mempool(peer_index),
+            ServerTask::Mempool(peer_index) => self.serve_mempool(peer_index, log),
         }
 
         None
@@ -261,7 +631,10 @@ where
         let notfound = types::NotFound {
             inventory: Vec::new(),
         };
-        Some(ServerTask::ReversedGetData(peer_index, message, notfound))
+        let deadline = precise_time_s() + SERVER_STREAM_TIMEOUT_S;
+        Some(ServerTask::ReversedGetData(
+            peer_index, message, notfound, deadline,
+        ))
     }
 
     fn serve_reversed_get_data(
@@ -269,11 +642,24 @@ where
         peer_index: PeerIndex,
         mut message: types::GetData,
         mut notfound: types::NotFound,
+        deadline: f64,
+        log: bool,
     ) -> Option<ServerTask> {
+        // the rest of timed out stream is answered with 'notfound'
+        if !message.inventory.is_empty() && precise_time_s() > deadline {
+            if log {
+                trace!(target: "sync", "'getdata' stream to peer#{} has timed out with {} items left", peer_index, message.inventory.len());
+            }
+            notfound.inventory.extend(message.inventory.drain(..).rev());
+        }
+
         let next_item = match message.inventory.pop() {
             None => {
                 if !notfound.inventory.is_empty() {
-                    trace!(target: "sync", "'getdata' from peer#{} container contains {} unknown items", peer_index, notfound.inventory.len());
+                    self.log_sampler.note(ServeOutcome::NotFound);
+                    if log {
+                        trace!(target: "sync", "'getdata' from peer#{} container contains {} unknown items", peer_index, notfound.inventory.len());
+                    }
                     self.executor.execute(Task::NotFound(peer_index, notfound));
                 }
                 return None;
@@ -283,40 +669,103 @@ where
 
         match next_item.inv_type {
             common::InventoryType::MessageBlock => {
-                if let Some(block) = self.storage.block(next_item.hash.clone().into()) {
-                    trace!(target: "sync", "'getblocks' response to peer#{} is ready with block {}", peer_index, next_item.hash.to_reversed_str());
-                    self.executor.execute(Task::Block(peer_index, block));
+                let now = precise_time_s();
+                // whitelisted peers are not limited
+                let is_whitelisted = self
+                    .peers
+                    .connection(peer_index)
+                    .map_or(false, |connection| connection.is_whitelisted());
+                let decision = if is_whitelisted {
+                    UploadDecision::Serve
                 } else {
-                    notfound.inventory.push(next_item);
+                    self.upload_budget.lock().check(peer_index, now)
+                };
+                match decision {
+                    UploadDecision::Serve => {
+                        if let Some(block) = self.serialized_block(&next_item.hash) {
+                            let span = debug_span!(target: "sync", "serve_block", hash = %next_item.hash.to_reversed_str(), peer = peer_index);
+                            let _enter = span.enter();
+                            self.log_sampler.note(ServeOutcome::BlockServed);
+                            if log {
+                                trace!(target: "sync", "'getblocks' response to peer#{} is ready with block {}", peer_index, next_item.hash.to_reversed_str());
+                            }
+                            self.upload_budget.lock().note_served(
+                                peer_index,
+                                block.size() as u64,
+                                now,
+                            );
+                            self.executor.execute(Task::Block(
+                                peer_index,
+                                next_item.hash.clone(),
+                                block,
+                            ));
+                        } else {
+                            notfound.inventory.push(next_item);
+                        }
+                    }
+                    UploadDecision::Throttle => {
+                        self.log_sampler.note(ServeOutcome::Throttled);
+                        if log {
+                            trace!(target: "sync", "'getdata' from peer#{} is throttled: upload budget is exhausted", peer_index);
+                        }
+                        notfound.inventory.push(next_item);
+                    }
+                    UploadDecision::Disconnect => {
+                        self.peers.misbehaving(
+                            peer_index,
+                            "Keeps requesting blocks after upload budget is exhausted",
+                        );
+                        return None;
+                    }
                 }
             }
             common::InventoryType::Error => (),
         }
 
-        Some(ServerTask::ReversedGetData(peer_index, message, notfound))
+        Some(ServerTask::ReversedGetData(
+            peer_index, message, notfound, deadline,
+        ))
     }
 
-    fn serve_get_blocks(&self, peer_index: PeerIndex, message: types::GetBlocks) {
+    /// Read block from the storage and serialize it, unless it has been recently served already
+    fn serialized_block(&self, hash: &H256) -> Option<SharedPayload> {
+        if let Some(block) = self.block_cache.lock().get(hash) {
+            return Some(block);
+        }
+
+        let block = serialize_block(&self.storage.block(hash.clone().into())?);
+        self.block_cache.lock().insert(hash.clone(), block.clone());
+        Some(block)
+    }
+
+    fn serve_get_blocks(&self, peer_index: PeerIndex, message: types::GetBlocks, log: bool) {
         if let Some(block_height) =
             self.locate_best_common_block(&message.hash_stop, &message.block_locator_hashes)
         {
+            // only hashes are needed, so blocks themselves are never read
             let inventory: Vec<_> = (block_height + 1
                 ..block_height + 1 + (types::GETBLOCKS_MAX_RESPONSE_HASHES as BlockHeight))
-                .map(|block_height| self.storage.block_hash(block_height))
+                .map(|number| self.storage.block_hash(number))
                 .take_while(Option::is_some)
-                .map(Option::unwrap)
+                .filter_map(|block_hash| block_hash)
                 .take_while(|block_hash| block_hash != &message.hash_stop)
                 .map(common::InventoryVector::block)
                 .collect();
             // empty inventory messages are invalid according to regtests, while empty headers messages are valid
             if !inventory.is_empty() {
-                trace!(target: "sync", "'getblocks' response to peer#{} is ready with {} hashes", peer_index, inventory.len());
+                self.log_sampler.note(ServeOutcome::InventoryServed);
+                if log {
+                    trace!(target: "sync", "'getblocks' response to peer#{} is ready with {} hashes", peer_index, inventory.len());
+                }
                 self.executor.execute(Task::Inventory(
                     peer_index,
                     types::Inv::with_inventory(inventory),
                 ));
             } else {
-                trace!(target: "sync", "'getblocks' request from peer#{} is ignored as there are no new blocks for peer", peer_index);
+                self.log_sampler.note(ServeOutcome::Ignored);
+                if log {
+                    trace!(target: "sync", "'getblocks' request from peer#{} is ignored as there are no new blocks for peer", peer_index);
+                }
             }
         } else {
             self.peers
@@ -330,15 +779,23 @@ where
         peer_index: PeerIndex,
         message: types::GetHeaders,
         request_id: RequestId,
+        log: bool,
     ) {
+        // headers are read from the snapshot, so that response never mixes forks,
+        // even if chain is reorganized while the response is built
+        let snapshot = self.storage.chain_snapshot();
         if let Some(block_height) =
             self.locate_best_common_block(&message.hash_stop, &message.block_locator_hashes)
         {
-            let headers: Vec<_> = (block_height + 1
-                ..block_height + 1 + (types::GETHEADERS_MAX_RESPONSE_HEADERS as BlockHeight))
-                .map(|block_height| self.storage.block_hash(block_height))
-                .take_while(Option::is_some)
-                .map(Option::unwrap)
+            let headers: Vec<_> = snapshot
+                .block_hashes(
+                    self.storage.as_block_provider(),
+                    block_height + 1
+                        ..block_height
+                            + 1
+                            + (types::GETHEADERS_MAX_RESPONSE_HEADERS as BlockHeight),
+                )
+                .into_iter()
                 .take_while(|block_hash| block_hash != &message.hash_stop)
                 .map(|block_hash| self.storage.block_header(block_hash.into()))
                 .take_while(Option::is_some)
@@ -346,7 +803,10 @@ where
                 .map(|h| h.raw)
                 .collect();
             // empty inventory messages are invalid according to regtests, while empty headers messages are valid
-            trace!(target: "sync", "'getheaders' response to peer#{} is ready with {} headers", peer_index, headers.len());
+            self.log_sampler.note(ServeOutcome::HeadersServed);
+            if log {
+                trace!(target: "sync", "'getheaders' response to peer#{} is ready with {} headers", peer_index, headers.len());
+            }
             self.executor.execute(Task::Headers(
                 peer_index,
                 types::Headers::with_headers(headers),
@@ -360,34 +820,48 @@ where
     }
 
     // TODO:
-    fn serve_mempool(&self, peer_index: PeerIndex) {
-        trace!(target: "sync", "'mempool' request from peer#{} is ignored as pool is empty", peer_index);
+    fn serve_mempool(&self, peer_index: PeerIndex, log: bool) {
+        self.log_sampler.note(ServeOutcome::Ignored);
+        if log {
+            trace!(target: "sync", "'mempool' request from peer#{} is ignored as pool is empty", peer_index);
+        }
     }
 
     fn locate_best_common_block(&self, hash_stop: &H256, locator: &[H256]) -> Option<BlockHeight> {
-        for block_hash in locator.iter().chain(&[hash_stop.clone()]) {
-            if let Some(block_number) = self.storage.block_number(block_hash) {
-                return Some(block_number);
-            }
+        // when new block is announced, many peers are asking for headers using (almost) the same locator
+        // => remember recent resolutions to avoid walking the storage for every request
+        let best_block_hash = self.storage.best_block().hash;
+        let locator_key = LocatorCache::key(locator, hash_stop);
+        if let Some(block_number) = self
+            .locator_cache
+            .lock()
+            .get(&best_block_hash, &locator_key)
+        {
+            return Some(block_number);
+        }
 
-            // block with this hash is definitely not in the main chain (block_number has returned None)
-            // but maybe it is in some fork? if so => we should find intersection with main chain
-            // and this would be our best common block
-            let mut block_hash = block_hash.clone();
-            loop {
-                let block_header = match self.storage.block_header(block_hash.into()) {
-                    None => break,
-                    Some(block_header) => block_header,
-                };
+        let block_number = self.locate_best_common_block_in_storage(hash_stop, locator);
+        if let Some(block_number) = block_number {
+            self.locator_cache
+                .lock()
+                .insert(&best_block_hash, locator_key, block_number);
+        }
+        block_number
+    }
 
-                if let Some(block_number) = self
-                    .storage
-                    .block_number(&block_header.raw.previous_header_hash)
-                {
-                    return Some(block_number);
+    fn locate_best_common_block_in_storage(
+        &self,
+        hash_stop: &H256,
+        locator: &[H256],
+    ) -> Option<BlockHeight> {
+        for block_hash in locator.iter().chain(&[hash_stop.clone()]) {
+            // if block is in some fork, intersection of this fork with main chain is our best common block
+            match self.storage.chain_membership(block_hash) {
+                Some(ChainMembership::CanonChain { block_number }) => return Some(block_number),
+                Some(ChainMembership::SideChain { fork_block_number }) => {
+                    return Some(fork_block_number)
                 }
-
-                block_hash = block_header.raw.previous_header_hash;
+                None => (),
             }
         }
 
@@ -399,23 +873,42 @@ where
 pub mod tests {
     extern crate test_data;
 
-    use super::{Server, ServerImpl, ServerTask};
+    use super::{
+        PeerQueue, PeerQueueState, RoundRobinPolicy, SchedulingPolicy, Server, ServerImpl,
+        ServerTask, ServerTaskExecutor, ServerTaskPriority, MAX_HIGH_PRIORITY_TASKS_IN_ROW,
+        SERVER_STREAM_TIMEOUT_S,
+    };
     use db::BlockChainDatabase;
+    use inbound_connection::tests::DummyOutboundSyncConnection;
     use message::common::{InventoryType, InventoryVector};
-    use message::types;
-    use parking_lot::Mutex;
+    use message::{types, Services, SharedPayload};
+    use parking_lot::{Condvar, Mutex};
     use primitives::hash::H256;
     use std::mem::replace;
     use std::sync::Arc;
+    use std::thread;
+    use std::time::Duration;
     use synchronization_executor::tests::DummyTaskExecutor;
-    use synchronization_executor::Task;
-    use synchronization_peers::PeersImpl;
-    use types::{ExecutorRef, PeerIndex, PeersRef, StorageRef};
+    use synchronization_executor::{Task, TaskExecutor};
+    use synchronization_peers::{PeersContainer, PeersImpl};
+    use types::{ExecutorRef, MemoryBudgetRef, PeerIndex, PeersRef, StorageRef};
+    use utils::{
+        set_virtual_time, MemoryBudget, ServerLogSampler, ServerLogStats, UploadBudget, UploadLimit,
+    };
 
     pub struct DummyServer {
         tasks: Mutex<Vec<ServerTask>>,
     }
 
+    fn genesis_block_task(peer_index: PeerIndex) -> Task {
+        let genesis = test_data::genesis();
+        Task::Block(
+            peer_index,
+            genesis.hash(),
+            SharedPayload::new(&types::Block { block: genesis }).unwrap(),
+        )
+    }
+
     impl DummyServer {
         pub fn new() -> Self {
             DummyServer {
@@ -434,6 +927,18 @@ pub mod tests {
         }
 
         fn on_disconnect(&self, _peer_index: PeerIndex) {}
+
+        fn set_log_sampling(&self, _sampling: u32) {}
+
+        fn log_stats(&self) -> ServerLogStats {
+            ServerLogStats::default()
+        }
+
+        fn set_upload_limit(&self, _limit: Option<UploadLimit>) {}
+
+        fn queue_state(&self) -> Vec<PeerQueueState> {
+            Vec::new()
+        }
     }
 
     fn create_synchronization_server() -> (
@@ -447,7 +952,15 @@ pub mod tests {
             test_data::genesis().into(),
         ]));
         let executor = DummyTaskExecutor::new();
-        let server = ServerImpl::new(peers.clone(), storage.clone(), executor.clone());
+        let server = ServerImpl::new(
+            peers.clone(),
+            storage.clone(),
+            executor.clone(),
+            2,
+            None,
+            Arc::default(),
+            Arc::default(),
+        );
         (storage, executor, peers, server)
     }
 
@@ -488,7 +1001,127 @@ pub mod tests {
         ));
         // => respond with block
         let tasks = DummyTaskExecutor::wait_tasks(executor);
-        assert_eq!(tasks, vec![Task::Block(0, test_data::genesis().into())]);
+        assert_eq!(tasks, vec![genesis_block_task(0)]);
+    }
+
+    #[test]
+    fn server_getdata_responds_notfound_when_upload_budget_is_exhausted() {
+        let (storage, executor, peers, _) = create_synchronization_server();
+        let upload_budget = UploadBudget::new(Some(UploadLimit {
+            max_bytes: 1,
+            window_s: 600,
+        }));
+        // tasks are not logged, but their outcomes are counted
+        let log_sampler = Arc::new(ServerLogSampler::new(0));
+        let server_executor = ServerTaskExecutor::new(
+            peers,
+            storage,
+            executor.clone(),
+            Arc::new(Mutex::new(upload_budget)),
+            log_sampler.clone(),
+            Arc::default(),
+        );
+        // when asking for two blocks, while budget allows to serve only one
+        let inventory = vec![
+            InventoryVector::block(test_data::genesis().hash()),
+            InventoryVector::block(test_data::genesis().hash()),
+        ];
+        let mut task = Some(ServerTask::GetData(
+            0,
+            types::GetData::with_inventory(inventory.clone()),
+        ));
+        while let Some(next_task) = task {
+            task = server_executor.execute(next_task);
+        }
+        // => first block is served and second is throttled
+        let tasks = executor.take_tasks();
+        assert_eq!(
+            tasks,
+            vec![
+                genesis_block_task(0),
+                Task::NotFound(
+                    0,
+                    types::NotFound::with_inventory(vec![inventory[1].clone()])
+                ),
+            ]
+        );
+        let stats = log_sampler.stats();
+        assert_eq!(stats.logged_tasks, 0);
+        assert_eq!(stats.blocks_served, 1);
+        assert_eq!(stats.throttled, 1);
+        assert_eq!(stats.not_found, 1);
+    }
+
+    #[test]
+    fn server_getdata_ignores_upload_budget_of_whitelisted_peer() {
+        let (storage, executor, peers, _) = create_synchronization_server();
+        peers.insert(
+            1,
+            Services::default(),
+            DummyOutboundSyncConnection::whitelisted(),
+        );
+        let upload_budget = UploadBudget::new(Some(UploadLimit {
+            max_bytes: 1,
+            window_s: 600,
+        }));
+        let server_executor = ServerTaskExecutor::new(
+            peers,
+            storage,
+            executor.clone(),
+            Arc::new(Mutex::new(upload_budget)),
+            Arc::new(ServerLogSampler::default()),
+            Arc::default(),
+        );
+        let inventory = vec![
+            InventoryVector::block(test_data::genesis().hash()),
+            InventoryVector::block(test_data::genesis().hash()),
+        ];
+        let mut task = Some(ServerTask::GetData(
+            1,
+            types::GetData::with_inventory(inventory),
+        ));
+        while let Some(next_task) = task {
+            task = server_executor.execute(next_task);
+        }
+        // => both blocks are served
+        let tasks = executor.take_tasks();
+        assert_eq!(tasks, vec![genesis_block_task(1), genesis_block_task(1),]);
+    }
+
+    #[test]
+    fn server_getdata_responds_notfound_when_stream_times_out() {
+        let (storage, executor, peers, _) = create_synchronization_server();
+        let server_executor = ServerTaskExecutor::new(
+            peers,
+            storage,
+            executor.clone(),
+            Arc::new(Mutex::new(UploadBudget::new(None))),
+            Arc::default(),
+            Arc::default(),
+        );
+        let inventory = vec![InventoryVector::block(test_data::genesis().hash()); 3];
+
+        // first block is served in time
+        set_virtual_time(Some(100.0));
+        let task = server_executor.execute(ServerTask::GetData(
+            0,
+            types::GetData::with_inventory(inventory.clone()),
+        ));
+        let task = server_executor.execute(task.unwrap());
+        assert_eq!(executor.take_tasks(), vec![genesis_block_task(0)]);
+
+        // => the rest of stream is answered with notfound after timeout
+        set_virtual_time(Some(100.0 + SERVER_STREAM_TIMEOUT_S + 1.0));
+        let task = server_executor.execute(task.unwrap());
+        set_virtual_time(None);
+        assert_eq!(task, None);
+        assert_eq!(
+            executor.take_tasks(),
+            vec![Task::NotFound(
+                0,
+                types::NotFound::with_inventory(inventory[1..].to_vec())
+            )]
+        );
     }
 
     #[test]
@@ -667,4 +1300,356 @@ pub mod tests {
             )]
         );
     }
+
+    #[test]
+    fn server_resolves_repeated_locators_from_cache() {
+        let (storage, executor, peers, _) = create_synchronization_server();
+        let executor = ServerTaskExecutor::new(
+            peers,
+            storage.clone(),
+            executor,
+            Arc::new(Mutex::new(UploadBudget::new(None))),
+            Arc::new(ServerLogSampler::default()),
+            Arc::default(),
+        );
+        let locator = vec![test_data::genesis().hash()];
+        assert_eq!(
+            executor.locate_best_common_block(&H256::default(), &locator),
+            Some(0)
+        );
+        assert_eq!(executor.locator_cache.lock().len(), 1);
+        assert_eq!(
+            executor.locate_best_common_block(&H256::default(), &locator),
+            Some(0)
+        );
+        assert_eq!(executor.locator_cache.lock().len(), 1);
+
+        // when best block changes, cached resolutions are dropped
+        storage
+            .insert(test_data::block_h1().into())
+            .expect("Db write error");
+        storage.canonize(&test_data::block_h1().hash()).unwrap();
+        let locator = vec![test_data::block_h1().hash()];
+        assert_eq!(
+            executor.locate_best_common_block(&H256::default(), &locator),
+            Some(1)
+        );
+        assert_eq!(executor.locator_cache.lock().len(), 1);
+    }
+
+    fn dummy_get_blocks(peer_index: PeerIndex) -> ServerTask {
+        ServerTask::GetBlocks(
+            peer_index,
+            types::GetBlocks {
+                version: 0,
+                block_locator_hashes: vec![],
+                hash_stop: H256::default(),
+            },
+        )
+    }
+
+    fn dummy_get_headers(peer_index: PeerIndex) -> ServerTask {
+        ServerTask::GetHeaders(
+            peer_index,
+            types::GetHeaders {
+                version: 0,
+                block_locator_hashes: vec![],
+                hash_stop: H256::default(),
+            },
+            0,
+        )
+    }
+
+    fn peer_queue(memory_budget: MemoryBudgetRef) -> PeerQueue {
+        PeerQueue::new(0, Arc::new(RoundRobinPolicy), memory_budget)
+    }
+
+    fn next_task(queue: &PeerQueue) -> Option<ServerTask> {
+        let task = queue.next_task();
+        queue.task_done(None);
+        task
+    }
+
+    #[test]
+    fn peer_queue_serves_high_priority_tasks_first() {
+        let queue = peer_queue(Arc::default());
+        queue.add_task(dummy_get_blocks(0));
+        queue.add_task(dummy_get_headers(0));
+        queue.add_task(ServerTask::Mempool(0));
+
+        assert_eq!(next_task(&queue), Some(dummy_get_headers(0)));
+        assert_eq!(next_task(&queue), Some(ServerTask::Mempool(0)));
+        assert_eq!(next_task(&queue), Some(dummy_get_blocks(0)));
+        assert_eq!(next_task(&queue), None);
+    }
+
+    #[test]
+    fn peer_queue_does_not_starve_normal_priority_tasks() {
+        let queue = peer_queue(Arc::default());
+        queue.add_task(dummy_get_blocks(0));
+        for _ in 0..MAX_HIGH_PRIORITY_TASKS_IN_ROW + 1 {
+            queue.add_task(dummy_get_headers(0));
+        }
+
+        for _ in 0..MAX_HIGH_PRIORITY_TASKS_IN_ROW {
+            assert_eq!(next_task(&queue), Some(dummy_get_headers(0)));
+        }
+        assert_eq!(next_task(&queue), Some(dummy_get_blocks(0)));
+        assert_eq!(next_task(&queue), Some(dummy_get_headers(0)));
+        assert_eq!(next_task(&queue), None);
+    }
+
+    #[test]
+    fn peer_queue_does_not_serve_tasks_in_parallel() {
+        let queue = peer_queue(Arc::default());
+        queue.add_task(dummy_get_headers(0));
+        queue.add_task(ServerTask::Mempool(0));
+
+        // next task waits until previous one is served
+        assert_eq!(queue.next_task(), Some(dummy_get_headers(0)));
+        assert_eq!(queue.next_task(), None);
+        queue.task_done(None);
+        assert_eq!(queue.next_task(), Some(ServerTask::Mempool(0)));
+        queue.task_done(None);
+        assert_eq!(queue.next_task(), None);
+    }
+
+    fn peer_state(
+        peer_index: PeerIndex,
+        high: usize,
+        normal: usize,
+        high_in_row: usize,
+        busy: bool,
+    ) -> PeerQueueState {
+        PeerQueueState {
+            peer_index: peer_index,
+            high: high,
+            normal: normal,
+            high_in_row: high_in_row,
+            busy: busy,
+        }
+    }
+
+    /// Serves normal-priority tasks first
+    struct ReversePolicy;
+
+    impl SchedulingPolicy for ReversePolicy {
+        fn select_priority(&self, _peer: &PeerQueueState) -> ServerTaskPriority {
+            ServerTaskPriority::Normal
+        }
+    }
+
+    #[test]
+    fn peer_queue_state_snapshot() {
+        let queue = peer_queue(Arc::default());
+        queue.add_task(dummy_get_blocks(0));
+        queue.add_task(dummy_get_headers(0));
+        queue.add_task(ServerTask::Mempool(0));
+        assert_eq!(queue.state(), peer_state(0, 2, 1, 0, false));
+
+        // peer is busy until task is done
+        assert_eq!(queue.next_task(), Some(dummy_get_headers(0)));
+        assert_eq!(queue.state(), peer_state(0, 1, 1, 1, true));
+        queue.task_done(None);
+        assert_eq!(queue.state(), peer_state(0, 1, 1, 1, false));
+
+        // cancelled queue has no tasks
+        queue.cancel();
+        assert_eq!(queue.state(), peer_state(0, 0, 0, 1, false));
+    }
+
+    #[test]
+    fn peer_queue_uses_scheduling_policy() {
+        let queue = PeerQueue::new(0, Arc::new(ReversePolicy), Arc::default());
+        queue.add_task(dummy_get_headers(0));
+        queue.add_task(dummy_get_blocks(0));
+        queue.add_task(ServerTask::Mempool(0));
+
+        assert_eq!(next_task(&queue), Some(dummy_get_blocks(0)));
+        assert_eq!(next_task(&queue), Some(dummy_get_headers(0)));
+        assert_eq!(next_task(&queue), Some(ServerTask::Mempool(0)));
+        assert_eq!(next_task(&queue), None);
+    }
+
+    #[test]
+    fn peer_queue_accounts_queued_tasks_in_memory_budget() {
+        let budget = Arc::new(MemoryBudget::new(0));
+        let queue = peer_queue(budget.clone());
+        let get_data = ServerTask::GetData(
+            0,
+            types::GetData {
+                inventory: vec![InventoryVector::block(H256::default()); 10],
+            },
+        );
+        let get_data_size = get_data.memory_size();
+        // requests are accounted even when budget is exhausted
+        queue.add_task(get_data);
+        queue.add_task(dummy_get_headers(0));
+        assert_eq!(
+            budget.usage().server_queue,
+            get_data_size + dummy_get_headers(0).memory_size()
+        );
+
+        assert_eq!(next_task(&queue), Some(dummy_get_headers(0)));
+        assert_eq!(budget.usage().server_queue, get_data_size);
+        queue.cancel();
+        assert_eq!(budget.usage().server_queue, 0);
+    }
+
+    #[test]
+    fn peer_queue_cancels_stream_of_disconnected_peer() {
+        let budget = Arc::new(MemoryBudget::new(0));
+        let queue = peer_queue(budget.clone());
+        let get_data = || {
+            ServerTask::ReversedGetData(
+                0,
+                types::GetData {
+                    inventory: vec![InventoryVector::block(H256::default()); 10],
+                },
+                types::NotFound {
+                    inventory: Vec::new(),
+                },
+                SERVER_STREAM_TIMEOUT_S,
+            )
+        };
+        queue.add_task(get_data());
+        queue.add_task(ServerTask::Mempool(0));
+
+        // stream is served, when peer disconnects
+        assert_eq!(queue.next_task(), Some(ServerTask::Mempool(0)));
+        queue.task_done(None);
+        assert_eq!(queue.next_task(), Some(get_data()));
+        queue.cancel();
+
+        // => the rest of stream is dropped
+        queue.task_done(Some(get_data()));
+        assert_eq!(budget.usage().server_queue, 0);
+        assert_eq!(queue.next_task(), None);
+    }
+
+    /// Holds served blocks until released
+    #[derive(Default)]
+    struct HoldingTaskExecutor {
+        is_released: Mutex<bool>,
+        released: Condvar,
+        tasks: Mutex<Vec<Task>>,
+        task_executed: Condvar,
+    }
+
+    impl HoldingTaskExecutor {
+        fn release(&self) {
+            *self.is_released.lock() = true;
+            self.released.notify_all();
+        }
+
+        fn wait_tasks(&self, count: usize) -> Vec<Task> {
+            let mut tasks = self.tasks.lock();
+            while tasks.len() < count {
+                if self
+                    .task_executed
+                    .wait_for(&mut tasks, Duration::from_millis(1000))
+                    .timed_out()
+                {
+                    break;
+                }
+            }
+            tasks.drain(..).collect()
+        }
+    }
+
+    impl TaskExecutor for HoldingTaskExecutor {
+        fn execute(&self, task: Task) {
+            if let Task::Block(_, _, _) = task {
+                let mut is_released = self.is_released.lock();
+                while !*is_released {
+                    self.released.wait(&mut is_released);
+                }
+            }
+
+            self.tasks.lock().push(task);
+            self.task_executed.notify_all();
+        }
+    }
+
+    #[test]
+    fn server_does_not_serialize_peers_behind_busy_peer() {
+        let storage = Arc::new(BlockChainDatabase::init_test_chain(vec![
+            test_data::genesis().into(),
+        ]));
+        let executor = Arc::new(HoldingTaskExecutor::default());
+        let server = ServerImpl::new(
+            Arc::new(PeersImpl::default()),
+            storage,
+            executor.clone(),
+            2,
+            None,
+            Arc::default(),
+            Arc::default(),
+        );
+
+        // when peer#0 stream is stuck at sending block
+        let inventory = vec![InventoryVector::block(test_data::genesis().hash()); 2];
+        server.execute(ServerTask::GetData(
+            0,
+            types::GetData::with_inventory(inventory),
+        ));
+        server.execute(ServerTask::GetHeaders(
+            1,
+            types::GetHeaders {
+                version: 0,
+                block_locator_hashes: vec![test_data::genesis().hash()],
+                hash_stop: H256::default(),
+            },
+            0,
+        ));
+
+        // => peer#1 is served anyway
+        assert_eq!(
+            executor.wait_tasks(1),
+            vec![Task::Headers(
+                1,
+                types::Headers::with_headers(vec![]),
+                Some(0)
+            )]
+        );
+        executor.release();
+        assert_eq!(
+            executor.wait_tasks(2),
+            vec![genesis_block_task(0), genesis_block_task(0)]
+        );
+    }
+
+    #[test]
+    fn server_drops_queued_tasks_when_stopped() {
+        let budget = Arc::new(MemoryBudget::new(0));
+        let executor = Arc::new(HoldingTaskExecutor::default());
+        let server = ServerImpl::new(
+            Arc::new(PeersImpl::default()),
+            Arc::new(BlockChainDatabase::init_test_chain(vec![
+                test_data::genesis().into(),
+            ])),
+            executor.clone(),
+            1,
+            None,
+            Arc::default(),
+            budget.clone(),
+        );
+        let inventory = vec![InventoryVector::block(test_data::genesis().hash()); 10];
+        for peer_index in 0..4 {
+            server.execute(ServerTask::GetData(
+                peer_index,
+                types::GetData::with_inventory(inventory.clone()),
+            ));
+        }
+
+        // when server is stopped
+        executor.release();
+        drop(server);
+
+        // => served task steps are completed and nothing is left in queues
+        assert_eq!(budget.usage().server_queue, 0);
+        executor.wait_tasks(0);
+        thread::sleep(Duration::from_millis(50));
+        assert_eq!(executor.wait_tasks(0), vec![]);
+    }
 }
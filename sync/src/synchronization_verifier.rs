@@ -3,23 +3,98 @@ use network::Network;
 use parking_lot::Mutex;
 use primitives::hash::H256;
 use std::collections::VecDeque;
+use std::fmt;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::mpsc::{channel, Receiver, Sender};
 use std::sync::Arc;
 use std::thread;
+use storage;
+use tracing::{trace, Span};
 use types::StorageRef;
 use verification::{
-    BackwardsCompatibleChainVerifier as ChainVerifier, Error as VerificationError,
+    BackwardsCompatibleChainVerifier as ChainVerifier, Error as ChainVerifierError,
     VerificationLevel, Verify as VerificationVerify,
 };
 use VerificationParameters;
 
+/// Reason, why the block has been rejected.
+#[derive(Debug, Clone, PartialEq)]
+pub enum VerificationError {
+    /// Block is already in the chain
+    Duplicate,
+    /// Parent block is unknown or invalid
+    BadParent,
+    /// Invalid proof-of-work or VDF proof
+    BadProof,
+    /// Invalid or futuristic timestamp
+    BadTimestamp,
+    /// Bits or number of VDF iterations do not match consensus rules
+    BadDifficulty,
+    /// Block version is obsolete
+    BadVersion,
+    /// Block is not final
+    NonFinal,
+    /// Block is on the fork, which reorganizes finalized block
+    TooDeepReorg,
+    /// Storage has failed while block has been verified
+    Database(String),
+}
+
+impl VerificationError {
+    /// Short machine-readable reason of the rejection (like BIP22 reject reasons)
+    pub fn reason(&self) -> &'static str {
+        match *self {
+            VerificationError::Duplicate => "duplicate",
+            VerificationError::BadParent => "bad-parent",
+            VerificationError::BadProof => "bad-proof",
+            VerificationError::BadTimestamp => "bad-timestamp",
+            VerificationError::BadDifficulty => "bad-difficulty",
+            VerificationError::BadVersion => "bad-version",
+            VerificationError::NonFinal => "non-final",
+            VerificationError::TooDeepReorg => "too-deep-reorg",
+            VerificationError::Database(_) => "database-error",
+        }
+    }
+}
+
+impl fmt::Display for VerificationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            VerificationError::Database(ref err) => write!(f, "{}: {}", self.reason(), err),
+            _ => f.write_str(self.reason()),
+        }
+    }
+}
+
+impl From<ChainVerifierError> for VerificationError {
+    fn from(err: ChainVerifierError) -> Self {
+        match err {
+            ChainVerifierError::Duplicate => VerificationError::Duplicate,
+            ChainVerifierError::Pow | ChainVerifierError::Vdf => VerificationError::BadProof,
+            ChainVerifierError::ParentVdf => VerificationError::BadParent,
+            ChainVerifierError::Iterations { .. } | ChainVerifierError::Difficulty { .. } => {
+                VerificationError::BadDifficulty
+            }
+            ChainVerifierError::FuturisticTimestamp | ChainVerifierError::Timestamp => {
+                VerificationError::BadTimestamp
+            }
+            ChainVerifierError::NonFinalBlock => VerificationError::NonFinal,
+            ChainVerifierError::OldVersionBlock => VerificationError::BadVersion,
+            ChainVerifierError::Database(err) => VerificationError::Database(err.to_string()),
+        }
+    }
+}
+
 /// Block verification events sink
 pub trait BlockVerificationSink: Send + Sync + 'static {
     /// When block verification has completed successfully.
     fn on_block_verification_success(&self, block: IndexedBlock) -> Option<Vec<VerificationTask>>;
     /// When block verification has failed.
-    fn on_block_verification_error(&self, err: &str, hash: &H256);
+    fn on_block_verification_error(
+        &self,
+        err: &VerificationError,
+        hash: &H256,
+    ) -> Option<Vec<VerificationTask>>;
 }
 
 /// Verification events sink
@@ -28,8 +103,8 @@ pub trait VerificationSink: BlockVerificationSink {}
 /// Verification thread tasks
 #[derive(Debug)]
 pub enum VerificationTask {
-    /// Verify single block
-    VerifyBlock(IndexedBlock),
+    /// Verify single block. Block is verified within the span, where it has been scheduled
+    VerifyBlock(IndexedBlock, Span),
     /// Stop verification thread
     Stop,
 }
@@ -52,6 +127,8 @@ pub struct AsyncVerifier {
 pub struct ChainVerifierWrapper {
     /// Original verifier.
     pub verifier: Arc<ChainVerifier>,
+    /// Storage, used to find heights of verified blocks.
+    storage: StorageRef,
     /// Verification parameters.
     verification_params: VerificationParameters,
     /// Is verification edge passed.
@@ -70,13 +147,14 @@ impl ChainVerifierWrapper {
         );
         ChainVerifierWrapper {
             verifier: verifier,
+            storage: storage.clone(),
             verification_params: verification_params,
             enforce_full_verification: enforce_full_verification,
         }
     }
 
     /// Verify block.
-    pub fn verify_block(&self, block: &IndexedBlock) -> Result<(), VerificationError> {
+    pub fn verify_block(&self, block: &IndexedBlock) -> Result<(), ChainVerifierError> {
         let enforce_full_verification =
             if block.hash() == &self.verification_params.verification_edge {
                 self.enforce_full_verification
@@ -85,17 +163,123 @@ impl ChainVerifierWrapper {
             } else {
                 self.enforce_full_verification.load(Ordering::Relaxed)
             };
-        let verification_level = if enforce_full_verification {
-            VerificationLevel::Full
-        } else {
-            self.verification_params.verification_level
+        let verification_level = match self.rule_level(block) {
+            Some(level) => level,
+            None if enforce_full_verification => VerificationLevel::Full,
+            None => self.verification_params.verification_level,
         };
 
         self.verifier.verify(verification_level, block)
     }
+
+    /// Returns verification level of the rule, covering height of the block.
+    fn rule_level(&self, block: &IndexedBlock) -> Option<VerificationLevel> {
+        if self.verification_params.verification_rules.is_empty() {
+            return None;
+        }
+
+        // height of side chain blocks is not known yet => they're treated as blocks at the tip
+        let height = self
+            .storage
+            .block_number(&block.header.raw.previous_header_hash)
+            .unwrap_or_else(|| self.storage.best_block().number)
+            + 1;
+        self.verification_params.rule_level(height)
+    }
+}
+
+impl VerificationTask {
+    /// Verify block within the current span, so it is traced with the peer it came from
+    pub fn verify_block(block: IndexedBlock) -> Self {
+        VerificationTask::VerifyBlock(block, Span::current())
+    }
+}
+
+/// Canon block, which has failed re-verification.
+#[derive(Debug, PartialEq)]
+pub struct ChainCheckFailure {
+    /// Height of the block
+    pub height: u32,
+    /// Hash of the block
+    pub hash: H256,
+    /// Verification error
+    pub error: ChainVerifierError,
+}
+
+/// Result of re-verification of stored canon blocks.
+#[derive(Debug, PartialEq)]
+pub struct ChainCheck {
+    /// Number of blocks, which have passed verification
+    pub checked: u32,
+    /// The first block, which has failed verification
+    pub failure: Option<ChainCheckFailure>,
 }
 
-impl VerificationTask {}
+/// Verifies up to `depth` best canon blocks (all blocks if `depth` is 0) again, from the oldest
+/// to the newest, stopping at the first failure. Genesis block is never verified.
+/// Blocks with pruned bodies are verified at header level at most. Missing block is reported
+/// as a failure.
+pub fn verify_canon_chain(
+    verifier: &ChainVerifier,
+    storage: &StorageRef,
+    verification_level: VerificationLevel,
+    depth: u32,
+) -> ChainCheck {
+    let best_block_number = storage.best_block().number;
+    let from = match depth {
+        0 => 1,
+        depth => best_block_number.saturating_sub(depth - 1).max(1),
+    };
+    let _guard = storage.retain_bodies(from);
+    let pruned_height = storage.pruned_height();
+
+    let mut checked = 0;
+    let blocks = storage.block_iter(from..best_block_number.saturating_add(1));
+    for (height, block) in (from..).zip(blocks) {
+        let verification_level = match pruned_height {
+            Some(pruned_height)
+                if height <= pruned_height
+                    && verification_level != VerificationLevel::NoVerification =>
+            {
+                VerificationLevel::Header
+            }
+            _ => verification_level,
+        };
+        if let Err(error) = verifier.verify_canon_block(verification_level, &block, height) {
+            return ChainCheck {
+                checked: checked,
+                failure: Some(ChainCheckFailure {
+                    height: height,
+                    hash: block.hash().clone(),
+                    error: error,
+                }),
+            };
+        }
+        checked += 1;
+    }
+
+    // block iterator stops at the first missing block
+    let expected = (best_block_number + 1).saturating_sub(from);
+    if checked < expected {
+        let height = from + checked;
+        return ChainCheck {
+            checked: checked,
+            failure: Some(ChainCheckFailure {
+                height: height,
+                hash: storage.block_hash(height).unwrap_or_default(),
+                error: ChainVerifierError::Database(storage::Error::DatabaseError(format!(
+                    "canon block {} is missing",
+                    height
+                ))),
+            }),
+        };
+    }
+
+    ChainCheck {
+        checked: checked,
+        failure: None,
+    }
+}
 
 impl AsyncVerifier {
     /// Create new async verifier
@@ -155,7 +339,10 @@ impl AsyncVerifier {
 
         while let Some(task) = tasks_queue.pop_front() {
             match task {
-                VerificationTask::VerifyBlock(block) => {
+                VerificationTask::VerifyBlock(block, span) => {
+                    let span = debug_span!(target: "sync", parent: &span, "verify_block", hash = %block.hash().to_reversed_str());
+                    let _enter = span.enter();
+
                     // verify block
                     match verifier.verify_block(&block) {
                         Ok(_) => {
@@ -164,7 +351,11 @@ impl AsyncVerifier {
                             }
                         }
                         Err(e) => {
-                            sink.on_block_verification_error(&format!("{:?}", e), block.hash())
+                            if let Some(tasks) =
+                                sink.on_block_verification_error(&e.into(), block.hash())
+                            {
+                                tasks_queue.extend(tasks);
+                            }
                         }
                     }
                 }
@@ -194,7 +385,7 @@ impl Verifier for AsyncVerifier {
     fn verify_block(&self, block: IndexedBlock) {
         self.verification_work_sender
             .lock()
-            .send(VerificationTask::VerifyBlock(block))
+            .send(VerificationTask::verify_block(block))
             .expect("Verification thread have the same lifetime as `AsyncVerifier`");
     }
 }
@@ -240,9 +431,10 @@ where
                 // => we could ignore decanonized transactions
                 self.sink.on_block_verification_success(block);
             }
-            Err(e) => self
-                .sink
-                .on_block_verification_error(&format!("{:?}", e), block.hash()),
+            Err(e) => {
+                self.sink
+                    .on_block_verification_error(&e.into(), block.hash());
+            }
         }
     }
 }
@@ -252,9 +444,11 @@ pub mod tests {
     extern crate test_data;
 
     use super::{
-        AsyncVerifier, BlockVerificationSink, ChainVerifierWrapper, VerificationTask, Verifier,
+        verify_canon_chain, AsyncVerifier, BlockVerificationSink, ChainCheck, ChainCheckFailure,
+        ChainVerifierWrapper, VerificationError, VerificationTask, Verifier,
     };
     use chain::IndexedBlock;
+    use db::kv::{Key, KeyValueDatabase, SharedMemoryDatabase, Transaction};
     use db::BlockChainDatabase;
     use network::Network;
     use primitives::hash::H256;
@@ -264,13 +458,16 @@ pub mod tests {
     use synchronization_client_core::CoreVerificationSink;
     use synchronization_executor::tests::DummyTaskExecutor;
     use types::StorageRef;
-    use verification::{BackwardsCompatibleChainVerifier as ChainVerifier, VerificationLevel};
-    use VerificationParameters;
+    use verification::{
+        BackwardsCompatibleChainVerifier as ChainVerifier, Error as ChainVerifierError,
+        VerificationLevel,
+    };
+    use {VerificationParameters, VerificationRule};
 
     #[derive(Default)]
     pub struct DummyVerifier {
         sink: Option<Arc<CoreVerificationSink<DummyTaskExecutor>>>,
-        errors: HashMap<H256, String>,
+        errors: HashMap<H256, VerificationError>,
         actual_checks: HashSet<H256>,
         storage: Option<StorageRef>,
         verifier: Option<ChainVerifierWrapper>,
@@ -292,12 +489,13 @@ pub mod tests {
                 VerificationParameters {
                     verification_level: VerificationLevel::Full,
                     verification_edge: 0u8.into(),
+                    verification_rules: Vec::new(),
                 },
             ));
         }
 
-        pub fn error_when_verifying(&mut self, hash: H256, err: &str) {
-            self.errors.insert(hash, err.into());
+        pub fn error_when_verifying(&mut self, hash: H256, err: VerificationError) {
+            self.errors.insert(hash, err);
         }
 
         pub fn _actual_check_when_verifying(&mut self, hash: H256) {
@@ -309,13 +507,15 @@ pub mod tests {
         fn verify_block(&self, block: IndexedBlock) {
             match self.sink {
                 Some(ref sink) => match self.errors.get(&block.hash()) {
-                    Some(err) => sink.on_block_verification_error(&err, &block.hash()),
+                    Some(err) => {
+                        sink.on_block_verification_error(&err, &block.hash());
+                    }
                     None => {
                         if self.actual_checks.contains(block.hash()) {
                             AsyncVerifier::execute_single_task(
                                 sink,
                                 self.verifier.as_ref().unwrap(),
-                                VerificationTask::VerifyBlock(block),
+                                VerificationTask::verify_block(block),
                             );
                         } else {
                             sink.on_block_verification_success(block);
@@ -342,6 +542,7 @@ pub mod tests {
                 VerificationParameters {
                     verification_level: VerificationLevel::NoVerification,
                     verification_edge: test_data::genesis().hash(),
+                    verification_rules: Vec::new(),
                 }
             )
             .enforce_full_verification
@@ -356,6 +557,7 @@ pub mod tests {
             VerificationParameters {
                 verification_level: VerificationLevel::NoVerification,
                 verification_edge: test_data::block_h1().hash(),
+                verification_rules: Vec::new(),
             },
         );
         assert_eq!(
@@ -385,8 +587,127 @@ pub mod tests {
             VerificationParameters {
                 verification_level: VerificationLevel::NoVerification,
                 verification_edge: 1.into(),
+                verification_rules: Vec::new(),
             },
         );
         assert_eq!(wrapper.verify_block(&bad_block), Ok(()));
     }
+
+    #[test]
+    fn verification_rules_override_verification_level() {
+        let storage: StorageRef = Arc::new(BlockChainDatabase::init_test_chain(vec![
+            test_data::genesis().into(),
+        ]));
+        let verifier = Arc::new(ChainVerifier::new(storage.clone(), Network::Unitest));
+        let bad_block: IndexedBlock = test_data::block_builder().header().build().build().into();
+        let wrapper = |from, to| {
+            ChainVerifierWrapper::new(
+                verifier.clone(),
+                &storage,
+                VerificationParameters {
+                    verification_level: VerificationLevel::Full,
+                    verification_edge: 0u8.into(),
+                    verification_rules: vec![VerificationRule {
+                        from: from,
+                        to: to,
+                        level: VerificationLevel::NoVerification,
+                    }],
+                },
+            )
+        };
+
+        // block of unknown chain is verified as the block at height 1
+        assert_eq!(wrapper(1, Some(1)).verify_block(&bad_block), Ok(()));
+        assert_eq!(wrapper(0, None).verify_block(&bad_block), Ok(()));
+        assert!(wrapper(2, None).verify_block(&bad_block).is_err());
+    }
+
+    #[test]
+    fn verify_canon_chain_reports_first_failure() {
+        let mut b1: IndexedBlock = test_data::block_h1().into();
+        b1.proof.clear();
+        let storage: StorageRef = Arc::new(BlockChainDatabase::init_test_chain(vec![
+            test_data::genesis().into(),
+            b1.clone(),
+            test_data::block_h2().into(),
+        ]));
+        let verifier = ChainVerifier::new(storage.clone(), Network::Unitest);
+
+        // only the best block is verified
+        assert_eq!(
+            verify_canon_chain(&verifier, &storage, VerificationLevel::Full, 1),
+            ChainCheck {
+                checked: 1,
+                failure: None,
+            }
+        );
+        // corrupted proof isn't verified at header level
+        assert_eq!(
+            verify_canon_chain(&verifier, &storage, VerificationLevel::Header, 0),
+            ChainCheck {
+                checked: 2,
+                failure: None,
+            }
+        );
+        assert_eq!(
+            verify_canon_chain(&verifier, &storage, VerificationLevel::Full, 0),
+            ChainCheck {
+                checked: 0,
+                failure: Some(ChainCheckFailure {
+                    height: 1,
+                    hash: b1.hash().clone(),
+                    error: ChainVerifierError::Vdf,
+                }),
+            }
+        );
+    }
+
+    #[test]
+    fn verify_canon_chain_reports_missing_block() {
+        let shared_database = SharedMemoryDatabase::default();
+        let db = BlockChainDatabase::open(shared_database.clone());
+        let blocks: Vec<IndexedBlock> = vec![
+            test_data::genesis().into(),
+            test_data::block_h1().into(),
+            test_data::block_h2().into(),
+        ];
+        for block in &blocks {
+            db.insert(block.clone()).unwrap();
+            db.canonize(block.hash()).unwrap();
+        }
+        let mut tx = Transaction::new();
+        tx.delete(Key::Block(blocks[1].hash().clone()));
+        shared_database.write(tx).unwrap();
+
+        let storage: StorageRef = Arc::new(db);
+        let verifier = ChainVerifier::new(storage.clone(), Network::Unitest);
+        let check = verify_canon_chain(&verifier, &storage, VerificationLevel::Header, 0);
+        assert_eq!(check.checked, 0);
+        let failure = check.failure.unwrap();
+        assert_eq!(failure.height, 1);
+        assert_eq!(&failure.hash, blocks[1].hash());
+        match failure.error {
+            ChainVerifierError::Database(_) => (),
+            error => panic!("unexpected error: {:?}", error),
+        }
+    }
+
+    #[test]
+    fn chain_verifier_errors_are_classified() {
+        let error: VerificationError = ChainVerifierError::Pow.into();
+        assert_eq!(error, VerificationError::BadProof);
+        assert_eq!(error.reason(), "bad-proof");
+        assert_eq!(
+            VerificationError::from(ChainVerifierError::ParentVdf),
+            VerificationError::BadParent
+        );
+        assert_eq!(
+            VerificationError::from(ChainVerifierError::FuturisticTimestamp).to_string(),
+            "bad-timestamp"
+        );
+        assert_eq!(
+            VerificationError::Database("IO error".into()).to_string(),
+            "database-error: IO error"
+        );
+    }
 }
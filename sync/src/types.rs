@@ -9,7 +9,7 @@ use synchronization_executor::LocalSynchronizationTaskExecutor;
 use synchronization_peers::Peers;
 use synchronization_server::ServerImpl;
 use synchronization_verifier::AsyncVerifier;
-use utils::SynchronizationState;
+use utils::{MemoryBudget, SerializedBlockCache, SynchronizationState};
 
 pub use utils::BlockHeight;
 
@@ -50,3 +50,9 @@ pub type LocalNodeRef = Arc<
 
 /// Synchronization events listener reference
 pub type SyncListenerRef = Box<dyn SyncListener>;
+
+/// Serialized blocks cache, shared by synchronization server workers
+pub type SerializedBlockCacheRef = Arc<Mutex<SerializedBlockCache>>;
+
+/// Memory budget, shared by synchronization components
+pub type MemoryBudgetRef = Arc<MemoryBudget>;
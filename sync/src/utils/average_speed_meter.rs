@@ -1,5 +1,5 @@
+use super::precise_time_s;
 use std::collections::VecDeque;
-use time;
 
 /// Speed meter with given items number
 #[derive(Debug, Default)]
@@ -50,7 +50,7 @@ impl AverageSpeedMeter {
         }
 
         // add new item
-        let now = time::precise_time_s();
+        let now = precise_time_s();
         if let Some(last_timestamp) = self.last_timestamp {
             let newest = now - last_timestamp;
             self.speed = (self.inspected_items.len() as f64 * self.speed + newest)
@@ -61,7 +61,7 @@ impl AverageSpeedMeter {
     }
 
     pub fn start(&mut self) {
-        self.last_timestamp = Some(time::precise_time_s());
+        self.last_timestamp = Some(precise_time_s());
     }
 
     pub fn stop(&mut self) {
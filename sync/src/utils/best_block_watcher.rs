@@ -0,0 +1,88 @@
+use parking_lot::{Condvar, Mutex};
+use primitives::hash::H256;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use SyncListener;
+
+/// Lets threads wait for the best block change (e.g. long-polling block template requests).
+/// Clones of the watcher share the same state, so one clone is installed as a sync listener
+/// and others are waiting on it.
+#[derive(Clone)]
+pub struct BestBlockWatcher {
+    /// Hash of the current best block and condition, signalled when it changes
+    best_block: Arc<(Mutex<H256>, Condvar)>,
+}
+
+impl BestBlockWatcher {
+    pub fn new(best_block_hash: H256) -> Self {
+        BestBlockWatcher {
+            best_block: Arc::new((Mutex::new(best_block_hash), Condvar::new())),
+        }
+    }
+
+    /// Hash of the current best block
+    pub fn best_block_hash(&self) -> H256 {
+        self.best_block.0.lock().clone()
+    }
+
+    /// Wait until the best block isn't the block with given hash, or until timeout.
+    /// Returns hash of the current best block
+    pub fn wait_for_change(&self, best_block_hash: &H256, timeout: Duration) -> H256 {
+        let deadline = Instant::now() + timeout;
+        let (ref lock, ref condvar) = *self.best_block;
+        let mut current = lock.lock();
+        while *current == *best_block_hash {
+            if condvar.wait_until(&mut current, deadline).timed_out() {
+                break;
+            }
+        }
+        current.clone()
+    }
+}
+
+impl SyncListener for BestBlockWatcher {
+    fn synchronization_state_switched(&self, _is_synchronizing: bool) {}
+
+    fn best_storage_block_inserted(&self, block_hash: &H256) {
+        let (ref lock, ref condvar) = *self.best_block;
+        *lock.lock() = block_hash.clone();
+        condvar.notify_all();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::BestBlockWatcher;
+    use primitives::hash::H256;
+    use std::thread;
+    use std::time::Duration;
+    use SyncListener;
+
+    #[test]
+    fn best_block_watcher_waits_for_change() {
+        let watcher = BestBlockWatcher::new(H256::from(1));
+
+        // nothing has changed => timeout
+        assert_eq!(
+            watcher.wait_for_change(&H256::from(1), Duration::from_millis(10)),
+            H256::from(1)
+        );
+        // already changed => no waiting
+        assert_eq!(
+            watcher.wait_for_change(&H256::from(0), Duration::from_secs(60)),
+            H256::from(1)
+        );
+
+        let listener = watcher.clone();
+        let notifier = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(10));
+            listener.best_storage_block_inserted(&H256::from(2));
+        });
+        assert_eq!(
+            watcher.wait_for_change(&H256::from(1), Duration::from_secs(60)),
+            H256::from(2)
+        );
+        assert_eq!(watcher.best_block_hash(), H256::from(2));
+        notifier.join().unwrap();
+    }
+}
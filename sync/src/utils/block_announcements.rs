@@ -0,0 +1,127 @@
+use primitives::hash::H256;
+use std::collections::{HashMap, VecDeque};
+use types::PeerIndex;
+
+/// Max number of blocks, which announcements are remembered.
+const MAX_ANNOUNCED_BLOCKS: usize = 1024;
+
+/// Block announcement, observed from peers.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BlockAnnouncement {
+    /// Hash of the announced block
+    pub hash: H256,
+    /// Local time, when block has been announced for the first time
+    pub time: u32,
+    /// Peers, which have announced the block, with delays (in milliseconds) since the first
+    /// announcement. Ordered by delay
+    pub announcers: Vec<(PeerIndex, u32)>,
+}
+
+/// Log of recently announced blocks. Ordered from oldest to newest.
+#[derive(Debug, Default)]
+pub struct BlockAnnouncements {
+    /// Announcements with precise time of the first announcement
+    blocks: HashMap<H256, (BlockAnnouncement, f64)>,
+    /// Announced blocks hashes, in order of first announcement
+    order: VecDeque<H256>,
+}
+
+impl BlockAnnouncements {
+    /// Remember that block has been announced by the peer. Only the first announcement
+    /// of the block by every peer is remembered
+    pub fn insert(&mut self, hash: H256, peer_index: PeerIndex, time: u32, precise_time_s: f64) {
+        if let Some(&mut (ref mut announcement, first_seen_s)) = self.blocks.get_mut(&hash) {
+            if announcement
+                .announcers
+                .iter()
+                .all(|&(peer, _)| peer != peer_index)
+            {
+                let delay_ms = ((precise_time_s - first_seen_s).max(0f64) * 1000f64) as u32;
+                announcement.announcers.push((peer_index, delay_ms));
+            }
+            return;
+        }
+
+        if self.order.len() >= MAX_ANNOUNCED_BLOCKS {
+            if let Some(oldest) = self.order.pop_front() {
+                self.blocks.remove(&oldest);
+            }
+        }
+
+        self.order.push_back(hash.clone());
+        self.blocks.insert(
+            hash.clone(),
+            (
+                BlockAnnouncement {
+                    hash: hash,
+                    time: time,
+                    announcers: vec![(peer_index, 0)],
+                },
+                precise_time_s,
+            ),
+        );
+    }
+
+    /// Get blocks, first announced at given time or later
+    pub fn since(&self, time: u32) -> Vec<BlockAnnouncement> {
+        self.order
+            .iter()
+            .filter_map(|hash| self.blocks.get(hash))
+            .map(|&(ref announcement, _)| announcement)
+            .filter(|announcement| announcement.time >= time)
+            .cloned()
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{BlockAnnouncement, BlockAnnouncements, MAX_ANNOUNCED_BLOCKS};
+    use primitives::hash::H256;
+
+    #[test]
+    fn block_announcements_delays() {
+        let mut announcements = BlockAnnouncements::default();
+        announcements.insert(H256::from(1), 5, 100, 10.0);
+        announcements.insert(H256::from(1), 7, 100, 10.25);
+        // repeated announcement from the same peer is ignored
+        announcements.insert(H256::from(1), 5, 101, 11.0);
+        announcements.insert(H256::from(2), 7, 200, 20.0);
+
+        assert_eq!(
+            announcements.since(0),
+            vec![
+                BlockAnnouncement {
+                    hash: H256::from(1),
+                    time: 100,
+                    announcers: vec![(5, 0), (7, 250)],
+                },
+                BlockAnnouncement {
+                    hash: H256::from(2),
+                    time: 200,
+                    announcers: vec![(7, 0)],
+                },
+            ]
+        );
+        assert_eq!(announcements.since(150).len(), 1);
+    }
+
+    fn hash(i: usize) -> H256 {
+        let mut hash = H256::default();
+        hash[0] = i as u8;
+        hash[1] = (i >> 8) as u8;
+        hash
+    }
+
+    #[test]
+    fn block_announcements_are_limited() {
+        let mut announcements = BlockAnnouncements::default();
+        for i in 0..MAX_ANNOUNCED_BLOCKS + 1 {
+            announcements.insert(hash(i), 1, i as u32, i as f64);
+        }
+
+        let remembered = announcements.since(0);
+        assert_eq!(remembered.len(), MAX_ANNOUNCED_BLOCKS);
+        assert_eq!(remembered[0].hash, hash(1));
+    }
+}
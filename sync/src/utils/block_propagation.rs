@@ -0,0 +1,311 @@
+use primitives::hash::H256;
+use std::collections::{HashMap, VecDeque};
+use types::PeerIndex;
+
+/// Max number of blocks, which propagation is remembered.
+const MAX_PROPAGATED_BLOCKS: usize = 1024;
+/// Max number of remembered blocks, which have been first announced by the same peer and have
+/// not been received yet. Announcements are not verified, so peer could announce any number of
+/// fake blocks, pushing real blocks out of the log.
+const MAX_UNRECEIVED_ANNOUNCEMENTS_PER_PEER: usize = 16;
+
+/// Stage of block propagation through the local node.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PropagationStage {
+    /// Block has been announced by the peer (inventory or headers)
+    Announced,
+    /// Block has been received from the peer
+    Received,
+    /// Block has been verified
+    Verified,
+    /// Block has been connected to the canonical chain
+    Connected,
+}
+
+/// Propagation of the block through the local node. Delays are in milliseconds since the
+/// block has been first seen (announced or received).
+#[derive(Debug, Clone, PartialEq)]
+pub struct BlockPropagation {
+    /// Hash of the block
+    pub hash: H256,
+    /// Local time, when block has been first seen
+    pub time: u32,
+    /// Peer, which has announced (or sent) the block first
+    pub first_peer: PeerIndex,
+    /// True if block has been announced before it has been received
+    pub announced: bool,
+    /// Delay of the first receipt of the block
+    pub received_ms: Option<u32>,
+    /// Delay of the block verification
+    pub verified_ms: Option<u32>,
+    /// Delay of connecting block to the canonical chain
+    pub connected_ms: Option<u32>,
+}
+
+/// Log of recently seen blocks propagation. Ordered from oldest to newest.
+#[derive(Debug, Default)]
+pub struct BlockPropagations {
+    /// Propagations with precise time of the first sight of the block
+    blocks: HashMap<H256, (BlockPropagation, f64)>,
+    /// Blocks hashes, in order of first sight
+    order: VecDeque<H256>,
+    /// Number of remembered blocks, first announced by the peer, which have not been received yet
+    unreceived: HashMap<PeerIndex, usize>,
+}
+
+impl BlockPropagations {
+    /// Remember that block has reached given stage. Only the first time of every stage is
+    /// remembered. Blocks are only tracked after they have been seen from peers, so that
+    /// locally mined and stale blocks are ignored. Announcements of the peer are ignored, while
+    /// it has `MAX_UNRECEIVED_ANNOUNCEMENTS_PER_PEER` announced blocks, which are not received
+    pub fn insert(
+        &mut self,
+        hash: H256,
+        stage: PropagationStage,
+        peer_index: Option<PeerIndex>,
+        time: u32,
+        precise_time_s: f64,
+    ) {
+        if let Some(&mut (ref mut propagation, first_seen_s)) = self.blocks.get_mut(&hash) {
+            let delay_ms = Some(((precise_time_s - first_seen_s).max(0f64) * 1000f64) as u32);
+            let stage_delay_ms = match stage {
+                PropagationStage::Announced => return,
+                PropagationStage::Received => &mut propagation.received_ms,
+                PropagationStage::Verified => &mut propagation.verified_ms,
+                PropagationStage::Connected => &mut propagation.connected_ms,
+            };
+            if stage_delay_ms.is_none() {
+                *stage_delay_ms = delay_ms;
+                if stage == PropagationStage::Received {
+                    let first_peer = propagation.first_peer;
+                    self.release_announcement(first_peer);
+                }
+            }
+            return;
+        }
+
+        let first_peer = match (stage, peer_index) {
+            (PropagationStage::Announced, Some(peer_index))
+            | (PropagationStage::Received, Some(peer_index)) => peer_index,
+            _ => return,
+        };
+
+        let announced = stage == PropagationStage::Announced;
+        if announced {
+            let unreceived = self.unreceived.entry(first_peer).or_insert(0);
+            if *unreceived >= MAX_UNRECEIVED_ANNOUNCEMENTS_PER_PEER {
+                return;
+            }
+            *unreceived += 1;
+        }
+
+        if self.order.len() >= MAX_PROPAGATED_BLOCKS {
+            if let Some(oldest) = self.order.pop_front() {
+                if let Some((propagation, _)) = self.blocks.remove(&oldest) {
+                    if propagation.received_ms.is_none() {
+                        self.release_announcement(propagation.first_peer);
+                    }
+                }
+            }
+        }
+
+        self.order.push_back(hash.clone());
+        self.blocks.insert(
+            hash.clone(),
+            (
+                BlockPropagation {
+                    hash: hash,
+                    time: time,
+                    first_peer: first_peer,
+                    announced: announced,
+                    received_ms: if announced { None } else { Some(0) },
+                    verified_ms: None,
+                    connected_ms: None,
+                },
+                precise_time_s,
+            ),
+        );
+    }
+
+    /// Forget that the peer has announced block, which has not been received yet
+    fn release_announcement(&mut self, peer_index: PeerIndex) {
+        let is_last = match self.unreceived.get_mut(&peer_index) {
+            Some(unreceived) => {
+                *unreceived -= 1;
+                *unreceived == 0
+            }
+            None => false,
+        };
+        if is_last {
+            self.unreceived.remove(&peer_index);
+        }
+    }
+
+    /// Get blocks, first seen at given time or later
+    pub fn since(&self, time: u32) -> Vec<BlockPropagation> {
+        self.order
+            .iter()
+            .filter_map(|hash| self.blocks.get(hash))
+            .map(|&(ref propagation, _)| propagation)
+            .filter(|propagation| propagation.time >= time)
+            .cloned()
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        BlockPropagation, BlockPropagations, PropagationStage, MAX_PROPAGATED_BLOCKS,
+        MAX_UNRECEIVED_ANNOUNCEMENTS_PER_PEER,
+    };
+    use primitives::hash::H256;
+
+    #[test]
+    fn block_propagation_delays() {
+        let mut propagations = BlockPropagations::default();
+        propagations.insert(
+            H256::from(1),
+            PropagationStage::Announced,
+            Some(5),
+            100,
+            10.0,
+        );
+        // only the first announcement is remembered
+        propagations.insert(
+            H256::from(1),
+            PropagationStage::Announced,
+            Some(7),
+            100,
+            10.1,
+        );
+        propagations.insert(
+            H256::from(1),
+            PropagationStage::Received,
+            Some(7),
+            100,
+            10.25,
+        );
+        propagations.insert(
+            H256::from(1),
+            PropagationStage::Received,
+            Some(5),
+            100,
+            10.3,
+        );
+        propagations.insert(H256::from(1), PropagationStage::Verified, None, 100, 10.5);
+        propagations.insert(H256::from(1), PropagationStage::Connected, None, 101, 11.0);
+        // blocks, which have not been seen from peers, are not tracked
+        propagations.insert(H256::from(2), PropagationStage::Connected, None, 200, 20.0);
+        propagations.insert(
+            H256::from(3),
+            PropagationStage::Received,
+            Some(7),
+            300,
+            30.0,
+        );
+
+        assert_eq!(
+            propagations.since(0),
+            vec![
+                BlockPropagation {
+                    hash: H256::from(1),
+                    time: 100,
+                    first_peer: 5,
+                    announced: true,
+                    received_ms: Some(250),
+                    verified_ms: Some(500),
+                    connected_ms: Some(1000),
+                },
+                BlockPropagation {
+                    hash: H256::from(3),
+                    time: 300,
+                    first_peer: 7,
+                    announced: false,
+                    received_ms: Some(0),
+                    verified_ms: None,
+                    connected_ms: None,
+                },
+            ]
+        );
+        assert_eq!(propagations.since(150).len(), 1);
+    }
+
+    fn hash(i: usize) -> H256 {
+        let mut hash = H256::default();
+        hash[0] = i as u8;
+        hash[1] = (i >> 8) as u8;
+        hash
+    }
+
+    #[test]
+    fn block_propagations_are_limited() {
+        let mut propagations = BlockPropagations::default();
+        for i in 0..MAX_PROPAGATED_BLOCKS + 1 {
+            propagations.insert(
+                hash(i),
+                PropagationStage::Received,
+                Some(1),
+                i as u32,
+                i as f64,
+            );
+        }
+
+        let remembered = propagations.since(0);
+        assert_eq!(remembered.len(), MAX_PROPAGATED_BLOCKS);
+        assert_eq!(remembered[0].hash, hash(1));
+    }
+
+    #[test]
+    fn unreceived_announcements_are_limited_per_peer() {
+        let mut propagations = BlockPropagations::default();
+        let announce = |propagations: &mut BlockPropagations, i: usize, peer_index: usize| {
+            propagations.insert(
+                hash(i),
+                PropagationStage::Announced,
+                Some(peer_index),
+                i as u32,
+                i as f64,
+            )
+        };
+        for i in 0..MAX_UNRECEIVED_ANNOUNCEMENTS_PER_PEER + 1 {
+            announce(&mut propagations, i, 1);
+        }
+        // the last announcement of the peer is ignored, while other peers are still tracked
+        assert_eq!(
+            propagations.since(0).len(),
+            MAX_UNRECEIVED_ANNOUNCEMENTS_PER_PEER
+        );
+        announce(&mut propagations, 100, 2);
+        assert_eq!(
+            propagations.since(0).len(),
+            MAX_UNRECEIVED_ANNOUNCEMENTS_PER_PEER + 1
+        );
+
+        // once announced block is received, peer could announce another one
+        propagations.insert(hash(0), PropagationStage::Received, Some(3), 0, 1.0);
+        announce(&mut propagations, 101, 1);
+        announce(&mut propagations, 102, 1);
+        let remembered = propagations.since(0);
+        assert_eq!(remembered.len(), MAX_UNRECEIVED_ANNOUNCEMENTS_PER_PEER + 2);
+        assert_eq!(remembered.last().unwrap().hash, hash(101));
+    }
+
+    #[test]
+    fn evicted_announcements_are_released() {
+        let mut propagations = BlockPropagations::default();
+        for i in 0..MAX_UNRECEIVED_ANNOUNCEMENTS_PER_PEER {
+            propagations.insert(
+                hash(i),
+                PropagationStage::Announced,
+                Some(1),
+                i as u32,
+                i as f64,
+            );
+        }
+        for i in 0..MAX_PROPAGATED_BLOCKS {
+            propagations.insert(hash(1000 + i), PropagationStage::Received, Some(2), 0, 0.0);
+        }
+        assert!(propagations.unreceived.is_empty());
+    }
+}
@@ -0,0 +1,28 @@
+#[cfg(test)]
+use std::cell::Cell;
+use time;
+
+#[cfg(test)]
+thread_local! {
+    /// Virtual time of the current thread, used by simulation tests
+    static VIRTUAL_TIME: Cell<Option<f64>> = Cell::new(None);
+}
+
+/// Returns current time in seconds.
+/// In tests, the time could be replaced with virtual time of the current thread.
+pub fn precise_time_s() -> f64 {
+    #[cfg(test)]
+    {
+        if let Some(virtual_time) = VIRTUAL_TIME.with(|time| time.get()) {
+            return virtual_time;
+        }
+    }
+
+    time::precise_time_s()
+}
+
+/// Sets virtual time of the current thread. None switches back to the real time.
+#[cfg(test)]
+pub fn set_virtual_time(virtual_time: Option<f64>) {
+    VIRTUAL_TIME.with(|time| time.set(virtual_time));
+}
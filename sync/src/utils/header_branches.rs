@@ -0,0 +1,176 @@
+use super::BlockHeight;
+use chain::IndexedBlockHeader;
+use primitives::hash::H256;
+use std::cmp::min;
+use std::collections::HashMap;
+
+/// Default max number of headers-only side branches, tracked at once.
+pub const DEFAULT_MAX_HEADER_BRANCHES: usize = 8;
+/// Default max number of headers-only blocks in single side branch.
+pub const DEFAULT_MAX_HEADER_BRANCH_LEN: BlockHeight = 2048;
+/// Default max work deficit of side branch, in best blocks.
+pub const DEFAULT_MAX_HEADER_BRANCH_WORK_DEFICIT: BlockHeight = 2048;
+
+/// Limits of side branches, which headers are accepted before blocks bodies are downloaded.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct HeaderBranchesLimits {
+    /// Max number of headers-only side branches, tracked at once
+    pub max_branches: usize,
+    /// Max number of headers-only blocks in single side branch. Branch, which is longer
+    /// than the best headers chain, is not limited
+    pub max_branch_len: BlockHeight,
+    /// Max work deficit of side branch, measured in work of the best block. Branch, which work is below
+    /// the best headers chain work minus this deficit, is rejected before its headers are verified
+    pub max_work_deficit: BlockHeight,
+}
+
+/// Headers-only side branch
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct HeaderBranch {
+    /// Height of the branch tip
+    number: BlockHeight,
+    /// Number of headers-only blocks in the branch
+    len: BlockHeight,
+}
+
+/// Side branches, which headers have been received from peers, but which blocks are not yet stored.
+/// Branches are identified by their tips.
+#[derive(Debug)]
+pub struct HeaderBranches {
+    limits: HeaderBranchesLimits,
+    branches: HashMap<H256, HeaderBranch>,
+}
+
+impl Default for HeaderBranchesLimits {
+    fn default() -> Self {
+        HeaderBranchesLimits {
+            max_branches: DEFAULT_MAX_HEADER_BRANCHES,
+            max_branch_len: DEFAULT_MAX_HEADER_BRANCH_LEN,
+            max_work_deficit: DEFAULT_MAX_HEADER_BRANCH_WORK_DEFICIT,
+        }
+    }
+}
+
+impl HeaderBranches {
+    pub fn new(limits: HeaderBranchesLimits) -> Self {
+        HeaderBranches {
+            limits: limits,
+            branches: HashMap::new(),
+        }
+    }
+
+    /// Number of tracked branches
+    #[cfg(test)]
+    pub fn len(&self) -> usize {
+        self.branches.len()
+    }
+
+    /// Forget branches, which tips are not headers-only blocks anymore
+    pub fn retain<F>(&mut self, mut is_headers_only: F)
+    where
+        F: FnMut(&H256) -> bool,
+    {
+        self.branches.retain(|tip, _| is_headers_only(tip));
+    }
+
+    /// Accept side branch headers, which are following the block with given hash and number.
+    /// If parent number is unknown, parent must be the tip of the tracked branch.
+    /// Returns number of leading headers, which are accepted.
+    pub fn accept(
+        &mut self,
+        parent_hash: &H256,
+        parent_number: Option<BlockHeight>,
+        headers: &[IndexedBlockHeader],
+        best_header_number: BlockHeight,
+    ) -> usize {
+        let branch = match self.branches.get(parent_hash).cloned() {
+            Some(branch) => branch,
+            None => match parent_number {
+                Some(_) if self.branches.len() >= self.limits.max_branches => return 0,
+                Some(number) => HeaderBranch {
+                    number: number,
+                    len: 0,
+                },
+                None => return 0,
+            },
+        };
+
+        // branch, which becomes longer than the best chain, has more work => it is not limited
+        let num_headers = headers.len() as BlockHeight;
+        let num_accepted = if branch.number + num_headers > best_header_number {
+            num_headers
+        } else {
+            min(
+                num_headers,
+                self.limits.max_branch_len.saturating_sub(branch.len),
+            )
+        };
+        if num_accepted == 0 {
+            return 0;
+        }
+
+        self.branches.remove(parent_hash);
+        self.branches.insert(
+            headers[num_accepted as usize - 1].hash.clone(),
+            HeaderBranch {
+                number: branch.number + num_accepted,
+                len: branch.len + num_accepted,
+            },
+        );
+        num_accepted as usize
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate test_data;
+
+    use super::{HeaderBranches, HeaderBranchesLimits};
+    use chain::IndexedBlockHeader;
+
+    fn headers(n: u32, nonce: u32) -> Vec<IndexedBlockHeader> {
+        test_data::build_n_empty_blocks_from_genesis(n, nonce)
+            .into_iter()
+            .map(|block| block.block_header.into())
+            .collect()
+    }
+
+    #[test]
+    fn header_branches_are_limited() {
+        let mut branches = HeaderBranches::new(HeaderBranchesLimits {
+            max_branches: 1,
+            max_branch_len: 3,
+            max_work_deficit: 0,
+        });
+        let genesis = test_data::genesis().hash();
+        let branch = headers(4, 100);
+        assert_eq!(branches.accept(&genesis, Some(0), &branch[0..2], 10), 2);
+        assert_eq!(branches.len(), 1);
+
+        // the only branch is extended up to the limit
+        assert_eq!(branches.accept(&branch[1].hash, None, &branch[2..4], 10), 1);
+        assert_eq!(branches.accept(&branch[2].hash, None, &branch[3..4], 10), 0);
+
+        // new branch is not accepted
+        let other_branch = headers(1, 200);
+        assert_eq!(branches.accept(&genesis, Some(0), &other_branch, 10), 0);
+
+        // unless previous branch is forgotten
+        branches.retain(|_| false);
+        assert_eq!(branches.accept(&genesis, Some(0), &other_branch, 10), 1);
+    }
+
+    #[test]
+    fn header_branch_longer_than_best_chain_is_not_limited() {
+        let mut branches = HeaderBranches::new(HeaderBranchesLimits {
+            max_branches: 1,
+            max_branch_len: 1,
+            max_work_deficit: 0,
+        });
+        let genesis = test_data::genesis().hash();
+        let branch = headers(3, 100);
+        assert_eq!(branches.accept(&genesis, Some(0), &branch[0..2], 1), 2);
+        assert_eq!(branches.accept(&branch[1].hash, None, &branch[2..3], 1), 1);
+        assert_eq!(branches.accept(&genesis, None, &branch, 1), 0);
+    }
+}
@@ -0,0 +1,277 @@
+use chain::IndexedBlockHeader;
+use linked_hash_map::LinkedHashMap;
+use primitives::hash::H256;
+use std::collections::HashMap;
+use types::{PeerIndex, RequestId};
+use utils::precise_time_s;
+
+/// Headers messages with more headers are responses to our requests rather than announcements.
+pub const MAX_ANNOUNCED_HEADERS: usize = 8;
+/// Pending `getheaders` requests are forgotten after this number of seconds. Peer, which
+/// has not responded in time, is penalized by the synchronization manager long before that.
+const HEADERS_REQUEST_TIMEOUT_S: f64 = 60f64;
+/// Max number of pending `getheaders` requests to the single peer. Oldest request is
+/// forgotten when peer is asked for headers again.
+const MAX_PENDING_REQUESTS_PER_PEER: usize = 4;
+
+/// `getheaders` request, which is waiting for the response.
+#[derive(Debug, Clone, PartialEq)]
+struct PendingHeadersRequest {
+    /// Peer, which has been asked for headers
+    peer_index: PeerIndex,
+    /// Block locator hashes of the request
+    block_locator_hashes: Vec<H256>,
+    /// Time when request has been sent
+    timestamp: f64,
+}
+
+/// Attribution of the received `headers` message.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum HeadersResponse {
+    /// Response to the pending request
+    Expected(RequestId),
+    /// Response to the pending request, which is not connected to any known block
+    Mismatched(RequestId),
+    /// Response to the request, which has been already answered
+    Duplicate(RequestId),
+    /// Headers, which have not been requested (e.g. new blocks announcement)
+    Unsolicited,
+}
+
+/// Outgoing `getheaders` requests. `headers` messages carry no request id, so the response
+/// is attributed to the oldest pending request to the same peer, which block locator it follows.
+/// Older requests to this peer are considered answered with empty `headers` messages.
+/// Long responses, which follow another known block (e.g. the best block of the peer
+/// has been reorganized), are attributed to the oldest pending request.
+#[derive(Debug, Default)]
+pub struct HeadersRequests {
+    /// Id of the next request
+    next_request_id: RequestId,
+    /// Pending requests by id, oldest first
+    pending: LinkedHashMap<RequestId, PendingHeadersRequest>,
+    /// Last answered request of every peer and hash of the first header of the response
+    answered: HashMap<PeerIndex, (RequestId, H256)>,
+}
+
+impl HeadersRequests {
+    /// Remember `getheaders` request, sent to the peer. Returns id of the request
+    pub fn on_requested(
+        &mut self,
+        peer_index: PeerIndex,
+        block_locator_hashes: Vec<H256>,
+    ) -> RequestId {
+        self.remove_expired();
+
+        let peer_requests: Vec<RequestId> = self
+            .pending
+            .iter()
+            .filter(|&(_, request)| request.peer_index == peer_index)
+            .map(|(id, _)| *id)
+            .collect();
+        if peer_requests.len() >= MAX_PENDING_REQUESTS_PER_PEER {
+            self.pending.remove(&peer_requests[0]);
+        }
+
+        let request_id = self.next_request_id;
+        self.next_request_id = self.next_request_id.wrapping_add(1);
+        self.pending.insert(
+            request_id,
+            PendingHeadersRequest {
+                peer_index: peer_index,
+                block_locator_hashes: block_locator_hashes,
+                timestamp: precise_time_s(),
+            },
+        );
+        request_id
+    }
+
+    /// Attribute non-empty `headers` message, received from the peer.
+    /// `is_parent_known` tells whether parent of the first header is known to the chain
+    pub fn on_response(
+        &mut self,
+        peer_index: PeerIndex,
+        headers: &[IndexedBlockHeader],
+        is_parent_known: bool,
+    ) -> HeadersResponse {
+        self.remove_expired();
+
+        let first_header = &headers[0];
+        let peer_requests: Vec<(RequestId, bool)> = self
+            .pending
+            .iter()
+            .filter(|&(_, request)| request.peer_index == peer_index)
+            .map(|(id, request)| {
+                let is_following_locator = request
+                    .block_locator_hashes
+                    .contains(&first_header.raw.previous_header_hash);
+                (*id, is_following_locator)
+            })
+            .collect();
+        // short messages could be announcements of new blocks, sent before the response
+        let is_response = headers.len() > MAX_ANNOUNCED_HEADERS;
+        let position = peer_requests
+            .iter()
+            .position(|&(_, is_following_locator)| is_following_locator)
+            .or_else(|| {
+                if is_response && is_parent_known && !peer_requests.is_empty() {
+                    Some(0)
+                } else {
+                    None
+                }
+            });
+        if let Some(position) = position {
+            for &(request_id, _) in &peer_requests[..position + 1] {
+                self.pending.remove(&request_id);
+            }
+            let request_id = peer_requests[position].0;
+            self.answered
+                .insert(peer_index, (request_id, first_header.hash.clone()));
+            return HeadersResponse::Expected(request_id);
+        }
+        if !peer_requests.is_empty() && is_response {
+            let request_id = peer_requests[0].0;
+            self.pending.remove(&request_id);
+            return HeadersResponse::Mismatched(request_id);
+        }
+
+        match self.answered.get(&peer_index) {
+            Some(&(request_id, ref first_header_hash))
+                if *first_header_hash == first_header.hash =>
+            {
+                HeadersResponse::Duplicate(request_id)
+            }
+            _ => HeadersResponse::Unsolicited,
+        }
+    }
+
+    /// Forget requests to the disconnected peer
+    pub fn remove_peer(&mut self, peer_index: PeerIndex) {
+        let peer_requests: Vec<RequestId> = self
+            .pending
+            .iter()
+            .filter(|&(_, request)| request.peer_index == peer_index)
+            .map(|(id, _)| *id)
+            .collect();
+        for request_id in peer_requests {
+            self.pending.remove(&request_id);
+        }
+        self.answered.remove(&peer_index);
+    }
+
+    /// Forget requests, which have not been answered in time
+    fn remove_expired(&mut self) {
+        let now = precise_time_s();
+        loop {
+            match self.pending.front() {
+                Some((_, request)) if now - request.timestamp >= HEADERS_REQUEST_TIMEOUT_S => (),
+                _ => break,
+            }
+            self.pending.pop_front();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate test_data;
+
+    use super::{HeadersRequests, HeadersResponse, HEADERS_REQUEST_TIMEOUT_S};
+    use chain::IndexedBlockHeader;
+    use primitives::hash::H256;
+    use utils::set_virtual_time;
+
+    fn headers(count: usize) -> Vec<IndexedBlockHeader> {
+        let mut headers = Vec::new();
+        let mut previous_header_hash = test_data::genesis().hash();
+        for _ in 0..count {
+            let header = test_data::block_builder()
+                .header()
+                .parent(previous_header_hash)
+                .build()
+                .build()
+                .block_header;
+            previous_header_hash = header.hash();
+            headers.push(header.into());
+        }
+        headers
+    }
+
+    #[test]
+    fn headers_responses_are_attributed_to_requests() {
+        let mut requests = HeadersRequests::default();
+        let locator = vec![test_data::genesis().hash()];
+        let response = headers(16);
+
+        let request1 = requests.on_requested(1, locator.clone());
+        let request2 = requests.on_requested(1, locator.clone());
+        assert_eq!(
+            requests.on_response(1, &response, true),
+            HeadersResponse::Expected(request1)
+        );
+        assert_eq!(
+            requests.on_response(1, &response, true),
+            HeadersResponse::Expected(request2)
+        );
+        assert_eq!(
+            requests.on_response(1, &response, true),
+            HeadersResponse::Duplicate(request2)
+        );
+        assert_eq!(
+            requests.on_response(2, &response, true),
+            HeadersResponse::Unsolicited
+        );
+
+        // older request has been answered with empty message
+        requests.on_requested(2, vec![H256::from(1)]);
+        let request4 = requests.on_requested(2, locator.clone());
+        assert_eq!(
+            requests.on_response(2, &response, true),
+            HeadersResponse::Expected(request4)
+        );
+
+        // response, which does not follow the locator
+        let request5 = requests.on_requested(2, vec![H256::from(1)]);
+        assert_eq!(
+            requests.on_response(2, &response[1..2], false),
+            HeadersResponse::Unsolicited
+        );
+        assert_eq!(
+            requests.on_response(2, &response[1..], false),
+            HeadersResponse::Mismatched(request5)
+        );
+        assert_eq!(
+            requests.on_response(2, &response, true),
+            HeadersResponse::Duplicate(request4)
+        );
+
+        // response, which follows another known block
+        let request6 = requests.on_requested(2, vec![H256::from(1)]);
+        assert_eq!(
+            requests.on_response(2, &response[1..], true),
+            HeadersResponse::Expected(request6)
+        );
+    }
+
+    #[test]
+    fn headers_requests_are_forgotten() {
+        set_virtual_time(Some(1000.0));
+        let mut requests = HeadersRequests::default();
+        let locator = vec![test_data::genesis().hash()];
+        let response = headers(16);
+
+        requests.on_requested(1, locator.clone());
+        set_virtual_time(Some(1000.0 + HEADERS_REQUEST_TIMEOUT_S));
+        assert_eq!(
+            requests.on_response(1, &response, true),
+            HeadersResponse::Unsolicited
+        );
+
+        requests.on_requested(1, locator.clone());
+        requests.remove_peer(1);
+        assert_eq!(
+            requests.on_response(1, &response, true),
+            HeadersResponse::Unsolicited
+        );
+        set_virtual_time(None);
+    }
+}
@@ -11,7 +11,7 @@ pub enum KnownHashType {
     Block,
 }
 
-/// Known-hashes filter
+/// Known-hashes filter. When it is full, hashes, which haven't been seen for the longest time, are forgotten
 #[derive(Debug, Default)]
 pub struct KnownHashFilter {
     /// Insertion-time ordered known hashes
@@ -19,9 +19,9 @@ pub struct KnownHashFilter {
 }
 
 impl KnownHashFilter {
-    /// Insert known hash
+    /// Insert known hash. Already known hash becomes the most recently seen
     pub fn insert(&mut self, hash: H256, hash_type: KnownHashType) {
-        if !self.known_hashes.contains_key(&hash) {
+        if self.known_hashes.get_refresh(&hash).is_none() {
             self.known_hashes.insert(hash, hash_type);
             // remove oldest-known hash, if limits overflow
             if self.known_hashes.len() > MAX_KNOWN_HASHES_LEN {
@@ -112,4 +112,23 @@ mod tests {
         hash_data[1] = 0;
         assert!(filter.contains(&H256::from(hash_data.clone()), KnownHashType::Block));
     }
+
+    #[test]
+    fn known_hash_filter_refreshes_reinserted_hash() {
+        let hash = |i: usize| {
+            let mut hash_data = [0u8; 32];
+            hash_data[0] = (i % 256) as u8;
+            hash_data[1] = (i / 256) as u8;
+            H256::from(hash_data)
+        };
+        let mut filter = KnownHashFilter::default();
+        for i in 0..MAX_KNOWN_HASHES_LEN {
+            filter.insert(hash(i), KnownHashType::Block);
+        }
+        // oldest hash is seen again => next-to-oldest hash is forgotten instead
+        filter.insert(hash(0), KnownHashType::Block);
+        filter.insert(hash(MAX_KNOWN_HASHES_LEN), KnownHashType::Block);
+        assert!(filter.contains(&hash(0), KnownHashType::Block));
+        assert!(!filter.contains(&hash(1), KnownHashType::Block));
+    }
 }
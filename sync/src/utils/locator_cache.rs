@@ -0,0 +1,116 @@
+use super::BlockHeight;
+use bitcrypto::{DHash256, Digest};
+use linked_hash_map::LinkedHashMap;
+use primitives::hash::H256;
+
+/// Default max number of cached locators.
+pub const DEFAULT_LOCATOR_CACHE_SIZE: usize = 1024;
+
+/// Cache of recently resolved block locators.
+/// Maps locator key (hash of all locator hashes and the stop hash) to the best common block
+/// height of the locator and our main chain.
+/// All resolutions are only valid while the best block stays the same, so cache is
+/// cleared each time the best block changes.
+#[derive(Debug)]
+pub struct LocatorCache {
+    /// Max number of cached locators
+    max_size: usize,
+    /// Best block hash at the moment entries have been resolved
+    best_block_hash: H256,
+    /// Cached resolutions: locator key => best common block height
+    entries: LinkedHashMap<H256, BlockHeight>,
+}
+
+impl LocatorCache {
+    /// Create new cache with given capacity
+    pub fn new(max_size: usize) -> Self {
+        LocatorCache {
+            max_size: max_size,
+            best_block_hash: H256::default(),
+            entries: LinkedHashMap::new(),
+        }
+    }
+
+    /// Key of the locator with given stop hash. Locators, sharing the same head, may still
+    /// resolve to different blocks, so every hash is a part of the key
+    pub fn key(locator: &[H256], hash_stop: &H256) -> H256 {
+        let mut hasher = DHash256::new();
+        for hash in locator.iter().chain(Some(hash_stop)) {
+            hasher.input(&**hash);
+        }
+        hasher.finish()
+    }
+
+    /// Number of cached resolutions
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Get cached resolution of locator with given key
+    pub fn get(&mut self, best_block_hash: &H256, locator_key: &H256) -> Option<BlockHeight> {
+        if &self.best_block_hash != best_block_hash {
+            self.best_block_hash = best_block_hash.clone();
+            self.entries.clear();
+            return None;
+        }
+
+        self.entries.get_refresh(locator_key).cloned()
+    }
+
+    /// Remember resolution of locator with given key
+    pub fn insert(&mut self, best_block_hash: &H256, locator_key: H256, height: BlockHeight) {
+        if &self.best_block_hash != best_block_hash {
+            self.best_block_hash = best_block_hash.clone();
+            self.entries.clear();
+        }
+
+        self.entries.insert(locator_key, height);
+        if self.entries.len() > self.max_size {
+            self.entries.pop_front();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::LocatorCache;
+    use primitives::hash::H256;
+
+    #[test]
+    fn locator_cache_resolves_while_best_block_is_same() {
+        let mut cache = LocatorCache::new(16);
+        assert_eq!(cache.get(&H256::from(1), &H256::from(10)), None);
+        cache.insert(&H256::from(1), H256::from(10), 5);
+        assert_eq!(cache.get(&H256::from(1), &H256::from(10)), Some(5));
+        assert_eq!(cache.get(&H256::from(1), &H256::from(11)), None);
+        // best block has changed => all resolutions are dropped
+        assert_eq!(cache.get(&H256::from(2), &H256::from(10)), None);
+        assert_eq!(cache.len(), 0);
+    }
+
+    #[test]
+    fn locator_cache_evicts_least_recently_used() {
+        let mut cache = LocatorCache::new(2);
+        cache.insert(&H256::from(1), H256::from(10), 1);
+        cache.insert(&H256::from(1), H256::from(11), 2);
+        assert_eq!(cache.get(&H256::from(1), &H256::from(10)), Some(1));
+        cache.insert(&H256::from(1), H256::from(12), 3);
+        assert_eq!(cache.len(), 2);
+        assert_eq!(cache.get(&H256::from(1), &H256::from(11)), None);
+        assert_eq!(cache.get(&H256::from(1), &H256::from(10)), Some(1));
+        assert_eq!(cache.get(&H256::from(1), &H256::from(12)), Some(3));
+    }
+
+    #[test]
+    fn locator_cache_key_covers_whole_locator() {
+        let key = LocatorCache::key(&[H256::from(10), H256::from(9)], &H256::default());
+        assert_eq!(
+            key,
+            LocatorCache::key(&[H256::from(10), H256::from(9)], &H256::default())
+        );
+        // same head, different tail
+        assert!(key != LocatorCache::key(&[H256::from(10), H256::from(8)], &H256::default()));
+        assert!(key != LocatorCache::key(&[H256::from(10)], &H256::default()));
+        assert!(key != LocatorCache::key(&[H256::from(10), H256::from(9)], &H256::from(1)));
+    }
+}
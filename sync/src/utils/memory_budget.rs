@@ -0,0 +1,140 @@
+use parking_lot::Mutex;
+
+/// Default number of bytes, which blocks and requests, held in memory by synchronization, may occupy.
+pub const DEFAULT_MEMORY_BUDGET: usize = 256 * 1024 * 1024;
+
+/// Component, which holds blocks or requests in memory.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MemoryConsumer {
+    /// Blocks, waiting for their parents
+    OrphanBlocks,
+    /// Blocks, waiting for verification
+    VerificationQueue,
+    /// Peers requests, waiting to be served
+    ServerQueue,
+}
+
+/// Current usage of the memory budget, in bytes.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MemoryUsage {
+    /// Overall budget
+    pub limit: usize,
+    /// Blocks, waiting for their parents
+    pub orphan_blocks: usize,
+    /// Blocks, waiting for verification
+    pub verification_queue: usize,
+    /// Peers requests, waiting to be served
+    pub server_queue: usize,
+}
+
+/// Byte budget, shared by all synchronization components, holding blocks in memory.
+/// Every consumer has its own limit (e.g. max verification queue length), but when the overall
+/// budget is exhausted, consumers stop accepting data, which could be dropped or deferred.
+/// Data, which is required to make progress (e.g. requested blocks), is accounted even if it
+/// doesn't fit the budget.
+#[derive(Debug)]
+pub struct MemoryBudget {
+    /// Overall budget
+    limit: usize,
+    /// Bytes, used by every consumer
+    usage: Mutex<MemoryUsage>,
+}
+
+impl MemoryUsage {
+    /// Bytes, used by all consumers
+    pub fn used(&self) -> usize {
+        self.orphan_blocks + self.verification_queue + self.server_queue
+    }
+
+    fn consumer_mut(&mut self, consumer: MemoryConsumer) -> &mut usize {
+        match consumer {
+            MemoryConsumer::OrphanBlocks => &mut self.orphan_blocks,
+            MemoryConsumer::VerificationQueue => &mut self.verification_queue,
+            MemoryConsumer::ServerQueue => &mut self.server_queue,
+        }
+    }
+}
+
+impl MemoryBudget {
+    pub fn new(limit: usize) -> Self {
+        MemoryBudget {
+            limit: limit,
+            usage: Mutex::new(MemoryUsage {
+                limit: limit,
+                ..Default::default()
+            }),
+        }
+    }
+
+    /// Account bytes of the consumer if they fit the budget. Returns false otherwise
+    pub fn allocate(&self, consumer: MemoryConsumer, bytes: usize) -> bool {
+        let mut usage = self.usage.lock();
+        if usage.used().saturating_add(bytes) > self.limit {
+            return false;
+        }
+        *usage.consumer_mut(consumer) += bytes;
+        true
+    }
+
+    /// Account bytes of the consumer, even if they do not fit the budget
+    pub fn force_allocate(&self, consumer: MemoryConsumer, bytes: usize) {
+        let mut usage = self.usage.lock();
+        let used = usage.consumer_mut(consumer);
+        *used = used.saturating_add(bytes);
+    }
+
+    /// Forget bytes, which consumer has released
+    pub fn release(&self, consumer: MemoryConsumer, bytes: usize) {
+        let mut usage = self.usage.lock();
+        let used = usage.consumer_mut(consumer);
+        *used = used.saturating_sub(bytes);
+    }
+
+    /// Is the whole budget used?
+    pub fn is_exhausted(&self) -> bool {
+        self.usage.lock().used() >= self.limit
+    }
+
+    /// Current usage of the budget
+    pub fn usage(&self) -> MemoryUsage {
+        self.usage.lock().clone()
+    }
+}
+
+impl Default for MemoryBudget {
+    fn default() -> Self {
+        MemoryBudget::new(DEFAULT_MEMORY_BUDGET)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{MemoryBudget, MemoryConsumer, MemoryUsage};
+
+    #[test]
+    fn memory_budget_is_shared_by_consumers() {
+        let budget = MemoryBudget::new(100);
+        assert!(budget.allocate(MemoryConsumer::OrphanBlocks, 60));
+        assert!(!budget.allocate(MemoryConsumer::VerificationQueue, 50));
+        assert!(budget.allocate(MemoryConsumer::VerificationQueue, 40));
+        assert!(budget.is_exhausted());
+
+        // required data is accounted even when budget is exhausted
+        budget.force_allocate(MemoryConsumer::ServerQueue, 10);
+        assert_eq!(
+            budget.usage(),
+            MemoryUsage {
+                limit: 100,
+                orphan_blocks: 60,
+                verification_queue: 40,
+                server_queue: 10,
+            }
+        );
+
+        budget.release(MemoryConsumer::OrphanBlocks, 60);
+        budget.release(MemoryConsumer::ServerQueue, 20);
+        assert!(!budget.is_exhausted());
+        assert!(budget.allocate(MemoryConsumer::OrphanBlocks, 60));
+        assert_eq!(budget.usage().used(), 100);
+    }
+}
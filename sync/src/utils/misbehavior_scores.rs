@@ -0,0 +1,128 @@
+use std::collections::HashMap;
+use std::net::{IpAddr, Ipv6Addr};
+use utils::precise_time_s;
+
+/// Score, added when peer is misbehaving.
+pub const MISBEHAVING_SCORE: u32 = 20;
+/// Score, added when peer DoS attempt is detected.
+pub const DOS_SCORE: u32 = 100;
+/// Peers with this score are disconnected right after they have connected.
+pub const BAN_SCORE: u32 = 100;
+/// By default, misbehavior is remembered for a day.
+pub const DEFAULT_MISBEHAVIOR_SCORE_TTL_S: u32 = 24 * 60 * 60;
+
+#[derive(Debug)]
+struct Score {
+    /// Accumulated score
+    score: u32,
+    /// Time of the last score increase
+    updated: f64,
+}
+
+/// Misbehavior scores of peers addresses. Scores are kept after peer disconnects, so that peer
+/// can't reset its score by reconnecting. IPv6 peers are scored by /64 subnet, which is
+/// usually owned by a single host. Score is forgotten when it isn't increased for TTL.
+#[derive(Debug)]
+pub struct MisbehaviorScores {
+    /// Score time-to-live, in seconds
+    ttl_s: f64,
+    /// Scores by address (or subnet)
+    scores: HashMap<IpAddr, Score>,
+}
+
+impl Default for MisbehaviorScores {
+    fn default() -> Self {
+        MisbehaviorScores::new(DEFAULT_MISBEHAVIOR_SCORE_TTL_S)
+    }
+}
+
+impl MisbehaviorScores {
+    pub fn new(ttl_s: u32) -> Self {
+        MisbehaviorScores {
+            ttl_s: ttl_s as f64,
+            scores: HashMap::new(),
+        }
+    }
+
+    /// Increase score of the address. Returns new score
+    pub fn add(&mut self, address: &IpAddr, score: u32) -> u32 {
+        let now = precise_time_s();
+        let ttl_s = self.ttl_s;
+        self.scores.retain(|_, score| now - score.updated < ttl_s);
+
+        let entry = self.scores.entry(score_key(address)).or_insert(Score {
+            score: 0,
+            updated: now,
+        });
+        entry.score = entry.score.saturating_add(score);
+        entry.updated = now;
+        entry.score
+    }
+
+    /// Current score of the address
+    pub fn score(&self, address: &IpAddr) -> u32 {
+        let now = precise_time_s();
+        self.scores
+            .get(&score_key(address))
+            .filter(|score| now - score.updated < self.ttl_s)
+            .map(|score| score.score)
+            .unwrap_or(0)
+    }
+}
+
+/// IPv4 addresses are scored individually, IPv6 addresses are scored by /64 subnet
+fn score_key(address: &IpAddr) -> IpAddr {
+    match *address {
+        IpAddr::V4(_) => *address,
+        IpAddr::V6(ref address) => {
+            let segments = address.segments();
+            IpAddr::V6(Ipv6Addr::new(
+                segments[0],
+                segments[1],
+                segments[2],
+                segments[3],
+                0,
+                0,
+                0,
+                0,
+            ))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{MisbehaviorScores, MISBEHAVING_SCORE};
+    use utils::set_virtual_time;
+
+    #[test]
+    fn misbehavior_scores_are_accumulated_and_forgotten() {
+        set_virtual_time(Some(1000.0));
+        let mut scores = MisbehaviorScores::new(60);
+        let peer = "10.0.0.1".parse().unwrap();
+        let other_peer = "10.0.0.2".parse().unwrap();
+        assert_eq!(scores.add(&peer, MISBEHAVING_SCORE), MISBEHAVING_SCORE);
+        assert_eq!(scores.add(&peer, MISBEHAVING_SCORE), 2 * MISBEHAVING_SCORE);
+        assert_eq!(scores.score(&peer), 2 * MISBEHAVING_SCORE);
+        assert_eq!(scores.score(&other_peer), 0);
+
+        set_virtual_time(Some(1059.0));
+        assert_eq!(scores.score(&peer), 2 * MISBEHAVING_SCORE);
+
+        set_virtual_time(Some(1060.0));
+        assert_eq!(scores.score(&peer), 0);
+        assert_eq!(scores.add(&peer, MISBEHAVING_SCORE), MISBEHAVING_SCORE);
+        set_virtual_time(None);
+    }
+
+    #[test]
+    fn ipv6_misbehavior_scores_are_shared_by_subnet() {
+        let mut scores = MisbehaviorScores::default();
+        let peer = "2001:db8:1:2::1".parse().unwrap();
+        let same_subnet_peer = "2001:db8:1:2:ffff::2".parse().unwrap();
+        let other_subnet_peer = "2001:db8:1:3::1".parse().unwrap();
+        scores.add(&peer, MISBEHAVING_SCORE);
+        assert_eq!(scores.score(&same_subnet_peer), MISBEHAVING_SCORE);
+        assert_eq!(scores.score(&other_subnet_peer), 0);
+    }
+}
@@ -1,20 +1,60 @@
 mod average_speed_meter;
+mod best_block_watcher;
 mod best_headers_chain;
+mod block_announcements;
+mod block_propagation;
+mod clock;
 mod connection_filter;
 mod hash_queue;
+mod header_branches;
+mod headers_requests;
 mod known_hash_filter;
-mod message_block_headers_provider;
+mod locator_cache;
+mod memory_budget;
+mod misbehavior_scores;
+mod network_time;
 mod orphan_blocks_pool;
+mod serialized_block_cache;
+mod server_log_sampler;
+mod stale_blocks;
 mod synchronization_state;
+mod timestamp_stats;
+mod upload_budget;
 
 pub use self::average_speed_meter::AverageSpeedMeter;
+pub use self::best_block_watcher::BestBlockWatcher;
 pub use self::best_headers_chain::{BestHeadersChain, Information as BestHeadersChainInformation};
+pub use self::block_announcements::{BlockAnnouncement, BlockAnnouncements};
+pub use self::block_propagation::{BlockPropagation, BlockPropagations, PropagationStage};
+pub use self::clock::precise_time_s;
+#[cfg(test)]
+pub use self::clock::set_virtual_time;
 pub use self::connection_filter::ConnectionFilter;
 pub use self::hash_queue::{HashPosition, HashQueue, HashQueueChain};
+pub use self::header_branches::{
+    HeaderBranches, HeaderBranchesLimits, DEFAULT_MAX_HEADER_BRANCHES,
+    DEFAULT_MAX_HEADER_BRANCH_LEN, DEFAULT_MAX_HEADER_BRANCH_WORK_DEFICIT,
+};
+pub use self::headers_requests::{HeadersRequests, HeadersResponse, MAX_ANNOUNCED_HEADERS};
 pub use self::known_hash_filter::{KnownHashFilter, KnownHashType};
-pub use self::message_block_headers_provider::MessageBlockHeadersProvider;
-pub use self::orphan_blocks_pool::OrphanBlocksPool;
-pub use self::synchronization_state::SynchronizationState;
+pub use self::locator_cache::{LocatorCache, DEFAULT_LOCATOR_CACHE_SIZE};
+pub use self::memory_budget::{MemoryBudget, MemoryConsumer, MemoryUsage, DEFAULT_MEMORY_BUDGET};
+pub use self::misbehavior_scores::{
+    MisbehaviorScores, BAN_SCORE, DEFAULT_MISBEHAVIOR_SCORE_TTL_S, DOS_SCORE, MISBEHAVING_SCORE,
+};
+pub use self::network_time::NetworkTime;
+pub use self::orphan_blocks_pool::{OrphanBlockEntry, OrphanBlocksPool};
+pub use self::serialized_block_cache::{
+    serialize_block, RecentBlocksSerializer, SerializedBlockCache, DEFAULT_RECENT_BLOCKS,
+    DEFAULT_SERIALIZED_BLOCK_CACHE_SIZE,
+};
+pub use self::server_log_sampler::{
+    ServeOutcome, ServerLogSampler, ServerLogStats, DEFAULT_SERVER_LOG_SAMPLING,
+};
+pub use self::stale_blocks::{StaleBlock, StaleBlocks, MAX_STALE_BLOCK_AGE_S};
+pub use self::synchronization_state::{SynchronizationState, SynchronizationStateSnapshot};
+pub use self::timestamp_stats::{TimestampAlert, TimestampCounters, TimestampStats};
+pub use self::upload_budget::{UploadBudget, UploadDecision, UploadLimit, DEFAULT_UPLOAD_WINDOW_S};
 
 /// Block height type
 pub type BlockHeight = u32;
@@ -0,0 +1,155 @@
+use parking_lot::Mutex;
+use std::collections::HashMap;
+use time;
+use types::PeerIndex;
+use verification::TimeSource;
+
+/// Local time is not adjusted until time offsets of at least this number of peers are known
+const MIN_TIME_SAMPLES: usize = 5;
+/// Max number of peers, which time offsets are tracked
+const MAX_TIME_SAMPLES: usize = 200;
+/// Local time is never adjusted by more than this number of seconds
+pub const MAX_TIME_ADJUSTMENT_S: i64 = 70 * 60;
+/// Warning is printed when median peers time offset exceeds this number of seconds
+const CLOCK_SKEW_WARNING_S: i64 = 5 * 60;
+
+/// Network-adjusted time: local time, corrected by the median of time offsets, advertised
+/// by peers in their 'version' messages.
+#[derive(Debug, Default)]
+pub struct NetworkTime {
+    inner: Mutex<NetworkTimeInner>,
+}
+
+#[derive(Debug, Default)]
+struct NetworkTimeInner {
+    /// Time offsets (peer time - local time) of connected peers
+    offsets: HashMap<PeerIndex, i64>,
+    /// Current adjustment of the local time
+    offset: i64,
+    /// True if clock skew warning has been printed and skew is still above the threshold
+    warned: bool,
+}
+
+impl NetworkTime {
+    /// Remember time, advertised by the peer
+    pub fn add_sample(&self, peer_index: PeerIndex, peer_time: i64) {
+        self.add_sample_at(peer_index, peer_time, time::get_time().sec)
+    }
+
+    fn add_sample_at(&self, peer_index: PeerIndex, peer_time: i64, local_time: i64) {
+        let mut inner = self.inner.lock();
+        if inner.offsets.len() >= MAX_TIME_SAMPLES && !inner.offsets.contains_key(&peer_index) {
+            return;
+        }
+
+        inner.offsets.insert(peer_index, peer_time - local_time);
+        inner.update_offset();
+    }
+
+    /// Forget time offset of disconnected peer
+    pub fn remove_sample(&self, peer_index: PeerIndex) {
+        let mut inner = self.inner.lock();
+        if inner.offsets.remove(&peer_index).is_some() {
+            inner.update_offset();
+        }
+    }
+
+    /// Current adjustment of the local time, in seconds
+    pub fn offset(&self) -> i64 {
+        self.inner.lock().offset
+    }
+
+    /// Network-adjusted time in seconds since epoch
+    pub fn adjusted_time(&self) -> u32 {
+        (time::get_time().sec + self.offset()) as u32
+    }
+}
+
+impl TimeSource for NetworkTime {
+    fn now(&self) -> u32 {
+        self.adjusted_time()
+    }
+}
+
+impl NetworkTimeInner {
+    fn update_offset(&mut self) {
+        if self.offsets.len() < MIN_TIME_SAMPLES {
+            self.offset = 0;
+            return;
+        }
+
+        let mut offsets: Vec<_> = self.offsets.values().cloned().collect();
+        offsets.sort();
+        let middle = offsets.len() / 2;
+        let median = if offsets.len() % 2 == 0 {
+            (offsets[middle - 1] + offsets[middle]) / 2
+        } else {
+            offsets[middle]
+        };
+
+        // do not follow peers too far => this could be an attack
+        self.offset = if median.abs() <= MAX_TIME_ADJUSTMENT_S {
+            median
+        } else {
+            0
+        };
+
+        let skewed = median.abs() > CLOCK_SKEW_WARNING_S;
+        if skewed && !self.warned {
+            warn!(target: "sync", "Local clock differs from the time of {} peers by {} seconds (median). Please check that your clock is correct", offsets.len(), median);
+        }
+        self.warned = skewed;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{NetworkTime, MAX_TIME_ADJUSTMENT_S, MAX_TIME_SAMPLES};
+
+    #[test]
+    fn network_time_uses_median_offset() {
+        let time = NetworkTime::default();
+        for peer_index in 0..4 {
+            time.add_sample_at(peer_index, 1000 + peer_index as i64 * 10, 1000);
+        }
+        // not enough samples yet
+        assert_eq!(time.offset(), 0);
+
+        time.add_sample_at(4, 1000 + 40, 1000);
+        assert_eq!(time.offset(), 20);
+
+        // median of even number of samples is average of two middle offsets
+        time.add_sample_at(5, 1000 + 500, 1000);
+        assert_eq!(time.offset(), 25);
+
+        time.remove_sample(5);
+        time.remove_sample(4);
+        assert_eq!(time.offset(), 0);
+    }
+
+    #[test]
+    fn network_time_ignores_too_large_offsets() {
+        let time = NetworkTime::default();
+        for peer_index in 0..5 {
+            time.add_sample_at(peer_index, MAX_TIME_ADJUSTMENT_S + 1, 0);
+        }
+        assert_eq!(time.offset(), 0);
+
+        for peer_index in 0..5 {
+            time.add_sample_at(peer_index, -MAX_TIME_ADJUSTMENT_S, 0);
+        }
+        assert_eq!(time.offset(), -MAX_TIME_ADJUSTMENT_S);
+    }
+
+    #[test]
+    fn network_time_samples_are_limited() {
+        let time = NetworkTime::default();
+        for peer_index in 0..MAX_TIME_SAMPLES {
+            time.add_sample_at(peer_index, 0, 0);
+        }
+        for peer_index in MAX_TIME_SAMPLES..MAX_TIME_SAMPLES * 2 {
+            time.add_sample_at(peer_index, 100, 0);
+        }
+        assert_eq!(time.offset(), 0);
+    }
+}
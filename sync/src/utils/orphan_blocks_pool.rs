@@ -1,18 +1,35 @@
+use super::{precise_time_s, MemoryConsumer};
 use chain::IndexedBlock;
 use linked_hash_map::LinkedHashMap;
 use primitives::hash::H256;
 use std::collections::hash_map::Entry;
 use std::collections::{HashMap, HashSet, VecDeque};
 use time;
+use types::MemoryBudgetRef;
+
+/// Block, held in memory until its parent is received.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OrphanBlockEntry {
+    /// Block hash
+    pub hash: H256,
+    /// Hash of the parent block
+    pub parent: H256,
+    /// Serialized block size
+    pub size: usize,
+    /// Time in seconds since epoch, when block has been received
+    pub time: u32,
+}
 
 #[derive(Debug)]
 /// Storage for blocks, for which we have no parent yet.
 /// Blocks from this storage are either moved to verification queue, or removed at all.
 pub struct OrphanBlocksPool {
-    /// Blocks from requested_hashes, but received out-of-order.
-    orphaned_blocks: HashMap<H256, HashMap<H256, IndexedBlock>>,
+    /// Blocks from requested_hashes, but received out-of-order, with receiving time.
+    orphaned_blocks: HashMap<H256, HashMap<H256, (IndexedBlock, u32)>>,
     /// Blocks that we have received without requesting with receiving time.
     unknown_blocks: LinkedHashMap<H256, f64>,
+    /// Memory budget, blocks of the pool are accounted in
+    memory_budget: MemoryBudgetRef,
 }
 
 impl OrphanBlocksPool {
@@ -21,9 +38,16 @@ impl OrphanBlocksPool {
         OrphanBlocksPool {
             orphaned_blocks: HashMap::new(),
             unknown_blocks: LinkedHashMap::new(),
+            memory_budget: Default::default(),
         }
     }
 
+    /// Account blocks of the pool in given memory budget
+    pub fn with_memory_budget(mut self, memory_budget: MemoryBudgetRef) -> Self {
+        self.memory_budget = memory_budget;
+        self
+    }
+
     /// Get total number of blocks in pool
     pub fn len(&self) -> usize {
         self.orphaned_blocks.len()
@@ -39,22 +63,62 @@ impl OrphanBlocksPool {
         &self.unknown_blocks
     }
 
-    /// Insert orphaned block, for which we have already requested its parent block
+    /// Get all blocks in the pool, ordered by receiving time
+    pub fn entries(&self) -> Vec<OrphanBlockEntry> {
+        let mut entries: Vec<_> = self
+            .orphaned_blocks
+            .values()
+            .flat_map(|orphans| orphans.values())
+            .map(|&(ref block, time)| OrphanBlockEntry {
+                hash: block.header.hash.clone(),
+                parent: block.header.raw.previous_header_hash.clone(),
+                size: block.size(),
+                time: time,
+            })
+            .collect();
+        entries.sort_by_key(|entry| entry.time);
+        entries
+    }
+
+    /// Insert orphaned block, for which we have already requested its parent block.
+    /// Requested blocks are accounted even if they do not fit the memory budget
     pub fn insert_orphaned_block(&mut self, block: IndexedBlock) {
-        self.orphaned_blocks
-            .entry(block.header.raw.previous_header_hash.clone())
-            .or_insert_with(HashMap::new)
-            .insert(block.header.hash.clone(), block);
+        self.memory_budget
+            .force_allocate(MemoryConsumer::OrphanBlocks, block.size());
+        self.insert_block(block);
     }
 
-    /// Insert unknown block, for which we know nothing about its parent block
-    pub fn insert_unknown_block(&mut self, block: IndexedBlock) {
+    /// Insert unknown block, for which we know nothing about its parent block.
+    /// Returns false if block is ignored, because memory budget is exhausted
+    pub fn insert_unknown_block(&mut self, block: IndexedBlock) -> bool {
+        if !self
+            .memory_budget
+            .allocate(MemoryConsumer::OrphanBlocks, block.size())
+        {
+            return false;
+        }
+
         let previous_value = self
             .unknown_blocks
-            .insert(block.header.hash.clone(), time::precise_time_s());
+            .insert(block.header.hash.clone(), precise_time_s());
         assert_eq!(previous_value, None);
 
-        self.insert_orphaned_block(block);
+        self.insert_block(block);
+        true
+    }
+
+    /// Insert already accounted block
+    fn insert_block(&mut self, block: IndexedBlock) {
+        let time = time::get_time().sec as u32;
+        let replaced = self
+            .orphaned_blocks
+            .entry(block.header.raw.previous_header_hash.clone())
+            .or_insert_with(HashMap::new)
+            .insert(block.header.hash.clone(), (block, time));
+        if let Some((replaced, _)) = replaced {
+            self.memory_budget
+                .release(MemoryConsumer::OrphanBlocks, replaced.size());
+        }
     }
 
     /// Remove all blocks, which are not-unknown
@@ -82,7 +146,11 @@ impl OrphanBlocksPool {
                     self.unknown_blocks.remove(orphaned_hash);
                 }
                 queue.extend(orphaned.keys().cloned());
-                removed.extend(orphaned.into_iter().map(|(_, b)| b));
+                for (_, (block, _)) in orphaned {
+                    self.memory_budget
+                        .release(MemoryConsumer::OrphanBlocks, block.size());
+                    removed.push_back(block);
+                }
             }
         }
         removed
@@ -92,9 +160,13 @@ impl OrphanBlocksPool {
     pub fn remove_blocks(&mut self, hashes: &HashSet<H256>) -> Vec<H256> {
         let mut removed: Vec<H256> = Vec::new();
 
+        let memory_budget = &self.memory_budget;
         self.orphaned_blocks.retain(|_, orphans| {
             for hash in hashes {
-                orphans.remove(hash).map(|_| removed.push(*hash));
+                if let Some((block, _)) = orphans.remove(hash) {
+                    memory_budget.release(MemoryConsumer::OrphanBlocks, block.size());
+                    removed.push(*hash);
+                }
             }
             !orphans.is_empty()
         });
@@ -120,8 +192,11 @@ mod tests {
     extern crate test_data;
 
     use super::OrphanBlocksPool;
+    use chain::IndexedBlock;
     use primitives::hash::H256;
     use std::collections::HashSet;
+    use std::sync::Arc;
+    use utils::MemoryBudget;
 
     #[test]
     fn orphan_block_pool_empty_on_start() {
@@ -155,6 +230,28 @@ mod tests {
         assert_eq!(pool.unknown_blocks().len(), 1);
     }
 
+    #[test]
+    fn orphan_block_pool_respects_memory_budget() {
+        let b1: IndexedBlock = test_data::block_h1().into();
+        let b2: IndexedBlock = test_data::block_h169().into();
+        let b3: IndexedBlock = test_data::block_h2().into();
+        let (b1_size, b2_size, b3_size) = (b1.size(), b2.size(), b3.size());
+        let b2_hash = b2.hash().clone();
+        let budget = Arc::new(MemoryBudget::new(b1_size + b2_size - 1));
+        let mut pool = OrphanBlocksPool::new().with_memory_budget(budget.clone());
+
+        assert!(pool.insert_unknown_block(b1));
+        assert!(!pool.insert_unknown_block(b2.clone()));
+        assert!(!pool.contains_unknown_block(&b2_hash));
+        // requested blocks are accepted anyway
+        pool.insert_orphaned_block(b3);
+        assert_eq!(budget.usage().orphan_blocks, b1_size + b3_size);
+
+        pool.remove_blocks_for_parent(&test_data::genesis().hash());
+        assert_eq!(budget.usage().orphan_blocks, 0);
+        assert!(pool.insert_unknown_block(b2));
+    }
+
     #[test]
     fn orphan_block_pool_remove_known_blocks() {
         let mut pool = OrphanBlocksPool::new();
@@ -193,6 +290,12 @@ mod tests {
         pool.insert_unknown_block(b2.into());
         pool.insert_orphaned_block(b3.into());
 
+        let entries = pool.entries();
+        assert_eq!(entries.len(), 3);
+        assert!(entries
+            .iter()
+            .any(|entry| entry.hash == b3_hash && entry.parent == b1_hash));
+
         let removed = pool.remove_blocks_for_parent(&test_data::genesis().hash());
         assert_eq!(removed.len(), 2);
         assert_eq!(removed[0].hash(), &b1_hash);
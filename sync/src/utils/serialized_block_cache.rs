@@ -0,0 +1,260 @@
+use chain::IndexedBlock;
+use linked_hash_map::LinkedHashMap;
+use message::{types, SharedPayload};
+use primitives::hash::H256;
+use std::sync::atomic::{AtomicBool, Ordering};
+use types::{SerializedBlockCacheRef, StorageRef};
+use SyncListener;
+
+/// Default max total size of cached serialized blocks (in bytes).
+pub const DEFAULT_SERIALIZED_BLOCK_CACHE_SIZE: usize = 32 * 1024 * 1024;
+/// Default number of best chain blocks (the best block and its ancestors), serialized in advance.
+pub const DEFAULT_RECENT_BLOCKS: usize = 4;
+
+/// Cache of recently served blocks, serialized as 'block' message payload.
+/// When several peers are requesting the same block (e.g. recently mined), it is read and
+/// serialized once and the same payload is streamed to all of them.
+#[derive(Debug)]
+pub struct SerializedBlockCache {
+    /// Max total size of cached payloads (recent blocks are not counted)
+    max_size: usize,
+    /// Total size of cached payloads
+    size: usize,
+    /// Cached payloads, least recently used first
+    entries: LinkedHashMap<H256, SharedPayload>,
+    /// Best chain blocks, the best block first. These are never evicted by served blocks
+    recent: Vec<(H256, SharedPayload)>,
+}
+
+impl Default for SerializedBlockCache {
+    fn default() -> Self {
+        SerializedBlockCache::new(DEFAULT_SERIALIZED_BLOCK_CACHE_SIZE)
+    }
+}
+
+impl SerializedBlockCache {
+    /// Create new cache with given capacity (in bytes)
+    pub fn new(max_size: usize) -> Self {
+        SerializedBlockCache {
+            max_size: max_size,
+            size: 0,
+            entries: LinkedHashMap::new(),
+            recent: Vec::new(),
+        }
+    }
+
+    /// Total size of cached payloads
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// Hashes of cached best chain blocks, the best block first
+    pub fn recent(&self) -> Vec<H256> {
+        self.recent
+            .iter()
+            .map(|&(ref hash, _)| hash.clone())
+            .collect()
+    }
+
+    /// Get cached payload of the block
+    pub fn get(&mut self, hash: &H256) -> Option<SharedPayload> {
+        if let Some(&(_, ref payload)) = self.recent.iter().find(|&&(ref h, _)| h == hash) {
+            return Some(payload.clone());
+        }
+
+        self.entries.get_refresh(hash).cloned()
+    }
+
+    /// Replace cached best chain blocks. Previous best chain blocks are moved to the regular cache
+    pub fn set_recent(&mut self, recent: Vec<(H256, SharedPayload)>) {
+        let previous = ::std::mem::replace(&mut self.recent, recent);
+        for (hash, payload) in previous.into_iter().rev() {
+            if !self.recent.iter().any(|&(ref h, _)| h == &hash) {
+                self.insert(hash, payload);
+            }
+        }
+    }
+
+    /// Remember payload of the block. Payloads, larger than the cache, are never cached
+    pub fn insert(&mut self, hash: H256, payload: SharedPayload) {
+        if payload.size() > self.max_size {
+            return;
+        }
+
+        self.size += payload.size();
+        if let Some(replaced) = self.entries.insert(hash, payload) {
+            self.size -= replaced.size();
+        }
+        while self.size > self.max_size {
+            match self.entries.pop_front() {
+                Some((_, evicted)) => self.size -= evicted.size(),
+                None => break,
+            }
+        }
+    }
+}
+
+/// Serializes block as 'block' message payload.
+pub fn serialize_block(block: &IndexedBlock) -> SharedPayload {
+    SharedPayload::new(&types::Block {
+        block: block.to_raw_block(),
+    })
+    .expect("block serialization never fails")
+}
+
+/// Serializes the best block and its ancestors in advance, when the best block changes.
+/// New blocks are requested by many peers within seconds, so they are served from the cache.
+pub struct RecentBlocksSerializer {
+    storage: StorageRef,
+    cache: SerializedBlockCacheRef,
+    /// Number of best chain blocks to keep serialized
+    recent_blocks: usize,
+    /// Blocks are not serialized in advance while synchronizing: nobody requests them from us
+    is_synchronizing: AtomicBool,
+}
+
+impl RecentBlocksSerializer {
+    pub fn new(storage: StorageRef, cache: SerializedBlockCacheRef, recent_blocks: usize) -> Self {
+        RecentBlocksSerializer {
+            storage: storage,
+            cache: cache,
+            recent_blocks: recent_blocks,
+            is_synchronizing: AtomicBool::new(false),
+        }
+    }
+
+    /// Serialize blocks, which are missing in the cache. Storage is read without holding the cache lock
+    fn update(&self) {
+        let best_block = self.storage.best_block();
+        let mut recent = Vec::with_capacity(self.recent_blocks);
+        for depth in 0..self.recent_blocks as u32 {
+            if depth > best_block.number {
+                break;
+            }
+
+            let hash = match self.storage.block_hash(best_block.number - depth) {
+                Some(hash) => hash,
+                None => break,
+            };
+            let cached = self.cache.lock().get(&hash);
+            let payload = match cached {
+                Some(payload) => payload,
+                // block body could be pruned
+                None => match self.storage.block(hash.clone().into()) {
+                    Some(block) => serialize_block(&block),
+                    None => break,
+                },
+            };
+            recent.push((hash, payload));
+        }
+
+        self.cache.lock().set_recent(recent);
+    }
+}
+
+impl SyncListener for RecentBlocksSerializer {
+    fn synchronization_state_switched(&self, is_synchronizing: bool) {
+        self.is_synchronizing
+            .store(is_synchronizing, Ordering::SeqCst);
+        if !is_synchronizing {
+            self.update();
+        }
+    }
+
+    fn best_storage_block_inserted(&self, _block_hash: &H256) {
+        if !self.is_synchronizing.load(Ordering::SeqCst) {
+            self.update();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate test_data;
+
+    use super::{serialize_block, RecentBlocksSerializer, SerializedBlockCache};
+    use chain::IndexedBlock;
+    use db::BlockChainDatabase;
+    use message::SharedPayload;
+    use parking_lot::Mutex;
+    use std::sync::Arc;
+    use SyncListener;
+
+    fn payload(block: &IndexedBlock) -> SharedPayload {
+        serialize_block(block)
+    }
+
+    #[test]
+    fn serialized_block_cache_evicts_least_recently_used() {
+        let blocks = test_data::build_n_empty_blocks_from_genesis(3, 1);
+        let blocks: Vec<IndexedBlock> = blocks.into_iter().map(Into::into).collect();
+        let block_size = payload(&blocks[0]).size();
+
+        let mut cache = SerializedBlockCache::new(block_size * 2);
+        cache.insert(blocks[0].hash().clone(), payload(&blocks[0]));
+        cache.insert(blocks[1].hash().clone(), payload(&blocks[1]));
+        assert_eq!(cache.size(), block_size * 2);
+
+        // refresh first block => second block is evicted
+        assert_eq!(cache.get(blocks[0].hash()), Some(payload(&blocks[0])));
+        cache.insert(blocks[2].hash().clone(), payload(&blocks[2]));
+        assert_eq!(cache.size(), block_size * 2);
+        assert_eq!(cache.get(blocks[1].hash()), None);
+        assert!(cache.get(blocks[0].hash()).is_some());
+        assert!(cache.get(blocks[2].hash()).is_some());
+
+        // too large payloads are never cached
+        let mut cache = SerializedBlockCache::new(block_size - 1);
+        cache.insert(blocks[0].hash().clone(), payload(&blocks[0]));
+        assert_eq!(cache.size(), 0);
+        assert_eq!(cache.get(blocks[0].hash()), None);
+    }
+
+    #[test]
+    fn recent_blocks_are_serialized_in_advance() {
+        let blocks: Vec<IndexedBlock> = test_data::build_n_empty_blocks_from_genesis(3, 1)
+            .into_iter()
+            .map(Into::into)
+            .collect();
+        let storage = Arc::new(BlockChainDatabase::init_test_chain(vec![
+            test_data::genesis().into(),
+        ]));
+        let cache = Arc::new(Mutex::new(SerializedBlockCache::new(0)));
+        let serializer = RecentBlocksSerializer::new(storage.clone(), cache.clone(), 2);
+
+        serializer.best_storage_block_inserted(&test_data::genesis().hash());
+        assert_eq!(cache.lock().recent(), vec![test_data::genesis().hash()]);
+
+        for block in &blocks {
+            storage.insert(block.clone()).unwrap();
+            storage.canonize(block.hash()).unwrap();
+            serializer.best_storage_block_inserted(block.hash());
+        }
+        assert_eq!(
+            cache.lock().recent(),
+            vec![blocks[2].hash().clone(), blocks[1].hash().clone()]
+        );
+        assert_eq!(
+            cache.lock().get(blocks[2].hash()),
+            Some(payload(&blocks[2]))
+        );
+        // blocks, which are not recent anymore, are moved to the regular cache (which is empty here)
+        assert_eq!(cache.lock().get(blocks[0].hash()), None);
+        assert_eq!(cache.lock().size(), 0);
+
+        // while synchronizing, recent blocks are not updated
+        serializer.synchronization_state_switched(true);
+        let block = test_data::block_builder()
+            .header()
+            .parent(blocks[2].hash().clone())
+            .build()
+            .build();
+        let block: IndexedBlock = block.into();
+        storage.insert(block.clone()).unwrap();
+        storage.canonize(block.hash()).unwrap();
+        serializer.best_storage_block_inserted(block.hash());
+        assert_eq!(cache.lock().recent()[0], blocks[2].hash().clone());
+        serializer.synchronization_state_switched(false);
+        assert_eq!(cache.lock().recent()[0], block.hash().clone());
+    }
+}
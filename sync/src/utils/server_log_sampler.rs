@@ -0,0 +1,165 @@
+use std::sync::atomic::{AtomicU32, AtomicUsize, Ordering};
+
+/// By default, every served task is logged.
+pub const DEFAULT_SERVER_LOG_SAMPLING: u32 = 1;
+
+/// Outcome of serving the peer request.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ServeOutcome {
+    /// Block has been sent in response to 'getdata'
+    BlockServed,
+    /// Some of 'getdata' items are unknown and 'notfound' has been sent
+    NotFound,
+    /// 'getdata' item is not served, because peer upload budget is exhausted
+    Throttled,
+    /// Inventory has been sent in response to 'getblocks'
+    InventoryServed,
+    /// Headers have been sent in response to 'getheaders'
+    HeadersServed,
+    /// Request has been ignored, because there's nothing to respond with
+    Ignored,
+}
+
+/// Counters of serving outcomes, since node start.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct ServerLogStats {
+    /// Current sampling rate: 1 of `sampling` tasks is fully logged. 0 means that no tasks are logged
+    pub sampling: u32,
+    /// Number of executed server tasks
+    pub tasks: usize,
+    /// Number of tasks, which have been fully logged
+    pub logged_tasks: usize,
+    /// Number of blocks, sent in response to 'getdata'
+    pub blocks_served: usize,
+    /// Number of 'notfound' responses
+    pub not_found: usize,
+    /// Number of 'getdata' items, throttled by upload budget
+    pub throttled: usize,
+    /// Number of inventories, sent in response to 'getblocks'
+    pub inventories_served: usize,
+    /// Number of headers messages, sent in response to 'getheaders'
+    pub headers_served: usize,
+    /// Number of ignored requests
+    pub ignored: usize,
+}
+
+/// Decides which server tasks are fully logged and counts outcomes of the rest.
+/// Sampling rate could be changed at runtime.
+#[derive(Debug)]
+pub struct ServerLogSampler {
+    sampling: AtomicU32,
+    tasks: AtomicUsize,
+    logged_tasks: AtomicUsize,
+    blocks_served: AtomicUsize,
+    not_found: AtomicUsize,
+    throttled: AtomicUsize,
+    inventories_served: AtomicUsize,
+    headers_served: AtomicUsize,
+    ignored: AtomicUsize,
+}
+
+impl Default for ServerLogSampler {
+    fn default() -> Self {
+        ServerLogSampler::new(DEFAULT_SERVER_LOG_SAMPLING)
+    }
+}
+
+impl ServerLogSampler {
+    pub fn new(sampling: u32) -> Self {
+        ServerLogSampler {
+            sampling: AtomicU32::new(sampling),
+            tasks: AtomicUsize::new(0),
+            logged_tasks: AtomicUsize::new(0),
+            blocks_served: AtomicUsize::new(0),
+            not_found: AtomicUsize::new(0),
+            throttled: AtomicUsize::new(0),
+            inventories_served: AtomicUsize::new(0),
+            headers_served: AtomicUsize::new(0),
+            ignored: AtomicUsize::new(0),
+        }
+    }
+
+    /// Change sampling rate: 1 of `sampling` tasks is fully logged. 0 disables logging
+    pub fn set_sampling(&self, sampling: u32) {
+        self.sampling.store(sampling, Ordering::SeqCst);
+    }
+
+    /// Called when new task is started. Returns true if task must be fully logged
+    pub fn start_task(&self) -> bool {
+        let task = self.tasks.fetch_add(1, Ordering::Relaxed);
+        let sampling = self.sampling.load(Ordering::Relaxed);
+        let sampled = sampling != 0 && task % sampling as usize == 0;
+        if sampled {
+            self.logged_tasks.fetch_add(1, Ordering::Relaxed);
+        }
+        sampled
+    }
+
+    /// Count serving outcome
+    pub fn note(&self, outcome: ServeOutcome) {
+        let counter = match outcome {
+            ServeOutcome::BlockServed => &self.blocks_served,
+            ServeOutcome::NotFound => &self.not_found,
+            ServeOutcome::Throttled => &self.throttled,
+            ServeOutcome::InventoryServed => &self.inventories_served,
+            ServeOutcome::HeadersServed => &self.headers_served,
+            ServeOutcome::Ignored => &self.ignored,
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn stats(&self) -> ServerLogStats {
+        ServerLogStats {
+            sampling: self.sampling.load(Ordering::SeqCst),
+            tasks: self.tasks.load(Ordering::Relaxed),
+            logged_tasks: self.logged_tasks.load(Ordering::Relaxed),
+            blocks_served: self.blocks_served.load(Ordering::Relaxed),
+            not_found: self.not_found.load(Ordering::Relaxed),
+            throttled: self.throttled.load(Ordering::Relaxed),
+            inventories_served: self.inventories_served.load(Ordering::Relaxed),
+            headers_served: self.headers_served.load(Ordering::Relaxed),
+            ignored: self.ignored.load(Ordering::Relaxed),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ServeOutcome, ServerLogSampler};
+
+    #[test]
+    fn server_log_sampler_samples_one_of_n_tasks() {
+        let sampler = ServerLogSampler::new(3);
+        let sampled: Vec<_> = (0..7).map(|_| sampler.start_task()).collect();
+        assert_eq!(sampled, vec![true, false, false, true, false, false, true]);
+
+        sampler.set_sampling(0);
+        assert!((0..10).all(|_| !sampler.start_task()));
+
+        sampler.set_sampling(1);
+        assert!((0..10).all(|_| sampler.start_task()));
+
+        let stats = sampler.stats();
+        assert_eq!(stats.sampling, 1);
+        assert_eq!(stats.tasks, 27);
+        assert_eq!(stats.logged_tasks, 13);
+    }
+
+    #[test]
+    fn server_log_sampler_counts_all_outcomes() {
+        let sampler = ServerLogSampler::new(0);
+        sampler.note(ServeOutcome::BlockServed);
+        sampler.note(ServeOutcome::BlockServed);
+        sampler.note(ServeOutcome::Throttled);
+        sampler.note(ServeOutcome::HeadersServed);
+        sampler.note(ServeOutcome::Ignored);
+
+        let stats = sampler.stats();
+        assert_eq!(stats.blocks_served, 2);
+        assert_eq!(stats.throttled, 1);
+        assert_eq!(stats.headers_served, 1);
+        assert_eq!(stats.ignored, 1);
+        assert_eq!(stats.not_found, 0);
+        assert_eq!(stats.inventories_served, 0);
+    }
+}
@@ -0,0 +1,99 @@
+use super::BlockHeight;
+use primitives::hash::H256;
+use std::collections::VecDeque;
+
+/// Stale blocks are remembered for this number of seconds.
+pub const MAX_STALE_BLOCK_AGE_S: u32 = 7 * 24 * 60 * 60;
+/// Max number of remembered stale blocks.
+const MAX_STALE_BLOCKS: usize = 4096;
+
+/// Block, which has been canonical, but has been reorged out.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StaleBlock {
+    /// Hash of the stale block
+    pub hash: H256,
+    /// Height of the stale block
+    pub height: BlockHeight,
+    /// Hash of canonical block, which has replaced the stale block
+    pub replaced_by: H256,
+    /// Local time, when block has been reorged out
+    pub time: u32,
+}
+
+/// Log of recently reorged out blocks. Ordered from oldest to newest.
+#[derive(Debug, Default)]
+pub struct StaleBlocks {
+    blocks: VecDeque<StaleBlock>,
+}
+
+impl StaleBlocks {
+    /// Remember stale block
+    pub fn insert(&mut self, block: StaleBlock) {
+        let oldest_time = block.time.saturating_sub(MAX_STALE_BLOCK_AGE_S);
+        while self
+            .blocks
+            .front()
+            .map(|b| b.time < oldest_time || self.blocks.len() >= MAX_STALE_BLOCKS)
+            .unwrap_or(false)
+        {
+            self.blocks.pop_front();
+        }
+
+        self.blocks.push_back(block);
+    }
+
+    /// Get blocks, reorged out at given time or later
+    pub fn since(&self, time: u32) -> Vec<StaleBlock> {
+        self.blocks
+            .iter()
+            .filter(|b| b.time >= time)
+            .cloned()
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{StaleBlock, StaleBlocks, MAX_STALE_BLOCK_AGE_S};
+    use primitives::hash::H256;
+
+    fn stale_block(hash: u8, time: u32) -> StaleBlock {
+        StaleBlock {
+            hash: H256::from(hash),
+            height: hash as u32,
+            replaced_by: H256::from(hash + 100),
+            time: time,
+        }
+    }
+
+    #[test]
+    fn stale_blocks_since() {
+        let mut stale_blocks = StaleBlocks::default();
+        stale_blocks.insert(stale_block(1, 100));
+        stale_blocks.insert(stale_block(2, 200));
+        stale_blocks.insert(stale_block(3, 300));
+
+        assert_eq!(stale_blocks.since(0).len(), 3);
+        assert_eq!(
+            stale_blocks.since(200),
+            vec![stale_block(2, 200), stale_block(3, 300)]
+        );
+        assert!(stale_blocks.since(301).is_empty());
+    }
+
+    #[test]
+    fn stale_blocks_forgets_old_blocks() {
+        let mut stale_blocks = StaleBlocks::default();
+        stale_blocks.insert(stale_block(1, 100));
+        stale_blocks.insert(stale_block(2, 200));
+        stale_blocks.insert(stale_block(3, 150 + MAX_STALE_BLOCK_AGE_S));
+
+        assert_eq!(
+            stale_blocks.since(0),
+            vec![
+                stale_block(2, 200),
+                stale_block(3, 150 + MAX_STALE_BLOCK_AGE_S)
+            ]
+        );
+    }
+}
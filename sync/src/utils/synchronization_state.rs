@@ -1,34 +1,116 @@
-use super::super::types::{BlockHeight, StorageRef};
+use super::super::types::{BlockHeight, PeerIndex, StorageRef};
+use super::{
+    BlockAnnouncement, BlockAnnouncements, BlockPropagation, BlockPropagations, PropagationStage,
+    StaleBlock, StaleBlocks,
+};
 use p2p::InboundSyncConnectionState;
+use parking_lot::Mutex;
+use primitives::hash::H256;
 use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::thread;
 
 // AtomicU32 is unstable => using AtomicUsize here
 
+/// Consistent view of the shared synchronization state.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SynchronizationStateSnapshot {
+    /// Is synchronization in progress?
+    pub synchronizing: bool,
+    /// Height of best block in the storage
+    pub best_storage_block_height: BlockHeight,
+    /// Height of best known block header
+    pub best_header_height: BlockHeight,
+    /// Is free space on the database volume below the limit?
+    pub disk_space_low: bool,
+}
+
+impl SynchronizationStateSnapshot {
+    /// Estimated synchronization progress: fraction of known headers, which blocks are in the storage
+    pub fn sync_progress(&self) -> f64 {
+        if self.best_header_height <= self.best_storage_block_height {
+            return 1f64;
+        }
+
+        self.best_storage_block_height as f64 / self.best_header_height as f64
+    }
+}
+
 /// Shared synchronization client state.
-/// It can be slightly innacurate, but the accuracy is not required for it
+/// It can be slightly innacurate, but the accuracy is not required for it.
+/// Hot fields are atomics, so readers are never blocked by the synchronization. Use `snapshot()`
+/// when several fields must be read consistently.
 #[derive(Debug)]
 pub struct SynchronizationState {
+    /// Number of updates in progress
+    writers: AtomicUsize,
+    /// Incremented when any update is started. Snapshot is retried if it has changed during read
+    sequence: AtomicUsize,
     /// Is synchronization in progress?
     is_synchronizing: AtomicBool,
     /// Height of best block in the storage
     best_storage_block_height: AtomicUsize,
+    /// Height of best known block header (learned from peers or read from the storage)
+    best_header_height: AtomicUsize,
+    /// Is free space on the database volume below the limit?
+    /// Blocks are neither requested, nor accepted while it is true
+    is_disk_space_low: AtomicBool,
+    /// Recently reorged out blocks
+    stale_blocks: Mutex<StaleBlocks>,
+    /// Recently announced blocks (only tracked in watch-only mode)
+    block_announcements: Mutex<BlockAnnouncements>,
+    /// Propagation of recently seen blocks
+    block_propagations: Mutex<BlockPropagations>,
 }
 
 impl SynchronizationState {
     pub fn with_storage(storage: StorageRef) -> Self {
         let best_storage_block_height = storage.best_block().number;
         SynchronizationState {
+            writers: AtomicUsize::new(0),
+            sequence: AtomicUsize::new(0),
             is_synchronizing: AtomicBool::new(false),
             best_storage_block_height: AtomicUsize::new(best_storage_block_height as usize),
+            best_header_height: AtomicUsize::new(best_storage_block_height as usize),
+            is_disk_space_low: AtomicBool::new(false),
+            stale_blocks: Mutex::new(StaleBlocks::default()),
+            block_announcements: Mutex::new(BlockAnnouncements::default()),
+            block_propagations: Mutex::new(BlockPropagations::default()),
         }
     }
 
+    /// Read all fields consistently, without locking. Retried while there are concurrent updates
+    pub fn snapshot(&self) -> SynchronizationStateSnapshot {
+        loop {
+            let sequence = self.sequence.load(Ordering::SeqCst);
+            if self.writers.load(Ordering::SeqCst) == 0 {
+                let snapshot = SynchronizationStateSnapshot {
+                    synchronizing: self.synchronizing(),
+                    best_storage_block_height: self.best_storage_block_height(),
+                    best_header_height: self.best_header_height(),
+                    disk_space_low: self.disk_space_low(),
+                };
+                if self.sequence.load(Ordering::SeqCst) == sequence {
+                    return snapshot;
+                }
+            }
+            thread::yield_now();
+        }
+    }
+
+    /// Apply update, so that it is either fully visible in snapshot, or not visible at all
+    fn update<F: FnOnce()>(&self, update: F) {
+        self.writers.fetch_add(1, Ordering::SeqCst);
+        self.sequence.fetch_add(1, Ordering::SeqCst);
+        update();
+        self.writers.fetch_sub(1, Ordering::SeqCst);
+    }
+
     pub fn synchronizing(&self) -> bool {
         self.is_synchronizing.load(Ordering::SeqCst)
     }
 
     pub fn update_synchronizing(&self, synchronizing: bool) {
-        self.is_synchronizing.store(synchronizing, Ordering::SeqCst);
+        self.update(|| self.is_synchronizing.store(synchronizing, Ordering::SeqCst));
     }
 
     pub fn best_storage_block_height(&self) -> BlockHeight {
@@ -36,8 +118,104 @@ impl SynchronizationState {
     }
 
     pub fn update_best_storage_block_height(&self, height: BlockHeight) {
-        self.best_storage_block_height
-            .store(height as usize, Ordering::SeqCst);
+        self.update(|| {
+            self.best_storage_block_height
+                .store(height as usize, Ordering::SeqCst)
+        });
+    }
+
+    pub fn best_header_height(&self) -> BlockHeight {
+        self.best_header_height.load(Ordering::SeqCst) as BlockHeight
+    }
+
+    pub fn update_best_header_height(&self, height: BlockHeight) {
+        self.update(|| {
+            self.best_header_height
+                .store(height as usize, Ordering::SeqCst)
+        });
+    }
+
+    /// Update both heights at once, so that snapshot never sees one of them updated
+    pub fn update_best_heights(
+        &self,
+        storage_block_height: BlockHeight,
+        header_height: BlockHeight,
+    ) {
+        self.update(|| {
+            self.best_storage_block_height
+                .store(storage_block_height as usize, Ordering::SeqCst);
+            self.best_header_height
+                .store(header_height as usize, Ordering::SeqCst);
+        });
+    }
+
+    /// Estimated synchronization progress: fraction of known headers, which blocks are in the storage
+    pub fn sync_progress(&self) -> f64 {
+        self.snapshot().sync_progress()
+    }
+
+    pub fn disk_space_low(&self) -> bool {
+        self.is_disk_space_low.load(Ordering::SeqCst)
+    }
+
+    pub fn update_disk_space_low(&self, disk_space_low: bool) {
+        self.update(|| {
+            self.is_disk_space_low
+                .store(disk_space_low, Ordering::SeqCst)
+        });
+    }
+
+    /// Remember blocks, which have been reorged out
+    pub fn note_stale_blocks(&self, blocks: Vec<StaleBlock>) {
+        let mut stale_blocks = self.stale_blocks.lock();
+        for block in blocks {
+            stale_blocks.insert(block);
+        }
+    }
+
+    /// Get blocks, which have been reorged out at given time or later
+    pub fn stale_blocks(&self, since: u32) -> Vec<StaleBlock> {
+        self.stale_blocks.lock().since(since)
+    }
+
+    /// Remember blocks, which have been announced by the peer
+    pub fn note_block_announcements(
+        &self,
+        peer_index: PeerIndex,
+        hashes: Vec<H256>,
+        time: u32,
+        precise_time_s: f64,
+    ) {
+        let mut block_announcements = self.block_announcements.lock();
+        for hash in hashes {
+            block_announcements.insert(hash, peer_index, time, precise_time_s);
+        }
+    }
+
+    /// Get blocks, which have been first announced at given time or later
+    pub fn block_announcements(&self, since: u32) -> Vec<BlockAnnouncement> {
+        self.block_announcements.lock().since(since)
+    }
+
+    /// Remember that blocks have reached given propagation stage. Peer is None if the stage
+    /// has been reached locally
+    pub fn note_block_propagation(
+        &self,
+        hashes: Vec<H256>,
+        stage: PropagationStage,
+        peer_index: Option<PeerIndex>,
+        time: u32,
+        precise_time_s: f64,
+    ) {
+        let mut block_propagations = self.block_propagations.lock();
+        for hash in hashes {
+            block_propagations.insert(hash, stage, peer_index, time, precise_time_s);
+        }
+    }
+
+    /// Get propagation of blocks, which have been first seen at given time or later
+    pub fn block_propagations(&self, since: u32) -> Vec<BlockPropagation> {
+        self.block_propagations.lock().since(since)
     }
 }
 
@@ -46,3 +224,66 @@ impl InboundSyncConnectionState for SynchronizationState {
         SynchronizationState::synchronizing(self)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    extern crate test_data;
+
+    use super::SynchronizationState;
+    use db::BlockChainDatabase;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+    use std::thread;
+
+    #[test]
+    fn sync_progress() {
+        let storage = Arc::new(BlockChainDatabase::init_test_chain(vec![
+            test_data::genesis().into(),
+        ]));
+        let state = SynchronizationState::with_storage(storage);
+        assert_eq!(state.best_header_height(), 0);
+        assert_eq!(state.sync_progress(), 1f64);
+
+        state.update_best_header_height(100);
+        assert_eq!(state.sync_progress(), 0f64);
+
+        state.update_best_storage_block_height(25);
+        assert_eq!(state.sync_progress(), 0.25f64);
+
+        state.update_best_storage_block_height(100);
+        assert_eq!(state.sync_progress(), 1f64);
+    }
+
+    #[test]
+    fn snapshot_never_sees_partial_update() {
+        let storage = Arc::new(BlockChainDatabase::init_test_chain(vec![
+            test_data::genesis().into(),
+        ]));
+        let state = Arc::new(SynchronizationState::with_storage(storage));
+        let stop = Arc::new(AtomicBool::new(false));
+
+        let writer = {
+            let state = state.clone();
+            let stop = stop.clone();
+            thread::spawn(move || {
+                let mut height = 0;
+                while !stop.load(Ordering::SeqCst) {
+                    height += 1;
+                    state.update_best_heights(height, height);
+                }
+            })
+        };
+
+        for _ in 0..10_000 {
+            let snapshot = state.snapshot();
+            assert_eq!(
+                snapshot.best_storage_block_height,
+                snapshot.best_header_height
+            );
+            assert_eq!(snapshot.sync_progress(), 1f64);
+        }
+
+        stop.store(true, Ordering::SeqCst);
+        writer.join().unwrap();
+    }
+}
@@ -0,0 +1,214 @@
+use primitives::bytes::Bytes;
+use std::collections::HashMap;
+use types::PeerIndex;
+
+/// Block timestamp is considered skewed if it differs from local time by more than this number of seconds
+const MAX_LOCAL_TIME_SKEW_S: u32 = 2 * 60 * 60;
+/// Alert is not raised until at least this number of blocks is received from the source
+const MIN_BLOCKS_FOR_ALERT: u32 = 8;
+/// Alert is raised when share of blocks with bad timestamps exceeds this value
+const BAD_TIMESTAMPS_ALERT_RATIO: f64 = 0.25;
+/// Maximal number of block producers we're tracking statistics for
+const MAX_TRACKED_PRODUCERS: usize = 4096;
+
+/// Block timestamps statistics of single blocks source
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct TimestampCounters {
+    /// Number of blocks checked
+    pub blocks: u32,
+    /// Number of blocks with timestamp earlier than timestamp of parent block
+    pub earlier_than_parent: u32,
+    /// Number of blocks with timestamp far from local time
+    pub skewed: u32,
+    /// True if alert has been raised and share of bad timestamps is still above the threshold
+    alerted: bool,
+}
+
+/// Alert, raised when source is providing too many blocks with bad timestamps
+#[derive(Debug, Clone, PartialEq)]
+pub enum TimestampAlert {
+    /// Block producer (identified by public key) is creating blocks with bad timestamps
+    Producer(Bytes, TimestampCounters),
+    /// Peer is relaying blocks with bad timestamps
+    Peer(PeerIndex, TimestampCounters),
+}
+
+/// Block timestamps monotonicity && skew statistics, aggregated per block producer and per relaying peer
+#[derive(Debug, Default)]
+pub struct TimestampStats {
+    /// Statistics per block producer
+    producers: HashMap<Bytes, TimestampCounters>,
+    /// Statistics per relaying peer
+    peers: HashMap<PeerIndex, TimestampCounters>,
+}
+
+impl TimestampCounters {
+    /// Returns share of blocks with bad timestamps
+    pub fn bad_ratio(&self) -> f64 {
+        if self.blocks == 0 {
+            return 0f64;
+        }
+
+        (self.earlier_than_parent + self.skewed) as f64 / self.blocks as f64
+    }
+
+    /// Updates counters. Returns true if alert must be raised
+    fn update(&mut self, earlier_than_parent: bool, skewed: bool) -> bool {
+        self.blocks += 1;
+        if earlier_than_parent {
+            self.earlier_than_parent += 1;
+        }
+        if skewed {
+            self.skewed += 1;
+        }
+
+        let is_above_threshold =
+            self.blocks >= MIN_BLOCKS_FOR_ALERT && self.bad_ratio() > BAD_TIMESTAMPS_ALERT_RATIO;
+        let raise_alert = is_above_threshold && !self.alerted;
+        self.alerted = is_above_threshold;
+        raise_alert
+    }
+}
+
+impl TimestampStats {
+    /// Remember block timestamp.
+    /// `local_time` is None when local time check should be skipped (i.e. when synchronizing old blocks).
+    pub fn on_block(
+        &mut self,
+        peer_index: PeerIndex,
+        producer: Bytes,
+        time: u32,
+        parent_time: Option<u32>,
+        local_time: Option<u32>,
+    ) -> Vec<TimestampAlert> {
+        let earlier_than_parent = parent_time
+            .map(|parent_time| time < parent_time)
+            .unwrap_or(false);
+        let skewed = local_time
+            .map(|local_time| {
+                let skew = if time > local_time {
+                    time - local_time
+                } else {
+                    local_time - time
+                };
+                skew > MAX_LOCAL_TIME_SKEW_S
+            })
+            .unwrap_or(false);
+
+        let mut alerts = Vec::new();
+        if self.producers.len() < MAX_TRACKED_PRODUCERS || self.producers.contains_key(&producer) {
+            let counters = self
+                .producers
+                .entry(producer.clone())
+                .or_insert_with(TimestampCounters::default);
+            if counters.update(earlier_than_parent, skewed) {
+                alerts.push(TimestampAlert::Producer(producer, counters.clone()));
+            }
+        }
+
+        let counters = self
+            .peers
+            .entry(peer_index)
+            .or_insert_with(TimestampCounters::default);
+        if counters.update(earlier_than_parent, skewed) {
+            alerts.push(TimestampAlert::Peer(peer_index, counters.clone()));
+        }
+
+        alerts
+    }
+
+    /// Forget statistics of disconnected peer
+    pub fn on_peer_disconnected(&mut self, peer_index: PeerIndex) {
+        self.peers.remove(&peer_index);
+    }
+
+    /// Statistics of given block producer
+    pub fn producer(&self, producer: &Bytes) -> Option<&TimestampCounters> {
+        self.producers.get(producer)
+    }
+
+    /// Statistics of given peer
+    pub fn peer(&self, peer_index: PeerIndex) -> Option<&TimestampCounters> {
+        self.peers.get(&peer_index)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{TimestampAlert, TimestampStats, MAX_LOCAL_TIME_SKEW_S, MIN_BLOCKS_FOR_ALERT};
+    use primitives::bytes::Bytes;
+
+    #[test]
+    fn timestamp_stats_counts_bad_timestamps() {
+        let mut stats = TimestampStats::default();
+        let producer: Bytes = vec![1u8].into();
+        assert!(stats
+            .on_block(0, producer.clone(), 100, Some(99), Some(100))
+            .is_empty());
+        assert!(stats
+            .on_block(0, producer.clone(), 98, Some(99), None)
+            .is_empty());
+        assert!(stats
+            .on_block(
+                1,
+                producer.clone(),
+                100 + MAX_LOCAL_TIME_SKEW_S + 1,
+                None,
+                Some(100)
+            )
+            .is_empty());
+
+        let producer_stats = stats.producer(&producer).unwrap();
+        assert_eq!(producer_stats.blocks, 3);
+        assert_eq!(producer_stats.earlier_than_parent, 1);
+        assert_eq!(producer_stats.skewed, 1);
+        assert_eq!(stats.peer(0).unwrap().blocks, 2);
+        assert_eq!(stats.peer(1).unwrap().skewed, 1);
+
+        stats.on_peer_disconnected(0);
+        assert!(stats.peer(0).is_none());
+    }
+
+    #[test]
+    fn timestamp_stats_raises_alert_once() {
+        let mut stats = TimestampStats::default();
+        let good_producer: Bytes = vec![1u8].into();
+        let bad_producer: Bytes = vec![2u8].into();
+        for _ in 0..MIN_BLOCKS_FOR_ALERT - 1 {
+            assert!(stats
+                .on_block(0, good_producer.clone(), 100, Some(99), None)
+                .is_empty());
+        }
+
+        // bad producer is alerted as soon as enough blocks are received
+        for _ in 0..MIN_BLOCKS_FOR_ALERT - 1 {
+            assert!(stats
+                .on_block(1, bad_producer.clone(), 98, Some(99), None)
+                .is_empty());
+        }
+        let alerts = stats.on_block(1, bad_producer.clone(), 98, Some(99), None);
+        assert_eq!(alerts.len(), 2);
+        match alerts[0] {
+            TimestampAlert::Producer(ref producer, ref counters) => {
+                assert_eq!(producer, &bad_producer);
+                assert_eq!(counters.earlier_than_parent, MIN_BLOCKS_FOR_ALERT);
+            }
+            _ => panic!("unexpected alert"),
+        }
+        match alerts[1] {
+            TimestampAlert::Peer(peer_index, _) => assert_eq!(peer_index, 1),
+            _ => panic!("unexpected alert"),
+        }
+
+        // alert is not repeated while producer is still bad
+        assert!(stats
+            .on_block(1, bad_producer.clone(), 98, Some(99), None)
+            .is_empty());
+
+        // peer 0 is relaying good blocks from good producer, so it is only alerted after it
+        // has relayed enough blocks from bad producer
+        let alerts = stats.on_block(0, bad_producer.clone(), 98, Some(99), None);
+        assert!(alerts.is_empty());
+        assert_eq!(stats.peer(0).unwrap().blocks, MIN_BLOCKS_FOR_ALERT);
+    }
+}
@@ -0,0 +1,214 @@
+use std::collections::{HashMap, VecDeque};
+use types::PeerIndex;
+
+/// Default length of the upload accounting window, in seconds.
+pub const DEFAULT_UPLOAD_WINDOW_S: u32 = 600;
+/// Peer is disconnected when this number of its requests has been throttled within the window.
+const MAX_THROTTLED_REQUESTS: usize = 1024;
+
+/// Max number of bytes, served to single peer over the sliding window.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct UploadLimit {
+    /// Max number of bytes, served to single peer within the window.
+    pub max_bytes: u64,
+    /// Window length, in seconds.
+    pub window_s: u32,
+}
+
+/// What to do with the peer request.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum UploadDecision {
+    /// Peer is within its budget => serve the request.
+    Serve,
+    /// Peer has exhausted its budget => respond with 'notfound'.
+    Throttle,
+    /// Peer keeps requesting after its budget is exhausted => disconnect.
+    Disconnect,
+}
+
+/// Per-peer accounting of bytes, served over the sliding window.
+#[derive(Debug)]
+pub struct UploadBudget {
+    /// Upload limit. None if uploads are not limited.
+    limit: Option<UploadLimit>,
+    /// Upload stats of every peer, we have served something to.
+    peers: HashMap<PeerIndex, PeerUploads>,
+}
+
+/// Uploads of single peer within the window.
+#[derive(Debug, Default)]
+struct PeerUploads {
+    /// Served responses: time => size.
+    served: VecDeque<(f64, u64)>,
+    /// Total size of served responses.
+    served_bytes: u64,
+    /// Times of throttled requests.
+    throttled: VecDeque<f64>,
+}
+
+impl UploadBudget {
+    /// Create new budget with given limit.
+    pub fn new(limit: Option<UploadLimit>) -> Self {
+        UploadBudget {
+            limit: limit,
+            peers: HashMap::new(),
+        }
+    }
+
+    /// Change upload limit. Uploads, served within the window, are accounted against the new limit.
+    pub fn set_limit(&mut self, limit: Option<UploadLimit>) {
+        if limit.is_none() {
+            self.peers.clear();
+        }
+        self.limit = limit;
+    }
+
+    /// Decides whether next request of the peer should be served.
+    pub fn check(&mut self, peer_index: PeerIndex, now: f64) -> UploadDecision {
+        let limit = match self.limit {
+            Some(limit) => limit,
+            None => return UploadDecision::Serve,
+        };
+
+        let uploads = self
+            .peers
+            .entry(peer_index)
+            .or_insert_with(Default::default);
+        uploads.prune(now - limit.window_s as f64);
+        if uploads.served_bytes < limit.max_bytes {
+            return UploadDecision::Serve;
+        }
+
+        uploads.throttled.push_back(now);
+        if uploads.throttled.len() > MAX_THROTTLED_REQUESTS {
+            UploadDecision::Disconnect
+        } else {
+            UploadDecision::Throttle
+        }
+    }
+
+    /// Remember that response of given size has been served to the peer.
+    pub fn note_served(&mut self, peer_index: PeerIndex, bytes: u64, now: f64) {
+        if self.limit.is_none() {
+            return;
+        }
+
+        let uploads = self
+            .peers
+            .entry(peer_index)
+            .or_insert_with(Default::default);
+        uploads.served.push_back((now, bytes));
+        uploads.served_bytes += bytes;
+    }
+
+    /// Number of bytes, served to the peer within the window.
+    #[cfg(test)]
+    pub fn served_bytes(&mut self, peer_index: PeerIndex, now: f64) -> u64 {
+        let window_start = match self.limit {
+            Some(limit) => now - limit.window_s as f64,
+            None => return 0,
+        };
+
+        match self.peers.get_mut(&peer_index) {
+            Some(uploads) => {
+                uploads.prune(window_start);
+                uploads.served_bytes
+            }
+            None => 0,
+        }
+    }
+
+    /// Forget about disconnected peer.
+    pub fn remove_peer(&mut self, peer_index: PeerIndex) {
+        self.peers.remove(&peer_index);
+    }
+}
+
+impl PeerUploads {
+    /// Forget responses and requests, which are older than the window start.
+    fn prune(&mut self, window_start: f64) {
+        while self
+            .served
+            .front()
+            .map_or(false, |&(time, _)| time < window_start)
+        {
+            let (_, bytes) = self.served.pop_front().expect("checked above; qed");
+            self.served_bytes -= bytes;
+        }
+        while self
+            .throttled
+            .front()
+            .map_or(false, |time| *time < window_start)
+        {
+            self.throttled.pop_front();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{UploadBudget, UploadDecision, UploadLimit, MAX_THROTTLED_REQUESTS};
+
+    #[test]
+    fn upload_budget_is_unlimited_by_default() {
+        let mut budget = UploadBudget::new(None);
+        budget.note_served(0, 1_000_000, 0f64);
+        assert_eq!(budget.check(0, 0f64), UploadDecision::Serve);
+        assert_eq!(budget.served_bytes(0, 0f64), 0);
+    }
+
+    #[test]
+    fn upload_budget_throttles_peer_within_window() {
+        let mut budget = UploadBudget::new(Some(UploadLimit {
+            max_bytes: 100,
+            window_s: 10,
+        }));
+        assert_eq!(budget.check(0, 0f64), UploadDecision::Serve);
+        budget.note_served(0, 60, 0f64);
+        assert_eq!(budget.check(0, 1f64), UploadDecision::Serve);
+        budget.note_served(0, 60, 1f64);
+        assert_eq!(budget.served_bytes(0, 2f64), 120);
+        assert_eq!(budget.check(0, 2f64), UploadDecision::Throttle);
+        // other peers have their own budget
+        assert_eq!(budget.check(1, 2f64), UploadDecision::Serve);
+        // first response leaves the window
+        assert_eq!(budget.served_bytes(0, 10.5f64), 60);
+        assert_eq!(budget.check(0, 10.5f64), UploadDecision::Serve);
+    }
+
+    #[test]
+    fn upload_budget_disconnects_persistent_peer() {
+        let mut budget = UploadBudget::new(Some(UploadLimit {
+            max_bytes: 100,
+            window_s: 10,
+        }));
+        budget.note_served(0, 100, 0f64);
+        for _ in 0..MAX_THROTTLED_REQUESTS {
+            assert_eq!(budget.check(0, 1f64), UploadDecision::Throttle);
+        }
+        assert_eq!(budget.check(0, 1f64), UploadDecision::Disconnect);
+
+        budget.remove_peer(0);
+        assert_eq!(budget.check(0, 1f64), UploadDecision::Serve);
+    }
+
+    #[test]
+    fn upload_budget_limit_is_changed() {
+        let mut budget = UploadBudget::new(Some(UploadLimit {
+            max_bytes: 100,
+            window_s: 10,
+        }));
+        budget.note_served(0, 80, 0f64);
+
+        // already served bytes are accounted against the new limit
+        budget.set_limit(Some(UploadLimit {
+            max_bytes: 50,
+            window_s: 10,
+        }));
+        assert_eq!(budget.check(0, 1f64), UploadDecision::Throttle);
+
+        budget.set_limit(None);
+        assert_eq!(budget.check(0, 1f64), UploadDecision::Serve);
+        assert_eq!(budget.served_bytes(0, 1f64), 0);
+    }
+}
@@ -0,0 +1,238 @@
+//! Trees of competing branches for chain reorganization tests
+
+use chain::{Block, BlockHeader};
+use primitives::hash::H256;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
+
+use block::build_n_empty_blocks_from;
+
+/// Single branch of the fork tree
+#[derive(Debug, Clone)]
+pub struct ForkBranch {
+    /// Index of the parent branch, or None if branch is forked from the tree root
+    pub parent: Option<usize>,
+    /// Height (relatively to the tree root) of the block, branch is forked from
+    pub fork_height: u32,
+    /// Branch blocks, in order
+    pub blocks: Vec<Block>,
+}
+
+/// Tree of branches, which all are descendants of the common root block.
+/// Root block itself is not a part of the tree.
+#[derive(Debug, Clone)]
+pub struct ForkTree {
+    root: BlockHeader,
+    branches: Vec<ForkBranch>,
+}
+
+/// Fork tree builder
+#[derive(Debug, Clone)]
+pub struct ForkTreeBuilder {
+    tree: ForkTree,
+}
+
+pub fn fork_tree_builder(root: &BlockHeader) -> ForkTreeBuilder {
+    ForkTreeBuilder::new(root)
+}
+
+impl ForkTreeBuilder {
+    pub fn new(root: &BlockHeader) -> Self {
+        ForkTreeBuilder {
+            tree: ForkTree {
+                root: root.clone(),
+                branches: Vec::new(),
+            },
+        }
+    }
+
+    /// Add branch of `len` empty blocks, which is forked from the tree root.
+    /// Iterations of branch blocks are starting from `start_iterations`.
+    pub fn branch(self, len: u32, start_iterations: u32) -> Self {
+        let root = self.tree.root.clone();
+        self.push_branch(None, 0, &root, len, start_iterations)
+    }
+
+    /// Add branch of `len` empty blocks, which is forked from the block of `parent` branch
+    /// at given height (relatively to the tree root). Branches, forked from the same block,
+    /// must have different `start_iterations`, or they would consist of the same blocks.
+    pub fn fork(self, parent: usize, fork_height: u32, len: u32, start_iterations: u32) -> Self {
+        let fork_header = {
+            let parent_branch = &self.tree.branches[parent];
+            assert!(fork_height <= parent_branch.fork_height + parent_branch.blocks.len() as u32);
+            self.tree
+                .block_at(parent, fork_height)
+                .map(|block| block.block_header.clone())
+                .unwrap_or_else(|| self.tree.root.clone())
+        };
+        self.push_branch(
+            Some(parent),
+            fork_height,
+            &fork_header,
+            len,
+            start_iterations,
+        )
+    }
+
+    pub fn build(self) -> ForkTree {
+        self.tree
+    }
+
+    fn push_branch(
+        mut self,
+        parent: Option<usize>,
+        fork_height: u32,
+        fork_header: &BlockHeader,
+        len: u32,
+        start_iterations: u32,
+    ) -> Self {
+        self.tree.branches.push(ForkBranch {
+            parent: parent,
+            fork_height: fork_height,
+            blocks: build_n_empty_blocks_from(len, start_iterations, fork_header),
+        });
+        self
+    }
+}
+
+impl ForkTree {
+    /// Root block header
+    pub fn root(&self) -> &BlockHeader {
+        &self.root
+    }
+
+    /// All tree branches, in order of creation
+    pub fn branches(&self) -> &[ForkBranch] {
+        &self.branches
+    }
+
+    /// Height of the branch tip (relatively to the tree root)
+    pub fn height(&self, branch: usize) -> u32 {
+        let branch = &self.branches[branch];
+        branch.fork_height + branch.blocks.len() as u32
+    }
+
+    /// Hash of the branch tip. For empty branch, this is hash of the fork block
+    pub fn tip(&self, branch: usize) -> H256 {
+        let height = self.height(branch);
+        self.block_at(branch, height)
+            .map(Block::hash)
+            .unwrap_or_else(|| self.root.hash())
+    }
+
+    /// Index of the branch with the highest tip. Of branches with the same height,
+    /// the first created is returned
+    pub fn longest_branch(&self) -> usize {
+        (0..self.branches.len())
+            .rev()
+            .max_by_key(|branch| self.height(*branch))
+            .expect("fork tree has no branches")
+    }
+
+    /// Block of the chain, ending with given branch tip, at given height (relatively
+    /// to the tree root). Returns None for the root block itself
+    pub fn block_at(&self, branch: usize, height: u32) -> Option<&Block> {
+        let branch_ref = &self.branches[branch];
+        if height > branch_ref.fork_height {
+            return branch_ref
+                .blocks
+                .get((height - branch_ref.fork_height - 1) as usize);
+        }
+
+        match branch_ref.parent {
+            Some(parent) => self.block_at(parent, height),
+            None => None,
+        }
+    }
+
+    /// All blocks of the tree, each block is following its parent
+    pub fn blocks(&self) -> Vec<Block> {
+        self.branches
+            .iter()
+            .flat_map(|branch| branch.blocks.iter().cloned())
+            .collect()
+    }
+
+    /// All blocks of the tree, in random order, defined by the seed. Child blocks may
+    /// precede their parents
+    pub fn shuffled_blocks(&self, seed: u64) -> Vec<Block> {
+        let mut blocks = self.blocks();
+        blocks.shuffle(&mut StdRng::seed_from_u64(seed));
+        blocks
+    }
+
+    /// Passes all blocks of the tree to the `append` (e.g. `BlocksWriter::append_block`),
+    /// in random order, defined by the seed. Stops at the first error
+    pub fn append_shuffled<F, E>(&self, seed: u64, mut append: F) -> Result<(), E>
+    where
+        F: FnMut(Block) -> Result<(), E>,
+    {
+        for block in self.shuffled_blocks(seed) {
+            append(block)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::fork_tree_builder;
+    use genesis;
+    use std::collections::HashSet;
+
+    #[test]
+    fn fork_tree_branches_are_linked() {
+        let genesis = genesis();
+        // genesis -> 5 blocks of branch#0
+        //   \-> 2 blocks of branch#0 -> 4 blocks of branch#1
+        //         \-> 3 blocks of branch#1 -> 1 block of branch#2
+        let tree = fork_tree_builder(&genesis.block_header)
+            .branch(5, 10)
+            .fork(0, 2, 4, 20)
+            .fork(1, 3, 1, 30)
+            .build();
+
+        assert_eq!(tree.height(0), 5);
+        assert_eq!(tree.height(1), 6);
+        assert_eq!(tree.height(2), 4);
+        assert_eq!(tree.longest_branch(), 1);
+        assert_eq!(tree.blocks().len(), 10);
+
+        let branch2 = &tree.branches()[2].blocks;
+        assert_eq!(tree.tip(2), branch2[0].hash());
+        assert_eq!(
+            branch2[0].block_header.previous_header_hash,
+            tree.block_at(1, 3).unwrap().hash()
+        );
+        assert_eq!(
+            tree.block_at(1, 3)
+                .unwrap()
+                .block_header
+                .previous_header_hash,
+            tree.block_at(0, 2).unwrap().hash()
+        );
+        assert_eq!(
+            tree.block_at(2, 1)
+                .unwrap()
+                .block_header
+                .previous_header_hash,
+            genesis.hash()
+        );
+        assert!(tree.block_at(2, 0).is_none());
+    }
+
+    #[test]
+    fn fork_tree_shuffle_is_deterministic() {
+        let tree = fork_tree_builder(&genesis().block_header)
+            .branch(8, 10)
+            .fork(0, 4, 8, 20)
+            .build();
+
+        let shuffled = tree.shuffled_blocks(7);
+        assert_eq!(shuffled, tree.shuffled_blocks(7));
+        assert_ne!(shuffled, tree.blocks());
+        let hashes: HashSet<_> = shuffled.iter().map(|block| block.hash()).collect();
+        assert_eq!(hashes.len(), 16);
+    }
+}
@@ -1,6 +1,7 @@
 //! Various chain-specific test dummies
 
 extern crate ecvrf;
+extern crate rand;
 extern crate rug;
 extern crate time;
 
@@ -19,6 +20,7 @@ use primitives::compact::Compact;
 
 pub mod block;
 pub mod chain_builder;
+pub mod fork_tree;
 pub mod invoke;
 
 pub use block::{
@@ -26,6 +28,7 @@ pub use block::{
     build_n_empty_blocks_from_genesis,
 };
 pub use chain_builder::ChainBuilder;
+pub use fork_tree::{fork_tree_builder, ForkBranch, ForkTree, ForkTreeBuilder};
 
 pub fn genesis() -> Block {
     block_h0()
@@ -9,4 +9,6 @@ mod vdf;
 
 pub use crate::config::MODULUS;
 pub use crate::vdf::Proof;
-pub use crate::vdf::{eval, prove, verify};
+pub use crate::vdf::{
+    eval, eval_with_modulus, prove, prove_with_modulus, verify, verify_with_modulus,
+};
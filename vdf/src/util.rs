@@ -1,10 +1,8 @@
 use rug::{integer::Order, Integer};
 use sha2::{Digest, Sha256};
 
-use super::config::MODULUS;
-
 /// Fiat–Shamir heuristic non-iterative signature
-pub fn hash_fs(inputs: &[&Integer]) -> Integer {
+pub fn hash_fs(inputs: &[&Integer], modulus: &Integer) -> Integer {
     let mut hasher = Sha256::new();
     for input in inputs {
         hasher.update(input.to_digits::<u8>(Order::Lsf));
@@ -24,5 +22,5 @@ pub fn hash_fs(inputs: &[&Integer]) -> Integer {
         .flatten()
         .collect();
     let result = Integer::from_digits(&all_2048, Order::Lsf);
-    result.div_rem_floor(MODULUS.clone()).1
+    result.div_rem_floor(modulus.clone()).1
 }
@@ -6,14 +6,22 @@ use chain::{BlockHeader, IndexedBlock, IndexedBlockHeader};
 use error::Error;
 use hash::H256;
 use network::Network;
+use rayon::prelude::*;
+use std::sync::Arc;
 use storage::{BlockHeaderProvider, BlockOrigin, SharedStore};
+use time_source::{LocalTime, TimeSource};
+use verify_block::BlockVerifier;
 use verify_chain::ChainVerifier;
 use verify_header::HeaderVerifier;
 use {VerificationLevel, Verify};
 
+/// Headers are verified in parallel, when there are at least this number of headers.
+const MIN_PARALLEL_VERIFIED_HEADERS: usize = 32;
+
 pub struct BackwardsCompatibleChainVerifier {
     store: SharedStore,
     network: Network,
+    time_source: Arc<dyn TimeSource>,
 }
 
 impl BackwardsCompatibleChainVerifier {
@@ -21,9 +29,16 @@ impl BackwardsCompatibleChainVerifier {
         BackwardsCompatibleChainVerifier {
             store: store,
             network: network,
+            time_source: Arc::new(LocalTime),
         }
     }
 
+    /// Use given source of the current time (local clock by default) to reject blocks from the future
+    pub fn with_time_source(mut self, time_source: Arc<dyn TimeSource>) -> Self {
+        self.time_source = time_source;
+        self
+    }
+
     fn verify_block(
         &self,
         verification_level: VerificationLevel,
@@ -33,10 +48,8 @@ impl BackwardsCompatibleChainVerifier {
             return Ok(());
         }
 
-        let current_time = ::time::get_time().sec as u32;
         // first run pre-verification
-        let chain_verifier = ChainVerifier::new(block, self.network, current_time);
-        chain_verifier.check()?;
+        self.pre_verify(verification_level, block)?;
 
         assert_eq!(
             Some(self.store.best_block().hash),
@@ -89,6 +102,65 @@ impl BackwardsCompatibleChainVerifier {
         Ok(())
     }
 
+    /// Verifies block, which is already stored in the canon chain at given height, again.
+    /// Used to detect storage corruption.
+    pub fn verify_canon_block(
+        &self,
+        verification_level: VerificationLevel,
+        block: &IndexedBlock,
+        height: u32,
+    ) -> Result<(), Error> {
+        if verification_level == VerificationLevel::NoVerification {
+            return Ok(());
+        }
+
+        self.pre_verify(verification_level, block)?;
+
+        let header_provider = self.store.as_store().as_block_header_provider();
+        ChainAcceptor::new(
+            header_provider,
+            &self.network,
+            CanonBlock::new(block),
+            height,
+        )
+        .check()
+    }
+
+    /// Context-free verification of the block.
+    fn pre_verify(
+        &self,
+        verification_level: VerificationLevel,
+        block: &IndexedBlock,
+    ) -> Result<(), Error> {
+        let current_time = self.time_source.now();
+        let chain_verifier = ChainVerifier::new(block, self.network, current_time);
+        match verification_level {
+            VerificationLevel::Header => chain_verifier.header.check()?,
+            _ => chain_verifier.check()?,
+        }
+        if verification_level == VerificationLevel::Paranoid {
+            self.verify_stored_parent(block)?;
+        }
+        Ok(())
+    }
+
+    /// Verifies proof of the parent block again to detect storage corruption.
+    /// Genesis block and blocks with pruned bodies are not verified.
+    fn verify_stored_parent(&self, block: &IndexedBlock) -> Result<(), Error> {
+        let parent = match self
+            .store
+            .block(block.header.raw.previous_header_hash.clone().into())
+        {
+            Some(ref parent) if parent.header.raw.previous_header_hash.is_zero() => return Ok(()),
+            Some(parent) => parent,
+            None => return Ok(()),
+        };
+
+        BlockVerifier::new(&parent, self.network)
+            .check()
+            .map_err(|_| Error::ParentVdf)
+    }
+
     pub fn verify_block_header(
         &self,
         _block_header_provider: &dyn BlockHeaderProvider,
@@ -97,11 +169,41 @@ impl BackwardsCompatibleChainVerifier {
     ) -> Result<(), Error> {
         // let's do only preverifcation
         // TODO: full verification
-        let current_time = ::time::get_time().sec as u32;
+        let current_time = self.time_source.now();
         let header = IndexedBlockHeader::new(hash.clone(), header.clone());
         let header_verifier = HeaderVerifier::new(&header, self.network, current_time);
         header_verifier.check()
     }
+
+    /// Verifies context-free rules of every header. Large batches of headers are verified
+    /// in parallel. Returns index and error of the first invalid header
+    pub fn verify_block_headers(
+        &self,
+        headers: &[IndexedBlockHeader],
+    ) -> Result<(), (usize, Error)> {
+        let current_time = self.time_source.now();
+        let network = self.network;
+        let verify = |(index, header): (usize, &IndexedBlockHeader)| {
+            HeaderVerifier::new(header, network, current_time)
+                .check()
+                .err()
+                .map(|error| (index, error))
+        };
+
+        let first_invalid = if headers.len() < MIN_PARALLEL_VERIFIED_HEADERS {
+            headers.iter().enumerate().filter_map(verify).next()
+        } else {
+            headers
+                .par_iter()
+                .enumerate()
+                .filter_map(verify)
+                .min_by_key(|&(index, _)| index)
+        };
+        match first_invalid {
+            Some(invalid) => Err(invalid),
+            None => Ok(()),
+        }
+    }
 }
 
 impl Verify for BackwardsCompatibleChainVerifier {
@@ -123,11 +225,20 @@ mod tests {
     extern crate test_data;
 
     use super::BackwardsCompatibleChainVerifier as ChainVerifier;
+    use chain::IndexedBlock;
     use db::BlockChainDatabase;
     use network::Network;
     use std::sync::Arc;
-    use storage::Error as DBError;
-    use {Error, VerificationLevel, Verify};
+    use storage::{AsSubstore, Error as DBError};
+    use {Error, TimeSource, VerificationLevel, Verify};
+
+    struct FixedTime(u32);
+
+    impl TimeSource for FixedTime {
+        fn now(&self) -> u32 {
+            self.0
+        }
+    }
 
     #[test]
     fn verify_orphan() {
@@ -151,4 +262,113 @@ mod tests {
         let verifier = ChainVerifier::new(storage, Network::Unitest);
         assert!(verifier.verify(VerificationLevel::Full, &b1.into()).is_ok());
     }
+
+    #[test]
+    fn verify_header_uses_time_source() {
+        let storage = Arc::new(BlockChainDatabase::init_test_chain(vec![
+            test_data::genesis().into(),
+        ]));
+        let b1 = test_data::block_h1();
+        let b1_hash = b1.hash();
+        let mut header = b1.block_header;
+        header.time = 1_000_000;
+
+        let verifier = ChainVerifier::new(storage.clone(), Network::Unitest)
+            .with_time_source(Arc::new(FixedTime(1_000_000)));
+        assert_eq!(
+            Ok(()),
+            verifier.verify_block_header(storage.as_block_header_provider(), &b1_hash, &header)
+        );
+
+        // block is too far in the future of the current time
+        let verifier = ChainVerifier::new(storage.clone(), Network::Unitest)
+            .with_time_source(Arc::new(FixedTime(1_000_000 - 3 * 60 * 60)));
+        assert_eq!(
+            Err(Error::FuturisticTimestamp),
+            verifier.verify_block_header(storage.as_block_header_provider(), &b1_hash, &header)
+        );
+    }
+
+    #[test]
+    fn verify_headers_finds_first_invalid_header() {
+        let storage = Arc::new(BlockChainDatabase::init_test_chain(vec![
+            test_data::genesis().into(),
+        ]));
+        let mut header = IndexedBlock::from(test_data::block_h1()).header;
+        header.raw.time = 1_000_000;
+        let mut futuristic = header.clone();
+        futuristic.raw.time = 1_000_000 + 3 * 60 * 60;
+
+        let verifier = ChainVerifier::new(storage, Network::Unitest)
+            .with_time_source(Arc::new(FixedTime(1_000_000)));
+        // both sequential and parallel verification
+        for count in vec![4, 100] {
+            let mut headers = vec![header.clone(); count];
+            assert_eq!(Ok(()), verifier.verify_block_headers(&headers));
+
+            headers[count - 1] = futuristic.clone();
+            headers[count / 2] = futuristic.clone();
+            assert_eq!(
+                Err((count / 2, Error::FuturisticTimestamp)),
+                verifier.verify_block_headers(&headers)
+            );
+        }
+    }
+
+    #[test]
+    fn verify_header_level_skips_vdf_proof() {
+        let storage = Arc::new(BlockChainDatabase::init_test_chain(vec![
+            test_data::genesis().into(),
+        ]));
+        let mut b1: IndexedBlock = test_data::block_h1().into();
+        b1.proof.clear();
+        let verifier = ChainVerifier::new(storage, Network::Unitest);
+        assert_eq!(Ok(()), verifier.verify(VerificationLevel::Header, &b1));
+        assert_eq!(
+            Err(Error::Vdf),
+            verifier.verify(VerificationLevel::Full, &b1)
+        );
+    }
+
+    #[test]
+    fn verify_paranoid_level_checks_stored_parent() {
+        let mut b1: IndexedBlock = test_data::block_h1().into();
+        b1.proof.clear();
+        let storage = Arc::new(BlockChainDatabase::init_test_chain(vec![
+            test_data::genesis().into(),
+            b1,
+        ]));
+        let b2: IndexedBlock = test_data::block_h2().into();
+        let verifier = ChainVerifier::new(storage, Network::Unitest);
+        assert!(verifier.verify(VerificationLevel::Full, &b2).is_ok());
+        assert_eq!(
+            Err(Error::ParentVdf),
+            verifier.verify(VerificationLevel::Paranoid, &b2)
+        );
+    }
+
+    #[test]
+    fn verify_canon_block_detects_corrupted_proof() {
+        let mut b1: IndexedBlock = test_data::block_h1().into();
+        b1.proof.clear();
+        let storage = Arc::new(BlockChainDatabase::init_test_chain(vec![
+            test_data::genesis().into(),
+            b1.clone(),
+            test_data::block_h2().into(),
+        ]));
+        let b2: IndexedBlock = test_data::block_h2().into();
+        let verifier = ChainVerifier::new(storage, Network::Unitest);
+        assert_eq!(
+            Ok(()),
+            verifier.verify_canon_block(VerificationLevel::Full, &b2, 2)
+        );
+        assert_eq!(
+            Ok(()),
+            verifier.verify_canon_block(VerificationLevel::Header, &b1, 1)
+        );
+        assert_eq!(
+            Err(Error::Vdf),
+            verifier.verify_canon_block(VerificationLevel::Full, &b1, 1)
+        );
+    }
 }
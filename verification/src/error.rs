@@ -10,6 +10,10 @@ pub enum Error {
     Pow,
     /// Invalid vdf proof
     Vdf,
+    /// Invalid vdf proof of the parent block, read from the storage
+    ParentVdf,
+    /// Number of vdf iterations is out of range allowed by the network
+    Iterations { min: u32, max: u32, actual: u32 },
     /// Futuristic timestamp
     FuturisticTimestamp,
     /// Invalid timestamp
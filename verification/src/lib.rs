@@ -74,6 +74,7 @@ extern crate vdf;
 mod canon;
 pub mod constants;
 mod error;
+mod time_source;
 mod timestamp;
 mod work;
 
@@ -97,12 +98,13 @@ pub use accept_chain::ChainAcceptor;
 pub use accept_header::HeaderAcceptor;
 pub use canon::{CanonBlock, CanonHeader};
 
-pub use verify_block::{h_g, BlockVerifier};
+pub use verify_block::{h_g, h_g_with_params, hash_to_group, BlockVerifier};
 pub use verify_chain::ChainVerifier;
 pub use verify_header::HeaderVerifier;
 
 pub use chain_verifier::BackwardsCompatibleChainVerifier;
 pub use error::Error;
+pub use time_source::{LocalTime, TimeSource};
 pub use timestamp::{median_timestamp, median_timestamp_inclusive};
 pub use work::{
     block_reward_satoshi, is_valid_proof_of_work, is_valid_proof_of_work_hash, work_required,
@@ -111,9 +113,11 @@ pub use work::{
 #[derive(Debug, Clone, Copy, PartialEq)]
 /// Blocks verification level.
 pub enum VerificationLevel {
+    /// Full verification and re-verification of the parent block, read from the storage.
+    Paranoid,
     /// Full verification.
     Full,
-    /// Transaction scripts are not checked.
+    /// Block VDF proof is not checked.
     Header,
     /// No verification at all.
     NoVerification,
@@ -0,0 +1,17 @@
+//! Source of the current time, used to reject blocks from the future
+
+/// Source of the current time
+pub trait TimeSource: Send + Sync {
+    /// Returns current time in seconds since epoch (Jan 1 1970 GMT)
+    fn now(&self) -> u32;
+}
+
+/// Local system clock
+#[derive(Debug, Default)]
+pub struct LocalTime;
+
+impl TimeSource for LocalTime {
+    fn now(&self) -> u32 {
+        ::time::get_time().sec as u32
+    }
+}
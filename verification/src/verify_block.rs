@@ -1,12 +1,17 @@
 use chain::IndexedBlock;
 use crypto::dhash256;
 use error::Error;
+use network::{Network, VdfParams};
 use primitives::bytes::Bytes;
 use rug::{integer::Order, Integer};
 use ser::Stream;
 use sha2::{Digest, Sha256};
 
 pub fn h_g(block: &IndexedBlock) -> Integer {
+    h_g_with_params(block, &VdfParams::default())
+}
+
+pub fn h_g_with_params(block: &IndexedBlock, params: &VdfParams) -> Integer {
     let mut stream = Stream::default();
     stream
         .append(&block.header.raw.version)
@@ -14,11 +19,15 @@ pub fn h_g(block: &IndexedBlock) -> Integer {
         .append(&block.header.raw.time)
         .append(&block.header.raw.bits)
         .append(&Bytes::from(block.header.raw.pubkey.to_bytes().to_vec()));
-    let data = stream.out();
-    let seed = dhash256(&data);
+    hash_to_group(&stream.out(), params)
+}
+
+/// Maps data to the element of the VDF group.
+pub fn hash_to_group(data: &[u8], params: &VdfParams) -> Integer {
+    let seed = dhash256(data);
     let prefix = "residue_part_".as_bytes();
-    // concat 8 sha256 to a 2048-bit hash
-    let all_2048: Vec<u8> = (0..((2048 / 256) as u8))
+    // concat sha256 hashes to a hash of group element size
+    let all_bits: Vec<u8> = (0..((params.element_bits / 256) as u8))
         .map(|index| {
             let mut hasher = Sha256::new();
             hasher.update(prefix);
@@ -28,8 +37,8 @@ pub fn h_g(block: &IndexedBlock) -> Integer {
         })
         .flatten()
         .collect();
-    let result = Integer::from_digits(&all_2048, Order::Lsf);
-    result.div_rem_floor(vdf::MODULUS.clone()).1
+    let result = Integer::from_digits(&all_bits, Order::Lsf);
+    result.div_rem_floor(params.modulus.clone()).1
 }
 
 pub struct BlockVerifier<'a> {
@@ -37,9 +46,9 @@ pub struct BlockVerifier<'a> {
 }
 
 impl<'a> BlockVerifier<'a> {
-    pub fn new(block: &'a IndexedBlock) -> Self {
+    pub fn new(block: &'a IndexedBlock, network: Network) -> Self {
         BlockVerifier {
-            vdf: BlockVDF::new(block),
+            vdf: BlockVDF::new(block, network.vdf_params()),
         }
     }
 
@@ -50,21 +59,26 @@ impl<'a> BlockVerifier<'a> {
 
 pub struct BlockVDF<'a> {
     block: &'a IndexedBlock,
+    params: &'a VdfParams,
 }
 
 impl<'a> BlockVDF<'a> {
-    fn new(block: &'a IndexedBlock) -> Self {
-        BlockVDF { block: block }
+    fn new(block: &'a IndexedBlock, params: &'a VdfParams) -> Self {
+        BlockVDF {
+            block: block,
+            params: params,
+        }
     }
 
     fn check(&self) -> Result<(), Error> {
-        let g = h_g(self.block);
+        let g = h_g_with_params(self.block, self.params);
 
-        match vdf::verify(
+        match vdf::verify_with_modulus(
             &g,
             &self.block.header.raw.randomness,
             self.block.header.raw.iterations,
             &self.block.proof,
+            &self.params.modulus,
         ) {
             false => Err(Error::Vdf),
             true => Ok(()),
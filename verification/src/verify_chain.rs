@@ -13,7 +13,7 @@ impl<'a> ChainVerifier<'a> {
     pub fn new(block: &'a IndexedBlock, network: Network, current_time: u32) -> Self {
         trace!(target: "verification", "Block pre-verification {}", block.hash().to_reversed_str());
         ChainVerifier {
-            block: BlockVerifier::new(block),
+            block: BlockVerifier::new(block, network),
             header: HeaderVerifier::new(&block.header, network, current_time),
         }
     }
@@ -1,12 +1,13 @@
 use chain::IndexedBlockHeader;
 use constants::BLOCK_MAX_FUTURE;
 use error::Error;
-use network::Network;
+use network::{Network, VdfParams};
 use primitives::compact::Compact;
 use work::is_valid_proof_of_work;
 
 pub struct HeaderVerifier<'a> {
     pub proof_of_work: HeaderProofOfWork<'a>,
+    pub iterations: HeaderIterations<'a>,
     pub timestamp: HeaderTimestamp<'a>,
 }
 
@@ -14,12 +15,14 @@ impl<'a> HeaderVerifier<'a> {
     pub fn new(header: &'a IndexedBlockHeader, network: Network, current_time: u32) -> Self {
         HeaderVerifier {
             proof_of_work: HeaderProofOfWork::new(header, network),
+            iterations: HeaderIterations::new(header, network.vdf_params()),
             timestamp: HeaderTimestamp::new(header, current_time, BLOCK_MAX_FUTURE as u32),
         }
     }
 
     pub fn check(&self) -> Result<(), Error> {
         self.proof_of_work.check()?;
+        self.iterations.check()?;
         self.timestamp.check()?;
         Ok(())
     }
@@ -47,6 +50,32 @@ impl<'a> HeaderProofOfWork<'a> {
     }
 }
 
+pub struct HeaderIterations<'a> {
+    header: &'a IndexedBlockHeader,
+    params: &'a VdfParams,
+}
+
+impl<'a> HeaderIterations<'a> {
+    fn new(header: &'a IndexedBlockHeader, params: &'a VdfParams) -> Self {
+        HeaderIterations {
+            header: header,
+            params: params,
+        }
+    }
+
+    fn check(&self) -> Result<(), Error> {
+        if self.params.is_valid_iterations(self.header.raw.iterations) {
+            Ok(())
+        } else {
+            Err(Error::Iterations {
+                min: self.params.min_iterations,
+                max: self.params.max_iterations,
+                actual: self.header.raw.iterations,
+            })
+        }
+    }
+}
+
 pub struct HeaderTimestamp<'a> {
     header: &'a IndexedBlockHeader,
     current_time: u32,